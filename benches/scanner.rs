@@ -0,0 +1,19 @@
+//! Measures how fast `build_tree` scans a generated tree of 10,000 entries, written to tmpfs
+//! where the system has it so the disk doesn't dominate.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use disk_scout::scanner::{self, ScanOptions};
+use disk_scout::synthetic::{self, Spec};
+
+fn build_tree(c: &mut Criterion) {
+    let dir = synthetic::scratch_dir().expect("failed to create a scratch folder");
+    let tree = synthetic::generate(&Spec { nodes: 10_000, ..Spec::default() });
+    synthetic::write(dir.path(), &tree).expect("failed to write the tree");
+    let options = ScanOptions::default();
+    c.bench_function("build_tree 10k entries", |b| {
+        b.iter(|| scanner::build_tree(dir.path(), &options).expect("the scan failed"))
+    });
+}
+
+criterion_group!(benches, build_tree);
+criterion_main!(benches);
@@ -0,0 +1,22 @@
+//! Measures how fast `generate_treemap` lays out generated trees of 10k, 100k, and 1M nodes.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use disk_scout::scanner::SizeMetric;
+use disk_scout::synthetic::{self, Spec};
+use disk_scout::treemap::{self, Rectangle};
+
+fn generate_treemap(c: &mut Criterion) {
+    let bounds = Rectangle { x: 0.0, y: 0.0, width: 1920.0, height: 1080.0 };
+    let mut group = c.benchmark_group("generate_treemap");
+    group.sample_size(10);
+    for nodes in [10_000, 100_000, 1_000_000] {
+        let tree = synthetic::generate(&Spec { nodes, ..Spec::default() });
+        group.bench_with_input(BenchmarkId::from_parameter(nodes), &tree, |b, tree| {
+            b.iter(|| treemap::generate_treemap(tree, bounds, SizeMetric::Apparent))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, generate_treemap);
+criterion_main!(benches);
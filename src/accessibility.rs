@@ -0,0 +1,195 @@
+//! This module makes the treemap and the donut chart usable without a mouse and without
+//! telling colors apart. They are painted rather than built from widgets, so the treemap is
+//! announced to screen readers (through egui's AccessKit support) as one widget describing
+//! the selection, and the selection is moved with the keyboard: the arrow keys step through
+//! the items of the current folder in order of size, Enter opens the selected one,
+//! Backspace goes up, and Space ticks it. In high-contrast mode the treemap tells items
+//! apart by patterns and borders instead of color alone.
+
+use crate::basis::Basis;
+use crate::format;
+use crate::interaction::PointerAction;
+use crate::scanner::{FileSystemNode, SizeMetric};
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// The spacing of the lines of the high-contrast patterns, in points.
+const PATTERN_SPACING: f32 = 7.0;
+
+/// The accessibility preferences.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    /// Whether the treemap is drawn with patterns and borders instead of colors.
+    pub high_contrast: bool,
+}
+
+impl AccessibilitySettings {
+    /// Draws the accessibility settings.
+    pub fn show_settings(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.high_contrast, "High-contrast treemap")
+            .on_hover_text("Tells items apart by patterns and borders instead of color alone");
+        ui.weak("Tab moves between controls and into the treemap, where the arrow keys move the selection, Enter opens it, Backspace goes up, and Space ticks it.");
+    }
+}
+
+/// Returns the item `step` places from the selected one among the items of its folder,
+/// ordered by size as they are laid out, or the largest item of `root` if none is selected.
+/// Paths are relative to `root`. The selection stops at the first and last items.
+pub fn step_selection(root: &FileSystemNode, selected: Option<&[String]>, step: isize, metric: SizeMetric) -> Option<Vec<String>> {
+    let (parent_path, name) = match selected {
+        Some([parent @ .., name]) => (parent, Some(name)),
+        _ => (&[][..], None),
+    };
+    let mut siblings: Vec<&FileSystemNode> = root.find(parent_path)?.children.iter().collect();
+    siblings.sort_by(|a, b| b.size_by(metric).cmp(&a.size_by(metric)).then_with(|| a.name.cmp(&b.name)));
+    let index = match name.and_then(|name| siblings.iter().position(|c| &c.name == name)) {
+        Some(i) => i.saturating_add_signed(step).min(siblings.len().checked_sub(1)?),
+        None => 0,
+    };
+    let mut path = parent_path.to_vec();
+    path.push(siblings.get(index)?.name.clone());
+    Some(path)
+}
+
+/// Turns this frame's key presses into an action on the view of `root`, where `selected` is
+/// the path of the selected item from `root`.
+pub fn keyboard_action(ui: &egui::Ui, root: &FileSystemNode, selected: Option<&[String]>, metric: SizeMetric) -> Option<PointerAction> {
+    let (next, previous, open, up, tick) = ui.input(|i| {
+        (
+            i.key_pressed(egui::Key::ArrowRight) || i.key_pressed(egui::Key::ArrowDown),
+            i.key_pressed(egui::Key::ArrowLeft) || i.key_pressed(egui::Key::ArrowUp),
+            i.key_pressed(egui::Key::Enter),
+            i.key_pressed(egui::Key::Backspace),
+            i.key_pressed(egui::Key::Space),
+        )
+    });
+    if next || previous {
+        step_selection(root, selected, if next { 1 } else { -1 }, metric).map(PointerAction::Select)
+    } else if up {
+        Some(PointerAction::Up)
+    } else if open {
+        selected.map(|path| PointerAction::Open(path.to_vec()))
+    } else if tick {
+        selected.map(|path| PointerAction::Tick(path.to_vec()))
+    } else {
+        None
+    }
+}
+
+/// Describes the view of `root` and the item selected in it, with its share of `basis`, for
+/// screen readers.
+pub fn describe(root: &FileSystemNode, selected: Option<&[String]>, metric: SizeMetric, basis: &Basis) -> String {
+    let mut description = format!("Treemap of {}, {}.", root.name, format::format_size(root.size_by(metric)));
+    match selected.and_then(|path| Some((path, root.find(path)?))) {
+        Some((path, node)) => {
+            let kind = if node.children.is_empty() { "file" } else { "folder" };
+            description += &format!(
+                " Selected {} {}, {}, {}.",
+                kind,
+                path.join("/"),
+                format::format_size(node.size_by(metric)),
+                basis.share(node.size_by(metric))
+            );
+        }
+        None => description += " Nothing selected, press an arrow key to select the largest item.",
+    }
+    description
+}
+
+/// Makes the treemap's `response` keep the arrow keys while it has focus, instead of
+/// moving the focus to another widget, and outlines it while it does.
+pub fn keep_arrow_keys(ui: &egui::Ui, response: &egui::Response) {
+    if !response.has_focus() {
+        return;
+    }
+    let filter = egui::EventFilter { horizontal_arrows: true, vertical_arrows: true, ..Default::default() };
+    ui.memory_mut(|m| m.set_focus_lock_filter(response.id, filter));
+    ui.painter().rect_stroke(response.rect.shrink(1.0), 0.0, ui.visuals().selection.stroke);
+}
+
+/// The shapes drawing a node of the treemap in high-contrast mode: a black rectangle with a
+/// white border, filled with one of four patterns picked by `pattern`.
+pub fn high_contrast_shapes(rect: egui::Rect, pattern: usize) -> Vec<egui::Shape> {
+    let mut shapes = vec![egui::Shape::rect_filled(rect, 0.0, egui::Color32::BLACK)];
+    // The lines stop short of the border.
+    let inner = rect.shrink(2.0);
+    let stroke = egui::Stroke::new(1.0, egui::Color32::from_gray(170));
+    let lines = |count: f32| (1..count as usize + 1).map(|i| i as f32 * PATTERN_SPACING);
+    match pattern % 4 {
+        // Solid black.
+        0 => {}
+        // Diagonal lines, each where x + y is constant.
+        1 => {
+            for offset in lines((rect.width() + rect.height()) / PATTERN_SPACING) {
+                let sum = rect.min.x + offset + rect.min.y;
+                let top = inner.min.y.max(sum - inner.max.x);
+                let bottom = inner.max.y.min(sum - inner.min.x);
+                if top < bottom {
+                    let segment = [egui::pos2(sum - top, top), egui::pos2(sum - bottom, bottom)];
+                    shapes.push(egui::Shape::line_segment(segment, stroke));
+                }
+            }
+        }
+        // Horizontal lines.
+        2 => {
+            for offset in lines(rect.height() / PATTERN_SPACING) {
+                let y = rect.min.y + offset;
+                if (inner.min.y..inner.max.y).contains(&y) {
+                    shapes.push(egui::Shape::line_segment([egui::pos2(inner.min.x, y), egui::pos2(inner.max.x, y)], stroke));
+                }
+            }
+        }
+        // Vertical lines.
+        _ => {
+            for offset in lines(rect.width() / PATTERN_SPACING) {
+                let x = rect.min.x + offset;
+                if (inner.min.x..inner.max.x).contains(&x) {
+                    shapes.push(egui::Shape::line_segment([egui::pos2(x, inner.min.y), egui::pos2(x, inner.max.y)], stroke));
+                }
+            }
+        }
+    }
+    shapes.push(egui::Shape::rect_stroke(rect, 0.0, egui::Stroke::new(1.5, egui::Color32::WHITE)));
+    shapes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, size: u64, children: Vec<FileSystemNode>) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    #[test]
+    fn test_arrow_keys_step_through_items_by_size() {
+        let tree = node(
+            "home",
+            100,
+            vec![
+                node("music", 20, vec![]),
+                node("videos", 70, vec![node("a.mp4", 40, vec![]), node("b.mp4", 30, vec![])]),
+                node("notes.txt", 10, vec![]),
+            ],
+        );
+        let path = |p: &str| p.split('/').map(str::to_string).collect::<Vec<_>>();
+        let step = |selected: Option<&str>, step| {
+            step_selection(&tree, selected.map(path).as_deref(), step, SizeMetric::Apparent).map(|p| p.join("/"))
+        };
+        assert_eq!(step(None, 1).as_deref(), Some("videos"));
+        assert_eq!(step(Some("videos"), 1).as_deref(), Some("music"));
+        assert_eq!(step(Some("music"), 1).as_deref(), Some("notes.txt"));
+        assert_eq!(step(Some("notes.txt"), 1).as_deref(), Some("notes.txt"));
+        assert_eq!(step(Some("videos"), -1).as_deref(), Some("videos"));
+        assert_eq!(step(Some("videos/a.mp4"), 1).as_deref(), Some("videos/b.mp4"));
+        assert_eq!(step(Some("gone"), 1).as_deref(), Some("videos"));
+        assert_eq!(step_selection(&node("empty", 0, vec![]), None, 1, SizeMetric::Apparent), None);
+
+        let basis = Basis { total: tree.size, name: "home".to_string() };
+        let described = describe(&tree, Some(&path("videos/a.mp4")), SizeMetric::Apparent, &basis);
+        assert!(described.starts_with("Treemap of home"), "{}", described);
+        assert!(described.contains("Selected file videos/a.mp4"), "{}", described);
+        assert!(described.ends_with("40.0% of home."), "{}", described);
+    }
+}
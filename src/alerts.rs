@@ -0,0 +1,148 @@
+//! This module watches volume usage and raises alerts when a volume fills up past a threshold.
+//! Alerts are shown as desktop notifications and in the tray icon's menu.
+
+use crate::volumes::{self, VolumeInfo};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// How often volume usage is checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// User settings for the tray icon and disk usage alerts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertSettings {
+    /// Whether the tray icon is shown.
+    pub tray_enabled: bool,
+    /// Whether a desktop notification is sent when a volume crosses the threshold.
+    pub notifications_enabled: bool,
+    /// The usage, in percent, above which a volume triggers an alert.
+    pub threshold_percent: f64,
+    /// The mount points to watch. An empty list watches every volume.
+    pub watched: Vec<String>,
+}
+
+impl Default for AlertSettings {
+    fn default() -> Self {
+        Self {
+            tray_enabled: false,
+            notifications_enabled: true,
+            threshold_percent: 90.0,
+            watched: Vec::new(),
+        }
+    }
+}
+
+impl AlertSettings {
+    /// Whether the volume at `mount_point` is being watched.
+    pub fn is_watched(&self, mount_point: &str) -> bool {
+        self.watched.is_empty() || self.watched.iter().any(|w| w == mount_point)
+    }
+}
+
+/// Returns the watched volumes whose usage is above the threshold.
+pub fn volumes_over_threshold<'a>(volumes: &'a [VolumeInfo], settings: &AlertSettings) -> Vec<&'a VolumeInfo> {
+    volumes
+        .iter()
+        .filter(|v| settings.is_watched(&v.label()))
+        .filter(|v| v.usage_fraction() * 100.0 >= settings.threshold_percent)
+        .collect()
+}
+
+/// Periodically checks volume usage and decides when to alert.
+///
+/// A volume is only alerted about once when it crosses the threshold; it has to drop
+/// back below the threshold before it can trigger another alert.
+#[derive(Default)]
+pub struct AlertMonitor {
+    last_check: Option<Instant>,
+    /// The mount points currently over the threshold that have already been alerted about.
+    alerted: HashSet<String>,
+    /// The volumes as of the last check.
+    volumes: Vec<VolumeInfo>,
+}
+
+impl AlertMonitor {
+    /// The volumes as of the last check.
+    pub fn volumes(&self) -> &[VolumeInfo] {
+        &self.volumes
+    }
+
+    /// Refreshes the volume list if the check interval has elapsed.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the volumes were refreshed, along with any volumes that newly crossed the threshold.
+    pub fn poll(&mut self, settings: &AlertSettings) -> (bool, Vec<VolumeInfo>) {
+        if self.last_check.is_some_and(|t| t.elapsed() < CHECK_INTERVAL) {
+            return (false, Vec::new());
+        }
+        self.last_check = Some(Instant::now());
+        self.volumes = volumes::list_volumes();
+        (true, self.check(settings))
+    }
+
+    /// Compares the current volumes against the threshold and returns the newly full ones.
+    fn check(&mut self, settings: &AlertSettings) -> Vec<VolumeInfo> {
+        let over: Vec<VolumeInfo> = volumes_over_threshold(&self.volumes, settings)
+            .into_iter()
+            .cloned()
+            .collect();
+        let over_labels: HashSet<String> = over.iter().map(|v| v.label()).collect();
+        let new_alerts = over.into_iter().filter(|v| !self.alerted.contains(&v.label())).collect();
+        self.alerted = over_labels;
+        new_alerts
+    }
+}
+
+/// Shows a desktop notification saying that `volume` is nearly full.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn notify(volume: &VolumeInfo) {
+    let body = format!("{} is {:.0}% full", volume.label(), volume.usage_fraction() * 100.0);
+    if let Err(e) = notify_rust::Notification::new().summary("Disk Scout").body(&body).show() {
+        eprintln!("Failed to show notification: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn volume(mount_point: &str, total: u64, available: u64) -> VolumeInfo {
+        VolumeInfo {
+            name: mount_point.to_string(),
+            mount_point: PathBuf::from(mount_point),
+            file_system: "ext4".to_string(),
+            total,
+            available,
+            read_only: false,
+        }
+    }
+
+    #[test]
+    fn test_alerts_fire_once_per_crossing() {
+        let settings = AlertSettings::default();
+        let mut monitor = AlertMonitor {
+            volumes: vec![volume("/", 100, 5), volume("/home", 100, 50)],
+            ..Default::default()
+        };
+        let alerts = monitor.check(&settings);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].label(), "/");
+
+        // Still full: no repeated alert.
+        assert!(monitor.check(&settings).is_empty());
+
+        // Drops below the threshold, then fills up again.
+        monitor.volumes = vec![volume("/", 100, 50)];
+        assert!(monitor.check(&settings).is_empty());
+        monitor.volumes = vec![volume("/", 100, 1)];
+        assert_eq!(monitor.check(&settings).len(), 1);
+
+        // Unwatched volumes never alert.
+        let settings = AlertSettings { watched: vec!["/home".to_string()], ..settings };
+        assert!(volumes_over_threshold(&monitor.volumes, &settings).is_empty());
+    }
+}
@@ -0,0 +1,164 @@
+//! This module recognizes mounts that show data found elsewhere in the same scan, so a scan
+//! of `/` doesn't count it twice. A bind mount makes a directory appear at a second path,
+//! and an overlay mount (as used by containers) shows the merged contents of layer
+//! directories stored elsewhere on the disk. The scanner counts such data once, where it is
+//! really stored or else where it is found first, and leaves the other paths empty, noting
+//! them as aliases.
+//!
+//! The mounts come from the mount table, which only Linux has, so elsewhere nothing is
+//! recognized.
+
+use crate::virtual_fs::{self, Mount};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How a path came to show data found elsewhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AliasKind {
+    /// The directory is bind mounted at another path too.
+    BindMount,
+    /// The directory is an overlay mount, merging layer directories.
+    Overlay,
+}
+
+impl AliasKind {
+    /// A short description, shown in the UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            AliasKind::BindMount => "bind mount",
+            AliasKind::Overlay => "overlay mount",
+        }
+    }
+}
+
+/// A directory that wasn't scanned, since its contents were counted at other paths.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alias {
+    /// The path of the directory.
+    pub path: PathBuf,
+    pub kind: AliasKind,
+    /// Where the contents were counted: the other path of a bind mount, or the layers of an
+    /// overlay.
+    pub originals: Vec<PathBuf>,
+}
+
+/// The mounts within a scan that may show data found elsewhere in it.
+#[derive(Debug, Default)]
+pub struct MountAliases {
+    /// The identities of the directories mounted within the scan, with the path each was
+    /// found at first, once it has been.
+    mounted: HashMap<(u64, u64), Option<PathBuf>>,
+    /// The devices of the overlay mounts whose layers are all within the scan, with the layers.
+    overlays: HashMap<u64, Vec<PathBuf>>,
+}
+
+impl MountAliases {
+    /// Finds the mounts in `mounts` that can show data found elsewhere in a scan of `root`,
+    /// a canonical path. `id_of` returns the device and inode numbers of a directory.
+    pub fn new(mounts: &[Mount], root: &Path, id_of: impl Fn(&Path) -> Option<(u64, u64)>) -> Self {
+        let mut aliases = Self::default();
+        for mount in mounts.iter().filter(|m| !m.is_virtual() && m.mount_point.starts_with(root)) {
+            let Some(id) = id_of(&mount.mount_point) else {
+                continue;
+            };
+            if mount.fs_type == "overlay" {
+                // Docker names the layers through symbolic links, so they are resolved first.
+                let layers: Vec<PathBuf> =
+                    overlay_layers(&mount.options).into_iter().map(|l| fs::canonicalize(&l).unwrap_or(l)).collect();
+                if !layers.is_empty() && layers.iter().all(|l| l.starts_with(root)) {
+                    aliases.overlays.insert(id.0, layers);
+                }
+            } else {
+                aliases.mounted.insert(id, None);
+            }
+        }
+        aliases
+    }
+
+    /// Finds the mounts that can show data found elsewhere in a scan of `root`.
+    pub fn for_scan(root: &Path, id_of: impl Fn(&Path) -> Option<(u64, u64)>) -> Self {
+        match fs::canonicalize(root) {
+            Ok(root) => Self::new(&virtual_fs::mounts(), &root, id_of),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Returns the alias the directory at `path`, identified by `id`, is, if it is one.
+    /// Directories are to be checked in the order they are scanned.
+    pub fn check(&mut self, path: &Path, id: (u64, u64)) -> Option<Alias> {
+        if let Some(layers) = self.overlays.get(&id.0) {
+            return Some(Alias { path: path.to_path_buf(), kind: AliasKind::Overlay, originals: layers.clone() });
+        }
+        match self.mounted.get_mut(&id)? {
+            Some(first) => Some(Alias { path: path.to_path_buf(), kind: AliasKind::BindMount, originals: vec![first.clone()] }),
+            first => {
+                *first = Some(path.to_path_buf());
+                None
+            }
+        }
+    }
+}
+
+/// Returns the layer directories named in the mount options of an overlay: the lower ones,
+/// then the upper one.
+pub fn overlay_layers(options: &str) -> Vec<PathBuf> {
+    let mut layers = Vec::new();
+    for option in options.split(',') {
+        if let Some(lower) = option.strip_prefix("lowerdir=") {
+            layers.extend(lower.split(':').filter(|l| !l.is_empty()).map(PathBuf::from));
+        } else if let Some(upper) = option.strip_prefix("upperdir=") {
+            layers.push(PathBuf::from(upper));
+        }
+    }
+    layers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aliases_are_found_once_counted() {
+        let table = "\
+/dev/sda1 / ext4 rw 0 0
+/dev/sda1 /srv/www ext4 rw 0 0
+proc /proc proc rw 0 0
+overlay /var/lib/docker/overlay2/c1/merged overlay rw,lowerdir=/var/lib/docker/overlay2/l1/diff:/var/lib/docker/overlay2/l2/diff,upperdir=/var/lib/docker/overlay2/c1/diff,workdir=/var/lib/docker/overlay2/c1/work 0 0
+";
+        let mounts = virtual_fs::parse_mounts(table);
+        assert_eq!(
+            overlay_layers(&mounts[3].options),
+            [
+                PathBuf::from("/var/lib/docker/overlay2/l1/diff"),
+                PathBuf::from("/var/lib/docker/overlay2/l2/diff"),
+                PathBuf::from("/var/lib/docker/overlay2/c1/diff"),
+            ]
+        );
+
+        // /srv/www is a bind mount of /home/site, which shares its inode.
+        let id_of = |path: &Path| {
+            Some(match path.to_str()? {
+                "/" => (1, 2),
+                "/srv/www" => (1, 500),
+                "/var/lib/docker/overlay2/c1/merged" => (40, 2),
+                _ => return None,
+            })
+        };
+        let mut aliases = MountAliases::new(&mounts, Path::new("/"), id_of);
+        assert_eq!(aliases.check(Path::new("/"), (1, 2)), None);
+        assert_eq!(aliases.check(Path::new("/home/site"), (1, 500)), None);
+        assert_eq!(
+            aliases.check(Path::new("/srv/www"), (1, 500)),
+            Some(Alias { path: PathBuf::from("/srv/www"), kind: AliasKind::BindMount, originals: vec![PathBuf::from("/home/site")] })
+        );
+        let overlay = aliases.check(Path::new("/var/lib/docker/overlay2/c1/merged"), (40, 2)).unwrap();
+        assert_eq!((overlay.kind, overlay.originals.len()), (AliasKind::Overlay, 3));
+
+        // In a scan of the container alone, some layers are outside the scan, so the overlay
+        // is scanned like any other mount.
+        let mut aliases = MountAliases::new(&mounts, Path::new("/var/lib/docker/overlay2/c1"), id_of);
+        assert_eq!(aliases.check(Path::new("/var/lib/docker/overlay2/c1/merged"), (40, 2)), None);
+    }
+}
@@ -0,0 +1,152 @@
+//! This module computes and draws statistics about the scanned tree for the analysis panel.
+//! Currently this is a histogram of file sizes, which shows whether space is taken by many
+//! small files or by a few huge ones.
+
+use crate::format;
+use crate::scanner::FileSystemNode;
+use eframe::egui;
+
+/// The upper bounds (exclusive) of the histogram buckets, in bytes. Each bucket is 16 times
+/// larger than the previous one, up to 1 GB; everything above that goes into a final bucket.
+const BUCKET_LIMITS: [u64; 6] = [
+    4 << 10,
+    64 << 10,
+    1 << 20,
+    16 << 20,
+    256 << 20,
+    1 << 30,
+];
+
+/// The labels of the buckets, one more than there are limits.
+const BUCKET_LABELS: [&str; 7] = [
+    "< 4 KB",
+    "4–64 KB",
+    "64 KB–1 MB",
+    "1–16 MB",
+    "16–256 MB",
+    "256 MB–1 GB",
+    "> 1 GB",
+];
+
+/// One bar of the size histogram.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Bucket {
+    /// The number of files in the bucket.
+    pub count: u64,
+    /// The combined size of the files in the bucket, in bytes.
+    pub bytes: u64,
+}
+
+/// A histogram of file sizes using log-scale buckets.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SizeHistogram {
+    pub buckets: [Bucket; BUCKET_LABELS.len()],
+}
+
+impl SizeHistogram {
+    /// Builds the histogram from all files (leaf nodes) below `node`.
+    pub fn from_tree(node: &FileSystemNode) -> Self {
+        let mut histogram = Self::default();
+        histogram.add_files(node);
+        histogram
+    }
+
+    fn add_files(&mut self, node: &FileSystemNode) {
+        if node.children.is_empty() {
+            let index = BUCKET_LIMITS
+                .iter()
+                .position(|&limit| node.size < limit)
+                .unwrap_or(BUCKET_LIMITS.len());
+            self.buckets[index].count += 1;
+            self.buckets[index].bytes += node.size;
+        }
+        for child in &node.children {
+            self.add_files(child);
+        }
+    }
+
+    /// The total number of files across all buckets.
+    pub fn total_count(&self) -> u64 {
+        self.buckets.iter().map(|b| b.count).sum()
+    }
+
+    /// The total size of the files across all buckets, in bytes.
+    pub fn total_bytes(&self) -> u64 {
+        self.buckets.iter().map(|b| b.bytes).sum()
+    }
+}
+
+/// Draws the histogram as two rows of horizontal bars per bucket: file count and bytes.
+pub fn show_histogram(ui: &mut egui::Ui, histogram: &SizeHistogram) {
+    let total_count = histogram.total_count().max(1);
+    let total_bytes = histogram.total_bytes().max(1);
+    let count_color = egui::Color32::from_rgb(0, 153, 255);
+    let bytes_color = egui::Color32::from_rgb(255, 153, 0);
+
+    ui.horizontal(|ui| {
+        ui.colored_label(count_color, "■ files");
+        ui.colored_label(bytes_color, "■ bytes");
+    });
+
+    egui::Grid::new("size_histogram").num_columns(3).striped(true).show(ui, |ui| {
+        let bar_width = (ui.available_width() - 260.0).max(80.0);
+        for (label, bucket) in BUCKET_LABELS.iter().zip(&histogram.buckets) {
+            ui.label(*label);
+            ui.vertical(|ui| {
+                bar(ui, bar_width, bucket.count as f32 / total_count as f32, count_color);
+                bar(ui, bar_width, bucket.bytes as f32 / total_bytes as f32, bytes_color);
+            });
+            ui.vertical(|ui| {
+                ui.label(format!("{} files", format::format_count(bucket.count)));
+                format::size_label(ui, bucket.bytes);
+            });
+            ui.end_row();
+        }
+    });
+}
+
+/// Draws a single horizontal bar filled to `fraction` of `width`.
+fn bar(ui: &mut egui::Ui, width: f32, fraction: f32, color: egui::Color32) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(width, 8.0), egui::Sense::hover());
+    ui.painter().rect_filled(rect, 2.0, egui::Color32::from_gray(40));
+    let filled = egui::Rect::from_min_size(rect.min, egui::vec2(rect.width() * fraction, rect.height()));
+    ui.painter().rect_filled(filled, 2.0, color);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(name: &str, size: u64) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children: vec![], special: None }
+    }
+
+    #[test]
+    fn test_size_histogram_buckets() {
+        let tree = FileSystemNode {
+            name: "root".to_string(),
+            size: 0,
+            allocated: 0,
+            children: vec![
+                leaf("tiny", 100),
+                leaf("small", 4 << 10),
+                FileSystemNode {
+                    name: "videos".to_string(),
+                    size: 0,
+                    allocated: 0,
+                    children: vec![leaf("movie.mkv", 3 << 30), leaf("clip.mp4", 20 << 20)],
+                    special: None,
+                },
+            ],
+            special: None,
+        };
+
+        let histogram = SizeHistogram::from_tree(&tree);
+        assert_eq!(histogram.buckets[0], Bucket { count: 1, bytes: 100 });
+        // Bucket limits are exclusive, so exactly 4 KB lands in the second bucket.
+        assert_eq!(histogram.buckets[1], Bucket { count: 1, bytes: 4 << 10 });
+        assert_eq!(histogram.buckets[4], Bucket { count: 1, bytes: 20 << 20 });
+        assert_eq!(histogram.buckets[6], Bucket { count: 1, bytes: 3 << 30 });
+        assert_eq!(histogram.total_count(), 4);
+    }
+}
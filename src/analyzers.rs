@@ -0,0 +1,387 @@
+//! This module defines analyzers: checks that look at a finished scan and report what they
+//! find in one shape, a finding with a title, the space involved, the paths it is about,
+//! and what to do about it. The built-in searches (duplicates, build output, logs, old
+//! files, hard links, and games) are analyzers, and others can be added to the registry:
+//! compiled in by registering them at startup, or loaded from dynamic libraries (see
+//! `plugins`).
+
+use crate::scanner::{FileSystemNode, SizeMetric};
+use crate::{artifacts, duplicates, games, hardlinks, logs, name_audit};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+/// Files not modified for this long are old.
+const OLD_AGE: u64 = 365 * 86_400;
+/// Old files smaller than this aren't worth reporting.
+const OLD_MIN_SIZE: u64 = 1 << 20;
+/// The most old files reported.
+const OLD_LIMIT: usize = 200;
+/// The most path problems reported.
+const PATH_PROBLEM_LIMIT: usize = 500;
+
+/// What an analyzer gets to look at.
+#[derive(Debug, Clone, Copy)]
+pub struct Input<'a> {
+    /// The folder that was scanned.
+    pub root: &'a Path,
+    /// The finished scan.
+    pub tree: &'a FileSystemNode,
+    /// How sizes are measured.
+    pub metric: SizeMetric,
+    /// When the analysis runs, in seconds since the Unix epoch.
+    pub now: u64,
+}
+
+/// What an analyzer suggests doing about a finding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    /// Have a look before deciding.
+    #[default]
+    Review,
+    /// Move it to the trash; it can be recreated or isn't needed.
+    Trash,
+    /// Pack it into an archive, since it is kept but not used.
+    Archive,
+}
+
+impl Action {
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Review => "Review",
+            Action::Trash => "Move to trash",
+            Action::Archive => "Archive",
+        }
+    }
+}
+
+/// Something an analyzer found.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    pub title: String,
+    /// The space the finding is about, or could free, in bytes.
+    pub size: u64,
+    /// The paths it is about, from the scan root.
+    #[serde(default)]
+    pub paths: Vec<Vec<String>>,
+    #[serde(default)]
+    pub action: Action,
+}
+
+/// A check that looks at a finished scan.
+pub trait Analyzer: Send + Sync {
+    /// A short name telling it apart from the others, e.g. `duplicates`.
+    fn id(&self) -> &str;
+    /// The name shown in the UI.
+    fn title(&self) -> &str;
+    /// What it looks for, in a sentence.
+    fn description(&self) -> &str;
+    /// Looks at the scan and returns what it found, the most important first.
+    fn analyze(&self, input: &Input) -> Result<Vec<Finding>, String>;
+}
+
+/// The analyzers the app knows.
+#[derive(Clone, Default)]
+pub struct Registry {
+    analyzers: Vec<Arc<dyn Analyzer>>,
+}
+
+impl Registry {
+    /// A registry of the built-in analyzers.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::default();
+        registry.register(Arc::new(Duplicates));
+        registry.register(Arc::new(BuildOutput));
+        registry.register(Arc::new(Logs));
+        registry.register(Arc::new(OldFiles));
+        registry.register(Arc::new(Hardlinks));
+        registry.register(Arc::new(Games));
+        registry.register(Arc::new(PathProblems));
+        registry
+    }
+
+    /// Adds `analyzer`, replacing the one with the same id if there is one.
+    pub fn register(&mut self, analyzer: Arc<dyn Analyzer>) {
+        match self.analyzers.iter_mut().find(|a| a.id() == analyzer.id()) {
+            Some(existing) => *existing = analyzer,
+            None => self.analyzers.push(analyzer),
+        }
+    }
+
+    /// The analyzers, in the order they were registered.
+    pub fn analyzers(&self) -> &[Arc<dyn Analyzer>] {
+        &self.analyzers
+    }
+}
+
+/// Splits a path joined with `/` into its names.
+fn split(path: &str) -> Vec<String> {
+    path.split('/').map(str::to_string).collect()
+}
+
+struct Duplicates;
+
+impl Analyzer for Duplicates {
+    fn id(&self) -> &str {
+        "duplicates"
+    }
+
+    fn title(&self) -> &str {
+        "Duplicate files"
+    }
+
+    fn description(&self) -> &str {
+        "Files with the same content, of which one copy is enough."
+    }
+
+    fn analyze(&self, input: &Input) -> Result<Vec<Finding>, String> {
+        let progress = duplicates::Progress::default();
+        let cache = duplicates::HashCache::default_path();
+        duplicates::find(input.root, input.tree, cache.as_deref(), &progress);
+        Ok(progress
+            .found()
+            .into_iter()
+            .map(|group| Finding {
+                title: format!("{} copies of {}", group.paths.len(), group.paths[0].rsplit('/').next().unwrap_or_default()),
+                size: group.wasted(),
+                paths: group.paths.iter().map(|p| split(p)).collect(),
+                action: Action::Trash,
+            })
+            .collect())
+    }
+}
+
+struct BuildOutput;
+
+impl Analyzer for BuildOutput {
+    fn id(&self) -> &str {
+        "build-output"
+    }
+
+    fn title(&self) -> &str {
+        "Build output"
+    }
+
+    fn description(&self) -> &str {
+        "Folders of build tools and package managers, which are recreated when needed."
+    }
+
+    fn analyze(&self, input: &Input) -> Result<Vec<Finding>, String> {
+        Ok(artifacts::find(input.tree, input.metric)
+            .into_iter()
+            .map(|artifact| Finding {
+                title: format!("{} ({})", artifact.path.join("/"), artifact.kind),
+                size: artifact.size,
+                paths: vec![artifact.path],
+                action: Action::Trash,
+            })
+            .collect())
+    }
+}
+
+struct Logs;
+
+impl Analyzer for Logs {
+    fn id(&self) -> &str {
+        "logs"
+    }
+
+    fn title(&self) -> &str {
+        "Log files"
+    }
+
+    fn description(&self) -> &str {
+        "Large log files, which are worth rotating or cleaning up."
+    }
+
+    fn analyze(&self, input: &Input) -> Result<Vec<Finding>, String> {
+        Ok(logs::find(input.root, input.tree, None, input.now)
+            .into_iter()
+            .map(|log| Finding { title: log.path.join("/"), size: log.size, paths: vec![log.path], action: Action::Review })
+            .collect())
+    }
+}
+
+struct OldFiles;
+
+impl Analyzer for OldFiles {
+    fn id(&self) -> &str {
+        "old-files"
+    }
+
+    fn title(&self) -> &str {
+        "Old files"
+    }
+
+    fn description(&self) -> &str {
+        "Large files nobody modified for a year, which could be archived."
+    }
+
+    fn analyze(&self, input: &Input) -> Result<Vec<Finding>, String> {
+        let mut old = Vec::new();
+        let mut stack: Vec<(Vec<String>, &FileSystemNode)> = vec![(Vec::new(), input.tree)];
+        while let Some((path, node)) = stack.pop() {
+            for child in &node.children {
+                let mut child_path = path.clone();
+                child_path.push(child.name.clone());
+                if !child.children.is_empty() {
+                    stack.push((child_path, child));
+                    continue;
+                }
+                let size = child.size_by(input.metric);
+                if size < OLD_MIN_SIZE {
+                    continue;
+                }
+                let on_disk = child_path.iter().fold(input.root.to_path_buf(), |dir, name| dir.join(name));
+                let Some(modified) = std::fs::metadata(on_disk).and_then(|m| m.modified()).ok() else {
+                    continue;
+                };
+                let modified = modified.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+                if input.now.saturating_sub(modified) >= OLD_AGE {
+                    let years = input.now.saturating_sub(modified) / OLD_AGE;
+                    let title = format!("{} (untouched for {} year{})", child_path.join("/"), years, if years == 1 { "" } else { "s" });
+                    old.push(Finding { title, size, paths: vec![child_path], action: Action::Archive });
+                }
+            }
+        }
+        old.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.paths.cmp(&b.paths)));
+        old.truncate(OLD_LIMIT);
+        Ok(old)
+    }
+}
+
+struct Hardlinks;
+
+impl Analyzer for Hardlinks {
+    fn id(&self) -> &str {
+        "hard-links"
+    }
+
+    fn title(&self) -> &str {
+        "Hard links"
+    }
+
+    fn description(&self) -> &str {
+        "Files with several names, which take up space only once."
+    }
+
+    fn analyze(&self, input: &Input) -> Result<Vec<Finding>, String> {
+        Ok(hardlinks::find_groups(input.root, input.tree)
+            .into_iter()
+            .map(|group| Finding {
+                title: format!("{} links to one file", group.link_count),
+                size: group.size,
+                paths: group.paths.iter().map(|p| split(p)).collect(),
+                action: Action::Review,
+            })
+            .collect())
+    }
+}
+
+struct Games;
+
+impl Analyzer for Games {
+    fn id(&self) -> &str {
+        "games"
+    }
+
+    fn title(&self) -> &str {
+        "Games"
+    }
+
+    fn description(&self) -> &str {
+        "Games installed by Steam, Epic, and GOG, which their launchers can remove."
+    }
+
+    fn analyze(&self, input: &Input) -> Result<Vec<Finding>, String> {
+        Ok(games::find(input.root, input.tree, input.metric)
+            .into_iter()
+            .map(|game| Finding {
+                title: format!("{} ({})", game.title, game.store.label()),
+                size: game.size,
+                paths: vec![game.path],
+                action: Action::Review,
+            })
+            .collect())
+    }
+}
+
+struct PathProblems;
+
+impl Analyzer for PathProblems {
+    fn id(&self) -> &str {
+        "path-problems"
+    }
+
+    fn title(&self) -> &str {
+        "Path problems"
+    }
+
+    fn description(&self) -> &str {
+        "Paths too long for some systems and names other systems don't allow, to fix before moving data to another filesystem."
+    }
+
+    fn analyze(&self, input: &Input) -> Result<Vec<Finding>, String> {
+        Ok(name_audit::audit(input.root, input.tree, input.metric)
+            .into_iter()
+            .take(PATH_PROBLEM_LIMIT)
+            .map(|issue| Finding {
+                title: format!("{}: {}", issue.path.join("/"), issue.problem.describe()),
+                size: issue.size,
+                paths: vec![issue.path],
+                action: Action::Review,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Largest;
+
+    impl Analyzer for Largest {
+        fn id(&self) -> &str {
+            "build-output"
+        }
+
+        fn title(&self) -> &str {
+            "Largest file"
+        }
+
+        fn description(&self) -> &str {
+            "The largest file in the scan."
+        }
+
+        fn analyze(&self, input: &Input) -> Result<Vec<Finding>, String> {
+            let largest = input.tree.children.iter().max_by_key(|c| c.size).ok_or("the scan is empty")?;
+            Ok(vec![Finding { title: largest.name.clone(), size: largest.size, paths: vec![vec![largest.name.clone()]], action: Action::Review }])
+        }
+    }
+
+    #[test]
+    fn test_registry_runs_builtin_and_added_analyzers() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("app/target")).unwrap();
+        std::fs::write(dir.path().join("app/Cargo.toml"), "").unwrap();
+        std::fs::write(dir.path().join("app/target/app.bin"), vec![0u8; 100]).unwrap();
+        let tree = crate::scanner::build_tree(dir.path(), &Default::default()).unwrap();
+        let input = Input { root: dir.path(), tree: &tree, metric: SizeMetric::Apparent, now: 0 };
+
+        let mut registry = Registry::with_builtins();
+        let build_output = registry.analyzers().iter().find(|a| a.id() == "build-output").unwrap().clone();
+        let findings = build_output.analyze(&input).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!((findings[0].size, findings[0].action), (100, Action::Trash));
+        assert_eq!(findings[0].paths, [["app", "target"]]);
+
+        // An analyzer with the same id replaces the built-in one.
+        let count = registry.analyzers().len();
+        registry.register(Arc::new(Largest));
+        assert_eq!(registry.analyzers().len(), count);
+        assert_eq!(registry.analyzers()[1].analyze(&input).unwrap()[0].title, "app");
+    }
+}
@@ -0,0 +1,142 @@
+//! This module animates the treemap between two layouts.
+//! When the layout changes (drilling down, resizing, toggling the view), each rectangle
+//! is interpolated from where the same node was drawn before to its new position, so the
+//! user can follow where things went instead of the whole map snapping to a new state.
+
+use crate::treemap::{Rectangle, TreemapNode};
+use std::collections::HashMap;
+
+/// How long a transition between two layouts lasts, in seconds.
+pub const TRANSITION_DURATION: f64 = 0.2;
+
+/// The starting state of one node in a transition.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StartState {
+    /// Where the node starts moving from.
+    pub rect: Rectangle,
+    /// The depth the node was drawn at, used to interpolate its color.
+    pub depth: usize,
+    /// The share of its parent the node was drawn with, also used to interpolate its color.
+    pub share: f64,
+}
+
+/// An in-progress transition from a previous layout to the current one.
+#[derive(Debug, Clone)]
+pub struct Transition {
+    /// The start state for each node of the new layout, in the same order.
+    starts: Vec<StartState>,
+    /// The time (in seconds) at which the transition began.
+    started_at: f64,
+}
+
+impl Transition {
+    /// Matches the nodes of `next` against those of `previous` by their path from the scan root.
+    ///
+    /// `previous_root` and `next_root` are the paths from the scan root to the root of each layout,
+    /// so nodes can be matched even if the user drilled down or went back up in between.
+    /// A node that wasn't visible before starts from the rectangle of its nearest visible ancestor,
+    /// so new children appear to grow out of their parent.
+    pub fn new(
+        previous: &[TreemapNode],
+        previous_root: &[String],
+        next: &[TreemapNode],
+        next_root: &[String],
+        now: f64,
+    ) -> Self {
+        let previous_by_path: HashMap<Vec<String>, StartState> = previous
+            .iter()
+            .map(|node| {
+                let start = StartState { rect: node.rect, depth: node.depth, share: node.share };
+                (absolute_path(previous_root, &node.path), start)
+            })
+            .collect();
+
+        let starts = next
+            .iter()
+            .map(|node| {
+                let mut path = absolute_path(next_root, &node.path);
+                loop {
+                    if let Some(start) = previous_by_path.get(&path) {
+                        // An ancestor keeps its own depth so the child blends from the parent's color.
+                        return *start;
+                    }
+                    if path.pop().is_none() || path.len() < next_root.len() {
+                        return StartState { rect: node.rect, depth: node.depth, share: node.share };
+                    }
+                }
+            })
+            .collect();
+
+        Self { starts, started_at: now }
+    }
+
+    /// Returns the eased progress of the transition at time `now`, from 0.0 to 1.0.
+    pub fn progress(&self, now: f64) -> f64 {
+        let t = ((now - self.started_at) / TRANSITION_DURATION).clamp(0.0, 1.0);
+        // Ease out cubic: fast at the start, settling gently into place.
+        1.0 - (1.0 - t).powi(3)
+    }
+
+    /// Whether the transition has finished at time `now`.
+    pub fn is_finished(&self, now: f64) -> bool {
+        now - self.started_at >= TRANSITION_DURATION
+    }
+
+    /// Returns the start state of the node at `index` in the new layout.
+    pub fn start(&self, index: usize) -> Option<&StartState> {
+        self.starts.get(index)
+    }
+}
+
+/// Joins the path of a layout root with the path of a node inside that layout.
+fn absolute_path(root: &[String], path: &[String]) -> Vec<String> {
+    root.iter().chain(path).cloned().collect()
+}
+
+/// Linearly interpolates between two rectangles, where `t` is 0.0 at `from` and 1.0 at `to`.
+pub fn lerp_rect(from: Rectangle, to: Rectangle, t: f64) -> Rectangle {
+    let lerp = |a: f64, b: f64| a + (b - a) * t;
+    Rectangle {
+        x: lerp(from.x, to.x),
+        y: lerp(from.y, to.y),
+        width: lerp(from.width, to.width),
+        height: lerp(from.height, to.height),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout_node(path: &[&str], rect: Rectangle) -> TreemapNode {
+        TreemapNode {
+            rect,
+            depth: path.len(),
+            share: 1.0,
+            path: path.iter().map(|n| n.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_transition_matches_nodes_across_drill_down() {
+        let small = Rectangle { x: 0.0, y: 0.0, width: 10.0, height: 10.0 };
+        let full = Rectangle { x: 0.0, y: 0.0, width: 100.0, height: 100.0 };
+        let previous = vec![layout_node(&["docs"], small), layout_node(&["docs", "a.txt"], small)];
+        // After drilling into "docs", "a.txt" fills the view and "b.txt" is new.
+        let next = vec![layout_node(&["a.txt"], full), layout_node(&["b.txt"], full)];
+        let root = vec!["docs".to_string()];
+
+        let transition = Transition::new(&previous, &[], &next, &root, 1.0);
+        assert_eq!(transition.start(0).unwrap().depth, 2);
+        // The new node grows out of its parent.
+        assert_eq!(transition.start(1).unwrap().rect, small);
+        assert_eq!(transition.start(1).unwrap().depth, 1);
+
+        assert_eq!(transition.progress(1.0), 0.0);
+        assert_eq!(transition.progress(1.0 + TRANSITION_DURATION), 1.0);
+        assert!(transition.is_finished(2.0));
+
+        let halfway = lerp_rect(small, full, 0.5);
+        assert_eq!(halfway.width, 55.0);
+    }
+}
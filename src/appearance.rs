@@ -0,0 +1,112 @@
+//! This module holds the size of the interface: a scale factor for everything, the size of
+//! the text, and a presentation mode that enlarges both for screen sharing and projectors.
+//! They are applied by overriding egui's zoom factor and text styles. Ctrl+Plus, Ctrl+Minus,
+//! and Ctrl+0 change the scale setting, so zooming from the keyboard is remembered too.
+
+use eframe::egui;
+use egui::gui_zoom::kb_shortcuts;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The size of body text in egui's default style.
+const DEFAULT_FONT_SIZE: f32 = 12.5;
+
+/// How much presentation mode enlarges the interface and its text, on top of the settings.
+const PRESENTATION_SCALE: f32 = 1.5;
+
+/// The range of the scale factor.
+const SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.5..=3.0;
+
+/// The user's interface size preferences.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppearanceSettings {
+    /// How much the whole interface is enlarged, 1.0 being egui's size.
+    pub scale: f32,
+    /// The size of body text, in points. The other text styles keep their proportions to it.
+    pub font_size: f32,
+    /// Whether everything is shown larger still, for screen sharing.
+    pub presentation_mode: bool,
+}
+
+impl Default for AppearanceSettings {
+    fn default() -> Self {
+        Self { scale: 1.0, font_size: DEFAULT_FONT_SIZE, presentation_mode: false }
+    }
+}
+
+impl AppearanceSettings {
+    /// The zoom factor the interface is shown at.
+    pub fn zoom_factor(&self) -> f32 {
+        let presentation = if self.presentation_mode { PRESENTATION_SCALE } else { 1.0 };
+        self.scale.clamp(*SCALE_RANGE.start(), *SCALE_RANGE.end()) * presentation
+    }
+
+    /// egui's text styles, resized to the font size.
+    pub fn text_styles(&self) -> BTreeMap<egui::TextStyle, egui::FontId> {
+        let ratio = self.font_size.max(1.0) / DEFAULT_FONT_SIZE;
+        let mut styles = egui::style::default_text_styles();
+        for font in styles.values_mut() {
+            font.size *= ratio;
+        }
+        styles
+    }
+
+    /// Changes the scale from the keyboard shortcuts pressed this frame, then applies the
+    /// settings to `ctx` where they differ from what it shows.
+    pub fn apply(&mut self, ctx: &egui::Context) {
+        ctx.input_mut(|i| {
+            if i.consume_shortcut(&kb_shortcuts::ZOOM_RESET) {
+                self.scale = 1.0;
+            }
+            if i.consume_shortcut(&kb_shortcuts::ZOOM_IN) || i.consume_shortcut(&kb_shortcuts::ZOOM_IN_SECONDARY) {
+                self.scale = ((self.scale + 0.1) * 10.0).round() / 10.0;
+            }
+            if i.consume_shortcut(&kb_shortcuts::ZOOM_OUT) {
+                self.scale = ((self.scale - 0.1) * 10.0).round() / 10.0;
+            }
+        });
+        self.scale = self.scale.clamp(*SCALE_RANGE.start(), *SCALE_RANGE.end());
+        if ctx.zoom_factor() != self.zoom_factor() {
+            ctx.set_zoom_factor(self.zoom_factor());
+        }
+        let text_styles = self.text_styles();
+        if ctx.style().text_styles != text_styles {
+            ctx.style_mut(|style| style.text_styles = text_styles);
+        }
+    }
+
+    /// Draws the appearance settings.
+    pub fn show_settings(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.scale, SCALE_RANGE).step_by(0.1).text("Interface scale"))
+            .on_hover_text("Ctrl+Plus and Ctrl+Minus change it too, and Ctrl+0 resets it");
+        ui.add(egui::Slider::new(&mut self.font_size, 8.0..=32.0).step_by(0.5).suffix(" pt").text("Text size"));
+        ui.checkbox(&mut self.presentation_mode, "Presentation mode")
+            .on_hover_text("Shows everything larger, for screen sharing and projectors");
+        if *self != Self::default() && ui.button("Reset").clicked() {
+            *self = Self::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_and_interface_are_resized() {
+        let settings = AppearanceSettings::default();
+        assert_eq!(settings.zoom_factor(), 1.0);
+        assert_eq!(settings.text_styles(), egui::style::default_text_styles());
+
+        let larger = AppearanceSettings { scale: 1.2, font_size: 25.0, presentation_mode: true };
+        assert!((larger.zoom_factor() - 1.8).abs() < 1e-6);
+        let styles = larger.text_styles();
+        assert_eq!(styles[&egui::TextStyle::Body].size, 25.0);
+        assert_eq!(styles[&egui::TextStyle::Heading].size, 36.0);
+        assert_eq!(styles[&egui::TextStyle::Monospace].family, egui::FontFamily::Monospace);
+
+        let out_of_range = AppearanceSettings { scale: 40.0, ..AppearanceSettings::default() };
+        assert_eq!(out_of_range.zoom_factor(), 3.0);
+    }
+}
@@ -0,0 +1,179 @@
+//! This module packs a folder into a `.tar.zst` or `.zip` archive, so folders that are
+//! rarely used can be kept in less space or moved elsewhere. The archive is written next to
+//! its destination under a temporary name and only renamed once it is complete, so a failed
+//! or interrupted run never leaves something that looks like a finished archive.
+
+use crate::read_only;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The zstd level, which favors speed since archives of large folders take a while.
+const ZSTD_LEVEL: i32 = 3;
+
+/// A format folders can be archived in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    TarZst,
+    Zip,
+}
+
+impl Format {
+    pub const ALL: [Format; 2] = [Format::TarZst, Format::Zip];
+
+    /// The extension of archives in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::TarZst => "tar.zst",
+            Format::Zip => "zip",
+        }
+    }
+
+    /// The name of the format, as shown to the user.
+    pub fn label(self) -> &'static str {
+        match self {
+            Format::TarZst => "tar.zst (smaller, keeps permissions)",
+            Format::Zip => "zip (opens everywhere)",
+        }
+    }
+}
+
+/// Packs the folder at `source` into an archive at `destination`. `progress` is increased by
+/// the number of bytes of file contents read as they are packed.
+pub fn create(source: &Path, destination: &Path, format: Format, progress: &AtomicU64) -> io::Result<()> {
+    read_only::check("Archiving")?;
+    if !source.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Only folders can be archived"));
+    }
+    if destination.starts_with(source) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "The archive can't be inside the folder it archives"));
+    }
+    let mut partial = destination.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let name = source.file_name().map_or_else(|| PathBuf::from("archive"), PathBuf::from);
+    let result = File::create(&partial).and_then(|file| match format {
+        Format::TarZst => write_tar(file, source, &name, progress),
+        Format::Zip => write_zip(file, source, &name, progress),
+    });
+    match result.and_then(|()| fs::rename(&partial, destination)) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+/// Calls `visit` with the path of every entry below `dir`, parents before their children,
+/// without following links. Paths are relative to `base`.
+fn walk(base: &Path, dir: &Path, visit: &mut impl FnMut(&Path, &fs::Metadata) -> io::Result<()>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let metadata = fs::symlink_metadata(&path)?;
+        visit(path.strip_prefix(base).unwrap_or(&path), &metadata)?;
+        if metadata.is_dir() {
+            walk(base, &path, visit)?;
+        }
+    }
+    Ok(())
+}
+
+/// A reader that counts the bytes read through it.
+struct Counting<'a, R> {
+    inner: R,
+    progress: &'a AtomicU64,
+}
+
+impl<R: Read> Read for Counting<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.progress.fetch_add(read as u64, Ordering::Relaxed);
+        Ok(read)
+    }
+}
+
+fn write_tar(file: File, source: &Path, name: &Path, progress: &AtomicU64) -> io::Result<()> {
+    let encoder = zstd::Encoder::new(file, ZSTD_LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+    builder.follow_symlinks(false);
+    builder.append_dir(name, source)?;
+    walk(source, source, &mut |relative, metadata| {
+        let (path, archived) = (source.join(relative), name.join(relative));
+        if metadata.is_file() {
+            let mut header = tar::Header::new_gnu();
+            header.set_metadata(metadata);
+            let reader = Counting { inner: File::open(&path)?, progress };
+            builder.append_data(&mut header, archived, reader)
+        } else {
+            // Folders and links are stored as they are.
+            builder.append_path_with_name(&path, archived)
+        }
+    })?;
+    builder.into_inner()?.finish()?.sync_all()
+}
+
+fn write_zip(file: File, source: &Path, name: &Path, progress: &AtomicU64) -> io::Result<()> {
+    let mut writer = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default().large_file(true);
+    // Zip paths always use forward slashes.
+    let zip_path = |path: &Path| path.iter().map(|p| p.to_string_lossy()).collect::<Vec<_>>().join("/");
+    writer.add_directory(zip_path(name), options)?;
+    walk(source, source, &mut |relative, metadata| {
+        let (path, archived) = (source.join(relative), zip_path(&name.join(relative)));
+        if metadata.is_dir() {
+            writer.add_directory(archived, options)?;
+        } else if metadata.is_symlink() {
+            writer.add_symlink(archived, fs::read_link(&path)?.to_string_lossy(), options)?;
+        } else if metadata.is_file() {
+            writer.start_file(archived, options)?;
+            io::copy(&mut Counting { inner: File::open(&path)?, progress }, &mut writer)?;
+        }
+        Ok(())
+    })?;
+    let mut file = writer.finish()?;
+    file.flush()?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folders_are_archived_in_both_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("photos");
+        fs::create_dir_all(source.join("2019/raw")).unwrap();
+        fs::write(source.join("2019/beach.jpg"), [7u8; 3000]).unwrap();
+        fs::write(source.join("notes.txt"), "keep").unwrap();
+
+        let tar_zst = dir.path().join("photos.tar.zst");
+        let progress = AtomicU64::new(0);
+        create(&source, &tar_zst, Format::TarZst, &progress).unwrap();
+        assert_eq!(progress.load(Ordering::Relaxed), 3004);
+        let mut archive = tar::Archive::new(zstd::Decoder::new(File::open(&tar_zst).unwrap()).unwrap());
+        let mut paths: Vec<String> =
+            archive.entries().unwrap().map(|e| e.unwrap().path().unwrap().to_string_lossy().into_owned()).collect();
+        paths.sort();
+        assert_eq!(paths, ["photos", "photos/2019", "photos/2019/beach.jpg", "photos/2019/raw", "photos/notes.txt"]);
+
+        let zip_file = dir.path().join("photos.zip");
+        create(&source, &zip_file, Format::Zip, &AtomicU64::new(0)).unwrap();
+        let mut archive = zip::ZipArchive::new(File::open(&zip_file).unwrap()).unwrap();
+        let mut contents = String::new();
+        archive.by_name("photos/notes.txt").unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "keep");
+        assert_eq!(archive.by_name("photos/2019/beach.jpg").unwrap().size(), 3000);
+
+        // A failed run leaves nothing behind.
+        let inside = source.join("photos.zip");
+        assert!(create(&source, &inside, Format::Zip, &AtomicU64::new(0)).is_err());
+        assert!(!source.join("photos.zip.partial").exists());
+        assert!(create(&dir.path().join("missing"), &zip_file, Format::Zip, &AtomicU64::new(0)).is_err());
+        assert!(!dir.path().join("photos.zip.partial").exists());
+    }
+}
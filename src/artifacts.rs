@@ -0,0 +1,137 @@
+//! This module recognizes well-known build output folders, such as Rust's `target` or
+//! JavaScript's `node_modules`. They can be rebuilt or reinstalled at any time, which makes
+//! them the safest space to reclaim. Names that are also common for other folders (`build`,
+//! `dist`, `target`) only count next to a file of the tool that produces them.
+
+use crate::scanner::{FileSystemNode, SizeMetric};
+
+/// A folder name that holds build output, the files next to it that confirm it (none if the
+/// name alone is enough), and what it is.
+const KNOWN: &[(&str, &[&str], &str)] = &[
+    ("target", &["Cargo.toml", "pom.xml"], "Rust or Maven build output"),
+    (
+        "build",
+        &["build.gradle", "build.gradle.kts", "CMakeLists.txt", "package.json", "setup.py", "pyproject.toml"],
+        "Build output",
+    ),
+    ("dist", &["package.json", "setup.py", "pyproject.toml"], "Distribution build output"),
+    ("node_modules", &[], "Installed npm packages"),
+    (".venv", &[], "Python virtual environment"),
+    ("__pycache__", &[], "Python bytecode cache"),
+];
+
+/// A build output folder found in a scan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Artifact {
+    /// The path of the folder from the scan root.
+    pub path: Vec<String>,
+    /// What the folder holds.
+    pub kind: &'static str,
+    /// The size of the folder.
+    pub size: u64,
+}
+
+/// Tells what `node` holds if it is a build output folder, judging by its name and by the
+/// names of its siblings.
+pub fn detect(node: &FileSystemNode, siblings: &[FileSystemNode]) -> Option<&'static str> {
+    if node.children.is_empty() {
+        return None;
+    }
+    KNOWN
+        .iter()
+        .find(|(name, markers, _)| {
+            node.name == *name && (markers.is_empty() || siblings.iter().any(|s| markers.contains(&s.name.as_str())))
+        })
+        .map(|(_, _, kind)| *kind)
+}
+
+/// Returns the build output folders in `tree`, largest first, sized by `metric`. Folders
+/// inside build output (such as packages nested in `node_modules`) are part of it and not
+/// listed separately.
+pub fn find(tree: &FileSystemNode, metric: SizeMetric) -> Vec<Artifact> {
+    let mut artifacts = Vec::new();
+    find_in(tree, metric, &mut Vec::new(), &mut artifacts);
+    artifacts.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    artifacts
+}
+
+fn find_in(node: &FileSystemNode, metric: SizeMetric, path: &mut Vec<String>, artifacts: &mut Vec<Artifact>) {
+    for child in &node.children {
+        path.push(child.name.clone());
+        match detect(child, &node.children) {
+            Some(kind) => artifacts.push(Artifact { path: path.clone(), kind, size: child.size_by(metric) }),
+            None => find_in(child, metric, path, artifacts),
+        }
+        path.pop();
+    }
+}
+
+/// Moves the build output folders at `paths` from the scan root `root` to the trash, after
+/// checking that each is still a folder. Returns the paths that were moved, and a message
+/// for each one that wasn't.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn move_to_trash(root: &std::path::Path, paths: &[Vec<String>]) -> (Vec<Vec<String>>, Vec<String>) {
+    if let Err(e) = crate::read_only::check("Moving to the trash") {
+        return (Vec::new(), vec![e.to_string()]);
+    }
+    let (mut moved, mut failures) = (Vec::new(), Vec::new());
+    for path in paths {
+        let on_disk = path.iter().fold(root.to_path_buf(), |dir, name| dir.join(name));
+        // Don't follow a link that replaced the folder since the scan.
+        let result = match on_disk.symlink_metadata() {
+            Ok(metadata) if metadata.is_dir() => trash::delete(&on_disk).map_err(|e| e.to_string()),
+            Ok(_) => Err("it is no longer a folder".to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        match result {
+            Ok(()) => moved.push(path.clone()),
+            Err(e) => failures.push(format!("Failed to move {} to the trash: {}", on_disk.display(), e)),
+        }
+    }
+    (moved, failures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, size: u64, children: Vec<FileSystemNode>) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    #[test]
+    fn test_build_output_is_recognized() {
+        let tree = node(
+            "code",
+            0,
+            vec![
+                node(
+                    "app",
+                    0,
+                    vec![
+                        node("Cargo.toml", 1, vec![]),
+                        node("target", 50, vec![node("debug", 50, vec![])]),
+                        // Without a marker, a generic name isn't trusted.
+                        node("build", 7, vec![node("notes.txt", 7, vec![])]),
+                    ],
+                ),
+                node(
+                    "web",
+                    0,
+                    vec![node(
+                        "node_modules",
+                        80,
+                        vec![node("left-pad", 80, vec![node("node_modules", 30, vec![node("x", 30, vec![])])])],
+                    )],
+                ),
+                // A file is never build output.
+                node("__pycache__", 3, vec![]),
+            ],
+        );
+        let artifacts = find(&tree, SizeMetric::Apparent);
+        let paths: Vec<String> = artifacts.iter().map(|a| a.path.join("/")).collect();
+        assert_eq!(paths, ["web/node_modules", "app/target"]);
+        assert_eq!(artifacts[1].kind, "Rust or Maven build output");
+        assert_eq!(artifacts[0].size, 80);
+    }
+}
@@ -0,0 +1,67 @@
+//! This module holds what shares of space are measured against: the folder shown in the
+//! treemap, the whole scan, or the capacity of the volume the scan is on. The tooltips, the
+//! screen reader description, the donut chart's legend, the copied tables, and the reports
+//! all give their percentages against the same basis.
+
+use serde::{Deserialize, Serialize};
+
+/// What percentages are relative to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SizeBasis {
+    /// The folder the treemap shows.
+    #[default]
+    View,
+    /// The folder that was scanned.
+    Scan,
+    /// The capacity of the volume holding the scanned folder.
+    Volume,
+}
+
+impl SizeBasis {
+    /// Every basis, in the order they are offered.
+    pub const ALL: [SizeBasis; 3] = [SizeBasis::View, SizeBasis::Scan, SizeBasis::Volume];
+
+    /// The name of the basis, as shown in the UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            SizeBasis::View => "Folder shown",
+            SizeBasis::Scan => "Whole scan",
+            SizeBasis::Volume => "Volume capacity",
+        }
+    }
+}
+
+/// The size shares are measured against, and what to call it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Basis {
+    /// The size, in bytes.
+    pub total: u64,
+    /// What has that size, e.g. "Documents" or "the volume /".
+    pub name: String,
+}
+
+impl Basis {
+    /// The share of the basis that `size` is, in percent.
+    pub fn percent(&self, size: u64) -> f64 {
+        if self.total > 0 { size as f64 * 100.0 / self.total as f64 } else { 0.0 }
+    }
+
+    /// The share of the basis that `size` is, e.g. "12.5% of Documents".
+    pub fn share(&self, size: u64) -> String {
+        format!("{:.1}% of {}", self.percent(size), self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share() {
+        let basis = Basis { total: 800, name: "the volume /".to_string() };
+        assert_eq!(basis.percent(200), 25.0);
+        assert_eq!(basis.share(100), "12.5% of the volume /");
+        let empty = Basis { total: 0, name: "empty".to_string() };
+        assert_eq!(empty.percent(10), 0.0);
+    }
+}
@@ -0,0 +1,189 @@
+//! This module checks scans against space budgets set by the user, such as
+//! "Downloads ≤ 20 GiB" or "logs ≤ 5 GiB". A budget applies to every folder matching
+//! its glob pattern, and the folders over budget are flagged once a scan has finished.
+
+use crate::format;
+use crate::scanner::{FileSystemNode, SizeMetric};
+use eframe::egui;
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+
+/// The number of bytes in a gibibyte, the unit budgets are entered in.
+const GIB: f64 = (1u64 << 30) as f64;
+
+/// The most space one group of folders should take up.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Budget {
+    /// A glob pattern matched against folder names and against their paths from the scan root.
+    pub pattern: String,
+    /// The size the matching folders should stay within, in bytes.
+    pub limit: u64,
+}
+
+/// The user's budgets, and whether exceeding them is notified.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetSettings {
+    /// The budgets, in the order they were added.
+    pub budgets: Vec<Budget>,
+    /// Whether a desktop notification is sent when a scan that finished in the background
+    /// exceeds a budget.
+    pub notifications_enabled: bool,
+}
+
+/// A folder that is over budget.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// The path of the folder from the scan root.
+    pub path: Vec<String>,
+    /// The size of the folder.
+    pub size: u64,
+    /// The budget the folder exceeds.
+    pub budget: Budget,
+}
+
+/// Returns the folders in `tree` that are larger than a budget they match, sized by `metric`.
+/// A folder matching several budgets is reported for each one it exceeds.
+pub fn check(tree: &FileSystemNode, budgets: &[Budget], metric: SizeMetric) -> Vec<Violation> {
+    let patterns: Vec<(Pattern, &Budget)> = budgets
+        .iter()
+        .filter_map(|budget| match Pattern::new(&budget.pattern) {
+            Ok(pattern) => Some((pattern, budget)),
+            Err(e) => {
+                eprintln!("Ignoring invalid budget pattern {:?}: {}", budget.pattern, e);
+                None
+            }
+        })
+        .collect();
+    let mut violations = Vec::new();
+    if !patterns.is_empty() {
+        check_children(tree, &patterns, metric, &mut Vec::new(), &mut violations);
+    }
+    violations
+}
+
+/// Checks the folders below `node`, whose path from the scan root is `path`.
+fn check_children(
+    node: &FileSystemNode,
+    patterns: &[(Pattern, &Budget)],
+    metric: SizeMetric,
+    path: &mut Vec<String>,
+    violations: &mut Vec<Violation>,
+) {
+    for child in node.children.iter().filter(|c| !c.children.is_empty()) {
+        path.push(child.name.clone());
+        let full_path = path.join("/");
+        let size = child.size_by(metric);
+        for (pattern, budget) in patterns {
+            if size > budget.limit && (pattern.matches(&child.name) || pattern.matches(&full_path)) {
+                violations.push(Violation { path: path.clone(), size, budget: (*budget).clone() });
+            }
+        }
+        check_children(child, patterns, metric, path, violations);
+        path.pop();
+    }
+}
+
+/// Describes `violation` in one line, e.g. `Downloads: 25.0 GiB of 20.0 GiB`.
+pub fn describe(violation: &Violation) -> String {
+    format!(
+        "{}: {} of {}",
+        violation.path.join("/"),
+        format::format_size(violation.size),
+        format::format_size(violation.budget.limit)
+    )
+}
+
+/// Shows a desktop notification listing the folders over budget.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn notify(violations: &[Violation]) {
+    let mut body = violations.iter().take(3).map(describe).collect::<Vec<_>>().join("\n");
+    if violations.len() > 3 {
+        body.push_str(&format!("\nand {} more", violations.len() - 3));
+    }
+    let summary = format!("Disk Scout: {} folders over budget", violations.len());
+    if let Err(e) = notify_rust::Notification::new().summary(&summary).body(&body).show() {
+        eprintln!("Failed to show notification: {}", e);
+    }
+}
+
+/// Draws the budget settings. Returns whether the budgets were changed.
+pub fn show_settings(ui: &mut egui::Ui, settings: &mut BudgetSettings) -> bool {
+    let mut changed = false;
+    let mut remove = None;
+    for (i, budget) in settings.budgets.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            changed |= ui
+                .add(egui::TextEdit::singleline(&mut budget.pattern).hint_text("Folder or pattern").desired_width(160.0))
+                .changed();
+            ui.label("≤");
+            let mut gib = budget.limit as f64 / GIB;
+            if ui.add(egui::DragValue::new(&mut gib).range(0.0..=f64::MAX).speed(0.1).suffix(" GiB")).changed() {
+                budget.limit = (gib * GIB) as u64;
+                changed = true;
+            }
+            if ui.small_button("Remove").clicked() {
+                remove = Some(i);
+            }
+        });
+    }
+    if let Some(i) = remove {
+        settings.budgets.remove(i);
+        changed = true;
+    }
+    if ui.button("Add budget").clicked() {
+        settings.budgets.push(Budget { pattern: String::new(), limit: 20 << 30 });
+        changed = true;
+    }
+    if cfg!(not(target_arch = "wasm32")) {
+        ui.checkbox(
+            &mut settings.notifications_enabled,
+            "Notify when a scan that finished in the background exceeds a budget",
+        );
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folder(name: &str, children: Vec<FileSystemNode>) -> FileSystemNode {
+        let size = children.iter().map(|c| c.size).sum();
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    fn file(name: &str, size: u64) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children: vec![], special: None }
+    }
+
+    #[test]
+    fn test_folders_over_budget_are_found() {
+        let tree = folder(
+            "home",
+            vec![
+                folder("Downloads", vec![file("movie.mkv", 30)]),
+                folder("app", vec![folder("logs", vec![file("a.log", 8)])]),
+                folder("web", vec![folder("logs", vec![file("b.log", 2)])]),
+                // Files are never over budget, even if their name matches.
+                file("logs", 100),
+            ],
+        );
+        let budgets = vec![
+            Budget { pattern: "Downloads".to_string(), limit: 20 },
+            Budget { pattern: "logs".to_string(), limit: 5 },
+            Budget { pattern: "[".to_string(), limit: 0 },
+        ];
+        let violations = check(&tree, &budgets, SizeMetric::Apparent);
+        let paths: Vec<String> = violations.iter().map(|v| v.path.join("/")).collect();
+        assert_eq!(paths, ["Downloads", "app/logs"]);
+        assert_eq!(violations[1].size, 8);
+        assert_eq!(violations[1].budget, budgets[1]);
+
+        // Patterns also match paths from the scan root.
+        let budgets = vec![Budget { pattern: "web/*".to_string(), limit: 1 }];
+        let violations = check(&tree, &budgets, SizeMetric::Apparent);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(describe(&violations[0]), "web/logs: 2 B of 1 B");
+    }
+}
@@ -0,0 +1,237 @@
+//! This module finds what changed when a folder is scanned again, so the user sees at once
+//! what just grew. The rectangles that changed get a badge with their difference, e.g.
+//! `+1.2 GiB`, which fades after a few seconds, and the biggest changes are listed in a
+//! toast in the corner of the treemap until it is closed or the next scan starts.
+//!
+//! A folder only counts as a change of its own if none of its items accounts for most of its
+//! difference; otherwise the change is that item's, and the folder's badge would only repeat it.
+
+use crate::format::format_size;
+use crate::render;
+use crate::scanner::{self, FileSystemNode, SizeMetric};
+use crate::treemap::TreemapNode;
+use eframe::egui;
+
+/// How long the badges show, in seconds, including their fade.
+const BADGE_SECONDS: f64 = 12.0;
+/// How long the badges take to fade out at the end, in seconds.
+const FADE_SECONDS: f64 = 3.0;
+/// The most changes kept, and so badged.
+const MAX_CHANGES: usize = 50;
+/// The changes listed in the toast.
+const TOAST_ROWS: usize = 5;
+/// Changes smaller than this, or than a hundredth of the scan, are left out.
+const MIN_CHANGE: u64 = 1024 * 1024;
+/// The smallest rectangle, in points, a badge is drawn on.
+const MIN_BADGE_RECT: egui::Vec2 = egui::vec2(64.0, 20.0);
+
+/// A file or folder whose size differs between two scans.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// The path from the scan root.
+    pub path: Vec<String>,
+    /// The size in the earlier scan, 0 if it wasn't there.
+    pub before: u64,
+    /// The size now, 0 if it is gone.
+    pub after: u64,
+}
+
+impl Change {
+    /// The difference in size, with its sign, e.g. `+1.5 GiB`.
+    pub fn difference(&self) -> String {
+        signed_difference(self.before, self.after)
+    }
+
+    fn magnitude(&self) -> u64 {
+        self.before.abs_diff(self.after)
+    }
+}
+
+/// Formats the difference from `before` to `after` with its sign, e.g. `+1.5 GiB`.
+pub fn signed_difference(before: u64, after: u64) -> String {
+    if after >= before { format!("+{}", format_size(after - before)) } else { format!("-{}", format_size(before - after)) }
+}
+
+/// The changes found after a rescan, and when their badges were first shown.
+pub struct RecentChanges {
+    /// The change of the whole scan.
+    pub total: Change,
+    /// The notable changes, the biggest first.
+    pub changes: Vec<Change>,
+    /// The time, as egui counts it, the badges were first drawn.
+    pub shown_at: Option<f64>,
+}
+
+impl RecentChanges {
+    /// How opaque the badges are at `time`, or `None` once they are gone.
+    pub fn badge_opacity(&mut self, time: f64) -> Option<f32> {
+        let elapsed = time - *self.shown_at.get_or_insert(time);
+        (elapsed < BADGE_SECONDS).then(|| ((BADGE_SECONDS - elapsed) / FADE_SECONDS).min(1.0) as f32)
+    }
+}
+
+/// Compares `after` with the earlier scan `before` of the same folder, measuring by `metric`.
+pub fn diff(before: &FileSystemNode, after: &FileSystemNode, metric: SizeMetric) -> RecentChanges {
+    let total = Change { path: Vec::new(), before: before.size_by(metric), after: after.size_by(metric) };
+    let min_change = MIN_CHANGE.min(total.before.max(total.after) / 100).max(1);
+    let mut changes = Vec::new();
+    for (before, after) in pairs(Some(before), Some(after)) {
+        collect(before, after, &mut Vec::new(), metric, min_change, &mut changes);
+    }
+    changes.sort_by(|a, b| b.magnitude().cmp(&a.magnitude()).then_with(|| a.path.cmp(&b.path)));
+    changes.truncate(MAX_CHANGES);
+    RecentChanges { total, changes, shown_at: None }
+}
+
+/// The children of `before` and `after` matched by name, including those only one of them has.
+fn pairs<'a>(before: Option<&'a FileSystemNode>, after: Option<&'a FileSystemNode>) -> Vec<(Option<&'a FileSystemNode>, Option<&'a FileSystemNode>)> {
+    let earlier = before.map_or(&[][..], |n| n.children.as_slice());
+    let later = after.map_or(&[][..], |n| n.children.as_slice());
+    scanner::pair_by_name(later, earlier).into_iter().map(|(a, b)| (b, a)).collect()
+}
+
+/// Adds the notable changes at and below the node in `before` and `after`, whose parent is at
+/// `prefix`, to `changes`.
+fn collect(
+    before: Option<&FileSystemNode>,
+    after: Option<&FileSystemNode>,
+    prefix: &mut Vec<String>,
+    metric: SizeMetric,
+    min_change: u64,
+    changes: &mut Vec<Change>,
+) {
+    let Some(name) = after.or(before).map(|n| n.name.clone()) else {
+        return;
+    };
+    prefix.push(name);
+    let change = Change {
+        path: prefix.clone(),
+        before: before.map_or(0, |n| n.size_by(metric)),
+        after: after.map_or(0, |n| n.size_by(metric)),
+    };
+    if change.magnitude() >= min_change {
+        let grew = change.after > change.before;
+        let mut dominated = false;
+        for (b, a) in pairs(before, after) {
+            let (b_size, a_size) = (b.map_or(0, |n| n.size_by(metric)), a.map_or(0, |n| n.size_by(metric)));
+            if a_size != b_size && (a_size > b_size) == grew && a_size.abs_diff(b_size) * 2 >= change.magnitude() {
+                dominated = true;
+            }
+            collect(b, a, prefix, metric, min_change, changes);
+        }
+        if !dominated {
+            changes.push(change);
+        }
+    }
+    prefix.pop();
+}
+
+/// Draws a badge with the difference at the top-right of each rectangle in `badged`, pairs of
+/// an index into `layout` and its change, that is large enough to hold one.
+pub fn show_badges(
+    painter: &egui::Painter,
+    layout: &[TreemapNode],
+    badged: &[(usize, &Change)],
+    to_screen: &dyn Fn(egui::Rect) -> egui::Rect,
+    opacity: f32,
+) {
+    let font = egui::FontId::proportional(11.0);
+    for &(index, change) in badged {
+        let rect = to_screen(render::to_egui(layout[index].rect));
+        if rect.width() < MIN_BADGE_RECT.x || rect.height() < MIN_BADGE_RECT.y {
+            continue;
+        }
+        let color = if change.after >= change.before { egui::Color32::from_rgb(200, 60, 50) } else { egui::Color32::from_rgb(40, 140, 70) };
+        let galley = painter.layout_no_wrap(change.difference(), font.clone(), egui::Color32::WHITE.gamma_multiply(opacity));
+        let size = galley.size() + egui::vec2(8.0, 2.0);
+        let badge = egui::Rect::from_min_size(egui::pos2(rect.max.x - size.x - 3.0, rect.min.y + 3.0), size);
+        painter.rect_filled(badge, size.y / 2.0, color.gamma_multiply(opacity));
+        painter.galley(badge.min + egui::vec2(4.0, 1.0), galley, egui::Color32::WHITE);
+    }
+}
+
+/// What the user did in the toast.
+pub enum ToastAction {
+    /// Show the change at this path from the scan root.
+    Show(Vec<String>),
+    Close,
+}
+
+/// Draws the toast listing the biggest changes at the bottom-right of the treemap `area`.
+pub fn show_toast(ui: &egui::Ui, area: egui::Rect, recent: &RecentChanges) -> Option<ToastAction> {
+    let mut action = None;
+    egui::Area::new(ui.id().with("recent_changes"))
+        .order(egui::Order::Foreground)
+        .pivot(egui::Align2::RIGHT_BOTTOM)
+        .fixed_pos(area.max - egui::vec2(12.0, 12.0))
+        .show(ui.ctx(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong(format!("Since the last scan: {}", recent.total.difference()));
+                    if ui.small_button("✖").on_hover_text("Close").clicked() {
+                        action = Some(ToastAction::Close);
+                    }
+                });
+                if recent.changes.is_empty() {
+                    ui.label("Nothing changed noticeably.");
+                }
+                egui::Grid::new("recent_changes_grid").show(ui, |ui| {
+                    for change in recent.changes.iter().take(TOAST_ROWS) {
+                        let name = change.path.join("/");
+                        if change.after == 0 {
+                            ui.label(format!("{} (removed)", name));
+                        } else if ui.link(&name).on_hover_text("Show it in the treemap").clicked() {
+                            action = Some(ToastAction::Show(change.path.clone()));
+                        }
+                        ui.label(change.difference());
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+    action
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn node(name: &str, size: u64, children: Vec<FileSystemNode>) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    #[test]
+    fn test_diff_finds_notable_changes() {
+        let before = node(
+            "root",
+            300 * MIB,
+            vec![
+                node("cache", 100 * MIB, vec![node("a", 60 * MIB, vec![]), node("b", 40 * MIB, vec![])]),
+                node("old.iso", 200 * MIB, vec![]),
+            ],
+        );
+        let after = node(
+            "root",
+            310 * MIB,
+            vec![
+                node("cache", 110 * MIB, vec![node("a", 35 * MIB, vec![]), node("b", 40 * MIB, vec![]), node("c", 35 * MIB, vec![])]),
+                node("new.iso", 200 * MIB, vec![]),
+            ],
+        );
+        let recent = diff(&before, &after, SizeMetric::Apparent);
+        assert_eq!(recent.total.difference(), "+10.0 MiB");
+        let found: Vec<(String, String)> = recent.changes.iter().map(|c| (c.path.join("/"), c.difference())).collect();
+        // The cache grew because of c, so only c is listed for it.
+        assert_eq!(
+            found,
+            [
+                ("new.iso".to_string(), "+200.0 MiB".to_string()),
+                ("old.iso".to_string(), "-200.0 MiB".to_string()),
+                ("cache/c".to_string(), "+35.0 MiB".to_string()),
+                ("cache/a".to_string(), "-25.0 MiB".to_string()),
+            ]
+        );
+    }
+}
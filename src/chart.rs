@@ -0,0 +1,217 @@
+//! This module draws a donut chart of the current directory's immediate children.
+//! It is a simpler alternative to the treemap: the largest children get their own
+//! slice and the rest are grouped into a single "Other" slice.
+
+use crate::basis::Basis;
+use crate::format;
+use crate::interaction::{self, PointerAction};
+use crate::scanner::{FileSystemNode, SizeMetric};
+use eframe::egui;
+use std::f32::consts::TAU;
+
+/// The number of children that get their own slice before the rest are grouped as "Other".
+pub const MAX_SLICES: usize = 10;
+
+/// One slice of the donut chart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slice {
+    /// The label shown for the slice.
+    pub name: String,
+    /// The path of the child relative to the chart's root, or `None` for the "Other" slice.
+    pub path: Option<Vec<String>>,
+    /// The size represented by the slice, in bytes.
+    pub size: u64,
+    /// The angle the slice starts at, in radians clockwise from the top.
+    pub start_angle: f32,
+    /// The angle the slice covers, in radians.
+    pub sweep: f32,
+}
+
+/// Splits the children of `node` into slices: the `max_slices` largest, plus "Other" for the rest.
+/// The slices are sized by `metric`.
+pub fn donut_slices(node: &FileSystemNode, max_slices: usize, metric: SizeMetric) -> Vec<Slice> {
+    let total = node.children.iter().map(|c| c.size_by(metric)).sum::<u64>();
+    if total == 0 {
+        return Vec::new();
+    }
+
+    let mut children: Vec<&FileSystemNode> = node.children.iter().filter(|c| c.size_by(metric) > 0).collect();
+    children.sort_by(|a, b| b.size_by(metric).cmp(&a.size_by(metric)).then_with(|| a.name.cmp(&b.name)));
+
+    let mut entries: Vec<(String, Option<Vec<String>>, u64)> = children
+        .iter()
+        .take(max_slices)
+        .map(|c| (c.name.clone(), Some(vec![c.name.clone()]), c.size_by(metric)))
+        .collect();
+    if children.len() > max_slices {
+        let rest = children[max_slices..].iter().map(|c| c.size_by(metric)).sum();
+        let label = format!("Other ({} items)", children.len() - max_slices);
+        entries.push((label, None, rest));
+    }
+
+    let mut angle = 0.0;
+    entries
+        .into_iter()
+        .map(|(name, path, size)| {
+            let sweep = size as f32 / total as f32 * TAU;
+            let slice = Slice { name, path, size, start_angle: angle, sweep };
+            angle += sweep;
+            slice
+        })
+        .collect()
+}
+
+/// Returns the index of the slice under `pos`, if it falls within the ring.
+fn slice_at(slices: &[Slice], center: egui::Pos2, inner: f32, outer: f32, pos: egui::Pos2) -> Option<usize> {
+    let offset = pos - center;
+    let distance = offset.length();
+    if distance < inner || distance > outer {
+        return None;
+    }
+    // Angles are measured clockwise from 12 o'clock.
+    let angle = offset.x.atan2(-offset.y).rem_euclid(TAU);
+    slices
+        .iter()
+        .position(|s| angle >= s.start_angle && angle < s.start_angle + s.sweep)
+}
+
+/// Returns a distinct color for the slice at `index`, stepping around the color wheel.
+fn color_for_slice(index: usize) -> egui::Color32 {
+    // The golden angle keeps neighbouring slices far apart in hue.
+    let hue = (index as f32 * 0.381_966).fract();
+    egui::ecolor::Hsva::new(hue, 0.55, 0.75, 1.0).into()
+}
+
+/// Returns the point at `radius` from `center` at `angle` radians clockwise from the top.
+fn point_at(center: egui::Pos2, radius: f32, angle: f32) -> egui::Pos2 {
+    center + egui::vec2(angle.sin(), -angle.cos()) * radius
+}
+
+/// Draws the donut chart for `node` into `area`.
+///
+/// `selected` is the name of the selected child, which is marked next to its slice. Slices
+/// respond to the pointer like the nodes of the treemap, see `interaction::pointer_action`.
+/// The legend gives the slices' shares of `basis`.
+pub fn show_donut(
+    ui: &mut egui::Ui,
+    area: egui::Rect,
+    node: &FileSystemNode,
+    selected: Option<&str>,
+    metric: SizeMetric,
+    basis: &Basis,
+) -> Option<PointerAction> {
+    let slices = donut_slices(node, MAX_SLICES, metric);
+    if slices.is_empty() {
+        ui.centered_and_justified(|ui| {
+            ui.label("This folder is empty.");
+        });
+        return None;
+    }
+
+    // Leave room on the right for the legend.
+    let legend_width = (area.width() * 0.35).min(320.0);
+    let chart_area = egui::Rect::from_min_max(area.min, egui::pos2(area.max.x - legend_width, area.max.y));
+    let center = chart_area.center();
+    let outer = chart_area.width().min(chart_area.height()) * 0.45;
+    let inner = outer * 0.55;
+
+    let response = ui.interact(chart_area, ui.id().with("donut"), egui::Sense::click());
+    let hovered = response
+        .hover_pos()
+        .and_then(|pos| slice_at(&slices, center, inner, outer, pos));
+
+    let painter = ui.painter();
+    for (i, slice) in slices.iter().enumerate() {
+        let grow = if hovered == Some(i) { outer * 0.04 } else { 0.0 };
+        let color = color_for_slice(i);
+        // Draw the slice as a strip of small convex quads, about two degrees each.
+        let steps = ((slice.sweep / TAU * 180.0).ceil() as usize).max(1);
+        for step in 0..steps {
+            let a0 = slice.start_angle + slice.sweep * step as f32 / steps as f32;
+            let a1 = slice.start_angle + slice.sweep * (step + 1) as f32 / steps as f32;
+            let quad = vec![
+                point_at(center, inner, a0),
+                point_at(center, outer + grow, a0),
+                point_at(center, outer + grow, a1),
+                point_at(center, inner, a1),
+            ];
+            painter.add(egui::Shape::convex_polygon(quad, color, egui::Stroke::NONE));
+        }
+        // Separate the slices with a thin line.
+        painter.line_segment(
+            [point_at(center, inner, slice.start_angle), point_at(center, outer + grow, slice.start_angle)],
+            egui::Stroke::new(1.0, egui::Color32::from_gray(150)),
+        );
+        if selected.is_some() && slice.path.as_ref().and_then(|p| p.last()).map(String::as_str) == selected {
+            let mid = slice.start_angle + slice.sweep / 2.0;
+            painter.circle_filled(point_at(center, outer + grow + 8.0, mid), 4.0, egui::Color32::YELLOW);
+        }
+    }
+    painter.text(
+        center,
+        egui::Align2::CENTER_CENTER,
+        format!("{}\n{}", node.name, format::format_size(node.size_by(metric))),
+        egui::FontId::proportional(14.0),
+        ui.visuals().text_color(),
+    );
+
+    // The legend lists every slice with its share of the basis.
+    let legend = egui::Rect::from_min_max(egui::pos2(chart_area.max.x, area.min.y), area.max);
+    let mut legend_ui = ui.new_child(egui::UiBuilder::new().max_rect(legend.shrink(8.0)));
+    legend_ui.add_space(chart_area.height() * 0.1);
+    for (i, slice) in slices.iter().enumerate() {
+        legend_ui.horizontal(|ui| {
+            let (swatch, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+            ui.painter().rect_filled(swatch, 2.0, color_for_slice(i));
+            let text = egui::RichText::new(format!("{} — {:.1}% ({})", slice.name, basis.percent(slice.size), format::format_size(slice.size)));
+            ui.label(if hovered == Some(i) { text.strong() } else { text });
+        });
+    }
+
+    let slice = hovered.map(|i| &slices[i]);
+    if let Some(slice) = slice {
+        response.clone().on_hover_text(format!("Name: {}\nSize: {} ({})", slice.name, format::format_size(slice.size), format::format_exact(slice.size)));
+    }
+
+    let path = slice.and_then(|s| s.path.clone());
+    interaction::pointer_action(ui, &response, path.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(name: &str, size: u64) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children: vec![], special: None }
+    }
+
+    #[test]
+    fn test_donut_slices_group_the_rest_as_other() {
+        let node = FileSystemNode {
+            name: "root".to_string(),
+            size: 100,
+            allocated: 100,
+            children: vec![leaf("a", 10), leaf("b", 50), leaf("c", 25), leaf("d", 15), leaf("empty", 0)],
+            special: None,
+        };
+
+        let slices = donut_slices(&node, 2, SizeMetric::Apparent);
+        let names: Vec<_> = slices.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["b", "c", "Other (2 items)"]);
+        assert_eq!(slices[2].size, 25);
+        assert_eq!(slices[2].path, None);
+        assert_eq!(slices[0].path, Some(vec!["b".to_string()]));
+
+        // The slices cover the full circle, back to back.
+        assert!((slices[0].sweep - TAU / 2.0).abs() < 1e-5);
+        assert!((slices[1].start_angle - TAU / 2.0).abs() < 1e-5);
+        let end = slices.last().map(|s| s.start_angle + s.sweep).unwrap();
+        assert!((end - TAU).abs() < 1e-5);
+
+        let center = egui::pos2(0.0, 0.0);
+        // Straight up is the start of the first slice; straight down is the start of the second.
+        assert_eq!(slice_at(&slices, center, 5.0, 10.0, egui::pos2(0.1, -8.0)), Some(0));
+        assert_eq!(slice_at(&slices, center, 5.0, 10.0, egui::pos2(-0.1, 8.0)), Some(1));
+        assert_eq!(slice_at(&slices, center, 5.0, 10.0, egui::pos2(0.0, 1.0)), None);
+    }
+}
@@ -0,0 +1,180 @@
+//! This module defines the command-line interface.
+//! A path given on the command line is pre-filled and scanned as soon as the window opens,
+//! unless `--headless` scans it without one, and the scan flags map directly onto `ScanOptions`.
+
+use crate::headless::HeadlessOptions;
+use crate::import::LineFormat;
+use crate::scanner::{DEFAULT_MAX_NESTING, ScanOptions};
+use clap::Parser;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// A visual disk space analyzer.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// The directory to scan on startup. A file is shown selected within its folder.
+    /// If omitted, the current directory is pre-filled but not scanned.
+    pub path: Option<PathBuf>,
+
+    /// Only keep this many levels below the root in the tree. Deeper contents still
+    /// count towards the sizes of their ancestors.
+    #[arg(long, value_name = "LEVELS")]
+    pub depth: Option<usize>,
+
+    /// Skip entries matching this glob pattern (matched against names and full paths).
+    /// Can be given multiple times.
+    #[arg(long, value_name = "PATTERN")]
+    pub exclude: Vec<String>,
+
+    /// Don't cross into other filesystems (mount points) below the root.
+    #[arg(long, short = 'x')]
+    pub one_file_system: bool,
+
+    /// Scan pseudo-filesystems such as /proc, /sys, /dev, and /run, which are skipped by default.
+    #[arg(long)]
+    pub include_virtual: bool,
+
+    /// Scan the pseudo-filesystem mounted here even though the others are skipped.
+    /// Can be given multiple times.
+    #[arg(long, value_name = "PATH")]
+    pub include_mount: Vec<PathBuf>,
+
+    /// List folders at most this many levels below the root. Deeper ones are left out and
+    /// reported, as they are usually a folder copied into itself over and over.
+    #[arg(long, value_name = "LEVELS", default_value_t = DEFAULT_MAX_NESTING)]
+    pub max_nesting: usize,
+
+    /// Count the sizes that sockets, FIFOs, and devices report. They hold no data, so they
+    /// count as empty by default.
+    #[arg(long)]
+    pub count_special_files: bool,
+
+    /// Count symbolic links as small files instead of following them to what they point to.
+    #[arg(long)]
+    pub no_follow_symlinks: bool,
+
+    /// Run as an HTTP service on this address (e.g. 127.0.0.1:8080) instead of opening
+    /// a window. The scan flags apply to every scan it runs.
+    #[arg(long, value_name = "ADDR", conflicts_with = "path")]
+    pub serve: Option<String>,
+
+    /// Disable every action that changes the disk or the system, such as moving to the
+    /// trash or running hooks, for the whole session.
+    #[arg(long)]
+    pub read_only: bool,
+
+    /// Show the output of `du -ab` piped in on stdin (a size in bytes, a tab, and a path per
+    /// line) instead of scanning, e.g. `ssh server du -ab /srv | disk-scout --stdin-du`.
+    #[arg(long, conflicts_with_all = ["path", "serve"])]
+    pub stdin_du: bool,
+
+    /// Show a listing piped in on stdin instead of scanning, with a path, a tab, and a size in
+    /// bytes per line.
+    #[arg(long, conflicts_with_all = ["path", "serve", "stdin_du"])]
+    pub stdin_tsv: bool,
+
+    /// Show a generated tree instead of scanning, to try out or demonstrate the app. Actions
+    /// that change the disk are disabled.
+    #[arg(long, hide = true, conflicts_with_all = ["path", "serve", "stdin_du", "stdin_tsv"])]
+    pub demo: bool,
+
+    /// Scan the path and write the largest items to stdout instead of opening a window. The
+    /// exit code tells how it went: 0 finished, 1 failed, 2 finished but some entries couldn't
+    /// be read, 3 the path doesn't exist, 4 cancelled by Ctrl+C, SIGTERM, or --timeout.
+    #[arg(long, requires = "path", conflicts_with_all = ["stdin_du", "stdin_tsv", "demo"])]
+    pub headless: bool,
+
+    /// With --headless, write nothing to stdout; problems still go to stderr.
+    #[arg(long, short = 'q', requires = "headless", conflicts_with = "porcelain")]
+    pub quiet: bool,
+
+    /// With --headless, write tab-separated lines in a format that stays the same between
+    /// versions and locales, for scripts.
+    #[arg(long, requires = "headless")]
+    pub porcelain: bool,
+
+    /// With --headless, how many of the largest items at the top of the scan to list.
+    #[arg(long, value_name = "COUNT", default_value_t = 10, requires = "headless")]
+    pub top: usize,
+
+    /// With --headless, cancel the scan if it takes longer than this many seconds.
+    #[arg(long, value_name = "SECONDS", requires = "headless")]
+    pub timeout: Option<u64>,
+}
+
+impl Cli {
+    /// The format of the listing to read from stdin, if one was asked for.
+    pub fn stdin_format(&self) -> Option<LineFormat> {
+        if self.stdin_du {
+            Some(LineFormat::Du)
+        } else if self.stdin_tsv {
+            Some(LineFormat::PathSize)
+        } else {
+            None
+        }
+    }
+
+    /// What a headless scan writes, and how long it may take, as the flags say.
+    pub fn headless_options(&self) -> HeadlessOptions {
+        HeadlessOptions {
+            quiet: self.quiet,
+            porcelain: self.porcelain,
+            top: self.top,
+            timeout: self.timeout.map(Duration::from_secs),
+        }
+    }
+
+    /// The scan options selected by the flags.
+    pub fn scan_options(&self) -> ScanOptions {
+        ScanOptions {
+            max_depth: self.depth,
+            exclude: self.exclude.clone(),
+            one_file_system: self.one_file_system,
+            include_virtual: self.include_virtual,
+            include_mounts: self.include_mount.clone(),
+            max_nesting: self.max_nesting,
+            count_special: self.count_special_files,
+            follow_symlinks: !self.no_follow_symlinks,
+            ..ScanOptions::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_maps_flags_onto_scan_options() {
+        let cli = Cli::parse_from([
+            "disk-scout",
+            "/data",
+            "--depth",
+            "3",
+            "--exclude",
+            "*.iso",
+            "--exclude",
+            "node_modules",
+            "-x",
+            "--include-mount",
+            "/dev",
+        ]);
+        assert_eq!(cli.path, Some(PathBuf::from("/data")));
+        assert_eq!(
+            cli.scan_options(),
+            ScanOptions {
+                max_depth: Some(3),
+                exclude: vec!["*.iso".to_string(), "node_modules".to_string()],
+                one_file_system: true,
+                include_virtual: false,
+                include_mounts: vec![PathBuf::from("/dev")],
+                max_nesting: DEFAULT_MAX_NESTING,
+                count_special: false,
+                follow_symlinks: true,
+                hash_files: false,
+                metric: Default::default(),
+            }
+        );
+    }
+}
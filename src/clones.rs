@@ -0,0 +1,124 @@
+//! This module recognizes cloned files on APFS, which macOS makes when a file is copied in
+//! the Finder or with `cp -c`: the copy shares the original's blocks until either of them is
+//! changed. Each file still reports the blocks it uses as allocated, so adding them up would
+//! count the shared blocks once per clone and overstate how much space deleting them frees.
+//!
+//! APFS tells which files may share blocks, the clone ID they were cloned with, and how much
+//! of each file only it holds. The first file of a clone ID is counted in full, and the others
+//! only with the blocks they don't share. The files are kept in the scan statistics, so their
+//! tooltips can say that deleting them frees less than they take.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// A file whose blocks are shared with clones of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClonedFile {
+    pub path: PathBuf,
+    /// The allocated bytes shared with other files, which deleting this one doesn't free.
+    pub shared: u64,
+    /// Whether the shared bytes were counted with another clone rather than this file.
+    pub counted_elsewhere: bool,
+}
+
+/// The clones seen so far in a scan.
+#[derive(Debug, Default)]
+pub struct Clones {
+    seen: HashSet<u64>,
+}
+
+impl Clones {
+    /// Returns the bytes to count for the file at `path`, which has `allocated` bytes, and the
+    /// clone it is, if it is one.
+    pub fn check(&mut self, path: &Path, allocated: u64) -> (u64, Option<ClonedFile>) {
+        match clone_info(path) {
+            Some((id, private)) => self.account(path, id, private, allocated),
+            None => (allocated, None),
+        }
+    }
+
+    /// Accounts for the file at `path` of clone ID `id`, holding `private` of its `allocated`
+    /// bytes on its own.
+    fn account(&mut self, path: &Path, id: u64, private: u64, allocated: u64) -> (u64, Option<ClonedFile>) {
+        let private = private.min(allocated);
+        let counted_elsewhere = !self.seen.insert(id);
+        let cloned = ClonedFile { path: path.to_path_buf(), shared: allocated - private, counted_elsewhere };
+        (if counted_elsewhere { private } else { allocated }, Some(cloned))
+    }
+}
+
+/// Returns the clone ID of the file at `path` and the bytes only it holds, if it may share
+/// blocks with other files.
+#[cfg(target_os = "macos")]
+fn clone_info(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::ffi::OsStrExt;
+    /// The extended flag of files that may share blocks with others, from `sys/attr.h`.
+    const EF_MAY_SHARE_BLOCKS: u64 = 0x1;
+    const WANTED: libc::attrgroup_t = libc::ATTR_CMNEXT_PRIVATESIZE | libc::ATTR_CMNEXT_CLONEID | libc::ATTR_CMNEXT_EXT_FLAGS;
+    /// The attributes as `getattrlist` returns them, in the order of their bits.
+    #[repr(C, packed(4))]
+    struct Attributes {
+        length: u32,
+        returned: libc::attribute_set_t,
+        private_size: libc::off_t,
+        clone_id: u64,
+        ext_flags: u64,
+    }
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut request = libc::attrlist {
+        bitmapcount: libc::ATTR_BIT_MAP_COUNT,
+        reserved: 0,
+        commonattr: libc::ATTR_CMN_RETURNED_ATTRS,
+        volattr: 0,
+        dirattr: 0,
+        fileattr: 0,
+        // With FSOPT_ATTR_CMN_EXTENDED, the fork attributes ask for the extended common ones.
+        forkattr: WANTED,
+    };
+    let mut attributes = std::mem::MaybeUninit::<Attributes>::zeroed();
+    // SAFETY: the path is NUL-terminated, and the buffer is as large as the size passed.
+    let result = unsafe {
+        libc::getattrlist(
+            path.as_ptr(),
+            (&raw mut request).cast(),
+            attributes.as_mut_ptr().cast(),
+            size_of::<Attributes>(),
+            libc::FSOPT_NOFOLLOW | libc::FSOPT_ATTR_CMN_EXTENDED,
+        )
+    };
+    if result != 0 {
+        return None;
+    }
+    // SAFETY: the buffer started out zeroed, which is valid for every field.
+    let attributes = unsafe { attributes.assume_init() };
+    // Other filesystems don't return these attributes at all.
+    let returned = attributes.returned.forkattr;
+    let (ext_flags, clone_id, private_size) = (attributes.ext_flags, attributes.clone_id, attributes.private_size);
+    (returned & WANTED == WANTED && ext_flags & EF_MAY_SHARE_BLOCKS != 0).then(|| (clone_id, private_size.max(0) as u64))
+}
+
+/// Returns the clone ID of the file at `path` and the bytes only it holds, if it may share
+/// blocks with other files. Only APFS clones files, so there are none on this platform.
+#[cfg(not(target_os = "macos"))]
+fn clone_info(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_blocks_are_counted_once() {
+        let mut clones = Clones::default();
+        let (counted, first) = clones.account(Path::new("a.mov"), 7, 0, 1000);
+        assert_eq!((counted, first.map(|c| (c.shared, c.counted_elsewhere))), (1000, Some((1000, false))));
+        // A clone changed since holds some blocks of its own, which are all it adds.
+        let (counted, second) = clones.account(Path::new("b.mov"), 7, 200, 1000);
+        assert_eq!((counted, second.map(|c| (c.shared, c.counted_elsewhere))), (200, Some((800, true))));
+        let (counted, _) = clones.account(Path::new("c.mov"), 8, 0, 500);
+        assert_eq!(counted, 500);
+    }
+}
@@ -0,0 +1,139 @@
+//! This module collapses chains of single-child directories.
+//! A directory whose only child is another directory (e.g. `a/b/c/d/...` in a Java
+//! or `node_modules` tree) is merged with that child into a single node named `a/b/c/d`,
+//! which removes levels of the hierarchy that carry no information.
+
+use crate::scanner::FileSystemNode;
+
+/// The separator used to join the names of a collapsed chain.
+/// It can't appear in a file name, so a collapsed name can always be split back.
+const CHAIN_SEPARATOR: char = '/';
+
+/// Returns a copy of `tree` with every chain of single-child directories below the root
+/// merged into one node. The root itself is kept as it is.
+pub fn collapse_chains(tree: &FileSystemNode) -> FileSystemNode {
+    FileSystemNode {
+        name: tree.name.clone(),
+        size: tree.size,
+        allocated: tree.allocated,
+        children: tree.children.iter().map(collapse_node).collect(),
+        special: tree.special,
+    }
+}
+
+/// Collapses the chain starting at `node`, then recurses into the remaining children.
+fn collapse_node(node: &FileSystemNode) -> FileSystemNode {
+    let mut name = node.name.clone();
+    let mut current = node;
+    while let [only_child] = current.children.as_slice() {
+        if only_child.children.is_empty() {
+            break;
+        }
+        name.push(CHAIN_SEPARATOR);
+        name.push_str(&only_child.name);
+        current = only_child;
+    }
+
+    FileSystemNode {
+        name,
+        size: current.size,
+        allocated: current.allocated,
+        children: current.children.iter().map(collapse_node).collect(),
+        special: current.special,
+    }
+}
+
+/// Converts a path through a (possibly) collapsed tree into the equivalent path
+/// through the original tree, by splitting collapsed names back into their parts.
+pub fn to_canonical_path(view_path: &[String]) -> Vec<String> {
+    view_path
+        .iter()
+        .flat_map(|name| name.split(CHAIN_SEPARATOR).map(str::to_string))
+        .collect()
+}
+
+/// Converts a path through the original tree into the equivalent path through `view`,
+/// which may or may not have had its chains collapsed.
+///
+/// If the path leads into the middle of a collapsed chain, the result ends at the
+/// collapsed node containing it. Names that can't be found are dropped along with the
+/// rest of the path.
+pub fn to_view_path(view: &FileSystemNode, canonical_path: &[String]) -> Vec<String> {
+    let mut view_path = Vec::new();
+    let mut current = view;
+    let mut remaining = canonical_path;
+
+    while !remaining.is_empty() {
+        let next = current.children.iter().find_map(|child| {
+            let parts: Vec<&str> = child.name.split(CHAIN_SEPARATOR).collect();
+            let matched = parts
+                .iter()
+                .zip(remaining)
+                .take_while(|(part, name)| *part == name)
+                .count();
+            (matched > 0 && (matched == parts.len() || matched == remaining.len()))
+                .then_some((child, matched))
+        });
+        match next {
+            Some((child, matched)) => {
+                view_path.push(child.name.clone());
+                current = child;
+                remaining = &remaining[matched..];
+            }
+            None => break,
+        }
+    }
+    view_path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, size: u64, children: Vec<FileSystemNode>) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    fn path(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_collapse_chains() {
+        // root/
+        // |- a/b/c/ (x.java, y.java)
+        // |- lonely/file.txt
+        let tree = node(
+            "root",
+            35,
+            vec![
+                node(
+                    "a",
+                    30,
+                    vec![node(
+                        "b",
+                        30,
+                        vec![node("c", 30, vec![node("x.java", 10, vec![]), node("y.java", 20, vec![])])],
+                    )],
+                ),
+                node("lonely", 5, vec![node("file.txt", 5, vec![])]),
+            ],
+        );
+
+        let collapsed = collapse_chains(&tree);
+        let names: Vec<_> = collapsed.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["a/b/c", "lonely"]);
+        assert_eq!(collapsed.children[0].size, 30);
+        assert_eq!(collapsed.children[0].children.len(), 2);
+        // A directory containing a single file is not a chain.
+        assert_eq!(collapsed.children[1].children.len(), 1);
+
+        // Paths translate in both directions.
+        let canonical = path(&["a", "b", "c", "x.java"]);
+        let view_path = to_view_path(&collapsed, &canonical);
+        assert_eq!(view_path, path(&["a/b/c", "x.java"]));
+        assert_eq!(to_canonical_path(&view_path), canonical);
+        assert_eq!(to_view_path(&collapsed, &path(&["a", "b"])), path(&["a/b/c"]));
+        assert_eq!(to_view_path(&tree, &canonical), canonical);
+    }
+}
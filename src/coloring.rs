@@ -0,0 +1,144 @@
+//! This module decides the color of each treemap rectangle. A color strategy maps what is
+//! known about a node to a color, and strategies compose: `ShareEmphasis` takes the hue
+//! from another strategy (such as the depth palette) and encodes the node's share of its
+//! parent in the saturation and brightness, so dominant children stand out. `GitColors`
+//! colors nodes by how git sees them instead, where that is known, and `TagColors` by the
+//! tags users attached to them.
+
+use crate::animation::StartState;
+use crate::git::GitClass;
+use crate::notes::Tag;
+use crate::palette::DepthPalette;
+use crate::treemap::TreemapNode;
+use eframe::egui;
+use eframe::egui::ecolor::Hsva;
+
+/// What a color strategy knows about the node it colors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorInput {
+    /// The depth of the node below the view root, its children being at depth 1.
+    pub depth: usize,
+    /// The node's share of its parent, from 0.0 to 1.0.
+    pub share: f32,
+    /// How git sees the node, if the scan is in a git repository.
+    pub git: Option<GitClass>,
+    /// The tag of the node, if it or a folder containing it is tagged.
+    pub tag: Option<Tag>,
+}
+
+impl From<&TreemapNode> for ColorInput {
+    fn from(node: &TreemapNode) -> Self {
+        Self { depth: node.depth, share: node.share as f32, git: None, tag: None }
+    }
+}
+
+impl From<&StartState> for ColorInput {
+    fn from(start: &StartState) -> Self {
+        Self { depth: start.depth, share: start.share as f32, git: None, tag: None }
+    }
+}
+
+/// A way of coloring nodes.
+pub trait ColorStrategy {
+    /// Returns the color of `node`.
+    fn color_of(&self, node: &ColorInput) -> egui::Color32;
+}
+
+impl<S: ColorStrategy + ?Sized> ColorStrategy for Box<S> {
+    fn color_of(&self, node: &ColorInput) -> egui::Color32 {
+        (**self).color_of(node)
+    }
+}
+
+/// Colors nodes by their depth alone.
+impl ColorStrategy for DepthPalette {
+    fn color_of(&self, node: &ColorInput) -> egui::Color32 {
+        self.color(node.depth)
+    }
+}
+
+/// Keeps the hue of `base` but fades nodes that take up little of their parent, by lowering
+/// their saturation and brightness. `strength` ranges from 0.0 (no effect) to 1.0.
+pub struct ShareEmphasis<S> {
+    /// The strategy the hue comes from.
+    pub base: S,
+    /// How much small shares are faded.
+    pub strength: f32,
+}
+
+impl<S: ColorStrategy> ColorStrategy for ShareEmphasis<S> {
+    fn color_of(&self, node: &ColorInput) -> egui::Color32 {
+        let base = Hsva::from(self.base.color_of(node));
+        // The square root keeps mid-sized shares from fading as much as the small ones.
+        let emphasis = node.share.clamp(0.0, 1.0).sqrt();
+        let factor = 1.0 - self.strength.clamp(0.0, 1.0) * (1.0 - emphasis);
+        Hsva::new(base.h, base.s * factor, base.v * (0.4 + 0.6 * factor), base.a).into()
+    }
+}
+
+/// Colors nodes by how git sees them, falling back to `base` for nodes git knows nothing about.
+pub struct GitColors<S> {
+    /// The strategy for nodes outside of a git repository.
+    pub base: S,
+}
+
+impl<S: ColorStrategy> ColorStrategy for GitColors<S> {
+    fn color_of(&self, node: &ColorInput) -> egui::Color32 {
+        match node.git {
+            Some(class) => class.color(),
+            None => self.base.color_of(node),
+        }
+    }
+}
+
+/// Colors nodes by their tag, falling back to `base` for untagged nodes.
+pub struct TagColors<S> {
+    /// The strategy for untagged nodes.
+    pub base: S,
+}
+
+impl<S: ColorStrategy> ColorStrategy for TagColors<S> {
+    fn color_of(&self, node: &ColorInput) -> egui::Color32 {
+        match node.tag {
+            Some(tag) => tag.color(),
+            None => self.base.color_of(node),
+        }
+    }
+}
+
+/// Returns the strategy the treemap and minimap are colored with, as set up in `palette`,
+/// by git status if `by_git` is set, and by tags if `by_tag` is set.
+pub fn strategy(palette: &DepthPalette, by_git: bool, by_tag: bool) -> Box<dyn ColorStrategy> {
+    let base: Box<dyn ColorStrategy> = if palette.share_emphasis > 0.0 {
+        Box::new(ShareEmphasis { base: palette.clone(), strength: palette.share_emphasis })
+    } else {
+        Box::new(palette.clone())
+    };
+    let base: Box<dyn ColorStrategy> = if by_git { Box::new(GitColors { base }) } else { base };
+    if by_tag { Box::new(TagColors { base }) } else { base }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_emphasis_fades_small_children() {
+        let palette = DepthPalette::default();
+        let large = ColorInput { depth: 2, share: 1.0, git: None, tag: None };
+        let small = ColorInput { depth: 2, share: 0.01, git: None, tag: None };
+
+        // Without emphasis, only the depth matters.
+        let plain = strategy(&palette, false, false);
+        assert_eq!(plain.color_of(&large), plain.color_of(&small));
+
+        let emphasized = strategy(&DepthPalette { share_emphasis: 1.0, ..palette.clone() }, false, false);
+        // A node that fills its parent keeps the depth color, and a small one is faded
+        // but keeps its hue.
+        assert_eq!(emphasized.color_of(&large), palette.color(2));
+        let faded = Hsva::from(emphasized.color_of(&small));
+        let base = Hsva::from(palette.color(2));
+        assert!(faded.s < base.s && faded.v < base.v);
+        assert!((faded.h - base.h).abs() < 0.01);
+    }
+}
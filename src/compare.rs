@@ -0,0 +1,539 @@
+//! This module compares two directories, such as a folder and its backup, to check that a
+//! copy is complete. Both are scanned, and their trees are matched up by path: files and
+//! folders only in the first are missing from the second, those only in the second are
+//! extra, and files in both whose sizes differ are mismatched. A missing or extra folder is
+//! reported once, with what it contains, rather than file by file.
+//!
+//! Files of the same size can optionally be hashed too, to catch those whose content differs
+//! anyway, such as a copy that was corrupted or interrupted and padded. The files are hashed
+//! on several threads, since a backup is usually on another disk than its source.
+//!
+//! Ignore rules keep differences that don't matter out of the report, like rsync's options
+//! do: paths matching glob patterns are left out, sizes within a tolerance count as the
+//! same, and files whose modification time alone differs can be reported or not.
+
+use crate::error::ScanError;
+use crate::format::{format_size, size_label};
+use crate::integrity;
+use crate::scanner::{self, FileSystemNode, ScanOptions};
+use eframe::egui;
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// The most files hashed at once.
+const MAX_HASHING_THREADS: usize = 8;
+
+/// Modification times closer than this count as the same, since FAT only stores them to
+/// two seconds and copies to such drives are rounded.
+const MODIFY_WINDOW: Duration = Duration::from_secs(2);
+
+/// Which differences a comparison leaves out of its report.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IgnoreRules {
+    /// Glob patterns for paths to leave out on both sides, matched against names and
+    /// against paths from the compared directories, e.g. `Thumbs.db` or `cache/*`.
+    pub patterns: Vec<String>,
+    /// Whether files of the same size whose modification time alone differs go unreported,
+    /// as they do when times aren't compared at all.
+    pub ignore_timestamps: bool,
+    /// Files whose sizes differ by at most this many bytes count as the same size.
+    pub size_tolerance: u64,
+}
+
+impl Default for IgnoreRules {
+    fn default() -> Self {
+        Self { patterns: Vec::new(), ignore_timestamps: true, size_tolerance: 0 }
+    }
+}
+
+impl IgnoreRules {
+    /// The valid patterns, compiled. Invalid ones are skipped.
+    fn compiled(&self) -> Vec<Pattern> {
+        self.patterns.iter().filter(|p| !p.trim().is_empty()).filter_map(|p| Pattern::new(p.trim()).ok()).collect()
+    }
+
+    /// Draws the controls to edit the rules.
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        ui.label("Leave out paths matching:");
+        crate::exclusion::show_settings(ui, &mut self.patterns);
+        ui.horizontal(|ui| {
+            ui.label("Count sizes as the same within");
+            ui.add(egui::DragValue::new(&mut self.size_tolerance).speed(1.0).suffix(" bytes"));
+        });
+        ui.checkbox(&mut self.ignore_timestamps, "Ignore changes of the modification time alone")
+            .on_hover_text("Otherwise, files of the same size modified at different times are listed, as rsync would copy them again");
+    }
+}
+
+/// How far hashing the files of a comparison has got, in bytes of both sides. The total is
+/// zero until the directories are scanned.
+#[derive(Debug, Default)]
+pub struct HashProgress {
+    pub hashed: AtomicU64,
+    pub total: AtomicU64,
+}
+
+/// A file or folder found on one side only.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// The path from the compared directory, joined with `/`.
+    pub path: String,
+    /// The size of the file, or of everything in the folder.
+    pub size: u64,
+    /// The number of files, 1 for a file.
+    pub files: u64,
+}
+
+/// A file found on both sides with different sizes, or a file on one side and a folder on
+/// the other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    /// The path from the compared directories, joined with `/`.
+    pub path: String,
+    /// The size on the first side.
+    pub left: u64,
+    /// The size on the second side.
+    pub right: u64,
+}
+
+/// How two directories differ.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompareReport {
+    /// The first directory, e.g. the source of a backup.
+    pub left: PathBuf,
+    /// The second directory, e.g. the backup.
+    pub right: PathBuf,
+    /// The total size of the first directory.
+    pub left_size: u64,
+    /// The total size of the second directory.
+    pub right_size: u64,
+    /// What the first directory has and the second lacks.
+    pub missing: Vec<Entry>,
+    /// What the second directory has and the first lacks.
+    pub extra: Vec<Entry>,
+    /// The files whose sizes differ.
+    pub mismatched: Vec<Mismatch>,
+    /// The files of the same size on both sides whose content differs, if they were hashed.
+    pub content_differs: Vec<Mismatch>,
+    /// The files of the same size on both sides that couldn't be read to hash them.
+    pub unreadable: Vec<String>,
+    /// The files of the same size on both sides whose modification times differ, and whose
+    /// content is the same if it was hashed. Empty if timestamps are ignored.
+    pub retimed: Vec<String>,
+    /// The number of differences the ignore rules left out.
+    pub ignored: u64,
+    /// Whether the files of the same size on both sides were hashed.
+    pub hashed: bool,
+    /// The number of files of the same size on both sides, and of the same content if they
+    /// were hashed.
+    pub matching: u64,
+}
+
+impl CompareReport {
+    /// Whether the second directory has the same files as the first.
+    pub fn is_identical(&self) -> bool {
+        self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.mismatched.is_empty()
+            && self.content_differs.is_empty()
+            && self.unreadable.is_empty()
+            && self.retimed.is_empty()
+    }
+
+    /// The total size of what is missing from the second directory.
+    pub fn missing_size(&self) -> u64 {
+        self.missing.iter().map(|e| e.size).sum()
+    }
+
+    /// The total size of what only the second directory has.
+    pub fn extra_size(&self) -> u64 {
+        self.extra.iter().map(|e| e.size).sum()
+    }
+
+    /// Writes the differences as CSV, one per line: what differs, the path, and the size on
+    /// each side, left empty where there is nothing.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("difference,path,source_bytes,copy_bytes\n");
+        let mut row = |difference: &str, path: &str, left: Option<u64>, right: Option<u64>| {
+            let size = |size: Option<u64>| size.map_or(String::new(), |s| s.to_string());
+            let _ = writeln!(csv, "{},\"{}\",{},{}", difference, path.replace('"', "\"\""), size(left), size(right));
+        };
+        for entry in &self.missing {
+            row("missing", &entry.path, Some(entry.size), None);
+        }
+        for mismatch in &self.mismatched {
+            row("size", &mismatch.path, Some(mismatch.left), Some(mismatch.right));
+        }
+        for mismatch in &self.content_differs {
+            row("content", &mismatch.path, Some(mismatch.left), Some(mismatch.right));
+        }
+        for path in &self.unreadable {
+            row("unreadable", path, None, None);
+        }
+        for path in &self.retimed {
+            row("timestamp", path, None, None);
+        }
+        for entry in &self.extra {
+            row("extra", &entry.path, None, Some(entry.size));
+        }
+        csv
+    }
+}
+
+/// Scans `left` and `right` side by side with `options`, and compares them, leaving out
+/// what `rules` say to. If `progress` is given, the files of the same size on both sides
+/// are hashed too, and it is updated as they are. Setting `cancel` stops the scans, the
+/// comparison, and the hashing, and the result is then [`ScanError::Cancelled`].
+pub fn compare_dirs(
+    left: &Path,
+    right: &Path,
+    options: &ScanOptions,
+    rules: &IgnoreRules,
+    progress: Option<&HashProgress>,
+    cancel: &AtomicBool,
+) -> Result<CompareReport, ScanError> {
+    let scan = |path| scanner::build_tree_with_progress(path, options, &|_| {}, cancel, None).map(|(tree, _)| tree);
+    let (left_tree, right_tree) = thread::scope(|scope| {
+        let left_scan = scope.spawn(|| scan(left));
+        let right_tree = scan(right);
+        (left_scan.join().unwrap_or(Err(ScanError::Cancelled)), right_tree)
+    });
+    let (mut report, same_size) = compare_trees(&left_tree?, &right_tree?, rules, cancel);
+    report.left = left.to_path_buf();
+    report.right = right.to_path_buf();
+    let retimed = if rules.ignore_timestamps { Vec::new() } else { compare_times(&report, &same_size) };
+    if let Some(progress) = progress {
+        compare_contents(&mut report, &same_size, progress, cancel);
+    }
+    if cancel.load(Ordering::Relaxed) {
+        return Err(ScanError::Cancelled);
+    }
+    // Files whose content differs are reported as such, whatever their times.
+    let differing: HashSet<&str> =
+        report.content_differs.iter().map(|m| m.path.as_str()).chain(report.unreadable.iter().map(String::as_str)).collect();
+    let retimed = retimed.into_iter().filter(|path| !differing.contains(path.as_str())).collect();
+    report.retimed = retimed;
+    report.matching -= report.retimed.len() as u64;
+    Ok(report)
+}
+
+/// Compares two scanned trees by size, leaving out what `rules` say to. Returns the
+/// report, whose paths are left empty, and the files of the same size on both sides, with
+/// their sizes. Setting `cancel` stops the comparison, leaving both unfinished.
+pub fn compare_trees(
+    left: &FileSystemNode,
+    right: &FileSystemNode,
+    rules: &IgnoreRules,
+    cancel: &AtomicBool,
+) -> (CompareReport, Vec<(String, u64)>) {
+    let mut report = CompareReport { left_size: left.size, right_size: right.size, ..CompareReport::default() };
+    let mut same_size = Vec::new();
+    let comparison = Comparison { patterns: rules.compiled(), size_tolerance: rules.size_tolerance, cancel };
+    comparison.children(left, right, &mut Vec::new(), &mut report, &mut same_size);
+    report.matching = same_size.len() as u64;
+    (report, same_size)
+}
+
+/// The ignore rules of a comparison, ready to apply.
+struct Comparison<'a> {
+    patterns: Vec<Pattern>,
+    size_tolerance: u64,
+    /// Set from another thread to stop the comparison.
+    cancel: &'a AtomicBool,
+}
+
+impl Comparison<'_> {
+    /// Whether the entry at `path` is left out by a pattern.
+    fn is_ignored(&self, path: &[String]) -> bool {
+        let name = path.last().map_or("", String::as_str);
+        let joined = path.join("/");
+        self.patterns.iter().any(|p| p.matches(name) || p.matches(&joined))
+    }
+
+    fn children(
+        &self,
+        left: &FileSystemNode,
+        right: &FileSystemNode,
+        path: &mut Vec<String>,
+        report: &mut CompareReport,
+        same_size: &mut Vec<(String, u64)>,
+    ) {
+        if self.cancel.load(Ordering::Relaxed) {
+            return;
+        }
+        for (l, r) in scanner::pair_by_name(&left.children, &right.children) {
+            let Some(name) = l.or(r).map(|n| n.name.clone()) else {
+                continue;
+            };
+            path.push(name);
+            if self.is_ignored(path) {
+                report.ignored += 1;
+                path.pop();
+                continue;
+            }
+            match (l, r) {
+                (Some(l), None) => report.missing.push(entry(path, l)),
+                (None, Some(r)) => report.extra.push(entry(path, r)),
+                (Some(l), Some(r)) if l.children.is_empty() != r.children.is_empty() => {
+                    report.mismatched.push(Mismatch { path: path.join("/"), left: l.size, right: r.size });
+                }
+                (Some(l), Some(r)) if l.children.is_empty() => {
+                    if l.size == r.size {
+                        same_size.push((path.join("/"), l.size));
+                    } else if l.size.abs_diff(r.size) <= self.size_tolerance {
+                        report.ignored += 1;
+                    } else {
+                        report.mismatched.push(Mismatch { path: path.join("/"), left: l.size, right: r.size });
+                    }
+                }
+                (Some(l), Some(r)) => self.children(l, r, path, report, same_size),
+                (None, None) => {}
+            }
+            path.pop();
+        }
+    }
+}
+
+/// The files in `same_size` whose modification times differ on the two sides of `report`.
+/// Files whose time can't be read on either side are taken to be unchanged.
+fn compare_times(report: &CompareReport, same_size: &[(String, u64)]) -> Vec<String> {
+    let modified = |root: &Path, path: &str| std::fs::metadata(root.join(path)).and_then(|m| m.modified()).ok();
+    let apart = |a: SystemTime, b: SystemTime| a.duration_since(b).or_else(|_| b.duration_since(a)).unwrap_or_default() >= MODIFY_WINDOW;
+    same_size
+        .iter()
+        .filter(|(path, _)| modified(&report.left, path).zip(modified(&report.right, path)).is_some_and(|(l, r)| apart(l, r)))
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
+/// Hashes the files in `same_size` on both sides of `report` on several threads, and moves
+/// those whose content differs or that can't be read out of the matching ones. Setting
+/// `cancel` stops the threads once they are done with the files they are hashing.
+fn compare_contents(report: &mut CompareReport, same_size: &[(String, u64)], progress: &HashProgress, cancel: &AtomicBool) {
+    progress.total.store(same_size.iter().map(|(_, size)| size * 2).sum(), Ordering::Relaxed);
+    let next = AtomicUsize::new(0);
+    let found = Mutex::new((Vec::new(), Vec::new()));
+    let threads = thread::available_parallelism().map_or(1, |n| n.get()).min(MAX_HASHING_THREADS);
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                while !cancel.load(Ordering::Relaxed)
+                    && let Some((path, size)) = same_size.get(next.fetch_add(1, Ordering::Relaxed))
+                {
+                    let hash = |root: &Path| integrity::hash_file(&root.join(path));
+                    let differs = match (hash(&report.left), hash(&report.right)) {
+                        (Ok(left), Ok(right)) => Some(left != right),
+                        _ => None,
+                    };
+                    progress.hashed.fetch_add(size * 2, Ordering::Relaxed);
+                    let mut found = found.lock().unwrap_or_else(|e| e.into_inner());
+                    match differs {
+                        Some(false) => {}
+                        Some(true) => found.0.push(Mismatch { path: path.clone(), left: *size, right: *size }),
+                        None => found.1.push(path.clone()),
+                    }
+                }
+            });
+        }
+    });
+    let (mut content_differs, mut unreadable) = found.into_inner().unwrap_or_else(|e| e.into_inner());
+    content_differs.sort_by(|a, b| a.path.cmp(&b.path));
+    unreadable.sort();
+    report.matching -= (content_differs.len() + unreadable.len()) as u64;
+    report.content_differs = content_differs;
+    report.unreadable = unreadable;
+    report.hashed = true;
+}
+
+fn entry(path: &[String], node: &FileSystemNode) -> Entry {
+    Entry { path: path.join("/"), size: node.size, files: count_files(node) }
+}
+
+fn count_files(node: &FileSystemNode) -> u64 {
+    if node.children.is_empty() { 1 } else { node.children.iter().map(count_files).sum() }
+}
+
+/// Draws the totals of `report`, then what differs side by side: the path, what the first
+/// directory has there, and what the second has.
+pub fn show_report(ui: &mut egui::Ui, report: &CompareReport) {
+    ui.label(format!(
+        "Source: {}, copy: {}.",
+        format_size(report.left_size),
+        format_size(report.right_size)
+    ));
+    let compared = if report.hashed { "size and content" } else { "size" };
+    if report.ignored > 0 {
+        ui.weak(format!("{} differences left out by the ignore rules.", report.ignored));
+    }
+    if report.is_identical() {
+        ui.colored_label(egui::Color32::LIGHT_GREEN, format!("All {} files match in {}.", report.matching, compared));
+        return;
+    }
+    let mut summary = format!(
+        "{} missing from the copy ({}), {} only in the copy ({}), {} of different size",
+        report.missing.len(),
+        format_size(report.missing_size()),
+        report.extra.len(),
+        format_size(report.extra_size()),
+        report.mismatched.len(),
+    );
+    if report.hashed {
+        summary += &format!(", {} of different content", report.content_differs.len());
+        if !report.unreadable.is_empty() {
+            summary += &format!(", {} unreadable", report.unreadable.len());
+        }
+    }
+    if !report.retimed.is_empty() {
+        summary += &format!(", {} modified at a different time", report.retimed.len());
+    }
+    ui.label(format!("{}; {} matching in {}.", summary, report.matching, compared));
+    let describe = |entry: &Entry| match entry.files {
+        1 => format_size(entry.size),
+        files => format!("{} in {} files", format_size(entry.size), files),
+    };
+    egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+        egui::Grid::new("compare_report").num_columns(3).striped(true).show(ui, |ui| {
+            ui.strong("Path");
+            ui.strong(report.left.display().to_string());
+            ui.strong(report.right.display().to_string());
+            ui.end_row();
+            for entry in &report.missing {
+                ui.colored_label(egui::Color32::LIGHT_RED, &entry.path);
+                ui.label(describe(entry));
+                ui.weak("Missing");
+                ui.end_row();
+            }
+            for mismatch in &report.mismatched {
+                ui.colored_label(egui::Color32::YELLOW, &mismatch.path);
+                size_label(ui, mismatch.left);
+                size_label(ui, mismatch.right);
+                ui.end_row();
+            }
+            for mismatch in &report.content_differs {
+                ui.colored_label(egui::Color32::ORANGE, &mismatch.path).on_hover_text("Same size, different content");
+                size_label(ui, mismatch.left);
+                size_label(ui, mismatch.right);
+                ui.end_row();
+            }
+            for path in &report.unreadable {
+                ui.colored_label(egui::Color32::GRAY, path).on_hover_text("Couldn't be read to compare the content");
+                ui.weak("Unreadable");
+                ui.weak("Unreadable");
+                ui.end_row();
+            }
+            let retimed = if report.hashed { "Same content, modified at a different time" } else { "Same size, modified at a different time" };
+            for path in &report.retimed {
+                ui.colored_label(egui::Color32::LIGHT_BLUE, path).on_hover_text(retimed);
+                ui.weak("Other time");
+                ui.weak("Other time");
+                ui.end_row();
+            }
+            for entry in &report.extra {
+                ui.colored_label(egui::Color32::LIGHT_GREEN, &entry.path);
+                ui.weak("Missing");
+                ui.label(describe(entry));
+                ui.end_row();
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, size: u64, children: Vec<FileSystemNode>) -> FileSystemNode {
+        let size = if children.is_empty() { size } else { children.iter().map(|c| c.size).sum() };
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    #[test]
+    fn test_compare_trees() {
+        let source = node(
+            "photos",
+            0,
+            vec![
+                node("2023", 0, vec![node("a.jpg", 10, vec![]), node("b.jpg", 20, vec![])]),
+                node("2024", 0, vec![node("c.jpg", 30, vec![]), node("d.jpg", 40, vec![])]),
+                node("notes.txt", 5, vec![]),
+            ],
+        );
+        let backup = node(
+            "photos",
+            0,
+            vec![
+                node("2023", 0, vec![node("a.jpg", 10, vec![]), node("b.jpg", 15, vec![])]),
+                node("notes.txt", 5, vec![]),
+                node("Thumbs.db", 2, vec![]),
+            ],
+        );
+        let (report, same_size) = compare_trees(&source, &backup, &IgnoreRules::default(), &AtomicBool::new(false));
+        assert!(!report.is_identical());
+        assert_eq!((report.left_size, report.right_size), (105, 32));
+        // The missing folder is reported as a whole.
+        assert_eq!(report.missing, [Entry { path: "2024".to_string(), size: 70, files: 2 }]);
+        assert_eq!(report.extra, [Entry { path: "Thumbs.db".to_string(), size: 2, files: 1 }]);
+        assert_eq!(report.mismatched, [Mismatch { path: "2023/b.jpg".to_string(), left: 20, right: 15 }]);
+        assert_eq!(report.matching, 2);
+        assert_eq!(same_size, [("2023/a.jpg".to_string(), 10), ("notes.txt".to_string(), 5)]);
+        assert_eq!((report.missing_size(), report.extra_size()), (70, 2));
+        assert!(compare_trees(&source, &source, &IgnoreRules::default(), &AtomicBool::new(false)).0.is_identical());
+
+        // Thumbnails and small size changes don't matter for this backup.
+        let rules = IgnoreRules { patterns: vec!["Thumbs.db".to_string(), "2024".to_string()], size_tolerance: 5, ..IgnoreRules::default() };
+        let (report, _) = compare_trees(&source, &backup, &rules, &AtomicBool::new(false));
+        assert!(report.is_identical());
+        assert_eq!(report.ignored, 3);
+    }
+
+    #[test]
+    fn test_compare_dirs_by_content() {
+        let source = tempfile::tempdir().unwrap();
+        let copy = tempfile::tempdir().unwrap();
+        for (dir, corrupted) in [(&source, "abcd"), (&copy, "abXd")] {
+            std::fs::write(dir.path().join("same.txt"), "hello").unwrap();
+            std::fs::write(dir.path().join("corrupted.txt"), corrupted).unwrap();
+        }
+        std::fs::write(source.path().join("only, here.txt"), "x").unwrap();
+        let options = ScanOptions::default();
+
+        let rules = IgnoreRules::default();
+        let by_size = compare_dirs(source.path(), copy.path(), &options, &rules, None, &AtomicBool::new(false)).unwrap();
+        assert!(!by_size.hashed);
+        assert_eq!((by_size.matching, by_size.content_differs.len()), (2, 0));
+
+        let progress = HashProgress::default();
+        let report = compare_dirs(source.path(), copy.path(), &options, &rules, Some(&progress), &AtomicBool::new(false)).unwrap();
+        assert!(report.hashed);
+        assert_eq!(report.content_differs, [Mismatch { path: "corrupted.txt".to_string(), left: 4, right: 4 }]);
+        assert_eq!(report.matching, 1);
+        assert_eq!(progress.hashed.load(Ordering::Relaxed), 18);
+        assert_eq!(progress.total.load(Ordering::Relaxed), 18);
+        assert_eq!(
+            report.to_csv(),
+            "difference,path,source_bytes,copy_bytes\n\
+             missing,\"only, here.txt\",1,\n\
+             content,\"corrupted.txt\",4,4\n"
+        );
+
+        // Touched later, the file the copy has the same content of is only retimed.
+        let touched = std::fs::File::options().write(true).open(copy.path().join("same.txt")).unwrap();
+        touched.set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        let rules = IgnoreRules { ignore_timestamps: false, ..IgnoreRules::default() };
+        let report = compare_dirs(source.path(), copy.path(), &options, &rules, Some(&HashProgress::default()), &AtomicBool::new(false)).unwrap();
+        assert_eq!(report.retimed, ["same.txt"]);
+        assert_eq!(report.content_differs.len(), 1);
+        assert_eq!(report.matching, 0);
+
+        let cancelled = compare_dirs(source.path(), copy.path(), &options, &rules, None, &AtomicBool::new(true));
+        assert!(matches!(cancelled, Err(ScanError::Cancelled)));
+    }
+}
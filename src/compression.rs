@@ -0,0 +1,146 @@
+//! This module estimates how much space compressing a folder would save, before turning on
+//! filesystem compression or archiving it. Rather than compressing everything, it samples a
+//! few blocks spread over each of the largest files, compresses them with zstd, and assumes
+//! the rest of each file (and the files left out) compresses as well as the samples did.
+
+use crate::format;
+use crate::scanner::FileSystemNode;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// The size of a sampled block, about what filesystems compress at a time.
+const BLOCK_SIZE: u64 = 128 << 10;
+/// How many blocks are sampled from each file.
+const BLOCKS_PER_FILE: u64 = 4;
+/// How many of the largest files are sampled.
+const MAX_FILES: usize = 200;
+/// The zstd level, a fast one as filesystems use.
+const LEVEL: i32 = 3;
+
+/// How well a folder would compress.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Estimate {
+    /// The apparent size of the files.
+    pub size: u64,
+    /// Their estimated size once compressed.
+    pub compressed: u64,
+    /// How many files were sampled.
+    pub sampled_files: usize,
+    /// How many bytes were read from them.
+    pub sampled_bytes: u64,
+}
+
+impl Estimate {
+    /// Describes the estimate, such as "This 30.0 GiB folder would compress to ~9.0 GiB".
+    pub fn describe(&self) -> String {
+        let saved = self.size.saturating_sub(self.compressed);
+        format!(
+            "This {} folder would compress to ~{}, saving ~{} ({:.0}%).",
+            format::format_size(self.size),
+            format::format_size(self.compressed),
+            format::format_size(saved),
+            saved as f64 * 100.0 / self.size.max(1) as f64
+        )
+    }
+}
+
+/// Estimates how well the folder `node`, which is at `dir` on disk, would compress.
+pub fn estimate(dir: &Path, node: &FileSystemNode) -> Result<Estimate, String> {
+    let mut files = Vec::new();
+    collect_files(node, dir, &mut files);
+    files.sort_by(|(a_path, a), (b_path, b)| b.cmp(a).then_with(|| a_path.cmp(b_path)));
+    let size: u64 = files.iter().map(|(_, size)| size).sum();
+
+    let (mut sampled_size, mut sampled_compressed) = (0u64, 0f64);
+    let (mut sampled_files, mut sampled_bytes) = (0, 0);
+    for (path, file_size) in files.iter().take(MAX_FILES) {
+        // Files that vanished or can't be read are left to the average.
+        let Ok((read, compressed)) = sample(path, *file_size) else {
+            continue;
+        };
+        if read == 0 {
+            continue;
+        }
+        sampled_size += file_size;
+        sampled_compressed += *file_size as f64 * compressed as f64 / read as f64;
+        sampled_files += 1;
+        sampled_bytes += read;
+    }
+    if sampled_files == 0 {
+        return Err("None of the files could be read.".to_string());
+    }
+    let ratio = sampled_compressed / sampled_size as f64;
+    let compressed = sampled_compressed + (size - sampled_size) as f64 * ratio;
+    Ok(Estimate { size, compressed: compressed.round() as u64, sampled_files, sampled_bytes })
+}
+
+/// Adds the files below `node`, which is at `path` on disk, with their sizes to `files`.
+fn collect_files(node: &FileSystemNode, path: &Path, files: &mut Vec<(PathBuf, u64)>) {
+    for child in &node.children {
+        let child_path = path.join(&child.name);
+        if child.children.is_empty() {
+            if child.size > 0 {
+                files.push((child_path, child.size));
+            }
+        } else {
+            collect_files(child, &child_path, files);
+        }
+    }
+}
+
+/// Compresses blocks spread evenly over the file at `path`, which is `size` bytes long.
+/// Returns how many bytes were read, and their compressed size.
+fn sample(path: &Path, size: u64) -> std::io::Result<(u64, u64)> {
+    let mut file = File::open(path)?;
+    let blocks = size.div_ceil(BLOCK_SIZE).min(BLOCKS_PER_FILE);
+    let (mut read, mut compressed) = (0, 0);
+    let mut buffer = Vec::with_capacity(BLOCK_SIZE as usize);
+    for i in 0..blocks {
+        // Spread the blocks from the start to the end of the file.
+        let offset = if blocks > 1 { (size - BLOCK_SIZE.min(size)) / (blocks - 1) * i } else { 0 };
+        file.seek(SeekFrom::Start(offset))?;
+        buffer.clear();
+        (&mut file).take(BLOCK_SIZE).read_to_end(&mut buffer)?;
+        read += buffer.len() as u64;
+        compressed += zstd::bulk::compress(&buffer, LEVEL)?.len() as u64;
+    }
+    Ok((read, compressed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{self, ScanOptions};
+
+    #[test]
+    fn test_compressible_and_random_data_are_told_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("logs")).unwrap();
+        std::fs::write(dir.path().join("logs/app.log"), "GET /index.html 200\n".repeat(100_000)).unwrap();
+        // Noise from a simple generator doesn't compress.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let noise: Vec<u8> = (0..2_000_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        std::fs::write(dir.path().join("video.bin"), &noise).unwrap();
+
+        let tree = scanner::build_tree(dir.path(), &ScanOptions::default()).unwrap();
+        let logs = estimate(&dir.path().join("logs"), tree.find(&["logs".to_string()]).unwrap()).unwrap();
+        assert_eq!(logs.size, 2_000_000);
+        assert!(logs.compressed < logs.size / 50, "{:?}", logs);
+        assert_eq!(logs.sampled_bytes, BLOCKS_PER_FILE * BLOCK_SIZE);
+
+        let all = estimate(dir.path(), &tree).unwrap();
+        assert_eq!((all.size, all.sampled_files), (4_000_000, 2));
+        assert!(all.compressed > 1_950_000 && all.compressed < 2_100_000, "{:?}", all);
+        assert!(all.describe().starts_with("This 3.8 MiB folder would compress to ~1.9 MiB"), "{}", all.describe());
+
+        assert!(estimate(&dir.path().join("missing"), &tree).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+//! This module measures how much space deleting a folder would actually free on
+//! copy-on-write filesystems (Btrfs and ZFS).
+//!
+//! On these filesystems a file's blocks may be shared with snapshots or reflinked copies,
+//! and may be compressed, so the logical size shown in the treemap can be far from the
+//! space that would be reclaimed. The numbers come from the filesystems' own tools:
+//! `btrfs filesystem du` for Btrfs, and `zfs list` for ZFS (which only knows about whole
+//! datasets, not individual folders).
+
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// A copy-on-write filesystem whose space accounting can be queried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CowFilesystem {
+    Btrfs,
+    Zfs,
+}
+
+impl CowFilesystem {
+    /// Recognizes a filesystem type name, as reported for a volume.
+    pub fn detect(file_system: &str) -> Option<Self> {
+        match file_system.to_ascii_lowercase().as_str() {
+            "btrfs" => Some(Self::Btrfs),
+            "zfs" => Some(Self::Zfs),
+            _ => None,
+        }
+    }
+}
+
+/// The space accounting of a folder (Btrfs) or of the dataset holding it (ZFS).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CowUsage {
+    /// The size of the data before compression and deduplication.
+    pub logical: u64,
+    /// The space that deleting the folder would free: blocks not shared with snapshots
+    /// or other files.
+    pub reclaimable: u64,
+    /// The space shared with snapshots or other files, which deleting the folder keeps in use.
+    pub shared: u64,
+    /// The compression ratio, if the filesystem reports one.
+    pub compression_ratio: Option<f64>,
+    /// Whether the numbers describe the whole dataset rather than just the folder.
+    pub whole_dataset: bool,
+}
+
+/// Asks the filesystem tools how much space deleting `path` would free.
+pub fn measure(path: &Path, filesystem: CowFilesystem) -> io::Result<CowUsage> {
+    let output = match filesystem {
+        CowFilesystem::Btrfs => run(Command::new("btrfs").args(["filesystem", "du", "-s", "--raw"]).arg(path))?,
+        CowFilesystem::Zfs => run(Command::new("zfs")
+            .args(["list", "-Hp", "-o", "logicalreferenced,usedbydataset,usedbysnapshots,compressratio"])
+            .arg(path))?,
+    };
+    let usage = match filesystem {
+        CowFilesystem::Btrfs => parse_btrfs_du(&output),
+        CowFilesystem::Zfs => parse_zfs_list(&output),
+    };
+    usage.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("unexpected output: {}", output.trim())))
+}
+
+/// Runs `command` and returns its standard output, turning a failure into an error.
+fn run(command: &mut Command) -> io::Result<String> {
+    let output = command.output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(stderr.trim().to_string()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parses the output of `btrfs filesystem du -s --raw`, whose last line holds the totals:
+/// total, exclusive, and shared bytes, followed by the path.
+fn parse_btrfs_du(output: &str) -> Option<CowUsage> {
+    let line = output.lines().rfind(|l| !l.trim().is_empty())?;
+    let mut fields = line.split_whitespace().map(|f| f.parse::<u64>());
+    let logical = fields.next()?.ok()?;
+    let reclaimable = fields.next()?.ok()?;
+    let shared = fields.next()?.ok()?;
+    Some(CowUsage { logical, reclaimable, shared, compression_ratio: None, whole_dataset: false })
+}
+
+/// Parses the output of `zfs list -Hp -o logicalreferenced,usedbydataset,usedbysnapshots,compressratio`.
+fn parse_zfs_list(output: &str) -> Option<CowUsage> {
+    let mut fields = output.lines().next()?.split('\t');
+    let logical = fields.next()?.trim().parse().ok()?;
+    let reclaimable = fields.next()?.trim().parse().ok()?;
+    let shared = fields.next()?.trim().parse().ok()?;
+    let compression_ratio = fields.next().and_then(|r| r.trim().trim_end_matches('x').parse().ok());
+    Some(CowUsage { logical, reclaimable, shared, compression_ratio, whole_dataset: true })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tool_output() {
+        let btrfs = "     Total   Exclusive  Set shared  Filename\n  10485760     4194304     6291456  /data/vm\n";
+        assert_eq!(
+            parse_btrfs_du(btrfs),
+            Some(CowUsage {
+                logical: 10485760,
+                reclaimable: 4194304,
+                shared: 6291456,
+                compression_ratio: None,
+                whole_dataset: false,
+            })
+        );
+
+        let zfs = "8000000\t3000000\t1000000\t1.85\n";
+        let usage = parse_zfs_list(zfs).unwrap();
+        assert_eq!((usage.logical, usage.reclaimable, usage.shared), (8000000, 3000000, 1000000));
+        assert_eq!(usage.compression_ratio, Some(1.85));
+        assert!(usage.whole_dataset);
+
+        assert_eq!(parse_btrfs_du("ERROR: not a btrfs filesystem\n"), None);
+    }
+}
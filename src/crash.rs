@@ -0,0 +1,288 @@
+//! This module writes a crash report when the app panics, if the user opted in. The report
+//! is a Markdown file in the app's data folder holding the panic message, a backtrace, and a
+//! summary of what the app was doing, ready to attach to an issue. On the next launch the
+//! app offers to open it.
+//!
+//! Panics a scan recovers from, which the app reports as a failed scan, aren't crashes and
+//! get no report.
+//!
+//! File paths can tell a lot about a person, so reports leave them out unless the user
+//! allowed them: a path in the scanned folder becomes `<scanned folder>/…`, one in the home
+//! folder `~/…`, any other path `<path>`, and the source locations of the backtrace are
+//! dropped, keeping only the functions.
+
+use crate::scanner;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::backtrace::Backtrace;
+use std::fs;
+use std::io;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Where issues are reported.
+pub const ISSUES_URL: &str = "https://github.com/k3rnel-paN1c5/disk-scout/issues/new";
+
+/// Whether reports are written.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// Whether reports may hold file paths.
+static INCLUDE_PATHS: AtomicBool = AtomicBool::new(false);
+/// What the app was doing, as last noted.
+static STATE: Mutex<AppState> = Mutex::new(AppState::EMPTY);
+/// The folder reports are written to, and when the app started.
+static REPORTER: OnceLock<(PathBuf, Instant)> = OnceLock::new();
+
+/// The user's choices about crash reports.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrashSettings {
+    /// Whether a report is written when the app crashes.
+    pub enabled: bool,
+    /// Whether reports may hold file paths.
+    pub include_paths: bool,
+    /// When the newest report that was offered was written, in seconds since the Unix epoch.
+    pub offered_until: u64,
+}
+
+impl CrashSettings {
+    /// Draws the settings. Returns whether they changed.
+    pub fn show_settings(&mut self, ui: &mut eframe::egui::Ui) -> bool {
+        let mut changed = ui.checkbox(&mut self.enabled, "Save a crash report if Disk Scout crashes").changed();
+        ui.add_enabled_ui(self.enabled, |ui| {
+            changed |= ui
+                .checkbox(&mut self.include_paths, "Include file paths in crash reports")
+                .on_hover_text("Without them, paths in the report are replaced by placeholders.")
+                .changed();
+        });
+        ui.weak("Reports stay on this computer until you attach one to an issue.");
+        changed
+    }
+}
+
+/// A summary of what the app was doing, for the report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppState {
+    /// Whether a scan was running.
+    pub scanning: bool,
+    /// The folder scanned, if any.
+    pub scan_root: Option<PathBuf>,
+    /// The number of files and folders in the scan, once it finished.
+    pub items: Option<u64>,
+    /// How many levels below the scan root the treemap showed.
+    pub view_depth: usize,
+    /// The number of open tabs.
+    pub tabs: usize,
+}
+
+impl AppState {
+    const EMPTY: AppState = AppState { scanning: false, scan_root: None, items: None, view_depth: 0, tabs: 0 };
+}
+
+/// Installs the panic hook writing reports to `dir`. Nothing is written until reports are
+/// turned on with [`set`].
+pub fn install(dir: PathBuf) {
+    if REPORTER.set((dir, Instant::now())).is_err() {
+        return;
+    }
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if ENABLED.load(Ordering::Relaxed) && !scanner::panic_is_recovered() {
+            match write_from_hook(info) {
+                Ok(path) => eprintln!("A crash report was saved to {}", path.display()),
+                Err(e) => eprintln!("Failed to save a crash report: {}", e),
+            }
+        }
+        default_hook(info);
+    }));
+}
+
+/// The crash reports folder in the app's data folder, if the platform has one.
+pub fn default_dir() -> Option<PathBuf> {
+    eframe::storage_dir("Disk Scout").map(|dir| dir.join("crashes"))
+}
+
+/// Applies `settings` to the running app.
+pub fn set(settings: &CrashSettings) {
+    ENABLED.store(settings.enabled, Ordering::Relaxed);
+    INCLUDE_PATHS.store(settings.include_paths, Ordering::Relaxed);
+}
+
+/// Notes what the app is doing, for a report written later.
+pub fn note_state(state: AppState) {
+    if let Ok(mut noted) = STATE.try_lock() {
+        *noted = state;
+    }
+}
+
+/// The newest report in `dir` written after `after`, in seconds since the Unix epoch, and
+/// when it was written.
+pub fn pending(dir: &Path, after: u64) -> Option<(PathBuf, u64)> {
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let time = name.to_str()?.strip_prefix("crash-")?.strip_suffix(".md")?.parse::<u64>().ok()?;
+            (time > after).then(|| (entry.path(), time))
+        })
+        .max_by_key(|(_, time)| *time)
+}
+
+/// Writes the report of the panic described by `info`.
+fn write_from_hook(info: &PanicHookInfo) -> io::Result<PathBuf> {
+    let Some((dir, started)) = REPORTER.get() else {
+        return Err(io::Error::other("crash reports aren't set up"));
+    };
+    let message = match info.payload().downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => info.payload().downcast_ref::<String>().cloned().unwrap_or_else(|| "unknown panic".to_string()),
+    };
+    // The lock is held by nobody else while the app panics, unless it panicked noting its state.
+    let state = STATE.try_lock().map(|s| s.clone()).unwrap_or(AppState::EMPTY);
+    let crash = Crash {
+        message,
+        location: info.location().map(|l| l.to_string()),
+        thread: std::thread::current().name().unwrap_or("unnamed").to_string(),
+        backtrace: Backtrace::force_capture().to_string(),
+        uptime: started.elapsed().as_secs(),
+        state,
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")).map(PathBuf::from);
+    let report = crash.report(INCLUDE_PATHS.load(Ordering::Relaxed), home.as_deref(), now);
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("crash-{}.md", now));
+    fs::write(&path, report)?;
+    Ok(path)
+}
+
+/// What is known about a panic.
+struct Crash {
+    message: String,
+    /// Where in the source the panic was raised.
+    location: Option<String>,
+    /// The name of the thread that panicked.
+    thread: String,
+    backtrace: String,
+    /// How long the app had been running, in seconds.
+    uptime: u64,
+    state: AppState,
+}
+
+impl Crash {
+    /// The report, written at `now`. Unless `include_paths`, paths are replaced by
+    /// placeholders, the user's `home` folder among them.
+    fn report(&self, include_paths: bool, home: Option<&Path>, now: u64) -> String {
+        let state = &self.state;
+        let root = state.scan_root.as_ref().map(|r| r.display().to_string());
+        let anonymize = |text: &str| if include_paths { text.to_string() } else { anonymize(text, root.as_deref(), home) };
+        let backtrace = if include_paths { self.backtrace.clone() } else { without_locations(&self.backtrace) };
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        let mut report = String::from("# Disk Scout crash report\n\n");
+        report += &format!("- Version: {}\n", env!("CARGO_PKG_VERSION"));
+        report += &format!("- Platform: {} {}\n", std::env::consts::OS, std::env::consts::ARCH);
+        report += &format!("- Time: {}\n", crate::format::format_timestamp(now));
+        report += &format!("- Running for: {} s\n\n", self.uptime);
+        report += "## Panic\n\n";
+        report += &format!("Thread `{}` panicked", self.thread);
+        if let Some(location) = &self.location {
+            report += &format!(" at `{}`", anonymize(location));
+        }
+        report += &format!(":\n\n```\n{}\n```\n\n", anonymize(&self.message));
+        report += "## State\n\n";
+        report += &format!("- Scanning: {}\n", yes_no(state.scanning));
+        report += &format!("- Scanned folder: {}\n", root.as_deref().map_or("none".to_string(), anonymize));
+        report += &format!("- Items in the scan: {}\n", state.items.map_or("unknown".to_string(), |n| n.to_string()));
+        report += &format!("- Levels below the scanned folder shown: {}\n", state.view_depth);
+        report += &format!("- Open tabs: {}\n\n", state.tabs);
+        report += &format!("## Backtrace\n\n```\n{}\n```\n", backtrace.trim_end());
+        report
+    }
+}
+
+/// Replaces the absolute paths in `text`, with what follows them in their folders: the scan
+/// `root` and the paths in it by `<scanned folder>` and `<scanned folder>/…`, the `home`
+/// folder and the paths in it by `~` and `~/…`, and any other path by `<path>`.
+///
+/// A path ends at a quote, a closing parenthesis, or the end of the line, and at a space unless
+/// the word after the space goes on to another folder, as in `/srv/My Docs/a.txt`. The scan
+/// root and the home folder are known, so spaces in them never end a path.
+fn anonymize(text: &str, root: Option<&str>, home: Option<&Path>) -> String {
+    let root = root.filter(|r| !r.is_empty());
+    let home = home.map(|h| h.display().to_string()).filter(|h| h.len() > 1);
+    let known: String = root.iter().copied().chain(home.as_deref()).map(|p| regex::escape(p) + "|").collect();
+    // Paths from a known folder, or the root of a Unix file system, a drive, or a share.
+    let pattern = format!(
+        r#"(^|[\s'"`(=:])((?:{}/|[A-Za-z]:\\|\\\\)[^\s'"`)]*(?: [^\s'"`)/\\]*[/\\][^\s'"`)]*)*)"#,
+        known
+    );
+    let paths = Regex::new(&pattern).expect("the pattern is valid");
+    paths
+        .replace_all(text, |caps: &regex::Captures| {
+            // Punctuation at the end belongs to the sentence around the path.
+            let path = caps[2].trim_end_matches(['.', ',', ';', ':']);
+            let within = |folder: &str| {
+                let rest = path.strip_prefix(folder)?;
+                if rest.is_empty() {
+                    Some("")
+                } else {
+                    rest.starts_with(['/', '\\']).then_some("/…")
+                }
+            };
+            let placeholder = match (root.and_then(within), home.as_deref().and_then(within)) {
+                (Some(tail), _) => format!("<scanned folder>{}", tail),
+                (None, Some(tail)) => format!("~{}", tail),
+                (None, None) => "<path>".to_string(),
+            };
+            format!("{}{}{}", &caps[1], placeholder, &caps[2][path.len()..])
+        })
+        .into_owned()
+}
+
+/// The backtrace without the lines giving the source file of each frame.
+fn without_locations(backtrace: &str) -> String {
+    backtrace.lines().filter(|line| !line.trim_start().starts_with("at ")).collect::<Vec<_>>().join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_leaves_out_paths() {
+        let crash = Crash {
+            message: "Failed to read /home/ann/photos/cat.jpg and /srv/data/x: denied (C:\\Users\\ann\\a.txt), see /srv/My Docs/secret.txt.".to_string(),
+            location: Some("src/treemap.rs:120:5".to_string()),
+            thread: "main".to_string(),
+            backtrace: "   0: disk_scout::treemap::arrange\n             at /home/ann/disk-scout/src/treemap.rs:120:5\n".to_string(),
+            uptime: 42,
+            state: AppState { scanning: true, scan_root: Some(PathBuf::from("/srv/data")), items: None, view_depth: 2, tabs: 1 },
+        };
+        let report = crash.report(false, Some(Path::new("/home/ann")), 0);
+        assert!(report.contains("Failed to read ~/… and <scanned folder>/…: denied (<path>), see <path>.\n"));
+        assert!(report.contains("Thread `main` panicked at `src/treemap.rs:120:5`"));
+        assert!(report.contains("- Scanned folder: <scanned folder>\n"));
+        assert!(report.contains("0: disk_scout::treemap::arrange"));
+        for leaked in ["/ann", "\\ann", "/srv", "photos", "cat.jpg", "Docs", "secret"] {
+            assert!(!report.contains(leaked), "{} is in the report", leaked);
+        }
+
+        let report = crash.report(true, Some(Path::new("/home/ann")), 0);
+        assert!(report.contains("Failed to read /home/ann/photos/cat.jpg"));
+        assert!(report.contains("at /home/ann/disk-scout/src/treemap.rs:120:5"));
+    }
+
+    #[test]
+    fn test_pending_report() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(pending(dir.path(), 0), None);
+        for name in ["crash-100.md", "crash-300.md", "crash-200.md", "notes.md"] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+        assert_eq!(pending(dir.path(), 0), Some((dir.path().join("crash-300.md"), 300)));
+        assert_eq!(pending(dir.path(), 300), None);
+    }
+}
@@ -0,0 +1,56 @@
+//! This module finds the folder a scan is dominated by: when one child of the root holds
+//! nearly all of its size, as `/home` does in a scan of `/` on many desktops, the first view
+//! would be a single giant rectangle. The app offers to focus on that child instead, or
+//! does so right away if asked to, with the path bar leading back.
+
+use crate::scanner::{FileSystemNode, SizeMetric};
+
+/// The share of its parent's size a child needs to dominate it.
+pub const THRESHOLD: f64 = 0.95;
+
+/// The path below `tree` of the folder it is dominated by, following the dominating child
+/// down as long as the next one dominates too. Empty if no child dominates the root.
+pub fn dominant_path(tree: &FileSystemNode, metric: SizeMetric) -> Vec<String> {
+    let mut path = Vec::new();
+    let mut node = tree;
+    while let Some(child) = dominant_child(node, metric) {
+        path.push(child.name.clone());
+        node = child;
+    }
+    path
+}
+
+/// The child folder of `node` holding more than [`THRESHOLD`] of its size, if any.
+fn dominant_child(node: &FileSystemNode, metric: SizeMetric) -> Option<&FileSystemNode> {
+    let total = node.size_by(metric);
+    let child = node.children.iter().max_by_key(|c| c.size_by(metric))?;
+    // Focusing on a file would leave nothing to see.
+    (total > 0 && !child.children.is_empty() && child.size_by(metric) as f64 > total as f64 * THRESHOLD).then_some(child)
+}
+
+/// The share of `tree` the node at `path` holds.
+pub fn share(tree: &FileSystemNode, path: &[String], metric: SizeMetric) -> f64 {
+    let total = tree.size_by(metric);
+    tree.find(path).filter(|_| total > 0).map_or(0.0, |node| node.size_by(metric) as f64 / total as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, size: u64, children: Vec<FileSystemNode>) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    #[test]
+    fn test_dominant_path_follows_dominating_folders() {
+        let user = node("user", 960, vec![node("videos", 500, vec![node("a.mp4", 500, vec![])]), node("big.iso", 460, vec![])]);
+        let tree = node("/", 1000, vec![node("home", 980, vec![user]), node("etc", 20, vec![node("hosts", 20, vec![])])]);
+        assert_eq!(dominant_path(&tree, SizeMetric::Apparent), ["home", "user"]);
+        assert!((share(&tree, &["home".to_string(), "user".to_string()], SizeMetric::Apparent) - 0.96).abs() < 1e-9);
+
+        // A dominating file is left where it is.
+        let tree = node("downloads", 100, vec![node("disk.img", 99, vec![]), node("a.txt", 1, vec![])]);
+        assert!(dominant_path(&tree, SizeMetric::Apparent).is_empty());
+    }
+}
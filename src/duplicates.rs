@@ -0,0 +1,421 @@
+//! This module finds duplicate files in a scan: files of the same size whose content is the
+//! same. Files are grouped by size first, and each group is narrowed down by a hash of the
+//! start of each file and then by a hash of the whole of it, so most files are never read
+//! to the end. The groups that could free the most space are hashed first, so the biggest
+//! duplicates show up early in a long search.
+//!
+//! Hashes are kept in a cache in the app's data folder, keyed by path and valid while the
+//! file's size and modification time stay the same. The cache is saved as the search goes,
+//! so a search that was paused, stopped, or cut short by closing the app picks up where it
+//! left off the next time: the files it already hashed aren't read again.
+
+use crate::integrity;
+use crate::scanner::{self, FileSystemNode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How much of the start of each file is hashed before deciding whether to hash all of it.
+const PREFIX_BYTES: u64 = 64 * 1024;
+/// How often the cache is saved while a search runs.
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+/// The most files the cache keeps hashes of. The ones used longest ago are dropped first.
+const CACHE_CAPACITY: usize = 500_000;
+/// How often a paused search checks whether it was resumed.
+const PAUSE_POLL: Duration = Duration::from_millis(100);
+
+/// Files of the same size and content found in a scan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateGroup {
+    /// The size of each of the files.
+    pub size: u64,
+    /// The paths of the files, relative to the scan root and joined with `/`, sorted.
+    pub paths: Vec<String>,
+}
+
+impl DuplicateGroup {
+    /// The space freed by keeping only one of the files.
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+/// How far a search has got, shared with the thread running it, and how to steer it.
+#[derive(Debug, Default)]
+pub struct Progress {
+    /// The bytes of the candidate files settled so far, whether they were read or not.
+    pub settled: AtomicU64,
+    /// The bytes of all candidate files.
+    pub total: AtomicU64,
+    /// Set to hold the search after the file being hashed.
+    pub paused: AtomicBool,
+    /// Set to end the search after the file being hashed.
+    pub cancel: AtomicBool,
+    /// Set once the search ended, whether it finished or was stopped.
+    pub done: AtomicBool,
+    /// The groups found so far, the ones freeing the most space first.
+    pub found: Mutex<Vec<DuplicateGroup>>,
+}
+
+impl Progress {
+    /// A copy of the groups found so far.
+    pub fn found(&self) -> Vec<DuplicateGroup> {
+        self.found.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// The hashes known of a file, as it was when they were taken.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CachedHash {
+    size: u64,
+    /// The modification time, in seconds and nanoseconds since the Unix epoch.
+    modified: (u64, u32),
+    /// The hash of the first `PREFIX_BYTES` of the file.
+    prefix: Option<String>,
+    /// The hash of the whole file.
+    full: Option<String>,
+    /// When the hashes were last used, in seconds since the Unix epoch.
+    used: u64,
+}
+
+/// The hashes of files taken by earlier searches.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HashCache {
+    /// By absolute path.
+    entries: HashMap<String, CachedHash>,
+}
+
+impl HashCache {
+    /// The cache file in the app's data folder, if the platform has one.
+    pub fn default_path() -> Option<PathBuf> {
+        eframe::storage_dir("Disk Scout").map(|dir| dir.join("hash-cache.json"))
+    }
+
+    /// Reads the cache at `path`. A missing or unreadable cache is an empty one.
+    pub fn load(path: &Path) -> Self {
+        match fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                eprintln!("Ignoring the unreadable hash cache {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Writes the cache to `path`, dropping the hashes used longest ago if there are too
+    /// many. The file is replaced in one step, so a crash while saving leaves the previous one.
+    pub fn save(&mut self, path: &Path) -> io::Result<()> {
+        if self.entries.len() > CACHE_CAPACITY {
+            let mut used: Vec<u64> = self.entries.values().map(|e| e.used).collect();
+            let (_, &mut oldest_kept, _) = used.select_nth_unstable_by(CACHE_CAPACITY, |a, b| b.cmp(a));
+            self.entries.retain(|_, e| e.used > oldest_kept);
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let partial = path.with_extension("partial");
+        let mut writer = BufWriter::new(File::create(&partial)?);
+        serde_json::to_writer(&mut writer, self).map_err(io::Error::other)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&partial, path)
+    }
+
+    /// The cached hashes of the file at `path`, if it hasn't changed since they were taken.
+    fn get(&mut self, path: &Path, metadata: &fs::Metadata) -> Option<&mut CachedHash> {
+        let (size, modified) = (metadata.len(), modified(metadata));
+        let entry = self.entries.get_mut(&*path.to_string_lossy())?;
+        (entry.size == size && entry.modified == modified).then_some(entry)
+    }
+
+    /// The entry of the file at `path`, emptied if the file changed since it was made.
+    fn entry(&mut self, path: &Path, metadata: &fs::Metadata) -> &mut CachedHash {
+        let (size, modified) = (metadata.len(), modified(metadata));
+        let entry = self
+            .entries
+            .entry(path.to_string_lossy().into_owned())
+            .or_insert_with(|| CachedHash { size, modified, prefix: None, full: None, used: 0 });
+        if entry.size != size || entry.modified != modified {
+            *entry = CachedHash { size, modified, prefix: None, full: None, used: 0 };
+        }
+        entry.used = now();
+        entry
+    }
+}
+
+/// The files in `tree` that could have duplicates: those sharing their size with another
+/// file, grouped by size and sorted by path. The groups that could free the most space come
+/// first.
+pub fn candidates(tree: &FileSystemNode) -> Vec<(u64, Vec<String>)> {
+    let mut by_size: HashMap<u64, Vec<String>> = HashMap::new();
+    let mut stack: Vec<(&FileSystemNode, String)> = vec![(tree, String::new())];
+    while let Some((node, path)) = stack.pop() {
+        for child in &node.children {
+            let child_path = if path.is_empty() { child.name.clone() } else { format!("{}/{}", path, child.name) };
+            if !child.children.is_empty() {
+                stack.push((child, child_path));
+            } else if child.size > 0 && child.special.is_none() {
+                by_size.entry(child.size).or_default().push(child_path);
+            }
+        }
+    }
+    let mut groups: Vec<(u64, Vec<String>)> = by_size.into_iter().filter(|(_, paths)| paths.len() > 1).collect();
+    for (_, paths) in &mut groups {
+        paths.sort();
+    }
+    groups.sort_by(|(a, a_paths), (b, b_paths)| {
+        let potential = |size: u64, paths: &[String]| size * (paths.len() as u64 - 1);
+        potential(*b, b_paths).cmp(&potential(*a, a_paths)).then_with(|| b.cmp(a))
+    });
+    groups
+}
+
+/// Finds the duplicate files in `tree`, which was scanned from `root`, reporting them in
+/// `progress` as they are confirmed. Hashes come from and go to the cache at `cache_path`
+/// when one is given. Returns once every group is settled or the search is cancelled.
+pub fn find(root: &Path, tree: &FileSystemNode, cache_path: Option<&Path>, progress: &Progress) {
+    let mut cache = cache_path.map(HashCache::load).unwrap_or_default();
+    let groups = candidates(tree);
+    progress.total.store(groups.iter().map(|(size, paths)| size * paths.len() as u64).sum(), Ordering::Relaxed);
+    let mut saved = Instant::now();
+    for (size, paths) in groups {
+        if !wait_while_paused(progress, &mut cache, cache_path) {
+            break;
+        }
+        let found = settle(root, (size, &paths), &mut cache, cache_path, progress);
+        if !found.is_empty() {
+            let mut all = progress.found.lock().unwrap_or_else(|e| e.into_inner());
+            all.extend(found);
+            all.sort_by(|a, b| b.wasted().cmp(&a.wasted()).then_with(|| a.paths.cmp(&b.paths)));
+        }
+        if let Some(path) = cache_path
+            && saved.elapsed() >= SAVE_INTERVAL
+        {
+            save(&mut cache, path);
+            saved = Instant::now();
+        }
+    }
+    if let Some(path) = cache_path {
+        save(&mut cache, path);
+    }
+    progress.done.store(true, Ordering::Relaxed);
+}
+
+/// Narrows the files at `paths` (from `root`), all of `size` bytes, down to the groups with
+/// the same content. Nothing is found if the search is stopped on the way. Files that changed size since the scan, and further hard links to a
+/// file already among them, are left out.
+fn settle(
+    root: &Path,
+    (size, paths): (u64, &[String]),
+    cache: &mut HashCache,
+    cache_path: Option<&Path>,
+    progress: &Progress,
+) -> Vec<DuplicateGroup> {
+    let mut files = Vec::new();
+    let mut seen = Vec::new();
+    for path in paths {
+        match fs::metadata(root.join(path)) {
+            Ok(metadata) if metadata.is_file() && metadata.len() == size => {
+                if let Some(id) = scanner::file_id(&metadata) {
+                    if seen.contains(&id) {
+                        continue;
+                    }
+                    seen.push(id);
+                }
+                files.push((path, metadata));
+            }
+            _ => {}
+        }
+    }
+
+    let mut hash_file = |path: &str, metadata: &fs::Metadata, stage| {
+        // Pausing holds the search between two files, even in the middle of a large group.
+        wait_while_paused(progress, cache, cache_path).then(|| hash(cache, &root.join(path), metadata, stage)).flatten()
+    };
+    let mut found = Vec::new();
+    for same_prefix in split_by(&files, |path, metadata| hash_file(path, metadata, Stage::Prefix)) {
+        // The prefix of a small file is all of it.
+        let same_content = if size <= PREFIX_BYTES {
+            vec![same_prefix]
+        } else {
+            split_by(&same_prefix, |path, metadata| hash_file(path, metadata, Stage::Full))
+        };
+        for group in same_content {
+            let mut paths: Vec<String> = group.into_iter().map(|(path, _)| path.to_string()).collect();
+            paths.sort();
+            found.push(DuplicateGroup { size, paths });
+        }
+    }
+    // Files skipped because the search was stopped may have been copies too.
+    if progress.cancel.load(Ordering::Relaxed) {
+        return Vec::new();
+    }
+    progress.settled.fetch_add(size * paths.len() as u64, Ordering::Relaxed);
+    found
+}
+
+/// Which hash of a file to take.
+#[derive(Clone, Copy)]
+enum Stage {
+    Prefix,
+    Full,
+}
+
+/// Groups `files` by `key`, leaving out those without one and those in no group with another.
+fn split_by<'a>(
+    files: &[(&'a String, fs::Metadata)],
+    mut key: impl FnMut(&str, &fs::Metadata) -> Option<String>,
+) -> Vec<Vec<(&'a String, fs::Metadata)>> {
+    let mut groups: HashMap<String, Vec<(&'a String, fs::Metadata)>> = HashMap::new();
+    for (path, metadata) in files {
+        if let Some(key) = key(path, metadata) {
+            groups.entry(key).or_default().push((*path, metadata.clone()));
+        }
+    }
+    groups.into_values().filter(|group| group.len() > 1).collect()
+}
+
+/// The hash of the file at `path` at `stage`, from the cache if it is there.
+fn hash(cache: &mut HashCache, path: &Path, metadata: &fs::Metadata, stage: Stage) -> Option<String> {
+    if let Some(entry) = cache.get(path, metadata) {
+        let known = match stage {
+            Stage::Prefix => &entry.prefix,
+            Stage::Full => &entry.full,
+        };
+        if let Some(known) = known.clone() {
+            entry.used = now();
+            return Some(known);
+        }
+    }
+    let hashed = match stage {
+        Stage::Prefix => hash_prefix(path),
+        Stage::Full => integrity::hash_file(path),
+    };
+    match hashed {
+        Ok(hashed) => {
+            let entry = cache.entry(path, metadata);
+            match stage {
+                Stage::Prefix => entry.prefix = Some(hashed.clone()),
+                Stage::Full => entry.full = Some(hashed.clone()),
+            }
+            Some(hashed)
+        }
+        Err(e) => {
+            eprintln!("Failed to hash {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Hashes the first `PREFIX_BYTES` of the file at `path`.
+fn hash_prefix(path: &Path) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(File::open(path)?.take(PREFIX_BYTES))?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Holds the search while it is paused, saving the cache once so the search can resume in a
+/// later session. Returns whether to go on.
+fn wait_while_paused(progress: &Progress, cache: &mut HashCache, cache_path: Option<&Path>) -> bool {
+    if progress.paused.load(Ordering::Relaxed) {
+        if let Some(path) = cache_path {
+            save(cache, path);
+        }
+        while progress.paused.load(Ordering::Relaxed) && !progress.cancel.load(Ordering::Relaxed) {
+            thread::sleep(PAUSE_POLL);
+        }
+    }
+    !progress.cancel.load(Ordering::Relaxed)
+}
+
+fn save(cache: &mut HashCache, path: &Path) {
+    if let Err(e) = cache.save(path) {
+        eprintln!("Failed to save the hash cache to {}: {}", path.display(), e);
+    }
+}
+
+fn modified(metadata: &fs::Metadata) -> (u64, u32) {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or((0, 0), |d| (d.as_secs(), d.subsec_nanos()))
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::ScanOptions;
+
+    #[test]
+    fn test_find_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let big: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let mut differs_at_end = big.clone();
+        *differs_at_end.last_mut().unwrap() ^= 1;
+        fs::create_dir(dir.path().join("backup")).unwrap();
+        fs::write(dir.path().join("video.mp4"), &big).unwrap();
+        fs::write(dir.path().join("backup/video.mp4"), &big).unwrap();
+        fs::write(dir.path().join("edited.mp4"), &differs_at_end).unwrap();
+        fs::write(dir.path().join("a.txt"), "hello").unwrap();
+        fs::write(dir.path().join("backup/a.txt"), "hello").unwrap();
+        fs::write(dir.path().join("b.txt"), "world").unwrap();
+        #[cfg(unix)]
+        fs::hard_link(dir.path().join("video.mp4"), dir.path().join("link.mp4")).unwrap();
+
+        let tree = scanner::build_tree(dir.path(), &ScanOptions::default()).unwrap();
+        // Every file shares its size with another one.
+        assert_eq!(candidates(&tree)[0].0, 200_000);
+        let progress = Progress::default();
+        find(dir.path(), &tree, None, &progress);
+        assert!(progress.done.load(Ordering::Relaxed));
+        assert_eq!(progress.settled.load(Ordering::Relaxed), progress.total.load(Ordering::Relaxed));
+        let found = progress.found();
+        assert_eq!(found.len(), 2);
+        // Hard links are the same file, not copies of it, so only the first one counts.
+        assert_eq!(found[0].paths[0], "backup/video.mp4");
+        assert_eq!(found[0].paths.len(), 2);
+        assert_eq!(found[0].wasted(), 200_000);
+        assert_eq!(found[1].paths, ["a.txt", "backup/a.txt"]);
+    }
+
+    #[test]
+    fn test_search_resumes_from_the_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("cache").join("hash-cache.json");
+        let root = dir.path().join("files");
+        fs::create_dir(&root).unwrap();
+        for name in ["one", "two", "three"] {
+            fs::write(root.join(name), "same").unwrap();
+        }
+        let tree = scanner::build_tree(&root, &ScanOptions::default()).unwrap();
+
+        // A search stopped before it started hashes nothing.
+        let stopped = Progress::default();
+        stopped.cancel.store(true, Ordering::Relaxed);
+        find(&root, &tree, Some(&cache_path), &stopped);
+        assert!(stopped.found().is_empty());
+
+        find(&root, &tree, Some(&cache_path), &Progress::default());
+        assert_eq!(HashCache::load(&cache_path).entries.len(), 3);
+        // A file rewritten with the same size and time keeps its cached hash, which shows
+        // that the next search reads hashes from the cache rather than the files.
+        let file = File::options().write(true).open(root.join("three")).unwrap();
+        let modified = file.metadata().unwrap().modified().unwrap();
+        fs::write(root.join("three"), "diff").unwrap();
+        file.set_modified(modified).unwrap();
+        let progress = Progress::default();
+        find(&root, &tree, Some(&cache_path), &progress);
+        assert_eq!(progress.found()[0].paths, ["one", "three", "two"]);
+    }
+}
@@ -0,0 +1,130 @@
+//! This module defines the errors a scan can fail with.
+//! Each kind of failure gets its own variant so the UI can explain what went wrong
+//! and suggest what to do about it.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Why a scan (or part of a scan) failed.
+#[derive(Debug, Error)]
+pub enum ScanError {
+    /// The user isn't allowed to read the path.
+    #[error("Permission denied: {}", .0.display())]
+    PermissionDenied(PathBuf),
+    /// The path doesn't exist.
+    #[error("Not found: {}", .0.display())]
+    NotFound(PathBuf),
+    /// The network share or drive holding the path stopped answering.
+    #[error("Connection lost: {}", .0.display())]
+    Disconnected(PathBuf),
+    /// The path is a symbolic link to one of its own ancestors.
+    #[error("Symbolic link loop: {}", .0.display())]
+    CyclicSymlink(PathBuf),
+    /// The directory is nested deeper below the scan root than the scan goes.
+    #[error("Nested too deeply: {}", .0.display())]
+    TooDeep(PathBuf),
+    /// The scan was stopped before it finished.
+    #[error("The scan was cancelled")]
+    Cancelled,
+    /// The scan root is a file rather than a directory.
+    #[error("Not a directory: {}", .0.display())]
+    NotADirectory(PathBuf),
+    /// The scan hit a bug in Disk Scout and stopped, with the message of the panic.
+    #[error("The scan stopped unexpectedly: {0}")]
+    Panicked(String),
+    /// Any other I/O error.
+    #[error("Failed to read {}: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl ScanError {
+    /// Classifies an I/O error that occurred while reading `path`.
+    pub fn from_io(path: &Path, error: io::Error) -> Self {
+        let path = path.to_path_buf();
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied(path),
+            io::ErrorKind::NotFound => Self::NotFound(path),
+            _ if is_disconnection(&error) => Self::Disconnected(path),
+            _ => Self::Io { path, source: error },
+        }
+    }
+
+    /// A short name for the kind of error, used to count errors by kind.
+    pub fn category(&self) -> &'static str {
+        match self {
+            Self::PermissionDenied(_) => "Permission denied",
+            Self::NotFound(_) => "Not found",
+            Self::Disconnected(_) => "Connection lost",
+            Self::CyclicSymlink(_) => "Symbolic link loop",
+            Self::TooDeep(_) => "Nested too deeply",
+            Self::Cancelled => "Cancelled",
+            Self::NotADirectory(_) => "Not a directory",
+            Self::Panicked(_) => "Internal errors",
+            Self::Io { .. } => "Other I/O errors",
+        }
+    }
+
+    /// A suggestion for the user on how to get past the error.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Self::PermissionDenied(_) => {
+                "Pick a folder you own, or run Disk Scout as a user that can read this one."
+            }
+            Self::NotFound(_) => "Check the path for typos, or use \"Browse...\" to pick a folder.",
+            Self::Disconnected(_) => "Check the network connection and that the share is still mounted, then scan again.",
+            Self::CyclicSymlink(_) => "Remove the link, or exclude it from the scan.",
+            Self::TooDeep(_) => "Look for a folder copied into itself, or scan the deep folder directly, or raise --max-nesting.",
+            Self::Cancelled => "Click \"Scan\" to start again.",
+            Self::NotADirectory(_) => "Scan the folder that contains it instead.",
+            Self::Panicked(_) => "This is a bug in Disk Scout. Scanning again may work; please report it with the message.",
+            Self::Io { .. } => "Check that the drive is still connected and readable.",
+        }
+    }
+}
+
+/// Whether `error` means the network share or drive being read went away, rather than
+/// something being wrong with the file itself.
+pub fn is_disconnection(error: &io::Error) -> bool {
+    use io::ErrorKind::*;
+    if matches!(
+        error.kind(),
+        NotConnected | ConnectionReset | ConnectionAborted | TimedOut | HostUnreachable | NetworkUnreachable | NetworkDown | StaleNetworkFileHandle
+    ) {
+        return true;
+    }
+    // Windows reports a share that went away with codes of its own: ERROR_BAD_NETPATH,
+    // ERROR_UNEXP_NET_ERR, ERROR_NETNAME_DELETED, ERROR_NO_NETWORK, and ERROR_NETWORK_UNREACHABLE.
+    cfg!(windows) && matches!(error.raw_os_error(), Some(53 | 59 | 64 | 1222 | 1231))
+}
+
+/// Whether `error` may mean the drive being read was removed: the paths on it are gone, or
+/// the device no longer answers. Whether it really was takes a look at the volume.
+pub fn is_removal(error: &io::Error) -> bool {
+    // EIO, ENXIO, and ENODEV on Unix; ERROR_NOT_READY and ERROR_DEV_NOT_EXIST on Windows.
+    error.kind() == io::ErrorKind::NotFound
+        || (cfg!(unix) && matches!(error.raw_os_error(), Some(5 | 6 | 19)))
+        || (cfg!(windows) && matches!(error.raw_os_error(), Some(21 | 55)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_io_classifies_errors() {
+        let path = Path::new("/some/path");
+        let error = ScanError::from_io(path, io::Error::from(io::ErrorKind::PermissionDenied));
+        assert!(matches!(error, ScanError::PermissionDenied(ref p) if p == path));
+        let error = ScanError::from_io(path, io::Error::from(io::ErrorKind::NotFound));
+        assert_eq!(error.to_string(), "Not found: /some/path");
+        let error = ScanError::from_io(path, io::Error::from(io::ErrorKind::StaleNetworkFileHandle));
+        assert_eq!(error.category(), "Connection lost");
+        let error = ScanError::from_io(path, io::Error::other("disk on fire"));
+        assert_eq!(error.to_string(), "Failed to read /some/path: disk on fire");
+    }
+}
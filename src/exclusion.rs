@@ -0,0 +1,132 @@
+//! This module handles nodes the user has hidden from the view.
+//! Excluded nodes are removed from a copy of the scanned tree and their sizes are
+//! subtracted from every ancestor, so the totals reflect what is left without rescanning.
+
+use crate::scanner::FileSystemNode;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// A node that has been hidden from the view.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExcludedItem {
+    /// The names of the nodes leading from the scan root (exclusive) to the excluded node (inclusive).
+    pub path: Vec<String>,
+    /// The size of the node at the time it was excluded, in bytes.
+    pub size: u64,
+}
+
+/// Returns a copy of `tree` with every node in `excluded` removed and the sizes of
+/// their ancestors reduced accordingly.
+///
+/// Paths that no longer exist in the tree are ignored.
+pub fn apply_exclusions(tree: &FileSystemNode, excluded: &[ExcludedItem]) -> FileSystemNode {
+    let paths: Vec<&[String]> = excluded.iter().map(|e| e.path.as_slice()).collect();
+    prune(tree, &paths)
+}
+
+/// Recursively copies `node`, dropping children whose path (relative to `node`) is in `paths`.
+fn prune(node: &FileSystemNode, paths: &[&[String]]) -> FileSystemNode {
+    if paths.is_empty() {
+        return node.clone();
+    }
+
+    let mut removed_size = 0;
+    let mut removed_allocated = 0;
+    let mut children = Vec::with_capacity(node.children.len());
+    for child in &node.children {
+        // Collect the exclusions that lie at or below this child.
+        let mut child_paths = Vec::new();
+        let mut excluded_here = false;
+        for path in paths {
+            if path.first() == Some(&child.name) {
+                if path.len() == 1 {
+                    excluded_here = true;
+                } else {
+                    child_paths.push(&path[1..]);
+                }
+            }
+        }
+
+        if excluded_here {
+            removed_size += child.size;
+            removed_allocated += child.allocated;
+            continue;
+        }
+        let pruned = prune(child, &child_paths);
+        removed_size += child.size - pruned.size;
+        removed_allocated += child.allocated - pruned.allocated;
+        children.push(pruned);
+    }
+
+    FileSystemNode {
+        name: node.name.clone(),
+        size: node.size - removed_size,
+        allocated: node.allocated - removed_allocated,
+        children,
+        special: node.special,
+    }
+}
+
+/// Draws the list of glob patterns every scan skips, as edited in the settings.
+pub fn show_settings(ui: &mut egui::Ui, patterns: &mut Vec<String>) {
+    let mut remove = None;
+    for (i, pattern) in patterns.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(pattern).hint_text("Path or pattern").desired_width(240.0));
+            if ui.small_button("Remove").clicked() {
+                remove = Some(i);
+            }
+        });
+    }
+    if let Some(i) = remove {
+        patterns.remove(i);
+    }
+    if ui.button("Add pattern").clicked() {
+        patterns.push(String::new());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(name: &str, size: u64) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children: vec![], special: None }
+    }
+
+    fn path(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_apply_exclusions_reaggregates_ancestors() {
+        let tree = FileSystemNode {
+            name: "root".to_string(),
+            size: 100,
+            allocated: 100,
+            children: vec![
+                FileSystemNode {
+                    name: "home".to_string(),
+                    size: 90,
+                    allocated: 90,
+                    children: vec![leaf("backups", 70), leaf("notes.txt", 20)],
+                    special: None,
+                },
+                leaf("etc", 10),
+            ],
+            special: None,
+        };
+
+        let excluded = vec![ExcludedItem { path: path(&["home", "backups"]), size: 70 }];
+        let view = apply_exclusions(&tree, &excluded);
+
+        assert_eq!(view.size, 30);
+        let home = view.find(&path(&["home"])).unwrap();
+        assert_eq!(home.size, 20);
+        assert_eq!(home.children, vec![leaf("notes.txt", 20)]);
+        assert!(view.find(&path(&["home", "backups"])).is_none());
+
+        // Restoring everything gives back the original tree.
+        assert_eq!(apply_exclusions(&tree, &[]), tree);
+    }
+}
@@ -0,0 +1,341 @@
+//! This module formats values such as byte counts for display.
+//!
+//! How sizes are written is a setting: in binary (KiB, MiB) or decimal (KB, MB) units, with
+//! the digits grouped and the decimal point written as in the user's locale. The settings
+//! are applied here once for the whole app, so every view and export writes sizes the same
+//! way without being told how.
+//!
+//! Times are written as how long ago they were, e.g. `3 months ago`, with the date and time
+//! on hover, or the other way around if the settings say so.
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// The binary unit suffixes, from bytes up to pebibytes.
+const BINARY_UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+/// The decimal unit suffixes, from bytes up to petabytes.
+const DECIMAL_UNITS: [&str; 6] = ["B", "KB", "MB", "GB", "TB", "PB"];
+
+/// The style sizes are written in by the running app.
+static STYLE: RwLock<Style> = RwLock::new(Style::PLAIN);
+/// Whether sizes shown in the panels give the exact number of bytes on hover.
+static EXACT_ON_HOVER: AtomicBool = AtomicBool::new(false);
+/// Whether times are written as dates rather than as how long ago they were.
+static ABSOLUTE_TIMES: AtomicBool = AtomicBool::new(false);
+
+/// The units sizes are written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SizeUnits {
+    /// Powers of 1024: KiB, MiB, GiB.
+    #[default]
+    Binary,
+    /// Powers of 1000: KB, MB, GB, as drive makers count.
+    Decimal,
+}
+
+/// How the digits of numbers are grouped, and what the decimal point is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NumberStyle {
+    /// As the system's locale writes them.
+    #[default]
+    Automatic,
+    /// Without grouping: 1234567.5
+    Plain,
+    /// 1,234,567.5
+    Comma,
+    /// 1.234.567,5
+    Period,
+    /// 1 234 567,5
+    Space,
+    /// 1'234'567.5
+    Apostrophe,
+}
+
+impl NumberStyle {
+    /// Every style, in the order they are offered.
+    pub const ALL: [NumberStyle; 6] = [
+        NumberStyle::Automatic,
+        NumberStyle::Plain,
+        NumberStyle::Comma,
+        NumberStyle::Period,
+        NumberStyle::Space,
+        NumberStyle::Apostrophe,
+    ];
+
+    /// The name of the style, as shown in the settings.
+    pub fn label(self) -> &'static str {
+        match self {
+            NumberStyle::Automatic => "As the system's locale",
+            NumberStyle::Plain => "1234567.5",
+            NumberStyle::Comma => "1,234,567.5",
+            NumberStyle::Period => "1.234.567,5",
+            NumberStyle::Space => "1 234 567,5",
+            NumberStyle::Apostrophe => "1'234'567.5",
+        }
+    }
+
+    /// The style of numbers in `locale`, e.g. `de_DE.UTF-8` or `fr-CA`.
+    pub fn for_locale(locale: &str) -> NumberStyle {
+        let tag = locale.split(['.', '@']).next().unwrap_or_default();
+        let (language, region) = tag.split_once(['_', '-']).unwrap_or((tag, ""));
+        match (language, region) {
+            ("C" | "POSIX", _) => NumberStyle::Plain,
+            ("de" | "it" | "fr", "CH") | (_, "LI") => NumberStyle::Apostrophe,
+            ("de" | "nl" | "da" | "it" | "es" | "pt" | "id" | "tr" | "el" | "ro" | "hr" | "sl" | "sr", _) => NumberStyle::Period,
+            (
+                "fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "nb" | "nn" | "no" | "fi" | "uk" | "hu" | "bg" | "lt" | "lv" | "et",
+                _,
+            ) => NumberStyle::Space,
+            _ => NumberStyle::Comma,
+        }
+    }
+
+    /// The style of numbers in the system's locale, as the environment gives it.
+    fn system() -> NumberStyle {
+        let locale = ["LC_ALL", "LC_NUMERIC", "LANG"].iter().find_map(|v| std::env::var(v).ok().filter(|l| !l.is_empty()));
+        locale.map_or(NumberStyle::Comma, |l| NumberStyle::for_locale(&l))
+    }
+
+    /// The character grouping the thousands, if any, and the decimal point.
+    fn separators(self) -> (Option<char>, char) {
+        match self {
+            NumberStyle::Automatic => NumberStyle::system().separators(),
+            NumberStyle::Plain => (None, '.'),
+            NumberStyle::Comma => (Some(','), '.'),
+            NumberStyle::Period => (Some('.'), ','),
+            NumberStyle::Space => (Some(' '), ','),
+            NumberStyle::Apostrophe => (Some('\''), '.'),
+        }
+    }
+}
+
+/// The user's preferences for writing sizes and numbers.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FormatSettings {
+    /// Binary or decimal units.
+    pub units: SizeUnits,
+    /// How digits are grouped, and the decimal point.
+    pub numbers: NumberStyle,
+    /// Whether sizes in the panels give the exact number of bytes on hover.
+    pub exact_on_hover: bool,
+    /// Whether times are written as dates, with how long ago they were on hover.
+    pub absolute_times: bool,
+}
+
+impl FormatSettings {
+    /// Draws the settings. Returns whether they changed.
+    pub fn show_settings(&mut self, ui: &mut egui::Ui) -> bool {
+        let before = self.clone();
+        ui.horizontal(|ui| {
+            ui.label("Units");
+            ui.radio_value(&mut self.units, SizeUnits::Binary, "Binary (KiB, MiB)");
+            ui.radio_value(&mut self.units, SizeUnits::Decimal, "Decimal (KB, MB)");
+        });
+        egui::ComboBox::from_label("Numbers").selected_text(self.numbers.label()).show_ui(ui, |ui| {
+            for style in NumberStyle::ALL {
+                ui.selectable_value(&mut self.numbers, style, style.label());
+            }
+        });
+        ui.checkbox(&mut self.exact_on_hover, "Show the exact number of bytes on hover");
+        ui.checkbox(&mut self.absolute_times, "Show dates and times instead of how long ago");
+        *self != before
+    }
+}
+
+/// A resolved way of writing sizes and numbers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Style {
+    units: SizeUnits,
+    grouping: Option<char>,
+    decimal_point: char,
+}
+
+impl Style {
+    /// The style sizes were written in before it was a setting.
+    const PLAIN: Style = Style { units: SizeUnits::Binary, grouping: None, decimal_point: '.' };
+
+    fn new(settings: &FormatSettings) -> Self {
+        let (grouping, decimal_point) = settings.numbers.separators();
+        Self { units: settings.units, grouping, decimal_point }
+    }
+
+    /// Writes `n` with its digits grouped.
+    fn count(self, n: u64) -> String {
+        let digits = n.to_string();
+        let Some(separator) = self.grouping else {
+            return digits;
+        };
+        let mut grouped = String::with_capacity(digits.len() * 4 / 3);
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                grouped.push(separator);
+            }
+            grouped.push(digit);
+        }
+        grouped
+    }
+
+    /// Writes a byte count in the largest unit it fills, to a tenth.
+    fn size(self, bytes: u64) -> String {
+        let (base, units) = match self.units {
+            SizeUnits::Binary => (1024.0, BINARY_UNITS),
+            SizeUnits::Decimal => (1000.0, DECIMAL_UNITS),
+        };
+        let mut value = bytes as f64;
+        let mut unit = 0;
+        while value >= base && unit < units.len() - 1 {
+            value /= base;
+            unit += 1;
+        }
+        if unit == 0 {
+            return format!("{} {}", self.count(bytes), units[0]);
+        }
+        let tenths = (value * 10.0).round() as u64;
+        format!("{}{}{} {}", self.count(tenths / 10), self.decimal_point, tenths % 10, units[unit])
+    }
+}
+
+/// Makes the app write sizes and numbers as `settings` say.
+pub fn set(settings: &FormatSettings) {
+    *STYLE.write().unwrap_or_else(|e| e.into_inner()) = Style::new(settings);
+    EXACT_ON_HOVER.store(settings.exact_on_hover, Ordering::Relaxed);
+    ABSOLUTE_TIMES.store(settings.absolute_times, Ordering::Relaxed);
+}
+
+fn style() -> Style {
+    *STYLE.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Formats a byte count as a short human-readable string, e.g. `1.5 GiB`.
+pub fn format_size(bytes: u64) -> String {
+    style().size(bytes)
+}
+
+/// Formats a number with its digits grouped, e.g. `12,345`.
+pub fn format_count(n: u64) -> String {
+    style().count(n)
+}
+
+/// Formats a byte count exactly, e.g. `1,610,612,736 bytes`.
+pub fn format_exact(bytes: u64) -> String {
+    format!("{} {}", format_count(bytes), if bytes == 1 { "byte" } else { "bytes" })
+}
+
+/// Shows a byte count as a label, with the exact number of bytes on hover if the settings
+/// ask for it.
+pub fn size_label(ui: &mut egui::Ui, bytes: u64) -> egui::Response {
+    let response = ui.label(format_size(bytes));
+    if EXACT_ON_HOVER.load(Ordering::Relaxed) { response.on_hover_text(format_exact(bytes)) } else { response }
+}
+
+/// Formats how long ago something happened, e.g. `5 minutes ago`.
+pub fn format_age(age: Duration) -> String {
+    let minutes = age.as_secs() / 60;
+    match minutes {
+        0 => "less than a minute ago".to_string(),
+        1 => "1 minute ago".to_string(),
+        2..60 => format!("{} minutes ago", minutes),
+        60..120 => "1 hour ago".to_string(),
+        120..2880 => format!("{} hours ago", minutes / 60),
+        2880..86400 => format!("{} days ago", minutes / 1440),
+        86400..525600 => format!("{} months ago", minutes / 43200),
+        _ => match minutes / 525600 {
+            1 => "1 year ago".to_string(),
+            years => format!("{} years ago", years),
+        },
+    }
+}
+
+/// Formats a time given in seconds since the Unix epoch as an ISO 8601 date and time in
+/// UTC, e.g. `2024-03-05T14:02:11Z`.
+pub fn format_timestamp(time: u64) -> String {
+    let (days, seconds) = (time / 86400, time % 86400);
+    // Howard Hinnant's days_from_civil, run backwards, over eras of 400 years.
+    let z = days + 719468;
+    let (era, day_of_era) = (z / 146097, z % 146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+/// Formats `time`, in seconds since the Unix epoch, as the settings say: how long before
+/// `now` it was, or its date and time.
+pub fn format_time(time: u64, now: u64) -> String {
+    let (shown, _) = time_and_hover(time, now);
+    shown
+}
+
+/// Shows `time`, in seconds since the Unix epoch, as a label, with the way of writing it
+/// that isn't shown on hover.
+pub fn time_label(ui: &mut egui::Ui, time: u64, now: u64) -> egui::Response {
+    let (shown, hover) = time_and_hover(time, now);
+    ui.label(shown).on_hover_text(hover)
+}
+
+/// `time` written as the settings say, and the other way.
+pub fn time_and_hover(time: u64, now: u64) -> (String, String) {
+    let relative = format_age(Duration::from_secs(now.saturating_sub(time)));
+    let absolute = format_timestamp(time);
+    if ABSOLUTE_TIMES.load(Ordering::Relaxed) { (absolute, relative) } else { (relative, absolute) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 << 30), "3.0 GiB");
+
+        // The app's style is shared by the tests running at the same time, so the settings
+        // are tried on styles of their own.
+        let style = |units, numbers| Style::new(&FormatSettings { units, numbers, ..Default::default() });
+        let german = style(SizeUnits::Decimal, NumberStyle::Period);
+        assert_eq!(german.size(1536), "1,5 KB");
+        assert_eq!(german.size(999), "999 B");
+        assert_eq!(german.count(1_234_567), "1.234.567");
+        let english = style(SizeUnits::Binary, NumberStyle::Comma);
+        assert_eq!(english.size(1023), "1,023 B");
+        assert_eq!(english.size(1023 << 20), "1,023.0 MiB");
+        assert_eq!(english.count(123), "123");
+        assert_eq!(style(SizeUnits::Binary, NumberStyle::Plain).count(1_234_567), "1234567");
+    }
+
+    #[test]
+    fn test_number_style_for_locale() {
+        assert_eq!(NumberStyle::for_locale("de_DE.UTF-8"), NumberStyle::Period);
+        assert_eq!(NumberStyle::for_locale("de_CH.UTF-8"), NumberStyle::Apostrophe);
+        assert_eq!(NumberStyle::for_locale("fr-CA"), NumberStyle::Space);
+        assert_eq!(NumberStyle::for_locale("en_US.UTF-8"), NumberStyle::Comma);
+        assert_eq!(NumberStyle::for_locale("C"), NumberStyle::Plain);
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::from_secs(59)), "less than a minute ago");
+        assert_eq!(format_age(Duration::from_secs(5 * 60)), "5 minutes ago");
+        assert_eq!(format_age(Duration::from_secs(3 * 3600)), "3 hours ago");
+        assert_eq!(format_age(Duration::from_secs(4 * 86400)), "4 days ago");
+        assert_eq!(format_age(Duration::from_secs(95 * 86400)), "3 months ago");
+        assert_eq!(format_age(Duration::from_secs(400 * 86400)), "1 year ago");
+        assert_eq!(format_age(Duration::from_secs(800 * 86400)), "2 years ago");
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+}
@@ -0,0 +1,209 @@
+//! This module finds the games installed in a scan by Steam, the Epic Games Launcher and
+//! GOG, so players can see which games take up the most space. Titles come from the
+//! stores' own files:
+//!
+//! * Steam keeps an `appmanifest_<id>.acf` per game in `steamapps`, naming the game and
+//!   its folder in `steamapps/common`.
+//! * Epic marks game folders with an `.egstore` folder, and the launcher keeps a manifest
+//!   per game in its data folder, naming the game and where it is installed.
+//! * GOG puts a `goggame-<id>.info` file naming the game in each game folder.
+//!
+//! Games whose title can't be found are listed by their folder name.
+
+use crate::scanner::{FileSystemNode, SizeMetric};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A store games are installed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Store {
+    Steam,
+    Epic,
+    Gog,
+}
+
+impl Store {
+    /// The name of the store, as shown to the user.
+    pub fn label(self) -> &'static str {
+        match self {
+            Store::Steam => "Steam",
+            Store::Epic => "Epic",
+            Store::Gog => "GOG",
+        }
+    }
+}
+
+/// An installed game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Game {
+    /// The title of the game.
+    pub title: String,
+    /// The store it was installed from.
+    pub store: Store,
+    /// The path of its folder from the scan root.
+    pub path: Vec<String>,
+    /// The size of its folder.
+    pub size: u64,
+}
+
+/// Returns the games in `tree`, the scan of `root`, largest first, sized by `metric`.
+pub fn find(root: &Path, tree: &FileSystemNode, metric: SizeMetric) -> Vec<Game> {
+    let epic_titles = epic_launcher_titles();
+    let mut games = Vec::new();
+    find_in(root, tree, metric, &epic_titles, &mut Vec::new(), &mut games);
+    games.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    games
+}
+
+/// Looks for games below `node`, which is at `path` from the scan root `root`.
+fn find_in(
+    root: &Path,
+    node: &FileSystemNode,
+    metric: SizeMetric,
+    epic_titles: &HashMap<String, String>,
+    path: &mut Vec<String>,
+    games: &mut Vec<Game>,
+) {
+    let on_disk = path.iter().fold(root.to_path_buf(), |dir, name| dir.join(name));
+    if node.name == "steamapps"
+        && let Some(common) = node.children.iter().find(|c| c.name == "common")
+    {
+        let titles = steam_titles(&on_disk, node);
+        for game in common.children.iter().filter(|c| !c.children.is_empty()) {
+            let mut game_path = path.clone();
+            game_path.extend(["common".to_string(), game.name.clone()]);
+            let title = titles.get(&game.name).cloned().unwrap_or_else(|| game.name.clone());
+            games.push(Game { title, store: Store::Steam, path: game_path, size: game.size_by(metric) });
+        }
+        return;
+    }
+    for child in node.children.iter().filter(|c| !c.children.is_empty()) {
+        path.push(child.name.clone());
+        let gog_info = child.children.iter().find(|c| c.name.starts_with("goggame-") && c.name.ends_with(".info"));
+        let game = if child.children.iter().any(|c| c.name == ".egstore") {
+            Some((Store::Epic, epic_titles.get(&child.name).cloned()))
+        } else {
+            gog_info.map(|info| (Store::Gog, gog_title(&on_disk.join(&child.name).join(&info.name))))
+        };
+        match game {
+            Some((store, title)) => games.push(Game {
+                title: title.unwrap_or_else(|| child.name.clone()),
+                store,
+                path: path.clone(),
+                size: child.size_by(metric),
+            }),
+            None => find_in(root, child, metric, epic_titles, path, games),
+        }
+        path.pop();
+    }
+}
+
+/// Reads the Steam app manifests in the `steamapps` folder at `dir`, whose scan is `node`.
+/// Returns the titles of the games by the names of their folders in `common`.
+fn steam_titles(dir: &Path, node: &FileSystemNode) -> HashMap<String, String> {
+    node.children
+        .iter()
+        .filter(|c| c.name.starts_with("appmanifest_") && c.name.ends_with(".acf"))
+        .filter_map(|c| fs::read_to_string(dir.join(&c.name)).ok())
+        .filter_map(|manifest| {
+            let values = acf_values(&manifest);
+            Some((values.get("installdir")?.clone(), values.get("name")?.clone()))
+        })
+        .collect()
+}
+
+/// Returns the quoted key-value pairs of a Steam `.acf` file, such as `"name" "Portal 2"`.
+/// Nesting is ignored; the first value of a key wins.
+fn acf_values(text: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    for line in text.lines() {
+        let quoted: Vec<&str> = line.split('"').skip(1).step_by(2).collect();
+        if let [key, value] = quoted[..] {
+            values.entry(key.to_ascii_lowercase()).or_insert_with(|| value.replace("\\\\", "\\"));
+        }
+    }
+    values
+}
+
+/// Reads the title of a GOG game from its `goggame-<id>.info` file.
+fn gog_title(info: &Path) -> Option<String> {
+    let value: serde_json::Value = serde_json::from_slice(&fs::read(info).ok()?).ok()?;
+    Some(value.get("name")?.as_str()?.to_string())
+}
+
+/// The folders the Epic Games Launcher keeps its manifests of installed games in.
+fn epic_manifest_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(program_data) = std::env::var_os("ProgramData") {
+        dirs.push(PathBuf::from(program_data).join("Epic/EpicGamesLauncher/Data/Manifests"));
+    }
+    if let Some(home) = std::env::var_os("HOME") {
+        dirs.push(PathBuf::from(home).join("Library/Application Support/Epic/EpicGamesLauncher/Data/Manifests"));
+    }
+    dirs
+}
+
+/// Reads the Epic Games Launcher's manifests. Returns the titles of the installed games by
+/// the names of their folders.
+fn epic_launcher_titles() -> HashMap<String, String> {
+    let mut titles = HashMap::new();
+    for dir in epic_manifest_dirs() {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten().filter(|e| e.path().extension().is_some_and(|ext| ext == "item")) {
+            let Some(value) = fs::read(entry.path()).ok().and_then(|b| serde_json::from_slice::<serde_json::Value>(&b).ok())
+            else {
+                continue;
+            };
+            let field = |name: &str| value.get(name).and_then(|v| v.as_str()).map(str::to_string);
+            if let (Some(title), Some(location)) = (field("DisplayName"), field("InstallLocation"))
+                && let Some(folder) = Path::new(&location.replace('\\', "/")).file_name()
+            {
+                titles.insert(folder.to_string_lossy().into_owned(), title);
+            }
+        }
+    }
+    titles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{self, ScanOptions};
+
+    #[test]
+    fn test_games_are_found_with_their_titles() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let steam = root.join("Steam/steamapps");
+        fs::create_dir_all(steam.join("common/Portal 2")).unwrap();
+        fs::write(steam.join("common/Portal 2/portal2.exe"), [0u8; 300]).unwrap();
+        fs::create_dir_all(steam.join("common/Orphan")).unwrap();
+        fs::write(steam.join("common/Orphan/data.pak"), [0u8; 100]).unwrap();
+        fs::write(
+            steam.join("appmanifest_620.acf"),
+            "\"AppState\"\n{\n\t\"appid\"\t\t\"620\"\n\t\"name\"\t\t\"Portal 2\"\n\t\"installdir\"\t\t\"Portal 2\"\n}\n",
+        )
+        .unwrap();
+        let gog = root.join("GOG Games/Witcher");
+        fs::create_dir_all(&gog).unwrap();
+        fs::write(gog.join("goggame-1207664643.info"), r#"{"gameId": "1207664643", "name": "The Witcher"}"#).unwrap();
+        fs::write(gog.join("witcher.exe"), [0u8; 200]).unwrap();
+        let epic = root.join("Epic Games/Fortnite");
+        fs::create_dir_all(epic.join(".egstore")).unwrap();
+        fs::write(epic.join(".egstore/x.mancpn"), b"{}").unwrap();
+        fs::write(epic.join("game.pak"), [0u8; 50]).unwrap();
+
+        let tree = scanner::build_tree(root, &ScanOptions::default()).unwrap();
+        let games = find(root, &tree, SizeMetric::Apparent);
+        let found: Vec<(&str, Store)> = games.iter().map(|g| (g.title.as_str(), g.store)).collect();
+        assert_eq!(
+            found,
+            [("Portal 2", Store::Steam), ("The Witcher", Store::Gog), ("Orphan", Store::Steam), ("Fortnite", Store::Epic)]
+        );
+        assert_eq!(games[0].path, ["Steam", "steamapps", "common", "Portal 2"]);
+        assert_eq!(games[0].size, 300);
+    }
+}
@@ -0,0 +1,266 @@
+//! This module tells which parts of a scan inside a git repository belong to the
+//! repository: files git tracks, untracked files, files git ignores (such as `target/` or
+//! `node_modules/`), and git's own data in `.git`. The information comes from the `git`
+//! command, so only repositories that contain the scan root are recognized, not ones
+//! nested below it.
+
+use crate::scanner::{FileSystemNode, SizeMetric};
+use eframe::egui;
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+/// How git sees a file or folder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GitClass {
+    /// Tracked by git, or a folder holding tracked files.
+    Tracked,
+    /// Neither tracked nor ignored.
+    Untracked,
+    /// Matched by an ignore rule.
+    Ignored,
+    /// Git's own data: the `.git` folder.
+    Internal,
+}
+
+impl GitClass {
+    /// All classes, in the order they are listed in.
+    pub const ALL: [GitClass; 4] = [GitClass::Tracked, GitClass::Untracked, GitClass::Ignored, GitClass::Internal];
+
+    /// The name of the class, as shown to the user.
+    pub fn label(self) -> &'static str {
+        match self {
+            GitClass::Tracked => "Tracked",
+            GitClass::Untracked => "Untracked",
+            GitClass::Ignored => "Ignored",
+            GitClass::Internal => "Git data",
+        }
+    }
+
+    /// The color nodes of the class are drawn in.
+    pub fn color(self) -> egui::Color32 {
+        match self {
+            GitClass::Tracked => egui::Color32::from_rgb(70, 130, 180),
+            GitClass::Untracked => egui::Color32::from_rgb(220, 170, 60),
+            GitClass::Ignored => egui::Color32::from_rgb(200, 80, 80),
+            GitClass::Internal => egui::Color32::from_gray(110),
+        }
+    }
+}
+
+/// What git knows about the files below a scan root. Paths are from the scan root,
+/// joined with `/`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GitStatus {
+    /// The tracked files, and the folders holding them.
+    tracked: HashSet<String>,
+    /// The untracked files, and folders holding nothing but untracked files.
+    untracked: HashSet<String>,
+    /// The ignored files and folders.
+    ignored: HashSet<String>,
+}
+
+impl GitStatus {
+    /// Asks git about the files below `root`. Returns `None` if `root` isn't in a git
+    /// repository (or git isn't installed).
+    pub fn read(root: &Path) -> io::Result<Option<Self>> {
+        let inside = Command::new("git").arg("-C").arg(root).args(["rev-parse", "--is-inside-work-tree"]).output();
+        match inside {
+            Ok(output) if output.status.success() && output.stdout.starts_with(b"true") => {}
+            Ok(_) => return Ok(None),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let ls_files = |args: &[&str]| -> io::Result<Vec<u8>> {
+            let output = Command::new("git").arg("-C").arg(root).args(["ls-files", "-z"]).args(args).output()?;
+            if !output.status.success() {
+                return Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+            }
+            Ok(output.stdout)
+        };
+        let tracked = ls_files(&[])?;
+        let untracked = ls_files(&["--others", "--exclude-standard", "--directory"])?;
+        let ignored = ls_files(&["--others", "--ignored", "--exclude-standard", "--directory"])?;
+        Ok(Some(Self::from_listings(&tracked, &untracked, &ignored)))
+    }
+
+    /// Builds the status from the NUL-separated output of `git ls-files` for the tracked,
+    /// untracked and ignored files. Folders are listed with a trailing `/`.
+    pub fn from_listings(tracked: &[u8], untracked: &[u8], ignored: &[u8]) -> Self {
+        let paths = |listing: &[u8]| -> Vec<String> {
+            listing
+                .split(|&b| b == 0)
+                .filter(|p| !p.is_empty())
+                .map(|p| String::from_utf8_lossy(p).trim_end_matches('/').to_string())
+                .collect()
+        };
+        let mut status = Self {
+            tracked: HashSet::new(),
+            untracked: paths(untracked).into_iter().collect(),
+            ignored: paths(ignored).into_iter().collect(),
+        };
+        for file in paths(tracked) {
+            // The folders leading to a tracked file count as tracked too.
+            let mut end = 0;
+            while let Some(slash) = file[end..].find('/') {
+                end += slash;
+                status.tracked.insert(file[..end].to_string());
+                end += 1;
+            }
+            status.tracked.insert(file);
+        }
+        status
+    }
+
+    /// Classifies the file or folder at `path` from the scan root, and tells whether the
+    /// class is inherited by everything below it (as it is for untracked and ignored
+    /// folders, but not for tracked folders, which may hold anything).
+    pub fn classify(&self, path: &[String]) -> (GitClass, bool) {
+        if path.first().is_some_and(|name| name == ".git") {
+            return (GitClass::Internal, true);
+        }
+        let joined = path.join("/");
+        if self.tracked.contains(&joined) {
+            return (GitClass::Tracked, false);
+        }
+        // A folder listed as ignored or untracked covers everything in it.
+        let mut prefix = String::new();
+        for name in path {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(name);
+            if self.ignored.contains(&prefix) {
+                return (GitClass::Ignored, true);
+            }
+            if self.untracked.contains(&prefix) {
+                return (GitClass::Untracked, true);
+            }
+        }
+        // Git doesn't list everything, e.g. empty folders.
+        (GitClass::Untracked, true)
+    }
+
+    /// Returns how much of `tree` each class takes up, in the order of `GitClass::ALL`.
+    pub fn totals(&self, tree: &FileSystemNode, metric: SizeMetric) -> [u64; 4] {
+        let mut totals = [0; 4];
+        let mut stack: Vec<(Vec<String>, &FileSystemNode)> =
+            tree.children.iter().map(|c| (vec![c.name.clone()], c)).collect();
+        while let Some((path, node)) = stack.pop() {
+            let (class, inherited) = self.classify(&path);
+            if inherited || node.children.is_empty() {
+                totals[class as usize] += node.size_by(metric);
+                continue;
+            }
+            // Whatever the folder's own entry takes up beyond its children is tracked.
+            let children: u64 = node.children.iter().map(|c| c.size_by(metric)).sum();
+            totals[class as usize] += node.size_by(metric).saturating_sub(children);
+            for child in &node.children {
+                let mut child_path = path.clone();
+                child_path.push(child.name.clone());
+                stack.push((child_path, child));
+            }
+        }
+        totals
+    }
+
+    /// Returns a copy of `tree` keeping only what is of `class`, with the sizes of the
+    /// folders reduced to what is left in them.
+    pub fn filter(&self, tree: &FileSystemNode, class: GitClass) -> FileSystemNode {
+        let mut path = Vec::new();
+        let children = tree.children.iter().filter_map(|c| self.filter_node(c, class, &mut path)).collect();
+        sum_children(tree, children)
+    }
+
+    /// Filters `node`, whose parent is at `path`. Returns `None` if nothing of `class` is left.
+    fn filter_node(&self, node: &FileSystemNode, class: GitClass, path: &mut Vec<String>) -> Option<FileSystemNode> {
+        path.push(node.name.clone());
+        let (node_class, inherited) = self.classify(path);
+        let filtered = if inherited || node.children.is_empty() {
+            (node_class == class).then(|| node.clone())
+        } else {
+            let children: Vec<_> = node.children.iter().filter_map(|c| self.filter_node(c, class, path)).collect();
+            (!children.is_empty()).then(|| sum_children(node, children))
+        };
+        path.pop();
+        filtered
+    }
+}
+
+/// Draws a legend of the classes in the bottom-left corner of `area`, in place of the depth legend.
+pub fn show_legend(ui: &egui::Ui, area: egui::Rect) {
+    const ROW: f32 = 16.0;
+    let size = egui::vec2(110.0, ROW * GitClass::ALL.len() as f32 + 8.0);
+    let legend = egui::Rect::from_min_size(egui::pos2(area.min.x + 12.0, area.max.y - 12.0 - size.y), size);
+    let painter = ui.painter().with_clip_rect(legend);
+    painter.rect_filled(legend, 3.0, egui::Color32::from_black_alpha(180));
+    for (i, class) in GitClass::ALL.into_iter().enumerate() {
+        let top_left = legend.min + egui::vec2(4.0, 4.0 + ROW * i as f32);
+        painter.rect_filled(egui::Rect::from_min_size(top_left, egui::vec2(12.0, 12.0)), 0.0, class.color());
+        painter.text(
+            top_left + egui::vec2(18.0, 6.0),
+            egui::Align2::LEFT_CENTER,
+            class.label(),
+            egui::FontId::proportional(11.0),
+            egui::Color32::from_gray(220),
+        );
+    }
+}
+
+/// Returns `node` with `children` in place of its own, sized as their sum.
+pub fn sum_children(node: &FileSystemNode, children: Vec<FileSystemNode>) -> FileSystemNode {
+    FileSystemNode {
+        name: node.name.clone(),
+        size: children.iter().map(|c| c.size).sum(),
+        allocated: children.iter().map(|c| c.allocated).sum(),
+        children,
+        special: node.special,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, size: u64, children: Vec<FileSystemNode>) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    fn path(path: &str) -> Vec<String> {
+        path.split('/').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_entries_are_classified_by_git() {
+        let status = GitStatus::from_listings(
+            b"Cargo.toml\0src/main.rs\0",
+            b"notes.txt\0scratch/\0",
+            b"target/\0src/main.rs.orig\0",
+        );
+        assert_eq!(status.classify(&path("src/main.rs")), (GitClass::Tracked, false));
+        assert_eq!(status.classify(&path("src")), (GitClass::Tracked, false));
+        assert_eq!(status.classify(&path("target/debug/app")), (GitClass::Ignored, true));
+        assert_eq!(status.classify(&path("scratch/a/b")), (GitClass::Untracked, true));
+        assert_eq!(status.classify(&path(".git/objects")), (GitClass::Internal, true));
+
+        let tree = node(
+            "repo",
+            175,
+            vec![
+                node("Cargo.toml", 1, vec![]),
+                node("src", 12, vec![node("main.rs", 10, vec![]), node("main.rs.orig", 2, vec![])]),
+                node("target", 100, vec![node("debug", 100, vec![node("app", 100, vec![])])]),
+                node("notes.txt", 2, vec![]),
+                node(".git", 60, vec![]),
+            ],
+        );
+        assert_eq!(status.totals(&tree, SizeMetric::Apparent), [11, 2, 102, 60]);
+
+        let ignored = status.filter(&tree, GitClass::Ignored);
+        assert_eq!(ignored.size, 102);
+        let names: Vec<&str> = ignored.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["src", "target"]);
+        assert_eq!(ignored.children[0].children[0].name, "main.rs.orig");
+    }
+}
@@ -0,0 +1,116 @@
+//! This module finds groups of hard links: paths that are different names for the same file.
+//! Deleting one of them frees no space as long as another remains, which the analysis panel
+//! explains by listing each group with all of its paths found in the scan.
+
+use crate::scanner::FileSystemNode;
+use std::path::Path;
+
+/// The paths in a scan that all refer to the same file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HardlinkGroup {
+    /// The inode number of the file.
+    pub inode: u64,
+    /// The total number of links to the file, including ones outside the scan.
+    pub link_count: u64,
+    /// The size of the file in bytes. It takes up this space only once.
+    pub size: u64,
+    /// The paths of the links found in the scan, relative to the scan root and joined with `/`.
+    pub paths: Vec<String>,
+}
+
+impl HardlinkGroup {
+    /// Whether some of the links are outside the scanned folder, so that deleting every
+    /// path in the scan still wouldn't free the space.
+    pub fn has_links_elsewhere(&self) -> bool {
+        (self.paths.len() as u64) < self.link_count
+    }
+}
+
+/// Looks up every file in `tree`, which was scanned from `root`, and groups the ones with
+/// more than one link by the file they refer to. The largest files come first.
+#[cfg(unix)]
+pub fn find_groups(root: &Path, tree: &FileSystemNode) -> Vec<HardlinkGroup> {
+    use std::collections::HashMap;
+    use std::os::unix::fs::MetadataExt;
+
+    let mut groups: HashMap<(u64, u64), HardlinkGroup> = HashMap::new();
+    let mut files = Vec::new();
+    collect_files(tree, &mut Vec::new(), &mut files);
+    for relative in files {
+        let path = root.join(relative.join("/"));
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        if !metadata.is_file() || metadata.nlink() < 2 {
+            continue;
+        }
+        groups
+            .entry((metadata.dev(), metadata.ino()))
+            .or_insert_with(|| HardlinkGroup {
+                inode: metadata.ino(),
+                link_count: metadata.nlink(),
+                size: metadata.len(),
+                paths: Vec::new(),
+            })
+            .paths
+            .push(relative.join("/"));
+    }
+
+    let mut groups: Vec<HardlinkGroup> = groups.into_values().collect();
+    for group in &mut groups {
+        group.paths.sort();
+    }
+    groups.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.paths.cmp(&b.paths)));
+    groups
+}
+
+/// Hard links can't be detected on this platform, so no groups are found.
+#[cfg(not(unix))]
+pub fn find_groups(_root: &Path, _tree: &FileSystemNode) -> Vec<HardlinkGroup> {
+    Vec::new()
+}
+
+/// Collects the paths (from the root of the tree) of every leaf node below `node`.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn collect_files(node: &FileSystemNode, prefix: &mut Vec<String>, files: &mut Vec<Vec<String>>) {
+    for child in &node.children {
+        prefix.push(child.name.clone());
+        if child.children.is_empty() {
+            files.push(prefix.clone());
+        } else {
+            collect_files(child, prefix, files);
+        }
+        prefix.pop();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::scanner::{self, ScanOptions};
+    use std::fs;
+
+    #[test]
+    fn test_find_groups_and_dedup_disk_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("sub")).unwrap();
+        fs::write(root.join("original.bin"), [0u8; 8192]).unwrap();
+        fs::hard_link(root.join("original.bin"), root.join("sub").join("link.bin")).unwrap();
+        fs::write(root.join("single.txt"), b"hello").unwrap();
+
+        let tree = scanner::build_tree(root, &ScanOptions::default()).unwrap();
+        let groups = find_groups(root, &tree);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].link_count, 2);
+        assert_eq!(groups[0].size, 8192);
+        assert_eq!(groups[0].paths, ["original.bin", "sub/link.bin"]);
+        assert!(!groups[0].has_links_elsewhere());
+
+        // Both links count towards the apparent size, but the disk space is only counted once.
+        let original = tree.find(&["original.bin".to_string()]).unwrap();
+        let link = tree.find(&["sub".to_string(), "link.bin".to_string()]).unwrap();
+        assert_eq!(original.size + link.size, 2 * 8192);
+        assert_eq!(original.allocated.min(link.allocated), 0);
+    }
+}
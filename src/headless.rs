@@ -0,0 +1,248 @@
+//! This module scans a folder without opening a window (`--headless <PATH>`), for scripts
+//! and monitoring checks. The scan flags apply to it, but the app's settings don't.
+//!
+//! What a script can rely on is the exit code:
+//!
+//! * 0: the scan finished and every entry was read.
+//! * 1: the scan couldn't run, e.g. because of a wrong flag or an unreadable root.
+//! * 2: the scan finished, but some entries couldn't be read and aren't in the sizes.
+//! * 3: the path doesn't exist.
+//! * 4: the scan was cancelled, by Ctrl+C, a request to terminate such as SIGTERM, or because
+//!   it took longer than `--timeout`.
+//!
+//! and, with `--porcelain`, the output: one record per line, its fields separated by tabs,
+//! the first naming what the line is. Sizes are in bytes and numbers are never grouped, whatever
+//! the locale. Tabs, line breaks, and backslashes in paths are written as `\t`, `\n`, and `\\`.
+//!
+//! * `total <bytes> <files> <folders> <path>`: the whole scan.
+//! * `item <bytes> <path>`: an item at the top of the scan, the largest first.
+//! * `errors <count> <kind>`: how many entries couldn't be read for one reason, such as
+//!   `permission-denied`.
+//!
+//! Lines of other kinds may be added later, so scripts should skip those they don't know. The
+//! output without `--porcelain` is meant for people: it writes sizes and numbers as the
+//! locale does and may change between versions. `--quiet` writes nothing to stdout. Entries
+//! that couldn't be read, and why a scan couldn't run, are written to stderr either way.
+
+use crate::error::ScanError;
+use crate::format::{self, FormatSettings, format_count, format_size};
+use crate::scanner::{self, FileSystemNode, ScanOptions};
+use crate::stats::ScanStats;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// How a headless scan ended, as its exit code says.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    Ok = 0,
+    Failed = 1,
+    Partial = 2,
+    NotFound = 3,
+    Cancelled = 4,
+}
+
+impl Exit {
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+/// What a headless scan writes, and how long it may take.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeadlessOptions {
+    /// Whether to write nothing to stdout.
+    pub quiet: bool,
+    /// Whether to write the stable, machine-readable output.
+    pub porcelain: bool,
+    /// How many of the items at the top of the scan to list.
+    pub top: usize,
+    /// How long the scan may take before it is cancelled.
+    pub timeout: Option<Duration>,
+}
+
+/// The flag that cancels the scan, for the handler of interruptions to set.
+static INTERRUPT: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+/// Sets `cancel` when the process is interrupted with Ctrl+C or asked to terminate, so the scan
+/// stops and the process exits as a cancelled scan does. A second interruption ends the process
+/// at once, in case the scan is stuck on a drive that stopped answering.
+pub fn cancel_on_interrupt(cancel: Arc<AtomicBool>) {
+    if INTERRUPT.set(cancel).is_ok() {
+        install_interrupt_handler();
+    }
+}
+
+#[cfg(unix)]
+fn install_interrupt_handler() {
+    extern "C" fn handle(_signal: libc::c_int) {
+        if INTERRUPT.get().is_some_and(|cancel| cancel.swap(true, Ordering::Relaxed)) {
+            // SAFETY: unlike `exit`, `_exit` may be called in a signal handler.
+            unsafe { libc::_exit(Exit::Cancelled.code()) };
+        }
+    }
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: the handler only sets an atomic flag, or calls `_exit`.
+        unsafe { libc::signal(signal, handle as extern "C" fn(libc::c_int) as libc::sighandler_t) };
+    }
+}
+
+#[cfg(windows)]
+fn install_interrupt_handler() {
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn SetConsoleCtrlHandler(handler: Option<unsafe extern "system" fn(u32) -> i32>, add: i32) -> i32;
+    }
+    /// Windows calls this on a thread of its own. Returning 0 hands the event on to the default
+    /// handler, which ends the process.
+    unsafe extern "system" fn handle(_event: u32) -> i32 {
+        i32::from(!INTERRUPT.get().is_some_and(|cancel| cancel.swap(true, Ordering::Relaxed)))
+    }
+    // SAFETY: the handler lives as long as the process and only touches an atomic flag.
+    unsafe { SetConsoleCtrlHandler(Some(handle), 1) };
+}
+
+/// Interruptions can't be caught on this platform, so they end the process as usual.
+#[cfg(not(any(unix, windows)))]
+fn install_interrupt_handler() {}
+
+/// Scans `path` with `options`, writing the result to `out` and problems to `err`. Setting
+/// `cancel` stops the scan.
+pub fn run(
+    path: &Path,
+    options: &ScanOptions,
+    headless: &HeadlessOptions,
+    cancel: &Arc<AtomicBool>,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> Exit {
+    let (done, finished) = mpsc::channel::<()>();
+    if let Some(timeout) = headless.timeout {
+        let cancel = Arc::clone(cancel);
+        thread::spawn(move || {
+            if finished.recv_timeout(timeout) == Err(mpsc::RecvTimeoutError::Timeout) {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        });
+    }
+    let result = scanner::build_tree_with_progress(path, options, &|_| {}, cancel, None);
+    drop(done);
+    let (tree, stats) = match result {
+        Ok(scanned) => scanned,
+        Err(e) => {
+            let _ = writeln!(err, "Failed to scan {}: {}", path.display(), e);
+            return match e {
+                ScanError::NotFound(_) => Exit::NotFound,
+                ScanError::Cancelled => Exit::Cancelled,
+                _ => Exit::Failed,
+            };
+        }
+    };
+    if !headless.quiet {
+        let written = if headless.porcelain {
+            write_porcelain(path, &tree, &stats, options, headless.top, out)
+        } else {
+            write_summary(path, &tree, &stats, options, headless.top, out, err)
+        };
+        if let Err(e) = written {
+            let _ = writeln!(err, "Failed to write the result: {}", e);
+            return Exit::Failed;
+        }
+    }
+    if stats.error_count() > 0 || stats.volume_lost { Exit::Partial } else { Exit::Ok }
+}
+
+/// The items at the top of `tree`, the largest first, at most `top` of them.
+fn largest<'a>(tree: &'a FileSystemNode, options: &ScanOptions, top: usize) -> Vec<&'a FileSystemNode> {
+    let mut items: Vec<&FileSystemNode> = tree.children.iter().collect();
+    items.sort_by(|a, b| b.size_by(options.metric).cmp(&a.size_by(options.metric)).then_with(|| a.name.cmp(&b.name)));
+    items.truncate(top);
+    items
+}
+
+/// Writes the result as the `--porcelain` contract in the module documentation describes.
+fn write_porcelain(path: &Path, tree: &FileSystemNode, stats: &ScanStats, options: &ScanOptions, top: usize, out: &mut dyn Write) -> io::Result<()> {
+    let size = tree.size_by(options.metric);
+    writeln!(out, "total\t{}\t{}\t{}\t{}", size, stats.files, stats.directories, escape(&path.to_string_lossy()))?;
+    for item in largest(tree, options, top) {
+        writeln!(out, "item\t{}\t{}", item.size_by(options.metric), escape(&path.join(&item.name).to_string_lossy()))?;
+    }
+    for (category, count) in &stats.errors {
+        writeln!(out, "errors\t{}\t{}", count, category.to_lowercase().replace(' ', "-"))?;
+    }
+    Ok(())
+}
+
+/// Writes the result for people, in the units and number style of the locale, with what
+/// couldn't be read written to `err`.
+fn write_summary(
+    path: &Path,
+    tree: &FileSystemNode,
+    stats: &ScanStats,
+    options: &ScanOptions,
+    top: usize,
+    out: &mut dyn Write,
+    err: &mut dyn Write,
+) -> io::Result<()> {
+    format::set(&FormatSettings::default());
+    let files = if stats.files == 1 { "1 file".to_string() } else { format!("{} files", format_count(stats.files)) };
+    let folders = if stats.directories == 1 { "1 folder".to_string() } else { format!("{} folders", format_count(stats.directories)) };
+    writeln!(out, "{}: {} in {} and {}", path.display(), format_size(tree.size_by(options.metric)), files, folders)?;
+    for item in largest(tree, options, top) {
+        writeln!(out, "{:>12}  {}", format_size(item.size_by(options.metric)), item.name)?;
+    }
+    if stats.error_count() > 0 {
+        let reasons: Vec<String> = stats.errors.iter().map(|(category, count)| format!("{}: {}", category, format_count(*count))).collect();
+        writeln!(err, "{} entries couldn't be read and aren't counted ({}).", format_count(stats.error_count()), reasons.join(", "))?;
+    }
+    if stats.volume_lost {
+        writeln!(err, "The volume went away during the scan, so it only holds what was read before.")?;
+    }
+    Ok(())
+}
+
+/// Escapes the tabs, line breaks, and backslashes in `path`, so it fits on one line and field.
+fn escape(path: &str) -> String {
+    path.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn run_to_strings(path: &Path, headless: &HeadlessOptions) -> (Exit, String, String) {
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        let exit = run(path, &ScanOptions::default(), headless, &Arc::new(AtomicBool::new(false)), &mut out, &mut err);
+        (exit, String::from_utf8(out).unwrap(), String::from_utf8(err).unwrap())
+    }
+
+    #[test]
+    fn test_porcelain_output_and_exit_codes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("videos")).unwrap();
+        fs::write(dir.path().join("videos").join("a.mp4"), vec![0; 3000]).unwrap();
+        fs::write(dir.path().join("notes.txt"), vec![0; 100]).unwrap();
+        let porcelain = HeadlessOptions { quiet: false, porcelain: true, top: 10, timeout: None };
+
+        let (exit, out, err) = run_to_strings(dir.path(), &porcelain);
+        let path = |name: &str| escape(&dir.path().join(name).to_string_lossy());
+        assert_eq!((exit, err.as_str()), (Exit::Ok, ""));
+        assert_eq!(
+            out.lines().collect::<Vec<_>>(),
+            [format!("total\t3100\t2\t2\t{}", escape(&dir.path().to_string_lossy())), format!("item\t3000\t{}", path("videos")), format!("item\t100\t{}", path("notes.txt"))]
+        );
+        assert_eq!(escape("a\tb\nc\\d"), "a\\tb\\nc\\\\d");
+
+        let quiet = HeadlessOptions { quiet: true, ..porcelain };
+        let (exit, out, err) = run_to_strings(&dir.path().join("missing"), &quiet);
+        assert_eq!((exit.code(), out.as_str()), (3, ""));
+        assert!(err.starts_with("Failed to scan"));
+        let (exit, out, _) = run_to_strings(dir.path(), &quiet);
+        assert_eq!((exit, out.as_str()), (Exit::Ok, ""));
+    }
+}
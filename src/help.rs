@@ -0,0 +1,39 @@
+//! This module holds the help shown from the "Help" menu: how to use the treemap with the
+//! mouse and keyboard, and what the app is.
+
+use crate::tooltip;
+use eframe::egui;
+
+/// What each mouse and keyboard control does in the treemap.
+pub fn controls() -> [(&'static str, &'static str); 11] {
+    [
+        ("Click", "Select an item and show its details"),
+        ("Double-click", "Open a folder, or a file with its default application"),
+        ("Middle-click", "Go up one level"),
+        ("Right-click", "Open the menu of an item, e.g. to exclude or tick it"),
+        ("Arrow keys", "Select the next or previous item, by size"),
+        ("Enter", "Open the selected item"),
+        ("Backspace", "Go up one level"),
+        ("Space", "Tick the selected item, to add it up with others"),
+        (tooltip::PIN_KEY.name(), "Pin the tooltip of the item under the pointer"),
+        ("Hold Alt", "Magnify the treemap under the pointer in a lens"),
+        ("Ctrl+Plus, Ctrl+Minus, Ctrl+0", "Make the interface larger, smaller, or the default size"),
+    ]
+}
+
+/// Draws the controls as a table.
+pub fn show_controls(ui: &mut egui::Ui) {
+    egui::Grid::new("controls").striped(true).show(ui, |ui| {
+        for (control, action) in controls() {
+            ui.strong(control);
+            ui.label(action);
+            ui.end_row();
+        }
+    });
+}
+
+/// Draws what the app is and its version.
+pub fn show_about(ui: &mut egui::Ui) {
+    ui.heading(format!("Disk Scout {}", env!("CARGO_PKG_VERSION")));
+    ui.label("A visual disk space analyzer: scan a folder and see what takes up the space as a treemap.");
+}
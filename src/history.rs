@@ -0,0 +1,322 @@
+//! This module keeps a history of the scans of a folder, so any earlier point in time can
+//! be opened again. To keep the history small, most entries only store what changed since
+//! the entry before them: the nodes that were added or removed, and the new sizes of the
+//! nodes whose sizes changed. Every `FULL_EVERY` entries a full snapshot is stored, which
+//! bounds how many deltas have to be replayed to reconstruct an entry.
+//!
+//! Entries are files named `<index>-<unix time>.full.json` or `<index>-<unix time>.delta.json`
+//! in the history folder. Reconstructed trees don't keep the order of children (which no
+//! view depends on), nor file hashes.
+
+use crate::scanner::{self, FileSystemNode};
+use crate::snapshot::{self, Snapshot, SnapshotHeader};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// How often a full snapshot is stored instead of a delta.
+const FULL_EVERY: u32 = 10;
+
+/// One difference between two trees. Paths are from the scan root.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    /// A node appeared, with everything below it.
+    Added { path: Vec<String>, node: FileSystemNode },
+    /// A node disappeared, with everything below it.
+    Removed { path: Vec<String> },
+    /// The sizes of a node changed.
+    Resized { path: Vec<String>, size: u64, allocated: u64 },
+}
+
+/// An entry stored as the changes since the previous entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Delta {
+    header: SnapshotHeader,
+    root: PathBuf,
+    changes: Vec<Change>,
+}
+
+/// Returns the changes that turn `old` into `new`.
+pub fn diff(old: &FileSystemNode, new: &FileSystemNode) -> Vec<Change> {
+    let mut changes = Vec::new();
+    diff_nodes(old, new, &mut Vec::new(), &mut changes);
+    changes
+}
+
+fn diff_nodes(old: &FileSystemNode, new: &FileSystemNode, path: &mut Vec<String>, changes: &mut Vec<Change>) {
+    if old.size != new.size || old.allocated != new.allocated {
+        changes.push(Change::Resized { path: path.clone(), size: new.size, allocated: new.allocated });
+    }
+    for (new_child, old_child) in scanner::pair_by_name(&new.children, &old.children) {
+        let Some(name) = new_child.or(old_child).map(|c| c.name.clone()) else {
+            continue;
+        };
+        path.push(name);
+        match (new_child, old_child) {
+            (Some(child), Some(old_child)) => diff_nodes(old_child, child, path, changes),
+            (Some(child), None) => changes.push(Change::Added { path: path.clone(), node: child.clone() }),
+            _ => changes.push(Change::Removed { path: path.clone() }),
+        }
+        path.pop();
+    }
+}
+
+/// Applies `changes` to `tree`. Fails if a change refers to a node that isn't there.
+///
+/// The changes are grouped by the folder they are in, so each folder is looked up and its
+/// items matched up once, however many of them changed.
+pub fn apply(tree: &mut FileSystemNode, changes: &[Change]) -> Result<(), String> {
+    let mut groups: Vec<(&[String], Vec<&Change>)> = Vec::new();
+    let mut group_of: HashMap<&[String], usize> = HashMap::new();
+    for change in changes {
+        let path = match change {
+            Change::Added { path, .. } | Change::Removed { path } | Change::Resized { path, .. } => path,
+        };
+        let Some((_, parent)) = path.split_last() else {
+            match change {
+                Change::Resized { size, allocated, .. } => (tree.size, tree.allocated) = (*size, *allocated),
+                _ => return Err("The scan root can't be added or removed".to_string()),
+            }
+            continue;
+        };
+        let group = *group_of.entry(parent).or_insert_with(|| {
+            groups.push((parent, Vec::new()));
+            groups.len() - 1
+        });
+        groups[group].1.push(change);
+    }
+    for (parent_path, group) in groups {
+        let parent = find_mut(tree, parent_path)?;
+        let name = |path: &[String]| path.last().cloned().unwrap_or_default();
+        let replaced: HashSet<String> = group
+            .iter()
+            .filter_map(|change| match change {
+                Change::Added { path, .. } | Change::Removed { path } => Some(name(path)),
+                Change::Resized { .. } => None,
+            })
+            .collect();
+        if !replaced.is_empty() {
+            parent.children.retain(|c| !replaced.contains(&c.name));
+        }
+        let mut resized = Vec::new();
+        for change in group {
+            match change {
+                Change::Added { node, .. } => parent.children.push(node.clone()),
+                Change::Resized { path, size, allocated } => resized.push((name(path), *size, *allocated)),
+                Change::Removed { .. } => {}
+            }
+        }
+        if resized.is_empty() {
+            continue;
+        }
+        let positions: HashMap<String, usize> = parent.children.iter().enumerate().map(|(i, c)| (c.name.clone(), i)).collect();
+        for (name, size, allocated) in resized {
+            let Some(&i) = positions.get(&name) else {
+                return Err(format!("{} isn't in the tree", [parent_path, &[name]].concat().join("/")));
+            };
+            (parent.children[i].size, parent.children[i].allocated) = (size, allocated);
+        }
+    }
+    Ok(())
+}
+
+/// Finds the node at `path` below `tree`.
+fn find_mut<'a>(tree: &'a mut FileSystemNode, path: &[String]) -> Result<&'a mut FileSystemNode, String> {
+    let mut node = tree;
+    for name in path {
+        node = node
+            .children
+            .iter_mut()
+            .find(|c| c.name == *name)
+            .ok_or_else(|| format!("{} isn't in the tree", path.join("/")))?;
+    }
+    Ok(node)
+}
+
+/// An entry of a history.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    /// The position of the entry in the history, counting from 1.
+    pub index: u32,
+    /// When the scan was made, in seconds since the Unix epoch.
+    pub created_at: u64,
+    /// Whether the entry is a full snapshot rather than a delta.
+    pub full: bool,
+    /// The file the entry is stored in.
+    pub path: PathBuf,
+}
+
+impl HistoryEntry {
+    /// Parses the name of an entry's file.
+    fn from_path(path: PathBuf) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        let (stem, full) = match name.strip_suffix(".full.json") {
+            Some(stem) => (stem, true),
+            None => (name.strip_suffix(".delta.json")?, false),
+        };
+        let (index, created_at) = stem.split_once('-')?;
+        Some(Self { index: index.parse().ok()?, created_at: created_at.parse().ok()?, full, path })
+    }
+}
+
+/// The history of the scans of one folder, stored in a folder of its own.
+pub struct History {
+    dir: PathBuf,
+}
+
+impl History {
+    /// The history stored in `dir`.
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The history of the scans of `root`, in the app's data folder.
+    pub fn for_root(root: &Path) -> Option<Self> {
+        let key = blake3::hash(root.to_string_lossy().as_bytes()).to_hex();
+        let dir = eframe::storage_dir("Disk Scout")?.join("history").join(&key.as_str()[..16]);
+        Some(Self::new(dir))
+    }
+
+    /// The entries, oldest first. A history that was never written to has none.
+    pub fn entries(&self) -> io::Result<Vec<HistoryEntry>> {
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut entries = Vec::new();
+        for dir_entry in read_dir {
+            entries.extend(HistoryEntry::from_path(dir_entry?.path()));
+        }
+        entries.sort_by_key(|e| e.index);
+        Ok(entries)
+    }
+
+    /// Adds `snapshot` to the history, as a delta from the latest entry where possible.
+    pub fn append(&self, snapshot: &Snapshot) -> io::Result<HistoryEntry> {
+        let entries = self.entries()?;
+        let index = entries.last().map_or(1, |e| e.index + 1);
+        let last_full = entries.iter().rev().find(|e| e.full).map(|e| e.index);
+        let full = last_full.is_none_or(|full| index - full >= FULL_EVERY);
+        let kind = if full { "full" } else { "delta" };
+        let path = self.dir.join(format!("{:06}-{}.{}.json", index, snapshot.header.created_at, kind));
+        fs::create_dir_all(&self.dir)?;
+        if full {
+            snapshot::save(&path, &Snapshot { hashes: None, ..snapshot.clone() })?;
+        } else {
+            let previous = self.load(index - 1)?;
+            let delta = Delta {
+                header: snapshot.header.clone(),
+                root: snapshot.root.clone(),
+                changes: diff(&previous.tree, &snapshot.tree),
+            };
+            let writer = BufWriter::new(File::create(&path)?);
+            serde_json::to_writer(writer, &delta).map_err(io::Error::other)?;
+        }
+        Ok(HistoryEntry { index, created_at: snapshot.header.created_at, full, path })
+    }
+
+    /// Reconstructs the scan stored as entry `index`.
+    pub fn load(&self, index: u32) -> io::Result<Snapshot> {
+        let entries = self.entries()?;
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let start = entries
+            .iter()
+            .rposition(|e| e.full && e.index <= index)
+            .ok_or_else(|| invalid(format!("No full snapshot before entry {}", index)))?;
+        let mut snapshot = snapshot::from_value(serde_json::from_slice(&fs::read(&entries[start].path)?)?)?;
+        for entry in entries[start + 1..].iter().take_while(|e| e.index <= index) {
+            if entry.full {
+                return Err(invalid(format!("Entry {} should have been the starting point", entry.index)));
+            }
+            let delta: Delta = serde_json::from_slice(&fs::read(&entry.path)?)?;
+            apply(&mut snapshot.tree, &delta.changes)
+                .map_err(|e| invalid(format!("Entry {} doesn't fit the one before it: {}", entry.index, e)))?;
+            snapshot.header = delta.header;
+            snapshot.root = delta.root;
+        }
+        if entries.iter().all(|e| e.index != index) {
+            return Err(invalid(format!("There is no entry {}", index)));
+        }
+        Ok(snapshot)
+    }
+
+    /// Reconstructs the latest scan made at or before `time`, in seconds since the Unix
+    /// epoch, if there is one.
+    pub fn load_latest_before(&self, time: u64) -> io::Result<Option<Snapshot>> {
+        match self.entries()?.into_iter().rev().find(|e| e.created_at <= time) {
+            Some(entry) => self.load(entry.index).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Adds the scan of `root` that produced `tree`, made as `header` says, to the history of `root`.
+pub fn record(root: PathBuf, tree: &FileSystemNode, header: SnapshotHeader) -> Result<HistoryEntry, String> {
+    let history = History::for_root(&root).ok_or("There is nowhere to keep a history on this system")?;
+    let snapshot = Snapshot { header, ..Snapshot::new(root, tree.clone()) };
+    history.append(&snapshot).map_err(|e| format!("Failed to add the scan to the history: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, size: u64, children: Vec<FileSystemNode>) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    /// Sorts the children of every node by name, since reconstruction doesn't keep their order.
+    fn sorted(mut tree: FileSystemNode) -> FileSystemNode {
+        tree.children = tree.children.into_iter().map(sorted).collect();
+        tree.children.sort_by(|a, b| a.name.cmp(&b.name));
+        tree
+    }
+
+    #[test]
+    fn test_history_reconstructs_every_entry_from_deltas() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::new(dir.path().join("history"));
+        assert!(history.entries().unwrap().is_empty());
+
+        // Each scan grows one file, and every other one adds or removes a folder.
+        let scans: Vec<Snapshot> = (0..12u64)
+            .map(|i| {
+                let mut children = vec![node("log.txt", 10 + i, vec![]), node("docs", 5, vec![node("a", 5, vec![])])];
+                if i % 2 == 1 {
+                    children.push(node("tmp", i, vec![node("x", i, vec![])]));
+                }
+                let size = children.iter().map(|c| c.size).sum();
+                Snapshot::new(PathBuf::from("/data"), node("data", size, children))
+            })
+            .collect();
+        for scan in &scans {
+            history.append(scan).unwrap();
+        }
+
+        let entries = history.entries().unwrap();
+        assert_eq!(entries.len(), 12);
+        let full: Vec<u32> = entries.iter().filter(|e| e.full).map(|e| e.index).collect();
+        assert_eq!(full, [1, 11]);
+        // A delta only holds what changed.
+        let delta: Delta = serde_json::from_slice(&fs::read(&entries[1].path).unwrap()).unwrap();
+        assert_eq!(delta.changes.len(), 3);
+        assert!(delta.changes.contains(&Change::Resized { path: vec![], size: 17, allocated: 17 }));
+        assert!(delta.changes.iter().any(|c| matches!(c, Change::Added { path, .. } if path == &["tmp"])));
+        let delta: Delta = serde_json::from_slice(&fs::read(&entries[2].path).unwrap()).unwrap();
+        assert!(delta.changes.contains(&Change::Removed { path: vec!["tmp".to_string()] }));
+
+        for (entry, scan) in entries.iter().zip(&scans) {
+            let loaded = history.load(entry.index).unwrap();
+            assert_eq!(sorted(loaded.tree), sorted(scan.tree.clone()), "entry {}", entry.index);
+        }
+        assert!(history.load(13).is_err());
+
+        let mut tree = scans[0].tree.clone();
+        assert!(apply(&mut tree, &[Change::Resized { path: vec!["docs".to_string(), "b".to_string()], size: 1, allocated: 1 }]).is_err());
+        assert!(apply(&mut tree, &[Change::Removed { path: vec![] }]).is_err());
+    }
+}
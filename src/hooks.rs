@@ -0,0 +1,340 @@
+//! This module runs commands configured by the user after a scan finishes, so Disk Scout
+//! can feed other tools, e.g. post a report to a chat or prune old backups. Each command
+//! is run by the shell in the scanned folder and receives a JSON summary of the scan on
+//! its standard input.
+//!
+//! Commands can also be run before and after items are moved to the trash, receiving the
+//! paths of the items, e.g. to back them up first or to log what was deleted. A command run
+//! before that fails cancels the deletion.
+
+use crate::budgets::Violation;
+use crate::read_only;
+use crate::scanner::FileSystemNode;
+use crate::stats::ScanStats;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// The name of the event a summary is sent for, in its `event` field.
+pub const SCAN_FINISHED: &str = "scan_finished";
+/// The event of items about to be moved to the trash.
+pub const BEFORE_DELETE: &str = "before_delete";
+/// The event of items moved to the trash.
+pub const AFTER_DELETE: &str = "after_delete";
+/// How many of the largest items of the scanned folder a summary lists.
+const LARGEST_COUNT: usize = 10;
+
+/// A command to run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hook {
+    /// The command line, run by `sh -c` (or `cmd /C` on Windows).
+    pub command: String,
+    /// Whether the hook is run. Disabled hooks are kept so they can be turned back on.
+    pub enabled: bool,
+}
+
+/// The user's hooks.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookSettings {
+    /// The hooks run after every successful scan.
+    pub after_scan: Vec<Hook>,
+    /// The hooks run before items are moved to the trash. If one fails, nothing is.
+    pub before_delete: Vec<Hook>,
+    /// The hooks run after items were moved to the trash.
+    pub after_delete: Vec<Hook>,
+}
+
+impl HookSettings {
+    /// Every hook, enabled or not.
+    pub fn all(&self) -> impl Iterator<Item = &Hook> {
+        self.after_scan.iter().chain(&self.before_delete).chain(&self.after_delete)
+    }
+}
+
+/// An item of the scan, as listed in a summary.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SummaryItem {
+    /// The path of the item from the scan root.
+    pub path: String,
+    /// The apparent size of the item.
+    pub size: u64,
+    /// The budget the item exceeds, if it is listed for that.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget: Option<u64>,
+}
+
+/// What a hook is told about a scan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanSummary {
+    /// What happened, e.g. `scan_finished`.
+    pub event: String,
+    /// The scanned folder.
+    pub root: PathBuf,
+    /// The apparent size of the scanned folder.
+    pub size: u64,
+    /// The disk space the scanned folder takes up.
+    pub allocated: u64,
+    /// The number of files found. For a loaded snapshot, which has no statistics, it is
+    /// counted from the tree, where empty folders can't be told from files and count as files.
+    pub files: u64,
+    /// The number of folders found, not counting the scanned folder itself, and counted from
+    /// the tree like `files` for a loaded snapshot.
+    pub folders: u64,
+    /// The largest items directly in the scanned folder, largest first.
+    pub largest: Vec<SummaryItem>,
+    /// The folders that exceed a space budget.
+    pub over_budget: Vec<SummaryItem>,
+    /// The statistics of the scan, if it was made by the app rather than loaded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<ScanStats>,
+}
+
+impl ScanSummary {
+    /// Summarizes the scan of `root` that produced `tree`, with the statistics of the scan if
+    /// it was made by the app, which tell the numbers of files and folders.
+    pub fn new(root: &Path, tree: &FileSystemNode, violations: &[Violation], stats: Option<ScanStats>) -> Self {
+        let (files, folders) = match &stats {
+            // The scan root is among the directories the scanner counts.
+            Some(stats) => (stats.files, stats.directories.saturating_sub(1)),
+            None => count_items(tree),
+        };
+        let mut largest: Vec<&FileSystemNode> = tree.children.iter().collect();
+        largest.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+        Self {
+            event: SCAN_FINISHED.to_string(),
+            root: root.to_path_buf(),
+            size: tree.size,
+            allocated: tree.allocated,
+            files,
+            folders,
+            largest: largest
+                .into_iter()
+                .take(LARGEST_COUNT)
+                .map(|n| SummaryItem { path: n.name.clone(), size: n.size, budget: None })
+                .collect(),
+            over_budget: violations
+                .iter()
+                .map(|v| SummaryItem { path: v.path.join("/"), size: v.size, budget: Some(v.budget.limit) })
+                .collect(),
+            stats,
+        }
+    }
+}
+
+/// Counts the files and folders below the root of `tree`, taking every item without children
+/// for a file.
+fn count_items(tree: &FileSystemNode) -> (u64, u64) {
+    let (mut files, mut folders) = (0, 0);
+    let mut stack: Vec<&FileSystemNode> = tree.children.iter().collect();
+    while let Some(node) = stack.pop() {
+        if node.children.is_empty() {
+            files += 1;
+        } else {
+            folders += 1;
+            stack.extend(&node.children);
+        }
+    }
+    (files, folders)
+}
+
+/// What a hook is told about items moved to the trash, or about to be.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeletionSummary {
+    /// `before_delete` or `after_delete`.
+    pub event: String,
+    /// The scanned folder.
+    pub root: PathBuf,
+    /// The items, as full paths.
+    pub paths: Vec<PathBuf>,
+}
+
+/// Runs `command` in `dir` with `input` on its standard input, and waits for it to exit.
+/// A command that fails to start or exits unsuccessfully is an error.
+pub fn run(command: &str, dir: &Path, input: &[u8]) -> io::Result<()> {
+    read_only::check("Running hooks")?;
+    let (shell, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let mut child = Command::new(shell)
+        .args([flag, command])
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A command that doesn't read its input closes the pipe early, which is fine.
+        match stdin.write_all(input) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e),
+            _ => {}
+        }
+    }
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(io::Error::other(format!("{} {}", output.status, stderr.trim()).trim_end().to_string()))
+}
+
+/// Runs every enabled hook in `hooks` with `summary`, one after another. Returns a message
+/// for each hook that failed.
+pub fn run_all(hooks: &[Hook], summary: &ScanSummary) -> Vec<String> {
+    match serde_json::to_vec(summary) {
+        Ok(input) => run_each(hooks, &summary.root, &input),
+        Err(e) => vec![format!("Failed to summarize the scan for the hooks: {}", e)],
+    }
+}
+
+/// Runs every enabled hook in `hooks` in `dir` with `input`, one after another. Returns a
+/// message for each hook that failed.
+fn run_each(hooks: &[Hook], dir: &Path, input: &[u8]) -> Vec<String> {
+    hooks
+        .iter()
+        .filter(|h| h.enabled && !h.command.trim().is_empty())
+        .filter_map(|h| run(&h.command, dir, input).err().map(|e| format!("Hook `{}` failed: {}", h.command, e)))
+        .collect()
+}
+
+/// Runs the hooks of `event` in `hooks` for the items at `paths` from the scan root `root`.
+/// Returns a message for each hook that failed.
+fn run_for_deletion(hooks: &[Hook], event: &str, root: &Path, paths: &[Vec<String>]) -> Vec<String> {
+    if !hooks.iter().any(|h| h.enabled) {
+        return Vec::new();
+    }
+    let paths = paths.iter().map(|p| p.iter().fold(root.to_path_buf(), |dir, name| dir.join(name))).collect();
+    let summary = DeletionSummary { event: event.to_string(), root: root.to_path_buf(), paths };
+    match serde_json::to_vec(&summary) {
+        Ok(input) => run_each(hooks, root, &input),
+        Err(e) => vec![format!("Failed to list the items for the hooks: {}", e)],
+    }
+}
+
+/// Deletes the items at `paths` from the scan root `root` with `delete`, which returns the
+/// paths it deleted and a message for each it didn't, running the hooks in `settings` before
+/// and after. If a hook run before fails, nothing is deleted.
+pub fn guard_deletion(
+    settings: &HookSettings,
+    root: &Path,
+    paths: &[Vec<String>],
+    delete: impl FnOnce(&Path, &[Vec<String>]) -> (Vec<Vec<String>>, Vec<String>),
+) -> (Vec<Vec<String>>, Vec<String>) {
+    let mut failures = run_for_deletion(&settings.before_delete, BEFORE_DELETE, root, paths);
+    if !failures.is_empty() {
+        failures.insert(0, "Nothing was moved to the trash, since a hook run before it failed.".to_string());
+        return (Vec::new(), failures);
+    }
+    let (deleted, mut failures) = delete(root, paths);
+    if !deleted.is_empty() {
+        failures.extend(run_for_deletion(&settings.after_delete, AFTER_DELETE, root, &deleted));
+    }
+    (deleted, failures)
+}
+
+/// Draws the hook settings.
+pub fn show_settings(ui: &mut egui::Ui, settings: &mut HookSettings) {
+    ui.label("Commands run in the scanned folder, with a JSON summary of what happened on their standard input.");
+    ui.strong("After each scan");
+    show_list(ui, "after_scan", &mut settings.after_scan);
+    ui.strong("Before moving to the trash");
+    ui.weak("They receive the paths of the items. If one fails, nothing is moved.");
+    show_list(ui, "before_delete", &mut settings.before_delete);
+    ui.strong("After moving to the trash");
+    ui.weak("They receive the paths of the items that were moved.");
+    show_list(ui, "after_delete", &mut settings.after_delete);
+}
+
+/// Draws the hooks of one event, `id` telling the lists apart.
+fn show_list(ui: &mut egui::Ui, id: &str, hooks: &mut Vec<Hook>) {
+    let mut remove = None;
+    for (i, hook) in hooks.iter_mut().enumerate() {
+        ui.push_id((id, i), |ui| ui.horizontal(|ui| {
+            ui.checkbox(&mut hook.enabled, "");
+            ui.add(egui::TextEdit::singleline(&mut hook.command).hint_text("Command").code_editor());
+            if ui.small_button("Remove").clicked() {
+                remove = Some(i);
+            }
+        }));
+    }
+    if let Some(i) = remove {
+        hooks.remove(i);
+    }
+    if ui.push_id(id, |ui| ui.button("Add hook")).inner.clicked() {
+        hooks.push(Hook { command: String::new(), enabled: true });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budgets::Budget;
+
+    fn node(name: &str, size: u64, children: Vec<FileSystemNode>) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hooks_receive_the_summary() {
+        let tree = node(
+            "home",
+            30,
+            vec![node("small.txt", 5, vec![]), node("docs", 25, vec![node("a", 10, vec![]), node("b", 15, vec![])])],
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let violations = vec![Violation {
+            path: vec!["docs".to_string()],
+            size: 25,
+            budget: Budget { pattern: "docs".to_string(), limit: 20 },
+        }];
+        let summary = ScanSummary::new(dir.path(), &tree, &violations, None);
+        assert_eq!((summary.files, summary.folders), (3, 1));
+        // The statistics of a scan know empty folders from files.
+        let stats = ScanStats { files: 2, directories: 3, ..ScanStats::default() };
+        let scanned = ScanSummary::new(dir.path(), &tree, &violations, Some(stats.clone()));
+        assert_eq!((scanned.files, scanned.folders, scanned.stats), (2, 2, Some(stats)));
+        assert_eq!(summary.largest[0], SummaryItem { path: "docs".to_string(), size: 25, budget: None });
+        assert_eq!(summary.over_budget[0].budget, Some(20));
+
+        let hooks = vec![
+            Hook { command: "cat > summary.json".to_string(), enabled: true },
+            Hook { command: "echo broken >&2; exit 3".to_string(), enabled: true },
+            Hook { command: "touch never".to_string(), enabled: false },
+        ];
+        let failures = run_all(&hooks, &summary);
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].contains("broken"), "{}", failures[0]);
+        assert!(!dir.path().join("never").exists());
+
+        let written = std::fs::read(dir.path().join("summary.json")).unwrap();
+        let received: ScanSummary = serde_json::from_slice(&written).unwrap();
+        assert_eq!(received, summary);
+        assert_eq!(received.event, SCAN_FINISHED);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hooks_guard_deletions() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let hook = |command: &str| vec![Hook { command: command.to_string(), enabled: true }];
+        let paths = vec![vec!["target".to_string()], vec!["web".to_string(), "node_modules".to_string()]];
+        let delete = |_: &Path, paths: &[Vec<String>]| (paths[..1].to_vec(), vec!["Failed to move node_modules".to_string()]);
+
+        let settings = HookSettings { before_delete: hook("cat > before.json"), after_delete: hook("cat > after.json"), ..HookSettings::default() };
+        let (deleted, failures) = guard_deletion(&settings, root, &paths, delete);
+        assert_eq!((deleted, failures.len()), (vec![vec!["target".to_string()]], 1));
+        let before: DeletionSummary = serde_json::from_slice(&std::fs::read(root.join("before.json")).unwrap()).unwrap();
+        assert_eq!(before.event, BEFORE_DELETE);
+        assert_eq!(before.paths, [root.join("target"), root.join("web").join("node_modules")]);
+        // Only what was deleted is passed on afterwards.
+        let after: DeletionSummary = serde_json::from_slice(&std::fs::read(root.join("after.json")).unwrap()).unwrap();
+        assert_eq!((after.event.as_str(), after.paths), (AFTER_DELETE, vec![root.join("target")]));
+
+        let settings = HookSettings { before_delete: hook("exit 1"), ..HookSettings::default() };
+        let (deleted, failures) = guard_deletion(&settings, root, &paths, |_, _| panic!("the deletion should be cancelled"));
+        assert!(deleted.is_empty());
+        assert!(failures[0].starts_with("Nothing was moved"));
+    }
+}
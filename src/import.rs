@@ -0,0 +1,255 @@
+//! This module reads scans exported elsewhere: our own snapshots, the JSON export of ncdu
+//! (`ncdu -o`), and line-based listings such as the output of `du -ab`. It is how the web
+//! build gets a tree to show, since a browser can't scan the disk itself, and it lets the
+//! desktop build open scans made on a server, or piped in from any command.
+
+use crate::scanner::FileSystemNode;
+use crate::snapshot::{self, Snapshot};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Parses the contents of an exported scan, which may be a snapshot or an ncdu export.
+pub fn parse_export(bytes: &[u8]) -> Result<Snapshot, String> {
+    let value: Value = serde_json::from_slice(bytes).map_err(|e| format!("Not a JSON file: {e}"))?;
+    // Snapshots are objects and ncdu exports are arrays.
+    if value.is_object() {
+        return snapshot::from_value(value).map_err(|e| e.to_string());
+    }
+    parse_ncdu(&value).ok_or_else(|| "Not a snapshot or an ncdu export.".to_string())
+}
+
+/// Builds a snapshot from a flat list of files, given as `/`-separated paths that all start
+/// with the same root folder, along with their sizes. This is what a browser reveals about a
+/// folder the user picked. Files can't be told apart from their disk usage there, so the
+/// allocated size is taken to be the apparent size.
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn tree_from_files(files: impl IntoIterator<Item = (String, u64)>) -> Option<Snapshot> {
+    let mut root: Option<FileSystemNode> = None;
+    for (path, size) in files {
+        let mut parts = path.split('/').filter(|p| !p.is_empty());
+        let Some(root_name) = parts.next() else {
+            continue;
+        };
+        let mut node = root.get_or_insert_with(|| FileSystemNode {
+            name: root_name.to_string(),
+            size: 0,
+            allocated: 0,
+            children: vec![],
+            special: None,
+        });
+        node.size += size;
+        node.allocated += size;
+        for part in parts {
+            let index = match node.children.iter().position(|c| c.name == part) {
+                Some(index) => index,
+                None => {
+                    node.children.push(FileSystemNode { name: part.to_string(), size: 0, allocated: 0, children: vec![], special: None });
+                    node.children.len() - 1
+                }
+            };
+            node = &mut node.children[index];
+            node.size += size;
+            node.allocated += size;
+        }
+    }
+    root.map(|tree| Snapshot::new(PathBuf::from(&tree.name), tree))
+}
+
+/// The layout of the lines of a listing piped in on stdin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineFormat {
+    /// The output of `du -ab`: a size in bytes, a tab, and a path.
+    Du,
+    /// A path, a tab, and a size in bytes.
+    PathSize,
+}
+
+/// Builds a snapshot from a listing of paths with their sizes in bytes, one per line, such as
+/// `du -ab` prints. Folders may be listed along with their contents, as du does, in which case
+/// their listed size counts whatever the listing leaves out. The root is the folder all paths
+/// are in. Only apparent sizes are listed, so the allocated size is taken to be the same.
+///
+/// Paths aren't always UTF-8, so the listing is read as bytes, and the parts of a path that
+/// aren't valid UTF-8 are replaced by `�` rather than failing the whole listing.
+pub fn parse_lines(bytes: &[u8], format: LineFormat) -> Result<Snapshot, String> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut entries = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let split = match format {
+            LineFormat::Du => line.split_once('\t').map(|(size, path)| (path, size)),
+            LineFormat::PathSize => line.rsplit_once('\t'),
+        };
+        let Some((path, size)) = split else {
+            return Err(format!("Line {} has no tab between the path and the size.", i + 1));
+        };
+        // `du -c` ends with the grand total.
+        if format == LineFormat::Du && path == "total" {
+            continue;
+        }
+        let size = size.trim().parse::<u64>().map_err(|_| format!("Line {} has no size in bytes: {:?}", i + 1, size))?;
+        let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty() && *p != ".").collect();
+        entries.push((path.starts_with('/'), parts, size));
+    }
+    let Some((absolute, first, _)) = entries.first() else {
+        return Err("Nothing was listed.".to_string());
+    };
+    let absolute = *absolute;
+    let mut prefix_len = first.len();
+    for (_, parts, _) in &entries {
+        prefix_len = prefix_len.min(first.iter().zip(parts).take_while(|(a, b)| a == b).count());
+    }
+    let prefix = first[..prefix_len].to_vec();
+    let root_path = match (absolute, prefix.is_empty()) {
+        (true, _) => PathBuf::from(format!("/{}", prefix.join("/"))),
+        (false, true) => PathBuf::from("."),
+        (false, false) => PathBuf::from(prefix.join("/")),
+    };
+    let name = prefix.last().map_or_else(|| root_path.to_string_lossy().into_owned(), |n| n.to_string());
+
+    let mut tree = Listed::new(name);
+    for (_, parts, size) in &entries {
+        let mut node = &mut tree;
+        for part in &parts[prefix_len..] {
+            let index = match node.index.get(*part) {
+                Some(&index) => index,
+                None => {
+                    node.index.insert(part.to_string(), node.children.len());
+                    node.children.push(Listed::new(part.to_string()));
+                    node.children.len() - 1
+                }
+            };
+            node = &mut node.children[index];
+        }
+        node.listed = Some(*size);
+    }
+    Ok(Snapshot::new(root_path, tree.finish()))
+}
+
+/// A node of a listing, with the size listed for it, if any, before the sizes are added up.
+struct Listed {
+    name: String,
+    listed: Option<u64>,
+    children: Vec<Listed>,
+    /// The position of each child in `children` by name, so huge folders are built quickly.
+    index: HashMap<String, usize>,
+}
+
+impl Listed {
+    fn new(name: String) -> Self {
+        Listed { name, listed: None, children: Vec::new(), index: HashMap::new() }
+    }
+
+    /// Adds up the sizes: a node is as large as its contents, or as listed if that is more.
+    fn finish(self) -> FileSystemNode {
+        let children: Vec<FileSystemNode> = self.children.into_iter().map(Listed::finish).collect();
+        let size = children.iter().map(|c| c.size).sum::<u64>().max(self.listed.unwrap_or(0));
+        FileSystemNode { name: self.name, size, allocated: size, children, special: None }
+    }
+}
+
+/// Converts an ncdu export, `[major, minor, {metadata}, [{root}, children...]]`, into a snapshot.
+fn parse_ncdu(value: &Value) -> Option<Snapshot> {
+    let array = value.as_array()?;
+    if array.first()?.as_u64()? != 1 {
+        return None;
+    }
+    let root = array.get(3)?;
+    let root_path = PathBuf::from(root.as_array()?.first()?.get("name")?.as_str()?);
+    let mut tree = ncdu_node(root, &mut HashSet::new())?;
+    // ncdu names the root by its full path; the tree only uses the last component.
+    if let Some(name) = Path::new(&tree.name).file_name() {
+        tree.name = name.to_string_lossy().into_owned();
+    }
+    Some(Snapshot::new(root_path, tree))
+}
+
+/// Converts one ncdu entry: a directory is an array of its own info followed by its
+/// children, and anything else is an object. Hard links (`hlnkc`) only use disk space once.
+fn ncdu_node(value: &Value, seen_links: &mut HashSet<u64>) -> Option<FileSystemNode> {
+    if let Some(entries) = value.as_array() {
+        let (info, children) = entries.split_first()?;
+        let children: Vec<FileSystemNode> = children.iter().filter_map(|c| ncdu_node(c, seen_links)).collect();
+        return Some(FileSystemNode {
+            name: info.get("name")?.as_str()?.to_string(),
+            size: children.iter().map(|c| c.size).sum(),
+            allocated: children.iter().map(|c| c.allocated).sum(),
+            children,
+            special: None,
+        });
+    }
+
+    let size = value.get("asize").and_then(Value::as_u64).unwrap_or(0);
+    let mut allocated = value.get("dsize").and_then(Value::as_u64).unwrap_or(0);
+    let is_link = value.get("hlnkc").and_then(Value::as_bool).unwrap_or(false);
+    if is_link && let Some(ino) = value.get("ino").and_then(Value::as_u64) && !seen_links.insert(ino) {
+        allocated = 0;
+    }
+    Some(FileSystemNode { name: value.get("name")?.as_str()?.to_string(), size, allocated, children: vec![], special: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ncdu_export() {
+        let export = br#"[1, 2, {"progname": "ncdu", "progver": "1.19", "timestamp": 1700000000},
+            [{"name": "/srv/data", "asize": 4096, "dsize": 4096},
+             {"name": "a.log", "asize": 1000, "dsize": 4096},
+             [{"name": "sub", "asize": 4096, "dsize": 4096},
+              {"name": "one", "asize": 500, "dsize": 4096, "ino": 7, "hlnkc": true},
+              {"name": "two", "asize": 500, "dsize": 4096, "ino": 7, "hlnkc": true}]]]"#;
+
+        let snapshot = parse_export(export).unwrap();
+        assert_eq!(snapshot.root, PathBuf::from("/srv/data"));
+        assert_eq!(snapshot.tree.name, "data");
+        assert_eq!(snapshot.tree.size, 2000);
+        // Like the scanner, folders count their contents only, and the second link to the
+        // same inode takes no extra space.
+        assert_eq!(snapshot.tree.allocated, 2 * 4096);
+        let sub = snapshot.tree.find(&["sub".to_string()]).unwrap();
+        assert_eq!(sub.children.len(), 2);
+
+        assert!(parse_export(b"[2, 0]").is_err());
+        assert!(parse_export(b"not json").is_err());
+    }
+
+    #[test]
+    fn test_parse_du_and_path_size_listings() {
+        let du = "4\t/srv/data/a.log\n100\t/srv/data/sub/one\n200\t/srv/data/sub/two\n4396\t/srv/data/sub\n8496\t/srv/data\n8496\ttotal\n";
+        let snapshot = parse_lines(du.as_bytes(), LineFormat::Du).unwrap();
+        assert_eq!(snapshot.root, PathBuf::from("/srv/data"));
+        assert_eq!((snapshot.tree.name.as_str(), snapshot.tree.size), ("data", 8496));
+        // The folders' own sizes, left out of the listing, still count.
+        assert_eq!(snapshot.tree.find(&["sub".to_string()]).unwrap().size, 4396);
+
+        let snapshot = parse_lines(b"./photos/a.jpg\t10\r\n./photos/b.jpg\t20\n./notes\t5\n", LineFormat::PathSize).unwrap();
+        assert_eq!(snapshot.root, PathBuf::from("."));
+        assert_eq!((snapshot.tree.size, snapshot.tree.children.len()), (35, 2));
+        assert!(parse_lines(b"10 /srv", LineFormat::Du).is_err());
+        assert!(parse_lines(b"/srv\tmany", LineFormat::PathSize).is_err());
+        assert!(parse_lines(b"\n", LineFormat::Du).is_err());
+
+        // A name that isn't UTF-8, e.g. in Latin-1, doesn't fail the listing.
+        let snapshot = parse_lines(b"10\t/srv/caf\xe9.txt\n5\t/srv/notes\n", LineFormat::Du).unwrap();
+        let names: Vec<&str> = snapshot.tree.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["caf\u{fffd}.txt", "notes"]);
+        assert_eq!(snapshot.tree.size, 15);
+    }
+
+    #[test]
+    fn test_tree_from_files() {
+        let files = [("photos/a.jpg", 10), ("photos/2024/b.jpg", 20), ("photos/2024/c.jpg", 5)];
+        let snapshot = tree_from_files(files.map(|(p, s)| (p.to_string(), s))).unwrap();
+        assert_eq!(snapshot.root, PathBuf::from("photos"));
+        assert_eq!(snapshot.tree.size, 35);
+        let year = snapshot.tree.find(&["2024".to_string()]).unwrap();
+        assert_eq!((year.size, year.allocated, year.children.len()), (25, 25, 2));
+        assert!(tree_from_files(Vec::new()).is_none());
+    }
+}
@@ -0,0 +1,147 @@
+//! This module records content hashes of scanned files and verifies them later.
+//! Verifying rescans the snapshot's root and reports every file whose content
+//! changed, that was added, or that vanished since the snapshot was taken.
+
+use crate::scanner::{self, FileSystemNode, ScanOptions};
+use crate::snapshot::Snapshot;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// The outcome of verifying a snapshot against the current state of the disk.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VerifyReport {
+    /// Files whose content hash differs from the snapshot.
+    pub changed: Vec<String>,
+    /// Files that exist now but weren't in the snapshot.
+    pub added: Vec<String>,
+    /// Files that were in the snapshot but are gone (or unreadable) now.
+    pub removed: Vec<String>,
+    /// The number of files whose content is unchanged.
+    pub unchanged: usize,
+}
+
+impl VerifyReport {
+    /// Whether the disk matches the snapshot exactly.
+    pub fn is_clean(&self) -> bool {
+        self.changed.is_empty() && self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Hashes the content of the file at `path`, returning the hash as a hex string.
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update_reader(File::open(path)?)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Hashes every file in `tree`, which was scanned from `root`.
+///
+/// The result is keyed by each file's path relative to `root`, joined with `/`.
+/// Files that can't be read are left out.
+pub fn hash_tree(root: &Path, tree: &FileSystemNode) -> BTreeMap<String, String> {
+    let mut hashes = BTreeMap::new();
+    // A root without children may be a single file rather than a directory.
+    if tree.children.is_empty() && root.is_file() {
+        if let Ok(hash) = hash_file(root) {
+            hashes.insert(tree.name.clone(), hash);
+        }
+        return hashes;
+    }
+    let mut relative = Vec::new();
+    hash_children(root, tree, &mut relative, &mut hashes);
+    hashes
+}
+
+fn hash_children(
+    dir: &Path,
+    node: &FileSystemNode,
+    relative: &mut Vec<String>,
+    hashes: &mut BTreeMap<String, String>,
+) {
+    for child in &node.children {
+        let path = dir.join(&child.name);
+        relative.push(child.name.clone());
+        if child.children.is_empty() {
+            // Empty directories have no children either; they simply fail to open as files.
+            if path.is_file() {
+                match hash_file(&path) {
+                    Ok(hash) => {
+                        hashes.insert(relative.join("/"), hash);
+                    }
+                    Err(e) => eprintln!("Failed to hash {}: {}", path.display(), e),
+                }
+            }
+        } else {
+            hash_children(&path, child, relative, hashes);
+        }
+        relative.pop();
+    }
+}
+
+/// Compares the hashes recorded in a snapshot with freshly computed ones.
+pub fn compare(expected: &BTreeMap<String, String>, actual: &BTreeMap<String, String>) -> VerifyReport {
+    let mut report = VerifyReport::default();
+    for (path, hash) in expected {
+        match actual.get(path) {
+            Some(current) if current == hash => report.unchanged += 1,
+            Some(_) => report.changed.push(path.clone()),
+            None => report.removed.push(path.clone()),
+        }
+    }
+    report.added = actual
+        .keys()
+        .filter(|path| !expected.contains_key(*path))
+        .cloned()
+        .collect();
+    report
+}
+
+/// Rescans the root of `snapshot`, hashes every file, and compares the result with the
+/// hashes recorded in the snapshot.
+///
+/// # Returns
+///
+/// The report, or an error if the snapshot has no hashes or the root can't be scanned.
+pub fn verify(snapshot: &Snapshot) -> io::Result<VerifyReport> {
+    let expected = snapshot.hashes.as_ref().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "this snapshot was saved without file hashes")
+    })?;
+    let tree = scanner::build_tree(&snapshot.root, &ScanOptions::default()).map_err(io::Error::other)?;
+    let actual = hash_tree(&snapshot.root, &tree);
+    Ok(compare(expected, &actual))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_verify_reports_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("same.txt"), "same").unwrap();
+        fs::write(root.join("sub").join("edit.txt"), "before").unwrap();
+        fs::write(root.join("gone.txt"), "gone").unwrap();
+
+        let tree = scanner::build_tree(root, &ScanOptions::default()).unwrap();
+        let snapshot = Snapshot { hashes: Some(hash_tree(root, &tree)), ..Snapshot::new(root.to_path_buf(), tree) };
+        assert!(verify(&snapshot).unwrap().is_clean());
+
+        fs::write(root.join("sub").join("edit.txt"), "after!").unwrap();
+        fs::remove_file(root.join("gone.txt")).unwrap();
+        fs::write(root.join("new.txt"), "new").unwrap();
+
+        let report = verify(&snapshot).unwrap();
+        assert_eq!(report.changed, vec!["sub/edit.txt".to_string()]);
+        assert_eq!(report.removed, vec!["gone.txt".to_string()]);
+        assert_eq!(report.added, vec!["new.txt".to_string()]);
+        assert_eq!(report.unchanged, 1);
+
+        let without_hashes = Snapshot { hashes: None, ..snapshot };
+        assert!(verify(&without_hashes).is_err());
+    }
+}
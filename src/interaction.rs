@@ -0,0 +1,228 @@
+//! This module turns pointer input on the treemap into actions. The whole treemap is one
+//! interactive area, and the node under the pointer is found by hit-testing the layout. A
+//! single click selects that node, a double click opens it, a middle click goes up one level,
+//! and a right click (or pressing and holding on a touchscreen) opens a menu of actions for it.
+//!
+//! Hit-testing goes through a `HitIndex`, which walks down the folders containing the point
+//! and binary-searches the contents of each, so it stays fast with hundreds of thousands of
+//! rectangles.
+
+use crate::read_only;
+use crate::treemap::{Rectangle, TreemapNode};
+use eframe::egui;
+
+/// What the user did with the treemap this frame. Paths are relative to the layout root.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PointerAction {
+    /// The node was clicked once.
+    Select(Vec<String>),
+    /// The node was double-clicked.
+    Open(Vec<String>),
+    /// The middle button was clicked.
+    Up,
+    /// "Exclude from view" was picked from the node's context menu.
+    Exclude(Vec<String>),
+    /// "Always exclude this path" was picked from the node's context menu, to hide the node
+    /// and skip it in every scan from now on.
+    AlwaysExclude(Vec<String>),
+    /// The node was ctrl-clicked (cmd-clicked on macOS), or "Tick" was picked from its context
+    /// menu, to add it to or remove it from the ticked nodes.
+    Tick(Vec<String>),
+    /// "Pin to the left pane" was picked from the node's context menu.
+    Pin(Vec<String>),
+    /// "Archive..." was picked from the node's context menu.
+    Archive(Vec<String>),
+}
+
+/// An index over a layout for finding nodes by position or path without going through
+/// every node.
+#[derive(Debug, Clone, Default)]
+pub struct HitIndex {
+    /// The nodes at the top of the layout, in layout order.
+    roots: Vec<usize>,
+    /// The nodes inside each node, in layout order.
+    children: Vec<Vec<usize>>,
+}
+
+impl HitIndex {
+    /// Indexes `layout`, where the contents of each node follow it, one level deeper.
+    pub fn new(layout: &[TreemapNode]) -> Self {
+        let mut index = Self { roots: Vec::new(), children: vec![Vec::new(); layout.len()] };
+        // The nodes from the top of the layout down to the one last visited.
+        let mut ancestors: Vec<usize> = Vec::new();
+        for (i, node) in layout.iter().enumerate() {
+            while ancestors.last().is_some_and(|&a| layout[a].depth >= node.depth) {
+                ancestors.pop();
+            }
+            match ancestors.last() {
+                Some(&parent) => index.children[parent].push(i),
+                None => index.roots.push(i),
+            }
+            ancestors.push(i);
+        }
+        index
+    }
+
+    /// Returns the index of the deepest node in `layout` that contains the point `(x, y)`,
+    /// given in layout coordinates. Nodes narrower or lower than `min_size` can't be hit, so
+    /// the point hits the folder around them.
+    pub fn hit_test(&self, layout: &[TreemapNode], x: f64, y: f64, min_size: f64) -> Option<usize> {
+        let mut hit = None;
+        let mut siblings = &self.roots;
+        while let Some(i) = sibling_at(layout, siblings, x, y) {
+            let rect = &layout[i].rect;
+            // What a node holds is no larger than it, so nothing deeper can be hit either.
+            if rect.width < min_size || rect.height < min_size {
+                break;
+            }
+            hit = Some(i);
+            siblings = &self.children[i];
+        }
+        hit
+    }
+
+    /// Returns the index of the node at `path`, relative to the layout root.
+    pub fn find(&self, layout: &[TreemapNode], path: &[String]) -> Option<usize> {
+        let mut found = None;
+        let mut siblings = &self.roots;
+        for name in path {
+            let i = *siblings.iter().find(|&&i| layout[i].path.last() == Some(name))?;
+            found = Some(i);
+            siblings = &self.children[i];
+        }
+        found
+    }
+}
+
+/// Returns which of `siblings` contains the point. Siblings are laid out side by side in
+/// order, along the axis their positions differ in, so the one to check is found by a
+/// binary search.
+fn sibling_at(layout: &[TreemapNode], siblings: &[usize], x: f64, y: f64) -> Option<usize> {
+    let first = &layout[*siblings.first()?].rect;
+    let last = &layout[*siblings.last()?].rect;
+    let position = if last.x > first.x {
+        siblings.partition_point(|&i| layout[i].rect.x + layout[i].rect.width <= x)
+    } else {
+        siblings.partition_point(|&i| layout[i].rect.y + layout[i].rect.height <= y)
+    };
+    let i = *siblings.get(position)?;
+    let r: &Rectangle = &layout[i].rect;
+    (x >= r.x && x < r.x + r.width && y >= r.y && y < r.y + r.height).then_some(i)
+}
+
+/// Interprets the pointer input on the treemap's `response`, where `hovered` is the path of
+/// the node under the pointer. This also shows the context menu.
+pub fn pointer_action(ui: &egui::Ui, response: &egui::Response, hovered: Option<&[String]>) -> Option<PointerAction> {
+    let mut action = None;
+    if let Some(path) = hovered {
+        // The first click of a double click has selected the node already.
+        if response.double_clicked() {
+            action = Some(PointerAction::Open(path.to_vec()));
+        } else if response.clicked() && ui.input(|i| i.modifiers.command) {
+            action = Some(PointerAction::Tick(path.to_vec()));
+        } else if response.clicked() {
+            action = Some(PointerAction::Select(path.to_vec()));
+        }
+    }
+    if response.middle_clicked() {
+        action = Some(PointerAction::Up);
+    }
+
+    // The pointer moves once the menu is open, so remember which node it was opened on.
+    let menu_id = response.id.with("menu_path");
+    if response.secondary_clicked() {
+        ui.data_mut(|d| d.insert_temp(menu_id, hovered.map(<[String]>::to_vec)));
+    }
+    let menu_path: Option<Vec<String>> = ui.data(|d| d.get_temp(menu_id)).flatten();
+    response.context_menu(|ui| match &menu_path {
+        Some(path) => {
+            if ui.button("Open").clicked() {
+                action = Some(PointerAction::Open(path.clone()));
+                ui.close_menu();
+            }
+            if ui.button("Exclude from view").clicked() {
+                action = Some(PointerAction::Exclude(path.clone()));
+                ui.close_menu();
+            }
+            if ui.button("Always exclude this path").on_hover_text("Hide it, and skip it in every scan from now on").clicked() {
+                action = Some(PointerAction::AlwaysExclude(path.clone()));
+                ui.close_menu();
+            }
+            if ui.button("Tick or untick").on_hover_text("Add up ticked items in the Ticked window").clicked() {
+                action = Some(PointerAction::Tick(path.clone()));
+                ui.close_menu();
+            }
+            if ui.button("Pin to the left pane").on_hover_text("Keep it in view beside the treemap, to compare it with other folders").clicked() {
+                action = Some(PointerAction::Pin(path.clone()));
+                ui.close_menu();
+            }
+            if cfg!(not(target_arch = "wasm32"))
+                && ui
+                    .add_enabled(!read_only::is_enabled(), egui::Button::new("Archive..."))
+                    .on_hover_text("Pack the folder into a .tar.zst or .zip")
+                    .clicked()
+            {
+                action = Some(PointerAction::Archive(path.clone()));
+                ui.close_menu();
+            }
+        }
+        None => {
+            ui.label("Right-click an item for actions.");
+        }
+    });
+    action
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{FileSystemNode, SizeMetric};
+    use crate::treemap;
+
+    fn node(name: &str, size: u64, children: Vec<FileSystemNode>) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    #[test]
+    fn test_hit_index_finds_the_deepest_node() {
+        // "dir" takes the left half, and its two files split it top and bottom.
+        let tree = node(
+            "root",
+            20,
+            vec![node("dir", 10, vec![node("a", 5, vec![]), node("b", 5, vec![])]), node("file", 10, vec![])],
+        );
+        let bounds = Rectangle { x: 0.0, y: 0.0, width: 100.0, height: 100.0 };
+        let layout = treemap::generate_treemap(&tree, bounds, SizeMetric::Apparent);
+        let index = HitIndex::new(&layout);
+        let path_at = |x, y| index.hit_test(&layout, x, y, 1.0).map(|i| layout[i].path.join("/"));
+
+        assert_eq!(path_at(10.0, 10.0).as_deref(), Some("dir/a"));
+        assert_eq!(path_at(10.0, 90.0).as_deref(), Some("dir/b"));
+        assert_eq!(path_at(75.0, 50.0).as_deref(), Some("file"));
+        assert_eq!(path_at(150.0, 50.0), None);
+        // Everything is too small to be hit.
+        assert_eq!(index.hit_test(&layout, 10.0, 10.0, 60.0), None);
+
+        let path = |p: &str| p.split('/').map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(index.find(&layout, &path("dir/b")).map(|i| layout[i].path.join("/")).as_deref(), Some("dir/b"));
+        assert_eq!(index.find(&layout, &path("dir/c")), None);
+    }
+
+    #[test]
+    fn test_hit_index_agrees_with_checking_every_node() {
+        let tree = crate::synthetic::generate(&crate::synthetic::Spec::demo());
+        let bounds = Rectangle { x: 0.0, y: 0.0, width: 800.0, height: 600.0 };
+        let layout = treemap::generate_treemap(&tree, bounds, SizeMetric::Apparent);
+        let index = HitIndex::new(&layout);
+        for min_size in [0.0, 4.0] {
+            for (x, y) in (0..80).flat_map(|i| (0..60).map(move |j| (i as f64 * 10.0 + 0.5, j as f64 * 10.0 + 0.5))) {
+                // Children follow their parent in the layout, so the last node containing the point is the deepest.
+                let expected = layout.iter().rposition(|n| {
+                    let r = &n.rect;
+                    r.width >= min_size && r.height >= min_size && x >= r.x && x < r.x + r.width && y >= r.y && y < r.y + r.height
+                });
+                assert_eq!(index.hit_test(&layout, x, y, min_size), expected, "at ({}, {})", x, y);
+            }
+        }
+    }
+}
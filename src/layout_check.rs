@@ -0,0 +1,152 @@
+//! This module checks a treemap layout against the invariants every layout algorithm must
+//! keep: each item lies within its parent, siblings don't overlap, each item's area is its
+//! share of its parent's by size, and the children of a folder fill it completely. The
+//! property tests hold `generate_treemap` to them for random trees, and the app can outline
+//! the items breaking them, to help when trying out other layout algorithms.
+
+use crate::scanner::{FileSystemNode, SizeMetric};
+use crate::treemap::{Rectangle, TreemapNode};
+use std::collections::HashMap;
+
+/// The tolerance of the comparisons, relative to the area of the whole layout.
+const EPSILON: f64 = 1e-9;
+
+/// An invariant an item of the layout breaks.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// The item sticks out of its parent.
+    OutsideParent,
+    /// The item overlaps the sibling at this path.
+    Overlap(Vec<String>),
+    /// The item's area isn't its share of its parent's. Areas are fractions of the parent's.
+    WrongArea { expected: f64, actual: f64 },
+    /// The children of the folder don't fill it. Areas are fractions of the folder's.
+    Unfilled { filled: f64 },
+}
+
+/// An item of the layout breaking an invariant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// The path of the item from the layout root.
+    pub path: Vec<String>,
+    pub problem: Problem,
+}
+
+fn area(rect: &Rectangle) -> f64 {
+    rect.width * rect.height
+}
+
+fn contains(outer: &Rectangle, inner: &Rectangle, tolerance: f64) -> bool {
+    inner.x >= outer.x - tolerance
+        && inner.y >= outer.y - tolerance
+        && inner.x + inner.width <= outer.x + outer.width + tolerance
+        && inner.y + inner.height <= outer.y + outer.height + tolerance
+}
+
+fn overlap(a: &Rectangle, b: &Rectangle) -> f64 {
+    let width = (a.x + a.width).min(b.x + b.width) - a.x.max(b.x);
+    let height = (a.y + a.height).min(b.y + b.height) - a.y.max(b.y);
+    width.max(0.0) * height.max(0.0)
+}
+
+/// Returns the items of `layout`, generated for `tree` within `bounds` with `metric`, that
+/// break an invariant.
+pub fn check(tree: &FileSystemNode, layout: &[TreemapNode], bounds: Rectangle, metric: SizeMetric) -> Vec<Violation> {
+    let tolerance = EPSILON * area(&bounds).max(1.0);
+    let length_tolerance = tolerance.sqrt();
+    let index: HashMap<&[String], usize> = layout.iter().enumerate().map(|(i, n)| (n.path.as_slice(), i)).collect();
+    // The children of each folder, keyed by the folder's path. The root's path is empty.
+    let mut children: HashMap<&[String], Vec<usize>> = HashMap::new();
+    for (i, node) in layout.iter().enumerate() {
+        if let Some((_, parent)) = node.path.split_last() {
+            children.entry(parent).or_default().push(i);
+        }
+    }
+
+    let mut violations = Vec::new();
+    let mut report = |path: &[String], problem| violations.push(Violation { path: path.to_vec(), problem });
+    for (parent_path, siblings) in &children {
+        let parent_rect = match index.get(parent_path) {
+            Some(&i) => layout[i].rect,
+            None => bounds,
+        };
+        let parent_area = area(&parent_rect);
+        let Some(parent) = tree.find(parent_path) else {
+            continue;
+        };
+        let total: u64 = parent.children.iter().map(|c| c.size_by(metric)).sum();
+        let mut filled = 0.0;
+        for (n, &i) in siblings.iter().enumerate() {
+            let node = &layout[i];
+            filled += area(&node.rect);
+            if !contains(&parent_rect, &node.rect, length_tolerance) {
+                report(&node.path, Problem::OutsideParent);
+            }
+            for &j in &siblings[n + 1..] {
+                if overlap(&node.rect, &layout[j].rect) > tolerance {
+                    report(&node.path, Problem::Overlap(layout[j].path.clone()));
+                }
+            }
+            let size = tree.find(&node.path).map_or(0, |n| n.size_by(metric));
+            if total > 0 && parent_area > tolerance {
+                let expected = size as f64 / total as f64;
+                let actual = area(&node.rect) / parent_area;
+                if (expected - actual).abs() > EPSILON.sqrt() {
+                    report(&node.path, Problem::WrongArea { expected, actual });
+                }
+            }
+        }
+        if total > 0 && (filled - parent_area).abs() > tolerance {
+            report(parent_path, Problem::Unfilled { filled: filled / parent_area.max(f64::MIN_POSITIVE) });
+        }
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::treemap::generate_treemap;
+    use proptest::prelude::*;
+
+    /// Random trees whose folders are as large as their contents, as scans produce.
+    fn tree() -> impl Strategy<Value = FileSystemNode> {
+        let file = (0..1_000_000u64).prop_map(|size| FileSystemNode { name: String::new(), size, allocated: size, children: vec![], special: None });
+        file.prop_recursive(4, 200, 8, |inner| {
+            prop::collection::vec(inner, 1..8).prop_map(|mut children| {
+                for (i, child) in children.iter_mut().enumerate() {
+                    child.name = format!("item{}", i);
+                }
+                let size = children.iter().map(|c| c.size).sum();
+                FileSystemNode { name: String::new(), size, allocated: size, children, special: None }
+            })
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_layouts_keep_the_invariants(tree in tree(), width in 1.0..2000.0f64, height in 1.0..2000.0f64) {
+            let bounds = Rectangle { x: 0.0, y: 0.0, width, height };
+            let layout = generate_treemap(&tree, bounds, SizeMetric::Apparent);
+            let violations = check(&tree, &layout, bounds, SizeMetric::Apparent);
+            prop_assert!(violations.is_empty(), "{:?}", violations);
+        }
+    }
+
+    #[test]
+    fn test_broken_layouts_are_caught() {
+        let leaf = |name: &str, size| FileSystemNode { name: name.to_string(), size, allocated: size, children: vec![], special: None };
+        let tree = FileSystemNode { name: "root".to_string(), size: 30, allocated: 30, children: vec![leaf("a", 20), leaf("b", 10)], special: None };
+        let bounds = Rectangle { x: 0.0, y: 0.0, width: 30.0, height: 10.0 };
+        let mut layout = generate_treemap(&tree, bounds, SizeMetric::Apparent);
+        assert_eq!(check(&tree, &layout, bounds, SizeMetric::Apparent), []);
+
+        // Widening "a" over "b" breaks every invariant at once.
+        layout[0].rect.width = 35.0;
+        let problems: Vec<Problem> = check(&tree, &layout, bounds, SizeMetric::Apparent).into_iter().map(|v| v.problem).collect();
+        assert!(problems.contains(&Problem::OutsideParent));
+        assert!(problems.contains(&Problem::Overlap(vec!["b".to_string()])));
+        assert!(problems.iter().any(|p| matches!(p, Problem::WrongArea { .. })));
+        assert!(problems.iter().any(|p| matches!(p, Problem::Unfilled { .. })));
+    }
+}
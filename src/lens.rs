@@ -0,0 +1,71 @@
+//! This module places the lens: while Alt is held over the treemap, an inset beside the
+//! pointer shows the region under it magnified, so clusters of rectangles too small to
+//! tell apart can be inspected without zooming or drilling down. The inset is drawn like
+//! the treemap itself, only with every rectangle mapped through the magnification.
+
+use eframe::egui;
+
+/// The size of the lens inset, in points.
+const LENS_SIZE: egui::Vec2 = egui::vec2(240.0, 180.0);
+/// How much larger the region under the pointer is shown.
+pub const MAGNIFICATION: f32 = 5.0;
+/// The gap between the pointer and the lens.
+const POINTER_GAP: f32 = 24.0;
+
+/// Whether the lens is held, i.e. Alt (Option on macOS) is down.
+pub fn is_held(ui: &egui::Ui) -> bool {
+    ui.input(|i| i.modifiers.alt)
+}
+
+/// Where the lens is drawn for the pointer at `pointer` in the treemap `area`: below and to
+/// the right of the pointer, or on the other side where that would leave the area.
+pub fn inset(area: egui::Rect, pointer: egui::Pos2) -> egui::Rect {
+    let mut min = pointer + egui::vec2(POINTER_GAP, POINTER_GAP);
+    if min.x + LENS_SIZE.x > area.max.x {
+        min.x = pointer.x - POINTER_GAP - LENS_SIZE.x;
+    }
+    if min.y + LENS_SIZE.y > area.max.y {
+        min.y = pointer.y - POINTER_GAP - LENS_SIZE.y;
+    }
+    // In an area too small for either side, the lens covers what fits.
+    min.x = min.x.max(area.min.x);
+    min.y = min.y.max(area.min.y);
+    egui::Rect::from_min_size(min, LENS_SIZE)
+}
+
+/// Maps `rect` on screen to where it shows in the lens `inset`, which magnifies the region
+/// around `pointer` into its center.
+pub fn magnify(rect: egui::Rect, pointer: egui::Pos2, inset: egui::Rect) -> egui::Rect {
+    let center = inset.center();
+    egui::Rect::from_min_max(center + (rect.min - pointer) * MAGNIFICATION, center + (rect.max - pointer) * MAGNIFICATION)
+}
+
+/// Draws the frame of the lens at `inset`, with a cross marking the point under the pointer.
+pub fn show_frame(painter: &egui::Painter, inset: egui::Rect) {
+    let stroke = egui::Stroke::new(1.5, painter.ctx().style().visuals.strong_text_color());
+    let center = inset.center();
+    painter.line_segment([center - egui::vec2(6.0, 0.0), center + egui::vec2(6.0, 0.0)], stroke);
+    painter.line_segment([center - egui::vec2(0.0, 6.0), center + egui::vec2(0.0, 6.0)], stroke);
+    painter.rect_stroke(inset, 4.0, stroke);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lens_stays_in_area_and_magnifies_around_pointer() {
+        let area = egui::Rect::from_min_size(egui::pos2(0.0, 0.0), egui::vec2(800.0, 600.0));
+        assert_eq!(inset(area, egui::pos2(100.0, 100.0)).min, egui::pos2(124.0, 124.0));
+        // Near the bottom-right corner, it flips to the other side of the pointer.
+        let flipped = inset(area, egui::pos2(790.0, 590.0));
+        assert!(area.contains_rect(flipped));
+        assert_eq!(flipped.max, egui::pos2(766.0, 566.0));
+
+        let lens = inset(area, egui::pos2(100.0, 100.0));
+        let pixel = egui::Rect::from_min_size(egui::pos2(100.0, 100.0), egui::vec2(1.0, 2.0));
+        let magnified = magnify(pixel, egui::pos2(100.0, 100.0), lens);
+        assert_eq!(magnified.min, lens.center());
+        assert_eq!(magnified.size(), egui::vec2(MAGNIFICATION, 2.0 * MAGNIFICATION));
+    }
+}
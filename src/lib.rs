@@ -0,0 +1,13 @@
+//! The scanner and the treemap layout of Disk Scout, as a library so the benchmarks can
+//! measure them. The app uses these modules from here, and keeps the rest to itself.
+
+pub mod aliases;
+pub mod clones;
+pub mod error;
+pub mod layout_check;
+pub mod monitor;
+pub mod scanner;
+pub mod stats;
+pub mod synthetic;
+pub mod treemap;
+pub mod virtual_fs;
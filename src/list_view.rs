@@ -0,0 +1,83 @@
+//! This module shows the current folder as a list, one row per item with its size and its
+//! share, in the order picked for the list. Scans keep the items of a folder in the order the
+//! file system listed them, so the list sorts them itself, and only sorts them again when
+//! the folder, the order, or the view changes.
+
+use crate::basis::Basis;
+use crate::format::format_size;
+use crate::interaction::{self, PointerAction};
+use crate::scanner::{ChildOrder, FileSystemNode, SizeMetric};
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+/// The list of the items of a folder.
+#[derive(Debug, Default)]
+pub struct ListView {
+    /// The folder sorted last, where it is on the disk, how it was sorted, and its items in
+    /// that order as positions in its children.
+    sorted: Option<(PathBuf, ChildOrder, SizeMetric, Vec<usize>)>,
+}
+
+impl ListView {
+    /// Forgets the order of the folder shown, e.g. because the view was rebuilt.
+    pub fn invalidate(&mut self) {
+        self.sorted = None;
+    }
+
+    /// The items of `node` in `order`, sorted again only if something changed since the last
+    /// call. `dir` is where the folder is on the disk, which also tells folders apart.
+    fn order(&mut self, node: &FileSystemNode, dir: &Path, order: ChildOrder, metric: SizeMetric) -> &[usize] {
+        let fresh = self.sorted.as_ref().is_some_and(|(d, o, m, _)| d == dir && *o == order && *m == metric);
+        if !fresh {
+            self.sorted = Some((dir.to_path_buf(), order, metric, node.child_order(order, metric, dir)));
+        }
+        self.sorted.as_ref().map_or(&[], |(_, _, _, items)| items)
+    }
+
+    /// Draws the items of `node`, found on the disk at `dir`, with the picker of their `order`.
+    /// Sizes are by `metric`, and shares of `basis`. Returns what the user did to an item, with
+    /// its path from `node`.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        node: &FileSystemNode,
+        dir: &Path,
+        order: &mut ChildOrder,
+        metric: SizeMetric,
+        basis: &Basis,
+    ) -> Option<PointerAction> {
+        ui.horizontal(|ui| {
+            ui.label("Sort by");
+            egui::ComboBox::from_id_salt("list_order").selected_text(order.label()).show_ui(ui, |ui| {
+                for option in ChildOrder::ALL {
+                    ui.selectable_value(order, option, option.label());
+                }
+            });
+        });
+        if node.children.is_empty() {
+            ui.centered_and_justified(|ui| {
+                ui.label("This folder is empty.");
+            });
+            return None;
+        }
+
+        let items = self.order(node, dir, *order, metric);
+        let mut action = None;
+        let row_height = ui.text_style_height(&egui::TextStyle::Body) + ui.spacing().item_spacing.y;
+        egui::ScrollArea::vertical().auto_shrink(false).show_rows(ui, row_height, items.len(), |ui, rows| {
+            for &i in &items[rows] {
+                let child = &node.children[i];
+                let size = child.size_by(metric);
+                ui.horizontal(|ui| {
+                    ui.add_sized([100.0, row_height], egui::Label::new(format_size(size)));
+                    ui.add_sized([60.0, row_height], egui::Label::new(format!("{:.1}%", basis.percent(size))));
+                    let name = if child.children.is_empty() { child.name.clone() } else { format!("{}/", child.name) };
+                    let response = ui.selectable_label(false, name);
+                    let path = [child.name.clone()];
+                    action = interaction::pointer_action(ui, &response, Some(&path)).or(action.take());
+                });
+            }
+        });
+        action
+    }
+}
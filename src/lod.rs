@@ -0,0 +1,156 @@
+//! This module decides how much detail each rectangle of the treemap is drawn with. Huge
+//! scans have far more rectangles than pixels, and drawing each with an outline and rounded
+//! corners costs frames for nothing the eye can see. Rectangles large enough on screen get
+//! the full treatment and a label; smaller ones are plain fills; folders smaller still are
+//! drawn as a single block standing in for everything inside them. The thresholds grow when
+//! frames take too long and shrink back when there is time to spare.
+
+use crate::treemap::TreemapNode;
+use eframe::egui;
+
+/// The screen area, in square points, from which a rectangle gets an outline, rounded
+/// corners, and a label, before adapting to the frame time.
+const DETAIL_AREA: f32 = 100.0;
+/// The screen area below which a folder is drawn as one block, before adapting.
+const MERGE_AREA: f32 = 4.0;
+/// The longest a frame may take, in seconds, before the detail is lowered: a little over a
+/// 60 Hz frame, so frames waiting for the display don't count as slow.
+const SLOW_FRAME: f32 = 1.0 / 50.0;
+/// The frame time, in seconds, below which the detail is raised again.
+const FAST_FRAME: f32 = 1.0 / 58.0;
+/// Longer gaps between frames are the app being idle or stalled, not slow drawing.
+const STALL: f32 = 0.25;
+/// How far the thresholds may grow.
+const MAX_SCALE: f32 = 64.0;
+
+/// How a rectangle of the treemap is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detail {
+    /// With an outline, rounded corners, and a label if it fits.
+    Full,
+    /// As a plain fill.
+    Plain,
+    /// As a plain fill standing in for its contents too, which aren't drawn.
+    Merged,
+}
+
+/// The level of detail, adapted to how long frames take.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lod {
+    /// What the thresholds are multiplied by: 1.0 for the most detail.
+    scale: f32,
+    /// The smoothed time between frames, in seconds.
+    frame_time: f32,
+}
+
+impl Default for Lod {
+    fn default() -> Self {
+        Self { scale: 1.0, frame_time: 0.0 }
+    }
+}
+
+impl Lod {
+    /// Takes the time the last frame took into account, lowering the detail while frames
+    /// are slow and raising it again while they are fast.
+    pub fn adapt(&mut self, frame_time: f32) {
+        if frame_time > STALL {
+            return;
+        }
+        self.frame_time = 0.8 * self.frame_time + 0.2 * frame_time;
+        if self.frame_time > SLOW_FRAME {
+            self.scale = (self.scale * 1.25).min(MAX_SCALE);
+        } else if self.frame_time < FAST_FRAME {
+            self.scale = (self.scale * 0.95).max(1.0);
+        }
+    }
+
+    /// How to draw a rectangle covering `rect` on screen; `has_children` tells whether it
+    /// is a folder whose contents are laid out inside it.
+    pub fn detail(&self, rect: egui::Rect, has_children: bool) -> Detail {
+        let area = rect.area();
+        if area >= DETAIL_AREA * self.scale {
+            Detail::Full
+        } else if has_children && area < MERGE_AREA * self.scale {
+            Detail::Merged
+        } else {
+            Detail::Plain
+        }
+    }
+}
+
+/// Whether the node at `i` of `layout` has its contents laid out after it.
+pub fn has_children(layout: &[TreemapNode], i: usize) -> bool {
+    layout.get(i + 1).is_some_and(|next| next.depth > layout[i].depth)
+}
+
+/// The index just past the contents of the node at `i`, which follow it in the layout.
+pub fn skip_contents(layout: &[TreemapNode], i: usize) -> usize {
+    let depth = layout[i].depth;
+    layout[i + 1..].iter().position(|n| n.depth <= depth).map_or(layout.len(), |end| i + 1 + end)
+}
+
+/// The label naming a rectangle covering `rect`: `name` in its top left, cut short to fit,
+/// in black or white, whichever stands out from `fill`. There is none if the rectangle is
+/// too low for a line of text.
+pub fn label(fonts: &egui::text::Fonts, rect: egui::Rect, name: &str, fill: egui::Color32) -> Option<egui::Shape> {
+    let font = egui::FontId::proportional(11.0);
+    if rect.height() < font.size + 4.0 || rect.width() < 16.0 {
+        return None;
+    }
+    let luminance = 0.299 * f32::from(fill.r()) + 0.587 * f32::from(fill.g()) + 0.114 * f32::from(fill.b());
+    let color = if luminance > 140.0 { egui::Color32::BLACK } else { egui::Color32::WHITE };
+    let mut job = egui::text::LayoutJob::single_section(name.to_string(), egui::TextFormat::simple(font, color));
+    job.wrap = egui::text::TextWrapping::truncate_at_width(rect.width() - 8.0);
+    let galley = fonts.layout_job(job);
+    Some(egui::Shape::galley(rect.left_top() + egui::vec2(4.0, 2.0), galley, color))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::treemap::Rectangle;
+
+    #[test]
+    fn test_detail_adapts_to_frame_time() {
+        let mut lod = Lod::default();
+        let square = |side: f32| egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(side, side));
+        assert_eq!(lod.detail(square(20.0), true), Detail::Full);
+        assert_eq!(lod.detail(square(5.0), true), Detail::Plain);
+        assert_eq!(lod.detail(square(1.0), true), Detail::Merged);
+        assert_eq!(lod.detail(square(1.0), false), Detail::Plain);
+
+        // Slow frames coarsen the detail up to a limit; a stall changes nothing.
+        for _ in 0..100 {
+            lod.adapt(0.1);
+        }
+        assert_eq!(lod.scale, MAX_SCALE);
+        assert_eq!(lod.detail(square(20.0), true), Detail::Plain);
+        assert_eq!(lod.detail(square(5.0), true), Detail::Merged);
+        lod.adapt(1.0);
+        assert_eq!(lod.scale, MAX_SCALE);
+
+        // Fast frames bring it back.
+        for _ in 0..200 {
+            lod.adapt(0.005);
+        }
+        assert_eq!(lod.scale, 1.0);
+    }
+
+    #[test]
+    fn test_skip_contents() {
+        let node = |depth| TreemapNode {
+            rect: Rectangle { x: 0.0, y: 0.0, width: 1.0, height: 1.0 },
+            depth,
+            share: 1.0,
+            path: Vec::new(),
+        };
+        let layout = [node(1), node(2), node(3), node(2), node(1), node(2)];
+        assert!(has_children(&layout, 0));
+        assert!(!has_children(&layout, 2));
+        assert_eq!(skip_contents(&layout, 0), 4);
+        assert_eq!(skip_contents(&layout, 1), 3);
+        assert_eq!(skip_contents(&layout, 2), 3);
+        assert_eq!(skip_contents(&layout, 4), 6);
+        assert_eq!(skip_contents(&layout, 5), 6);
+    }
+}
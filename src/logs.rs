@@ -0,0 +1,143 @@
+//! This module finds log files that are worth rotating or cleaning up: large ones, and ones
+//! that grew since an earlier scan in the folder's history. Log files are recognized by
+//! their names (`*.log`, rotated `*.log.1` or `*.log.gz`, Windows event logs and traces) or
+//! by being in `/var/log`.
+
+use crate::scanner::FileSystemNode;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// Log files smaller than this aren't reported unless they grew.
+const MIN_SIZE: u64 = 1 << 20;
+/// The seconds in a day, the period growth is given for.
+const DAY: f64 = 86_400.0;
+
+/// A log file found in a scan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFile {
+    /// The path of the file from the scan root.
+    pub path: Vec<String>,
+    /// The apparent size of the file.
+    pub size: u64,
+    /// When the file was last written to, in seconds since the Unix epoch, if known.
+    pub modified: Option<u64>,
+    /// How many bytes a day the file grew by since the earlier scan, if it was in it.
+    pub growth: Option<f64>,
+    /// Whether there is an earlier scan and the file isn't in it.
+    pub new: bool,
+}
+
+/// An earlier scan of the same folder, to tell how fast logs grow.
+pub struct EarlierScan<'a> {
+    /// The scanned tree.
+    pub tree: &'a FileSystemNode,
+    /// When the scan was made, in seconds since the Unix epoch.
+    pub created_at: u64,
+}
+
+/// Tells whether the file at `path` from the scan root `root` is a log file.
+pub fn is_log(root: &Path, path: &[String]) -> bool {
+    let Some(name) = path.last().map(|n| n.to_ascii_lowercase()) else {
+        return false;
+    };
+    let rotated = name.split(".log.").nth(1).is_some_and(|suffix| {
+        suffix == "gz" || suffix == "xz" || suffix == "zst" || suffix.parse::<u32>().is_ok()
+    });
+    if name.ends_with(".log") || rotated || name.ends_with(".evtx") || name.ends_with(".etl") {
+        return true;
+    }
+    let mut on_disk = root.to_path_buf();
+    on_disk.extend(path);
+    on_disk.starts_with("/var/log")
+}
+
+/// Returns the log files in `tree`, the scan of `root` made at `now`, that are at least
+/// `MIN_SIZE` or grew since `earlier`. The fastest-growing come first, then the largest.
+pub fn find(root: &Path, tree: &FileSystemNode, earlier: Option<&EarlierScan>, now: u64) -> Vec<LogFile> {
+    let mut logs = Vec::new();
+    let mut stack: Vec<(Vec<String>, &FileSystemNode)> = tree.children.iter().map(|c| (vec![c.name.clone()], c)).collect();
+    while let Some((path, node)) = stack.pop() {
+        if !node.children.is_empty() {
+            for child in &node.children {
+                let mut child_path = path.clone();
+                child_path.push(child.name.clone());
+                stack.push((child_path, child));
+            }
+            continue;
+        }
+        if !is_log(root, &path) {
+            continue;
+        }
+        let new = earlier.is_some_and(|earlier| earlier.tree.find(&path).is_none());
+        let growth = earlier.and_then(|earlier| {
+            let before = earlier.tree.find(&path)?;
+            let days = now.saturating_sub(earlier.created_at) as f64 / DAY;
+            (days > 0.0).then(|| (node.size as f64 - before.size as f64) / days)
+        });
+        if node.size < MIN_SIZE && growth.is_none_or(|g| g <= 0.0) {
+            continue;
+        }
+        let mut on_disk = root.to_path_buf();
+        on_disk.extend(&path);
+        let modified = on_disk
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs());
+        logs.push(LogFile { path, size: node.size, modified, growth, new });
+    }
+    logs.sort_by(|a, b| {
+        let growth = |log: &LogFile| log.growth.unwrap_or(f64::MIN);
+        growth(b).total_cmp(&growth(a)).then(b.size.cmp(&a.size))
+    });
+    logs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, size: u64, children: Vec<FileSystemNode>) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    #[test]
+    fn test_large_and_growing_logs_are_reported() {
+        let root = Path::new("/srv/app");
+        assert!(is_log(root, &["app.log.3".to_string()]));
+        assert!(is_log(Path::new("/var"), &["log".to_string(), "syslog".to_string()]));
+        assert!(!is_log(root, &["catalog.txt".to_string()]));
+
+        let mib = 1 << 20;
+        let before = node(
+            "app",
+            0,
+            vec![node("logs", 0, vec![node("app.log", 2 * mib, vec![]), node("slow.log", 4 * mib, vec![])])],
+        );
+        let after = node(
+            "app",
+            0,
+            vec![node(
+                "logs",
+                0,
+                vec![
+                    node("app.log", 12 * mib, vec![]),
+                    node("slow.log", 5 * mib, vec![]),
+                    node("big.log", 50 * mib, vec![]),
+                    node("tiny.log", 10, vec![]),
+                    node("data.bin", 90 * mib, vec![]),
+                ],
+            )],
+        );
+        let earlier = EarlierScan { tree: &before, created_at: 1_000_000 };
+        let logs = find(root, &after, Some(&earlier), 1_000_000 + 2 * 86_400);
+        let names: Vec<&str> = logs.iter().map(|l| l.path[1].as_str()).collect();
+        assert_eq!(names, ["app.log", "slow.log", "big.log"]);
+        assert_eq!(logs[0].growth, Some(5.0 * mib as f64));
+        assert_eq!((logs[2].growth, logs[2].new), (None, true));
+        // Without an earlier scan, nothing is new, just of unknown growth.
+        let logs = find(root, &after, None, 1_000_000);
+        assert!(logs.iter().all(|l| l.growth.is_none() && !l.new));
+    }
+}
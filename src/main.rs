@@ -2,154 +2,5097 @@
 //! It uses the `eframe` and `egui` libraries to create a native window and
 //! render the treemap visualization.
 
-mod scanner;
-mod treemap;
+// The web build can't scan or reach the desktop, so much of the code goes unused there.
+#![cfg_attr(target_arch = "wasm32", allow(dead_code))]
 
+mod accessibility;
+mod alerts;
+#[cfg(not(target_arch = "wasm32"))]
+mod analyzers;
+mod analysis;
+mod artifacts;
+mod animation;
+mod appearance;
+#[cfg(not(target_arch = "wasm32"))]
+mod archive;
+mod basis;
+mod budgets;
+mod changes;
+mod chart;
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
+mod collapse;
+mod coloring;
+mod compare;
+#[cfg(not(target_arch = "wasm32"))]
+mod compression;
+mod cow;
+#[cfg(not(target_arch = "wasm32"))]
+mod crash;
+mod dominant;
+#[cfg(not(target_arch = "wasm32"))]
+mod duplicates;
+mod exclusion;
+mod format;
+mod games;
+mod git;
+mod hardlinks;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+mod help;
+#[cfg(not(target_arch = "wasm32"))]
+mod history;
+mod hooks;
+mod import;
+mod integrity;
+mod interaction;
+mod lens;
+mod list_view;
+mod lod;
+mod logs;
+#[cfg(not(target_arch = "wasm32"))]
+mod media;
+mod minimap;
+mod model;
+mod mounts;
+#[cfg(not(target_arch = "wasm32"))]
+mod name_audit;
+#[cfg(not(target_arch = "wasm32"))]
+mod network;
+mod notes;
+mod owners;
+mod palette;
+mod pdf;
+mod pinned;
+#[cfg(not(target_arch = "wasm32"))]
+mod plugins;
+#[cfg(not(target_arch = "wasm32"))]
+mod preview;
+mod queue;
+mod read_only;
+mod recovery;
+mod render;
+mod report;
+#[cfg(not(target_arch = "wasm32"))]
+mod scripting;
+mod search;
+#[cfg(not(target_arch = "wasm32"))]
+mod server;
+mod settings;
+mod shadow_copies;
+mod shell_integration;
+mod smart;
+mod snapshot;
+mod system_files;
+mod tally;
+#[cfg(not(target_arch = "wasm32"))]
+mod thumbnails;
+mod time_machine;
+mod tooltip;
+mod touch;
+#[cfg(not(target_arch = "wasm32"))]
+mod tray;
+mod treemap_style;
+mod volumes;
+#[cfg(not(target_arch = "wasm32"))]
+mod watchdog;
+#[cfg(target_arch = "wasm32")]
+mod web;
+mod welcome;
+#[cfg(not(target_arch = "wasm32"))]
+mod workspace;
+
+// The scanner and the layout live in the library, where the benchmarks can reach them.
+use disk_scout::{aliases, clones, error, layout_check, monitor, scanner, stats, synthetic, treemap};
+
+use alerts::AlertMonitor;
+use aliases::Alias;
+use clones::ClonedFile;
+use analysis::SizeHistogram;
+use animation::Transition;
+use artifacts::Artifact;
+use basis::{Basis, SizeBasis};
+use budgets::Violation;
+use compare::{CompareReport, HashProgress};
+use notes::{Note, Notes, Tag};
+use owners::OwnerReport;
+use cow::{CowFilesystem, CowUsage};
 use eframe::egui;
-use scanner::FileSystemNode;
+use error::ScanError;
+use exclusion::ExcludedItem;
+use games::Game;
+use git::{GitClass, GitStatus};
+use hardlinks::HardlinkGroup;
+use integrity::VerifyReport;
+use interaction::{HitIndex, PointerAction};
+use list_view::ListView;
+use lod::Lod;
+use logs::LogFile;
+use minimap::Minimap;
+use model::TreeModel;
+use monitor::{Reconnection, ScanMonitor};
+use mounts::ScanMount;
+use queue::{JobState, ResultTab, ScanQueue};
+use recovery::{Recovery, ViewState};
+use scanner::{FileSystemNode, ScanOptions, SizeMetric};
+use search::{SearchMode, SearchResult};
+use settings::Settings;
+use shadow_copies::ShadowStorage;
+use smart::{DiskHealth, Health};
+use snapshot::{Snapshot, SnapshotHeader};
+use stats::ScanStats;
+use time_machine::LocalSnapshots;
+use tooltip::TooltipSettings;
 use std::path::PathBuf;
-use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
 use std::thread;
+use std::time::{Duration, Instant};
+#[cfg(not(target_arch = "wasm32"))]
+use media::{Media, MediaCache};
+#[cfg(not(target_arch = "wasm32"))]
+use preview::{Preview, PreviewLoader};
+#[cfg(not(target_arch = "wasm32"))]
+use thumbnails::{Thumbnail, ThumbnailCache};
+#[cfg(not(target_arch = "wasm32"))]
+use tray::{Tray, TrayCommand};
 use treemap::{Rectangle, TreemapNode};
+use volumes::VolumeInfo;
+use welcome::{TourTargets, WelcomeAction};
+
+/// The different ways the current directory can be visualized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ViewMode {
+    /// Nested rectangles for the whole subtree.
+    Treemap,
+    /// A donut chart of the immediate children only.
+    Donut,
+    /// A list of the immediate children, in the order picked for it.
+    List,
+}
+
+/// A layout made before nodes were removed from the tree, to be updated rather than made
+/// again.
+struct LayoutUpdate {
+    /// The generation of the model with the nodes removed.
+    generation: u64,
+    /// The path (from the scan root) of the layout's root.
+    root: Vec<String>,
+    /// The size of the frame the layout was made for.
+    frame_size: egui::Vec2,
+    /// The layout.
+    layout: Vec<TreemapNode>,
+    /// The paths (from the layout's root) of the nodes removed.
+    removed: Vec<Vec<String>>,
+}
+
+/// The successful result of a background file operation.
+enum TaskOutcome {
+    /// A snapshot was written to the given path.
+    SnapshotSaved(PathBuf),
+    /// A report was written to the given path.
+    ReportSaved(PathBuf),
+    /// A snapshot was read from disk.
+    SnapshotLoaded(Box<Snapshot>),
+    /// A snapshot was verified against the disk.
+    Verified(VerifyReport),
+    /// Two directories were scanned and compared.
+    Compared(CompareReport),
+    /// The reclaimable space of the folder at the given path (from the scan root) was measured.
+    Measured(Vec<String>, CowUsage),
+    /// The owners of the folder at the given path (from the scan root) were looked up.
+    OwnersMeasured(Vec<String>, OwnerReport),
+    /// An analyzer, by its title, looked at the scan.
+    #[cfg(not(target_arch = "wasm32"))]
+    Analyzed(String, Vec<analyzers::Finding>),
+    /// A script of the console ran, or failed with what it printed until then.
+    #[cfg(not(target_arch = "wasm32"))]
+    ScriptRan(Result<scripting::ScriptOutput, (String, Vec<String>)>),
+    /// The hard link groups in the scan were found.
+    HardlinksFound(Vec<HardlinkGroup>),
+    /// Build output folders were moved to the trash: the paths (from the scan root) that
+    /// were moved, and the failures.
+    Trashed(Vec<Vec<String>>, Vec<String>),
+    /// The games installed in the scan were found.
+    GamesFound(Vec<Game>),
+    /// The log files worth rotating were found, and when the scan their growth is measured
+    /// against was made.
+    LogsFound(Vec<LogFile>, Option<u64>),
+    /// The shadow copy storage of the volumes was measured.
+    ShadowCopiesMeasured(Vec<ShadowStorage>),
+    /// The items Windows keeps at the top of the drives were found.
+    SystemFilesFound(Vec<system_files::Found>),
+    /// The local Time Machine snapshots and purgeable space of the volumes were looked up.
+    LocalSnapshotsFound(Vec<LocalSnapshots>),
+    /// The SMART health of the physical disks was read.
+    DiskHealthRead(Vec<DiskHealth>),
+    /// How well the folder at the given path (from the scan root) would compress was estimated.
+    #[cfg(not(target_arch = "wasm32"))]
+    CompressionEstimated(Vec<String>, compression::Estimate),
+    /// A folder was archived: the archive, and the path (from the scan root) of the folder
+    /// if it was then moved to the trash, or why it wasn't.
+    #[cfg(not(target_arch = "wasm32"))]
+    Archived(PathBuf, Option<Result<Vec<String>, String>>),
+    /// The network share was connected, so the scan of it can start.
+    #[cfg(not(target_arch = "wasm32"))]
+    ShareConnected(String),
+}
+
+/// The main application struct that holds the state of the GUI.
+struct DiskScannerApp {
+    /// The path to be scanned, as entered by the user.
+    path_input: String,
+    /// The options used for every scan, set from the command line.
+    scan_options: ScanOptions,
+    /// Holds the scanned tree, published by the scanner (partially while it runs) or by
+    /// loading a snapshot.
+    model: Arc<TreeModel>,
+    /// The generation of the model's tree that the view was last built from.
+    model_generation: u64,
+    /// The error of the last scan, if it failed.
+    scan_error: Option<ScanError>,
+    /// The scanned tree with excluded nodes removed. This is what is displayed and searched.
+    /// It shares the model's tree when there is nothing to transform.
+    view_tree: Option<Arc<FileSystemNode>>,
+    /// The nodes hidden from the view with "Exclude from view".
+    excluded: Vec<ExcludedItem>,
+    /// Whether chains of single-child directories are shown as a single node.
+    collapse_chains: bool,
+    /// How many levels of folders the treemap shows at once, if it is limited.
+    max_depth: Option<usize>,
+    /// The path the current scan result was produced from.
+    scanned_path: Option<PathBuf>,
+    /// The options the current tree was scanned with, unless it was loaded from elsewhere.
+    scanned_with: Option<ScanOptions>,
+    /// Where and when the tree shown was scanned, if known. Its options and statistics are
+    /// kept in `scanned_with` and `scan_stats`, which may change after the scan.
+    provenance: Option<SnapshotHeader>,
+    /// A receiver for the outcome of the background scanning thread. The tree itself is
+    /// published to the model.
+    scan_receiver: Option<Receiver<Result<ScanStats, ScanError>>>,
+    /// Set to stop the running scan.
+    scan_cancel: Arc<AtomicBool>,
+    /// Watches the running scan for directories that are slow to list.
+    scan_monitor: Arc<ScanMonitor>,
+    /// The calculated layout of rectangles to be drawn. This is generated from a successful scan.
+    layout: Option<Vec<TreemapNode>>,
+    /// Finds the nodes of the layout by position or path.
+    hit_index: HitIndex,
+    /// The layout to update after nodes were removed, until the next frame does so.
+    layout_update: Option<LayoutUpdate>,
+    /// The size of the last frame, used to detect window resizing.
+    last_frame_size: egui::Vec2,
+    /// The most recently generated layout and the path of its root, kept so that the
+    /// next layout can be animated from it.
+    previous_layout: Option<(Vec<String>, Vec<TreemapNode>)>,
+    /// The animation from the previous layout to the current one, if one is running.
+    transition: Option<Transition>,
+    /// The overview of the whole tree shown while drilled into a subtree.
+    minimap: Minimap,
+    /// The list of the current directory, shown instead of the treemap in the list view.
+    list_view: ListView,
+    /// How the current directory is visualized.
+    view_mode: ViewMode,
+    /// Which size of the nodes the treemap and donut chart are weighted by.
+    size_metric: SizeMetric,
+    /// Whether the analysis panel is open.
+    show_analysis: bool,
+    /// The volumes the last scan covered, shown in the analysis panel.
+    scan_mounts: Vec<ScanMount>,
+    /// The statistics of the scan shown, if it was made in this session or loaded from a
+    /// snapshot that has them.
+    scan_stats: Option<ScanStats>,
+    /// Whether the drive overview window is open.
+    show_drives: bool,
+    /// The volumes listed in the drive overview, refreshed whenever it is opened.
+    drives: Vec<VolumeInfo>,
+    /// The shadow copy storage of the volumes, once the user asked for it (Windows only).
+    shadow_storage: Option<Vec<ShadowStorage>>,
+    /// The Recycle Bins, page files, and the like at the top of the drives, once the user
+    /// looked for them (Windows only).
+    system_files: Option<Vec<system_files::Found>>,
+    /// The local Time Machine snapshots and purgeable space of the volumes, once the user
+    /// asked for them (macOS only).
+    local_snapshots: Option<Vec<LocalSnapshots>>,
+    /// The SMART health of the physical disks, once the user asked for it.
+    disk_health: Option<Vec<DiskHealth>>,
+    /// The copy-on-write space accounting last measured, with the path (from the scan root)
+    /// of the folder it was measured for.
+    cow_usage: Option<(Vec<String>, CowUsage)>,
+    /// The owners of a folder (by its path from the scan root) and the quotas of its volume.
+    owner_report: Option<(Vec<String>, OwnerReport)>,
+    /// The hard link groups found in the scan, once the user asked for them.
+    hardlink_groups: Option<Vec<HardlinkGroup>>,
+    /// The search for duplicate files in the scan, once the user started one.
+    #[cfg(not(target_arch = "wasm32"))]
+    duplicates: Option<Arc<duplicates::Progress>>,
+    /// Watches the volumes of the finished scan for being removed while it is browsed.
+    #[cfg(not(target_arch = "wasm32"))]
+    watchdog: Option<watchdog::VolumeWatchdog>,
+    /// The size histogram of the current view root, along with the path it was computed for.
+    histogram: Option<(Vec<String>, SizeHistogram)>,
+    /// The user's preferences, persisted between sessions.
+    settings: Settings,
+    /// Whether the settings window is open.
+    show_settings: bool,
+    /// Settings read from a file that bring hooks along, and the file, until the user decides
+    /// whether to run their commands.
+    #[cfg(not(target_arch = "wasm32"))]
+    pending_import: Option<(PathBuf, Settings)>,
+    /// Watches volume usage for the tray icon and alerts.
+    alert_monitor: AlertMonitor,
+    /// The tray icon, if it is enabled and available on this platform.
+    #[cfg(not(target_arch = "wasm32"))]
+    tray: Option<Tray>,
+    /// The thumbnails of the most recently previewed images.
+    #[cfg(not(target_arch = "wasm32"))]
+    thumbnails: ThumbnailCache,
+    /// The metadata of the most recently selected video and audio files.
+    #[cfg(not(target_arch = "wasm32"))]
+    media: MediaCache,
+    /// The preview of the last previewed text file.
+    #[cfg(not(target_arch = "wasm32"))]
+    text_preview: PreviewLoader,
+    /// A receiver for the result of a background snapshot operation.
+    task_receiver: Option<Receiver<Result<TaskOutcome, String>>>,
+    /// A message about the outcome of the last snapshot operation.
+    status_message: Option<String>,
+    /// The report of the last verification, shown in its own window until dismissed.
+    verify_report: Option<VerifyReport>,
+    /// Whether the file manager integration is installed, checked when first needed.
+    shell_integration_installed: Option<bool>,
+    /// The outcome of the last attempt to install or remove the file manager integration.
+    shell_integration_status: Option<Result<String, String>>,
+    /// The path from the root of the view tree to the directory currently shown.
+    root_path: Vec<String>,
+    /// The folder pinned to the left pane for comparing, which stays while others are opened.
+    pinned: Option<pinned::PinnedPane>,
+    /// The text currently entered in the search box.
+    search_query: String,
+    /// How the search query is interpreted.
+    search_mode: SearchMode,
+    /// The results of the last search, or the error message if the query was invalid.
+    search_results: Result<Vec<SearchResult>, String>,
+    /// The path (from the scan root) of the node picked from the search results, if any.
+    /// It is highlighted in the treemap.
+    selected_path: Option<Vec<String>>,
+    /// The path of a node to select once the running scan has finished, used when the
+    /// path to scan was a file and its folder is scanned instead.
+    pending_selection: Option<Vec<String>>,
+    /// The file the session is autosaved to, if the platform has a place for it.
+    recovery_path: Option<PathBuf>,
+    /// A receiver for the session left in the recovery file, while it is read at launch.
+    recovery_receiver: Option<Receiver<Option<Recovery>>>,
+    /// The session left behind by a crash, offered for restoring until the user decides.
+    recovery_offer: Option<Recovery>,
+    /// The crash report left by the last session, and when it was written, offered for
+    /// opening until the user decides.
+    #[cfg(not(target_arch = "wasm32"))]
+    crash_offer: Option<(PathBuf, u64)>,
+    /// When the session was last considered for autosaving.
+    last_autosave: Option<Instant>,
+    /// The tree generation and view state that were last autosaved.
+    autosaved: Option<(u64, ViewState)>,
+    /// Set while an autosave is being written.
+    autosave_running: Arc<AtomicBool>,
+    /// The path (from the root of the view tree) of the node under the pointer, and the
+    /// time it got there, used to delay its tooltip.
+    hover_start: Option<(Vec<String>, f64)>,
+    /// The path (from the root of the view tree) of the node whose tooltip is pinned,
+    /// and where the tooltip is shown.
+    pinned_tooltip: Option<(Vec<String>, egui::Pos2)>,
+    /// The folders of the finished scan that exceed a budget. Paths are canonical.
+    budget_violations: Vec<Violation>,
+    /// Scans queued to run in the background, whose results open in tabs.
+    scan_queue: ScanQueue,
+    /// Whether the scan queue window is open.
+    show_queue: bool,
+    /// Whether the window to compare two directories is open.
+    show_compare: bool,
+    /// The analyzers that can look at a finished scan.
+    #[cfg(not(target_arch = "wasm32"))]
+    analyzers: analyzers::Registry,
+    /// Whether the window to run analyzers is open.
+    #[cfg(not(target_arch = "wasm32"))]
+    show_analyzers: bool,
+    /// What the analyzer run last, by its title, found.
+    #[cfg(not(target_arch = "wasm32"))]
+    findings: Option<(String, Vec<analyzers::Finding>)>,
+    /// Whether the script console is open.
+    #[cfg(not(target_arch = "wasm32"))]
+    show_console: bool,
+    /// What the script run last in the console printed and asked for, or why it failed.
+    #[cfg(not(target_arch = "wasm32"))]
+    script_output: Option<Result<scripting::ScriptOutput, (String, Vec<String>)>>,
+    /// Whether the window listing the mouse and keyboard controls is open.
+    show_controls: bool,
+    /// Whether the window saying what the app is is open.
+    show_about: bool,
+    /// The step of the tour being shown, if it is.
+    tour_step: Option<usize>,
+    /// Where the parts of the window the tour points at were drawn.
+    tour_targets: TourTargets,
+    /// The directories to compare, as entered by the user: e.g. a source and its backup.
+    compare_paths: [String; 2],
+    /// Whether comparisons hash the files of the same size on both sides too.
+    compare_contents: bool,
+    /// How far hashing the files of the running comparison has got.
+    compare_progress: Option<Arc<HashProgress>>,
+    /// Set to stop the running comparison, if one is running.
+    compare_cancel: Option<Arc<AtomicBool>>,
+    /// The result of the last comparison, shown in the compare window.
+    compare_report: Option<CompareReport>,
+    /// The results of queued scans, and the scans that were switched away from.
+    tabs: Vec<ResultTab>,
+    /// The index of the tab shown, unless the tree shown doesn't have a tab (yet).
+    active_tab: Option<usize>,
+    /// Where hooks and history recording running in the background report their failures to.
+    hook_sender: Sender<Vec<String>>,
+    /// The failures of hooks and history recording run in the background.
+    hook_receiver: Receiver<Vec<String>>,
+    /// What git knows about the files of the scan, if it is in a git repository.
+    git_status: Option<Arc<GitStatus>>,
+    /// Delivers the git status of the scan, being read in the background.
+    git_receiver: Option<Receiver<Option<GitStatus>>>,
+    /// The previous scan of the folder being scanned again, to find what changed once it is done.
+    rescanned_from: Option<Arc<FileSystemNode>>,
+    /// What changed since the previous scan of the folder, for the badges and the toast.
+    recent_changes: Option<changes::RecentChanges>,
+    /// Delivers the changes since the previous scan, being found in the background.
+    changes_receiver: Option<Receiver<changes::RecentChanges>>,
+    /// How much of the scan each git class takes up, and the metric they were sized by.
+    git_totals: Option<(SizeMetric, [u64; 4])>,
+    /// Whether the treemap is colored by git status rather than by depth.
+    color_by_git: bool,
+    /// The only git class shown, if the view is filtered by git status.
+    git_filter: Option<GitClass>,
+    /// The build output folders in the scan, and the metric they were sized by.
+    artifacts: Option<(SizeMetric, Vec<Artifact>)>,
+    /// The build output folders ticked to be cleaned. Paths are canonical.
+    artifacts_to_clean: Vec<Vec<String>>,
+    /// Whether the user is being asked to confirm cleaning the ticked folders.
+    confirm_clean: bool,
+    /// The games installed in the scan, once looked for.
+    games: Option<Vec<Game>>,
+    /// The log files shown in the log report window, and when the scan their growth is
+    /// measured against was made. The window is open while this is set.
+    log_report: Option<(Vec<LogFile>, Option<u64>)>,
+    /// The path (from the scan root) of the folder whose compressibility was estimated, and
+    /// the estimate.
+    #[cfg(not(target_arch = "wasm32"))]
+    compression: Option<(Vec<String>, compression::Estimate)>,
+    /// The path (from the scan root) of the folder the archive window is open for.
+    #[cfg(not(target_arch = "wasm32"))]
+    archive_path: Option<Vec<String>>,
+    /// The format picked in the archive window.
+    #[cfg(not(target_arch = "wasm32"))]
+    archive_format: archive::Format,
+    /// Whether the archived folder is moved to the trash once the archive is complete.
+    #[cfg(not(target_arch = "wasm32"))]
+    archive_then_trash: bool,
+    /// The user name and password being asked for to connect a network share before scanning it.
+    #[cfg(not(target_arch = "wasm32"))]
+    credentials: Option<network::Credentials>,
+    /// The tags and notes attached to the nodes of the scan.
+    notes: Notes,
+    /// When the notes were last changed, while they haven't been saved since.
+    #[cfg(not(target_arch = "wasm32"))]
+    notes_changed: Option<Instant>,
+    /// The tag the view is limited to, if any.
+    tag_filter: Option<Tag>,
+    /// Whether the treemap is colored by the tags of the nodes.
+    color_by_tags: bool,
+    /// The nodes ticked to be added up, as canonical paths from the scan root.
+    ticked: Vec<Vec<String>>,
+    /// The tally of the ticked nodes, kept so it isn't added up again every frame.
+    ticked_total: tally::CachedTally,
+    /// Whether the finished tree was checked for a folder holding nearly all of it.
+    dominant_checked: bool,
+    /// The path of the folder holding nearly all of the tree, offered to focus on until the
+    /// offer is taken or dismissed. Empty if there is none.
+    dominant: Vec<String>,
+    /// The bytes packed so far by the running archive task, and how many there are.
+    archive_progress: Option<(Arc<AtomicU64>, u64)>,
+    /// How far the treemap is zoomed in.
+    zoom: touch::Zoom,
+    /// How much detail the treemap is drawn with, adapted to the frame time.
+    lod: Lod,
+    /// Whether the items of the treemap breaking a layout invariant are outlined.
+    show_layout_check: bool,
+    /// The items of the current layout breaking an invariant, once checked.
+    layout_violations: Option<Vec<layout_check::Violation>>,
+    /// Whether the workspaces window is open.
+    #[cfg(not(target_arch = "wasm32"))]
+    show_workspaces: bool,
+    /// The name typed for a new workspace.
+    #[cfg(not(target_arch = "wasm32"))]
+    new_workspace_name: String,
+}
+
+impl Default for DiskScannerApp {
+    fn default() -> Self {
+        let (hook_sender, hook_receiver) = mpsc::channel();
+        Self {
+            // Default to the current directory, but don't scan until the user clicks the button.
+            path_input: std::env::current_dir()
+                .unwrap_or_else(|_| PathBuf::from("."))
+                .to_string_lossy()
+                .to_string(),
+            scan_options: ScanOptions::default(),
+            model: Arc::default(),
+            model_generation: 0,
+            scan_error: None,
+            scanned_path: None,
+            scanned_with: None,
+            provenance: None,
+            view_tree: None,
+            excluded: Vec::new(),
+            collapse_chains: true,
+            max_depth: None,
+            scan_receiver: None, // No scan running at startup.
+            scan_stats: None,
+            scan_cancel: Arc::default(),
+            scan_monitor: Arc::default(),
+            layout: None,
+            layout_update: None,
+            hit_index: HitIndex::default(),
+            last_frame_size: egui::Vec2::ZERO,
+            previous_layout: None,
+            transition: None,
+            minimap: Minimap::default(),
+            list_view: ListView::default(),
+            view_mode: ViewMode::Treemap,
+            size_metric: SizeMetric::default(),
+            show_analysis: false,
+            scan_mounts: Vec::new(),
+            show_drives: false,
+            drives: Vec::new(),
+            shadow_storage: None,
+            system_files: None,
+            local_snapshots: None,
+            disk_health: None,
+            cow_usage: None,
+            owner_report: None,
+            hardlink_groups: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            duplicates: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            watchdog: None,
+            histogram: None,
+            settings: Settings::default(),
+            show_settings: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            pending_import: None,
+            alert_monitor: AlertMonitor::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            tray: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            thumbnails: ThumbnailCache::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            media: MediaCache::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            text_preview: PreviewLoader::default(),
+            task_receiver: None,
+            status_message: None,
+            verify_report: None,
+            shell_integration_installed: None,
+            shell_integration_status: None,
+            root_path: Vec::new(),
+            pinned: None,
+            search_query: String::new(),
+            search_mode: SearchMode::Substring,
+            search_results: Ok(Vec::new()),
+            selected_path: None,
+            pending_selection: None,
+            recovery_path: None,
+            recovery_receiver: None,
+            recovery_offer: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            crash_offer: None,
+            last_autosave: None,
+            autosaved: None,
+            autosave_running: Arc::default(),
+            hover_start: None,
+            pinned_tooltip: None,
+            budget_violations: Vec::new(),
+            scan_queue: ScanQueue::default(),
+            show_queue: false,
+            show_compare: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            analyzers: analyzers::Registry::with_builtins(),
+            #[cfg(not(target_arch = "wasm32"))]
+            show_analyzers: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            findings: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            show_console: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            script_output: None,
+            show_controls: false,
+            show_about: false,
+            tour_step: None,
+            tour_targets: TourTargets::default(),
+            compare_paths: Default::default(),
+            compare_contents: false,
+            compare_progress: None,
+            compare_cancel: None,
+            compare_report: None,
+            tabs: Vec::new(),
+            active_tab: None,
+            hook_sender,
+            hook_receiver,
+            git_status: None,
+            git_receiver: None,
+            rescanned_from: None,
+            recent_changes: None,
+            changes_receiver: None,
+            git_totals: None,
+            color_by_git: false,
+            git_filter: None,
+            artifacts: None,
+            artifacts_to_clean: Vec::new(),
+            confirm_clean: false,
+            games: None,
+            log_report: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            compression: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            archive_path: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            archive_format: archive::Format::TarZst,
+            #[cfg(not(target_arch = "wasm32"))]
+            archive_then_trash: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            credentials: None,
+            notes: Notes::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            notes_changed: None,
+            tag_filter: None,
+            color_by_tags: false,
+            dominant_checked: false,
+            dominant: Vec::new(),
+            ticked: Vec::new(),
+            ticked_total: tally::CachedTally::default(),
+            archive_progress: None,
+            zoom: touch::Zoom::default(),
+            lod: Lod::default(),
+            show_layout_check: false,
+            layout_violations: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            show_workspaces: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            new_workspace_name: String::new(),
+        }
+    }
+}
+
+/// The maximum number of search results shown in the results list.
+const MAX_SEARCH_RESULTS: usize = 200;
+
+/// How many levels the treemap shows when limiting them is turned on.
+const DEFAULT_MAX_DEPTH: usize = 2;
+
+/// The most levels the treemap can be limited to.
+const MAX_DEPTH_LIMIT: usize = 10;
+
+/// The most groups of duplicate files listed in the analysis panel.
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_DUPLICATE_GROUPS: usize = 100;
+
+/// How long the notes have to stay unchanged before they are saved, so typing a note doesn't
+/// rewrite the file for every letter.
+#[cfg(not(target_arch = "wasm32"))]
+const NOTES_SAVE_DELAY: Duration = Duration::from_secs(1);
+
+impl DiskScannerApp {
+    /// Creates the app, restoring the user's settings from the previous session.
+    /// If a path was given (on the command line), it is scanned right away.
+    /// If the previous session crashed, restoring it is offered once it has been read.
+    fn new(cc: &eframe::CreationContext<'_>, scan_options: ScanOptions, path: Option<PathBuf>) -> Self {
+        let mut app = Self {
+            settings: Settings::load(cc.storage),
+            scan_options,
+            ..Self::default()
+        };
+        read_only::set(app.settings.read_only);
+        format::set(&app.settings.format);
+        #[cfg(not(target_arch = "wasm32"))]
+        for path in app.settings.analyzer_plugins.clone() {
+            app.load_plugin(&path);
+        }
+        app.drives = volumes::list_volumes();
+        // The appearance settings handle zooming from the keyboard, so it is remembered.
+        cc.egui_ctx.options_mut(|o| o.zoom_with_keyboard = false);
+        #[cfg(not(target_arch = "wasm32"))]
+        app.read_recovery(recovery::default_path());
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            crash::set(&app.settings.crash_reports);
+            let offered_until = app.settings.crash_reports.offered_until;
+            app.crash_offer = crash::default_dir().and_then(|dir| crash::pending(&dir, offered_until));
+        }
+        if let Some(path) = path {
+            app.path_input = path.to_string_lossy().to_string();
+            app.start_scan();
+        }
+        app
+    }
+
+    /// Starts scanning the path in the path input on a background thread.
+    fn start_scan(&mut self) {
+        // A share that wants a password is connected first, and scanned once it is.
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(share) = network::share_needing_credentials(std::path::Path::new(&self.path_input)) {
+            self.credentials = Some(network::Credentials { share, ..Default::default() });
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        self.scan_receiver = Some(receiver);
+
+        let mut path_to_scan = PathBuf::from(self.path_input.clone());
+        // A single file makes for an empty treemap, so show it within its folder instead.
+        let mut file_name = None;
+        if path_to_scan.is_file()
+            && let Some(name) = path_to_scan.file_name()
+        {
+            file_name = Some(name.to_string_lossy().into_owned());
+            path_to_scan = match path_to_scan.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+        }
+        // Scanning the same folder again shows what changed since, once the scan is done.
+        let snapshot = self.model.snapshot();
+        let previous = snapshot.tree.filter(|_| snapshot.complete && self.scanned_path.as_ref() == Some(&path_to_scan));
+        // The notes not saved yet belong next to the folder scanned before.
+        self.flush_notes();
+        self.scanned_path = Some(path_to_scan.clone());
+        welcome::remember_scan(&mut self.settings.recent_scans, &path_to_scan);
+        let options = self.with_permanent_exclusions(self.scan_options.clone());
+        self.scanned_with = Some(options.clone());
+        self.provenance = Some(SnapshotHeader::scan_starting(options.clone()));
+        self.set_size_metric(options.metric);
+        println!("Starting scan of: {}", path_to_scan.display());
+
+        // Stop the previous scan, if it is still running, and give this one its own flag.
+        self.cancel_scan();
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.scan_cancel = Arc::clone(&cancel);
+        let monitor = Arc::new(ScanMonitor::default());
+        self.scan_monitor = Arc::clone(&monitor);
+
+        let publisher = self.model.publisher();
+        self.reset_for_new_tree();
+        self.rescanned_from = previous;
+        if let Some(name) = file_name {
+            self.status_message = Some(format!("{} is a file, so the folder containing it was scanned.", name));
+            self.pending_selection = Some(vec![name]);
+        }
+        thread::spawn(move || {
+            let publish_partial = |entries: FileSystemNode| publisher.publish_more(entries);
+            let result = scanner::build_tree_with_progress(&path_to_scan, &options, &publish_partial, &cancel, Some(&monitor));
+            let result = match result {
+                Ok((tree, stats)) => {
+                    publisher.publish(tree, true);
+                    Ok(stats)
+                }
+                Err(e) => {
+                    publisher.clear();
+                    Err(e)
+                }
+            };
+            // The receiver is gone if the scan was superseded, in which case nobody cares.
+            let _ = sender.send(result);
+        });
+    }
+
+    /// Returns the result of the running scan once it has finished, and stops waiting for it.
+    fn poll_scan(&mut self) -> Option<Result<ScanStats, ScanError>> {
+        let result = match self.scan_receiver.as_ref()?.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return None,
+            // The scanner turns its own panics into errors, so the thread died elsewhere. Its
+            // partial tree is cleared, as for a failed scan.
+            Err(TryRecvError::Disconnected) => {
+                drop(self.model.publisher());
+                Err(ScanError::Panicked("the scan stopped without a result".to_string()))
+            }
+        };
+        self.scan_receiver = None;
+        Some(result)
+    }
+
+    /// Asks the running scan, if any, to stop.
+    fn cancel_scan(&mut self) {
+        self.scan_cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Adds the paths the user always excludes to `options`.
+    fn with_permanent_exclusions(&self, mut options: ScanOptions) -> ScanOptions {
+        for pattern in self.settings.always_exclude.iter().filter(|p| !p.trim().is_empty()) {
+            if !options.exclude.contains(pattern) {
+                options.exclude.push(pattern.clone());
+            }
+        }
+        options
+    }
+
+    /// Hides the node at `path` (relative to the scan root), and makes every scan from now on
+    /// skip it.
+    fn always_exclude(&mut self, path: Vec<String>) {
+        let Some(root) = self.scanned_path.clone() else {
+            return;
+        };
+        let on_disk = collapse::to_canonical_path(&path).iter().fold(root, |path, name| path.join(name));
+        let pattern = glob::Pattern::escape(&on_disk.to_string_lossy());
+        if !self.settings.always_exclude.contains(&pattern) {
+            self.settings.always_exclude.push(pattern);
+        }
+        self.exclude(path);
+        self.status_message = Some(format!("{} will be skipped by every scan. The settings list what is excluded.", on_disk.display()));
+    }
+
+    /// Makes the scans from now on skip the directory at `path`.
+    fn exclude_from_scans(&mut self, path: &std::path::Path) {
+        let pattern = glob::Pattern::escape(&path.to_string_lossy());
+        if !self.scan_options.exclude.contains(&pattern) {
+            self.scan_options.exclude.push(pattern);
+        }
+        self.status_message = Some(format!("{} will be skipped by the next scans.", path.display()));
+    }
+
+    /// Once a scan finishes with one folder holding nearly all of it, offers to focus on that
+    /// folder, or focuses on it right away if the settings ask to.
+    fn show_dominant_banner(&mut self, ui: &mut egui::Ui) {
+        if !self.dominant_checked && self.model.snapshot().complete && let Some(tree) = &self.view_tree {
+            self.dominant_checked = true;
+            self.dominant = dominant::dominant_path(tree, self.size_metric);
+            if !self.dominant.is_empty() && self.settings.focus_dominant && self.root_path.is_empty() {
+                let share = dominant::share(tree, &self.dominant, self.size_metric);
+                let path = std::mem::take(&mut self.dominant);
+                self.status_message = Some(format!("Focused on {}, which holds {:.0}% of the scan. The path bar leads back.", path.join("/"), share * 100.0));
+                self.reset_navigation(&path);
+                self.layout = None;
+            }
+        }
+        let Some(tree) = self.view_tree.as_ref().filter(|_| !self.dominant.is_empty() && self.root_path.is_empty()) else {
+            return;
+        };
+        let share = dominant::share(tree, &self.dominant, self.size_metric);
+        let name = self.dominant.join("/");
+        ui.horizontal(|ui| {
+            ui.label(format!("{:.0}% of the scan is in {}.", share * 100.0, name));
+            if ui.button(format!("Focus on {}", name)).clicked() {
+                let path = std::mem::take(&mut self.dominant);
+                self.reset_navigation(&path);
+                self.layout = None;
+            }
+            if ui.small_button("Dismiss").clicked() {
+                self.dominant.clear();
+            }
+        });
+    }
+
+    /// While a scan is paused on a folder whose share stopped answering, asks whether to
+    /// retry it or skip what can't be reached.
+    fn show_unreachable(&mut self, ui: &mut egui::Ui) {
+        let Some(path) = self.scan_monitor.unreachable().filter(|_| self.scan_receiver.is_some()) else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            ui.colored_label(ui.visuals().error_fg_color, format!("Lost the connection to {}. The scan is paused.", path.display()))
+                .on_hover_text("The network share or drive it is on stopped answering");
+            if ui.button("Retry").on_hover_text("Read it again, once the share is back").clicked() {
+                self.scan_monitor.reconnect(Reconnection::Retry);
+            }
+            if ui.button("Skip").on_hover_text("Leave out what can't be reached for the rest of this scan").clicked() {
+                self.scan_monitor.reconnect(Reconnection::Skip);
+            }
+            if ui.button("Cancel scan").clicked() {
+                self.cancel_scan();
+            }
+        });
+    }
+
+    /// Draws the window asking for the user name and password of a network share, while
+    /// one is needed.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_credentials_window(&mut self, ctx: &egui::Context) {
+        let Some(credentials) = &mut self.credentials else {
+            return;
+        };
+        let (mut connect, mut cancelled) = (false, false);
+        egui::Window::new("Connect to share").collapsible(false).resizable(false).show(ctx, |ui| {
+            ui.label(format!("{} asks who is connecting.", credentials.share));
+            egui::Grid::new("credentials").num_columns(2).show(ui, |ui| {
+                ui.label("User name");
+                ui.text_edit_singleline(&mut credentials.user);
+                ui.end_row();
+                ui.label("Password");
+                let password = ui.add(egui::TextEdit::singleline(&mut credentials.password).password(true));
+                connect = password.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                ui.end_row();
+            });
+            ui.horizontal(|ui| {
+                connect |= ui.add_enabled(!credentials.user.is_empty(), egui::Button::new("Connect and scan")).clicked();
+                cancelled = ui.button("Cancel").clicked();
+            });
+        });
+        if cancelled {
+            self.credentials = None;
+        } else if connect && let Some(credentials) = self.credentials.take() {
+            let message = format!("Connecting to {}...", credentials.share);
+            self.spawn_task(move || {
+                network::connect(&credentials).map_err(|e| format!("Failed to connect to {}: {}", credentials.share, e))?;
+                Ok(TaskOutcome::ShareConnected(credentials.share))
+            });
+            self.status_message = Some(message);
+        }
+    }
+
+    /// While a scan runs, points out the directories that are slow to list, which usually
+    /// means a slow network mount or a failing disk, so they can be skipped.
+    fn show_slow_directories(&mut self, ui: &mut egui::Ui) {
+        if self.scan_receiver.is_none() {
+            return;
+        }
+        let stuck = self.scan_monitor.stuck();
+        let slow = self.scan_monitor.slow();
+        if stuck.is_none() && slow.is_empty() {
+            return;
+        }
+        let (mut skip, mut exclude, mut cancel, mut abort) = (None, None, false, false);
+        if let Some((path, elapsed)) = self.scan_monitor.stalled() {
+            ui.horizontal(|ui| {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("No answer from {} for {} s. The disk or network share may have hung.", path.display(), elapsed.as_secs()),
+                )
+                .on_hover_text("A folder is only skipped once the read it hangs on returns, which may be never");
+                if Some(&path) != self.scanned_path.as_ref()
+                    && ui.button("Skip").on_hover_text("Leave it out of this scan, should it answer, and of the next ones").clicked()
+                {
+                    skip = Some(path.clone());
+                }
+                abort = ui.button("Abort scan").on_hover_text("Stop waiting, and keep what was scanned so far").clicked();
+            });
+        } else if let Some((path, elapsed)) = &stuck {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.colored_label(
+                    ui.visuals().warn_fg_color,
+                    format!("Listing {} has taken {} s so far.", path.display(), elapsed.as_secs()),
+                )
+                .on_hover_text("This usually means a slow network drive or a failing disk");
+                if Some(path) != self.scanned_path.as_ref()
+                    && ui.button("Skip").on_hover_text("Leave it out of this scan and the next ones").clicked()
+                {
+                    skip = Some(path.clone());
+                }
+                cancel = ui.button("Cancel scan").clicked();
+            });
+        }
+        if !slow.is_empty() {
+            egui::CollapsingHeader::new(format!("Slow folders ({})", slow.len())).id_salt("slow_folders").show(ui, |ui| {
+                egui::Grid::new("slow_folders").striped(true).show(ui, |ui| {
+                    for folder in &slow {
+                        ui.label(folder.path.display().to_string());
+                        ui.label(format!("{:.1} s", folder.seconds));
+                        if ui.small_button("Exclude").on_hover_text("Leave it out of the next scans").clicked() {
+                            exclude = Some(folder.path.clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        }
+        if let Some(path) = skip {
+            self.scan_monitor.skip(&path);
+            self.exclude_from_scans(&path);
+        } else if let Some(path) = exclude {
+            self.exclude_from_scans(&path);
+        }
+        if cancel {
+            self.cancel_scan();
+        } else if abort {
+            self.abort_scan();
+        }
+    }
+
+    /// Stops waiting for the running scan, whose thread is left behind if it hangs for good,
+    /// and keeps the partial tree it published as the result.
+    fn abort_scan(&mut self) {
+        self.cancel_scan();
+        self.scan_receiver = None;
+        let partial = self.model.snapshot().tree;
+        // Taking over publishing keeps the scan from replacing the tree should it ever resume.
+        let publisher = self.model.publisher();
+        if let Some(tree) = partial {
+            publisher.publish_shared(tree, true);
+        }
+        self.sync_with_model();
+        self.status_message = Some("The scan was aborted, so the folders it hadn't finished are missing.".to_string());
+    }
+
+    /// Forgets the state tied to the previous tree, ahead of a new one being published.
+    fn reset_for_new_tree(&mut self) {
+        self.scan_error = None;
+        self.root_path.clear();
+        self.dominant_checked = false;
+        self.dominant.clear();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.text_preview.clear();
+            self.watchdog = None;
+            if let Some(search) = self.duplicates.take() {
+                search.cancel.store(true, Ordering::Relaxed);
+            }
+        }
+        self.excluded.clear();
+        self.selected_path = None;
+        self.pending_selection = None;
+        self.scan_mounts.clear();
+        self.scan_stats = None;
+        self.cow_usage = None;
+        self.owner_report = None;
+        self.hardlink_groups = None;
+        self.pinned_tooltip = None;
+        self.budget_violations.clear();
+        self.active_tab = None;
+        self.git_status = None;
+        self.git_receiver = None;
+        self.git_totals = None;
+        self.git_filter = None;
+        self.rescanned_from = None;
+        self.recent_changes = None;
+        self.changes_receiver = None;
+        self.artifacts = None;
+        self.artifacts_to_clean.clear();
+        self.confirm_clean = false;
+        self.games = None;
+        self.log_report = None;
+        self.ticked.clear();
+        self.tag_filter = None;
+        // Rescanning the same folder reads its notes again, so the ones not saved yet go first.
+        self.flush_notes();
+        self.notes = self.load_notes();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.compression = None;
+            self.archive_path = None;
+        }
+    }
+
+    /// Reads the notes of the scanned folder. The web build has nowhere to keep them.
+    fn load_notes(&mut self) -> Notes {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(root) = &self.scanned_path {
+            return notes::load(root).unwrap_or_else(|e| {
+                self.status_message = Some(e);
+                Notes::new()
+            });
+        }
+        Notes::new()
+    }
+
+    /// Changes the note of the node at `path` from the scan root to `note`, and saves the
+    /// notes of the scanned folder.
+    fn set_note(&mut self, path: &[String], note: Note) {
+        let tags_changed = self.notes.get(&notes::key(path)).map_or(&[][..], |n| &n.tags) != note.tags;
+        if note.is_empty() {
+            self.notes.remove(&notes::key(path));
+        } else {
+            self.notes.insert(notes::key(path), note);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.notes_changed = Some(Instant::now());
+        }
+        if tags_changed && self.tag_filter.is_some() {
+            self.rebuild_view();
+        }
+    }
+
+    /// Saves the notes next to the scanned folder, if it is on this machine.
+    fn save_notes(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(root) = &self.scanned_path
+            && let Err(e) = notes::save(root, &self.notes)
+        {
+            self.status_message = Some(e);
+        }
+    }
+
+    /// Saves the notes if they changed since they were last saved.
+    fn flush_notes(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.notes_changed.take().is_some() {
+            self.save_notes();
+        }
+    }
+
+    /// Saves the notes once they stopped changing for `NOTES_SAVE_DELAY`, and wakes the app up
+    /// to do so until then.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_notes_after_pause(&mut self, ctx: &egui::Context) {
+        let Some(changed) = self.notes_changed else {
+            return;
+        };
+        match NOTES_SAVE_DELAY.checked_sub(changed.elapsed()) {
+            Some(left) if !left.is_zero() => ctx.request_repaint_after(left),
+            _ => self.flush_notes(),
+        }
+    }
+
+    /// Draws the controls to color and filter the view by tag, once anything is tagged.
+    fn show_tag_controls(&mut self, ui: &mut egui::Ui) {
+        if self.notes.values().all(|n| n.tags.is_empty()) && self.tag_filter.is_none() {
+            return;
+        }
+        ui.separator();
+        ui.checkbox(&mut self.color_by_tags, "Color by tag");
+        let mut filter = self.tag_filter;
+        egui::ComboBox::from_id_salt("tag_filter")
+            .selected_text(filter.map_or("All tags", Tag::label))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut filter, None, "All tags");
+                for tag in Tag::ALL {
+                    ui.selectable_value(&mut filter, Some(tag), tag.label());
+                }
+            });
+        if filter != self.tag_filter {
+            self.tag_filter = filter;
+            self.rebuild_view();
+        }
+    }
+
+    /// Sizes the view by `metric`, redrawing it if that changes anything.
+    fn set_size_metric(&mut self, metric: SizeMetric) {
+        if metric == self.size_metric {
+            return;
+        }
+        self.size_metric = metric;
+        // New scans and snapshots are shown the way the current one is.
+        self.scan_options.metric = metric;
+        if let Some(options) = &mut self.scanned_with {
+            options.metric = metric;
+        }
+        self.minimap.invalidate();
+        self.layout = None;
+        if self.scan_receiver.is_none() {
+            self.check_budgets();
+        }
+    }
+
+    /// Limits the treemap to `max_depth` levels, redrawing it if that changes anything.
+    fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        if max_depth != self.max_depth {
+            self.max_depth = max_depth;
+            self.layout = None;
+        }
+    }
+
+    /// Draws a checkbox to limit how many levels the treemap shows, and a slider for how many.
+    fn show_depth_control(&mut self, ui: &mut egui::Ui) {
+        let mut limited = self.max_depth.is_some();
+        let mut depth = self.max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut limited, "Show only")
+                .on_hover_text("Deeper files and folders are shown as part of the folder holding them");
+            ui.add_enabled(limited, egui::Slider::new(&mut depth, 1..=MAX_DEPTH_LIMIT).suffix(" levels"));
+        });
+        self.set_max_depth(limited.then_some(depth));
+    }
+
+    /// Checks the finished scan against the user's budgets.
+    fn check_budgets(&mut self) {
+        self.budget_violations = match self.model.snapshot().tree {
+            Some(tree) => budgets::check(&tree, &self.settings.budgets.budgets, self.size_metric),
+            None => Vec::new(),
+        };
+    }
+
+    /// If the current view root is on a copy-on-write filesystem, returns its path from
+    /// the scan root, its path on disk, and the filesystem.
+    fn current_cow_root(&self) -> Option<(Vec<String>, PathBuf, CowFilesystem)> {
+        let path = collapse::to_canonical_path(&self.current_root_path());
+        // The innermost mount containing the folder is the one holding it.
+        let mount = self
+            .scan_mounts
+            .iter()
+            .filter(|m| !m.skipped && path.starts_with(&m.path))
+            .max_by_key(|m| m.path.len())?;
+        let filesystem = CowFilesystem::detect(&mount.volume.file_system)?;
+        let mut on_disk = self.scanned_path.as_ref()?.clone();
+        on_disk.extend(&path);
+        Some((path, on_disk, filesystem))
+    }
+
+    /// Draws the hard link groups of the scan, or a button to look for them.
+    fn show_hardlinks(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Hard links");
+        let Some(groups) = &self.hardlink_groups else {
+            ui.label("Files with several hard links take up space only once, and deleting one link frees nothing.");
+            if ui.add_enabled(self.can_search_scan(), egui::Button::new("Find hard links")).clicked() {
+                self.find_hardlinks();
+            }
+            return;
+        };
+        if groups.is_empty() {
+            ui.label("No file in this scan has more than one hard link.");
+            return;
+        }
+        for group in groups {
+            let title = format!(
+                "{} — inode {}, {} links, {}",
+                group.paths[0],
+                group.inode,
+                group.link_count,
+                format::format_size(group.size)
+            );
+            egui::CollapsingHeader::new(title).id_salt(group.inode).show(ui, |ui| {
+                for path in &group.paths {
+                    ui.label(path);
+                }
+                if group.has_links_elsewhere() {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("Links outside this scan: {}", group.link_count - group.paths.len() as u64),
+                    );
+                }
+            });
+        }
+    }
+
+    /// Draws the duplicate files found in the scan and how far the search has got, or a
+    /// button to start it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_duplicates(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Duplicate files");
+        let Some(search) = self.duplicates.clone() else {
+            let snapshot = self.model.snapshot();
+            let ready = snapshot.complete && snapshot.tree.is_some() && self.scanned_path.is_some();
+            let unfinished = self.scanned_path.is_some() && self.settings.unfinished_duplicates == self.scanned_path;
+            let label = if unfinished {
+                ui.label("The search for duplicates in this folder was stopped before it finished.");
+                "Resume"
+            } else {
+                ui.label("Files of the same size are compared by content, the largest first. Hashes are kept, so a search can be stopped and resumed later.");
+                "Find duplicates"
+            };
+            if ui.add_enabled(ready, egui::Button::new(label)).clicked() {
+                self.find_duplicates();
+            }
+            return;
+        };
+        let (settled, total) = (search.settled.load(Ordering::Relaxed), search.total.load(Ordering::Relaxed));
+        if search.done.load(Ordering::Relaxed) {
+            if !search.cancel.load(Ordering::Relaxed) && self.settings.unfinished_duplicates == self.scanned_path {
+                self.settings.unfinished_duplicates = None;
+            }
+        } else {
+            ui.horizontal(|ui| {
+                let fraction = if total > 0 { settled as f32 / total as f32 } else { 0.0 };
+                let text = format!("{} of {}", format::format_size(settled), format::format_size(total));
+                ui.add(egui::ProgressBar::new(fraction).desired_width(200.0).text(text));
+                let paused = search.paused.load(Ordering::Relaxed);
+                if ui.button(if paused { "Resume" } else { "Pause" }).clicked() {
+                    search.paused.store(!paused, Ordering::Relaxed);
+                }
+                if ui.button("Stop").on_hover_text("The files hashed so far aren't read again when the search is resumed").clicked() {
+                    search.cancel.store(true, Ordering::Relaxed);
+                }
+            });
+            ui.ctx().request_repaint_after(Duration::from_millis(500));
+        }
+        let found = search.found();
+        if found.is_empty() {
+            if search.done.load(Ordering::Relaxed) {
+                ui.label(if search.cancel.load(Ordering::Relaxed) { "The search was stopped." } else { "No file in this scan has a copy." });
+            }
+            return;
+        }
+        let wasted: u64 = found.iter().map(|g| g.wasted()).sum();
+        ui.label(format!("{} could be freed by keeping one file of each group.", format::format_size(wasted)));
+        for group in found.iter().take(MAX_DUPLICATE_GROUPS) {
+            let title = format!(
+                "{} — {} copies of {}, {} to free",
+                group.paths[0],
+                group.paths.len(),
+                format::format_size(group.size),
+                format::format_size(group.wasted())
+            );
+            egui::CollapsingHeader::new(title).id_salt(("duplicates", &group.paths[0])).show(ui, |ui| {
+                for path in &group.paths {
+                    ui.label(path);
+                }
+            });
+        }
+        if found.len() > MAX_DUPLICATE_GROUPS {
+            ui.weak(format!("And {} smaller groups.", found.len() - MAX_DUPLICATE_GROUPS));
+        }
+    }
+
+    /// Starts looking for duplicate files in the finished scan on a thread of its own.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn find_duplicates(&mut self) {
+        let snapshot = self.model.snapshot();
+        let (Some(tree), Some(root)) = (snapshot.tree.filter(|_| snapshot.complete), self.scanned_path.clone()) else {
+            return;
+        };
+        let search = Arc::new(duplicates::Progress::default());
+        let progress = Arc::clone(&search);
+        self.settings.unfinished_duplicates = Some(root.clone());
+        thread::spawn(move || duplicates::find(&root, &tree, duplicates::HashCache::default_path().as_deref(), &progress));
+        self.duplicates = Some(search);
+    }
+
+    /// Whether the finished scan can be searched in the background now.
+    fn can_search_scan(&self) -> bool {
+        let snapshot = self.model.snapshot();
+        snapshot.complete && snapshot.tree.is_some() && self.scanned_path.is_some() && self.task_receiver.is_none()
+    }
+
+    /// Looks for the hard link groups of the finished scan in the background.
+    fn find_hardlinks(&mut self) {
+        let snapshot = self.model.snapshot();
+        if let (Some(tree), Some(root)) = (snapshot.tree.filter(|_| snapshot.complete), self.scanned_path.clone()) {
+            self.spawn_task(move || Ok(TaskOutcome::HardlinksFound(hardlinks::find_groups(&root, &tree))));
+        }
+    }
+
+    /// Looks for the games installed in the finished scan in the background.
+    fn find_games(&mut self) {
+        let snapshot = self.model.snapshot();
+        let metric = self.size_metric;
+        if let (Some(tree), Some(root)) = (snapshot.tree.filter(|_| snapshot.complete), self.scanned_path.clone()) {
+            self.spawn_task(move || Ok(TaskOutcome::GamesFound(games::find(&root, &tree, metric))));
+        }
+    }
+
+    /// Draws the copy-on-write accounting of the current view root, if it is on such a filesystem.
+    fn show_cow_usage(&mut self, ui: &mut egui::Ui) {
+        let Some((path, on_disk, filesystem)) = self.current_cow_root() else {
+            return;
+        };
+        ui.separator();
+        ui.heading("Copy-on-write accounting");
+        match &self.cow_usage {
+            Some((measured, usage)) if *measured == path => {
+                let scope = if usage.whole_dataset { " (whole dataset)" } else { "" };
+                ui.label(format!("Logical size{}: {}", scope, format::format_size(usage.logical)));
+                ui.label(format!("Reclaimable if deleted{}: {}", scope, format::format_size(usage.reclaimable)));
+                ui.label(format!(
+                    "Shared with snapshots or other files: {}",
+                    format::format_size(usage.shared)
+                ));
+                if let Some(ratio) = usage.compression_ratio {
+                    ui.label(format!("Compression ratio: {:.2}x", ratio));
+                }
+            }
+            _ => {
+                ui.label("Sizes in the treemap are logical. Blocks shared with snapshots or copies aren't freed by deleting.");
+                let busy = self.task_receiver.is_some();
+                if ui.add_enabled(!busy, egui::Button::new("Measure reclaimable space")).clicked() {
+                    self.spawn_task(move || {
+                        let usage = cow::measure(&on_disk, filesystem).map_err(|e| format!("Failed to measure: {}", e))?;
+                        Ok(TaskOutcome::Measured(path, usage))
+                    });
+                }
+            }
+        }
+    }
+
+    /// Draws who owns how much of the current view root, next to their quotas on its volume,
+    /// or a button to measure it.
+    fn show_owners(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Owners and quotas");
+        let path = collapse::to_canonical_path(&self.current_root_path());
+        let snapshot = self.model.snapshot();
+        let busy = self.task_receiver.is_some();
+        let measured = self.owner_report.as_ref().is_some_and(|(measured, _)| *measured == path);
+        if !measured {
+            ui.label("Shows which users own the files in the folder shown, and how close each is to their quota.");
+            let label = if self.owner_report.is_some() { "Measure this folder by owner" } else { "Measure by owner" };
+            if ui.add_enabled(!busy && self.can_search_scan(), egui::Button::new(label)).clicked()
+                && let (Some(tree), Some(root)) = (snapshot.tree.filter(|_| snapshot.complete), self.scanned_path.clone())
+            {
+                let mount_point = self
+                    .scan_mounts
+                    .iter()
+                    .filter(|m| !m.skipped && path.starts_with(&m.path))
+                    .max_by_key(|m| m.path.len())
+                    .map(|m| m.volume.mount_point.clone());
+                let path = path.clone();
+                self.spawn_task(move || {
+                    let node = tree.find(&path).ok_or("The folder is no longer in the scan.")?;
+                    let mut on_disk = root;
+                    on_disk.extend(&path);
+                    let report = owners::measure(&on_disk, node, mount_point.as_deref()).map_err(|e| format!("Failed to read the owners: {}", e))?;
+                    Ok(TaskOutcome::OwnersMeasured(path, report))
+                });
+            }
+        }
+        let Some((measured, report)) = &self.owner_report else {
+            return;
+        };
+        if !measured.is_empty() && *measured != path {
+            ui.weak(format!("Measured in {}", measured.join("/")));
+        }
+        if report.owners.is_empty() {
+            ui.label("No files to attribute.");
+        } else {
+            let total: u64 = report.owners.iter().map(|o| o.allocated).sum();
+            egui::Grid::new("owners").striped(true).show(ui, |ui| {
+                for header in ["Owner", "Files", "Here", "Share", "Quota used", "Limit"] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                for owner in &report.owners {
+                    let (size, allocated) = (owner.size, owner.allocated);
+                    ui.label(&owner.name).on_hover_text(format!("uid {}", owner.uid));
+                    ui.label(format::format_count(owner.files));
+                    ui.label(format::format_size(match self.size_metric {
+                        SizeMetric::Apparent => size,
+                        SizeMetric::Allocated => allocated,
+                    }));
+                    ui.add(egui::ProgressBar::new(allocated as f32 / total.max(1) as f32).desired_width(80.0).show_percentage());
+                    match report.quota_of(&owner.name) {
+                        Some(quota) => {
+                            let used = egui::RichText::new(format::format_size(quota.used));
+                            if quota.exceeded() {
+                                ui.colored_label(ui.visuals().error_fg_color, used.strong());
+                            } else {
+                                ui.label(used);
+                            }
+                            match quota.limit() {
+                                Some(limit) => ui.add(
+                                    egui::ProgressBar::new((quota.used as f32 / limit as f32).min(1.0))
+                                        .desired_width(120.0)
+                                        .text(format::format_size(limit)),
+                                ),
+                                None => ui.label("None"),
+                            };
+                        }
+                        None => {
+                            ui.label("");
+                            ui.label("");
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        }
+        if let Err(e) = &report.quotas {
+            ui.weak(format!("Quotas unavailable: {}", e));
+        }
+    }
+
+    /// Looks up the volumes covered by the finished scan.
+    fn refresh_scan_mounts(&mut self) {
+        let Some(root) = self.scanned_path.as_ref().and_then(|p| p.canonicalize().ok()) else {
+            return;
+        };
+        self.scan_mounts = mounts::mounts_in_scan(&volumes::list_volumes(), &root, self.scan_options.one_file_system);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.watchdog = Some(watchdog::VolumeWatchdog::start(&self.scan_mounts));
+        }
+    }
+
+    /// Whether the node at `path` from the scan root is on a volume that was removed since
+    /// the scan, so its files are no longer on disk.
+    fn volume_gone(&self, path: &[String]) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.watchdog.as_ref().is_some_and(|w| w.is_gone(path));
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = path;
+            false
+        }
+    }
+
+    /// Points out the volumes of the scan that were removed while it is browsed.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_removed_volumes(&mut self, ui: &mut egui::Ui) {
+        let Some(watchdog) = &self.watchdog else {
+            return;
+        };
+        // The watchdog checks in the background, so look again for what it found.
+        ui.ctx().request_repaint_after(watchdog::CHECK_INTERVAL);
+        for path in watchdog.gone() {
+            let mount = self.scan_mounts.iter().find(|m| m.path == path).map_or_else(|| path.join("/"), |m| m.volume.label());
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                format!("{} was removed. What is shown from it is no longer on disk, so the actions on its files are disabled.", mount),
+            )
+            .on_hover_text("They come back once the volume is mounted again at the same place");
+        }
+    }
+
+    /// Starts finding what changed between the previous scan of the folder and `tree` on a
+    /// background thread.
+    fn find_changes(&mut self, before: Arc<FileSystemNode>, tree: Arc<FileSystemNode>) {
+        let (sender, receiver) = mpsc::channel();
+        self.changes_receiver = Some(receiver);
+        let metric = self.size_metric;
+        thread::spawn(move || {
+            let _ = sender.send(changes::diff(&before, &tree, metric));
+        });
+    }
+
+    /// Starts asking git about the files of the finished scan on a background thread.
+    fn refresh_git_status(&mut self) {
+        let Some(root) = self.scanned_path.clone().filter(|_| cfg!(not(target_arch = "wasm32"))) else {
+            return;
+        };
+        let (sender, receiver) = mpsc::channel();
+        self.git_receiver = Some(receiver);
+        thread::spawn(move || {
+            let status = GitStatus::read(&root).unwrap_or_else(|e| {
+                eprintln!("Failed to read the git status of {}: {}", root.display(), e);
+                None
+            });
+            let _ = sender.send(status);
+        });
+    }
+
+    /// Draws the controls to color and filter the view by git status, if the scan is in a
+    /// git repository.
+    fn show_git_controls(&mut self, ui: &mut egui::Ui) {
+        if self.git_status.is_none() {
+            return;
+        }
+        ui.separator();
+        ui.checkbox(&mut self.color_by_git, "Color by git status");
+        let mut filter = self.git_filter;
+        egui::ComboBox::from_id_salt("git_filter")
+            .selected_text(filter.map_or("All files", GitClass::label))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut filter, None, "All files");
+                for class in GitClass::ALL {
+                    ui.selectable_value(&mut filter, Some(class), class.label());
+                }
+            });
+        if filter != self.git_filter {
+            self.git_filter = filter;
+            self.rebuild_view();
+        }
+    }
+
+    /// Draws how much of the scan is tracked by git, untracked, ignored, and git's own data.
+    fn show_git_totals(&mut self, ui: &mut egui::Ui) {
+        let Some(status) = &self.git_status else {
+            return;
+        };
+        if self.git_totals.is_none_or(|(metric, _)| metric != self.size_metric)
+            && let Some(tree) = self.model.snapshot().tree
+        {
+            self.git_totals = Some((self.size_metric, status.totals(&tree, self.size_metric)));
+        }
+        let Some((_, totals)) = self.git_totals else {
+            return;
+        };
+        ui.separator();
+        ui.heading("Git repository");
+        egui::Grid::new("git_totals").striped(true).show(ui, |ui| {
+            for (class, total) in GitClass::ALL.into_iter().zip(totals) {
+                ui.colored_label(class.color(), "■");
+                ui.label(class.label());
+                format::size_label(ui, total);
+                ui.end_row();
+            }
+        });
+    }
+
+    /// Draws the combined size of the build output folders in the finished scan, with a list
+    /// of them to pick the ones to move to the trash.
+    fn show_artifacts(&mut self, ui: &mut egui::Ui) {
+        let snapshot = self.model.snapshot();
+        let Some(tree) = snapshot.tree.filter(|_| snapshot.complete) else {
+            return;
+        };
+        if self.artifacts.as_ref().is_none_or(|(metric, _)| *metric != self.size_metric) {
+            self.artifacts = Some((self.size_metric, artifacts::find(&tree, self.size_metric)));
+        }
+        let Some((_, found)) = &self.artifacts else {
+            return;
+        };
+        ui.separator();
+        let total: u64 = found.iter().map(|a| a.size).sum();
+        ui.heading(format!("Build artifacts: {}", format::format_size(total)));
+        if found.is_empty() {
+            ui.label("No build output folders (target, build, dist, node_modules, .venv, __pycache__) in this scan.");
+            return;
+        }
+        egui::CollapsingHeader::new(format!("{} folders", found.len())).id_salt("artifacts").show(ui, |ui| {
+            for artifact in found {
+                let mut ticked = self.artifacts_to_clean.contains(&artifact.path);
+                let text = format!("{} — {}, {}", artifact.path.join("/"), artifact.kind, format::format_size(artifact.size));
+                if ui.checkbox(&mut ticked, text).changed() {
+                    if ticked {
+                        self.artifacts_to_clean.push(artifact.path.clone());
+                    } else {
+                        self.artifacts_to_clean.retain(|p| *p != artifact.path);
+                    }
+                }
+            }
+        });
+        if cfg!(not(target_arch = "wasm32")) && self.scanned_path.is_some() {
+            let busy = self.task_receiver.is_some() || self.scan_receiver.is_some();
+            let ticked = self.artifacts_to_clean.len();
+            let gone = self.artifacts_to_clean.iter().any(|p| self.volume_gone(p));
+            let button = egui::Button::new(format!("Move {} ticked to the trash...", ticked));
+            if ui.add_enabled(!busy && ticked > 0 && !gone && !read_only::is_enabled(), button).clicked() {
+                self.confirm_clean = true;
+            }
+        }
+    }
+
+    /// Asks the user to confirm moving the ticked build output folders to the trash.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_clean_confirmation(&mut self, ctx: &egui::Context) {
+        if !self.confirm_clean {
+            return;
+        }
+        let Some((_, found)) = &self.artifacts else {
+            self.confirm_clean = false;
+            return;
+        };
+        let ticked: Vec<&Artifact> = found.iter().filter(|a| self.artifacts_to_clean.contains(&a.path)).collect();
+        let total: u64 = ticked.iter().map(|a| a.size).sum();
+        let (mut confirmed, mut cancelled) = (false, false);
+        egui::Window::new("Clean build artifacts?").collapsible(false).resizable(false).show(ctx, |ui| {
+            ui.label(format!(
+                "Move {} folders ({}) to the trash? They can be restored from there, or rebuilt.",
+                ticked.len(),
+                format::format_size(total)
+            ));
+            egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                for artifact in &ticked {
+                    ui.label(artifact.path.join("/"));
+                }
+            });
+            ui.horizontal(|ui| {
+                confirmed = ui.button("Move to trash").clicked();
+                cancelled = ui.button("Cancel").clicked();
+            });
+        });
+        if cancelled {
+            self.confirm_clean = false;
+        }
+        if confirmed
+            && let Some(root) = self.scanned_path.clone()
+        {
+            self.confirm_clean = false;
+            let paths: Vec<Vec<String>> = ticked.iter().map(|a| a.path.clone()).collect();
+            let hooks = self.settings.hooks.clone();
+            self.spawn_task(move || {
+                let (moved, failures) = hooks::guard_deletion(&hooks, &root, &paths, artifacts::move_to_trash);
+                Ok(TaskOutcome::Trashed(moved, failures))
+            });
+        }
+    }
+
+    /// Draws the window to archive a folder, while one is picked.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_archive_window(&mut self, ctx: &egui::Context) {
+        let Some(path) = self.archive_path.clone() else {
+            return;
+        };
+        let Some(size) = self.model.snapshot().tree.and_then(|tree| tree.find(&path).map(|node| node.size)) else {
+            self.archive_path = None;
+            return;
+        };
+        let busy = self.task_receiver.is_some();
+        let (mut start, mut cancelled) = (false, false);
+        egui::Window::new("Archive folder").collapsible(false).resizable(false).show(ctx, |ui| {
+            ui.label(format!("Pack {} ({}) into an archive.", path.join("/"), format::format_size(size)));
+            for format in archive::Format::ALL {
+                ui.radio_value(&mut self.archive_format, format, format.label());
+            }
+            ui.add_enabled(
+                !read_only::is_enabled(),
+                egui::Checkbox::new(&mut self.archive_then_trash, "Move the folder to the trash once it is archived"),
+            );
+            ui.horizontal(|ui| {
+                start = ui
+                    .add_enabled(!busy, egui::Button::new("Choose where to save..."))
+                    .on_disabled_hover_text("Another task is running")
+                    .clicked();
+                cancelled = ui.button("Cancel").clicked();
+            });
+        });
+        if cancelled {
+            self.archive_path = None;
+        }
+        if !start {
+            return;
+        }
+        let Some(root) = self.scanned_path.clone() else {
+            return;
+        };
+        let format = self.archive_format;
+        let name = path.last().cloned().unwrap_or_else(|| "archive".to_string());
+        let Some(file) = rfd::FileDialog::new().set_file_name(format!("{}.{}", name, format.extension())).save_file()
+        else {
+            return;
+        };
+        self.archive_path = None;
+        let progress = Arc::new(AtomicU64::new(0));
+        let then_trash = self.archive_then_trash;
+        let hooks = self.settings.hooks.clone();
+        let task_progress = Arc::clone(&progress);
+        self.spawn_task(move || {
+            let source = path.iter().fold(root.clone(), |dir, name| dir.join(name));
+            archive::create(&source, &file, format, &task_progress)
+                .map_err(|e| format!("Failed to archive {}: {}", source.display(), e))?;
+            let trashed = then_trash.then(|| match hooks::guard_deletion(&hooks, &root, std::slice::from_ref(&path), artifacts::move_to_trash) {
+                (_, failures) if !failures.is_empty() => Err(failures.join("\n")),
+                _ => Ok(path),
+            });
+            Ok(TaskOutcome::Archived(file, trashed))
+        });
+        self.archive_progress = Some((progress, size));
+    }
+
+    /// Removes the folders at `paths` (from the scan root), which were deleted, from the tree.
+    fn remove_from_tree(&mut self, paths: &[Vec<String>]) {
+        let root = self.current_root_path();
+        let layout = self.layout.take();
+        // Only the ancestors of the removed nodes change. Dropping the view's hold on the
+        // tree first lets the model change them in place rather than in a copy.
+        self.view_tree = None;
+        self.model.edit(|tree| {
+            for path in paths {
+                tree.remove(path);
+            }
+        });
+        self.sync_with_model();
+        // The treemap is laid out again only along the removed paths within the folder
+        // shown, unless collapsed chains make its paths differ from the tree's.
+        if let Some(layout) = layout
+            && !self.collapse_chains
+            && self.current_root_path() == root
+        {
+            let removed = paths.iter().filter_map(|p| p.strip_prefix(root.as_slice())).map(<[String]>::to_vec).collect();
+            let frame_size = self.last_frame_size;
+            self.layout_update = Some(LayoutUpdate { generation: self.model_generation, root, frame_size, layout, removed });
+        }
+        let removed = |path: &[String]| paths.iter().any(|p| path.starts_with(p));
+        self.ticked.retain(|p| !removed(p));
+        self.cow_usage.take_if(|(p, _)| removed(p));
+        // Removing anything within the measured folder changes what its owners own.
+        self.owner_report.take_if(|(p, _)| removed(p) || paths.iter().any(|r| r.starts_with(p)));
+        #[cfg(not(target_arch = "wasm32"))]
+        self.compression.take_if(|(p, _)| removed(p));
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(search) = &self.duplicates {
+            let mut found = search.found.lock().unwrap_or_else(|e| e.into_inner());
+            for group in found.iter_mut() {
+                group.paths.retain(|path| !removed(&path.split('/').map(str::to_string).collect::<Vec<_>>()));
+            }
+            found.retain(|group| group.paths.len() > 1);
+        }
+        self.pinned_tooltip.take_if(|(p, _)| removed(p));
+        if let Some((logs, _)) = &mut self.log_report {
+            logs.retain(|log| !removed(&log.path));
+        }
+        self.artifacts = None;
+        self.artifacts_to_clean.retain(|p| !paths.contains(p));
+        self.games = None;
+        self.git_totals = None;
+        self.hardlink_groups = None;
+        self.check_budgets();
+    }
+
+    /// Draws the games installed in the scan, largest first, or a button to look for them.
+    fn show_games(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Game libraries");
+        let Some(games) = &self.games else {
+            ui.label("Lists the games installed by Steam, Epic and GOG, by title.");
+            if ui.add_enabled(self.can_search_scan(), egui::Button::new("Find games")).clicked() {
+                self.find_games();
+            }
+            return;
+        };
+        if games.is_empty() {
+            ui.label("No game libraries in this scan.");
+            return;
+        }
+        let mut jump_to = None;
+        egui::Grid::new("games").striped(true).show(ui, |ui| {
+            for game in games {
+                if ui.link(&game.title).on_hover_text(game.path.join("/")).clicked() {
+                    jump_to = Some(game.path.clone());
+                }
+                ui.label(game.store.label());
+                format::size_label(ui, game.size);
+                ui.end_row();
+            }
+        });
+        if let Some(path) = jump_to.as_deref().and_then(|path| self.view_path_of(path)) {
+            self.navigate_to(&path);
+        } else if jump_to.is_some() {
+            self.status_message = Some("That folder is excluded from the view.".to_string());
+        }
+    }
+
+    /// Draws the statistics of the scan, if they are known.
+    fn show_scan_stats(&mut self, ui: &mut egui::Ui) {
+        let Some(stats) = &self.scan_stats else {
+            return;
+        };
+        ui.separator();
+        ui.heading("Scan statistics");
+        let mut jump_to = None;
+        egui::Grid::new("scan_stats").show(ui, |ui| {
+            ui.label("Duration");
+            ui.label(format!("{:.1} s", stats.seconds));
+            ui.end_row();
+            ui.label("Visited");
+            ui.label(format!("{} folders, {} files", stats.directories, stats.files));
+            ui.end_row();
+            ui.label("Speed");
+            ui.label(format!("{}/s", format::format_size(stats.bytes_per_second())));
+            ui.end_row();
+            ui.label("Errors");
+            if stats.errors.is_empty() {
+                ui.label("None");
+            } else {
+                let counts: Vec<String> = stats.errors.iter().map(|(kind, n)| format!("{}: {}", kind, n)).collect();
+                ui.label(counts.join(", "));
+            }
+            ui.end_row();
+            if let Some((path, depth)) = &stats.deepest {
+                ui.label("Deepest path");
+                if ui.link(format!("{} ({} levels)", path.display(), depth)).clicked() {
+                    jump_to = Some(path.clone());
+                }
+                ui.end_row();
+            }
+            if let Some((path, count)) = &stats.too_deep {
+                ui.label("Nested too deeply");
+                ui.colored_label(ui.visuals().warn_fg_color, format!("{} left out, such as {}", folders(*count), path.display()))
+                    .on_hover_text("Folders deeper than the nesting limit aren't scanned. Raise it with --max-nesting.");
+                ui.end_row();
+            }
+            if stats.volume_lost {
+                ui.label("Partial");
+                ui.colored_label(ui.visuals().error_fg_color, "The drive was removed during the scan")
+                    .on_hover_text("The scan stopped there, so the results only show what was read before.");
+                ui.end_row();
+            }
+            if !stats.cloned_files.is_empty() {
+                let shared: u64 = stats.cloned_files.iter().filter(|c| c.counted_elsewhere).map(|c| c.shared).sum();
+                ui.label("Cloned files");
+                ui.label(format!("{} sharing blocks, {} counted once", format::format_count(stats.cloned_files.len() as u64), format::format_size(shared)))
+                    .on_hover_text("Clones share their blocks until they are changed, so their disk usage only counts the shared blocks with one of them. Deleting a clone frees only what it doesn't share.");
+                ui.end_row();
+            }
+            if stats.special_files > 0 {
+                ui.label("Special files");
+                ui.label(format!("{} sockets, pipes, and devices", format::format_count(stats.special_files)))
+                    .on_hover_text("They hold no data, so they count as empty unless scanned with --count-special-files.");
+                ui.end_row();
+            }
+            if let Some((path, size)) = &stats.largest_directory {
+                ui.label("Largest folder");
+                if ui
+                    .link(format!("{} ({})", path.display(), format::format_size(*size)))
+                    .on_hover_text("The folder with the most data in its own files, not counting subfolders")
+                    .clicked()
+                {
+                    jump_to = Some(path.clone());
+                }
+                ui.end_row();
+            }
+        });
+        egui::CollapsingHeader::new(format!("Slowest folders to list ({})", stats.slowest.len()))
+            .id_salt("slowest")
+            .show(ui, |ui| {
+                egui::Grid::new("slowest_folders").striped(true).show(ui, |ui| {
+                    for slow in &stats.slowest {
+                        if ui.link(slow.path.display().to_string()).clicked() {
+                            jump_to = Some(slow.path.clone());
+                        }
+                        ui.label(format!("{:.3} s", slow.seconds));
+                        ui.end_row();
+                    }
+                });
+            });
+        if !stats.aliases.is_empty() {
+            egui::CollapsingHeader::new(format!("Counted elsewhere ({})", stats.aliases.len()))
+                .id_salt("aliases")
+                .show(ui, |ui| {
+                    ui.weak("These bind and overlay mounts show data scanned at other paths, so they were left empty.");
+                    egui::Grid::new("aliases").striped(true).show(ui, |ui| {
+                        for alias in &stats.aliases {
+                            if ui.link(alias.path.display().to_string()).clicked() {
+                                jump_to = Some(alias.path.clone());
+                            }
+                            let originals: Vec<String> = alias.originals.iter().map(|p| p.display().to_string()).collect();
+                            ui.label(format!("{} of {}", alias.kind.label(), originals.join(", ")));
+                            ui.end_row();
+                        }
+                    });
+                });
+        }
+        if let Some(path) = jump_to {
+            self.reveal(&path);
+        }
+    }
+
+    /// Draws how well the current folder would compress, or a button to estimate it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_compression(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Compressibility");
+        let path = collapse::to_canonical_path(&self.current_root_path());
+        if let Some((estimated, estimate)) = &self.compression
+            && *estimated == path
+        {
+            ui.label(estimate.describe());
+            ui.weak(format!(
+                "Estimated from {} of {} sampled files.",
+                format::format_size(estimate.sampled_bytes),
+                estimate.sampled_files
+            ));
+            return;
+        }
+        ui.label("Samples the largest files to tell how much space compressing this folder would save.");
+        let snapshot = self.model.snapshot();
+        let tree = snapshot.tree.filter(|_| snapshot.complete);
+        let busy = self.task_receiver.is_some();
+        if ui.add_enabled(!busy && tree.is_some(), egui::Button::new("Estimate compression")).clicked()
+            && let (Some(tree), Some(mut on_disk)) = (tree, self.scanned_path.clone())
+        {
+            on_disk.extend(&path);
+            self.spawn_task(move || {
+                let node = tree.find(&path).ok_or("The folder is no longer in the scan.")?;
+                let estimate = compression::estimate(&on_disk, node)?;
+                Ok(TaskOutcome::CompressionEstimated(path, estimate))
+            });
+        }
+    }
+
+    /// Draws the button that looks for log files worth rotating, comparing them with the
+    /// latest scan in the folder's history that is at least an hour old.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_log_button(&mut self, ui: &mut egui::Ui) {
+        if ui
+            .add_enabled(self.can_search_scan(), egui::Button::new("Log report..."))
+            .on_hover_text("Large and fast-growing log files, compared with the scan history")
+            .clicked()
+        {
+            self.find_logs();
+        }
+    }
+
+    /// Looks for the log files worth rotating in the finished scan in the background.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn find_logs(&mut self) {
+        let snapshot = self.model.snapshot();
+        let (Some(tree), Some(root)) = (snapshot.tree.filter(|_| snapshot.complete), self.scanned_path.clone()) else {
+            return;
+        };
+        self.spawn_task(move || {
+            let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+            let earlier = match history::History::for_root(&root) {
+                Some(history) => history
+                    .load_latest_before(now.saturating_sub(3600))
+                    .map_err(|e| format!("Failed to open the earlier scan from the history: {}", e))?,
+                None => None,
+            };
+            let earlier = earlier.as_ref().map(|s| logs::EarlierScan { tree: &s.tree, created_at: s.header.created_at });
+            let found = logs::find(&root, &tree, earlier.as_ref(), now);
+            Ok(TaskOutcome::LogsFound(found, earlier.map(|e| e.created_at)))
+        });
+    }
+
+    /// Draws the window listing the log files worth rotating.
+    fn show_log_report(&mut self, ctx: &egui::Context) {
+        let Some((logs, since)) = &self.log_report else {
+            return;
+        };
+        let mut open = true;
+        let mut jump_to = None;
+        egui::Window::new("Log files").open(&mut open).show(ctx, |ui| {
+            let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+            match since {
+                Some(since) => {
+                    let (shown, hover) = format::time_and_hover(*since, now);
+                    ui.label(format!("Growth is measured since the scan in the history from {}.", shown)).on_hover_text(hover)
+                }
+                None => ui.label("Add scans to the history to see how fast logs grow."),
+            };
+            if logs.is_empty() {
+                ui.label("No large or growing log files in this scan.");
+                return;
+            }
+            egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                egui::Grid::new("logs").striped(true).show(ui, |ui| {
+                    ui.strong("File");
+                    ui.strong("Size");
+                    ui.strong("Growth");
+                    ui.strong("Last written");
+                    ui.end_row();
+                    for log in logs {
+                        if ui.link(log.path.join("/")).clicked() {
+                            jump_to = Some(log.path.clone());
+                        }
+                        format::size_label(ui, log.size);
+                        match log.growth {
+                            Some(growth) if growth >= 0.0 => ui.label(format!("+{}/day", format::format_size(growth as u64))),
+                            Some(growth) => ui.label(format!("-{}/day", format::format_size(-growth as u64))),
+                            None if log.new => ui.weak("new"),
+                            None => ui.weak("—").on_hover_text("No earlier scan to compare with"),
+                        };
+                        match log.modified {
+                            Some(modified) => format::time_label(ui, modified, now),
+                            None => ui.label("unknown"),
+                        };
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+        if !open {
+            self.log_report = None;
+        }
+        if let Some(path) = jump_to.as_deref().and_then(|path| self.view_path_of(path)) {
+            self.navigate_to(&path);
+        } else if jump_to.is_some() {
+            self.status_message = Some("That file is excluded from the view.".to_string());
+        }
+    }
+
+    /// Draws the drive overview window listing every mounted volume.
+    fn show_drives_window(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        egui::Window::new("Drives").open(&mut open).show(ctx, |ui| {
+            if ui.button("Refresh").clicked() {
+                self.drives = volumes::list_volumes();
+            }
+            let rows = self.drives.iter().map(|v| (v.label(), v, false));
+            mounts::show_volume_table(ui, "drives", rows);
+            if cfg!(windows) {
+                self.show_system_files(ui);
+                self.show_shadow_storage(ui);
+            }
+            if cfg!(target_os = "macos") {
+                self.show_local_snapshots(ui);
+            }
+            if self.settings.disk_health {
+                self.show_disk_health(ui);
+            }
+        });
+        self.show_drives = open;
+    }
+
+    /// Draws the SMART health and temperature of each physical disk, or a button to read them.
+    fn show_disk_health(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Disk health");
+        ui.weak("What the disks report about themselves. A failing disk may stop working at any time.");
+        let busy = self.task_receiver.is_some();
+        let label = if self.disk_health.is_some() { "Check again" } else { "Check" };
+        let hint = if cfg!(windows) { "Reads the counters of the Storage module" } else { "Needs smartctl and root rights" };
+        if ui.add_enabled(!busy, egui::Button::new(label)).on_hover_text(hint).clicked() {
+            self.spawn_task(|| {
+                let disks = smart::query().map_err(|e| format!("Failed to read the health of the disks: {}", e))?;
+                Ok(TaskOutcome::DiskHealthRead(disks))
+            });
+        }
+        let Some(disks) = &self.disk_health else {
+            return;
+        };
+        if disks.is_empty() {
+            ui.label("No disk reports its health.");
+            return;
+        }
+        egui::Grid::new("disk_health").striped(true).show(ui, |ui| {
+            for header in ["Disk", "Model", "Health", "Temperature", "Powered on"] {
+                ui.strong(header);
+            }
+            ui.end_row();
+            for disk in disks {
+                ui.label(&disk.device);
+                ui.label(disk.model.as_deref().unwrap_or("Unknown"));
+                let health = egui::RichText::new(disk.health.label());
+                match disk.health {
+                    Health::Failing => ui.colored_label(ui.visuals().error_fg_color, health.strong()),
+                    Health::Warning => ui.colored_label(ui.visuals().warn_fg_color, health),
+                    Health::Passed | Health::Unknown => ui.label(health),
+                };
+                ui.label(disk.temperature.map_or("Unknown".to_string(), |t| format!("{} °C", t)));
+                ui.label(disk.power_on_hours.map_or("Unknown".to_string(), |h| format!("{} hours", format::format_count(h))));
+                ui.end_row();
+            }
+        });
+        if disks.iter().any(|d| d.health == Health::Failing) {
+            ui.colored_label(ui.visuals().error_fg_color, "Back up what you need from a failing disk before cleaning it up.");
+        }
+    }
+
+    /// Draws the local Time Machine snapshots and purgeable space of each APFS volume, or a
+    /// button to look them up.
+    fn show_local_snapshots(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Time Machine snapshots and purgeable space");
+        ui.weak(
+            "Finder counts purgeable space, local Time Machine snapshots among it, as free, so it shows more free \
+             space than this window. No folder holds that space, so a scan finds less than the used space shown here. \
+             macOS frees it when the space is needed.",
+        );
+        let busy = self.task_receiver.is_some();
+        let label = if self.local_snapshots.is_some() { "Look up again" } else { "Look up" };
+        if ui.add_enabled(!busy, egui::Button::new(label)).clicked() {
+            let volumes: Vec<PathBuf> = self
+                .drives
+                .iter()
+                .filter(|v| v.file_system.eq_ignore_ascii_case("apfs"))
+                .map(|v| v.mount_point.clone())
+                .collect();
+            self.spawn_task(move || {
+                let mut found = Vec::new();
+                let mut failure = None;
+                for volume in &volumes {
+                    match time_machine::query(volume) {
+                        Ok(snapshots) => found.push(snapshots),
+                        Err(e) => failure = Some(format!("Failed to look up the snapshots of {}: {}", volume.display(), e)),
+                    }
+                }
+                // System volumes can't always be asked, which is fine as long as some can.
+                match failure {
+                    Some(failure) if found.is_empty() => Err(failure),
+                    _ => Ok(TaskOutcome::LocalSnapshotsFound(found)),
+                }
+            });
+        }
+        let Some(volumes) = &self.local_snapshots else {
+            return;
+        };
+        egui::Grid::new("local_snapshots").striped(true).show(ui, |ui| {
+            for header in ["Volume", "Local snapshots", "Purgeable", "Free in Finder"] {
+                ui.strong(header);
+            }
+            ui.end_row();
+            let unknown = || "Unknown".to_string();
+            for volume in volumes {
+                ui.label(volume.volume.display().to_string());
+                match (volume.snapshots.first(), volume.snapshots.last()) {
+                    (Some(oldest), Some(newest)) => {
+                        ui.label(format!("{} ({} to {})", volume.snapshots.len(), oldest, newest))
+                    }
+                    _ => ui.label("None"),
+                };
+                ui.label(volume.purgeable.map_or_else(unknown, format::format_size));
+                ui.label(volume.finder_available.map_or_else(unknown, format::format_size));
+                ui.end_row();
+            }
+        });
+        if volumes.iter().any(|v| !v.snapshots.is_empty()) {
+            ui.weak("macOS doesn't tell how much each snapshot takes up. \"tmutil deletelocalsnapshots\" removes them.");
+        }
+    }
+
+    /// Draws the Recycle Bins, page files, and the like Windows keeps at the top of the
+    /// drives, with links to where Windows manages them, or a button to look for them.
+    fn show_system_files(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Recycle Bin, page file, and hibernation file");
+        ui.weak("Windows keeps these at the top of each drive. They can be large, but shouldn't be deleted by hand.");
+        let busy = self.task_receiver.is_some();
+        let label = if self.system_files.is_some() { "Look again" } else { "Look for them" };
+        if ui.add_enabled(!busy, egui::Button::new(label)).clicked() {
+            let volumes: Vec<PathBuf> = self.drives.iter().map(|v| v.mount_point.clone()).collect();
+            self.spawn_task(move || Ok(TaskOutcome::SystemFilesFound(volumes.iter().flat_map(|v| system_files::find(v)).collect())));
+        }
+        let Some(found) = &self.system_files else {
+            return;
+        };
+        if found.is_empty() {
+            ui.label("None of them is on these drives.");
+            return;
+        }
+        let mut open = None;
+        egui::Grid::new("system_files").striped(true).show(ui, |ui| {
+            for header in ["Drive", "Item", "Size", ""] {
+                ui.strong(header);
+            }
+            ui.end_row();
+            for item in found {
+                ui.label(item.volume.display().to_string());
+                ui.label(item.file.label()).on_hover_text(item.file.explanation());
+                if item.partial {
+                    ui.label(format!("at least {}", format::format_size(item.size)))
+                        .on_hover_text("Some of it couldn't be read, such as the Recycle Bins of other users.");
+                } else {
+                    format::size_label(ui, item.size);
+                }
+                if ui.link(item.file.setting()).on_hover_text(item.file.explanation()).clicked() {
+                    open = Some(item.file);
+                }
+                ui.end_row();
+            }
+        });
+        if let Some(file) = open
+            && let Err(e) = system_files::open_setting(file)
+        {
+            self.status_message = Some(format!("Failed to open the {}: {}", file.setting().to_lowercase(), e));
+        }
+    }
+
+    /// Draws the space taken by shadow copies and System Restore on each volume, or a
+    /// button to measure it.
+    fn show_shadow_storage(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("Shadow copies and System Restore");
+        ui.weak("Restore points and previous versions of files take up space that no folder shows.");
+        let busy = self.task_receiver.is_some();
+        let label = if self.shadow_storage.is_some() { "Measure again" } else { "Measure" };
+        if ui.add_enabled(!busy, egui::Button::new(label)).on_hover_text("Needs administrator rights").clicked() {
+            self.spawn_task(|| {
+                let storage = shadow_copies::query().map_err(|e| format!("Failed to measure shadow copies: {}", e))?;
+                Ok(TaskOutcome::ShadowCopiesMeasured(storage))
+            });
+        }
+        let Some(storage) = &self.shadow_storage else {
+            return;
+        };
+        if storage.is_empty() {
+            ui.label("No volume has shadow copies.");
+            return;
+        }
+        egui::Grid::new("shadow_storage").striped(true).show(ui, |ui| {
+            for header in ["Volume", "Copies", "Used", "Reserved", "Limit"] {
+                ui.strong(header);
+            }
+            ui.end_row();
+            for volume in storage {
+                ui.label(&volume.volume);
+                ui.label(volume.copies.to_string());
+                format::size_label(ui, volume.used);
+                format::size_label(ui, volume.allocated);
+                ui.label(volume.max.map_or("None".to_string(), format::format_size));
+                ui.end_row();
+            }
+        });
+    }
+
+    /// Draws the window listing the ticked nodes with their combined size, while any are ticked.
+    fn show_ticked_window(&mut self, ctx: &egui::Context) {
+        let snapshot = self.model.snapshot();
+        let Some(tree) = snapshot.tree.filter(|_| !self.ticked.is_empty()) else {
+            return;
+        };
+        let total = self.ticked_total.get(&tree, snapshot.generation, &self.ticked, self.size_metric);
+        // The volume holding the scan root is the one the ticked nodes would be moved off.
+        let volume = self.scan_mounts.iter().find(|m| m.path.is_empty()).map(|m| &m.volume);
+        let (mut open, mut untick, mut jump_to) = (true, None, None);
+        egui::Window::new("Ticked").open(&mut open).show(ctx, |ui| {
+            ui.label(format!(
+                "{} in {} files and {} folders",
+                format::format_size(total.size),
+                total.files,
+                total.folders
+            ));
+            if let Some(percent) = volume.and_then(|v| total.percent_of(v.total)) {
+                ui.label(format!("{:.1}% of {}", percent, volume.map_or_else(String::new, |v| v.label())));
+            }
+            ui.weak("Ctrl-click items in the treemap to tick or untick them.");
+            ui.separator();
+            egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                for path in &self.ticked {
+                    ui.horizontal(|ui| {
+                        if ui.checkbox(&mut true, "").on_hover_text("Untick").changed() {
+                            untick = Some(path.clone());
+                        }
+                        let size = tree.find(path).map_or(0, |n| n.size_by(self.size_metric));
+                        if ui.link(path.join("/")).clicked() {
+                            jump_to = Some(path.clone());
+                        }
+                        format::size_label(ui, size);
+                    });
+                }
+            });
+            if ui.button("Untick all").clicked() {
+                self.ticked.clear();
+            }
+        });
+        if !open {
+            self.ticked.clear();
+        }
+        if let Some(path) = untick {
+            tally::toggle(&mut self.ticked, &path);
+        }
+        if let Some(path) = jump_to.as_deref().and_then(|path| self.view_path_of(path)) {
+            self.navigate_to(&path);
+        } else if jump_to.is_some() {
+            self.status_message = Some("That folder is excluded from the view.".to_string());
+        }
+    }
+
+    /// Rebuilds the view if a new tree was published to the model since the last frame.
+    fn sync_with_model(&mut self) {
+        if self.model.generation() != self.model_generation {
+            self.rebuild_view();
+        }
+    }
+
+    /// Runs `task` on a background thread, delivering its outcome to `task_receiver`.
+    fn spawn_task(&mut self, task: impl FnOnce() -> Result<TaskOutcome, String> + Send + 'static) {
+        let (sender, receiver) = mpsc::channel();
+        self.task_receiver = Some(receiver);
+        self.status_message = None;
+        thread::spawn(move || {
+            // The receiver is gone if the app is closing, in which case nobody cares.
+            let _ = sender.send(task());
+        });
+    }
+
+    /// Replaces the current tree with the one in `snapshot`.
+    fn show_snapshot(&mut self, snapshot: Snapshot) {
+        self.show_tree(snapshot.root, Arc::new(snapshot.tree));
+        self.provenance = Some(snapshot.header.clone());
+        self.scan_stats = snapshot.header.scan_stats;
+        if let Some(options) = snapshot.header.scan_options {
+            self.set_size_metric(options.metric);
+            self.scanned_with = Some(options);
+        }
+        // Notes kept for the folder since the snapshot was saved take precedence.
+        for (key, note) in snapshot.notes {
+            self.notes.entry(key).or_insert(note);
+        }
+    }
+
+    /// Replaces the current tree with `tree`, the result of scanning `root`.
+    fn show_tree(&mut self, root: PathBuf, tree: Arc<FileSystemNode>) {
+        self.path_input = root.to_string_lossy().to_string();
+        self.flush_notes();
+        self.scanned_path = Some(root);
+        self.scanned_with = None;
+        self.provenance = None;
+        // Stop any running scan from overwriting the loaded tree.
+        self.cancel_scan();
+        self.scan_receiver = None;
+        let publisher = self.model.publisher();
+        self.reset_for_new_tree();
+        publisher.publish_shared(tree, true);
+        self.check_budgets();
+        self.refresh_git_status();
+    }
+
+    /// Shows a generated tree instead of a scan, for demonstrations.
+    fn show_demo(&mut self) {
+        let tree = synthetic::generate(&synthetic::Spec::demo());
+        self.show_tree(PathBuf::from("/home/demo"), Arc::new(FileSystemNode { name: "demo".to_string(), ..tree }));
+        self.status_message = Some("Demo mode: this tree is generated, nothing was scanned.".to_string());
+    }
+
+    /// Shows the tree of the tab at `index`, the way it was being viewed. The tree shown
+    /// so far is kept in a tab of its own, so the user can switch back to it.
+    fn switch_to_tab(&mut self, index: usize) {
+        if self.active_tab == Some(index) || index >= self.tabs.len() {
+            return;
+        }
+        let view = self.view_state();
+        match self.active_tab {
+            Some(active) => self.tabs[active].view = view,
+            None => {
+                let snapshot = self.model.snapshot();
+                if let (Some(tree), Some(root)) = (snapshot.tree.filter(|_| snapshot.complete), self.scanned_path.clone()) {
+                    self.tabs.push(ResultTab { root, tree, view });
+                }
+            }
+        }
+        let tab = self.tabs[index].clone();
+        self.show_tree(tab.root, tab.tree);
+        self.apply_view_state(tab.view);
+        self.refresh_scan_mounts();
+        self.active_tab = Some(index);
+    }
+
+    /// Closes the tab at `index`. If it is shown, its tree stays on screen until another is shown.
+    fn close_tab(&mut self, index: usize) {
+        self.tabs.remove(index);
+        self.active_tab = match self.active_tab {
+            Some(active) if active == index => None,
+            Some(active) if active > index => Some(active - 1),
+            active => active,
+        };
+    }
+
+    /// Runs the after-scan hooks for the scan of `root` that produced `tree` on a background
+    /// thread, telling them its statistics if they are known.
+    fn run_hooks(&self, root: PathBuf, tree: Arc<FileSystemNode>, stats: Option<ScanStats>) {
+        let hooks = self.settings.hooks.after_scan.clone();
+        if !hooks.iter().any(|h| h.enabled) || read_only::is_enabled() {
+            return;
+        }
+        let budgets = self.settings.budgets.budgets.clone();
+        let metric = self.size_metric;
+        let sender = self.hook_sender.clone();
+        thread::spawn(move || {
+            let violations = budgets::check(&tree, &budgets, metric);
+            let summary = hooks::ScanSummary::new(&root, &tree, &violations, stats);
+            let failures = hooks::run_all(&hooks, &summary);
+            let _ = sender.send(failures);
+        });
+    }
+
+    /// Adds the scan of `root` that produced `tree` to the history of `root` on a background
+    /// thread, if every scan is to be recorded.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn record_history(&self, root: PathBuf, tree: Arc<FileSystemNode>, header: SnapshotHeader) {
+        if !self.settings.record_history {
+            return;
+        }
+        let sender = self.hook_sender.clone();
+        thread::spawn(move || {
+            if let Err(e) = history::record(root, &tree, header) {
+                let _ = sender.send(vec![e]);
+            }
+        });
+    }
+
+    /// Opens a tab for each queued scan that finished.
+    fn collect_queued_scans(&mut self) {
+        for finished in self.scan_queue.poll() {
+            let view = ViewState {
+                collapse_chains: self.collapse_chains,
+                size_metric: self.size_metric,
+                max_depth: self.max_depth,
+                ..ViewState::default()
+            };
+            let tab = ResultTab { root: finished.path, tree: Arc::new(finished.tree), view };
+            self.run_hooks(tab.root.clone(), tab.tree.clone(), finished.header.scan_stats.clone());
+            // A partial scan would show up in the history as space freed.
+            #[cfg(not(target_arch = "wasm32"))]
+            if !finished.header.scan_stats.as_ref().is_some_and(|s| s.volume_lost) {
+                self.record_history(tab.root.clone(), tab.tree.clone(), finished.header);
+            }
+            self.status_message = Some(format!("The scan of {} finished in tab \"{}\".", tab.root.display(), tab.title()));
+            self.tabs.push(tab);
+        }
+    }
+
+    /// Starts reading the recovery file at `path` on a background thread, and autosaves
+    /// the session there from then on.
+    fn read_recovery(&mut self, path: Option<PathBuf>) {
+        let Some(path) = path else {
+            return;
+        };
+        let (sender, receiver) = mpsc::channel();
+        self.recovery_receiver = Some(receiver);
+        self.recovery_path = Some(path.clone());
+        thread::spawn(move || {
+            let recovery = recovery::load(&path).unwrap_or_else(|e| {
+                eprintln!("Failed to read the recovery file {}: {}", path.display(), e);
+                None
+            });
+            let _ = sender.send(recovery);
+        });
+    }
+
+    /// Asks whether to restore the session left behind by a crash, if there is one.
+    fn show_recovery_window(&mut self, ctx: &egui::Context) {
+        let Some(offer) = &self.recovery_offer else {
+            return;
+        };
+        let mut restore = None;
+        egui::Window::new("Restore previous session?")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label("Disk Scout didn't close properly last time.");
+                let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+                let (shown, hover) = format::time_and_hover(offer.saved_at, now);
+                ui.label(format!("The scan of {} was saved {}.", offer.snapshot.root.display(), shown)).on_hover_text(hover);
+                ui.horizontal(|ui| {
+                    if ui.button("Restore").clicked() {
+                        restore = Some(true);
+                    }
+                    if ui.button("Discard").clicked() {
+                        restore = Some(false);
+                    }
+                });
+            });
+        match restore {
+            Some(true) => {
+                if let Some(recovery) = self.recovery_offer.take() {
+                    self.restore(recovery);
+                }
+            }
+            Some(false) => {
+                self.recovery_offer = None;
+                if let Some(path) = &self.recovery_path
+                    && let Err(e) = recovery::discard(path)
+                {
+                    eprintln!("Failed to remove the recovery file: {}", e);
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Offers to open the crash report left by the last session, if there is one.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_crash_window(&mut self, ctx: &egui::Context) {
+        let Some((path, written)) = &self.crash_offer else {
+            return;
+        };
+        let mut done = false;
+        egui::Window::new("Disk Scout crashed")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 120.0))
+            .show(ctx, |ui| {
+                ui.label("Disk Scout crashed last time, and saved a crash report.");
+                if !self.settings.crash_reports.include_paths {
+                    ui.label("It holds no file paths, but read it before sharing it.");
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Open report").clicked() {
+                        if let Err(e) = shell_integration::open_with_default_app(path) {
+                            self.status_message = Some(format!("Failed to open the crash report: {}", e));
+                        }
+                        done = true;
+                    }
+                    if ui.button("Report an issue...").on_hover_text("Opens the issue tracker, to attach the report to a new issue").clicked() {
+                        ctx.open_url(egui::OpenUrl::new_tab(crash::ISSUES_URL));
+                        if let Err(e) = shell_integration::open_with_default_app(path) {
+                            self.status_message = Some(format!("Failed to open the crash report: {}", e));
+                        }
+                        done = true;
+                    }
+                    if ui.button("Dismiss").clicked() {
+                        done = true;
+                    }
+                });
+            });
+        if done {
+            self.settings.crash_reports.offered_until = *written;
+            self.crash_offer = None;
+        }
+    }
+
+    /// Notes what the app is doing, for a crash report.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn note_crash_state(&self) {
+        crash::note_state(crash::AppState {
+            scanning: self.scan_receiver.is_some(),
+            scan_root: self.scanned_path.clone(),
+            items: self.scan_stats.as_ref().map(|s| s.files + s.directories),
+            view_depth: self.current_root_path().len(),
+            tabs: self.tabs.len(),
+        });
+    }
+
+    /// The header describing the tree shown: where and when it was scanned, with which
+    /// options, and the statistics of the scan.
+    fn scan_header(&self) -> SnapshotHeader {
+        SnapshotHeader {
+            scan_options: self.scanned_with.clone(),
+            scan_stats: self.scan_stats.clone(),
+            ..self.provenance.clone().unwrap_or_else(|| SnapshotHeader::new(None))
+        }
+    }
+
+    /// Draws a button showing where and when the tree shown was scanned, for trees that
+    /// came from a scan or a snapshot that says.
+    fn show_provenance_button(&mut self, ui: &mut egui::Ui) {
+        let header = self.scan_header();
+        let rows = header.describe();
+        if self.view_tree.is_none() || rows.is_empty() {
+            return;
+        }
+        ui.menu_button("About this scan", |ui| {
+            egui::Grid::new("provenance").num_columns(2).show(ui, |ui| {
+                if let Some(root) = &self.scanned_path {
+                    ui.strong("Folder");
+                    ui.label(root.display().to_string());
+                    ui.end_row();
+                }
+                for (label, value) in rows {
+                    ui.strong(label);
+                    ui.label(value);
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    /// Shows the scan of an autosaved session, the way it was being viewed.
+    fn restore(&mut self, recovery: Recovery) {
+        self.show_snapshot(recovery.snapshot);
+        self.apply_view_state(recovery.view);
+        self.status_message = Some("Previous session restored.".to_string());
+    }
+
+    /// Returns how the tree is being viewed.
+    fn view_state(&self) -> ViewState {
+        ViewState {
+            root_path: collapse::to_canonical_path(&self.root_path),
+            selected_path: self.selected_path.as_deref().map(collapse::to_canonical_path),
+            excluded: self.excluded.clone(),
+            collapse_chains: self.collapse_chains,
+            size_metric: self.size_metric,
+            max_depth: self.max_depth,
+        }
+    }
+
+    /// Views the current tree the way `view` describes.
+    fn apply_view_state(&mut self, view: ViewState) {
+        self.excluded = view.excluded;
+        self.collapse_chains = view.collapse_chains;
+        self.size_metric = view.size_metric;
+        self.max_depth = view.max_depth;
+        // Rebuilding the view maps these canonical paths to the ones in the new view.
+        self.root_path = view.root_path;
+        self.selected_path = view.selected_path;
+        self.rebuild_view();
+    }
+
+    /// Writes the finished scan and how it is being viewed to the recovery file on a
+    /// background thread, if they changed since the last autosave and that was long enough ago.
+    fn autosave(&mut self) {
+        // Don't overwrite a crashed session before the user had the chance to restore it.
+        if self.recovery_receiver.is_some() || self.recovery_offer.is_some() {
+            return;
+        }
+        let Some(path) = self.recovery_path.clone() else {
+            return;
+        };
+        if self.last_autosave.is_some_and(|t| t.elapsed() < recovery::AUTOSAVE_INTERVAL)
+            || self.autosave_running.load(Ordering::Acquire)
+        {
+            return;
+        }
+        self.last_autosave = Some(Instant::now());
+
+        let snapshot = self.model.snapshot();
+        let (Some(tree), Some(root)) = (snapshot.tree.filter(|_| snapshot.complete), self.scanned_path.clone()) else {
+            return;
+        };
+        let view = self.view_state();
+        let state = Some((snapshot.generation, view.clone()));
+        if self.autosaved == state {
+            return;
+        }
+        self.autosaved = state;
+
+        let header = self.scan_header();
+        let running = self.autosave_running.clone();
+        running.store(true, Ordering::Release);
+        thread::spawn(move || {
+            let tree = FileSystemNode::clone(&tree);
+            let recovery = Recovery::new(Snapshot { header, ..Snapshot::new(root, tree) }, view);
+            if let Err(e) = recovery::save(&path, &recovery) {
+                eprintln!("Failed to autosave the session: {}", e);
+            }
+            running.store(false, Ordering::Release);
+        });
+    }
+
+    /// Returns the details of the node at `path` from the root of the view tree, as picked by `settings`.
+    fn node_details(&self, path: &[String], settings: &TooltipSettings) -> Option<Vec<tooltip::Detail>> {
+        let tree = self.view_tree.as_ref()?;
+        let node = tree.find(path)?;
+        let parent = path.split_last().and_then(|(_, parent_path)| tree.find(parent_path));
+        let canonical = collapse::to_canonical_path(path);
+        let mount = self
+            .scan_mounts
+            .iter()
+            .find(|m| !m.path.is_empty() && m.path == canonical)
+            .map(|m| &m.volume);
+        let siblings = parent.map_or(&[][..], |p| p.children.as_slice());
+        let artifact = artifacts::detect(node, siblings);
+        let basis = self.current_root().map(|root| self.basis_for(root, self.size_metric));
+        let clone = self.clone_of(&canonical);
+        let repeated_link = self.is_repeated_link(&canonical);
+        let drive_root = self
+            .scanned_path
+            .as_deref()
+            .is_some_and(|root| root.parent().is_none() || self.drives.iter().any(|d| d.mount_point == root));
+        let info = tooltip::NodeInfo { path: &canonical, node, parent, mount, artifact, basis: basis.as_ref(), clone, repeated_link, drive_root };
+        Some(tooltip::details(settings, &info, self.size_metric))
+    }
+
+    /// Draws the pinned tooltip, if any, with buttons to act on its node.
+    fn show_pinned_tooltip(&mut self, ctx: &egui::Context) {
+        let Some((path, pos)) = self.pinned_tooltip.clone() else {
+            return;
+        };
+        let Some(details) = self.node_details(&path, &self.settings.tooltip) else {
+            // The node is gone from the view.
+            self.pinned_tooltip = None;
+            return;
+        };
+        let (mut open, mut exclude, mut unpin) = (false, false, false);
+        egui::Area::new(egui::Id::new("pinned_tooltip"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(pos)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    tooltip::show_details(ui, &details);
+                    ui.horizontal(|ui| {
+                        open = ui.button("Open").clicked();
+                        exclude = ui.button("Exclude from view").clicked();
+                        unpin = ui.button("Unpin").clicked();
+                    });
+                });
+            });
+        unpin |= ctx.input(|i| i.key_pressed(egui::Key::Escape));
+
+        if open || exclude || unpin {
+            self.pinned_tooltip = None;
+        }
+        if open {
+            self.open_path(&path);
+        } else if exclude {
+            self.exclude(path);
+        }
+    }
+
+    /// Handles the outcome of a finished background snapshot operation.
+    fn finish_task(&mut self, outcome: Result<TaskOutcome, String>) {
+        self.task_receiver = None;
+        self.archive_progress = None;
+        self.compare_progress = None;
+        self.compare_cancel = None;
+        match outcome {
+            Ok(TaskOutcome::SnapshotSaved(path)) => {
+                self.status_message = Some(format!("Snapshot saved to {}", path.display()));
+            }
+            Ok(TaskOutcome::ReportSaved(path)) => {
+                self.status_message = Some(format!("Report saved to {}", path.display()));
+            }
+            Ok(TaskOutcome::SnapshotLoaded(snapshot)) => {
+                self.show_snapshot(*snapshot);
+                self.status_message = Some("Snapshot loaded.".to_string());
+            }
+            Ok(TaskOutcome::Verified(report)) => {
+                self.status_message = None;
+                self.verify_report = Some(report);
+            }
+            Ok(TaskOutcome::Compared(report)) => self.compare_report = Some(report),
+            Ok(TaskOutcome::Measured(path, usage)) => self.cow_usage = Some((path, usage)),
+            Ok(TaskOutcome::OwnersMeasured(path, report)) => self.owner_report = Some((path, report)),
+            #[cfg(not(target_arch = "wasm32"))]
+            Ok(TaskOutcome::Analyzed(title, findings)) => self.findings = Some((title, findings)),
+            #[cfg(not(target_arch = "wasm32"))]
+            Ok(TaskOutcome::ScriptRan(output)) => {
+                if let Ok(output) = &output {
+                    // The tags are added together, so the notes are saved and the view rebuilt once.
+                    let mut tagged = false;
+                    for (path, tag) in &output.tags {
+                        let note = self.notes.entry(notes::key(path)).or_default();
+                        if !note.tags.contains(tag) {
+                            note.toggle(*tag);
+                            tagged = true;
+                        }
+                    }
+                    if tagged {
+                        self.save_notes();
+                        if self.tag_filter.is_some() {
+                            self.rebuild_view();
+                        }
+                    }
+                }
+                self.script_output = Some(output);
+            }
+            Ok(TaskOutcome::HardlinksFound(groups)) => self.hardlink_groups = Some(groups),
+            Ok(TaskOutcome::GamesFound(games)) => self.games = Some(games),
+            Ok(TaskOutcome::ShadowCopiesMeasured(storage)) => self.shadow_storage = Some(storage),
+            Ok(TaskOutcome::SystemFilesFound(found)) => self.system_files = Some(found),
+            Ok(TaskOutcome::LocalSnapshotsFound(snapshots)) => self.local_snapshots = Some(snapshots),
+            Ok(TaskOutcome::DiskHealthRead(disks)) => self.disk_health = Some(disks),
+            Ok(TaskOutcome::LogsFound(logs, since)) => self.log_report = Some((logs, since)),
+            #[cfg(not(target_arch = "wasm32"))]
+            Ok(TaskOutcome::CompressionEstimated(path, estimate)) => self.compression = Some((path, estimate)),
+            #[cfg(not(target_arch = "wasm32"))]
+            Ok(TaskOutcome::Archived(file, trashed)) => {
+                let mut message = format!("Archived to {}.", file.display());
+                match trashed {
+                    Some(Ok(path)) => {
+                        self.remove_from_tree(std::slice::from_ref(&path));
+                        message.push_str(" The folder was moved to the trash.");
+                    }
+                    Some(Err(e)) => message = format!("{}\n{}", message, e),
+                    None => {}
+                }
+                self.status_message = Some(message);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Ok(TaskOutcome::ShareConnected(share)) => {
+                self.start_scan();
+                self.status_message = Some(format!("Connected to {}.", share));
+            }
+            Ok(TaskOutcome::Trashed(moved, failures)) => {
+                self.remove_from_tree(&moved);
+                let mut messages = vec![format!("Moved {} folders to the trash.", moved.len())];
+                messages.extend(failures);
+                self.status_message = Some(messages.join("\n"));
+            }
+            Err(e) => self.status_message = Some(e),
+        }
+    }
+
+    /// Draws the path input with the buttons to browse for a folder and to scan it, and the
+    /// "Snapshot" menu.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_scan_controls(&mut self, ui: &mut egui::Ui) {
+        let label = ui.label("Directory:");
+        ui.text_edit_singleline(&mut self.path_input).labelled_by(label.id);
+
+        // A "Browse" button to open a native file dialog.
+        if ui.button("Browse...").clicked()
+            && let Some(path) = rfd::FileDialog::new().pick_folder()
+        {
+            self.path_input = path.to_string_lossy().to_string();
+        }
+
+        // Disable the scan button if a scan is already in progress.
+        let scan_in_progress = self.scan_receiver.is_some();
+        if ui
+            .add_enabled(!scan_in_progress, egui::Button::new("Scan"))
+            .clicked()
+        {
+            self.start_scan();
+        }
+        if scan_in_progress {
+            ui.spinner();
+            ui.label("Scanning...");
+            if ui.button("Cancel").clicked() {
+                self.cancel_scan();
+            }
+        }
+        if ui
+            .button("Add to queue")
+            .on_hover_text("Scan in the background and show the result in a new tab")
+            .clicked()
+        {
+            self.scan_queue.push(PathBuf::from(&self.path_input), self.with_permanent_exclusions(self.scan_options.clone()));
+            self.show_queue = true;
+        }
+
+        ui.separator();
+        ui.menu_button("Snapshot", |ui| self.show_snapshot_menu(ui));
+    }
+
+    /// Draws the options the next scans are made with, in a section below the scan controls
+    /// that starts collapsed. They are recorded into the snapshots of the scans.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_scan_options(&mut self, ui: &mut egui::Ui) {
+        let options = &mut self.scan_options;
+        egui::CollapsingHeader::new("Scan options").id_salt("scan_options").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                let mut limited = options.max_depth.is_some();
+                ui.checkbox(&mut limited, "Keep only")
+                    .on_hover_text("Deeper contents still count towards the sizes of their folders");
+                let mut depth = options.max_depth.unwrap_or(3);
+                ui.add_enabled(limited, egui::DragValue::new(&mut depth).range(0..=64));
+                ui.label("levels below the root");
+                options.max_depth = limited.then_some(depth);
+                ui.separator();
+                ui.checkbox(&mut options.follow_symlinks, "Follow symbolic links")
+                    .on_hover_text("Otherwise a link counts as a small file of its own");
+                ui.checkbox(&mut options.one_file_system, "Stay on one file system")
+                    .on_hover_text("Skip the drives and shares mounted below the root");
+                ui.checkbox(&mut options.include_virtual, "Scan pseudo-filesystems")
+                    .on_hover_text("Such as /proc, /sys, /dev, and /run, which are skipped by default");
+                ui.checkbox(&mut options.count_special, "Count special files")
+                    .on_hover_text("Count the sizes sockets, FIFOs, and devices report, though they hold no data");
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut options.hash_files, "Hash file contents")
+                    .on_hover_text("Saved snapshots record a hash of every file, to verify the files against later");
+                ui.separator();
+                ui.label("Show sizes as");
+                for metric in SizeMetric::ALL {
+                    ui.radio_value(&mut options.metric, metric, metric.label());
+                }
+            });
+            ui.label("Skip paths matching (for this session; Settings has those skipped always):");
+            exclusion::show_settings(ui, &mut options.exclude);
+        });
+    }
+
+    /// Asks for a folder with a file dialog and scans it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn pick_and_scan(&mut self) {
+        if let Some(path) = rfd::FileDialog::new().pick_folder() {
+            self.path_input = path.to_string_lossy().to_string();
+            self.start_scan();
+        }
+    }
+
+    /// Draws the menus of the menu bar, which gather the app's features by what they are for.
+    fn show_menu_bar(&mut self, ui: &mut egui::Ui) {
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.menu_button("File", |ui| {
+            if ui.button("Scan folder...").clicked() {
+                ui.close_menu();
+                self.pick_and_scan();
+            }
+            if ui.button("Scan folder in the background...").clicked() {
+                ui.close_menu();
+                if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                    self.scan_queue.push(path, self.with_permanent_exclusions(self.scan_options.clone()));
+                    self.show_queue = true;
+                }
+            }
+            ui.separator();
+            self.show_snapshot_menu(ui);
+            ui.separator();
+            if ui.button("Quit").clicked() {
+                ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        });
+
+        ui.menu_button("View", |ui| {
+            ui.radio_value(&mut self.view_mode, ViewMode::Treemap, "Treemap");
+            ui.radio_value(&mut self.view_mode, ViewMode::Donut, "Donut");
+            ui.radio_value(&mut self.view_mode, ViewMode::List, "List");
+            ui.separator();
+            let mut metric = self.size_metric;
+            for option in SizeMetric::ALL {
+                ui.radio_value(&mut metric, option, option.label());
+            }
+            self.set_size_metric(metric);
+            ui.separator();
+            ui.label("Shares of");
+            for basis in SizeBasis::ALL {
+                ui.radio_value(&mut self.settings.size_basis, basis, basis.label());
+            }
+            ui.separator();
+            if ui.checkbox(&mut self.collapse_chains, "Collapse single-child folders").changed() {
+                self.rebuild_view();
+            }
+            self.show_depth_control(ui);
+            if self.git_status.is_some() {
+                ui.checkbox(&mut self.color_by_git, "Color by git status");
+            }
+            if self.notes.values().any(|n| !n.tags.is_empty()) {
+                ui.checkbox(&mut self.color_by_tags, "Color by tag");
+            }
+            ui.separator();
+            ui.checkbox(&mut self.show_analysis, "Analysis panel");
+            ui.checkbox(&mut self.show_settings, "Settings");
+            // The drives, the queue and the workspaces are those of the machine.
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                if ui.checkbox(&mut self.show_drives, "Drives").changed() && self.show_drives {
+                    self.drives = volumes::list_volumes();
+                }
+                ui.checkbox(&mut self.show_queue, "Scan queue");
+                ui.checkbox(&mut self.show_workspaces, "Workspaces");
+            }
+        });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.menu_button("Tools", |ui| {
+            if ui.button("Compare directories...").clicked() {
+                ui.close_menu();
+                self.show_compare = true;
+            }
+            ui.separator();
+            // These search the finished scan, and show what they find in the analysis panel.
+            let ready = self.can_search_scan();
+            if ui.add_enabled(ready, egui::Button::new("Find hard links")).clicked() {
+                ui.close_menu();
+                self.show_analysis = true;
+                self.find_hardlinks();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if ui.add_enabled(ready && self.duplicates.is_none(), egui::Button::new("Find duplicates")).clicked() {
+                ui.close_menu();
+                self.show_analysis = true;
+                self.find_duplicates();
+            }
+            if ui.add_enabled(ready, egui::Button::new("Find games")).clicked() {
+                ui.close_menu();
+                self.show_analysis = true;
+                self.find_games();
+            }
+            if ui.add_enabled(ready, egui::Button::new("Log report...")).clicked() {
+                ui.close_menu();
+                self.find_logs();
+            }
+            if ui.button("Analyzers...").on_hover_text("Run the built-in and added analyzers on the scan").clicked() {
+                ui.close_menu();
+                self.show_analyzers = true;
+            }
+            if ui.button("Script console...").on_hover_text("Write reports over the scan in a small scripting language").clicked() {
+                ui.close_menu();
+                self.show_console = true;
+            }
+            if ui
+                .add_enabled(self.view_tree.is_some(), egui::Button::new("Clean up build output..."))
+                .on_hover_text("Lists the build output folders in the analysis panel")
+                .clicked()
+            {
+                ui.close_menu();
+                self.show_analysis = true;
+            }
+        });
+
+        ui.menu_button("Help", |ui| {
+            if ui.button("Mouse and keyboard").clicked() {
+                ui.close_menu();
+                self.show_controls = true;
+            }
+            if ui.add_enabled(self.layout.is_some(), egui::Button::new("Take the tour")).clicked() {
+                ui.close_menu();
+                self.tour_step = Some(0);
+            }
+            if ui.button("About Disk Scout").clicked() {
+                ui.close_menu();
+                self.show_about = true;
+            }
+        });
+    }
+
+    /// Draws the buttons to open a folder or an exported scan in the browser.
+    #[cfg(target_arch = "wasm32")]
+    fn show_scan_controls(&mut self, ui: &mut egui::Ui) {
+        let busy = self.task_receiver.is_some();
+        let folder = ui.add_enabled(!busy, egui::Button::new("Open folder...")).clicked();
+        let export = ui.add_enabled(!busy, egui::Button::new("Open exported scan...")).clicked();
+        ui.separator();
+        if !folder && !export {
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        self.task_receiver = Some(receiver);
+        self.status_message = None;
+        wasm_bindgen_futures::spawn_local(async move {
+            let opened = if folder { web::open_folder().await } else { web::open_export().await };
+            // Dropping the sender without an outcome tells the app the user picked nothing.
+            if let Some(result) = opened {
+                let _ = sender.send(result.map(|s| TaskOutcome::SnapshotLoaded(Box::new(s))));
+            }
+        });
+    }
+
+    /// Draws the contents of the "Snapshot" menu.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_snapshot_menu(&mut self, ui: &mut egui::Ui) {
+        let busy = self.task_receiver.is_some();
+        // Only a finished scan is worth saving, not the partial tree of a running one.
+        let snapshot = self.model.snapshot();
+        let tree = snapshot.tree.filter(|_| snapshot.complete);
+        let can_save = tree.is_some() && self.scanned_path.is_some();
+
+        // Whether to hash is an option of the scan, recorded along with the others.
+        if let Some(options) = &mut self.scanned_with {
+            ui.checkbox(&mut options.hash_files, "Include file hashes");
+        }
+        if ui
+            .add_enabled(!busy && can_save, egui::Button::new("Save snapshot..."))
+            .clicked()
+        {
+            ui.close_menu();
+            let file = rfd::FileDialog::new()
+                .add_filter("Disk Scout snapshot", &[snapshot::EXTENSION])
+                .set_file_name(format!("snapshot.{}", snapshot::EXTENSION))
+                .save_file();
+            if let (Some(file), Some(tree), Some(root)) = (file, tree.clone(), &self.scanned_path) {
+                let root = root.clone();
+                let with_hashes = self.scanned_with.as_ref().is_some_and(|o| o.hash_files);
+                let header = self.scan_header().resaved();
+                let notes = self.notes.clone();
+                self.spawn_task(move || {
+                    // Copy the shared tree on the background thread rather than the UI thread.
+                    let tree = FileSystemNode::clone(&tree);
+                    let hashes = with_hashes.then(|| integrity::hash_tree(&root, &tree));
+                    let snapshot = Snapshot { header, root, tree, hashes, notes };
+                    snapshot::save(&file, &snapshot).map_err(|e| format!("Failed to save snapshot: {}", e))?;
+                    Ok(TaskOutcome::SnapshotSaved(file))
+                });
+            }
+        }
+        // The report shows what is in view, without the excluded items.
+        let view = self.view_tree.clone().filter(|_| snapshot.complete);
+        if ui
+            .add_enabled(!busy && view.is_some() && self.scanned_path.is_some(), egui::Button::new("Export HTML report..."))
+            .on_hover_text("A single page with the treemap, the largest folders and files, and the file types")
+            .clicked()
+        {
+            ui.close_menu();
+            let file = rfd::FileDialog::new().add_filter("HTML", &["html"]).set_file_name("disk-usage.html").save_file();
+            if let (Some(file), Some(tree), Some(root)) = (file, view.clone(), self.scanned_path.clone()) {
+                let metric = self.size_metric;
+                let basis = self.basis_for(&tree, SizeMetric::Apparent);
+                let about = self.scan_header().describe();
+                self.spawn_task(move || {
+                    let html = report::to_html(&root, &tree, metric, &basis, &about);
+                    std::fs::write(&file, html).map_err(|e| format!("Failed to save the report: {}", e))?;
+                    Ok(TaskOutcome::ReportSaved(file))
+                });
+            }
+        }
+        if ui
+            .add_enabled(!busy && view.is_some() && self.scanned_path.is_some(), egui::Button::new("Export summary..."))
+            .on_hover_text("The volumes, the largest folders and files, the file types, and the growth since the last scan in the history, as Markdown or PDF")
+            .clicked()
+        {
+            ui.close_menu();
+            let file = rfd::FileDialog::new()
+                .add_filter("Markdown", &["md"])
+                .add_filter("PDF", &["pdf"])
+                .set_file_name("disk-usage.md")
+                .save_file();
+            if let (Some(file), Some(view), Some(tree), Some(root)) = (file, view, tree, self.scanned_path.clone()) {
+                let mounts = self.scan_mounts.clone();
+                let basis = self.basis_for(&view, SizeMetric::Apparent);
+                let about = self.scan_header().describe();
+                self.spawn_task(move || {
+                    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+                    // The scan itself may be the latest entry, so growth is measured from an
+                    // entry at least an hour older.
+                    let earlier = match history::History::for_root(&root) {
+                        Some(history) => history
+                            .load_latest_before(now.saturating_sub(3600))
+                            .map_err(|e| format!("Failed to open the earlier scan from the history: {}", e))?,
+                        None => None,
+                    };
+                    let growth = earlier.as_ref().map(|s| report::Growth {
+                        earlier: logs::EarlierScan { tree: &s.tree, created_at: s.header.created_at },
+                        tree: &tree,
+                    });
+                    let contents = if file.extension().is_some_and(|e| e.eq_ignore_ascii_case("pdf")) {
+                        report::to_pdf(&root, &view, &basis, &mounts, growth.as_ref(), &about, now)
+                    } else {
+                        report::to_markdown(&root, &view, &basis, &mounts, growth.as_ref(), &about, now).into_bytes()
+                    };
+                    std::fs::write(&file, contents).map_err(|e| format!("Failed to save the summary: {}", e))?;
+                    Ok(TaskOutcome::ReportSaved(file))
+                });
+            }
+        }
+
+        ui.separator();
+        if ui
+            .add_enabled(!busy && can_save, egui::Button::new("Add scan to history"))
+            .on_hover_text("Keep this scan so it can be opened again later")
+            .clicked()
+        {
+            ui.close_menu();
+            if let (Some(tree), Some(root)) = (self.model.snapshot().tree, self.scanned_path.clone()) {
+                let header = self.scan_header();
+                self.spawn_task(move || {
+                    let entry = history::record(root, &tree, header)?;
+                    Ok(TaskOutcome::SnapshotSaved(entry.path))
+                });
+            }
+        }
+        ui.checkbox(&mut self.settings.record_history, "Add every scan to history");
+        let root = self.scanned_path.clone().unwrap_or_else(|| PathBuf::from(&self.path_input));
+        ui.add_enabled_ui(!busy, |ui| ui.menu_button("History", |ui| self.show_history_menu(ui, root)));
+
+        ui.separator();
+        let open = ui.add_enabled(!busy, egui::Button::new("Open snapshot...")).clicked();
+        let verify = ui.add_enabled(!busy, egui::Button::new("Verify against snapshot...")).clicked();
+        if open || verify {
+            ui.close_menu();
+            let file = rfd::FileDialog::new()
+                .add_filter("Disk Scout snapshot or ncdu export", &[snapshot::EXTENSION])
+                .pick_file();
+            if let Some(file) = file {
+                self.spawn_task(move || {
+                    let bytes = std::fs::read(&file).map_err(|e| format!("Failed to open snapshot: {}", e))?;
+                    let snapshot = import::parse_export(&bytes).map_err(|e| format!("Failed to open snapshot: {}", e))?;
+                    if !verify {
+                        return Ok(TaskOutcome::SnapshotLoaded(Box::new(snapshot)));
+                    }
+                    let report = integrity::verify(&snapshot).map_err(|e| format!("Failed to verify: {}", e))?;
+                    Ok(TaskOutcome::Verified(report))
+                });
+            }
+        }
+    }
+
+    /// Draws the entries of the history of `root`, newest first, to pick one to open.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_history_menu(&mut self, ui: &mut egui::Ui, root: PathBuf) {
+        let Some(history) = history::History::for_root(&root) else {
+            ui.weak("There is nowhere to keep a history on this system.");
+            return;
+        };
+        let entries = match history.entries() {
+            Ok(entries) => entries,
+            Err(e) => {
+                ui.label(format!("Failed to read the history: {}", e));
+                return;
+            }
+        };
+        if entries.is_empty() {
+            ui.weak(format!("No scans of {} in the history.", root.display()));
+        }
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+            for entry in entries.iter().rev() {
+                let (shown, hover) = format::time_and_hover(entry.created_at, now);
+                if ui.button(format!("#{} – {}", entry.index, shown)).on_hover_text(hover).clicked() {
+                    ui.close_menu();
+                    let index = entry.index;
+                    self.spawn_task(move || {
+                        let snapshot = history.load(index).map_err(|e| format!("Failed to open the scan from the history: {}", e))?;
+                        Ok(TaskOutcome::SnapshotLoaded(Box::new(snapshot)))
+                    });
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Draws the window listing the differences found by the last verification.
+    fn show_verify_window(&mut self, ctx: &egui::Context) {
+        let Some(report) = &self.verify_report else {
+            return;
+        };
+        let mut open = true;
+        egui::Window::new("Verification report").open(&mut open).show(ctx, |ui| {
+            if report.is_clean() {
+                ui.label(format!("All {} files match the snapshot.", report.unchanged));
+                return;
+            }
+            ui.label(format!(
+                "{} changed, {} added, {} removed, {} unchanged.",
+                report.changed.len(),
+                report.added.len(),
+                report.removed.len(),
+                report.unchanged
+            ));
+            egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                let sections = [
+                    ("Changed", &report.changed, egui::Color32::YELLOW),
+                    ("Added", &report.added, egui::Color32::LIGHT_GREEN),
+                    ("Removed", &report.removed, egui::Color32::LIGHT_RED),
+                ];
+                for (title, paths, color) in sections {
+                    if paths.is_empty() {
+                        continue;
+                    }
+                    egui::CollapsingHeader::new(format!("{} ({})", title, paths.len()))
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            for path in paths {
+                                ui.colored_label(color, path);
+                            }
+                        });
+                }
+            });
+        });
+        if !open {
+            self.verify_report = None;
+        }
+    }
+
+    /// Keeps the tray icon in sync with the settings, checks volume usage, and
+    /// handles commands picked from the tray menu.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_tray_and_alerts(&mut self, ctx: &egui::Context) {
+        let alerts = &self.settings.alerts;
+        if alerts.tray_enabled != self.tray.is_some() {
+            self.tray = if alerts.tray_enabled { Tray::new() } else { None };
+            // Force a refresh so a newly created icon gets populated right away.
+            self.alert_monitor = AlertMonitor::default();
+        }
+        if !alerts.tray_enabled && !alerts.notifications_enabled {
+            return;
+        }
+
+        let (refreshed, newly_full) = self.alert_monitor.poll(alerts);
+        if refreshed && let Some(tray) = &mut self.tray {
+            tray.update(self.alert_monitor.volumes(), alerts);
+        }
+        if alerts.notifications_enabled {
+            for volume in &newly_full {
+                alerts::notify(volume);
+            }
+        }
+
+        match self.tray.as_ref().and_then(Tray::poll) {
+            Some(TrayCommand::Show) => {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(false));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            }
+            Some(TrayCommand::Scan(path)) => {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+                if self.scan_receiver.is_none() {
+                    self.path_input = path.to_string_lossy().to_string();
+                    self.start_scan();
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Lists the folders over budget in the analysis panel. Clicking one shows it in the treemap.
+    fn show_budget_violations(&mut self, ui: &mut egui::Ui) {
+        if self.budget_violations.is_empty() {
+            return;
+        }
+        ui.separator();
+        ui.heading("Over budget");
+        let mut jump_to = None;
+        for violation in &self.budget_violations {
+            let text = egui::RichText::new(budgets::describe(violation)).color(egui::Color32::LIGHT_RED);
+            if ui
+                .link(text)
+                .on_hover_text(format!("Budget \"{}\"", violation.budget.pattern))
+                .clicked()
+            {
+                jump_to = Some(violation.path.clone());
+            }
+        }
+        if let Some(path) = jump_to.as_deref().and_then(|path| self.view_path_of(path)) {
+            self.navigate_to(&path);
+        } else if jump_to.is_some() {
+            self.status_message = Some("That folder is excluded from the view.".to_string());
+        }
+    }
+
+    /// Returns the path in the view tree of the node at `canonical_path`, unless it is hidden.
+    fn view_path_of(&self, canonical_path: &[String]) -> Option<Vec<String>> {
+        let view_path = collapse::to_view_path(self.view_tree.as_ref()?, canonical_path);
+        (collapse::to_canonical_path(&view_path) == canonical_path).then_some(view_path)
+    }
+
+    /// The clone the file at `canonical_path` is, if it shares blocks with clones of it.
+    fn clone_of(&self, canonical_path: &[String]) -> Option<&ClonedFile> {
+        let path = canonical_path.iter().fold(self.scanned_path.clone()?, |path, name| path.join(name));
+        self.scan_stats.as_ref()?.cloned_files.iter().find(|c| c.path == path)
+    }
+
+    /// Whether the file at `canonical_path` is a further hard link to a file the scan counted
+    /// at another link.
+    fn is_repeated_link(&self, canonical_path: &[String]) -> bool {
+        let (Some(root), Some(stats)) = (&self.scanned_path, &self.scan_stats) else {
+            return false;
+        };
+        let path = canonical_path.iter().fold(root.clone(), |path, name| path.join(name));
+        stats.repeated_links.contains(&path)
+    }
+
+    /// The alias the node at `canonical_path` is, if the scan left it empty because its
+    /// contents were counted at another path.
+    fn alias_of(&self, canonical_path: &[String]) -> Option<&Alias> {
+        let path = canonical_path.iter().fold(self.scanned_path.clone()?, |path, name| path.join(name));
+        self.scan_stats.as_ref()?.aliases.iter().find(|a| a.path == path)
+    }
+
+    /// Selects the node at `path` on disk, if it is in the scan and shown.
+    fn reveal(&mut self, path: &std::path::Path) {
+        let Some(relative) = self.scanned_path.as_ref().and_then(|root| path.strip_prefix(root).ok()) else {
+            return;
+        };
+        let canonical: Vec<String> = relative.iter().map(|c| c.to_string_lossy().into_owned()).collect();
+        match self.view_path_of(&canonical) {
+            Some(view_path) => self.navigate_to(&view_path),
+            None => self.status_message = Some(format!("{} isn't shown in the view.", path.display())),
+        }
+    }
+
+    /// Draws the scan queue window, listing each queued scan with its progress.
+    fn show_queue_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_queue;
+        egui::Window::new("Scan queue").open(&mut open).show(ctx, |ui| {
+            let queue = &mut self.scan_queue;
+            ui.add(egui::Slider::new(&mut queue.max_concurrent, 1..=queue::MAX_CONCURRENCY).text("Scans at once"));
+            ui.separator();
+            if queue.jobs().is_empty() {
+                ui.label("Click \"Add to queue\" to scan a folder in the background.");
+            }
+            let mut cancel = None;
+            egui::Grid::new("scan_queue").striped(true).show(ui, |ui| {
+                for job in queue.jobs() {
+                    ui.label(job.path.display().to_string());
+                    match &job.state {
+                        JobState::Queued => {
+                            ui.label("Queued");
+                        }
+                        JobState::Running => {
+                            ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.label(format!("{} found", format::format_size(job.found())));
+                            });
+                        }
+                        JobState::Done => {
+                            ui.label(format!("Done, {}", format::format_size(job.found())));
+                        }
+                        JobState::Failed(e) => {
+                            ui.colored_label(egui::Color32::LIGHT_RED, e);
+                        }
+                        JobState::Cancelled => {
+                            ui.label("Cancelled");
+                        }
+                    }
+                    if matches!(job.state, JobState::Queued | JobState::Running) && ui.small_button("Cancel").clicked() {
+                        cancel = Some(job.id);
+                    }
+                    ui.end_row();
+                }
+            });
+            if let Some(id) = cancel {
+                queue.cancel(id);
+            }
+            if ui.button("Clear finished").clicked() {
+                queue.clear_finished();
+            }
+        });
+        self.show_queue = open;
+    }
+
+    /// Loads the analyzer in the library at `path` into the registry.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_plugin(&mut self, path: &std::path::Path) {
+        match plugins::Plugin::load(path) {
+            Ok(plugin) => self.analyzers.register(Arc::new(plugin)),
+            Err(e) => self.status_message = Some(format!("Failed to load the analyzer {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Draws the window listing the analyzers, with a button to run each on the finished
+    /// scan, and what the one run last found.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_analyzers_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_analyzers;
+        let ready = self.can_search_scan() && self.task_receiver.is_none();
+        let (mut run, mut jump_to, mut tick, mut add, mut remove) = (None, None, None, false, None);
+        egui::Window::new("Analyzers").open(&mut open).show(ctx, |ui| {
+            egui::Grid::new("analyzers").striped(true).show(ui, |ui| {
+                for analyzer in self.analyzers.analyzers() {
+                    ui.label(analyzer.title()).on_hover_text(analyzer.description());
+                    if ui.add_enabled(ready, egui::Button::new("Run")).clicked() {
+                        run = Some(analyzer.clone());
+                    }
+                    ui.end_row();
+                }
+            });
+            egui::CollapsingHeader::new("Libraries").id_salt("analyzer_plugins").show(ui, |ui| {
+                ui.weak("Analyzers in dynamic libraries run with the rights of Disk Scout. Only add ones you trust.");
+                for (i, path) in self.settings.analyzer_plugins.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(path.display().to_string());
+                        if ui.small_button("Remove").on_hover_text("Takes effect the next time Disk Scout starts").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                add = ui.button("Add library...").clicked();
+            });
+            let Some((title, findings)) = &self.findings else {
+                return;
+            };
+            ui.separator();
+            ui.heading(title);
+            if findings.is_empty() {
+                ui.label("Nothing found.");
+                return;
+            }
+            egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                egui::Grid::new("findings").striped(true).show(ui, |ui| {
+                    for finding in findings {
+                        let label = ui.link(&finding.title);
+                        if let Some(path) = finding.paths.first()
+                            && label.clicked()
+                        {
+                            jump_to = Some(path.clone());
+                        }
+                        format::size_label(ui, finding.size);
+                        ui.label(finding.action.label());
+                        if !finding.paths.is_empty()
+                            && ui.small_button("Tick").on_hover_text("Add it to the ticked items, to act on them together").clicked()
+                        {
+                            tick = Some(finding.paths.clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+        self.show_analyzers = open;
+
+        if let Some(analyzer) = run {
+            let snapshot = self.model.snapshot();
+            let metric = self.size_metric;
+            if let (Some(tree), Some(root)) = (snapshot.tree.filter(|_| snapshot.complete), self.scanned_path.clone()) {
+                self.spawn_task(move || {
+                    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+                    let input = analyzers::Input { root: &root, tree: &tree, metric, now };
+                    let findings = analyzer.analyze(&input).map_err(|e| format!("{} failed: {}", analyzer.title(), e))?;
+                    Ok(TaskOutcome::Analyzed(analyzer.title().to_string(), findings))
+                });
+            }
+        }
+        if let Some(paths) = tick {
+            for path in paths {
+                if !self.ticked.contains(&path) {
+                    tally::toggle(&mut self.ticked, &path);
+                }
+            }
+        }
+        if let Some(path) = jump_to.as_deref().and_then(|path| self.view_path_of(path)) {
+            self.navigate_to(&path);
+        } else if jump_to.is_some() {
+            self.status_message = Some("That item is excluded from the view.".to_string());
+        }
+        if let Some(i) = remove {
+            self.settings.analyzer_plugins.remove(i);
+        }
+        if add && let Some(path) = rfd::FileDialog::new().pick_file() {
+            self.load_plugin(&path);
+            self.settings.analyzer_plugins.push(path);
+        }
+    }
+
+    /// Draws the script console: an editor for a script over the finished scan, and what the
+    /// script run last printed, tagged, and offered to export.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_console_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_console;
+        let ready = self.can_search_scan() && self.task_receiver.is_none();
+        let mut run = false;
+        let mut save = None;
+        egui::Window::new("Script console").open(&mut open).default_width(560.0).show(ctx, |ui| {
+            ui.weak("Scripts are in Rhai. nodes(), node(path), children(path), total(nodes), format_size(bytes), tag(path, tag), and export_csv(name, rows) reach the scan.");
+            ui.add(egui::TextEdit::multiline(&mut self.settings.console_script).code_editor().desired_rows(10).desired_width(f32::INFINITY));
+            ui.horizontal(|ui| {
+                run = ui.add_enabled(ready, egui::Button::new("Run")).on_disabled_hover_text("Needs a finished scan").clicked();
+                run |= ready && ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Enter));
+                ui.weak("Ctrl+Enter");
+            });
+            let Some(output) = &self.script_output else {
+                return;
+            };
+            ui.separator();
+            let (printed, error) = match output {
+                Ok(output) => (&output.printed, None),
+                Err((error, printed)) => (printed, Some(error)),
+            };
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                for line in printed {
+                    ui.monospace(line);
+                }
+                if let Ok(output) = output {
+                    if let Some(result) = &output.result {
+                        ui.monospace(format!("= {}", result));
+                    }
+                    if !output.tags.is_empty() {
+                        ui.label(format!("Tagged {} items.", output.tags.len()));
+                    }
+                    for export in &output.exports {
+                        if ui.button(format!("Save {}...", export.name)).on_hover_text("Save the exported rows as CSV").clicked() {
+                            save = Some(export.clone());
+                        }
+                    }
+                }
+                if let Some(error) = error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+            });
+        });
+        self.show_console = open;
+
+        let snapshot = self.model.snapshot();
+        if run && let Some(tree) = snapshot.tree.filter(|_| snapshot.complete) {
+            let script = self.settings.console_script.clone();
+            let metric = self.size_metric;
+            self.spawn_task(move || Ok(TaskOutcome::ScriptRan(scripting::run(&script, tree, metric))));
+        }
+        if let Some(export) = save
+            && let Some(file) = rfd::FileDialog::new().add_filter("CSV", &["csv"]).set_file_name(format!("{}.csv", export.name)).save_file()
+        {
+            self.status_message = Some(match std::fs::write(&file, export.csv) {
+                Ok(()) => format!("Saved {} to {}", export.name, file.display()),
+                Err(e) => format!("Failed to save {}: {}", export.name, e),
+            });
+        }
+    }
+
+    /// Draws the window to pick two directories, such as a source and its backup, compare
+    /// them, and list what differs.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_compare_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_compare;
+        let busy = self.task_receiver.is_some();
+        egui::Window::new("Compare directories").open(&mut open).show(ctx, |ui| {
+            egui::Grid::new("compare_paths").num_columns(3).show(ui, |ui| {
+                for (label, path) in ["Source:", "Copy:"].into_iter().zip(&mut self.compare_paths) {
+                    let label = ui.label(label);
+                    ui.text_edit_singleline(path).labelled_by(label.id);
+                    if ui.button("Browse...").clicked()
+                        && let Some(picked) = rfd::FileDialog::new().pick_folder()
+                    {
+                        *path = picked.to_string_lossy().to_string();
+                    }
+                    ui.end_row();
+                }
+            });
+            ui.checkbox(&mut self.compare_contents, "Compare the content of files of the same size")
+                .on_hover_text("Hashes every such file on both sides, which reads all of them");
+            egui::CollapsingHeader::new("Ignore rules").id_salt("compare_rules").show(ui, |ui| self.settings.compare_rules.show(ui));
+            let [left, right] = self.compare_paths.clone().map(|p| PathBuf::from(p.trim()));
+            let ready = !busy && !left.as_os_str().is_empty() && !right.as_os_str().is_empty();
+            ui.horizontal(|ui| {
+                if ui.add_enabled(ready, egui::Button::new("Compare")).clicked() {
+                    let options = self.with_permanent_exclusions(self.scan_options.clone());
+                    let progress = self.compare_contents.then(|| Arc::new(HashProgress::default()));
+                    let task_progress = progress.clone();
+                    let rules = self.settings.compare_rules.clone();
+                    let cancel = Arc::new(AtomicBool::new(false));
+                    let task_cancel = Arc::clone(&cancel);
+                    self.compare_report = None;
+                    self.spawn_task(move || {
+                        let report = compare::compare_dirs(&left, &right, &options, &rules, task_progress.as_deref(), &task_cancel)
+                            .map_err(|e| match e {
+                                ScanError::Cancelled => "The comparison was cancelled.".to_string(),
+                                e => format!("Failed to compare: {}", e),
+                            })?;
+                        Ok(TaskOutcome::Compared(report))
+                    });
+                    self.compare_progress = progress;
+                    self.compare_cancel = Some(cancel);
+                }
+                if let Some(cancel) = &self.compare_cancel
+                    && ui.button("Cancel").clicked()
+                {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+                match &self.compare_progress {
+                    Some(progress) if progress.total.load(Ordering::Relaxed) > 0 => {
+                        let (hashed, total) = (progress.hashed.load(Ordering::Relaxed), progress.total.load(Ordering::Relaxed));
+                        let bar = egui::ProgressBar::new(hashed as f32 / total as f32)
+                            .desired_width(200.0)
+                            .text(format!("Hashing: {} of {}", format::format_size(hashed), format::format_size(total)));
+                        ui.add(bar);
+                        ctx.request_repaint_after(Duration::from_millis(200));
+                    }
+                    _ if busy => {
+                        ui.spinner();
+                    }
+                    _ => {}
+                }
+            });
+            if let Some(report) = &self.compare_report {
+                ui.separator();
+                compare::show_report(ui, report);
+                if !report.is_identical()
+                    && ui.button("Save report...").on_hover_text("Save the differences as CSV").clicked()
+                    && let Some(file) = rfd::FileDialog::new().add_filter("CSV", &["csv"]).set_file_name("comparison.csv").save_file()
+                {
+                    self.status_message = Some(match std::fs::write(&file, report.to_csv()) {
+                        Ok(()) => format!("Comparison saved to {}", file.display()),
+                        Err(e) => format!("Failed to save the comparison: {}", e),
+                    });
+                }
+            }
+        });
+        // Closing the window gives up on the comparison.
+        if !open && let Some(cancel) = &self.compare_cancel {
+            cancel.store(true, Ordering::Relaxed);
+        }
+        self.show_compare = open;
+    }
+
+    /// Makes the workspace at `index` active, or none of them, swapping in its budgets and
+    /// history setting.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn switch_workspace(&mut self, index: Option<usize>) {
+        let mut current = workspace::Profile {
+            budgets: std::mem::take(&mut self.settings.budgets),
+            record_history: self.settings.record_history,
+        };
+        self.settings.workspaces.switch(index, &mut current);
+        self.settings.budgets = current.budgets;
+        self.settings.record_history = current.record_history;
+        if self.scan_receiver.is_none() {
+            self.check_budgets();
+        }
+    }
+
+    /// Adds the scan of a root of the active workspace to the scan queue.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn queue_workspace_root(&mut self, root: workspace::Root) {
+        let mut options = self.with_permanent_exclusions(root.options);
+        options.exclude.retain(|p| !p.trim().is_empty());
+        self.scan_queue.push(root.path, options);
+    }
+
+    /// Queues the roots of the active workspace whose scheduled scan is due, and wakes the
+    /// app up to check again while any are scheduled.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn run_scheduled_scans(&mut self, ctx: &egui::Context) {
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        for root in self.settings.workspaces.take_due(now) {
+            self.status_message = Some(format!("Started the scheduled scan of {}.", root.path.display()));
+            self.queue_workspace_root(root);
+        }
+        if self.settings.workspaces.active().is_some_and(|w| w.roots.iter().any(|r| r.every_hours > 0)) {
+            ctx.request_repaint_after(Duration::from_secs(60));
+        }
+    }
+
+    /// Draws the workspaces window, to switch workspaces and manage the roots of the active one.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_workspaces_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_workspaces;
+        egui::Window::new("Workspaces").open(&mut open).show(ctx, |ui| {
+            let workspaces = &self.settings.workspaces;
+            let mut selected = workspaces.active;
+            egui::ComboBox::from_label("Workspace")
+                .selected_text(workspaces.active().map_or("None", |w| w.name.as_str()))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected, None, "None");
+                    for (i, workspace) in workspaces.workspaces.iter().enumerate() {
+                        ui.selectable_value(&mut selected, Some(i), &workspace.name);
+                    }
+                });
+            if selected != self.settings.workspaces.active {
+                self.switch_workspace(selected);
+            }
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut self.new_workspace_name);
+                let name = self.new_workspace_name.trim().to_string();
+                if ui
+                    .add_enabled(!name.is_empty(), egui::Button::new("New workspace"))
+                    .on_hover_text("Starts with the budgets and history setting in use")
+                    .clicked()
+                {
+                    let profile = workspace::Profile {
+                        budgets: self.settings.budgets.clone(),
+                        record_history: self.settings.record_history,
+                    };
+                    let workspaces = &mut self.settings.workspaces.workspaces;
+                    workspaces.push(workspace::Workspace { name, profile, ..workspace::Workspace::default() });
+                    self.new_workspace_name.clear();
+                    self.switch_workspace(Some(self.settings.workspaces.workspaces.len() - 1));
+                }
+            });
+            ui.separator();
+
+            let Some(index) = self.settings.workspaces.active else {
+                ui.weak("Create a workspace to group the folders you scan, with their options, schedules, and budgets.");
+                return;
+            };
+            ui.horizontal(|ui| {
+                let label = ui.label("Name:");
+                ui.text_edit_singleline(&mut self.settings.workspaces.workspaces[index].name).labelled_by(label.id);
+                if ui.button("Delete workspace").clicked() {
+                    let mut current = workspace::Profile {
+                        budgets: std::mem::take(&mut self.settings.budgets),
+                        record_history: self.settings.record_history,
+                    };
+                    self.settings.workspaces.remove(index, &mut current);
+                    self.settings.budgets = current.budgets;
+                    self.settings.record_history = current.record_history;
+                }
+            });
+            if self.settings.workspaces.active != Some(index) {
+                return;
+            }
+            ui.weak("The budgets and the history setting in Settings belong to the active workspace.");
+
+            let mut scan = Vec::new();
+            let mut remove = None;
+            let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+            egui::Grid::new("workspace_roots").striped(true).show(ui, |ui| {
+                for i in 0..self.settings.workspaces.workspaces[index].roots.len() {
+                    let root = &mut self.settings.workspaces.workspaces[index].roots[i];
+                    let path = root.path.clone();
+                    ui.label(path.display().to_string()).on_hover_text(match root.last_scanned {
+                        Some(last) => format!("Last scanned {}", format::format_time(last, now)),
+                        None => "Not scanned from the workspace yet".to_string(),
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Every");
+                        ui.add(egui::DragValue::new(&mut root.every_hours).range(0..=24 * 30));
+                        ui.label("hours");
+                    })
+                    .response
+                    .on_hover_text("Rescans the folder while the app is open. 0 only scans it when asked.");
+                    ui.checkbox(&mut root.options.one_file_system, "One file system");
+                    let mut exclude = root.options.exclude.join(", ");
+                    if ui
+                        .add(egui::TextEdit::singleline(&mut exclude).hint_text("Exclude, e.g. node_modules, *.iso").desired_width(180.0))
+                        .changed()
+                    {
+                        root.options.exclude = exclude.split(',').map(|p| p.trim().to_string()).collect();
+                    }
+                    if ui.button("Scan").on_hover_text("Scan in the background and show the result in a new tab").clicked() {
+                        scan.push(i);
+                    }
+                    ui.menu_button("History", |ui| self.show_history_menu(ui, path));
+                    if ui.small_button("x").on_hover_text("Remove the folder from the workspace").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+            ui.horizontal(|ui| {
+                let mut added = None;
+                if ui.button("Add folder...").clicked()
+                    && let Some(path) = rfd::FileDialog::new().pick_folder()
+                {
+                    added = Some((path, self.scan_options.clone()));
+                }
+                if let Some(path) = &self.scanned_path
+                    && ui.button("Add the scanned folder").on_hover_text("Keeps the options it was scanned with").clicked()
+                {
+                    added = Some((path.clone(), self.scanned_with.clone().unwrap_or_else(|| self.scan_options.clone())));
+                }
+                let roots = &mut self.settings.workspaces.workspaces[index].roots;
+                if let Some((path, options)) = added
+                    && !roots.iter().any(|r| r.path == path)
+                {
+                    roots.push(workspace::Root { path, options, ..workspace::Root::default() });
+                }
+                if ui.add_enabled(!roots.is_empty(), egui::Button::new("Scan all")).clicked() {
+                    scan.extend(0..roots.len());
+                }
+            });
+            for i in scan {
+                let root = &mut self.settings.workspaces.workspaces[index].roots[i];
+                root.last_scanned = Some(now);
+                let root = root.clone();
+                self.queue_workspace_root(root);
+                self.show_queue = true;
+            }
+            if let Some(i) = remove {
+                self.settings.workspaces.workspaces[index].roots.remove(i);
+            }
+        });
+        self.show_workspaces = open;
+    }
+
+    /// Checks the current layout against the layout invariants.
+    fn check_layout(&self) -> Option<Vec<layout_check::Violation>> {
+        let bounds = Rectangle { x: 0.0, y: 0.0, width: self.last_frame_size.x as f64, height: self.last_frame_size.y as f64 };
+        Some(layout_check::check(self.current_root()?, self.layout.as_ref()?, bounds, self.size_metric))
+    }
+
+    /// Outlines the items of `layout`, drawn in `area`, that break a layout invariant, and
+    /// lists the problems in the top-left corner.
+    fn show_layout_violations(&self, ui: &mut egui::Ui, area: egui::Rect, layout: &[TreemapNode]) {
+        /// The most problems outlined.
+        const MAX_SHOWN: usize = 500;
+        let Some(violations) = &self.layout_violations else {
+            return;
+        };
+        let painter = ui.painter_at(area);
+        let origin = area.min.to_vec2();
+        for violation in violations.iter().take(MAX_SHOWN) {
+            let rect = match layout.iter().find(|n| n.path == violation.path) {
+                Some(node) => egui::Rect::from_min_size(
+                    egui::pos2(node.rect.x as f32, node.rect.y as f32),
+                    egui::vec2(node.rect.width as f32, node.rect.height as f32),
+                ),
+                None => egui::Rect::from_min_size(egui::Pos2::ZERO, area.size()),
+            };
+            painter.rect_stroke(self.zoom.to_screen(rect, origin), 0.0, egui::Stroke::new(2.0, egui::Color32::RED));
+        }
+        let summary = match violations.len() {
+            0 => "The layout keeps every invariant.".to_string(),
+            1 => "1 layout problem".to_string(),
+            n => format!("{} layout problems", n),
+        };
+        let details: Vec<String> = violations
+            .iter()
+            .take(20)
+            .map(|v| format!("{}: {:?}", if v.path.is_empty() { "(root)".to_string() } else { v.path.join("/") }, v.problem))
+            .collect();
+        let label = egui::Rect::from_min_size(area.min + egui::vec2(8.0, 8.0), egui::vec2(240.0, 20.0));
+        ui.put(label, egui::Label::new(egui::RichText::new(summary).background_color(egui::Color32::from_black_alpha(200))))
+            .on_hover_text(details.join("\n"));
+    }
+
+    /// Draws the tabs of the scan results. Switching is disabled while a scan is running,
+    /// since showing another tree would cancel it.
+    fn show_tabs(&mut self, ui: &mut egui::Ui) {
+        let mut switch_to = None;
+        let mut close = None;
+        ui.horizontal(|ui| {
+            ui.label("Results:");
+            ui.add_enabled_ui(self.scan_receiver.is_none(), |ui| {
+                for (i, tab) in self.tabs.iter().enumerate() {
+                    if ui
+                        .selectable_label(self.active_tab == Some(i), tab.title())
+                        .on_hover_text(tab.root.display().to_string())
+                        .clicked()
+                    {
+                        switch_to = Some(i);
+                    }
+                    if ui.small_button("x").on_hover_text("Close the tab").clicked() {
+                        close = Some(i);
+                    }
+                    ui.separator();
+                }
+            });
+        });
+        if let Some(i) = switch_to {
+            self.switch_to_tab(i);
+        } else if let Some(i) = close {
+            self.close_tab(i);
+        }
+    }
+
+    /// Draws the settings window.
+    fn show_settings_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_settings;
+        egui::Window::new("Settings").open(&mut open).show(ctx, |ui| {
+            ui.heading("Appearance");
+            self.settings.appearance.show_settings(ui);
+            ui.separator();
+            ui.heading("Treemap");
+            self.settings.treemap_style.show_settings(ui);
+            ui.separator();
+            ui.heading("Tooltips");
+            tooltip::show_settings(ui, &mut self.settings.tooltip);
+            ui.separator();
+            ui.heading("Colors");
+            self.settings.palette.show_settings(ui);
+            ui.separator();
+            ui.heading("Accessibility");
+            self.settings.accessibility.show_settings(ui);
+            ui.separator();
+            ui.heading("Touch");
+            self.settings.touch.show_settings(ui);
+            ui.separator();
+            ui.heading("Layout debugging");
+            ui.checkbox(&mut self.show_layout_check, "Outline items breaking layout invariants").on_hover_text(
+                "Items outside their parent, overlapping a sibling, sized out of proportion, or not filling their folder",
+            );
+            ui.separator();
+            ui.heading("Space budgets");
+            ui.label("Folders matching a pattern are flagged when they grow larger than its budget.");
+            if budgets::show_settings(ui, &mut self.settings.budgets) && self.scan_receiver.is_none() {
+                self.check_budgets();
+            }
+            // The rest is about the machine, which the web build can't reach.
+            if cfg!(target_arch = "wasm32") {
+                return;
+            }
+
+            ui.separator();
+            ui.heading("Always excluded");
+            ui.label("Every scan skips these paths. \"Always exclude this path\" in the treemap's menu adds to them.");
+            exclusion::show_settings(ui, &mut self.settings.always_exclude);
+
+            ui.separator();
+            ui.heading("Disk usage alerts");
+            let alerts = &mut self.settings.alerts;
+            ui.checkbox(&mut alerts.tray_enabled, "Show tray icon");
+            ui.checkbox(&mut alerts.notifications_enabled, "Notify when a volume is nearly full");
+            ui.add(
+                egui::Slider::new(&mut alerts.threshold_percent, 50.0..=99.0)
+                    .suffix("%")
+                    .text("Alert threshold"),
+            );
+            ui.label("Watched volumes (none ticked watches all):");
+            if self.alert_monitor.volumes().is_empty() {
+                ui.weak("Enable the tray icon or notifications to list volumes.");
+            }
+            for volume in self.alert_monitor.volumes() {
+                let label = volume.label();
+                let mut watched = alerts.watched.contains(&label);
+                let text = format!("{} ({:.0}% full)", label, volume.usage_fraction() * 100.0);
+                if ui.checkbox(&mut watched, text).changed() {
+                    if watched {
+                        alerts.watched.push(label);
+                    } else {
+                        alerts.watched.retain(|w| *w != label);
+                    }
+                }
+            }
+
+            ui.separator();
+            ui.heading("Disk health");
+            ui.checkbox(&mut self.settings.disk_health, "Show the health of the disks in the drive overview")
+                .on_hover_text("Reads the disks' SMART data, which needs smartctl on Linux and macOS. Nothing is written to the disks.");
+
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                ui.separator();
+                ui.heading("Crash reports");
+                if self.settings.crash_reports.show_settings(ui) {
+                    crash::set(&self.settings.crash_reports);
+                }
+            }
+
+            ui.separator();
+            ui.heading("Opening scans");
+            ui.checkbox(&mut self.settings.focus_dominant, "Focus on a folder holding nearly all of a scan")
+                .on_hover_text(format!("When one folder holds over {:.0}% of a finished scan, show it rather than offering to", dominant::THRESHOLD * 100.0));
+
+            ui.separator();
+            ui.heading("Units and numbers");
+            if self.settings.format.show_settings(ui) {
+                format::set(&self.settings.format);
+            }
+
+            ui.separator();
+            ui.heading("Read-only mode");
+            let locked = read_only::is_locked();
+            let checkbox = egui::Checkbox::new(&mut self.settings.read_only, "Disable actions that change the disk or the system");
+            if ui.add_enabled(!locked, checkbox).changed() {
+                read_only::set(self.settings.read_only);
+            }
+            if locked {
+                ui.weak("Turned on with --read-only for this session.");
+            }
+
+            ui.separator();
+            ui.heading("Hooks");
+            hooks::show_settings(ui, &mut self.settings.hooks);
+
+            ui.separator();
+            ui.heading("File manager integration");
+            ui.label(format!(
+                "Adds \"{}\" to the context menu of folders in your file manager.",
+                shell_integration::MENU_LABEL
+            ));
+            // Checking can mean spawning a process on Windows, so don't do it every frame.
+            let installed = *self
+                .shell_integration_installed
+                .get_or_insert_with(shell_integration::is_installed);
+            let writable = !read_only::is_enabled();
+            ui.horizontal(|ui| {
+                if installed {
+                    ui.label("Installed.");
+                    if ui.add_enabled(writable, egui::Button::new("Uninstall")).clicked() {
+                        self.shell_integration_status = Some(
+                            shell_integration::uninstall()
+                                .map(|_| "Context menu entry removed.".to_string())
+                                .map_err(|e| format!("Failed to remove the context menu entry: {}", e)),
+                        );
+                        self.shell_integration_installed = None;
+                    }
+                } else if ui.add_enabled(writable, egui::Button::new("Install")).clicked() {
+                    self.shell_integration_status = Some(
+                        shell_integration::install()
+                            .map(|_| "Context menu entry installed.".to_string())
+                            .map_err(|e| format!("Failed to install the context menu entry: {}", e)),
+                    );
+                    self.shell_integration_installed = None;
+                }
+            });
+            match &self.shell_integration_status {
+                Some(Ok(message)) => {
+                    ui.label(message);
+                }
+                Some(Err(message)) => {
+                    ui.colored_label(egui::Color32::LIGHT_RED, message);
+                }
+                None => {}
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                ui.separator();
+                ui.heading("Settings file");
+                ui.label("Moves these settings, the workspaces, and the comparison rules to another machine, or backs them up.");
+                ui.horizontal(|ui| {
+                    if ui.button("Export settings...").clicked() {
+                        self.export_settings();
+                    }
+                    if ui.button("Import settings...").clicked() {
+                        self.import_settings();
+                    }
+                });
+            }
+        });
+        self.show_settings = open;
+    }
+
+    /// Asks where to save a settings file and writes the settings to it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_settings(&mut self) {
+        let Some(file) = rfd::FileDialog::new()
+            .add_filter("Disk Scout settings", &[settings::BUNDLE_EXTENSION])
+            .set_file_name(format!("disk-scout-settings.{}", settings::BUNDLE_EXTENSION))
+            .save_file()
+        else {
+            return;
+        };
+        self.status_message = Some(match std::fs::write(&file, self.settings.export()) {
+            Ok(()) => format!("Settings exported to {}.", file.display()),
+            Err(e) => format!("Failed to export the settings: {}", e),
+        });
+    }
+
+    /// Asks for a settings file and replaces the settings with those in it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn import_settings(&mut self) {
+        let Some(file) = rfd::FileDialog::new().add_filter("Disk Scout settings", &[settings::BUNDLE_EXTENSION]).pick_file() else {
+            return;
+        };
+        let imported = std::fs::read_to_string(&file).map_err(|e| e.to_string()).and_then(|text| self.settings.import(&text));
+        match imported {
+            // Hooks run commands, so the user sees them before any is taken on.
+            Ok(settings) if settings.hooks.all().any(|h| !h.command.trim().is_empty()) && settings.hooks != self.settings.hooks => {
+                self.pending_import = Some((file, settings));
+            }
+            Ok(settings) => self.apply_imported_settings(&file, settings),
+            Err(e) => self.status_message = Some(format!("Failed to import {}: {}", file.display(), e)),
+        }
+    }
+
+    /// Asks whether to take on the hooks of the settings being imported, if there are any.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn show_import_confirmation(&mut self, ctx: &egui::Context) {
+        let Some((file, settings)) = &self.pending_import else {
+            return;
+        };
+        let (mut with_hooks, mut without_hooks, mut cancelled) = (false, false, false);
+        egui::Window::new("Import hooks?").collapsible(false).resizable(false).show(ctx, |ui| {
+            ui.label(format!("{} has hooks, which run these commands after scans and around deletions:", file.display()));
+            egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                for hook in settings.hooks.all().filter(|h| !h.command.trim().is_empty()) {
+                    ui.code(&hook.command);
+                }
+            });
+            ui.label("Only import them if you trust where the file came from.");
+            ui.horizontal(|ui| {
+                with_hooks = ui.button("Import with the hooks").clicked();
+                without_hooks = ui.button("Keep my hooks").on_hover_text("Import the other settings, keeping the hooks you have").clicked();
+                cancelled = ui.button("Cancel").clicked();
+            });
+        });
+        if (with_hooks || without_hooks || cancelled)
+            && let Some((file, mut settings)) = self.pending_import.take()
+        {
+            if without_hooks {
+                settings.hooks = self.settings.hooks.clone();
+            }
+            if !cancelled {
+                self.apply_imported_settings(&file, settings);
+            }
+        }
+    }
+
+    /// Replaces the settings with `settings`, imported from `file`.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn apply_imported_settings(&mut self, file: &std::path::Path, settings: Settings) {
+        self.settings = settings;
+        read_only::set(self.settings.read_only);
+        format::set(&self.settings.format);
+        crash::set(&self.settings.crash_reports);
+        if self.scan_receiver.is_none() {
+            self.check_budgets();
+        }
+        self.status_message = Some(format!("Settings imported from {}. They apply to the next scans.", file.display()));
+    }
+    /// Re-runs the search against the scanned tree using the current query and mode.
+    fn refresh_search(&mut self) {
+        self.search_results = match &self.view_tree {
+            Some(tree) => search::search(tree, &self.search_query, self.search_mode, MAX_SEARCH_RESULTS)
+                .map_err(|e| e.to_string()),
+            _ => Ok(Vec::new()),
+        };
+    }
+
+    /// Rebuilds the displayed tree from the scan result and the current exclusions,
+    /// keeping the user at the same place in the hierarchy where possible.
+    fn rebuild_view(&mut self) {
+        // Remember where the user is in terms of the original tree, since the
+        // names along the path change when chains are collapsed or expanded.
+        let current_path = collapse::to_canonical_path(&self.current_root_path());
+        let selected_path = self.selected_path.as_deref().map(collapse::to_canonical_path);
+
+        let snapshot = self.model.snapshot();
+        self.model_generation = snapshot.generation;
+        let git_filter = self.git_filter.zip(self.git_status.as_ref());
+        self.view_tree = snapshot.tree.map(|tree| {
+            if self.excluded.is_empty() && !self.collapse_chains && git_filter.is_none() && self.tag_filter.is_none() {
+                return tree;
+            }
+            let tree = match git_filter {
+                Some((class, status)) => status.filter(&tree, class),
+                None => FileSystemNode::clone(&tree),
+            };
+            let tree = match self.tag_filter {
+                Some(tag) => notes::filter(&tree, &self.notes, tag),
+                None => tree,
+            };
+            let tree = exclusion::apply_exclusions(&tree, &self.excluded);
+            Arc::new(if self.collapse_chains { collapse::collapse_chains(&tree) } else { tree })
+        });
+
+        self.minimap.invalidate();
+        self.list_view.invalidate();
+        self.histogram = None;
+        let Some(tree) = &self.view_tree else {
+            self.root_path.clear();
+            self.selected_path = None;
+            self.layout = None;
+            return;
+        };
+        let current_path = collapse::to_view_path(tree, &current_path);
+        // Drop the selection if the selected node was hidden.
+        self.selected_path = selected_path
+            .map(|path| (collapse::to_view_path(tree, &path), path))
+            .filter(|(view_path, path)| collapse::to_canonical_path(view_path) == *path)
+            .map(|(view_path, _)| view_path);
+        self.reset_navigation(&current_path);
+        self.layout = None; // Invalidate layout to trigger recalculation.
+        self.refresh_search();
+    }
+
+    /// Shows the directory at `path` from the root of the view tree, stopping early at
+    /// the first name that doesn't exist.
+    fn reset_navigation(&mut self, path: &[String]) {
+        self.root_path.clear();
+        let Some(mut current) = self.view_tree.as_deref() else {
+            return;
+        };
+        for name in path {
+            match current.children.iter().find(|c| &c.name == name) {
+                Some(child) => {
+                    self.root_path.push(name.clone());
+                    current = child;
+                }
+                None => break,
+            }
+        }
+    }
 
-/// The main application struct that holds the state of the GUI.
-struct DiskScannerApp {
-    /// The path to be scanned, as entered by the user.
-    path_input: String,
-    /// The result of the last scan. It's an Option containing a Result.
-    /// - `None`: The initial state before a scan is run or when a scan is in progress.
-    /// - `Some(Ok(tree))`: The scan was successful.
-    /// - `Some(Err(e))`: The scan failed.
-    scan_result: Option<Result<FileSystemNode, std::io::Error>>,
-    /// A receiver for the result of the background scanning thread.
-    scan_receiver: Option<Receiver<Result<FileSystemNode, std::io::Error>>>,
-    /// The calculated layout of rectangles to be drawn. This is generated from a successful scan.
-    layout: Option<Vec<TreemapNode>>,
-    /// The size of the last frame, used to detect window resizing.
-    last_frame_size: egui::Vec2,
-    /// A stack to manage the zoom level. The last element is the current root.
-    navigation_stack: Vec<FileSystemNode>,
-}
+    /// Returns the path from the scan root to the current view root.
+    fn current_root_path(&self) -> Vec<String> {
+        self.root_path.clone()
+    }
 
-impl Default for DiskScannerApp {
-    fn default() -> Self {
-        Self {
-            // Default to the current directory, but don't scan until the user clicks the button.
-            path_input: std::env::current_dir()
-                .unwrap_or_else(|_| PathBuf::from("."))
-                .to_string_lossy()
-                .to_string(),
-            scan_result: None,
-            scan_receiver: None, // No scan running at startup.
-            layout: None,
-            last_frame_size: egui::Vec2::ZERO,
-            navigation_stack: Vec::new(),
+    /// Returns the directory currently shown.
+    fn current_root(&self) -> Option<&FileSystemNode> {
+        self.view_tree.as_ref()?.find(&self.root_path)
+    }
+
+    /// What shares are measured against when `node` is what is shown or exported, with
+    /// sizes by `metric`. The volume's capacity falls back to the whole scan while the
+    /// volume is unknown.
+    fn basis_for(&self, node: &FileSystemNode, metric: SizeMetric) -> Basis {
+        let of = |node: &FileSystemNode| Basis { total: node.size_by(metric), name: node.name.clone() };
+        let scan = || self.view_tree.as_ref().map(|tree| of(tree));
+        let volume = || {
+            let mount = self.scan_mounts.iter().find(|m| m.path.is_empty())?;
+            Some(Basis { total: mount.volume.total, name: format!("the volume {}", mount.volume.label()) })
+        };
+        let basis = match self.settings.size_basis {
+            SizeBasis::View => None,
+            SizeBasis::Scan => scan(),
+            SizeBasis::Volume => volume().or_else(scan),
+        };
+        basis.unwrap_or_else(|| of(node))
+    }
+
+    /// Hides the node at `path` (relative to the scan root) and re-aggregates the totals.
+    fn exclude(&mut self, path: Vec<String>) {
+        let Some(node) = self.view_tree.as_ref().and_then(|t| t.find(&path)) else {
+            return;
+        };
+        self.excluded.push(ExcludedItem {
+            size: node.size,
+            path: collapse::to_canonical_path(&path),
+        });
+        self.rebuild_view();
+    }
+
+    /// Acts on what the user did with the treemap or donut chart, where paths are relative
+    /// to the current view root.
+    fn handle_pointer_action(&mut self, action: PointerAction) {
+        let full_path = |path: Vec<String>| [self.current_root_path(), path].concat();
+        match action {
+            PointerAction::Select(path) => self.selected_path = Some(full_path(path)),
+            PointerAction::Open(path) => self.open_path(&full_path(path)),
+            PointerAction::Exclude(path) => self.exclude(full_path(path)),
+            PointerAction::AlwaysExclude(path) => self.always_exclude(full_path(path)),
+            PointerAction::Pin(path) => self.pin(&full_path(path)),
+            PointerAction::Archive(path) => {
+                let path = full_path(path);
+                if self.view_tree.as_ref().and_then(|t| t.find(&path)).is_none_or(|n| n.children.is_empty()) {
+                    self.status_message = Some("Only folders can be archived.".to_string());
+                } else {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        self.archive_path = Some(collapse::to_canonical_path(&path));
+                    }
+                }
+            }
+            PointerAction::Tick(path) => {
+                let path = collapse::to_canonical_path(&full_path(path));
+                tally::toggle(&mut self.ticked, &path);
+            }
+            PointerAction::Up => {
+                if self.root_path.pop().is_some() {
+                    self.layout = None;
+                }
+            }
         }
     }
-}
 
-/// Generates a color by interpolating between two base colors based on depth.
-fn color_for_depth(depth: usize) -> egui::Color32 {
-    // Define a start and end color for the gradient.
-    let start_color = egui::Color32::from_rgb(2, 34, 63); // Dark Blue
-    let end_color = egui::Color32::from_rgb(0, 153, 255); // Bright Blue
-
-    // Determine how far to interpolate. We'll use a modulo operator
-    // to cycle the gradient for very deep trees, preventing it from
-    // getting stuck at the end_color.
-    let max_depth_for_gradient = 8; // Cycle the gradient every 8 levels.
-    let t = (depth.saturating_sub(1) % max_depth_for_gradient) as f32
-        / (max_depth_for_gradient - 1) as f32;
-
-    // Linear interpolation between the start and end colors.
-    egui::Color32::from_rgb(
-        (start_color.r() as f32 * (1.0 - t) + end_color.r() as f32 * t) as u8,
-        (start_color.g() as f32 * (1.0 - t) + end_color.g() as f32 * t) as u8,
-        (start_color.b() as f32 * (1.0 - t) + end_color.b() as f32 * t) as u8,
-    )
-}
+    /// Pins the folder at `path` from the root of the view tree to the left pane.
+    fn pin(&mut self, path: &[String]) {
+        let Some(node) = self.view_tree.as_ref().and_then(|t| t.find(path)) else {
+            return;
+        };
+        if node.children.is_empty() {
+            self.status_message = Some(format!("{} is a file. Only folders can be pinned.", node.name));
+            return;
+        }
+        let source = match &self.scanned_path {
+            Some(root) => collapse::to_canonical_path(path).iter().fold(root.clone(), |p, name| p.join(name)).display().to_string(),
+            None => node.name.clone(),
+        };
+        self.pinned = Some(pinned::PinnedPane::new(source, node.clone(), self.root_path.clone(), self.model_generation));
+    }
 
-impl eframe::App for DiskScannerApp {
-    /// This method is called once per frame and is responsible for all UI logic.
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Check if there's a result from the scanning thread.
-        if let Some(receiver) = &self.scan_receiver {
-            if let Ok(result) = receiver.try_recv() {
-                if let Ok(tree) = &result {
-                    self.navigation_stack.clear();
-                    self.navigation_stack.push(tree.clone());
-                }
-                self.scan_result = Some(result);
-                self.scan_receiver = None; // We've received the result, so we can drop the receiver.
-                // Invalidate the old layout, a new one will be generated.
+    /// Shows the folder pinned to the left pane, if any, beside the treemap, and keeps the
+    /// two panes in step.
+    fn show_pinned_pane(&mut self, ctx: &egui::Context) {
+        let metric = self.size_metric;
+        let right = self.current_root().map_or(0, |n| n.size_by(metric));
+        let right_area = self.last_frame_size.x * self.last_frame_size.y;
+        // The item under the pointer on the right, as a path from the folder shown there.
+        let right_hovered = self.hover_start.as_ref().and_then(|(path, _)| path.strip_prefix(self.root_path.as_slice())).map(<[String]>::to_vec);
+        let max_depth = self.max_depth.unwrap_or(usize::MAX);
+        let colors = coloring::strategy(&self.settings.palette, false, false);
+        let Some(pane) = &mut self.pinned else {
+            return;
+        };
+        pane.follow_right(&self.root_path, self.model_generation);
+        let mut unpin = false;
+        let mut step = None;
+        egui::SidePanel::left("pinned_pane").resizable(true).default_width(ctx.screen_rect().width() / 3.0).show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.strong(&pane.source).on_hover_text("Pinned for comparing. It stays while other folders and scans are opened.");
+                if !pane.root.is_empty() {
+                    ui.label(format!("› {}", pane.root.join(" › ")));
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!pane.root.is_empty(), egui::Button::new("⬆ Up")).clicked() {
+                    step = Some(pinned::Step::Up(1));
+                }
+                ui.checkbox(&mut pane.follow, "Follow").on_hover_text("Going into a folder or up on one side does the same on the other");
+                ui.checkbox(&mut pane.same_scale, "Same scale").on_hover_text("Equal areas stand for equal sizes on both sides");
+                if ui.button("Unpin").clicked() {
+                    unpin = true;
+                }
+            });
+            let available = ui.available_rect_before_wrap();
+            let (size, scale) = if pane.same_scale {
+                pinned::same_scale(available.size(), pane.current().size_by(metric), right, right_area)
+            } else {
+                (available.size(), 1.0)
+            };
+            let area = egui::Rect::from_min_size(available.min, size.floor());
+            let (layout, index) = pane.layout(area.size(), metric, max_depth);
+            let response = ui.interact(area, ui.id().with("pinned_treemap"), egui::Sense::click());
+            let origin = area.min.to_vec2();
+            let hovered = response.hover_pos().and_then(|pos| index.hit_test(layout, f64::from(pos.x - origin.x), f64::from(pos.y - origin.y), 0.0));
+            let scene = render::Scene {
+                layout,
+                transition: None,
+                to_screen: &|rect| rect.translate(origin),
+                area,
+                colors: &colors,
+                classify: &|_| (None, None),
+                style: &self.settings.treemap_style,
+                lod: &self.lod,
+                high_contrast: self.settings.accessibility.high_contrast,
+                background: ui.visuals().panel_fill,
+                selected: None,
+                // The item at the same place as the one under the pointer on the right is
+                // outlined too.
+                hovered: hovered.or_else(|| index.find(layout, right_hovered.as_deref()?)),
+                ticked: &[],
+                over_budget: &[],
+            };
+            ui.painter_at(area).extend(ui.fonts(|fonts| render::shapes(&scene, fonts)));
+            if scale < 1.0 {
+                ui.painter().text(
+                    area.left_bottom() + egui::vec2(8.0, -8.0),
+                    egui::Align2::LEFT_BOTTOM,
+                    format!("Drawn at {:.0}% of the scale on the right, to fit", scale * 100.0),
+                    egui::FontId::proportional(11.0),
+                    ui.visuals().strong_text_color(),
+                );
+            }
+            let hovered_path = hovered.map(|i| layout[i].path.clone());
+            if let Some(path) = &hovered_path
+                && let Some(node) = pane.current().find(path)
+            {
+                let elsewhere = [self.root_path.as_slice(), path].concat();
+                let other = match self.view_tree.as_ref().and_then(|t| t.find(&elsewhere)) {
+                    Some(other) => format!("{} on the right", format::format_size(other.size_by(metric))),
+                    None => "Not on the right".to_string(),
+                };
+                response.clone().on_hover_text(format!("{}\n{}\n{}", path.join("/"), format::format_size(node.size_by(metric)), other));
+                if response.double_clicked() {
+                    // A file opens the folder it is in.
+                    let folder = if node.children.is_empty() { &path[..path.len() - 1] } else { path };
+                    if !folder.is_empty() {
+                        step = Some(pinned::Step::Down(folder.to_vec()));
+                    }
+                }
+            }
+            if response.middle_clicked() {
+                step = Some(pinned::Step::Up(1));
+            }
+            pane.hovered = hovered_path;
+        });
+        if unpin {
+            self.pinned = None;
+        } else if let Some(step) = step
+            && pane.take(&step)
+            && pane.follow
+        {
+            let mut root = self.root_path.clone();
+            if let Some(tree) = &self.view_tree
+                && pinned::apply(&step, &mut root, tree)
+            {
+                self.reset_navigation(&root);
                 self.layout = None;
             }
+            // The right pane took the step already, so it isn't followed back.
+            if let Some(pane) = &mut self.pinned {
+                pane.follow_right(&self.root_path, self.model_generation);
+            }
         }
-        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                ui.label("Directory:");
-                ui.text_edit_singleline(&mut self.path_input);
+    }
 
-                // A "Browse" button to open a native file dialog.
-                if ui.button("Browse...").clicked() {
-                    if let Some(path) = rfd::FileDialog::new().pick_folder() {
-                        self.path_input = path.to_string_lossy().to_string();
+    /// Opens the node at `path` from the root of the view tree: directories are drilled into,
+    /// and files are selected and opened with the default application.
+    fn open_path(&mut self, path: &[String]) {
+        let Some(node) = self.view_tree.as_ref().and_then(|t| t.find(path)) else {
+            return;
+        };
+        if !node.children.is_empty() {
+            self.reset_navigation(path);
+            self.selected_path = None;
+            self.layout = None;
+            return;
+        }
+        self.selected_path = Some(path.to_vec());
+        if self.volume_gone(&collapse::to_canonical_path(path)) {
+            self.status_message = Some(format!("{} is on a volume that was removed.", node.name));
+            return;
+        }
+        if let Some(on_disk) = self.on_disk_path(path)
+            && let Err(e) = shell_integration::open_with_default_app(&on_disk)
+        {
+            self.status_message = Some(format!("Failed to open {}: {}", on_disk.display(), e));
+        }
+    }
+
+    /// Returns where the node at `path` from the root of the view tree is on disk.
+    fn on_disk_path(&self, path: &[String]) -> Option<PathBuf> {
+        let mut on_disk = self.scanned_path.clone()?;
+        on_disk.extend(collapse::to_canonical_path(path));
+        Some(on_disk)
+    }
+
+    /// Returns the thumbnail of the node at `path` from the root of the view tree, if it is
+    /// an image file.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn thumbnail(&mut self, ctx: &egui::Context, path: &[String]) -> Option<Thumbnail> {
+        let node = self.view_tree.as_ref()?.find(path)?;
+        let on_disk = self.on_disk_path(path).filter(|p| node.children.is_empty() && thumbnails::is_image(p))?;
+        Some(self.thumbnails.get(ctx, &on_disk))
+    }
+
+    /// Returns the duration, resolution and bitrate of the node at `path` from the root of
+    /// the view tree, if it is a video or audio file.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn media(&mut self, ctx: &egui::Context, path: &[String]) -> Option<Media> {
+        let node = self.view_tree.as_ref()?.find(path)?;
+        let on_disk = self.on_disk_path(path).filter(|p| node.children.is_empty() && media::is_media(p))?;
+        Some(self.media.get(ctx, &on_disk))
+    }
+
+    /// Returns the preview of the node at `path` from the root of the view tree, if it is a
+    /// file that isn't an image or media file.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn text_preview(&mut self, ctx: &egui::Context, path: &[String]) -> Option<Preview> {
+        let node = self.view_tree.as_ref()?.find(path)?;
+        let on_disk = self.on_disk_path(path).filter(|p| {
+            node.children.is_empty() && !thumbnails::is_image(p) && !media::is_media(p)
+        })?;
+        Some(self.text_preview.get(ctx, &on_disk))
+    }
+
+    /// Draws the panel with the details of the selected node and actions for it.
+    fn show_selection_panel(&mut self, ctx: &egui::Context) {
+        let Some(path) = self.selected_path.clone() else {
+            return;
+        };
+        let Some(details) = self.node_details(&path, &TooltipSettings::everything()) else {
+            return;
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let thumbnail = self.thumbnail(ctx, &path);
+        #[cfg(not(target_arch = "wasm32"))]
+        let media = self.media(ctx, &path);
+        #[cfg(not(target_arch = "wasm32"))]
+        let text_preview = self.text_preview(ctx, &path);
+
+        let can_archive = cfg!(not(target_arch = "wasm32"))
+            && self.view_tree.as_ref().and_then(|t| t.find(&path)).is_some_and(|n| !n.children.is_empty());
+
+        let canonical = collapse::to_canonical_path(&path);
+        let gone = self.volume_gone(&canonical);
+        let alias = self.alias_of(&canonical).cloned();
+        let old_note = self.notes.get(&notes::key(&canonical)).cloned().unwrap_or_default();
+        let mut note = old_note.clone();
+        let (mut open, mut exclude, mut clear, mut archive) = (false, false, false, false);
+        egui::SidePanel::right("selection_panel").show(ctx, |ui| {
+            ui.heading("Selection");
+            tooltip::show_details(ui, &details);
+            if let Some(alias) = &alias {
+                let originals: Vec<String> = alias.originals.iter().map(|p| p.display().to_string()).collect();
+                ui.label(format!("Counted elsewhere: this is a {} of {}.", alias.kind.label(), originals.join(", ")));
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(thumbnail) = &thumbnail {
+                thumbnails::show(ui, thumbnail);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(media) = &media {
+                media::show(ui, media);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(text_preview) = &text_preview {
+                preview::show(ui, text_preview);
+            }
+            ui.horizontal(|ui| {
+                open = ui.button("Open").clicked();
+                exclude = ui.button("Exclude from view").clicked();
+                clear = ui.button("Clear").clicked();
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                for tag in Tag::ALL {
+                    let text = egui::RichText::new(tag.label()).color(tag.color());
+                    if ui.selectable_label(note.tags.contains(&tag), text).clicked() {
+                        note.toggle(tag);
                     }
                 }
+            });
+            ui.add(egui::TextEdit::multiline(&mut note.text).hint_text("Note").desired_rows(2));
+            let mut ticked = self.ticked.contains(&canonical);
+            if ui.checkbox(&mut ticked, "Ticked").on_hover_text("Add up ticked items in the Ticked window").changed() {
+                tally::toggle(&mut self.ticked, &canonical);
+            }
+            if can_archive {
+                let button = ui.add_enabled(!read_only::is_enabled() && !gone, egui::Button::new("Archive..."));
+                archive = button.on_hover_text("Pack the folder into a .tar.zst or .zip").clicked();
+            }
+        });
+        if note != old_note {
+            self.set_note(&canonical, note);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if archive {
+            self.archive_path = Some(collapse::to_canonical_path(&path));
+        }
+        if open {
+            self.open_path(&path);
+        } else if exclude {
+            self.exclude(path);
+        } else if clear {
+            self.selected_path = None;
+        }
+    }
 
-                // Disable the scan button if a scan is already in progress.
-                let scan_in_progress = self.scan_receiver.is_some();
-                if ui
-                    .add_enabled(!scan_in_progress, egui::Button::new("Scan"))
-                    .clicked()
+    /// Drills down to the directory containing the node at `path` and selects that node.
+    fn navigate_to(&mut self, path: &[String]) {
+        if self.view_tree.is_none() {
+            return;
+        }
+        // Show the directory that contains the target.
+        self.reset_navigation(&path[..path.len().saturating_sub(1)]);
+        self.selected_path = Some(path.to_vec());
+        self.layout = None; // Invalidate layout to trigger recalculation.
+    }
+
+    /// Returns the name of the selected node if it is a direct child of the current view root.
+    fn selected_name_in_view(&self) -> Option<&str> {
+        let selected = self.selected_path.as_ref()?;
+        // The scan root itself is not part of the selected path.
+        if selected.len() == self.root_path.len() + 1 && selected.starts_with(&self.root_path) {
+            return selected.last().map(String::as_str);
+        }
+        None
+    }
+}
+
+impl eframe::App for DiskScannerApp {
+    /// Persists the user's settings.
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.settings.save(storage);
+    }
+
+    /// Saves the notes changed last, and removes the recovery file, since the session ended
+    /// cleanly. A crashed session the user hasn't decided about yet is kept for the next launch.
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.flush_notes();
+        // Let a running autosave finish, or it would bring the file back.
+        while self.autosave_running.load(Ordering::Acquire) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        if self.recovery_receiver.is_none()
+            && self.recovery_offer.is_none()
+            && let Some(path) = &self.recovery_path
+            && let Err(e) = recovery::discard(path)
+        {
+            eprintln!("Failed to remove the recovery file: {}", e);
+        }
+    }
+
+    /// This method is called once per frame and is responsible for all UI logic.
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.settings.appearance.apply(ctx);
+        self.settings.touch.apply_style(ctx);
+        // Check if the scanning thread has finished.
+        if let Some(result) = self.poll_scan() {
+            (self.scan_stats, self.scan_error) = match result {
+                Ok(stats) => (Some(stats), None),
+                Err(e) => (None, Some(e)),
+            };
+            let rescanned_from = self.rescanned_from.take();
+            if self.scan_error.is_none() {
+                if let Some(provenance) = &mut self.provenance {
+                    provenance.finish_scan();
+                }
+                self.refresh_scan_mounts();
+                self.check_budgets();
+                self.refresh_git_status();
+                let volume_lost = self.scan_stats.as_ref().is_some_and(|s| s.volume_lost);
+                if let (Some(root), Some(tree)) = (self.scanned_path.clone(), self.model.snapshot().tree) {
+                    // A partial scan would show up in the history as space freed.
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if !volume_lost {
+                        self.record_history(root.clone(), tree.clone(), self.scan_header());
+                    }
+                    if let Some(before) = rescanned_from.filter(|_| !volume_lost) {
+                        self.find_changes(before, tree.clone());
+                    }
+                    self.run_hooks(root, tree, self.scan_stats.clone());
+                }
+                // Nobody is looking at the window, so tell the user some other way.
+                #[cfg(not(target_arch = "wasm32"))]
+                if self.settings.budgets.notifications_enabled
+                    && !self.budget_violations.is_empty()
+                    && !ctx.input(|i| i.focused)
                 {
-                    let (sender, receiver) = mpsc::channel();
-                    self.scan_receiver = Some(receiver);
+                    budgets::notify(&self.budget_violations);
+                }
+                if volume_lost {
+                    self.status_message = Some("The drive was removed during the scan, so the results only show what was read before.".to_string());
+                } else if let Some((path, count)) = self.scan_stats.as_ref().and_then(|s| s.too_deep.as_ref()) {
+                    self.status_message = Some(format!(
+                        "Left out {} nested too deeply to scan, such as {}. The usual cause is a folder copied into itself.",
+                        folders(*count),
+                        path.display()
+                    ));
+                }
+            }
+            // The final tree was published before the result was sent, so the node to
+            // select is there now, even if the partial trees didn't have it.
+            self.sync_with_model();
+            if let Some(path) = self.pending_selection.take()
+                && let Some(tree) = &self.view_tree
+            {
+                let path = collapse::to_view_path(tree, &path);
+                self.navigate_to(&path);
+            }
+        }
+        // Pick up trees published by the scanner, including partial ones.
+        self.sync_with_model();
+        // Check if a background snapshot operation has finished.
+        if let Some(receiver) = &self.task_receiver {
+            match receiver.try_recv() {
+                Ok(outcome) => self.finish_task(outcome),
+                // The task ended without an outcome, e.g. because the user closed a file picker.
+                Err(TryRecvError::Disconnected) => self.task_receiver = None,
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+        if let Some(receiver) = &self.recovery_receiver
+            && let Ok(recovery) = receiver.try_recv()
+        {
+            self.recovery_receiver = None;
+            self.recovery_offer = recovery;
+        }
+        if let Some(receiver) = &self.git_receiver
+            && let Ok(status) = receiver.try_recv()
+        {
+            self.git_receiver = None;
+            self.git_status = status.map(Arc::new);
+            self.git_totals = None;
+        }
+        if let Some(receiver) = &self.changes_receiver
+            && let Ok(recent) = receiver.try_recv()
+        {
+            self.changes_receiver = None;
+            self.recent_changes = Some(recent);
+        }
+        self.collect_queued_scans();
+        if let Ok(failures) = self.hook_receiver.try_recv()
+            && !failures.is_empty()
+        {
+            self.status_message = Some(failures.join("\n"));
+        }
+        self.show_recovery_window(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.show_crash_window(ctx);
+            self.note_crash_state();
+            self.save_notes_after_pause(ctx);
+        }
+        self.autosave();
+        self.show_verify_window(ctx);
+        egui::Window::new("Mouse and keyboard").open(&mut self.show_controls).show(ctx, help::show_controls);
+        egui::Window::new("About Disk Scout").open(&mut self.show_about).collapsible(false).resizable(false).show(ctx, help::show_about);
+        self.show_log_report(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.show_clean_confirmation(ctx);
+        self.show_ticked_window(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.show_archive_window(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.show_credentials_window(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_tray_and_alerts(ctx);
+        if self.show_settings {
+            self.show_settings_window(ctx);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.show_import_confirmation(ctx);
+        if self.show_drives {
+            self.show_drives_window(ctx);
+        }
+        if self.show_queue {
+            self.show_queue_window(ctx);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.show_console {
+            self.show_console_window(ctx);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.show_analyzers {
+            self.show_analyzers_window(ctx);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.show_compare {
+            self.show_compare_window(ctx);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.show_workspaces {
+            self.show_workspaces_window(ctx);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.run_scheduled_scans(ctx);
 
-                    let path_to_scan = PathBuf::from(self.path_input.clone());
-                    println!("Starting scan of: {}", path_to_scan.display());
+        self.tour_targets.menu_bar = egui::TopBottomPanel::top("menu_bar")
+            .show(ctx, |ui| {
+                egui::menu::bar(ui, |ui| self.show_menu_bar(ui));
+            })
+            .response
+            .rect;
 
-                    thread::spawn(move || {
-                        let result = scanner::build_tree(&path_to_scan);
-                        sender.send(result).expect("Failed to send scan result");
+        self.tour_targets.toolbar = egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                self.show_scan_controls(ui);
+                if let Some((packed, total)) = &self.archive_progress {
+                    let packed = packed.load(Ordering::Relaxed);
+                    let bar = egui::ProgressBar::new(packed as f32 / (*total).max(1) as f32)
+                        .desired_width(160.0)
+                        .text(format!("Archiving: {} of {}", format::format_size(packed), format::format_size(*total)));
+                    ui.add(bar);
+                    ctx.request_repaint_after(Duration::from_millis(200));
+                } else if self.task_receiver.is_some() {
+                    ui.spinner();
+                } else if let Some(message) = &self.status_message {
+                    ui.label(message);
+                }
+                ui.toggle_value(&mut self.show_analysis, "Analysis");
+                self.show_provenance_button(ui);
+                if !self.budget_violations.is_empty() {
+                    let text = egui::RichText::new(format!("{} over budget", self.budget_violations.len()))
+                        .color(egui::Color32::LIGHT_RED);
+                    if ui.button(text).on_hover_text("Show the folders in the analysis panel").clicked() {
+                        self.show_analysis = true;
+                    }
+                }
+                ui.toggle_value(&mut self.show_settings, "Settings");
+                if read_only::is_enabled() {
+                    ui.label(egui::RichText::new("Read-only").strong())
+                        .on_hover_text("Actions that change the disk or the system are disabled");
+                }
+                // The drives are those of the machine, which a browser can't see.
+                if cfg!(not(target_arch = "wasm32"))
+                    && ui.toggle_value(&mut self.show_drives, "Drives").clicked()
+                    && self.show_drives
+                {
+                    self.drives = volumes::list_volumes();
+                }
+                if cfg!(not(target_arch = "wasm32")) {
+                    let queue_label = match self.scan_queue.pending() {
+                        0 => "Queue".to_string(),
+                        pending => format!("Queue ({})", pending),
+                    };
+                    ui.toggle_value(&mut self.show_queue, queue_label);
+                    ui.toggle_value(&mut self.show_compare, "Compare");
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    let label = match self.settings.workspaces.active() {
+                        Some(workspace) => format!("Workspace: {}", workspace.name),
+                        None => "Workspaces".to_string(),
+                    };
+                    ui.toggle_value(&mut self.show_workspaces, label);
+                }
+                ui.selectable_value(&mut self.view_mode, ViewMode::Treemap, "Treemap");
+                ui.selectable_value(&mut self.view_mode, ViewMode::Donut, "Donut");
+                ui.selectable_value(&mut self.view_mode, ViewMode::List, "List");
+                let mut metric = self.size_metric;
+                egui::ComboBox::from_id_salt("size_metric")
+                    .selected_text(metric.label())
+                    .show_ui(ui, |ui| {
+                        for option in SizeMetric::ALL {
+                            ui.selectable_value(&mut metric, option, option.label());
+                        }
                     });
+                self.set_size_metric(metric);
+                ui.separator();
+                if ui
+                    .checkbox(&mut self.collapse_chains, "Collapse single-child folders")
+                    .changed()
+                {
+                    self.rebuild_view();
                 }
+                self.show_depth_control(ui);
+                self.show_git_controls(ui);
+                self.show_tag_controls(ui);
             });
+            #[cfg(not(target_arch = "wasm32"))]
+            self.show_scan_options(ui);
+            if !self.tabs.is_empty() {
+                self.show_tabs(ui);
+            }
+            self.show_unreachable(ui);
+            #[cfg(not(target_arch = "wasm32"))]
+            self.show_removed_volumes(ui);
+            self.show_dominant_banner(ui);
+            self.show_slow_directories(ui);
             
             ui.horizontal(|ui| {
                 let mut truncate_to = None;
-                for (i, node) in self.navigation_stack.iter().enumerate() {
+                let root_name = self.view_tree.as_ref().map(|t| &t.name);
+                for (i, name) in root_name.into_iter().chain(&self.root_path).enumerate() {
                     if i > 0 {
                         ui.label(">");
                     }
                     // Make the button text a bit shorter if it's too long
-                    let display_name = if node.name.len() > 30 {
-                        format!("{}...", &node.name[..27])
+                    // Collapsed chains make long names common, so cut on a character boundary.
+                    let display_name = if name.chars().count() > 30 {
+                        format!("{}...", name.chars().take(27).collect::<String>())
                     } else {
-                        node.name.clone()
+                        name.clone()
                     };
                     if ui.button(display_name).clicked() {
-                        truncate_to = Some(i + 1);
+                        truncate_to = Some(i);
                     }
                 }
-                if let Some(len) = truncate_to {
-                    if len < self.navigation_stack.len() {
-                        self.navigation_stack.truncate(len);
-                        self.layout = None; // Invalidate layout to trigger recalculation.
-                    }
+                if let Some(len) = truncate_to
+                    && len < self.root_path.len()
+                {
+                    self.root_path.truncate(len);
+                    self.layout = None; // Invalidate layout to trigger recalculation.
+                }
+                if let Some(folder) = self.current_root() {
+                    let basis = self.basis_for(folder, self.size_metric);
+                    ui.menu_button("Copy", |ui| {
+                        for format in report::TableFormat::ALL {
+                            if ui.button(format!("Contents as {}", format.label())).clicked() {
+                                ui.ctx().copy_text(report::listing(folder, self.size_metric, &basis, format));
+                                ui.close_menu();
+                            }
+                        }
+                        for format in report::TableFormat::ALL {
+                            if ui.button(format!("Largest {} files as {}", report::TOP_FILES, format.label())).clicked() {
+                                ui.ctx().copy_text(report::largest_files(folder, &basis, format));
+                                ui.close_menu();
+                            }
+                        }
+                    })
+                    .response
+                    .on_hover_text("Copy a table of this folder to the clipboard");
+                }
+
+                // A chip listing the excluded items, each of which can be restored.
+                if !self.excluded.is_empty() {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let total: u64 = self.excluded.iter().map(|e| e.size).sum();
+                        let chip = format!("Excluded items: {} ({})", self.excluded.len(), format::format_size(total));
+                        let mut restore = None;
+                        let mut restore_all = false;
+                        ui.menu_button(chip, |ui| {
+                            for (i, item) in self.excluded.iter().enumerate() {
+                                ui.horizontal(|ui| {
+                                    if ui.small_button("Restore").clicked() {
+                                        restore = Some(i);
+                                    }
+                                    ui.label(item.path.join("/"));
+                                    format::size_label(ui, item.size);
+                                });
+                            }
+                            ui.separator();
+                            if ui.button("Restore all").clicked() {
+                                restore_all = true;
+                                ui.close_menu();
+                            }
+                        });
+                        if restore_all {
+                            self.excluded.clear();
+                            self.rebuild_view();
+                        } else if let Some(i) = restore {
+                            self.excluded.remove(i);
+                            self.rebuild_view();
+                        }
+                    });
+                }
+            });
+        })
+        .response
+        .rect;
+
+        self.show_selection_panel(ctx);
+        egui::SidePanel::right("search_panel").show(ctx, |ui| {
+            let heading = ui.heading("Search");
+            let mut query_changed = ui.text_edit_singleline(&mut self.search_query).labelled_by(heading.id).changed();
+            ui.horizontal(|ui| {
+                for mode in SearchMode::ALL {
+                    query_changed |= ui
+                        .selectable_value(&mut self.search_mode, mode, mode.label())
+                        .changed();
                 }
             });
+            if query_changed {
+                self.refresh_search();
+            }
+            ui.separator();
+
+            let mut jump_to = None;
+            match &self.search_results {
+                Err(e) => {
+                    ui.colored_label(egui::Color32::LIGHT_RED, format!("Invalid pattern: {}", e));
+                }
+                Ok(results) if results.is_empty() && !self.search_query.trim().is_empty() => {
+                    ui.label("No matches.");
+                }
+                Ok(results) => {
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for result in results {
+                            let selected = self.selected_path.as_ref() == Some(&result.path);
+                            let label = format!("{} ({})", result.display_path, format::format_size(result.size));
+                            if ui.selectable_label(selected, label).clicked() {
+                                jump_to = Some(result.path.clone());
+                            }
+                        }
+                    });
+                }
+            }
+            if let Some(path) = jump_to {
+                self.navigate_to(&path);
+            }
         });
 
+        if self.show_analysis {
+            egui::TopBottomPanel::bottom("analysis_panel").resizable(true).show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.heading("File size distribution");
+                    let root_path = self.current_root_path();
+                    match self.current_root() {
+                        Some(current_root) => {
+                            if self.histogram.as_ref().is_none_or(|(path, _)| *path != root_path) {
+                                let histogram = SizeHistogram::from_tree(current_root);
+                                self.histogram = Some((root_path, histogram));
+                            }
+                            if let Some((_, histogram)) = &self.histogram {
+                                analysis::show_histogram(ui, histogram);
+                            }
+                        }
+                        None => {
+                            ui.label("Scan a directory to see its statistics.");
+                        }
+                    }
+
+                    if !self.scan_mounts.is_empty() {
+                        ui.separator();
+                        ui.heading("Volumes in this scan");
+                        let rows = self.scan_mounts.iter().map(|m| {
+                            let location = if m.path.is_empty() { "(scan root)".to_string() } else { m.path.join("/") };
+                            (format!("{} on {}", location, m.volume.label()), &m.volume, m.skipped)
+                        });
+                        mounts::show_volume_table(ui, "scan_mounts", rows);
+                    }
+                    self.show_scan_stats(ui);
+                    self.show_budget_violations(ui);
+                    self.show_git_totals(ui);
+                    self.show_artifacts(ui);
+                    self.show_cow_usage(ui);
+                    if cfg!(unix) && self.view_tree.is_some() {
+                        self.show_owners(ui);
+                    }
+                    if self.view_tree.is_some() && cfg!(not(target_arch = "wasm32")) {
+                        self.show_hardlinks(ui);
+                        #[cfg(not(target_arch = "wasm32"))]
+                        self.show_duplicates(ui);
+                        self.show_games(ui);
+                        #[cfg(not(target_arch = "wasm32"))]
+                        {
+                            ui.separator();
+                            ui.heading("Log files");
+                            self.show_log_button(ui);
+                            self.show_compression(ui);
+                        }
+                    }
+                });
+            });
+        }
+
+        self.show_pinned_pane(ctx);
         egui::CentralPanel::default().show(ctx, |ui| {
             // Check if the window size has changed. If so, recalculate the layout.
             let current_frame_size = ui.available_size();
+            // The layout is generated at the origin and offset into the panel when drawn.
+            let area = ui.available_rect_before_wrap();
+            self.tour_targets.treemap = area;
+            let origin = area.min.to_vec2();
+            let now = ctx.input(|i| i.time);
             let layout_is_stale = self.last_frame_size != current_frame_size
-                || (self.scan_result.is_some() && self.layout.is_none());
+                || (self.view_tree.is_some() && self.layout.is_none());
 
             if layout_is_stale {
+                // After a file operation, the layout is updated along the removed paths.
+                let update = self.layout_update.take().filter(|u| {
+                    u.generation == self.model_generation && u.root == self.current_root_path() && u.frame_size == current_frame_size
+                });
                 // Generate the layout from the current navigation root.
-                if let Some(tree) = self.navigation_stack.last() {
+                if let Some(tree) = self.current_root() {
                     println!("Window resized or new view, recalculating layout...");
                     let bounds = Rectangle {
                         x: 0.0,
@@ -157,12 +5100,35 @@ impl eframe::App for DiskScannerApp {
                         width: current_frame_size.x as f64,
                         height: current_frame_size.y as f64,
                     };
-                    self.layout = Some(treemap::generate_treemap(tree, bounds));
+                    let max_depth = self.max_depth.unwrap_or(usize::MAX);
+                    let layout = match update {
+                        Some(update) => treemap::update_layout(&update.layout, tree, &update.removed, bounds, self.size_metric, max_depth),
+                        None => treemap::generate_treemap_to_depth(tree, bounds, self.size_metric, max_depth),
+                    };
+
+                    // Animate from the previous layout, matching nodes by their canonical path
+                    // so they still line up if chains were collapsed or expanded in between.
+                    let root_path = collapse::to_canonical_path(&self.current_root_path());
+                    let canonical = |layout: &[TreemapNode]| -> Vec<TreemapNode> {
+                        layout
+                            .iter()
+                            .map(|n| TreemapNode { path: collapse::to_canonical_path(&n.path), ..n.clone() })
+                            .collect()
+                    };
+                    let next = canonical(&layout);
+                    self.transition = self.previous_layout.as_ref().map(|(previous_root, previous)| {
+                        Transition::new(previous, previous_root, &next, &root_path, now)
+                    });
+                    self.previous_layout = Some((root_path, next));
+                    self.hit_index = HitIndex::new(&layout);
+                    self.layout = Some(layout);
+                    self.layout_violations = None;
                 }
                 self.last_frame_size = current_frame_size;
             }
 
-            if self.scan_receiver.is_some() {
+            // Partial results are shown as soon as the scanner publishes them.
+            if self.scan_receiver.is_some() && self.view_tree.is_none() {
                 ui.centered_and_justified(|ui| {
                     ui.label("Scanning...");
                 });
@@ -170,94 +5136,317 @@ impl eframe::App for DiskScannerApp {
             }
 
             // Show an error message if the scan failed.
-            if let Some(Err(e)) = &self.scan_result {
+            if let Some(e) = &self.scan_error {
                 ui.centered_and_justified(|ui| {
-                    ui.label(format!("Error scanning directory:\n{}", e));
+                    ui.label(format!("Error scanning directory:\n{}\n\n{}", e, e.hint()));
+                });
+                return;
+            }
+
+            // The arrow keys move the selection unless a control has the keyboard focus.
+            let keyboard_free = ctx.memory(|m| m.focused().is_none()) && !ctx.wants_keyboard_input();
+            if self.view_mode == ViewMode::Donut
+                && let Some(current_root) = self.current_root()
+            {
+                let selected_name = self.selected_name_in_view();
+                let selected = self.selected_path.as_ref().and_then(|p| p.strip_prefix(self.root_path.as_slice()));
+                let basis = self.basis_for(current_root, self.size_metric);
+                let action = chart::show_donut(ui, area, current_root, selected_name, self.size_metric, &basis).or_else(|| {
+                    keyboard_free.then(|| accessibility::keyboard_action(ui, current_root, selected, self.size_metric)).flatten()
                 });
+                if let Some(action) = action {
+                    self.handle_pointer_action(action);
+                }
+                return;
+            }
+            if self.view_mode == ViewMode::List
+                && let Some(tree) = self.view_tree.clone()
+                && let Some(current_root) = tree.find(&self.root_path)
+            {
+                let basis = self.basis_for(current_root, self.size_metric);
+                let dir = self.on_disk_path(&self.root_path).unwrap_or_else(|| self.root_path.iter().collect());
+                let mut order = self.settings.list_order;
+                let action = self.list_view.show(ui, current_root, &dir, &mut order, self.size_metric, &basis);
+                self.settings.list_order = order;
+                if let Some(action) = action {
+                    self.handle_pointer_action(action);
+                }
                 return;
             }
 
+            if self.show_layout_check && self.layout_violations.is_none() {
+                self.layout_violations = self.check_layout();
+            }
+
             // If the layout has been calculated, draw it.
+            self.lod.adapt(ctx.input(|i| i.unstable_dt));
             if let Some(layout) = &self.layout {
-                let painter = ui.painter();
-                let mut hovered_node: Option<&TreemapNode> = None;
-
-                // Find the children of the current root to match against the layout nodes.
-                let current_children = &self.navigation_stack.last().unwrap().children;
-
-                for (i, node) in layout.iter().enumerate() {
-                    let rect = egui::Rect::from_min_max(
-                        egui::pos2(node.rect.x as f32, node.rect.y as f32),
-                        egui::pos2(
-                            (node.rect.x + node.rect.width) as f32,
-                            (node.rect.y + node.rect.height) as f32,
-                        ),
-                    );
+                // Zoomed in, the treemap extends past its area.
+                let painter = &ui.painter_at(area);
+                // The whole treemap is one interactive area; the node under the pointer is hit-tested.
+                let response = ui.interact(area, ui.id().with("treemap"), egui::Sense::click());
+                self.zoom.update(ui, &response, &self.current_root_path());
+                let min_size = f64::from(self.settings.touch.min_target() / self.zoom.scale);
+                let hovered_index = response
+                    .hover_pos()
+                    .map(|pos| self.zoom.to_layout(pos, origin))
+                    .and_then(|pos| self.hit_index.hit_test(layout, pos.x as f64, pos.y as f64, min_size));
+                let hovered_node = hovered_index.map(|i| &layout[i]);
+                let action = interaction::pointer_action(ui, &response, hovered_node.map(|n| n.path.as_slice()));
+                if response.clicked() {
+                    response.request_focus();
+                }
+                accessibility::keep_arrow_keys(ui, &response);
 
-                    // Don't draw rectangles that are too small to see.
-                    if rect.width() < 1.0 || rect.height() < 1.0 {
-                        continue;
+                let selected = self.selected_path.as_ref().and_then(|p| p.strip_prefix(self.root_path.as_slice()));
+                let action = match (action, self.current_root()) {
+                    (None, Some(current_root)) if response.has_focus() || keyboard_free => {
+                        accessibility::keyboard_action(ui, current_root, selected, self.size_metric)
                     }
-
-                    painter.rect_filled(rect, 3.0, egui::Color32::from_gray(50));
-                    painter.rect_stroke(
-                        rect,
-                        3.0,
-                        egui::Stroke::new(1.0, egui::Color32::from_gray(150)),
-                    );
-
-                    let color = color_for_depth(node.depth);
-                    painter.rect_filled(rect, 3.0, color);
-                    painter.rect_stroke(
-                        rect,
-                        3.0,
-                        egui::Stroke::new(1.0, egui::Color32::from_gray(150)),
-                    );
-
-                    // Handle clicks for zooming in.
-                    let response = ui.interact(rect, ui.id().with(i), egui::Sense::click());
-                    if response.clicked() {
-                        // Find the corresponding FileSystemNode for the clicked rectangle.
-                        if let Some(clicked_fs_node) =
-                            current_children.iter().find(|c| c.name == node.name)
-                        {
-                            // Only zoom into directories (nodes with children).
-                            if !clicked_fs_node.children.is_empty() {
-                                self.navigation_stack.push(clicked_fs_node.clone());
-                                self.layout = None; // Invalidate layout
-                                return; // Exit early to avoid issues with collection modification.
+                    (action, _) => action,
+                };
+                if let Some(current_root) = self.current_root() {
+                    let basis = self.basis_for(current_root, self.size_metric);
+                    let description = accessibility::describe(current_root, selected, self.size_metric, &basis);
+                    response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Other, true, &description));
+                }
+                // The outlined nodes, as indices into the layout.
+                let selected_index = selected.and_then(|path| self.hit_index.find(layout, path));
+                let over_budget: Vec<usize> = self
+                    .budget_violations
+                    .iter()
+                    .filter_map(|v| self.view_path_of(&v.path))
+                    .filter_map(|path| self.hit_index.find(layout, path.strip_prefix(self.root_path.as_slice())?))
+                    .collect();
+                let ticked: Vec<usize> = self
+                    .ticked
+                    .iter()
+                    .filter_map(|path| self.view_path_of(path))
+                    .filter_map(|path| self.hit_index.find(layout, path.strip_prefix(self.root_path.as_slice())?))
+                    .collect();
+                let palette = &self.settings.palette;
+                let git_status = self.git_status.as_ref().filter(|_| self.color_by_git);
+                let colors = coloring::strategy(palette, git_status.is_some(), self.color_by_tags);
+                let root_path = self.current_root_path();
+                let classify = |node: &TreemapNode| {
+                    let canonical = (git_status.is_some() || self.color_by_tags)
+                        .then(|| collapse::to_canonical_path(&[root_path.as_slice(), &node.path].concat()));
+                    let git = git_status.zip(canonical.as_ref()).map(|(status, path)| status.classify(path).0);
+                    let tag = canonical.as_ref().filter(|_| self.color_by_tags).and_then(|path| notes::tag_of(&self.notes, path));
+                    (git, tag)
+                };
+                let zoom = &self.zoom;
+                let scene = render::Scene {
+                    layout,
+                    // Progress of the animation from the previous layout, if one is running.
+                    transition: self.transition.as_ref().filter(|t| !t.is_finished(now)).map(|t| (t, t.progress(now))),
+                    to_screen: &|rect| zoom.to_screen(rect, origin),
+                    area,
+                    colors: &colors,
+                    classify: &classify,
+                    style: &self.settings.treemap_style,
+                    lod: &self.lod,
+                    high_contrast: self.settings.accessibility.high_contrast,
+                    background: ui.visuals().panel_fill,
+                    selected: selected_index,
+                    // The item at the same place as the one under the pointer in the pinned
+                    // pane is outlined too.
+                    hovered: hovered_index.or_else(|| self.hit_index.find(layout, self.pinned.as_ref()?.hovered.as_deref()?)),
+                    ticked: &ticked,
+                    over_budget: &over_budget,
+                };
+                painter.extend(ui.fonts(|fonts| render::shapes(&scene, fonts)));
+                // Right after a rescan, what changed is badged for a few seconds.
+                let badge_opacity = self.recent_changes.as_mut().and_then(|recent| recent.badge_opacity(now));
+                if let (Some(opacity), Some(recent)) = (badge_opacity, &self.recent_changes) {
+                    let badged: Vec<(usize, &changes::Change)> = recent
+                        .changes
+                        .iter()
+                        .filter_map(|change| {
+                            let path = self.view_path_of(&change.path)?;
+                            Some((self.hit_index.find(layout, path.strip_prefix(self.root_path.as_slice())?)?, change))
+                        })
+                        .collect();
+                    changes::show_badges(painter, layout, &badged, scene.to_screen, opacity);
+                    ctx.request_repaint();
+                }
+                // While the lens is held, the region under the pointer is drawn again, magnified.
+                if let Some(pointer) = response.hover_pos().filter(|_| lens::is_held(ui)) {
+                    let inset = lens::inset(area, pointer);
+                    let to_lens = |rect| lens::magnify(zoom.to_screen(rect, origin), pointer, inset);
+                    let magnified = render::Scene { transition: None, to_screen: &to_lens, area: inset, ..scene };
+                    let lens_painter = ui.painter_at(inset).with_layer_id(egui::LayerId::new(egui::Order::Foreground, ui.id().with("lens")));
+                    lens_painter.rect_filled(inset, 4.0, ui.visuals().panel_fill);
+                    lens_painter.extend(ui.fonts(|fonts| render::shapes(&magnified, fonts)));
+                    lens::show_frame(&lens_painter, inset);
+                }
+                if self.show_layout_check {
+                    self.show_layout_violations(ui, area, layout);
+                }
+                if palette.show_legend && self.color_by_tags {
+                    notes::show_legend(ui, area);
+                } else if palette.show_legend && git_status.is_some() {
+                    git::show_legend(ui, area);
+                } else if palette.show_legend {
+                    palette.show_legend(ui, area);
+                }
+                if self.zoom.is_zoomed() {
+                    let corner = egui::Rect::from_min_size(egui::pos2(area.max.x - 128.0, area.min.y + 8.0), egui::vec2(120.0, 24.0));
+                    if ui.put(corner, egui::Button::new(format!("Reset zoom ({:.1}×)", self.zoom.scale))).clicked() {
+                        self.zoom = touch::Zoom::default();
+                    }
+                }
+                if let Some(recent) = &self.recent_changes {
+                    match changes::show_toast(ui, area, recent) {
+                        Some(changes::ToastAction::Show(path)) => {
+                            if let Some(path) = self.view_path_of(&path) {
+                                self.navigate_to(&path);
+                                return;
                             }
                         }
+                        Some(changes::ToastAction::Close) => self.recent_changes = None,
+                        None => {}
                     }
+                }
 
-                    // Check for hover to show a tooltip.
-                    if ui.rect_contains_pointer(rect) {
-                        hovered_node = Some(node);
+                // Show where we are in the whole tree while drilled into a subtree.
+                if !self.root_path.is_empty()
+                    && let Some(tree) = &self.view_tree
+                {
+                    let root_path = self.current_root_path();
+                    if let Some(path) = self.minimap.show(ui, area, tree, &root_path, self.size_metric, &colors) {
+                        self.reset_navigation(&path);
+                        self.layout = None;
+                        return;
                     }
                 }
 
-                if let Some(node) = hovered_node {
+                if let Some(action) = action {
+                    self.handle_pointer_action(action);
+                    return; // Exit early to avoid issues with collection modification.
+                }
+
+                let hovered_path = hovered_node.map(|node| {
+                    let mut full_path = self.current_root_path();
+                    full_path.extend(node.path.iter().cloned());
+                    full_path
+                });
+                let time = ctx.input(|i| i.time);
+                // Restart the delay whenever the pointer moves on to another node.
+                let hover_since = match (&self.hover_start, &hovered_path) {
+                    (Some((path, since)), Some(hovered)) if path == hovered => *since,
+                    _ => time,
+                };
+                self.hover_start = hovered_path.clone().map(|path| (path, hover_since));
+
+                let pin_pressed = !ctx.wants_keyboard_input() && ctx.input(|i| i.key_pressed(tooltip::PIN_KEY));
+                if pin_pressed {
+                    let pinned = self.pinned_tooltip.as_ref().map(|(path, _)| path);
+                    self.pinned_tooltip = match (&hovered_path, ui.ctx().pointer_hover_pos()) {
+                        (Some(path), Some(pos)) if pinned != Some(path) => {
+                            Some((path.clone(), pos + egui::vec2(16.0, 16.0)))
+                        }
+                        _ => None,
+                    };
+                }
+                if let Some(path) = hovered_path
+                    && self.pinned_tooltip.is_none()
+                    && time - hover_since >= f64::from(self.settings.tooltip.delay)
+                    && let Some(details) = self.node_details(&path, &self.settings.tooltip)
+                {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    let thumbnail = self.settings.tooltip.show_thumbnail.then(|| self.thumbnail(ctx, &path)).flatten();
                     let tooltip_id = egui::Id::new("treemap_tooltip");
                     let tooltip_layer_id = egui::LayerId::new(egui::Order::Tooltip, tooltip_id);
                     egui::show_tooltip_at_pointer(ctx, tooltip_layer_id, tooltip_id, |ui| {
-                        ui.label(format!("Name: {}", node.name));
-                        ui.label(format!("Size: {} bytes", node.size));
+                        tooltip::show_details(ui, &details);
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if let Some(thumbnail) = &thumbnail {
+                            thumbnails::show(ui, thumbnail);
+                        }
                     });
                 }
-            } else if self.scan_result.is_none() {
-                ui.centered_and_justified(|ui| {
-                    ui.label("Enter a path and click 'Scan' to begin.");
-                });
+            } else if self.view_tree.is_none() {
+                match welcome::show(ui, &self.drives, &self.settings.recent_scans) {
+                    Some(WelcomeAction::Scan(path)) => {
+                        self.path_input = path.to_string_lossy().to_string();
+                        self.start_scan();
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    Some(WelcomeAction::PickFolder) => self.pick_and_scan(),
+                    Some(WelcomeAction::ClearRecent) => self.settings.recent_scans.clear(),
+                    _ => {}
+                }
             }
         });
 
+        // The tour is about the treemap, so it waits for the first one.
+        if !self.settings.tour_done && self.tour_step.is_none() && self.layout.is_some() {
+            self.tour_step = Some(0);
+        }
+        if let Some(step) = &mut self.tour_step
+            && welcome::show_tour(ctx, step, &self.tour_targets)
+        {
+            self.tour_step = None;
+            self.settings.tour_done = true;
+        }
+
+        self.show_pinned_tooltip(ctx);
+
         // Trigger a repaint. This is important for the resizing logic to work smoothly.
         ctx.request_repaint();
     }
 }
 
+/// "1 folder" or "n folders".
+fn folders(count: u64) -> String {
+    if count == 1 { "1 folder".to_string() } else { format!("{} folders", count) }
+}
+
 /// The main entry point of the application.
+#[cfg(not(target_arch = "wasm32"))]
 fn main() -> Result<(), eframe::Error> {
+    use clap::Parser;
+    // Wrong flags exit with 1 rather than clap's 2, which a headless scan uses for a partial one.
+    let cli = cli::Cli::try_parse().unwrap_or_else(|e| {
+        let _ = e.print();
+        std::process::exit(if e.use_stderr() { headless::Exit::Failed.code() } else { 0 });
+    });
+    let scan_options = cli.scan_options();
+    // The demo tree isn't on disk, so nothing may be done to it.
+    if cli.read_only || cli.demo {
+        read_only::lock();
+    }
+    if cli.headless
+        && let Some(path) = &cli.path
+    {
+        let cancel = Arc::new(AtomicBool::new(false));
+        headless::cancel_on_interrupt(Arc::clone(&cancel));
+        let exit = headless::run(path, &scan_options, &cli.headless_options(), &cancel, &mut std::io::stdout(), &mut std::io::stderr());
+        std::process::exit(exit.code());
+    }
+    if let Some(addr) = &cli.serve {
+        if let Err(e) = server::serve(addr, scan_options) {
+            eprintln!("Failed to serve on {}: {}", addr, e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    // The listing is read before the window opens, so errors in it show in the terminal.
+    let piped = cli.stdin_format().map(|format| {
+        let mut listing = Vec::new();
+        if let Err(e) = std::io::Read::read_to_end(&mut std::io::stdin(), &mut listing) {
+            eprintln!("Failed to read stdin: {}", e);
+            std::process::exit(1);
+        }
+        import::parse_lines(&listing, format).unwrap_or_else(|e| {
+            eprintln!("Failed to read the listing on stdin: {}", e);
+            std::process::exit(1);
+        })
+    });
+    if let Some(dir) = crash::default_dir() {
+        crash::install(dir);
+    }
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default().with_inner_size([1024.0, 768.0]),
         ..Default::default()
@@ -266,6 +5455,40 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "Disk Scout",
         options,
-        Box::new(|_cc| Ok(Box::new(DiskScannerApp::default()))),
+        Box::new(move |cc| {
+            let mut app = DiskScannerApp::new(cc, scan_options, cli.path);
+            if cli.demo {
+                app.show_demo();
+            }
+            if let Some(snapshot) = piped {
+                app.show_snapshot(snapshot);
+                app.status_message = Some("Showing the listing piped in on stdin.".to_string());
+            }
+            Ok(Box::new(app))
+        }),
     )
 }
+
+/// The entry point of the web build, which runs the app in the page's canvas.
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    use wasm_bindgen::JsCast;
+
+    wasm_bindgen_futures::spawn_local(async {
+        let canvas = web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.get_element_by_id("the_canvas_id"))
+            .and_then(|element| element.dyn_into::<web_sys::HtmlCanvasElement>().ok())
+            .expect("the page should have a canvas with the id `the_canvas_id`");
+        let result = eframe::WebRunner::new()
+            .start(
+                canvas,
+                eframe::WebOptions::default(),
+                Box::new(|cc| Ok(Box::new(DiskScannerApp::new(cc, ScanOptions::default(), None)))),
+            )
+            .await;
+        if let Err(e) = result {
+            web_sys::console::error_1(&e);
+        }
+    });
+}
@@ -0,0 +1,329 @@
+//! This module reads the duration, resolution and bitrate of video and audio files, shown
+//! in the selection panel, since "4 hours of 4K footage" says more than a byte count. The
+//! metadata comes from `ffprobe` where it is installed. Without it, MP4 and QuickTime files
+//! (the usual format of camera and phone footage) are read by a parser of their own.
+
+use eframe::egui;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// The extensions of the video and audio formats whose metadata is read.
+const EXTENSIONS: [&str; 22] = [
+    "mp4", "m4v", "mov", "mkv", "webm", "avi", "wmv", "flv", "mpg", "mpeg", "ts", "3gp", "mp3", "m4a", "aac",
+    "flac", "wav", "ogg", "opus", "wma", "aiff", "mka",
+];
+/// The extensions of the formats the built-in parser reads.
+const MP4_EXTENSIONS: [&str; 5] = ["mp4", "m4v", "mov", "m4a", "3gp"];
+/// The largest `moov` box read, to not load a broken file into memory.
+const MAX_MOOV_SIZE: u64 = 32 << 20;
+/// How many files' metadata is kept before the cache starts over.
+const CACHE_CAPACITY: usize = 256;
+
+/// What is known about a video or audio file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaInfo {
+    /// The length, in seconds.
+    pub duration: Option<f64>,
+    /// The width and height of the video, in pixels.
+    pub resolution: Option<(u32, u32)>,
+    /// The overall bitrate, in bits per second.
+    pub bitrate: Option<u64>,
+}
+
+impl MediaInfo {
+    /// Describes the metadata in lines such as `Duration: 1:02:03`.
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(duration) = self.duration {
+            let seconds = duration.round() as u64;
+            lines.push(format!("Duration: {}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60));
+        }
+        if let Some((width, height)) = self.resolution {
+            let name = match width.max(height) {
+                7680.. => " (8K)",
+                3840.. => " (4K)",
+                2560.. => " (1440p)",
+                1920.. => " (1080p)",
+                1280.. => " (720p)",
+                _ => "",
+            };
+            lines.push(format!("Resolution: {} × {}{}", width, height, name));
+        }
+        if let Some(bitrate) = self.bitrate {
+            lines.push(match bitrate {
+                1_000_000.. => format!("Bitrate: {:.1} Mbit/s", bitrate as f64 / 1e6),
+                _ => format!("Bitrate: {} kbit/s", bitrate / 1000),
+            });
+        }
+        lines
+    }
+}
+
+/// Tells whether the file at `path` is a video or audio file, by its extension.
+pub fn is_media(path: &Path) -> bool {
+    has_extension(path, &EXTENSIONS)
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Reads the metadata of the video or audio file at `path`.
+pub fn probe(path: &Path) -> Result<MediaInfo, String> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "quiet", "-print_format", "json", "-show_format", "-show_streams"])
+        .arg(path)
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            let value: Value = serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+            return Ok(parse_ffprobe(&value));
+        }
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.to_string()),
+    }
+    if !has_extension(path, &MP4_EXTENSIONS) {
+        return Err("install ffprobe to read the metadata of this format".to_string());
+    }
+    let mut file = File::open(path).map_err(|e| e.to_string())?;
+    read_mp4(&mut file).map_err(|e| e.to_string())
+}
+
+/// Reads the metadata from the JSON written by `ffprobe -show_format -show_streams`.
+fn parse_ffprobe(value: &Value) -> MediaInfo {
+    let number = |value: &Value| value.as_str().and_then(|s| s.parse::<f64>().ok());
+    let format = &value["format"];
+    let resolution = value["streams"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|s| s["codec_type"] == "video" && s["disposition"]["attached_pic"] != 1)
+        .find_map(|s| Some((s["width"].as_u64()? as u32, s["height"].as_u64()? as u32)));
+    MediaInfo {
+        duration: number(&format["duration"]),
+        resolution,
+        bitrate: number(&format["bit_rate"]).map(|b| b as u64),
+    }
+}
+
+/// Reads the metadata of an MP4 or QuickTime file from its `moov` box: the duration from
+/// `mvhd`, and the resolution from the `tkhd` of the largest video track.
+pub fn read_mp4<R: Read + Seek>(file: &mut R) -> io::Result<MediaInfo> {
+    let file_size = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(0))?;
+    // Skip the top-level boxes, such as the media data, until the movie box.
+    let moov = loop {
+        let mut header = [0; 8];
+        file.read_exact(&mut header)?;
+        let mut size = u64::from(u32::from_be_bytes(header[..4].try_into().unwrap()));
+        let mut header_size = 8;
+        if size == 1 {
+            let mut large = [0; 8];
+            file.read_exact(&mut large)?;
+            size = u64::from_be_bytes(large);
+            header_size = 16;
+        } else if size == 0 {
+            size = file_size - file.stream_position()? + 8;
+        }
+        let body = size.checked_sub(header_size).ok_or_else(|| invalid("a box is smaller than its header"))?;
+        if &header[4..] == b"moov" {
+            if body > MAX_MOOV_SIZE {
+                return Err(invalid("the movie box is too large"));
+            }
+            let mut moov = vec![0; body as usize];
+            file.read_exact(&mut moov)?;
+            break moov;
+        }
+        file.seek(SeekFrom::Current(body as i64))?;
+    };
+
+    let mut info = MediaInfo::default();
+    for (kind, body) in boxes(&moov) {
+        match kind {
+            b"mvhd" => {
+                let (timescale, duration) = match body.first() {
+                    Some(1) => (read_u32(body, 20)?, read_u64(body, 24)?),
+                    _ => (read_u32(body, 12)?, u64::from(read_u32(body, 16)?)),
+                };
+                if timescale > 0 {
+                    info.duration = Some(duration as f64 / f64::from(timescale));
+                }
+            }
+            b"trak" => {
+                let Some((_, tkhd)) = boxes(body).find(|(kind, _)| *kind == b"tkhd") else {
+                    continue;
+                };
+                // The dimensions are 16.16 fixed-point numbers at the end of the box.
+                let offset = if tkhd.first() == Some(&1) { 88 } else { 76 };
+                let (width, height) = (read_u32(tkhd, offset)? >> 16, read_u32(tkhd, offset + 4)? >> 16);
+                let pixels = |(w, h): (u32, u32)| u64::from(w) * u64::from(h);
+                if width > 0 && info.resolution.is_none_or(|r| pixels((width, height)) > pixels(r)) {
+                    info.resolution = Some((width, height));
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(duration) = info.duration.filter(|d| *d > 0.0) {
+        info.bitrate = Some((file_size as f64 * 8.0 / duration) as u64);
+    }
+    Ok(info)
+}
+
+/// Iterates over the boxes in `data`, as their types and bodies.
+fn boxes(mut data: &[u8]) -> impl Iterator<Item = (&[u8; 4], &[u8])> {
+    std::iter::from_fn(move || {
+        let size = u32::from_be_bytes(data.get(..4)?.try_into().ok()?) as usize;
+        let kind: &[u8; 4] = data.get(4..8)?.try_into().ok()?;
+        let body = data.get(8..size.max(8))?;
+        data = &data[size.max(8)..];
+        Some((kind, body))
+    })
+}
+
+fn read_u32(data: &[u8], offset: usize) -> io::Result<u32> {
+    let bytes = data.get(offset..offset + 4).ok_or_else(|| invalid("a box is truncated"))?;
+    Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> io::Result<u64> {
+    let bytes = data.get(offset..offset + 8).ok_or_else(|| invalid("a box is truncated"))?;
+    Ok(u64::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("Not a valid MP4 file: {}", message))
+}
+
+/// The metadata of a video or audio file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Media {
+    /// The file is being read.
+    Loading,
+    /// The metadata of the file.
+    Ready(MediaInfo),
+    /// The metadata couldn't be read.
+    Failed(String),
+}
+
+/// The metadata of recently selected files, read on background threads.
+pub struct MediaCache {
+    entries: HashMap<PathBuf, Media>,
+    sender: Sender<(PathBuf, Result<MediaInfo, String>)>,
+    receiver: Receiver<(PathBuf, Result<MediaInfo, String>)>,
+}
+
+impl Default for MediaCache {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self { entries: HashMap::new(), sender, receiver }
+    }
+}
+
+impl MediaCache {
+    /// Returns the metadata of the file at `path`, starting to read it if it isn't cached.
+    pub fn get(&mut self, ctx: &egui::Context, path: &Path) -> Media {
+        while let Ok((read_path, info)) = self.receiver.try_recv() {
+            // Entries dropped while they were read stay dropped.
+            if let Some(entry) = self.entries.get_mut(&read_path) {
+                *entry = match info {
+                    Ok(info) => Media::Ready(info),
+                    Err(e) => Media::Failed(e),
+                };
+            }
+        }
+        if let Some(entry) = self.entries.get(path) {
+            return entry.clone();
+        }
+        if self.entries.len() >= CACHE_CAPACITY {
+            self.entries.clear();
+        }
+        self.entries.insert(path.to_path_buf(), Media::Loading);
+        let (sender, ctx, path) = (self.sender.clone(), ctx.clone(), path.to_path_buf());
+        thread::spawn(move || {
+            let info = probe(&path);
+            let _ = sender.send((path, info));
+            ctx.request_repaint();
+        });
+        Media::Loading
+    }
+}
+
+/// Draws the metadata of a video or audio file.
+pub fn show(ui: &mut egui::Ui, media: &Media) {
+    match media {
+        Media::Loading => {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.weak("Reading media metadata...");
+            });
+        }
+        Media::Ready(info) => {
+            for line in info.describe() {
+                ui.label(line);
+            }
+        }
+        Media::Failed(e) => {
+            ui.weak(format!("No media metadata: {}", e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Builds a box of type `kind` around `body`.
+    fn mp4_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut data = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend(kind);
+        data.extend(body);
+        data
+    }
+
+    #[test]
+    fn test_mp4_metadata_is_read() {
+        // A version 0 movie header: 90 000 units at 600 units per second, 150 seconds.
+        let mut mvhd = vec![0; 100];
+        mvhd[12..16].copy_from_slice(&600u32.to_be_bytes());
+        mvhd[16..20].copy_from_slice(&90_000u32.to_be_bytes());
+        // A 4K video track, and an audio track without dimensions.
+        let mut video = vec![0; 84];
+        video[76..80].copy_from_slice(&(3840u32 << 16).to_be_bytes());
+        video[80..84].copy_from_slice(&(2160u32 << 16).to_be_bytes());
+        let audio = vec![0; 84];
+        let moov = [
+            mp4_box(b"mvhd", &mvhd),
+            mp4_box(b"trak", &mp4_box(b"tkhd", &audio)),
+            mp4_box(b"trak", &mp4_box(b"tkhd", &video)),
+        ]
+        .concat();
+        // The media data comes first, as it often does.
+        let file = [mp4_box(b"ftyp", b"isom"), mp4_box(b"mdat", &[0; 1000]), mp4_box(b"moov", &moov)].concat();
+        let size = file.len() as u64;
+
+        let info = read_mp4(&mut Cursor::new(file)).unwrap();
+        assert_eq!(info.duration, Some(150.0));
+        assert_eq!(info.resolution, Some((3840, 2160)));
+        assert_eq!(info.bitrate, Some(size * 8 / 150));
+        assert_eq!(info.describe()[..2], ["Duration: 0:02:30", "Resolution: 3840 × 2160 (4K)"]);
+
+        assert!(read_mp4(&mut Cursor::new(mp4_box(b"mdat", &[0; 10]))).is_err());
+        let ffprobe = serde_json::json!({
+            "format": {"duration": "14400.5", "bit_rate": "45000000"},
+            "streams": [{"codec_type": "audio"}, {"codec_type": "video", "width": 1920, "height": 1080}]
+        });
+        let info = parse_ffprobe(&ffprobe);
+        assert_eq!(info.describe(), ["Duration: 4:00:01", "Resolution: 1920 × 1080 (1080p)", "Bitrate: 45.0 Mbit/s"]);
+    }
+}
@@ -0,0 +1,106 @@
+//! This module draws a small overview of the whole scan in a corner of the treemap.
+//! It is shown while the user is drilled into a subtree, highlights the part of the
+//! tree currently on screen, and can be clicked to jump to another directory.
+
+use crate::coloring::{ColorInput, ColorStrategy};
+use crate::scanner::{FileSystemNode, SizeMetric};
+use crate::treemap::{self, Rectangle, TreemapNode};
+use eframe::egui;
+
+/// The size of the minimap inset, in points.
+const MINIMAP_SIZE: egui::Vec2 = egui::vec2(220.0, 160.0);
+/// The gap between the minimap and the corner of the treemap area.
+const MINIMAP_MARGIN: f32 = 12.0;
+
+/// Caches the layout of the full tree at minimap size.
+#[derive(Default)]
+pub struct Minimap {
+    layout: Option<Vec<TreemapNode>>,
+}
+
+impl Minimap {
+    /// Discards the cached layout. Call this whenever the displayed tree changes.
+    pub fn invalidate(&mut self) {
+        self.layout = None;
+    }
+
+    /// Draws the minimap in the bottom-right corner of `area`.
+    ///
+    /// `tree` is the root of the whole view and `current_root` the path to the subtree that's
+    /// currently shown, which gets highlighted. `metric` and `colors` size and color
+    /// the rectangles the same way as the main treemap.
+    ///
+    /// # Returns
+    ///
+    /// The path of the directory the user clicked on, if any.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        area: egui::Rect,
+        tree: &FileSystemNode,
+        current_root: &[String],
+        metric: SizeMetric,
+        colors: &dyn ColorStrategy,
+    ) -> Option<Vec<String>> {
+        let layout = self.layout.get_or_insert_with(|| {
+            let bounds = Rectangle { x: 0.0, y: 0.0, width: MINIMAP_SIZE.x as f64, height: MINIMAP_SIZE.y as f64 };
+            treemap::generate_treemap(tree, bounds, metric)
+        });
+
+        let inset = egui::Rect::from_min_size(
+            area.max - MINIMAP_SIZE - egui::vec2(MINIMAP_MARGIN, MINIMAP_MARGIN),
+            MINIMAP_SIZE,
+        );
+        let to_screen = |r: &Rectangle| {
+            egui::Rect::from_min_size(
+                inset.min + egui::vec2(r.x as f32, r.y as f32),
+                egui::vec2(r.width as f32, r.height as f32),
+            )
+        };
+
+        let painter = ui.painter().with_clip_rect(inset);
+        painter.rect_filled(inset.expand(2.0), 2.0, egui::Color32::from_gray(20));
+        let mut highlight = None;
+        for node in layout.iter() {
+            let rect = to_screen(&node.rect);
+            if node.path == current_root {
+                highlight = Some(rect);
+            }
+            if rect.width() < 1.0 || rect.height() < 1.0 {
+                continue;
+            }
+            painter.rect_filled(rect, 0.0, colors.color_of(&ColorInput::from(node)));
+        }
+        // Dim everything outside the current subtree so the viewport stands out.
+        if let Some(highlight) = highlight {
+            let dim = egui::Color32::from_black_alpha(140);
+            for side in outside(inset, highlight) {
+                painter.rect_filled(side, 0.0, dim);
+            }
+            painter.rect_stroke(highlight, 0.0, egui::Stroke::new(2.0, egui::Color32::YELLOW));
+        }
+        painter.rect_stroke(inset, 2.0, egui::Stroke::new(1.0, egui::Color32::from_gray(150)));
+
+        // Jump to the deepest directory under the pointer.
+        let response = ui.interact(inset, ui.id().with("minimap"), egui::Sense::click());
+        let pos = response.interact_pointer_pos().filter(|_| response.clicked())?;
+        layout
+            .iter()
+            .filter(|node| to_screen(&node.rect).contains(pos))
+            .filter(|node| tree.find(&node.path).is_some_and(|n| !n.children.is_empty()))
+            .max_by_key(|node| node.depth)
+            .map(|node| node.path.clone())
+            // Clicking outside any directory goes back to the top.
+            .or(Some(Vec::new()))
+    }
+}
+
+/// Splits the part of `outer` that isn't covered by `inner` into up to four rectangles.
+fn outside(outer: egui::Rect, inner: egui::Rect) -> [egui::Rect; 4] {
+    [
+        egui::Rect::from_min_max(outer.min, egui::pos2(outer.max.x, inner.min.y)),
+        egui::Rect::from_min_max(egui::pos2(outer.min.x, inner.max.y), outer.max),
+        egui::Rect::from_min_max(egui::pos2(outer.min.x, inner.min.y), egui::pos2(inner.min.x, inner.max.y)),
+        egui::Rect::from_min_max(egui::pos2(inner.max.x, inner.min.y), egui::pos2(outer.max.x, inner.max.y)),
+    ]
+}
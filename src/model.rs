@@ -0,0 +1,186 @@
+//! This module is the data-model layer that owns the scanned tree.
+//!
+//! The tree is shared as immutable snapshots: a producer (the scanner, or a loaded snapshot)
+//! publishes a whole new `Arc<FileSystemNode>`, and readers take a cheap clone of the current
+//! `Arc` to query it. A reader never sees a half-updated tree, and any number of views can hold
+//! on to the same tree without copying it. A scan adds the entries it finished to a partial
+//! tree while it runs, so the UI can show results before the scan has finished. After a file
+//! operation, the tree is edited in place instead, under the same lock, so the change is seen
+//! all at once.
+
+use crate::scanner::FileSystemNode;
+use std::sync::{Arc, RwLock};
+
+/// The tree currently held by a [`TreeModel`].
+#[derive(Debug, Clone, Default)]
+pub struct TreeSnapshot {
+    /// The published tree, if any.
+    pub tree: Option<Arc<FileSystemNode>>,
+    /// Incremented every time a tree is published or cleared, so readers can tell
+    /// whether their copy is out of date.
+    pub generation: u64,
+    /// Whether the tree is final, as opposed to a partial result of a running scan.
+    pub complete: bool,
+}
+
+#[derive(Debug, Default)]
+struct ModelState {
+    snapshot: TreeSnapshot,
+    /// The publisher allowed to update the tree. Older publishers are ignored.
+    current_publisher: u64,
+}
+
+/// The shared, thread-safe home of the scanned tree.
+#[derive(Debug, Default)]
+pub struct TreeModel {
+    state: RwLock<ModelState>,
+}
+
+impl TreeModel {
+    /// Returns the current tree.
+    pub fn snapshot(&self) -> TreeSnapshot {
+        self.read().snapshot.clone()
+    }
+
+    /// Returns the generation of the current tree, which is cheaper than taking a snapshot
+    /// when checking for changes.
+    pub fn generation(&self) -> u64 {
+        self.read().snapshot.generation
+    }
+
+    /// Takes over publishing from any earlier publisher and clears the tree. Whatever an
+    /// earlier publisher (e.g. an abandoned scan) publishes afterwards is ignored.
+    pub fn publisher(self: &Arc<Self>) -> Publisher {
+        let mut state = self.write();
+        state.current_publisher += 1;
+        state.snapshot = TreeSnapshot { tree: None, generation: state.snapshot.generation + 1, complete: false };
+        Publisher { model: Arc::clone(self), id: state.current_publisher }
+    }
+
+    /// Applies `edit` to the current tree, if there is one, as a new generation. The tree is
+    /// copied first only if a reader still holds it; the reader keeps the old one.
+    pub fn edit(&self, edit: impl FnOnce(&mut FileSystemNode)) {
+        let mut state = self.write();
+        let snapshot = &mut state.snapshot;
+        if let Some(tree) = &mut snapshot.tree {
+            edit(Arc::make_mut(tree));
+            snapshot.generation += 1;
+        }
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, ModelState> {
+        // A panic while holding the lock can't leave the state half-written, since it is
+        // only ever replaced wholesale, so a poisoned lock is safe to keep using.
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, ModelState> {
+        self.state.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The right to publish trees into a [`TreeModel`], handed out by [`TreeModel::publisher`].
+pub struct Publisher {
+    model: Arc<TreeModel>,
+    id: u64,
+}
+
+impl Publisher {
+    /// Replaces the model's tree with `tree`. Does nothing if a newer publisher has taken over.
+    pub fn publish(&self, tree: FileSystemNode, complete: bool) {
+        self.publish_shared(Arc::new(tree), complete);
+    }
+
+    /// Like `publish`, but for a tree that is shared with its owner rather than copied.
+    pub fn publish_shared(&self, tree: Arc<FileSystemNode>, complete: bool) {
+        let mut state = self.model.write();
+        if state.current_publisher != self.id {
+            return;
+        }
+        state.snapshot = TreeSnapshot {
+            tree: Some(tree),
+            generation: state.snapshot.generation + 1,
+            complete,
+        };
+    }
+
+    /// Adds the top-level entries of `entries` to the partial tree, starting one with its root
+    /// if there is none. The tree is edited in place unless a reader still holds it, so only
+    /// the new entries are handed over. Does nothing if a newer publisher has taken over.
+    pub fn publish_more(&self, entries: FileSystemNode) {
+        let mut state = self.model.write();
+        if state.current_publisher != self.id {
+            return;
+        }
+        let snapshot = &mut state.snapshot;
+        match &mut snapshot.tree {
+            Some(tree) if !snapshot.complete => {
+                let tree = Arc::make_mut(tree);
+                tree.size += entries.size;
+                tree.allocated += entries.allocated;
+                tree.children.extend(entries.children);
+            }
+            tree => *tree = Some(Arc::new(entries)),
+        }
+        snapshot.generation += 1;
+        snapshot.complete = false;
+    }
+
+    /// Removes the model's tree, e.g. because the scan failed.
+    pub fn clear(&self) {
+        let mut state = self.model.write();
+        if state.current_publisher == self.id {
+            state.snapshot = TreeSnapshot { tree: None, generation: state.snapshot.generation + 1, complete: true };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(name: &str, size: u64) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children: vec![], special: None }
+    }
+
+    #[test]
+    fn test_only_the_latest_publisher_updates_the_model() {
+        let model = Arc::new(TreeModel::default());
+        let old = model.publisher();
+        old.publish(leaf("partial", 1), false);
+        let snapshot = model.snapshot();
+        assert_eq!(snapshot.tree.as_deref(), Some(&leaf("partial", 1)));
+        assert!(!snapshot.complete);
+
+        let new = model.publisher();
+        assert!(model.snapshot().tree.is_none());
+        old.publish(leaf("stale", 2), true);
+        assert!(model.snapshot().tree.is_none());
+
+        let before = model.generation();
+        new.publish(leaf("fresh", 3), true);
+        let snapshot = model.snapshot();
+        assert!(snapshot.generation > before);
+        assert!(snapshot.complete);
+        // Readers share the published tree rather than copying it.
+        assert!(Arc::ptr_eq(snapshot.tree.as_ref().unwrap(), model.snapshot().tree.as_ref().unwrap()));
+
+        // An edit leaves the tree held by a reader as it was.
+        let before = model.generation();
+        model.edit(|tree| tree.size = 4);
+        assert!(model.generation() > before);
+        assert_eq!(model.snapshot().tree.map(|t| t.size), Some(4));
+        assert_eq!(snapshot.tree.as_deref(), Some(&leaf("fresh", 3)));
+
+        // A scan adds its entries to the partial tree as it goes.
+        let scan = model.publisher();
+        scan.publish_more(FileSystemNode { children: vec![leaf("a", 1)], ..leaf("root", 1) });
+        let partial = model.snapshot();
+        scan.publish_more(FileSystemNode { children: vec![leaf("b", 2), leaf("c", 3)], ..leaf("root", 5) });
+        let tree = model.snapshot().tree.unwrap();
+        assert_eq!((tree.size, tree.children.len()), (6, 3));
+        assert_eq!(partial.tree.map(|t| t.children.len()), Some(1));
+        old.publish_more(leaf("stale", 2));
+        assert_eq!(model.snapshot().tree.map(|t| t.size), Some(6));
+    }
+}
@@ -0,0 +1,214 @@
+//! This module lets the UI watch a running scan for directories that take abnormally long
+//! to list, which usually means a slow network mount or a failing disk. The scanner notes
+//! which directory it is waiting on, and records those that turned out slow. The UI shows
+//! them while the scan runs, and can ask the scanner to skip them, which takes effect as
+//! soon as the directory's current read returns. A single read that doesn't return for
+//! much longer means the scan has hung, e.g. on a hung NFS mount or a dying disk, and is
+//! pointed out as such so the user can abort rather than wait forever.
+//!
+//! When a network share stops answering in the middle of a scan, the scanner pauses and
+//! waits here for the user to either retry the folder it lost or skip what can't be reached.
+
+use crate::stats::SlowDirectory;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Directories taking longer than this to list are reported as slow.
+pub const SLOW_LISTING: Duration = Duration::from_secs(2);
+
+/// A single read that hasn't returned for this long is taken to have hung.
+pub const STALL: Duration = Duration::from_secs(10);
+
+/// How often a scan paused on an unreachable folder checks whether it was cancelled.
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+
+/// What the user decided about a folder that became unreachable during a scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reconnection {
+    /// Read the folder again, e.g. once the share is back.
+    Retry,
+    /// Leave it out, along with every other folder that can't be reached for the rest of the scan.
+    Skip,
+}
+
+/// What a running scan is waiting on, shared between the scanning thread and the UI.
+#[derive(Debug, Default)]
+pub struct ScanMonitor {
+    /// The directory being listed, when its listing would have started had it not been
+    /// interrupted by scanning its subdirectories, and when the current read from it
+    /// started. `None` between reads.
+    waiting: Mutex<Option<(PathBuf, Instant, Instant)>>,
+    /// The directories found slow so far, in the order they finished.
+    slow: Mutex<Vec<SlowDirectory>>,
+    /// The directories the user asked to skip.
+    skipped: Mutex<Vec<PathBuf>>,
+    /// Whether `skipped` has anything in it, so the scanner needn't lock it for every entry.
+    skipping: AtomicBool,
+    /// The folder the scan is paused on because it became unreachable, until the user answers.
+    unreachable: Mutex<Option<PathBuf>>,
+    /// The user's answer about the unreachable folder, once given.
+    reconnection: Mutex<Option<Reconnection>>,
+    /// Wakes the paused scan up when the user answers.
+    answered: Condvar,
+    /// Whether the user chose to skip the folders that can't be reached, so the scan no
+    /// longer pauses for them.
+    skipping_unreachable: AtomicBool,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl ScanMonitor {
+    /// Notes that the scanner is about to read from the directory at `path`, which it has
+    /// spent `listed` listing so far.
+    pub fn start_waiting(&self, path: &Path, listed: Duration) {
+        let now = Instant::now();
+        let since = now.checked_sub(listed).unwrap_or(now);
+        *lock(&self.waiting) = Some((path.to_path_buf(), since, now));
+    }
+
+    /// Notes that the read from the directory returned.
+    pub fn stop_waiting(&self) {
+        *lock(&self.waiting) = None;
+    }
+
+    /// Records that the directory at `path` took `listing` to list, if that is slow.
+    pub fn finished(&self, path: &Path, listing: Duration) {
+        if listing >= SLOW_LISTING {
+            lock(&self.slow).push(SlowDirectory { path: path.to_path_buf(), seconds: listing.as_secs_f64() });
+        }
+    }
+
+    /// The directory being listed, if it has been listed for long enough to be slow, and
+    /// for how long.
+    pub fn stuck(&self) -> Option<(PathBuf, Duration)> {
+        let waiting = lock(&self.waiting);
+        let (path, since, _) = waiting.as_ref()?;
+        let elapsed = since.elapsed();
+        (elapsed >= SLOW_LISTING).then(|| (path.clone(), elapsed))
+    }
+
+    /// The directory the scan is stuck on, if the current read from it has hung, and how
+    /// long that read has gone without returning.
+    pub fn stalled(&self) -> Option<(PathBuf, Duration)> {
+        let waiting = lock(&self.waiting);
+        let (path, _, read_started) = waiting.as_ref()?;
+        let elapsed = read_started.elapsed();
+        (elapsed >= STALL).then(|| (path.clone(), elapsed))
+    }
+
+    /// The directories found slow so far.
+    pub fn slow(&self) -> Vec<SlowDirectory> {
+        lock(&self.slow).clone()
+    }
+
+    /// Asks the scanner to skip the directory at `path` and everything in it.
+    pub fn skip(&self, path: &Path) {
+        lock(&self.skipped).push(path.to_path_buf());
+        self.skipping.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the directory at `path` is to be skipped, because it or a directory
+    /// containing it was.
+    pub fn is_skipped(&self, path: &Path) -> bool {
+        self.skipping.load(Ordering::Relaxed) && lock(&self.skipped).iter().any(|s| path.starts_with(s))
+    }
+
+    /// Pauses the scan, which lost the connection to the folder at `path`, until the user
+    /// answers or `cancel` is set. Returns whether to read the folder again.
+    pub fn wait_for_reconnection(&self, path: &Path, cancel: Option<&AtomicBool>) -> bool {
+        if self.skipping_unreachable.load(Ordering::Relaxed) {
+            return false;
+        }
+        *lock(&self.unreachable) = Some(path.to_path_buf());
+        let mut answer = lock(&self.reconnection);
+        let retry = loop {
+            if let Some(answer) = answer.take() {
+                break answer == Reconnection::Retry;
+            }
+            if cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
+                break false;
+            }
+            answer = self.answered.wait_timeout(answer, CANCEL_POLL).unwrap_or_else(|e| e.into_inner()).0;
+        };
+        drop(answer);
+        *lock(&self.unreachable) = None;
+        retry
+    }
+
+    /// The folder the scan is paused on because it became unreachable, if any.
+    pub fn unreachable(&self) -> Option<PathBuf> {
+        lock(&self.unreachable).clone()
+    }
+
+    /// Resumes the scan paused on an unreachable folder as the user decided.
+    pub fn reconnect(&self, answer: Reconnection) {
+        if answer == Reconnection::Skip {
+            self.skipping_unreachable.store(true, Ordering::Relaxed);
+        }
+        *lock(&self.reconnection) = Some(answer);
+        self.answered.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slow_directories_are_reported() {
+        let monitor = ScanMonitor::default();
+        let path = Path::new("/mnt/nas");
+        monitor.start_waiting(path, Duration::from_millis(10));
+        assert_eq!(monitor.stuck(), None);
+        // Time spent listing before the current read counts towards being stuck.
+        monitor.start_waiting(path, SLOW_LISTING);
+        assert!(monitor.stuck().is_some_and(|(p, elapsed)| p == path && elapsed >= SLOW_LISTING));
+        // But only the current read counts towards having hung.
+        assert_eq!(monitor.stalled(), None);
+        *lock(&monitor.waiting) = Some((path.to_path_buf(), Instant::now() - STALL, Instant::now() - STALL));
+        assert!(monitor.stalled().is_some_and(|(p, elapsed)| p == path && elapsed >= STALL));
+        monitor.stop_waiting();
+        assert_eq!(monitor.stuck(), None);
+
+        monitor.finished(Path::new("/fast"), Duration::from_millis(5));
+        monitor.finished(path, SLOW_LISTING * 2);
+        assert_eq!(monitor.slow(), [SlowDirectory { path: path.to_path_buf(), seconds: 4.0 }]);
+
+        assert!(!monitor.is_skipped(path));
+        monitor.skip(path);
+        assert!(monitor.is_skipped(&path.join("backups")));
+        assert!(!monitor.is_skipped(Path::new("/mnt/usb")));
+    }
+
+    #[test]
+    fn test_scan_waits_for_reconnection() {
+        let monitor = ScanMonitor::default();
+        let path = Path::new("/mnt/nas/photos");
+        // Waits for the scan to pause on the folder, then answers.
+        let answer = |reconnection| {
+            while monitor.unreachable().is_none() {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            assert_eq!(monitor.unreachable().as_deref(), Some(path));
+            monitor.reconnect(reconnection);
+        };
+        std::thread::scope(|scope| {
+            let scan = scope.spawn(|| monitor.wait_for_reconnection(path, None));
+            answer(Reconnection::Retry);
+            assert!(scan.join().unwrap());
+            let scan = scope.spawn(|| monitor.wait_for_reconnection(path, None));
+            answer(Reconnection::Skip);
+            assert!(!scan.join().unwrap());
+        });
+        assert_eq!(monitor.unreachable(), None);
+        // Once skipped, unreachable folders no longer pause the scan.
+        assert!(!monitor.wait_for_reconnection(path, None));
+        // Neither does a cancelled scan wait.
+        let monitor = ScanMonitor::default();
+        assert!(!monitor.wait_for_reconnection(path, Some(&AtomicBool::new(true))));
+    }
+}
@@ -0,0 +1,110 @@
+//! This module works out which mounted volumes a scan covers, so the UI can show the
+//! filesystem type, capacity, and read-only status of each of them, and flag the mounts
+//! that the one-file-system option left out.
+
+use crate::format::{format_size, size_label};
+use crate::volumes::VolumeInfo;
+use eframe::egui;
+use std::path::Path;
+
+/// A volume that the scanned folder lives on or contains.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanMount {
+    /// The volume mounted there.
+    pub volume: VolumeInfo,
+    /// The path of the mount point from the scan root. Empty for the volume holding the root.
+    pub path: Vec<String>,
+    /// Whether the scan skipped this mount because of the one-file-system option.
+    pub skipped: bool,
+}
+
+/// Finds the volumes relevant to a scan of `root`: the one holding the root itself, and
+/// every volume mounted somewhere below it.
+///
+/// `root` should be canonical, so that it can be compared with the mount points.
+pub fn mounts_in_scan(volumes: &[VolumeInfo], root: &Path, one_file_system: bool) -> Vec<ScanMount> {
+    let mut mounts = Vec::new();
+    // The root lives on the volume with the longest mount point containing it.
+    let root_volume = volumes
+        .iter()
+        .filter(|v| root.starts_with(&v.mount_point))
+        .max_by_key(|v| v.mount_point.components().count());
+    if let Some(volume) = root_volume {
+        mounts.push(ScanMount { volume: volume.clone(), path: Vec::new(), skipped: false });
+    }
+
+    let mut below: Vec<ScanMount> = volumes
+        .iter()
+        .filter_map(|volume| {
+            let relative = volume.mount_point.strip_prefix(root).ok()?;
+            let path: Vec<String> = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            (!path.is_empty()).then(|| ScanMount { volume: volume.clone(), path, skipped: one_file_system })
+        })
+        .collect();
+    below.sort_by(|a, b| a.path.cmp(&b.path));
+    mounts.extend(below);
+    mounts
+}
+
+/// Draws a table of volumes with their filesystem, capacity, and status. Each row is
+/// labelled with the given text, e.g. the mount point or its path within the scan.
+pub fn show_volume_table<'a>(ui: &mut egui::Ui, id: &str, rows: impl Iterator<Item = (String, &'a VolumeInfo, bool)>) {
+    egui::Grid::new(id).num_columns(6).striped(true).show(ui, |ui| {
+        for header in ["Mount", "Filesystem", "Total", "Used", "Free", "Status"] {
+            ui.strong(header);
+        }
+        ui.end_row();
+        for (label, volume, skipped) in rows {
+            ui.label(label);
+            ui.label(&volume.file_system);
+            size_label(ui, volume.total);
+            ui.label(format!("{} ({:.0}%)", format_size(volume.used()), volume.usage_fraction() * 100.0));
+            size_label(ui, volume.available);
+            let mut status = Vec::new();
+            if volume.read_only {
+                status.push("read-only");
+            }
+            if skipped {
+                status.push("skipped (one filesystem)");
+            }
+            ui.label(status.join(", "));
+            ui.end_row();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn volume(mount_point: &str) -> VolumeInfo {
+        VolumeInfo {
+            name: mount_point.to_string(),
+            mount_point: PathBuf::from(mount_point),
+            file_system: "ext4".to_string(),
+            total: 100,
+            available: 50,
+            read_only: false,
+        }
+    }
+
+    #[test]
+    fn test_mounts_in_scan() {
+        let volumes = [volume("/"), volume("/home"), volume("/home/me/usb"), volume("/mnt/backup")];
+        let mounts = mounts_in_scan(&volumes, Path::new("/home/me"), true);
+        assert_eq!(mounts.len(), 2);
+        assert_eq!(mounts[0].volume.mount_point, PathBuf::from("/home"));
+        assert!(mounts[0].path.is_empty() && !mounts[0].skipped);
+        assert_eq!(mounts[1].path, ["usb"]);
+        assert!(mounts[1].skipped);
+
+        // Without the option, nested mounts are scanned.
+        let mounts = mounts_in_scan(&volumes, Path::new("/"), false);
+        assert_eq!(mounts.len(), 4);
+        assert!(mounts.iter().all(|m| !m.skipped));
+    }
+}
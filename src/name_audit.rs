@@ -0,0 +1,184 @@
+//! This module looks for paths that would cause trouble on another filesystem, which is worth
+//! knowing before copying data to a drive formatted for another system or syncing it with one:
+//! paths and names longer than a system allows, names ending in a space or a dot (which
+//! Windows drops), names Windows reserves for devices such as `CON` and `NUL`, characters
+//! Windows doesn't allow in names, and names that only differ in case, which are the same
+//! name on Windows and macOS.
+
+use crate::scanner::{FileSystemNode, SizeMetric};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::path::Path;
+
+/// How a length is counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    /// Bytes of UTF-8, as Linux and macOS count them.
+    Bytes,
+    /// UTF-16 code units, as Windows counts them.
+    Characters,
+}
+
+/// A limit on the length of a full path or of a single name.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Limit {
+    path: bool,
+    most: usize,
+    unit: Unit,
+    /// Which systems allow this much, e.g. `macOS allows`.
+    systems: &'static str,
+}
+
+/// The limits checked. A longer path is only reported at the shallowest item beyond each.
+static LIMITS: [Limit; 5] = [
+    Limit { path: true, most: 259, unit: Unit::Characters, systems: "Windows allows unless long paths are turned on" },
+    Limit { path: true, most: 1023, unit: Unit::Bytes, systems: "macOS allows" },
+    Limit { path: true, most: 4095, unit: Unit::Bytes, systems: "Linux allows" },
+    Limit { path: false, most: 255, unit: Unit::Characters, systems: "Windows and macOS allow" },
+    Limit { path: false, most: 255, unit: Unit::Bytes, systems: "Linux allows" },
+];
+
+/// The names Windows reserves for devices, with or without an extension.
+const RESERVED: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1", "LPT2", "LPT3", "LPT4",
+    "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// The characters Windows doesn't allow in names, besides control characters.
+const INVALID: [char; 8] = ['<', '>', ':', '"', '\\', '|', '?', '*'];
+
+/// What is wrong with a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The path or name is `length` long, beyond `limit`.
+    TooLong { limit: &'static Limit, length: usize },
+    TrailingSpaceOrDot,
+    Reserved,
+    InvalidCharacter(char),
+    /// Another item in the same folder has this name, differing only in case.
+    CaseCollision(String),
+}
+
+impl Problem {
+    pub fn describe(&self) -> String {
+        match self {
+            Problem::TooLong { limit, length } => format!(
+                "{} of {} {}, more than the {} {}",
+                if limit.path { "path" } else { "name" },
+                length,
+                if limit.unit == Unit::Bytes { "bytes" } else { "characters" },
+                limit.most,
+                limit.systems
+            ),
+            Problem::TrailingSpaceOrDot => "ends in a space or a dot, which Windows drops".to_string(),
+            Problem::Reserved => "a device name Windows reserves".to_string(),
+            Problem::InvalidCharacter(c) if c.is_control() => format!("holds the control character U+{:04X}, which Windows doesn't allow", *c as u32),
+            Problem::InvalidCharacter(c) => format!("holds {:?}, which Windows doesn't allow", c),
+            Problem::CaseCollision(other) => format!("the same name as {:?} on Windows and macOS, which ignore case", other),
+        }
+    }
+}
+
+/// A problem with the item at `path` from the scan root, of `size` bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub path: Vec<String>,
+    pub problem: Problem,
+    pub size: u64,
+}
+
+/// Looks for the problems of the paths in `tree`, scanned at `root`, measuring sizes by `metric`.
+pub fn audit(root: &Path, tree: &FileSystemNode, metric: SizeMetric) -> Vec<Issue> {
+    let root = root.to_string_lossy();
+    let lengths = (root.len(), root.encode_utf16().count());
+    let mut issues = Vec::new();
+    check_children(tree, &mut Vec::new(), lengths, [false; LIMITS.len()], metric, &mut issues);
+    issues
+}
+
+/// Checks the items in `folder`, at `path` from the scan root, whose full path is `lengths`
+/// long in bytes and characters. `exceeded` tells which path limits the folder is beyond.
+fn check_children(
+    folder: &FileSystemNode,
+    path: &mut Vec<String>,
+    lengths: (usize, usize),
+    exceeded: [bool; LIMITS.len()],
+    metric: SizeMetric,
+    issues: &mut Vec<Issue>,
+) {
+    // The first item of each name in lower case, to find those differing only in case.
+    let mut first_of: HashMap<String, &str> = HashMap::new();
+    for child in &folder.children {
+        path.push(child.name.clone());
+        let size = child.size_by(metric);
+        let mut report = |problem| issues.push(Issue { path: path.clone(), problem, size });
+        let name = (child.name.len(), child.name.encode_utf16().count());
+        let full = (lengths.0 + 1 + name.0, lengths.1 + 1 + name.1);
+        let mut beyond = exceeded;
+        for (j, limit) in LIMITS.iter().enumerate() {
+            let (length, total) = if limit.unit == Unit::Bytes { (name.0, full.0) } else { (name.1, full.1) };
+            let length = if limit.path { total } else { length };
+            if length > limit.most && !exceeded[j] {
+                report(Problem::TooLong { limit, length });
+                beyond[j] = limit.path;
+            }
+        }
+        if child.name.ends_with([' ', '.']) {
+            report(Problem::TrailingSpaceOrDot);
+        }
+        let stem = child.name.split('.').next().unwrap_or_default().trim_end();
+        if RESERVED.iter().any(|r| r.eq_ignore_ascii_case(stem)) {
+            report(Problem::Reserved);
+        }
+        if let Some(c) = child.name.chars().find(|c| INVALID.contains(c) || c.is_control()) {
+            report(Problem::InvalidCharacter(c));
+        }
+        match first_of.entry(child.name.to_lowercase()) {
+            Entry::Occupied(other) => report(Problem::CaseCollision(other.get().to_string())),
+            Entry::Vacant(first) => {
+                first.insert(&child.name);
+            }
+        }
+        check_children(child, path, full, beyond, metric, issues);
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, children: Vec<FileSystemNode>) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size: 10, allocated: 10, children, special: None }
+    }
+
+    #[test]
+    fn test_audit_finds_problems() {
+        let deep = "d".repeat(200);
+        let tree = node(
+            "root",
+            vec![
+                node("CON.txt", vec![]),
+                node("notes. ", vec![]),
+                node("a:b?.txt", vec![]),
+                node("Readme", vec![]),
+                node("README", vec![]),
+                node(&deep, vec![node(&deep, vec![node("inner.txt", vec![])])]),
+                node("fine.txt", vec![]),
+            ],
+        );
+        let issues = audit(Path::new("/data"), &tree, SizeMetric::Apparent);
+        let found: Vec<(String, String)> = issues.iter().map(|i| (i.path.last().unwrap().chars().take(8).collect(), i.problem.describe())).collect();
+        assert_eq!(
+            found,
+            [
+                ("CON.txt".to_string(), "a device name Windows reserves".to_string()),
+                ("notes. ".to_string(), "ends in a space or a dot, which Windows drops".to_string()),
+                ("a:b?.txt".to_string(), "holds ':', which Windows doesn't allow".to_string()),
+                ("README".to_string(), "the same name as \"Readme\" on Windows and macOS, which ignore case".to_string()),
+                // Only the shallowest item beyond the limit is reported, not what it holds.
+                ("dddddddd".to_string(), "path of 407 characters, more than the 259 Windows allows unless long paths are turned on".to_string()),
+            ]
+        );
+    }
+}
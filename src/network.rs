@@ -0,0 +1,95 @@
+//! This module connects Windows network shares before they are scanned. A share that
+//! wants a user name and password can't be listed until it is connected, so scanning its
+//! UNC path (e.g. `\\nas\backups`) first asks for them, and connects the share with
+//! PowerShell's `New-SmbMapping`. The password is handed over in the environment rather
+//! than on a command line, where other programs could read it.
+
+use std::io;
+use std::path::Path;
+#[cfg(windows)]
+use std::process::Command;
+
+/// Connects the share named in `DISK_SCOUT_SHARE` as the user and password in the other
+/// variables, without giving it a drive letter.
+#[cfg(windows)]
+const CONNECT: &str = r#"
+$ErrorActionPreference = 'Stop'
+New-SmbMapping -RemotePath $env:DISK_SCOUT_SHARE -UserName $env:DISK_SCOUT_USER -Password $env:DISK_SCOUT_PASSWORD | Out-Null
+"#;
+
+/// The user name and password to connect a share with.
+#[derive(Debug, Clone, Default)]
+pub struct Credentials {
+    /// The share, e.g. `\\nas\backups`.
+    pub share: String,
+    /// The user name, e.g. `alice` or `WORKGROUP\alice`.
+    pub user: String,
+    /// The password.
+    pub password: String,
+}
+
+/// Splits the share off a UNC path: `\\server\share\folder` gives `\\server\share`.
+/// Forward slashes work too. Other paths, including `\\?\` ones, give `None`.
+pub fn unc_share(path: &str) -> Option<String> {
+    let rest = path.strip_prefix(r"\\").or_else(|| path.strip_prefix("//"))?;
+    let mut parts = rest.split(['\\', '/']);
+    let server = parts.next().filter(|s| !s.is_empty() && *s != "?" && *s != ".")?;
+    let share = parts.next().filter(|s| !s.is_empty())?;
+    Some(format!(r"\\{}\{}", server, share))
+}
+
+/// The share `path` is on, if that is a share that can't be read until it is connected
+/// with a user name and password. Only Windows connects shares; elsewhere, `//server/share`
+/// is an ordinary path, so a permission error on it is just that.
+pub fn share_needing_credentials(path: &Path) -> Option<String> {
+    if !cfg!(windows) {
+        return None;
+    }
+    let share = unc_share(&path.to_string_lossy())?;
+    let error = std::fs::metadata(path).err()?;
+    needs_credentials(&error).then_some(share)
+}
+
+/// Whether `error` means the share wants to know who is connecting: access denied, or
+/// ERROR_INVALID_PASSWORD, ERROR_NOT_AUTHENTICATED, or ERROR_LOGON_FAILURE.
+fn needs_credentials(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::PermissionDenied || (cfg!(windows) && matches!(error.raw_os_error(), Some(86 | 1244 | 1326)))
+}
+
+/// Connects the share in `credentials`, so it can be scanned.
+#[cfg(windows)]
+pub fn connect(credentials: &Credentials) -> io::Result<()> {
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", CONNECT])
+        .env("DISK_SCOUT_SHARE", &credentials.share)
+        .env("DISK_SCOUT_USER", &credentials.user)
+        .env("DISK_SCOUT_PASSWORD", &credentials.password)
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()))
+    }
+}
+
+/// Shares are connected by UNC path only on Windows; elsewhere they are mounted.
+#[cfg(not(windows))]
+pub fn connect(_credentials: &Credentials) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "shares can only be connected by UNC path on Windows"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unc_share() {
+        assert_eq!(unc_share(r"\\nas\backups\2024\photos").as_deref(), Some(r"\\nas\backups"));
+        assert_eq!(unc_share(r"\\nas\backups").as_deref(), Some(r"\\nas\backups"));
+        assert_eq!(unc_share("//nas/media/").as_deref(), Some(r"\\nas\media"));
+        assert_eq!(unc_share(r"\\nas"), None);
+        assert_eq!(unc_share(r"\\?\C:\Users"), None);
+        assert_eq!(unc_share(r"C:\Users"), None);
+        assert_eq!(unc_share("/home/me"), None);
+    }
+}
@@ -0,0 +1,208 @@
+//! This module keeps the tags ("keep", "review", "archive") and free-text notes users attach
+//! to files and folders, so a cleanup can be picked up where it was left in a later session.
+//! Notes are keyed by the path from the scan root, saved per scanned folder in the app's
+//! data folder, and written into saved snapshots. A tag applies to everything inside the
+//! tagged folder, unless something inside is tagged otherwise.
+
+use crate::git::sum_children;
+use crate::scanner::FileSystemNode;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A tag that can be attached to a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tag {
+    Keep,
+    Review,
+    Archive,
+}
+
+impl Tag {
+    pub const ALL: [Tag; 3] = [Tag::Keep, Tag::Review, Tag::Archive];
+
+    /// The name of the tag, as shown to the user.
+    pub fn label(self) -> &'static str {
+        match self {
+            Tag::Keep => "Keep",
+            Tag::Review => "Review",
+            Tag::Archive => "Archive",
+        }
+    }
+
+    /// The color nodes with this tag are drawn in.
+    pub fn color(self) -> egui::Color32 {
+        match self {
+            Tag::Keep => egui::Color32::from_rgb(90, 170, 90),
+            Tag::Review => egui::Color32::from_rgb(220, 170, 60),
+            Tag::Archive => egui::Color32::from_rgb(120, 130, 210),
+        }
+    }
+}
+
+/// The tags and note attached to a node.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Note {
+    /// The tags, in the order of `Tag::ALL`.
+    pub tags: Vec<Tag>,
+    /// The free-text note. Empty if there is none.
+    pub text: String,
+}
+
+impl Note {
+    /// Whether there is nothing to keep.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.text.trim().is_empty()
+    }
+
+    /// Attaches `tag` if it isn't attached, and removes it otherwise.
+    pub fn toggle(&mut self, tag: Tag) {
+        match self.tags.iter().position(|t| *t == tag) {
+            Some(i) => {
+                self.tags.remove(i);
+            }
+            None => {
+                self.tags.push(tag);
+                self.tags.sort();
+            }
+        }
+    }
+}
+
+/// The notes of a scan, keyed by the path of their node from the scan root (joined with `/`).
+pub type Notes = BTreeMap<String, Note>;
+
+/// The key of the node at `path` from the scan root.
+pub fn key(path: &[String]) -> String {
+    path.join("/")
+}
+
+/// Returns the tag the node at `path` has, directly or from the nearest tagged folder
+/// containing it. A node with several tags has the first of them.
+pub fn tag_of(notes: &Notes, path: &[String]) -> Option<Tag> {
+    (0..=path.len()).rev().find_map(|len| notes.get(&key(&path[..len]))?.tags.first().copied())
+}
+
+/// Returns a copy of `tree` keeping only what has `tag`, with the sizes of the folders
+/// reduced to what is left in them.
+pub fn filter(tree: &FileSystemNode, notes: &Notes, tag: Tag) -> FileSystemNode {
+    let mut path = Vec::new();
+    let children = tree.children.iter().filter_map(|c| filter_node(c, notes, tag, &mut path)).collect();
+    sum_children(tree, children)
+}
+
+/// Filters `node`, whose parent is at `path`. Returns `None` if nothing with `tag` is left.
+fn filter_node(node: &FileSystemNode, notes: &Notes, tag: Tag, path: &mut Vec<String>) -> Option<FileSystemNode> {
+    path.push(node.name.clone());
+    let prefix = format!("{}/", key(path));
+    let tagged_inside = notes.range(prefix.clone()..).next().is_some_and(|(k, _)| k.starts_with(&prefix));
+    let filtered = if tag_of(notes, path) == Some(tag) && !tagged_inside {
+        Some(node.clone())
+    } else if node.children.is_empty() {
+        None
+    } else {
+        // Something inside is tagged, possibly otherwise, so look at each child.
+        let children: Vec<_> = node.children.iter().filter_map(|c| filter_node(c, notes, tag, path)).collect();
+        (!children.is_empty()).then(|| sum_children(node, children))
+    };
+    path.pop();
+    filtered
+}
+
+/// Where the notes of the scans of `root` are kept, in the app's data folder.
+#[cfg(not(target_arch = "wasm32"))]
+fn file_for(root: &std::path::Path) -> Option<std::path::PathBuf> {
+    let key = blake3::hash(root.to_string_lossy().as_bytes()).to_hex();
+    Some(eframe::storage_dir("Disk Scout")?.join("notes").join(format!("{}.json", &key.as_str()[..16])))
+}
+
+/// Reads the notes of the scans of `root`. There are none if they were never saved.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(root: &std::path::Path) -> Result<Notes, String> {
+    let Some(file) = file_for(root) else {
+        return Ok(Notes::new());
+    };
+    match std::fs::read(&file) {
+        Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| format!("Failed to read {}: {}", file.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Notes::new()),
+        Err(e) => Err(format!("Failed to read {}: {}", file.display(), e)),
+    }
+}
+
+/// Saves `notes` as the notes of the scans of `root`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(root: &std::path::Path, notes: &Notes) -> Result<(), String> {
+    let file = file_for(root).ok_or("There is nowhere to keep notes on this system.")?;
+    let write = || {
+        std::fs::create_dir_all(file.parent().unwrap_or(&file))?;
+        std::fs::write(&file, serde_json::to_vec_pretty(notes)?)
+    };
+    write().map_err(|e: std::io::Error| format!("Failed to save notes to {}: {}", file.display(), e))
+}
+
+/// Draws a legend of the tags in the bottom-left corner of `area`, in place of the depth legend.
+pub fn show_legend(ui: &egui::Ui, area: egui::Rect) {
+    const ROW: f32 = 16.0;
+    let size = egui::vec2(90.0, ROW * Tag::ALL.len() as f32 + 8.0);
+    let legend = egui::Rect::from_min_size(egui::pos2(area.min.x + 12.0, area.max.y - 12.0 - size.y), size);
+    let painter = ui.painter().with_clip_rect(legend);
+    painter.rect_filled(legend, 3.0, egui::Color32::from_black_alpha(180));
+    for (i, tag) in Tag::ALL.into_iter().enumerate() {
+        let top_left = legend.min + egui::vec2(4.0, 4.0 + ROW * i as f32);
+        painter.rect_filled(egui::Rect::from_min_size(top_left, egui::vec2(12.0, 12.0)), 0.0, tag.color());
+        painter.text(
+            top_left + egui::vec2(18.0, 6.0),
+            egui::Align2::LEFT_CENTER,
+            tag.label(),
+            egui::FontId::proportional(11.0),
+            egui::Color32::from_gray(220),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, size: u64, children: Vec<FileSystemNode>) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    #[test]
+    fn test_tags_are_inherited_and_filtered() {
+        let tree = node(
+            "home",
+            60,
+            vec![
+                node("photos", 30, vec![node("2019", 20, vec![]), node("raw", 10, vec![])]),
+                node("downloads", 30, vec![node("setup.exe", 25, vec![]), node("todo.txt", 5, vec![])]),
+            ],
+        );
+        let path = |p: &str| p.split('/').map(str::to_string).collect::<Vec<_>>();
+        let mut notes = Notes::new();
+        notes.entry(key(&path("photos"))).or_default().toggle(Tag::Keep);
+        notes.entry(key(&path("photos/raw"))).or_default().toggle(Tag::Review);
+        let installer = notes.entry(key(&path("downloads/setup.exe"))).or_default();
+        installer.toggle(Tag::Review);
+        installer.text = "Old driver, reinstall from the vendor".to_string();
+
+        assert_eq!(tag_of(&notes, &path("photos/2019")), Some(Tag::Keep));
+        assert_eq!(tag_of(&notes, &path("photos/raw")), Some(Tag::Review));
+        assert_eq!(tag_of(&notes, &path("downloads/todo.txt")), None);
+
+        let review = filter(&tree, &notes, Tag::Review);
+        let names: Vec<String> = review.children.iter().flat_map(|c| c.children.iter().map(|g| g.name.clone())).collect();
+        assert_eq!(names, ["raw", "setup.exe"]);
+        assert_eq!(review.size, 35);
+        assert_eq!(filter(&tree, &notes, Tag::Keep).size, 20);
+        assert_eq!(filter(&tree, &notes, Tag::Archive).children, []);
+
+        // Untagging leaves an entry only while it has a note.
+        notes.get_mut("photos").unwrap().toggle(Tag::Keep);
+        assert!(notes["photos"].is_empty());
+        let restored: Notes = serde_json::from_str(&serde_json::to_string(&notes).unwrap()).unwrap();
+        assert_eq!(restored, notes);
+    }
+}
@@ -0,0 +1,257 @@
+//! This module attributes the space in a folder to the users owning the files, and reads the
+//! disk quotas of the volume holding it, so admins of shared servers can see who uses how much
+//! of a directory next to how close each user is to their limit.
+//!
+//! Owners come from the files' metadata and their names from `/etc/passwd`, falling back to
+//! `id` for accounts kept elsewhere (e.g. LDAP or Directory Services). Quotas come from
+//! `repquota`, which needs the quota tools and root rights. Both only work on Unix.
+
+use crate::scanner::FileSystemNode;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+/// The space one user owns in a folder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnerUsage {
+    pub uid: u32,
+    /// The account name, or the uid when it has none.
+    pub name: String,
+    pub files: u64,
+    /// The logical size of the files in bytes.
+    pub size: u64,
+    /// The space allocated on disk for the files in bytes.
+    pub allocated: u64,
+}
+
+/// A user's disk quota on a volume.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quota {
+    pub user: String,
+    /// The space the user uses on the whole volume in bytes.
+    pub used: u64,
+    /// The limit the user may exceed for a grace period, if set.
+    pub soft: Option<u64>,
+    /// The limit the user can't exceed, if set.
+    pub hard: Option<u64>,
+}
+
+impl Quota {
+    /// The limit the usage is measured against: the soft limit if set, else the hard one.
+    pub fn limit(&self) -> Option<u64> {
+        self.soft.or(self.hard)
+    }
+
+    /// Whether the user is over the soft limit, or at the hard one.
+    pub fn exceeded(&self) -> bool {
+        self.soft.is_some_and(|soft| self.used > soft) || self.hard.is_some_and(|hard| self.used >= hard)
+    }
+}
+
+/// The owners of a folder, with the quotas of its volume.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnerReport {
+    /// The owners, the ones owning the most space first.
+    pub owners: Vec<OwnerUsage>,
+    /// The quotas of the volume, or why they couldn't be read.
+    pub quotas: Result<Vec<Quota>, String>,
+}
+
+impl OwnerReport {
+    /// The quota of the owner named `name`, if there is one.
+    pub fn quota_of(&self, name: &str) -> Option<&Quota> {
+        self.quotas.as_ref().ok()?.iter().find(|q| q.user == name)
+    }
+}
+
+/// Looks up the owner of every file in `node`, which is the folder `dir` on disk, and reads
+/// the quotas of the volume mounted at `mount_point`.
+pub fn measure(dir: &Path, node: &FileSystemNode, mount_point: Option<&Path>) -> io::Result<OwnerReport> {
+    let owners = usage_by_owner(dir, node)?;
+    let quotas = match mount_point {
+        Some(mount_point) => quotas(mount_point).map_err(|e| e.to_string()),
+        None => Err("the volume holding the folder is unknown".to_string()),
+    };
+    Ok(OwnerReport { owners, quotas })
+}
+
+/// Adds up the files in `node`, which is the folder `dir` on disk, by the user owning them.
+/// Hard links are counted once.
+#[cfg(unix)]
+pub fn usage_by_owner(dir: &Path, node: &FileSystemNode) -> io::Result<Vec<OwnerUsage>> {
+    use std::collections::HashSet;
+    use std::os::unix::fs::MetadataExt;
+
+    let mut files = Vec::new();
+    collect_files(node, &mut Vec::new(), &mut files);
+    let mut seen = HashSet::new();
+    let mut by_uid: HashMap<u32, OwnerUsage> = HashMap::new();
+    for relative in files {
+        let Ok(metadata) = std::fs::metadata(dir.join(relative.join("/"))) else {
+            continue;
+        };
+        if !metadata.is_file() || (metadata.nlink() > 1 && !seen.insert((metadata.dev(), metadata.ino()))) {
+            continue;
+        }
+        let usage = by_uid.entry(metadata.uid()).or_insert_with(|| OwnerUsage {
+            uid: metadata.uid(),
+            name: String::new(),
+            files: 0,
+            size: 0,
+            allocated: 0,
+        });
+        usage.files += 1;
+        usage.size += metadata.len();
+        usage.allocated += metadata.blocks() * 512;
+    }
+
+    let names = std::fs::read_to_string("/etc/passwd").map(|text| parse_passwd(&text)).unwrap_or_default();
+    let mut owners: Vec<OwnerUsage> = by_uid.into_values().collect();
+    for owner in &mut owners {
+        owner.name = names.get(&owner.uid).cloned().or_else(|| name_from_id(owner.uid)).unwrap_or_else(|| owner.uid.to_string());
+    }
+    owners.sort_by(|a, b| b.allocated.cmp(&a.allocated).then_with(|| a.name.cmp(&b.name)));
+    Ok(owners)
+}
+
+/// Files have no Unix owners on this platform.
+#[cfg(not(unix))]
+pub fn usage_by_owner(_dir: &Path, _node: &FileSystemNode) -> io::Result<Vec<OwnerUsage>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "file owners can't be read on this platform"))
+}
+
+/// Reads the user quotas of the volume mounted at `mount_point` with `repquota`.
+#[cfg(unix)]
+pub fn quotas(mount_point: &Path) -> io::Result<Vec<Quota>> {
+    use std::process::Command;
+
+    // `-p` prints the grace times as numbers, so that every column is always there.
+    let output = Command::new("repquota").args(["-u", "-p"]).arg(mount_point).output().map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => io::Error::new(e.kind(), "repquota isn't installed; it comes with the quota tools"),
+        _ => e,
+    })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let message = if stderr.contains("Permission denied") || stderr.contains("Operation not permitted") {
+            "reading quotas needs root rights".to_string()
+        } else if stderr.is_empty() {
+            "the volume has no user quotas".to_string()
+        } else {
+            stderr
+        };
+        return Err(io::Error::other(message));
+    }
+    Ok(parse_repquota(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// There are no quotas to read on this platform.
+#[cfg(not(unix))]
+pub fn quotas(_mount_point: &Path) -> io::Result<Vec<Quota>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "quotas can't be read on this platform"))
+}
+
+/// Asks `id` for the name of `uid`, for accounts that aren't in `/etc/passwd`.
+#[cfg(unix)]
+fn name_from_id(uid: u32) -> Option<String> {
+    let output = std::process::Command::new("id").args(["-nu", &uid.to_string()]).output().ok()?;
+    let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some(name).filter(|name| output.status.success() && !name.is_empty())
+}
+
+/// Parses `/etc/passwd` into the names of the uids.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn parse_passwd(text: &str) -> HashMap<u32, String> {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+/// Parses the output of `repquota -u -p`. Its sizes are in KiB, and a limit of 0 means none.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn parse_repquota(output: &str) -> Vec<Quota> {
+    let kib = |field: &str| field.parse::<u64>().ok().map(|k| k * 1024);
+    output
+        .lines()
+        .skip_while(|line| !line.starts_with("---"))
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [user, flags, used, soft, hard, ..] = fields[..] else {
+                return None;
+            };
+            if flags.len() != 2 {
+                return None;
+            }
+            Some(Quota {
+                user: user.to_string(),
+                used: kib(used)?,
+                soft: kib(soft).filter(|&s| s > 0),
+                hard: kib(hard).filter(|&h| h > 0),
+            })
+        })
+        .collect()
+}
+
+/// Collects the paths (from `node`) of every leaf node below it.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn collect_files(node: &FileSystemNode, prefix: &mut Vec<String>, files: &mut Vec<Vec<String>>) {
+    for child in &node.children {
+        prefix.push(child.name.clone());
+        if child.children.is_empty() {
+            files.push(prefix.clone());
+        } else {
+            collect_files(child, prefix, files);
+        }
+        prefix.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_repquota_and_passwd() {
+        let output = "\
+*** Report for user quotas on device /dev/sdb1
+Block grace time: 7days; Inode grace time: 7days
+                        Block limits                File limits
+User            used    soft    hard  grace    used  soft  hard  grace
+----------------------------------------------------------------------
+root      --      20       0       0      0       2     0     0      0
+alice     +-  120000  100000  150000 1760000000     120     0     0      0
+
+";
+        let quotas = parse_repquota(output);
+        assert_eq!(quotas.len(), 2);
+        assert_eq!(quotas[0], Quota { user: "root".to_string(), used: 20 * 1024, soft: None, hard: None });
+        assert_eq!((quotas[1].limit(), quotas[1].exceeded()), (Some(100000 * 1024), true));
+
+        let names = parse_passwd("# comment\nroot:x:0:0:root:/root:/bin/bash\nalice:x:1000:1000::/home/alice:/bin/sh\nbroken\n");
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[&1000], "alice");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_usage_by_owner() {
+        use crate::scanner::{ScanOptions, build_tree};
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("a.bin"), vec![0u8; 3000]).unwrap();
+        std::fs::write(dir.path().join("sub/b.bin"), vec![0u8; 1000]).unwrap();
+        std::fs::hard_link(dir.path().join("a.bin"), dir.path().join("sub/a-link.bin")).unwrap();
+
+        let tree = build_tree(dir.path(), &ScanOptions::default()).unwrap();
+        let owners = usage_by_owner(dir.path(), &tree).unwrap();
+        assert_eq!(owners.len(), 1);
+        assert_eq!((owners[0].files, owners[0].size), (2, 4000));
+    }
+}
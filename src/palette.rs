@@ -0,0 +1,126 @@
+//! This module colors treemap rectangles by their depth below the view root. The colors
+//! ramp from dark to bright while the hue moves between two configurable hues, and the
+//! ramp repeats every few levels so that deep trees don't end up in a single color.
+//! A small legend in a corner of the treemap shows which color stands for which depth.
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// The brightness of the first and the last level of the ramp.
+const VALUE_RANGE: (f32, f32) = (0.25, 1.0);
+/// The size of a legend swatch, in points.
+const SWATCH_SIZE: f32 = 18.0;
+/// The gap between the legend and the corner of the treemap area.
+const LEGEND_MARGIN: f32 = 12.0;
+
+/// How depths are mapped to colors.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DepthPalette {
+    /// The number of distinct colors before the ramp starts over.
+    pub levels: usize,
+    /// The hue of the first level, in degrees.
+    pub start_hue: f32,
+    /// The hue of the last level, in degrees.
+    pub end_hue: f32,
+    /// Whether the legend is shown on the treemap.
+    pub show_legend: bool,
+    /// How much nodes that take up little of their parent are faded, from 0.0 (not at all)
+    /// to 1.0. See `coloring::ShareEmphasis`.
+    pub share_emphasis: f32,
+}
+
+impl Default for DepthPalette {
+    /// A ramp from dark blue to bright blue over eight levels.
+    fn default() -> Self {
+        Self { levels: 8, start_hue: 208.0, end_hue: 204.0, show_legend: true, share_emphasis: 0.0 }
+    }
+}
+
+impl DepthPalette {
+    /// The fewest and the most levels that can be configured.
+    pub const LEVEL_RANGE: std::ops::RangeInclusive<usize> = 2..=16;
+
+    /// Returns the color of nodes at `depth` below the view root (its children being at depth 1).
+    pub fn color(&self, depth: usize) -> egui::Color32 {
+        let levels = self.levels.clamp(*Self::LEVEL_RANGE.start(), *Self::LEVEL_RANGE.end());
+        let t = (depth.saturating_sub(1) % levels) as f32 / (levels - 1) as f32;
+        let hue = (self.start_hue + (self.end_hue - self.start_hue) * t).rem_euclid(360.0) / 360.0;
+        let value = VALUE_RANGE.0 + (VALUE_RANGE.1 - VALUE_RANGE.0) * t;
+        egui::ecolor::Hsva::new(hue, 1.0, value, 1.0).into()
+    }
+
+    /// Draws the legend in the bottom-left corner of `area`: one numbered swatch per level.
+    pub fn show_legend(&self, ui: &egui::Ui, area: egui::Rect) {
+        let levels = self.levels.clamp(*Self::LEVEL_RANGE.start(), *Self::LEVEL_RANGE.end());
+        let size = egui::vec2(SWATCH_SIZE * levels as f32, SWATCH_SIZE * 2.0) + egui::vec2(8.0, 8.0);
+        let legend = egui::Rect::from_min_size(
+            egui::pos2(area.min.x + LEGEND_MARGIN, area.max.y - LEGEND_MARGIN - size.y),
+            size,
+        );
+        let painter = ui.painter().with_clip_rect(legend);
+        painter.rect_filled(legend, 3.0, egui::Color32::from_black_alpha(180));
+        painter.text(
+            legend.min + egui::vec2(4.0, 4.0),
+            egui::Align2::LEFT_TOP,
+            format!("Depth (repeats after {})", levels),
+            egui::FontId::proportional(11.0),
+            egui::Color32::from_gray(220),
+        );
+        for level in 1..=levels {
+            let swatch = egui::Rect::from_min_size(
+                legend.min + egui::vec2(4.0 + SWATCH_SIZE * (level - 1) as f32, 4.0 + SWATCH_SIZE),
+                egui::vec2(SWATCH_SIZE, SWATCH_SIZE),
+            );
+            let color = self.color(level);
+            painter.rect_filled(swatch, 0.0, color);
+            let bright = egui::Rgba::from(color).intensity() > 0.5;
+            let text_color = if bright { egui::Color32::BLACK } else { egui::Color32::WHITE };
+            painter.text(
+                swatch.center(),
+                egui::Align2::CENTER_CENTER,
+                level.to_string(),
+                egui::FontId::proportional(10.0),
+                text_color,
+            );
+        }
+    }
+
+    /// Draws the palette settings, with a preview of the ramp.
+    pub fn show_settings(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.levels, Self::LEVEL_RANGE).text("Distinct depth levels"));
+        ui.add(egui::Slider::new(&mut self.start_hue, 0.0..=360.0).suffix("°").text("Hue of the top level"));
+        ui.add(egui::Slider::new(&mut self.end_hue, 0.0..=360.0).suffix("°").text("Hue of the deepest level"));
+        ui.horizontal(|ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+            for level in 1..=self.levels {
+                let (swatch, _) = ui.allocate_exact_size(egui::vec2(14.0, 14.0), egui::Sense::hover());
+                ui.painter().rect_filled(swatch, 0.0, self.color(level));
+            }
+        });
+        ui.add(egui::Slider::new(&mut self.share_emphasis, 0.0..=1.0).text("Fade items that are small within their folder"));
+        ui.checkbox(&mut self.show_legend, "Show the legend on the treemap");
+        if ui.button("Reset colors").clicked() {
+            *self = Self { show_legend: self.show_legend, share_emphasis: self.share_emphasis, ..Self::default() };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_depth_colors_ramp_and_repeat() {
+        let palette = DepthPalette { levels: 4, start_hue: 0.0, end_hue: 120.0, ..DepthPalette::default() };
+        // The first level is dark red and the last one bright green.
+        assert_eq!(palette.color(1), egui::Color32::from(egui::ecolor::Hsva::new(0.0, 1.0, 0.25, 1.0)));
+        assert_eq!(palette.color(4), egui::Color32::from_rgb(0, 255, 0));
+        // The ramp starts over after the configured number of levels.
+        assert_eq!(palette.color(5), palette.color(1));
+        assert_ne!(palette.color(2), palette.color(3));
+        // Nonsensical level counts from a hand-edited settings file don't break the ramp.
+        let degenerate = DepthPalette { levels: 0, ..palette.clone() };
+        assert_eq!(degenerate.color(3), degenerate.color(1));
+    }
+}
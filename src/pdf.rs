@@ -0,0 +1,145 @@
+//! This module writes simple text documents as PDF, for reports to attach to tickets. It
+//! only knows what the reports need: lines of text in a few of the fonts every PDF reader
+//! has built in, laid out top to bottom on A4 pages. Characters those fonts lack are
+//! replaced with `?`.
+
+use std::fmt::Write;
+
+/// The width and height of an A4 page, in points.
+const PAGE_SIZE: (f32, f32) = (595.0, 842.0);
+/// The space left around the text, in points.
+const MARGIN: f32 = 50.0;
+
+/// How a line is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// A section heading, in bold.
+    Heading,
+    /// Running text.
+    Body,
+    /// Fixed-width text, for tables whose columns must line up.
+    Mono,
+}
+
+impl Style {
+    /// The resource name of the font, its font size, and the height of a line.
+    fn font(self) -> (&'static str, f32, f32) {
+        match self {
+            Style::Heading => ("F1", 14.0, 24.0),
+            Style::Body => ("F2", 10.0, 14.0),
+            Style::Mono => ("F3", 8.5, 11.0),
+        }
+    }
+
+    /// How many characters fit on a line, for text that is to be wrapped or cut.
+    pub fn line_width(self) -> usize {
+        // Courier's characters are 0.6 em wide; Helvetica's are about 0.5 on average.
+        let (_, size, _) = self.font();
+        let em = if self == Style::Mono { 0.6 } else { 0.5 };
+        ((PAGE_SIZE.0 - 2.0 * MARGIN) / (size * em)) as usize
+    }
+}
+
+/// Writes `lines` as a PDF document, starting a new page whenever one is full.
+pub fn write(lines: &[(Style, String)]) -> Vec<u8> {
+    let mut pages = vec![String::new()];
+    let mut y = PAGE_SIZE.1 - MARGIN;
+    for (style, text) in lines {
+        let (font, size, height) = style.font();
+        if y - height < MARGIN {
+            pages.push(String::new());
+            y = PAGE_SIZE.1 - MARGIN;
+        }
+        y -= height;
+        let page = pages.last_mut().expect("there is always a page");
+        let _ = writeln!(page, "BT /{} {} Tf {} {} Td ({}) Tj ET", font, size, MARGIN, y, escape(text));
+    }
+
+    // The objects are numbered: 1 the catalog, 2 the page tree, 3 to 5 the fonts, then a
+    // page and its contents for each page.
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..pages.len()).map(|i| format!("{} 0 R", 6 + 2 * i)).collect::<Vec<_>>().join(" "),
+            pages.len()
+        ),
+    ];
+    for font in ["Helvetica-Bold", "Helvetica", "Courier"] {
+        objects.push(format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>", font));
+    }
+    for (i, content) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R /F3 5 0 R >> >> /Contents {} 0 R >>",
+            PAGE_SIZE.0,
+            PAGE_SIZE.1,
+            7 + 2 * i
+        ));
+        objects.push(format!("<< /Length {} >>\nstream\n{}endstream", content.chars().count(), content));
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        push_bytes(&mut pdf, &format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+    let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(trailer, "{:010} 00000 n ", offset);
+    }
+    let _ = write!(trailer, "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, pdf.len());
+    push_bytes(&mut pdf, &trailer);
+    pdf
+}
+
+/// Appends `text`, whose characters were escaped to single bytes, one byte per character.
+fn push_bytes(pdf: &mut Vec<u8>, text: &str) {
+    pdf.extend(text.chars().map(|c| c as u8));
+}
+
+/// Escapes `text` to be put in a PDF string, as characters that each stand for one byte of
+/// the WinAnsi encoding.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '(' | ')' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            // WinAnsi matches Latin-1 except in the range it fills with other characters.
+            ' '..='~' | '\u{a0}'..='\u{ff}' => escaped.push(c),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_paginates_and_indexes_objects() {
+        let mut lines = vec![(Style::Heading, "Disk usage of C:\\Users (café)".to_string())];
+        lines.extend((0..100).map(|i| (Style::Mono, format!("line {} – done", i))));
+        let pdf = write(&lines);
+        let text: String = pdf.iter().map(|&b| b as char).collect();
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("(Disk usage of C:\\\\Users \\(caf\u{e9}\\))"));
+        assert!(text.contains("(line 7 ? done)"));
+        // 100 lines of 11 points don't fit on one page.
+        assert!(text.contains("/Count 2 >>"));
+
+        // The cross-reference table points at each object, counting in bytes.
+        let start: usize = text.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+        let xref = String::from_utf8_lossy(&pdf[start..]);
+        assert!(xref.starts_with("xref\n0 10\n"));
+        for (i, line) in xref.lines().skip(3).take(9).enumerate() {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", i + 1).as_bytes()));
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! This module keeps a folder pinned to a pane left of the treemap, so it can be compared by
+//! eye with whatever is browsed on the right, such as the photos on the NAS with those on the
+//! laptop. The pane holds its own copy of the folder's part of the scan, so it stays while
+//! other folders, tabs, or scans are opened on the right.
+//!
+//! The panes are kept in step. Hovering an item highlights the item at the same place in the
+//! other pane; while "Follow" is on, going into a folder or up in one pane does the same in the
+//! other, as far as it has a folder of that name; and with "Same scale", the pinned treemap is
+//! shrunk so that equal areas stand for equal sizes in both panes.
+
+use crate::interaction::HitIndex;
+use crate::scanner::{FileSystemNode, SizeMetric};
+use crate::treemap::{self, Rectangle, TreemapNode};
+use eframe::egui;
+use std::sync::Arc;
+
+/// A folder pinned to the left pane.
+pub struct PinnedPane {
+    /// Where the folder was pinned from, for the pane's heading.
+    pub source: String,
+    tree: Arc<FileSystemNode>,
+    /// The folder shown, as a path from the pinned folder.
+    pub root: Vec<String>,
+    /// Whether going into a folder or up in one pane does the same in the other.
+    pub follow: bool,
+    /// Whether the pinned treemap is drawn at the scale of the one on the right.
+    pub same_scale: bool,
+    /// The item under the pointer in this pane, as a path from the folder shown.
+    pub hovered: Option<Vec<String>>,
+    /// The folder shown on the right in the last frame, to follow where it goes from there.
+    right_root: Vec<String>,
+    /// The generation of the tree shown on the right in the last frame. Where a new tree
+    /// starts out isn't a step to follow.
+    right_generation: u64,
+    layout: Option<PaneLayout>,
+}
+
+/// The layout of the folder shown, and what it was made for.
+struct PaneLayout {
+    root: Vec<String>,
+    size: egui::Vec2,
+    metric: SizeMetric,
+    nodes: Vec<TreemapNode>,
+    index: HitIndex,
+}
+
+impl PinnedPane {
+    /// Pins `tree`, found at `source`, while the right pane shows `right_root` in the tree of
+    /// `generation`.
+    pub fn new(source: String, tree: FileSystemNode, right_root: Vec<String>, generation: u64) -> Self {
+        Self {
+            source,
+            tree: Arc::new(tree),
+            root: Vec::new(),
+            follow: true,
+            same_scale: true,
+            hovered: None,
+            right_root,
+            right_generation: generation,
+            layout: None,
+        }
+    }
+
+    /// The folder shown.
+    pub fn current(&self) -> &FileSystemNode {
+        self.tree.find(&self.root).unwrap_or(&self.tree)
+    }
+
+    /// The layout of the folder shown in a treemap of `size`, made again only when something
+    /// it depends on changed.
+    pub fn layout(&mut self, size: egui::Vec2, metric: SizeMetric, max_depth: usize) -> (&[TreemapNode], &HitIndex) {
+        let stale = self.layout.as_ref().is_none_or(|l| l.root != self.root || l.size != size || l.metric != metric);
+        if stale {
+            let bounds = Rectangle { x: 0.0, y: 0.0, width: size.x as f64, height: size.y as f64 };
+            let nodes = treemap::generate_treemap_to_depth(self.current(), bounds, metric, max_depth);
+            let index = HitIndex::new(&nodes);
+            self.layout = Some(PaneLayout { root: self.root.clone(), size, metric, nodes, index });
+        }
+        let layout = self.layout.as_ref().expect("the layout was just made");
+        (&layout.nodes, &layout.index)
+    }
+
+    /// Repeats where the right pane went since the last frame, to show `right_root` in the
+    /// tree of `generation`, if following it.
+    pub fn follow_right(&mut self, right_root: &[String], generation: u64) {
+        if self.follow
+            && generation == self.right_generation
+            && let Some(step) = step(&self.right_root, right_root)
+        {
+            self.take(&step);
+        }
+        self.right_root = right_root.to_vec();
+        self.right_generation = generation;
+    }
+
+    /// Takes `step` in this pane, if the folder it leads to is there.
+    pub fn take(&mut self, step: &Step) -> bool {
+        apply(step, &mut self.root, &self.tree)
+    }
+}
+
+/// A move from one folder to another, to be repeated in the other pane.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// Into the folder at this path below the one shown.
+    Down(Vec<String>),
+    /// Up this many levels.
+    Up(usize),
+}
+
+/// The step from the folder at `from` to the one at `to`, unless it was a jump elsewhere.
+pub fn step(from: &[String], to: &[String]) -> Option<Step> {
+    if let Some(below) = to.strip_prefix(from) {
+        (!below.is_empty()).then(|| Step::Down(below.to_vec()))
+    } else {
+        from.starts_with(to).then(|| Step::Up(from.len() - to.len()))
+    }
+}
+
+/// Takes `step` from the folder at `root` in `tree`, if it leads to a folder. Going up stops at
+/// the top of the tree.
+pub fn apply(step: &Step, root: &mut Vec<String>, tree: &FileSystemNode) -> bool {
+    match step {
+        Step::Up(levels) => {
+            let levels = (*levels).min(root.len());
+            root.truncate(root.len() - levels);
+            levels > 0
+        }
+        Step::Down(names) => {
+            let path = [root.as_slice(), names].concat();
+            let is_folder = tree.find(&path).is_some_and(|n| !n.children.is_empty());
+            if is_folder {
+                *root = path;
+            }
+            is_folder
+        }
+    }
+}
+
+/// The size to draw a treemap of `own` bytes at in a pane of `pane`, for equal areas to stand
+/// for equal sizes as in a treemap of `other` bytes covering `other_area` square points. If that
+/// doesn't fit, the pane is filled, and the scale it is drawn at compared to the other, below
+/// 1, is returned with it.
+pub fn same_scale(pane: egui::Vec2, own: u64, other: u64, other_area: f32) -> (egui::Vec2, f32) {
+    if own == 0 || other == 0 || pane.x <= 0.0 || pane.y <= 0.0 {
+        return (pane, 1.0);
+    }
+    let needed = own as f64 / other as f64 * other_area as f64;
+    let factor = (needed / (pane.x * pane.y) as f64).sqrt() as f32;
+    if factor > 1.0 { (pane, 1.0 / (factor * factor)) } else { (pane * factor, 1.0) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, children: Vec<FileSystemNode>) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size: 1, allocated: 1, children, special: None }
+    }
+
+    #[test]
+    fn test_steps_are_repeated_in_the_other_pane() {
+        let path = |p: &str| p.split('/').filter(|n| !n.is_empty()).map(str::to_string).collect::<Vec<_>>();
+        assert_eq!(step(&path("photos"), &path("photos/2021/june")), Some(Step::Down(path("2021/june"))));
+        assert_eq!(step(&path("photos/2021"), &path("")), Some(Step::Up(2)));
+        assert_eq!(step(&path("photos"), &path("music")), None);
+
+        let tree = node("photos", vec![node("2021", vec![node("june", vec![node("a.jpg", vec![])])])]);
+        let mut root = Vec::new();
+        assert!(apply(&Step::Down(path("2021/june")), &mut root, &tree));
+        assert_eq!(root, path("2021/june"));
+        // Files and missing folders can't be gone into, and going up stops at the top.
+        assert!(!apply(&Step::Down(path("a.jpg")), &mut root, &tree));
+        assert!(!apply(&Step::Down(path("july")), &mut root, &tree));
+        assert!(apply(&Step::Up(5), &mut root, &tree));
+        assert!(root.is_empty());
+
+        // A quarter of the size on the right takes a quarter of its area.
+        let (size, scale) = same_scale(egui::vec2(400.0, 400.0), 100, 400, 400.0 * 400.0);
+        assert_eq!((size, scale), (egui::vec2(200.0, 200.0), 1.0));
+        let (size, scale) = same_scale(egui::vec2(400.0, 400.0), 1600, 400, 400.0 * 400.0);
+        assert_eq!((size, scale), (egui::vec2(400.0, 400.0), 0.25));
+    }
+}
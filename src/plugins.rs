@@ -0,0 +1,142 @@
+//! This module loads third-party analyzers from dynamic libraries (`.so`, `.dylib`, or
+//! `.dll`), listed in the settings. Rust has no stable ABI, so a library talks to the app
+//! through C functions exchanging JSON strings:
+//!
+//! - `uint32_t disk_scout_abi_version(void)` returns [`ABI_VERSION`].
+//! - `char *disk_scout_analyzer_info(void)` returns `{"id", "title", "description"}`.
+//! - `char *disk_scout_analyze(const char *input)` gets `{"root", "tree", "metric", "now"}`,
+//!   the tree being in the snapshot format, and returns `{"findings": [...]}` or
+//!   `{"error": "..."}`, each finding having a `title`, a `size`, `paths` from the scan root
+//!   as lists of names, and an `action` of `review`, `trash`, or `archive`.
+//! - `void disk_scout_free(char *string)` frees a string the library returned.
+//!
+//! Loading a library runs its code with the rights of the app, so only libraries the user
+//! listed are loaded.
+
+use crate::analyzers::{Analyzer, Finding, Input};
+use libloading::{Library, Symbol};
+use serde::Deserialize;
+use serde_json::json;
+use std::ffi::{CStr, CString, c_char};
+use std::path::Path;
+
+/// The version of the interface libraries have to implement.
+pub const ABI_VERSION: u32 = 1;
+
+type VersionFn = unsafe extern "C" fn() -> u32;
+type InfoFn = unsafe extern "C" fn() -> *mut c_char;
+type AnalyzeFn = unsafe extern "C" fn(*const c_char) -> *mut c_char;
+type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+/// What a library says about its analyzer.
+#[derive(Debug, Deserialize)]
+struct Info {
+    id: String,
+    title: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// What a library returns from an analysis.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Output {
+    Findings { findings: Vec<Finding> },
+    Error { error: String },
+}
+
+/// An analyzer in a dynamic library, which stays loaded as long as this does.
+pub struct Plugin {
+    info: Info,
+    library: Library,
+}
+
+impl Plugin {
+    /// Loads the analyzer in the library at `path`.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        // SAFETY: loading runs the library's initializers, which the user chose to trust by
+        // listing it in the settings.
+        let library = unsafe { Library::new(path) }.map_err(|e| e.to_string())?;
+        // SAFETY: the symbols are looked up with the signatures the interface defines.
+        let version = unsafe { library.get::<VersionFn>(b"disk_scout_abi_version") }.map_err(|e| e.to_string())?;
+        // SAFETY: as above.
+        let version = unsafe { version() };
+        if version != ABI_VERSION {
+            return Err(format!("it implements version {} of the interface rather than {}", version, ABI_VERSION));
+        }
+        let mut plugin = Self { info: Info { id: String::new(), title: String::new(), description: String::new() }, library };
+        // SAFETY: as above.
+        let info = plugin.call(|| unsafe { plugin.library.get::<InfoFn>(b"disk_scout_analyzer_info").map(|f| f()) })?;
+        plugin.info = serde_json::from_str(&info).map_err(|e| format!("its description is invalid: {}", e))?;
+        Ok(plugin)
+    }
+
+    /// Runs `f`, which calls the library and returns a string it allocated, and takes the
+    /// string over, freeing the library's copy.
+    fn call(&self, f: impl FnOnce() -> Result<*mut c_char, libloading::Error>) -> Result<String, String> {
+        // SAFETY: the free function has the signature the interface defines.
+        let free: Symbol<FreeFn> = unsafe { self.library.get(b"disk_scout_free") }.map_err(|e| e.to_string())?;
+        let pointer = f().map_err(|e| e.to_string())?;
+        if pointer.is_null() {
+            return Err("it returned nothing".to_string());
+        }
+        // SAFETY: the library returns a NUL-terminated string, which stays valid until freed.
+        let string = unsafe { CStr::from_ptr(pointer) }.to_string_lossy().into_owned();
+        // SAFETY: the string came from the library and is freed only once.
+        unsafe { free(pointer) };
+        Ok(string)
+    }
+}
+
+impl Analyzer for Plugin {
+    fn id(&self) -> &str {
+        &self.info.id
+    }
+
+    fn title(&self) -> &str {
+        &self.info.title
+    }
+
+    fn description(&self) -> &str {
+        &self.info.description
+    }
+
+    fn analyze(&self, input: &Input) -> Result<Vec<Finding>, String> {
+        let request = json!({ "root": input.root, "tree": input.tree, "metric": input.metric, "now": input.now });
+        let request = CString::new(request.to_string()).map_err(|e| e.to_string())?;
+        // SAFETY: the analyze function has the signature the interface defines, and the
+        // request outlives the call.
+        let output = self.call(|| unsafe { self.library.get::<AnalyzeFn>(b"disk_scout_analyze").map(|f| f(request.as_ptr())) })?;
+        parse_output(&output)
+    }
+}
+
+/// Parses what a library returned from an analysis.
+fn parse_output(output: &str) -> Result<Vec<Finding>, String> {
+    match serde_json::from_str(output) {
+        Ok(Output::Findings { findings }) => Ok(findings),
+        Ok(Output::Error { error }) => Err(error),
+        Err(e) => Err(format!("its findings are invalid: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzers::Action;
+
+    #[test]
+    fn test_parse_output() {
+        let output = r#"{"findings": [
+            {"title": "Old ISO images", "size": 4700000000, "paths": [["Downloads", "debian.iso"]], "action": "trash"},
+            {"title": "Thumbnails", "size": 1200}
+        ]}"#;
+        let findings = parse_output(output).unwrap();
+        assert_eq!(findings.len(), 2);
+        assert_eq!((findings[0].action, findings[0].paths.len()), (Action::Trash, 1));
+        assert_eq!((findings[1].action, findings[1].paths.len()), (Action::Review, 0));
+        assert_eq!(parse_output(r#"{"error": "no cache folders"}"#), Err("no cache folders".to_string()));
+        assert!(parse_output("[]").is_err());
+        assert!(Plugin::load(Path::new("/nonexistent/libanalyzer.so")).is_err());
+    }
+}
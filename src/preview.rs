@@ -0,0 +1,198 @@
+//! This module previews the start of text files in the selection panel, so users can tell
+//! what a mystery config or log file is before deleting it. The encoding is taken from a
+//! byte order mark if there is one, and guessed from the text otherwise. Files are read on a
+//! background thread, since a slow or sleeping disk would otherwise freeze the panel.
+
+use chardetng::EncodingDetector;
+use eframe::egui;
+use encoding_rs::Encoding;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// How much of a file is previewed.
+const PREVIEW_SIZE: u64 = 16 << 10;
+
+/// The start of a text file, decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextPreview {
+    /// The previewed text.
+    pub text: String,
+    /// The name of the encoding the text was decoded from.
+    pub encoding: &'static str,
+    /// Whether the file is longer than the preview.
+    pub truncated: bool,
+}
+
+/// The preview of the selected file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Preview {
+    /// The file is being read.
+    Loading,
+    /// The start of the file, or `None` if it isn't text.
+    Ready(Option<TextPreview>),
+    /// The file couldn't be read.
+    Failed(String),
+}
+
+/// The preview of the last previewed file.
+#[derive(Default)]
+pub struct PreviewLoader {
+    /// The path of the previewed file, and its preview.
+    current: Option<(PathBuf, Preview)>,
+    /// Where the preview being read arrives.
+    receiver: Option<Receiver<Result<Option<TextPreview>, String>>>,
+}
+
+impl PreviewLoader {
+    /// Returns the preview of the file at `path`, starting to read it if another file was
+    /// previewed last. The result for a file previewed before is dropped with its receiver.
+    pub fn get(&mut self, ctx: &egui::Context, path: &Path) -> Preview {
+        if self.current.as_ref().is_none_or(|(previewed, _)| previewed != path) {
+            let (sender, receiver) = mpsc::channel();
+            let (ctx, read_path) = (ctx.clone(), path.to_path_buf());
+            thread::spawn(move || {
+                // Empty folders have no children either.
+                let preview = if read_path.is_file() { read(&read_path) } else { Ok(None) };
+                let _ = sender.send(preview);
+                ctx.request_repaint();
+            });
+            self.current = Some((path.to_path_buf(), Preview::Loading));
+            self.receiver = Some(receiver);
+        } else if let Some(preview) = self.receiver.as_ref().and_then(|r| r.try_recv().ok()) {
+            self.receiver = None;
+            self.current = Some((path.to_path_buf(), match preview {
+                Ok(preview) => Preview::Ready(preview),
+                Err(e) => Preview::Failed(e),
+            }));
+        }
+        self.current.as_ref().map_or(Preview::Loading, |(_, preview)| preview.clone())
+    }
+
+    /// Forgets the last preview, e.g. because a new tree was loaded.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Reads the start of the file at `path`. Returns `None` if it doesn't look like text.
+pub fn read(path: &Path) -> Result<Option<TextPreview>, String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let mut bytes = Vec::new();
+    // One byte more than is shown tells whether the file goes on.
+    file.take(PREVIEW_SIZE + 1).read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+    let truncated = bytes.len() as u64 > PREVIEW_SIZE;
+    bytes.truncate(PREVIEW_SIZE as usize);
+    Ok(decode(&bytes, truncated).map(|(text, encoding)| TextPreview { text, encoding: encoding.name(), truncated }))
+}
+
+/// Decodes `bytes` as text, or returns `None` if they look binary. `truncated` tells whether
+/// the bytes were cut off, which may have split the last character.
+fn decode(bytes: &[u8], truncated: bool) -> Option<(String, &'static Encoding)> {
+    let (encoding, bom_length) = match Encoding::for_bom(bytes) {
+        Some(found) => found,
+        None => {
+            // Text without a byte order mark never holds NUL bytes, which binary files are full of.
+            if bytes.contains(&0) {
+                return None;
+            }
+            let mut detector = EncodingDetector::new();
+            detector.feed(bytes, !truncated);
+            (detector.guess(None, true), 0)
+        }
+    };
+    let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+    // Text full of control characters is binary data that happened to lack NUL bytes.
+    let control = text.chars().filter(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t' | '\x0c')).count();
+    (control * 10 <= text.chars().count()).then(|| (text.into_owned(), encoding))
+}
+
+/// Draws `preview`, with the text in a scrollable, read-only box.
+pub fn show(ui: &mut egui::Ui, preview: &Preview) {
+    match preview {
+        Preview::Loading => {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.weak("Loading preview...");
+            });
+        }
+        Preview::Ready(Some(text)) => show_text(ui, text),
+        Preview::Ready(None) => {}
+        Preview::Failed(e) => {
+            ui.weak(format!("No preview: {}", e));
+        }
+    }
+}
+
+/// Draws the text of `preview` in a scrollable, read-only box.
+fn show_text(ui: &mut egui::Ui, preview: &TextPreview) {
+    egui::CollapsingHeader::new("Preview").default_open(true).show(ui, |ui| {
+        let note = if preview.truncated { format!("First {} KB, ", PREVIEW_SIZE >> 10) } else { String::new() };
+        ui.weak(format!("{}{}", note, preview.encoding));
+        egui::ScrollArea::both().max_height(240.0).show(ui, |ui| {
+            ui.add(egui::Label::new(egui::RichText::new(&preview.text).monospace()).extend());
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_is_decoded_and_binary_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("settings");
+        std::fs::write(&config, "[server]\nport = 8080\n").unwrap();
+        let preview = read(&config).unwrap().unwrap();
+        assert_eq!(preview.text, "[server]\nport = 8080\n");
+        assert!(!preview.truncated);
+
+        // UTF-16 is recognized by its byte order mark.
+        let mut utf16 = vec![0xff, 0xfe];
+        utf16.extend("héllo".encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(decode(&utf16, false), Some(("héllo".to_string(), encoding_rs::UTF_16LE)));
+        // Latin-1 text is guessed to be Windows-1252.
+        let (text, _) = decode(b"Caf\xe9 cr\xe8me br\xfbl\xe9e, d\xe9j\xe0 vu", false).unwrap();
+        assert_eq!(text, "Café crème brûlée, déjà vu");
+
+        let log = dir.path().join("big.log");
+        std::fs::write(&log, "line\n".repeat(10_000)).unwrap();
+        let preview = read(&log).unwrap().unwrap();
+        assert!(preview.truncated);
+        assert_eq!(preview.text.len() as u64, PREVIEW_SIZE);
+
+        assert_eq!(decode(&[0x7f, 0x45, 0x4c, 0x46, 2, 1, 1, 0], false), None);
+        assert_eq!(decode(&[1, 2, 3, 4, 5, 6, 7, 8, b'a'], false), None);
+        assert!(read(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_previews_are_read_in_the_background() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("notes.txt");
+        std::fs::write(&notes, "remember the milk\n").unwrap();
+        let ctx = egui::Context::default();
+        let mut loader = PreviewLoader::default();
+        assert_eq!(loader.get(&ctx, &notes), Preview::Loading);
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        let preview = loop {
+            match loader.get(&ctx, &notes) {
+                Preview::Loading => {
+                    assert!(std::time::Instant::now() < deadline, "the preview wasn't read");
+                    thread::sleep(std::time::Duration::from_millis(5));
+                }
+                preview => break preview,
+            }
+        };
+        assert!(matches!(preview, Preview::Ready(Some(text)) if text.text == "remember the milk\n"));
+        // Selecting a folder previews nothing.
+        while loader.get(&ctx, dir.path()) == Preview::Loading {
+            assert!(std::time::Instant::now() < deadline, "the folder wasn't checked");
+            thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert_eq!(loader.get(&ctx, dir.path()), Preview::Ready(None));
+    }
+}
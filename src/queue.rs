@@ -0,0 +1,246 @@
+//! This module runs queued scans in the background, next to the scan shown in the
+//! treemap. Jobs start in the order they were queued, with at most `max_concurrent` of
+//! them running at a time, and each finished scan becomes a tab of its own.
+
+use crate::error::ScanError;
+use crate::recovery::ViewState;
+use crate::scanner::{self, FileSystemNode, ScanOptions};
+use crate::snapshot::SnapshotHeader;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// The most scans that can be set to run at once.
+pub const MAX_CONCURRENCY: usize = 4;
+
+/// Where a queued scan is at.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobState {
+    /// Waiting for a running job to finish.
+    Queued,
+    /// Being scanned.
+    Running,
+    /// Finished, and shown in a tab.
+    Done,
+    /// Stopped by an error.
+    Failed(String),
+    /// Stopped by the user.
+    Cancelled,
+}
+
+/// A scan in the queue.
+#[derive(Debug)]
+pub struct ScanJob {
+    /// Identifies the job within its queue.
+    pub id: u64,
+    /// The folder to scan.
+    pub path: PathBuf,
+    /// The options to scan it with.
+    pub options: ScanOptions,
+    /// Where the job is at.
+    pub state: JobState,
+    /// The bytes found so far, updated whenever the scanner reports progress.
+    found: Arc<AtomicU64>,
+    /// Set to stop the job.
+    cancel: Arc<AtomicBool>,
+}
+
+impl ScanJob {
+    /// The bytes found so far.
+    pub fn found(&self) -> u64 {
+        self.found.load(Ordering::Relaxed)
+    }
+}
+
+/// A finished scan, waiting to be shown in a tab.
+#[derive(Debug)]
+pub struct FinishedScan {
+    /// The id of the job that scanned it.
+    pub id: u64,
+    /// The folder that was scanned.
+    pub path: PathBuf,
+    /// The scanned tree.
+    pub tree: FileSystemNode,
+    /// Where and when it was scanned, with the statistics of the scan.
+    pub header: SnapshotHeader,
+}
+
+/// The result of a scan, shown in its own tab.
+#[derive(Debug, Clone)]
+pub struct ResultTab {
+    /// The folder that was scanned.
+    pub root: PathBuf,
+    /// The scanned tree, shared with the model while the tab is shown.
+    pub tree: Arc<FileSystemNode>,
+    /// How the tree was being viewed when the user last switched away from the tab.
+    pub view: ViewState,
+}
+
+impl ResultTab {
+    /// The text of the tab: the name of the scanned folder.
+    pub fn title(&self) -> String {
+        self.root
+            .file_name()
+            .map_or_else(|| self.root.to_string_lossy(), |name| name.to_string_lossy())
+            .into_owned()
+    }
+}
+
+/// What a job came to: the scanned tree and where and when it was scanned, or why it wasn't.
+type Outcome = Result<(FileSystemNode, SnapshotHeader), ScanError>;
+
+/// The queue of scans, with the jobs that finished or failed kept for display.
+pub struct ScanQueue {
+    jobs: Vec<ScanJob>,
+    /// How many jobs may run at the same time.
+    pub max_concurrent: usize,
+    next_id: u64,
+    sender: Sender<(u64, Outcome)>,
+    receiver: Receiver<(u64, Outcome)>,
+}
+
+impl Default for ScanQueue {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self { jobs: Vec::new(), max_concurrent: 1, next_id: 0, sender, receiver }
+    }
+}
+
+impl ScanQueue {
+    /// The jobs, in the order they were queued.
+    pub fn jobs(&self) -> &[ScanJob] {
+        &self.jobs
+    }
+
+    /// The number of jobs that are queued or running.
+    pub fn pending(&self) -> usize {
+        self.jobs.iter().filter(|j| matches!(j.state, JobState::Queued | JobState::Running)).count()
+    }
+
+    /// The job with the given id, unless it was cleared.
+    pub fn job(&self, id: u64) -> Option<&ScanJob> {
+        self.jobs.iter().find(|j| j.id == id)
+    }
+
+    /// Queues a scan of `path` with `options`, and returns the id of its job.
+    pub fn push(&mut self, path: PathBuf, options: ScanOptions) -> u64 {
+        self.next_id += 1;
+        self.jobs.push(ScanJob {
+            id: self.next_id,
+            path,
+            options,
+            state: JobState::Queued,
+            found: Arc::default(),
+            cancel: Arc::default(),
+        });
+        self.next_id
+    }
+
+    /// Stops the job with the given id, whether it is running or still waiting.
+    pub fn cancel(&mut self, id: u64) {
+        if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+            job.cancel.store(true, Ordering::Relaxed);
+            if job.state == JobState::Queued {
+                job.state = JobState::Cancelled;
+            }
+        }
+    }
+
+    /// Removes the jobs that are no longer queued or running from the list.
+    pub fn clear_finished(&mut self) {
+        self.jobs.retain(|j| matches!(j.state, JobState::Queued | JobState::Running));
+    }
+
+    /// Collects the scans that finished since the last call, and starts queued jobs while
+    /// fewer than `max_concurrent` are running.
+    pub fn poll(&mut self) -> Vec<FinishedScan> {
+        let mut finished = Vec::new();
+        while let Ok((id, result)) = self.receiver.try_recv() {
+            let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) else {
+                continue;
+            };
+            job.state = match result {
+                Ok((tree, header)) => {
+                    job.found.store(tree.size, Ordering::Relaxed);
+                    finished.push(FinishedScan { id, path: job.path.clone(), tree, header });
+                    JobState::Done
+                }
+                Err(ScanError::Cancelled) => JobState::Cancelled,
+                Err(e) => JobState::Failed(e.to_string()),
+            };
+        }
+
+        let running = self.jobs.iter().filter(|j| j.state == JobState::Running).count();
+        let free = self.max_concurrent.clamp(1, MAX_CONCURRENCY).saturating_sub(running);
+        for job in self.jobs.iter_mut().filter(|j| j.state == JobState::Queued).take(free) {
+            job.state = JobState::Running;
+            let (id, path, options) = (job.id, job.path.clone(), job.options.clone());
+            let (found, cancel, sender) = (job.found.clone(), job.cancel.clone(), self.sender.clone());
+            thread::spawn(move || {
+                let progress = |entries: FileSystemNode| {
+                    found.fetch_add(entries.size, Ordering::Relaxed);
+                };
+                let mut header = SnapshotHeader::scan_starting(options.clone());
+                let result = scanner::build_tree_with_progress(&path, &options, &progress, &cancel, None).map(|(tree, stats)| {
+                    header.finish_scan();
+                    header.scan_stats = Some(stats);
+                    (tree, header)
+                });
+                // The receiver is gone if the app is closing, in which case nobody cares.
+                let _ = sender.send((id, result));
+            });
+        }
+        finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_jobs_run_in_order_within_the_concurrency_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a", "b"] {
+            fs::create_dir(dir.path().join(name)).unwrap();
+            fs::write(dir.path().join(name).join("file"), name.repeat(10)).unwrap();
+        }
+        let mut queue = ScanQueue::default();
+        queue.push(dir.path().join("a"), ScanOptions::default());
+        queue.push(dir.path().join("missing"), ScanOptions::default());
+        queue.push(dir.path().join("b"), ScanOptions::default());
+        queue.push(dir.path().join("a"), ScanOptions::default());
+        queue.cancel(4);
+
+        let mut finished = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while queue.pending() > 0 {
+            assert!(Instant::now() < deadline, "the queue didn't finish");
+            finished.extend(queue.poll());
+            let running = queue.jobs().iter().filter(|j| j.state == JobState::Running).count();
+            assert!(running <= 1);
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let names: Vec<_> = finished.iter().map(|f| f.path.file_name().unwrap().to_owned()).collect();
+        assert_eq!(names, ["a", "b"]);
+        assert_eq!(finished[0].tree.size, 10);
+        // The scans are recorded with when they ran and what they found.
+        let header = &finished[0].header;
+        assert!(header.scan_started > 0 && header.scan_finished >= header.scan_started);
+        assert_eq!(header.scan_stats.as_ref().map(|s| s.files), Some(1));
+        let states: Vec<_> = queue.jobs().iter().map(|j| j.state.clone()).collect();
+        assert_eq!(states[0], JobState::Done);
+        assert!(matches!(states[1], JobState::Failed(_)));
+        assert_eq!(states[2], JobState::Done);
+        assert_eq!(states[3], JobState::Cancelled);
+        assert_eq!(queue.jobs()[2].found(), 10);
+
+        queue.clear_finished();
+        assert!(queue.jobs().is_empty());
+    }
+}
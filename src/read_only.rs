@@ -0,0 +1,92 @@
+//! This module holds the read-only switch, for auditing production servers or demonstrating
+//! the app without risk. While it is on, every action that changes the disk or the system
+//! (moving to the trash, archiving, running hooks, installing the file manager entry)
+//! refuses to run. The check lives in the actions themselves, so a button that was missed
+//! in the UI still can't do any harm.
+//!
+//! `--read-only` on the command line turns the switch on for the whole session, and it
+//! can't be turned off from the UI then.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The switch of the running app.
+static SWITCH: Switch = Switch::new();
+
+/// Whether read-only mode is on, and whether it is locked on.
+struct Switch {
+    enabled: AtomicBool,
+    locked: AtomicBool,
+}
+
+impl Switch {
+    const fn new() -> Self {
+        Self { enabled: AtomicBool::new(false), locked: AtomicBool::new(false) }
+    }
+
+    fn set(&self, enabled: bool) {
+        if !self.locked.load(Ordering::Relaxed) {
+            self.enabled.store(enabled, Ordering::Relaxed);
+        }
+    }
+
+    fn lock(&self) {
+        self.locked.store(true, Ordering::Relaxed);
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    fn check(&self, action: &str) -> io::Result<()> {
+        if self.enabled.load(Ordering::Relaxed) {
+            let message = format!("{} is disabled in read-only mode", action);
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, message));
+        }
+        Ok(())
+    }
+}
+
+/// Whether read-only mode is on.
+pub fn is_enabled() -> bool {
+    SWITCH.enabled.load(Ordering::Relaxed)
+}
+
+/// Whether read-only mode was turned on from the command line and can't be turned off.
+pub fn is_locked() -> bool {
+    SWITCH.locked.load(Ordering::Relaxed)
+}
+
+/// Turns read-only mode on or off, unless it is locked on.
+pub fn set(enabled: bool) {
+    SWITCH.set(enabled);
+}
+
+/// Turns read-only mode on for the rest of the session.
+pub fn lock() {
+    SWITCH.lock();
+}
+
+/// Fails if read-only mode is on. `action` names what was refused, e.g. "Moving to the trash".
+pub fn check(action: &str) -> io::Result<()> {
+    SWITCH.check(action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_only_mode_refuses_actions_and_stays_locked() {
+        // A switch of its own, since the app's is shared by the tests running at the same time.
+        let switch = Switch::new();
+        assert!(switch.check("Archiving").is_ok());
+        switch.set(true);
+        let error = switch.check("Moving to the trash").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(error.to_string(), "Moving to the trash is disabled in read-only mode");
+        switch.set(false);
+        assert!(switch.check("Archiving").is_ok());
+
+        switch.lock();
+        switch.set(false);
+        assert!(switch.check("Archiving").is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//! This module autosaves the current scan and where the user is in it, so a session can be
+//! restored if the app crashes or is killed. The recovery file is removed on a clean exit,
+//! which means that finding one at launch means the last session ended abnormally.
+
+use crate::exclusion::ExcludedItem;
+use crate::scanner::SizeMetric;
+use crate::snapshot::Snapshot;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How often the session is autosaved, if it changed.
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How the scan was being viewed. Paths are canonical, i.e. from the scan root without
+/// collapsed chains, so they apply whatever the view settings are.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ViewState {
+    /// The path of the directory that was shown.
+    pub root_path: Vec<String>,
+    /// The path of the selected node, if any.
+    pub selected_path: Option<Vec<String>>,
+    /// The nodes hidden from the view.
+    pub excluded: Vec<ExcludedItem>,
+    /// Whether chains of single-child directories were collapsed.
+    pub collapse_chains: bool,
+    /// Which size the views were weighted by.
+    pub size_metric: SizeMetric,
+    /// How many levels of folders the treemap showed, if that was limited.
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+}
+
+/// An autosaved session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recovery {
+    /// When the session was saved, in seconds since the Unix epoch.
+    pub saved_at: u64,
+    /// The scan.
+    pub snapshot: Snapshot,
+    /// How the scan was being viewed.
+    pub view: ViewState,
+}
+
+impl Recovery {
+    /// Creates a recovery of `snapshot` and `view`, saved now.
+    pub fn new(snapshot: Snapshot, view: ViewState) -> Self {
+        let saved_at = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        Self { saved_at, snapshot, view }
+    }
+}
+
+/// The recovery file in the app's data folder, if the platform has one.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn default_path() -> Option<std::path::PathBuf> {
+    eframe::storage_dir("Disk Scout").map(|dir| dir.join("recovery.json"))
+}
+
+/// Writes `recovery` to `path`. The file is replaced in one step, so a crash while saving
+/// leaves the previous recovery intact.
+pub fn save(path: &Path, recovery: &Recovery) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let partial = path.with_extension("partial");
+    let mut writer = BufWriter::new(File::create(&partial)?);
+    serde_json::to_writer(&mut writer, recovery).map_err(io::Error::other)?;
+    writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    fs::rename(&partial, path)
+}
+
+/// Reads the recovery file at `path`. Returns `None` if there is none.
+pub fn load(path: &Path) -> io::Result<Option<Recovery>> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    serde_json::from_slice(&bytes)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Removes the recovery file at `path`, if there is one.
+pub fn discard(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::FileSystemNode;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_recovery_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data").join("recovery.json");
+        assert_eq!(load(&path).unwrap(), None);
+
+        let tree = FileSystemNode { name: "home".to_string(), size: 3, allocated: 4096, children: vec![], special: None };
+        let view = ViewState {
+            root_path: vec!["docs".to_string()],
+            selected_path: Some(vec!["docs".to_string(), "a.txt".to_string()]),
+            excluded: vec![ExcludedItem { path: vec!["cache".to_string()], size: 10 }],
+            collapse_chains: true,
+            size_metric: SizeMetric::Allocated,
+            max_depth: Some(2),
+        };
+        let recovery = Recovery::new(Snapshot::new(PathBuf::from("/home"), tree), view);
+        save(&path, &recovery).unwrap();
+        assert_eq!(load(&path).unwrap(), Some(recovery.clone()));
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        assert!(now - recovery.saved_at < 60);
+
+        discard(&path).unwrap();
+        assert_eq!(load(&path).unwrap(), None);
+        // Discarding twice is fine.
+        discard(&path).unwrap();
+    }
+}
@@ -0,0 +1,208 @@
+//! This module turns a treemap layout into the shapes that draw it, once per frame. Each
+//! visible rectangle becomes one shape, filled and outlined as the treemap style says and
+//! colored by the color strategy, with a label and an outline for the selection and the
+//! like on top. Producing shapes rather than painting keeps the drawing testable.
+
+use crate::accessibility;
+use crate::animation::{self, Transition};
+use crate::coloring::{ColorInput, ColorStrategy};
+use crate::git::GitClass;
+use crate::lod::{self, Detail, Lod};
+use crate::notes::Tag;
+use crate::treemap::{Rectangle, TreemapNode};
+use crate::treemap_style::TreemapStyle;
+use eframe::egui;
+
+/// Tells how git sees a node of the layout and how it is tagged.
+pub type Classifier<'a> = dyn Fn(&TreemapNode) -> (Option<GitClass>, Option<Tag>) + 'a;
+
+/// Everything that decides how the treemap looks this frame.
+pub struct Scene<'a> {
+    /// The rectangles to draw, each folder followed by its contents.
+    pub layout: &'a [TreemapNode],
+    /// The animation from the previous layout and how far along it is, if one is running.
+    pub transition: Option<(&'a Transition, f64)>,
+    /// Maps a rectangle of the layout to where it is on screen.
+    pub to_screen: &'a dyn Fn(egui::Rect) -> egui::Rect,
+    /// The part of the screen the treemap is shown in. Nothing outside it is drawn.
+    pub area: egui::Rect,
+    /// Picks the color of each rectangle.
+    pub colors: &'a dyn ColorStrategy,
+    /// How git sees a node and how it is tagged, for the color strategy.
+    pub classify: &'a Classifier<'a>,
+    /// The corners, outlines, and gaps of the rectangles.
+    pub style: &'a TreemapStyle,
+    /// Which rectangles get the full detail.
+    pub lod: &'a Lod,
+    /// Whether rectangles are told apart by patterns instead of colors.
+    pub high_contrast: bool,
+    /// The color of the window behind the treemap.
+    pub background: egui::Color32,
+    /// The index of the selected node.
+    pub selected: Option<usize>,
+    /// The index of the node under the pointer.
+    pub hovered: Option<usize>,
+    /// The indices of the ticked nodes.
+    pub ticked: &'a [usize],
+    /// The indices of the folders over budget.
+    pub over_budget: &'a [usize],
+}
+
+/// Returns the shapes drawing `scene`, back to front. `fonts` lays out the labels.
+pub fn shapes(scene: &Scene, fonts: &egui::text::Fonts) -> Vec<egui::Shape> {
+    let layout = scene.layout;
+    let mut shapes = Vec::new();
+    let mut i = 0;
+    while i < layout.len() {
+        let node = &layout[i];
+        let start = scene.transition.and_then(|(t, progress)| Some((t.start(i)?, progress)));
+        let node_rect = match start {
+            Some((start, progress)) => animation::lerp_rect(start.rect, node.rect, progress),
+            None => node.rect,
+        };
+        let rect = (scene.to_screen)(to_egui(node_rect));
+
+        // Don't draw rectangles that are too small to see or out of view, nor what they hold.
+        if rect.width() < 1.0 || rect.height() < 1.0 || !scene.area.intersects(rect) {
+            i = lod::skip_contents(layout, i);
+            continue;
+        }
+        let has_children = lod::has_children(layout, i);
+        let detail = scene.lod.detail(rect, has_children);
+
+        let (git, tag) = (scene.classify)(node);
+        if scene.high_contrast {
+            // The pattern stands for what the color would: the tag, the git class, or the depth.
+            let pattern = tag.map(|t| t as usize).or(git.map(|g| g as usize)).unwrap_or(node.depth);
+            shapes.extend(accessibility::high_contrast_shapes(rect, pattern));
+        } else {
+            let color = match start {
+                Some((start, progress)) => lerp_color(
+                    scene.colors.color_of(&ColorInput { git, tag, ..start.into() }),
+                    scene.colors.color_of(&ColorInput { git, tag, ..node.into() }),
+                    progress as f32,
+                ),
+                None => scene.colors.color_of(&ColorInput { git, tag, ..node.into() }),
+            };
+            if detail == Detail::Full {
+                let style = scene.style;
+                let inset = style.inset(rect);
+                let stroke = style.stroke(color, scene.background);
+                shapes.push(egui::epaint::RectShape::new(inset, style.corner_radius, color, stroke).into());
+                // Folders are covered by what they hold, so only the innermost rectangles are named.
+                if !has_children
+                    && let Some(name) = node.path.last()
+                    && let Some(label) = lod::label(fonts, inset, name, color)
+                {
+                    shapes.push(label);
+                }
+            } else {
+                // Too small for an outline or rounded corners to show.
+                shapes.push(egui::Shape::rect_filled(rect, 0.0, color));
+            }
+        }
+        shapes.extend(highlight(scene, i, rect));
+        i = if detail == Detail::Merged { lod::skip_contents(layout, i) } else { i + 1 };
+    }
+    shapes
+}
+
+/// The outline of the node at `i`, if it is highlighted: the selected node, and more
+/// faintly the one under the pointer. Ticked nodes are outlined in light blue, and folders
+/// over budget in red.
+fn highlight(scene: &Scene, i: usize, rect: egui::Rect) -> Vec<egui::Shape> {
+    let radius = scene.style.corner_radius;
+    let stroke = if scene.selected == Some(i) {
+        egui::Stroke::new(3.0, egui::Color32::YELLOW)
+    } else if scene.hovered == Some(i) {
+        egui::Stroke::new(1.5, egui::Color32::WHITE)
+    } else if scene.ticked.contains(&i) {
+        egui::Stroke::new(2.0, egui::Color32::LIGHT_BLUE)
+    } else if scene.over_budget.contains(&i) {
+        let stroke = egui::Stroke::new(2.0, egui::Color32::LIGHT_RED);
+        if scene.high_contrast {
+            // Dashed, so it can be told from the ticked outline without its color.
+            let corners = [rect.left_top(), rect.right_top(), rect.right_bottom(), rect.left_bottom(), rect.left_top()];
+            return egui::Shape::dashed_line(&corners, stroke, 6.0, 4.0);
+        }
+        stroke
+    } else {
+        return Vec::new();
+    };
+    vec![egui::Shape::rect_stroke(rect, radius, stroke)]
+}
+
+/// Converts a rectangle of the layout to egui's.
+pub fn to_egui(rect: Rectangle) -> egui::Rect {
+    egui::Rect::from_min_max(
+        egui::pos2(rect.x as f32, rect.y as f32),
+        egui::pos2((rect.x + rect.width) as f32, (rect.y + rect.height) as f32),
+    )
+}
+
+/// Linearly interpolates between two colors, where `t` is 0.0 at `from` and 1.0 at `to`.
+fn lerp_color(from: egui::Color32, to: egui::Color32, t: f32) -> egui::Color32 {
+    let lerp = |a: u8, b: u8| (a as f32 * (1.0 - t) + b as f32 * t) as u8;
+    egui::Color32::from_rgb(lerp(from.r(), to.r()), lerp(from.g(), to.g()), lerp(from.b(), to.b()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::DepthPalette;
+    use crate::scanner::{FileSystemNode, SizeMetric};
+    use crate::treemap;
+
+    fn node(name: &str, size: u64, children: Vec<FileSystemNode>) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    #[test]
+    fn test_each_visible_rectangle_is_one_shape() {
+        // "dir" takes the left half with its two files, "file" the right half.
+        let tree = node(
+            "root",
+            20,
+            vec![node("dir", 10, vec![node("a", 5, vec![]), node("b", 5, vec![])]), node("file", 10, vec![])],
+        );
+        let bounds = Rectangle { x: 0.0, y: 0.0, width: 200.0, height: 100.0 };
+        let layout = treemap::generate_treemap(&tree, bounds, SizeMetric::Apparent);
+        let palette = DepthPalette::default();
+        let style = TreemapStyle::default();
+        let lod = Lod::default();
+        let identity = |rect: egui::Rect| rect;
+        let scene = Scene {
+            layout: &layout,
+            transition: None,
+            to_screen: &identity,
+            area: egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(200.0, 100.0)),
+            colors: &palette,
+            classify: &|_| (None, None),
+            style: &style,
+            lod: &lod,
+            high_contrast: false,
+            background: egui::Color32::BLACK,
+            selected: None,
+            hovered: None,
+            ticked: &[],
+            over_budget: &[],
+        };
+        let count = |scene: &Scene| {
+            let ctx = egui::Context::default();
+            let mut shapes = Vec::new();
+            let _ = ctx.run(Default::default(), |ctx| shapes = ctx.fonts(|fonts| super::shapes(scene, fonts)));
+            let rects = shapes.iter().filter(|s| matches!(s, egui::Shape::Rect(_))).count();
+            let labels = shapes.iter().filter(|s| matches!(s, egui::Shape::Text(_))).count();
+            (rects, labels)
+        };
+
+        // Four rectangles, three of them files with a label.
+        assert_eq!(count(&scene), (4, 3));
+        // The selected file gets an outline on top.
+        let selected = layout.iter().position(|n| n.path == ["dir", "b"]);
+        assert_eq!(count(&Scene { selected, ..scene }), (5, 3));
+        // Zoomed in on the right half, only "file" is in view.
+        let zoom = |rect: egui::Rect| rect.translate(egui::vec2(-100.5, 0.0));
+        assert_eq!(count(&Scene { to_screen: &zoom, selected: None, ..scene }), (1, 1));
+    }
+}
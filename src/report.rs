@@ -0,0 +1,713 @@
+//! This module writes reports of a scan to share with people who don't have the app.
+//!
+//! Every report says where and when the scan was made, so a report passed around keeps
+//! its context. The HTML report is a standalone page with a treemap drawn with positioned
+//! boxes, the largest folders and files, and a breakdown by file type. It needs no script: the boxes
+//! show their path and size when hovered, through their title.
+//!
+//! The summary, written as Markdown or PDF to attach to tickets, lists the volumes the scan
+//! covers, the largest folders and files, the file types, and how the top-level folders grew
+//! since an earlier scan in the history.
+//!
+//! The contents of a folder and its largest files can also be copied as a table, as aligned
+//! text to paste into chat or as CSV for spreadsheets.
+
+use crate::basis::Basis;
+use crate::changes;
+use crate::format::{self, format_count, format_size};
+use crate::logs::EarlierScan;
+use crate::mounts::ScanMount;
+use crate::pdf::{self, Style};
+use crate::scanner::{FileSystemNode, SizeMetric};
+use crate::treemap::{self, Rectangle};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// How many of the largest folders are listed.
+pub const TOP_FOLDERS: usize = 20;
+/// How many of the largest files are listed.
+pub const TOP_FILES: usize = 50;
+/// How many file types are listed before the rest are put together.
+const TOP_TYPES: usize = 15;
+/// How many of the top-level folders that changed most since the earlier scan are listed.
+const TOP_CHANGES: usize = 10;
+/// The most boxes drawn in the treemap, the largest ones, to keep the page small.
+const MAX_BOXES: usize = 4000;
+/// The size of the treemap the boxes are laid out in, before it is scaled to the page.
+const TREEMAP_BOUNDS: Rectangle = Rectangle { x: 0.0, y: 0.0, width: 1200.0, height: 700.0 };
+
+/// An item listed in a report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    /// The path from the scan root, joined with `/`.
+    pub path: String,
+    pub size: u64,
+}
+
+/// The files of one type, by extension.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeShare {
+    /// The extension in lower case, or a description such as "No extension".
+    pub name: String,
+    pub files: u64,
+    pub size: u64,
+}
+
+/// What a report says about a scan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub size: u64,
+    pub allocated: u64,
+    pub files: u64,
+    /// The number of folders, not counting the scan root.
+    pub folders: u64,
+    /// The largest folders at any depth, largest first.
+    pub largest_folders: Vec<Item>,
+    /// The largest files, largest first.
+    pub largest_files: Vec<Item>,
+    /// The space taken by each type of file, largest first, the smallest types put together
+    /// as "Other".
+    pub types: Vec<TypeShare>,
+}
+
+impl Summary {
+    /// Summarizes `tree`.
+    pub fn new(tree: &FileSystemNode) -> Self {
+        let mut summary = Summary {
+            size: tree.size,
+            allocated: tree.allocated,
+            files: 0,
+            folders: 0,
+            largest_folders: Vec::new(),
+            largest_files: Vec::new(),
+            types: Vec::new(),
+        };
+        let mut types: HashMap<String, (u64, u64)> = HashMap::new();
+        let mut path = Vec::new();
+        summary.visit(&tree.children, &mut path, &mut types);
+
+        let mut types: Vec<TypeShare> =
+            types.into_iter().map(|(name, (files, size))| TypeShare { name, files, size }).collect();
+        types.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+        if types.len() > TOP_TYPES {
+            let rest = types.split_off(TOP_TYPES - 1);
+            types.push(TypeShare {
+                name: "Other".to_string(),
+                files: rest.iter().map(|t| t.files).sum(),
+                size: rest.iter().map(|t| t.size).sum(),
+            });
+        }
+        summary.types = types;
+        summary
+    }
+
+    fn visit<'a>(&mut self, nodes: &'a [FileSystemNode], path: &mut Vec<&'a str>, types: &mut HashMap<String, (u64, u64)>) {
+        for node in nodes {
+            path.push(&node.name);
+            if node.children.is_empty() {
+                self.files += 1;
+                keep_largest(&mut self.largest_files, TOP_FILES, path, node.size);
+                let share = types.entry(file_type(&node.name)).or_default();
+                share.0 += 1;
+                share.1 += node.size;
+            } else {
+                self.folders += 1;
+                keep_largest(&mut self.largest_folders, TOP_FOLDERS, path, node.size);
+                self.visit(&node.children, path, types);
+            }
+            path.pop();
+        }
+    }
+}
+
+/// Adds the item at `path` to `largest`, kept sorted largest first, if it is among the
+/// `count` largest. Items of the same size are ranked by path, so the list doesn't depend on
+/// the order the scan listed them in.
+fn keep_largest(largest: &mut Vec<Item>, count: usize, path: &[&str], size: u64) {
+    let full = largest.len() == count;
+    if full && largest.last().is_some_and(|l| l.size > size) {
+        return;
+    }
+    let path = path.join("/");
+    let ranks_higher = |l: &Item| l.size > size || (l.size == size && l.path < path);
+    if full && largest.last().is_some_and(ranks_higher) {
+        return;
+    }
+    let at = largest.partition_point(ranks_higher);
+    largest.insert(at, Item { path, size });
+    largest.truncate(count);
+}
+
+/// The type of the file called `name`: its extension in lower case.
+fn file_type(name: &str) -> String {
+    match Path::new(name).extension() {
+        Some(extension) => extension.to_string_lossy().to_lowercase(),
+        None => "No extension".to_string(),
+    }
+}
+
+/// Escapes `text` to be put in HTML, in an element or a quoted attribute.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2em; color: #222; }
+h1 { font-size: 1.5em; margin-bottom: 0.2em; }
+.treemap { position: relative; width: 100%; aspect-ratio: 12 / 7; background: #333; }
+.treemap div { position: absolute; box-sizing: border-box; border: 1px solid rgba(0, 0, 0, 0.35);
+    overflow: hidden; font-size: 11px; white-space: nowrap; color: #111; }
+.treemap div:hover { outline: 2px solid #fff; z-index: 1; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { padding: 0.2em 0.8em; text-align: left; }
+td.size { text-align: right; font-variant-numeric: tabular-nums; }
+tr:nth-child(even) { background: #f2f2f2; }
+.bar { display: inline-block; height: 0.7em; background: #4a90d9; }
+.sections { display: flex; flex-wrap: wrap; gap: 3em; }
+";
+
+/// Writes the scan of `root` that produced `tree` as a standalone HTML page, with the
+/// treemap sized by `metric` and shares of `basis`. `about` tells where and when the scan
+/// was made, as labels and values.
+pub fn to_html(root: &Path, tree: &FileSystemNode, metric: SizeMetric, basis: &Basis, about: &[(&str, String)]) -> String {
+    let summary = Summary::new(tree);
+    let title = format!("Disk usage of {}", root.display());
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(&title),
+        STYLE
+    );
+    let _ = writeln!(html, "<h1>{}</h1>", escape(&title));
+    let _ = writeln!(
+        html,
+        "<p>{} in {} files and {} folders, taking up {} on disk. Shares are of {}. Made by Disk Scout {}.</p>",
+        format_size(summary.size),
+        format_count(summary.files),
+        format_count(summary.folders),
+        format_size(summary.allocated),
+        escape(&basis.name),
+        env!("CARGO_PKG_VERSION")
+    );
+    if !about.is_empty() {
+        let details: Vec<String> = about.iter().map(|(label, value)| format!("<b>{}:</b> {}", escape(label), escape(value))).collect();
+        let _ = writeln!(html, "<p>{}</p>", details.join(" &middot; "));
+    }
+
+    html.push_str("<div class=\"treemap\">\n");
+    let mut boxes = treemap::generate_treemap(tree, TREEMAP_BOUNDS, metric);
+    boxes.retain(|b| b.rect.width >= 2.0 && b.rect.height >= 2.0);
+    if boxes.len() > MAX_BOXES {
+        boxes.sort_by(|a, b| (b.rect.width * b.rect.height).total_cmp(&(a.rect.width * a.rect.height)));
+        boxes.truncate(MAX_BOXES);
+        // Parents are drawn before their children, so they stay beneath them.
+        boxes.sort_by_key(|b| b.depth);
+    }
+    for b in &boxes {
+        let node = node_at(tree, &b.path);
+        let path = b.path.join("/");
+        let size = node.map_or(0, |n| n.size_by(metric));
+        // The hue goes round with the depth, and deeper boxes are lighter.
+        let color = format!("hsl({}, 55%, {}%)", (b.depth * 47) % 360, 45 + (b.depth * 6).min(40));
+        let label = if b.rect.width >= 60.0 && b.rect.height >= 14.0 {
+            escape(b.path.last().map_or("", String::as_str))
+        } else {
+            String::new()
+        };
+        let _ = writeln!(
+            html,
+            "<div style=\"left:{:.3}%;top:{:.3}%;width:{:.3}%;height:{:.3}%;background:{}\" title=\"{} ({})\">{}</div>",
+            b.rect.x * 100.0 / TREEMAP_BOUNDS.width,
+            b.rect.y * 100.0 / TREEMAP_BOUNDS.height,
+            b.rect.width * 100.0 / TREEMAP_BOUNDS.width,
+            b.rect.height * 100.0 / TREEMAP_BOUNDS.height,
+            color,
+            escape(&path),
+            format_size(size),
+            label
+        );
+    }
+    html.push_str("</div>\n<div class=\"sections\">\n");
+
+    for (heading, items) in [("Largest folders", &summary.largest_folders), ("Largest files", &summary.largest_files)] {
+        let _ = writeln!(html, "<section>\n<h2>{}</h2>\n<table>\n<tr><th>Path</th><th>Size</th><th>Share</th></tr>", heading);
+        for item in items {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td class=\"size\">{}</td><td class=\"size\">{:.1}%</td></tr>",
+                escape(&item.path),
+                format_size(item.size),
+                basis.percent(item.size)
+            );
+        }
+        html.push_str("</table>\n</section>\n");
+    }
+
+    html.push_str("<section>\n<h2>File types</h2>\n<table>\n<tr><th>Type</th><th>Files</th><th>Size</th><th></th></tr>\n");
+    for share in &summary.types {
+        let percent = basis.percent(share.size);
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td class=\"size\">{}</td><td class=\"size\">{}</td><td><span class=\"bar\" style=\"width:{:.0}px\"></span> {:.1}%</td></tr>",
+            escape(&share.name),
+            share.files,
+            format_size(share.size),
+            percent * 2.0,
+            percent
+        );
+    }
+    html.push_str("</table>\n</section>\n</div>\n</body>\n</html>\n");
+    html
+}
+
+/// A top-level folder or file whose size changed between two scans.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub name: String,
+    /// The size in the earlier scan, 0 if it wasn't there.
+    pub before: u64,
+    /// The size now, 0 if it is gone.
+    pub after: u64,
+}
+
+impl Change {
+    /// The difference in size, with its sign, e.g. `+1.5 GiB`.
+    fn difference(&self) -> String {
+        changes::signed_difference(self.before, self.after)
+    }
+}
+
+/// Returns the items directly in the scanned folder whose sizes differ between `earlier`
+/// and `tree`, the biggest changes first.
+pub fn changes(earlier: &FileSystemNode, tree: &FileSystemNode) -> Vec<Change> {
+    let mut changes: Vec<Change> = tree
+        .children
+        .iter()
+        .map(|node| Change {
+            name: node.name.clone(),
+            before: earlier.children.iter().find(|e| e.name == node.name).map_or(0, |e| e.size),
+            after: node.size,
+        })
+        .chain(
+            earlier
+                .children
+                .iter()
+                .filter(|e| !tree.children.iter().any(|n| n.name == e.name))
+                .map(|e| Change { name: e.name.clone(), before: e.size, after: 0 }),
+        )
+        .filter(|c| c.before != c.after)
+        .collect();
+    changes.sort_by(|a, b| b.before.abs_diff(b.after).cmp(&a.before.abs_diff(a.after)).then_with(|| a.name.cmp(&b.name)));
+    changes.truncate(TOP_CHANGES);
+    changes
+}
+
+/// The scans the growth in a summary is measured between.
+pub struct Growth<'a> {
+    /// The earlier scan, from the history.
+    pub earlier: EarlierScan<'a>,
+    /// The current scan, without anything excluded from the view, like the earlier one.
+    pub tree: &'a FileSystemNode,
+}
+
+/// A part of the summary, which is written either as Markdown or as PDF.
+#[derive(Debug, Clone, PartialEq)]
+enum Block {
+    Heading(String),
+    Paragraph(String),
+    /// A table with its column headings. The first column holds names, and the others,
+    /// mostly numbers, are aligned to the right.
+    Table(Vec<&'static str>, Vec<Vec<String>>),
+}
+
+/// Puts together the summary of the scan of `root` that produced `tree`, made at `now`.
+fn summary_blocks(
+    root: &Path,
+    tree: &FileSystemNode,
+    basis: &Basis,
+    mounts: &[ScanMount],
+    growth: Option<&Growth>,
+    about: &[(&str, String)],
+    now: u64,
+) -> Vec<Block> {
+    let summary = Summary::new(tree);
+    let share = |size| format!("{:.1}%", basis.percent(size));
+    let mut blocks = vec![
+        Block::Heading(format!("Disk usage of {}", root.display())),
+        Block::Paragraph(format!(
+            "{} in {} files and {} folders, taking up {} on disk. Shares are of {}. Made by Disk Scout {}.",
+            format_size(summary.size),
+            format_count(summary.files),
+            format_count(summary.folders),
+            format_size(summary.allocated),
+            basis.name,
+            env!("CARGO_PKG_VERSION")
+        )),
+    ];
+    if !about.is_empty() {
+        let details: Vec<String> = about.iter().map(|(label, value)| format!("{}: {}.", label, value)).collect();
+        blocks.push(Block::Paragraph(details.join(" ")));
+    }
+
+    if !mounts.is_empty() {
+        blocks.push(Block::Heading("Volumes".to_string()));
+        let rows = mounts
+            .iter()
+            .map(|m| {
+                let v = &m.volume;
+                vec![
+                    v.label(),
+                    v.file_system.clone(),
+                    format_size(v.used()),
+                    format_size(v.available),
+                    format_size(v.total),
+                    format!("{:.0}%", v.usage_fraction() * 100.0),
+                ]
+            })
+            .collect();
+        blocks.push(Block::Table(vec!["Volume", "File system", "Used", "Free", "Capacity", "Full"], rows));
+    }
+
+    if let Some(Growth { earlier, tree }) = growth {
+        // A report is read away from the app, so both ways of writing the time are given.
+        let (shown, other) = format::time_and_hover(earlier.created_at, now);
+        let change = Change { name: String::new(), before: earlier.tree.size, after: tree.size };
+        blocks.push(Block::Heading(format!("Growth since the scan of {} ({})", shown, other)));
+        blocks.push(Block::Paragraph(format!(
+            "From {} to {} ({}).",
+            format_size(change.before),
+            format_size(change.after),
+            change.difference()
+        )));
+        let rows: Vec<Vec<String>> = changes(earlier.tree, tree)
+            .into_iter()
+            .map(|c| vec![c.name.clone(), format_size(c.before), format_size(c.after), c.difference()])
+            .collect();
+        if !rows.is_empty() {
+            blocks.push(Block::Table(vec!["Item", "Before", "Now", "Change"], rows));
+        }
+    }
+
+    for (heading, items) in [
+        (format!("Top {} folders", TOP_FOLDERS), &summary.largest_folders),
+        (format!("Top {} files", TOP_FILES), &summary.largest_files),
+    ] {
+        blocks.push(Block::Heading(heading));
+        let rows = items.iter().map(|i| vec![i.path.clone(), format_size(i.size), share(i.size)]).collect();
+        blocks.push(Block::Table(vec!["Path", "Size", "Share"], rows));
+    }
+
+    blocks.push(Block::Heading("File types".to_string()));
+    let rows = summary
+        .types
+        .iter()
+        .map(|t| vec![t.name.clone(), t.files.to_string(), format_size(t.size), share(t.size)])
+        .collect();
+    blocks.push(Block::Table(vec!["Type", "Files", "Size", "Share"], rows));
+    blocks
+}
+
+/// Writes the summary of the scan of `root` that produced `tree`, made at `now`, as Markdown.
+/// Shares are of `basis`, `mounts` are the volumes the scan covers, `growth` the scans
+/// growth is measured between, and `about` where and when the scan was made.
+pub fn to_markdown(
+    root: &Path,
+    tree: &FileSystemNode,
+    basis: &Basis,
+    mounts: &[ScanMount],
+    growth: Option<&Growth>,
+    about: &[(&str, String)],
+    now: u64,
+) -> String {
+    let mut markdown = String::new();
+    for (i, block) in summary_blocks(root, tree, basis, mounts, growth, about, now).into_iter().enumerate() {
+        match block {
+            Block::Heading(text) => {
+                let _ = writeln!(markdown, "{} {}\n", if i == 0 { "#" } else { "##" }, text);
+            }
+            Block::Paragraph(text) => {
+                let _ = writeln!(markdown, "{}\n", text);
+            }
+            Block::Table(headings, rows) => {
+                let _ = writeln!(markdown, "| {} |", headings.join(" | "));
+                let rules: Vec<&str> = (0..headings.len()).map(|c| if c == 0 { "---" } else { "---:" }).collect();
+                let _ = writeln!(markdown, "| {} |", rules.join(" | "));
+                for row in rows {
+                    // The first column holds names, shown as code so nothing in them is taken
+                    // as formatting; a pipe would still end the cell.
+                    let name = format!("`{}`", row[0].replace('`', "'").replace('|', "\\|"));
+                    let cells: Vec<&str> = std::iter::once(name.as_str()).chain(row[1..].iter().map(String::as_str)).collect();
+                    let _ = writeln!(markdown, "| {} |", cells.join(" | "));
+                }
+                markdown.push('\n');
+            }
+        }
+    }
+    markdown
+}
+
+/// Writes the summary of the scan of `root` that produced `tree`, made at `now`, as PDF.
+/// Shares are of `basis`, `mounts` are the volumes the scan covers, `growth` the scans
+/// growth is measured between, and `about` where and when the scan was made.
+pub fn to_pdf(
+    root: &Path,
+    tree: &FileSystemNode,
+    basis: &Basis,
+    mounts: &[ScanMount],
+    growth: Option<&Growth>,
+    about: &[(&str, String)],
+    now: u64,
+) -> Vec<u8> {
+    let mut lines = Vec::new();
+    for block in summary_blocks(root, tree, basis, mounts, growth, about, now) {
+        match block {
+            Block::Heading(text) => lines.push((Style::Heading, text)),
+            Block::Paragraph(text) => lines.extend(wrap(&text, Style::Body.line_width()).map(|l| (Style::Body, l))),
+            Block::Table(headings, rows) => {
+                let table = align(&headings, &rows, Some(Style::Mono.line_width()));
+                lines.extend(table.into_iter().map(|l| (Style::Mono, l)));
+            }
+        }
+    }
+    pdf::write(&lines)
+}
+
+/// Lays out a table as lines of text whose columns line up: the first one aligned to the
+/// left and the others to the right. If the lines may be at most `max_width` characters
+/// long, the first column takes the room the others leave, and long names are cut at the
+/// start, since the end of a path says most.
+fn align(headings: &[&str], rows: &[Vec<String>], max_width: Option<usize>) -> Vec<String> {
+    let mut widths: Vec<usize> = headings.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    if let Some(max_width) = max_width {
+        let others: usize = widths[1..].iter().map(|w| w + 2).sum();
+        widths[0] = widths[0].min(max_width.saturating_sub(others)).max(10);
+    }
+    let line = |cells: Vec<&str>| {
+        let mut line = String::new();
+        for (c, (cell, width)) in cells.into_iter().zip(&widths).enumerate() {
+            let length = cell.chars().count();
+            let cell = if length > *width {
+                format!("...{}", cell.chars().skip(length + 3 - width).collect::<String>())
+            } else {
+                cell.to_string()
+            };
+            if c == 0 {
+                let _ = write!(line, "{:<width$}", cell, width = width);
+            } else {
+                let _ = write!(line, "  {:>width$}", cell, width = width);
+            }
+        }
+        line.trim_end().to_string()
+    };
+    std::iter::once(line(headings.to_vec()))
+        .chain(rows.iter().map(|row| line(row.iter().map(String::as_str).collect())))
+        .collect()
+}
+
+/// How a table copied to the clipboard is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    /// Aligned text with readable sizes, to paste into chat or a ticket.
+    Text,
+    /// CSV with sizes in bytes, to paste into a spreadsheet.
+    Csv,
+}
+
+impl TableFormat {
+    pub const ALL: [TableFormat; 2] = [TableFormat::Text, TableFormat::Csv];
+
+    /// The name of the format, as shown in the UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            TableFormat::Text => "text",
+            TableFormat::Csv => "CSV",
+        }
+    }
+
+    /// Writes `items` as a table of their names, sizes, and shares of `basis`.
+    fn write(self, heading: &str, items: &[Item], basis: &Basis) -> String {
+        match self {
+            TableFormat::Text => {
+                let rows: Vec<Vec<String>> = items
+                    .iter()
+                    .map(|i| vec![i.path.clone(), format_size(i.size), format!("{:.1}%", basis.percent(i.size))])
+                    .collect();
+                let mut text = align(&[heading, "Size", "Share"], &rows, None).join("\n");
+                text.push('\n');
+                text
+            }
+            TableFormat::Csv => {
+                let mut csv = format!("{},bytes,percent\n", heading.to_lowercase());
+                for item in items {
+                    let _ = writeln!(csv, "{},{},{:.2}", csv_field(&item.path), item.size, basis.percent(item.size));
+                }
+                csv
+            }
+        }
+    }
+}
+
+/// Writes the contents of `folder`, largest first by `metric`, as a table in `format` with
+/// their shares of `basis`.
+pub fn listing(folder: &FileSystemNode, metric: SizeMetric, basis: &Basis, format: TableFormat) -> String {
+    let mut items: Vec<Item> =
+        folder.children.iter().map(|c| Item { path: c.name.clone(), size: c.size_by(metric) }).collect();
+    items.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    format.write("Name", &items, basis)
+}
+
+/// Writes the largest files in `folder`, with their paths from it, as a table in `format`
+/// with their shares of `basis`.
+pub fn largest_files(folder: &FileSystemNode, basis: &Basis, format: TableFormat) -> String {
+    format.write("Path", &Summary::new(folder).largest_files, basis)
+}
+
+/// Quotes `text` as a CSV field if it needs to be.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) { format!("\"{}\"", text.replace('"', "\"\"")) } else { text.to_string() }
+}
+
+/// Breaks `text` into lines of at most `width` characters, between words.
+fn wrap(text: &str, width: usize) -> impl Iterator<Item = String> {
+    let mut lines = vec![String::new()];
+    for word in text.split_whitespace() {
+        let line = lines.last_mut().expect("there is always a line");
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(word.to_string());
+        } else {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+    }
+    lines.into_iter()
+}
+
+/// Returns the node at `path` below `tree`.
+fn node_at<'a>(tree: &'a FileSystemNode, path: &[String]) -> Option<&'a FileSystemNode> {
+    path.iter().try_fold(tree, |node, name| node.children.iter().find(|c| &c.name == name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, size: u64, children: Vec<FileSystemNode>) -> FileSystemNode {
+        let size = if children.is_empty() { size } else { children.iter().map(|c| c.size).sum() };
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    #[test]
+    fn test_summary_and_html() {
+        let tree = node(
+            "home",
+            0,
+            vec![
+                node("videos", 0, vec![node("trip.MP4", 700, vec![]), node("talk.mp4", 200, vec![])]),
+                node("docs", 0, vec![node("a <b>.txt", 50, vec![]), node("Makefile", 30, vec![])]),
+                node("notes.txt", 20, vec![]),
+            ],
+        );
+        let summary = Summary::new(&tree);
+        assert_eq!((summary.size, summary.files, summary.folders), (1000, 5, 2));
+        let folders: Vec<_> = summary.largest_folders.iter().map(|i| (i.path.as_str(), i.size)).collect();
+        assert_eq!(folders, [("videos", 900), ("docs", 80)]);
+        assert_eq!(summary.largest_files[0], Item { path: "videos/trip.MP4".to_string(), size: 700 });
+
+        // Ties are ranked by path, whichever comes first.
+        let mut largest = Vec::new();
+        for name in ["c", "a", "d", "b"] {
+            keep_largest(&mut largest, 2, &[name], 10);
+        }
+        let paths: Vec<_> = largest.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths, ["a", "b"]);
+        assert_eq!(
+            summary.types,
+            [
+                TypeShare { name: "mp4".to_string(), files: 2, size: 900 },
+                TypeShare { name: "txt".to_string(), files: 2, size: 70 },
+                TypeShare { name: "No extension".to_string(), files: 1, size: 30 },
+            ]
+        );
+
+        let basis = Basis { total: tree.size, name: "home".to_string() };
+        let about = [("Machine", "nas <1>".to_string())];
+        let html = to_html(Path::new("/home"), &tree, SizeMetric::Apparent, &basis, &about);
+        assert!(html.contains("<b>Machine:</b> nas &lt;1&gt;"));
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(!html.contains("<script"));
+        assert!(html.contains("title=\"videos/trip.MP4 (700 B)\""));
+        assert!(html.contains("docs/a &lt;b&gt;.txt"));
+        assert!(!html.contains("a <b>.txt"));
+    }
+
+    #[test]
+    fn test_summary_with_growth() {
+        let earlier = node("home", 0, vec![node("videos", 400, vec![]), node("old.iso", 300, vec![]), node("notes.txt", 20, vec![])]);
+        let tree = node(
+            "home",
+            0,
+            vec![
+                node("videos", 0, vec![node("trip.mp4", 700, vec![]), node("a|b.mp4", 200, vec![])]),
+                node("notes.txt", 20, vec![]),
+            ],
+        );
+        assert_eq!(
+            changes(&earlier, &tree),
+            [
+                Change { name: "videos".to_string(), before: 400, after: 900 },
+                Change { name: "old.iso".to_string(), before: 300, after: 0 },
+            ]
+        );
+
+        let growth = Growth { earlier: EarlierScan { tree: &earlier, created_at: 0 }, tree: &tree };
+        let basis = Basis { total: tree.size, name: "home".to_string() };
+        let about = [("Machine", "nas".to_string()), ("Started", "1970-01-04T00:00:00Z".to_string())];
+        let markdown = to_markdown(Path::new("/home"), &tree, &basis, &[], Some(&growth), &about, 3 * 86400);
+        assert!(markdown.contains("\n\nMachine: nas. Started: 1970-01-04T00:00:00Z.\n\n"));
+        assert!(markdown.starts_with("# Disk usage of /home\n"));
+        assert!(markdown.contains("Shares are of home."));
+        assert!(markdown.contains("## Growth since the scan of 3 days ago (1970-01-01T00:00:00Z)\n\nFrom 720 B to 920 B (+200 B).\n"));
+        assert!(markdown.contains("| `old.iso` | 300 B | 0 B | -300 B |\n"));
+        assert!(markdown.contains("| `videos/a\\|b.mp4` | 200 B | 21.7% |\n"));
+        assert!(!markdown.contains("## Volumes"));
+
+        let pdf = to_pdf(Path::new("/home"), &tree, &basis, &[], Some(&growth), &about, 3 * 86400);
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert!(pdf.windows(12).any(|w| w == b"(File types)"));
+
+        assert_eq!(
+            listing(&tree, SizeMetric::Apparent, &basis, TableFormat::Text),
+            "Name        Size  Share\n\
+             videos     900 B  97.8%\n\
+             notes.txt   20 B   2.2%\n"
+        );
+        assert_eq!(
+            largest_files(&tree.children[0], &Basis { total: 900, name: "videos".to_string() }, TableFormat::Csv),
+            "path,bytes,percent\ntrip.mp4,700,77.78\na|b.mp4,200,22.22\n"
+        );
+        // Shares of the whole volume are smaller.
+        let volume = Basis { total: 92_000, name: "the volume /".to_string() };
+        assert!(listing(&tree, SizeMetric::Apparent, &volume, TableFormat::Text).contains("videos     900 B   1.0%\n"));
+        assert_eq!(csv_field("a, \"b\""), "\"a, \"\"b\"\"\"");
+    }
+}
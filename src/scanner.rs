@@ -1,21 +1,324 @@
 //! This module is responsible for scanning the file system.
-//! It contains the logic to recursively traverse a directory and build a hierarchical
+//! It contains the logic to traverse a directory and build a hierarchical
 //! tree structure representing its contents.
 
+use crate::aliases::MountAliases;
+use crate::clones::Clones;
+use crate::error::{self, ScanError};
+use crate::monitor::ScanMonitor;
+use crate::stats::ScanStats;
+use crate::virtual_fs;
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// The minimum time between two progress reports of a scan.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+/// How many times as long as a progress report took the scan waits before the next one, so
+/// that reports stay a small share of the scan however large the partial tree gets.
+const PROGRESS_BACKOFF: u32 = 20;
+
+/// How many levels below the scan root directories are listed by default. Each level
+/// being listed holds a file handle open, so this stays well below the usual limit of
+/// 1024 handles per process.
+pub const DEFAULT_MAX_NESTING: usize = 512;
 
 /// Represents a node in the file system tree.
 /// It can be either a file or a directory, and it owns its data.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FileSystemNode {
     /// The name of the file or directory (e.g., "src", "main.rs").
     pub name: String,
     /// The total size of the node in bytes. For a file, it's the file size.
     /// For a directory, it's the sum of the sizes of all its children.
     pub size: u64,
+    /// The disk space actually allocated to the node in bytes, summed like `size`.
+    /// It is smaller than `size` for sparse and compressed files, and usually a bit
+    /// larger for small files, which take up whole blocks. A file with several hard
+    /// links in the scan only counts towards the first one found.
+    #[serde(default)]
+    pub allocated: u64,
     /// A vector of child nodes. This is empty for files.
     pub children: Vec<FileSystemNode>,
+    /// What kind of special file the node is, if it is one rather than a regular file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub special: Option<SpecialFile>,
+}
+
+impl FileSystemNode {
+    /// Finds the descendant reached by following `path`, a list of child names
+    /// starting below this node. An empty path returns the node itself.
+    pub fn find(&self, path: &[String]) -> Option<&FileSystemNode> {
+        path.iter().try_fold(self, |node, name| {
+            node.children.iter().find(|c| &c.name == name)
+        })
+    }
+
+    /// Removes the descendant reached by following `path`, and takes its sizes off every
+    /// node on the way to it. Returns the removed node, or `None` if there is none there.
+    pub fn remove(&mut self, path: &[String]) -> Option<FileSystemNode> {
+        let (name, rest) = path.split_first()?;
+        let i = self.children.iter().position(|c| &c.name == name)?;
+        let removed = if rest.is_empty() { self.children.remove(i) } else { self.children[i].remove(rest)? };
+        self.size -= removed.size;
+        self.allocated -= removed.allocated;
+        Some(removed)
+    }
+
+    /// The children in the order `order` asks for. Ordering by modification time reads the
+    /// times of the children from `dir`, where the node is on the disk, as the scan doesn't
+    /// keep them; children whose time can't be read come last.
+    pub fn sorted_children(&self, order: ChildOrder, metric: SizeMetric, dir: &Path) -> Vec<&FileSystemNode> {
+        self.child_order(order, metric, dir).into_iter().map(|i| &self.children[i]).collect()
+    }
+
+    /// Like [`FileSystemNode::sorted_children`], but gives the positions of the children in
+    /// `children`, for views that keep the order while the tree stays the same.
+    pub fn child_order(&self, order: ChildOrder, metric: SizeMetric, dir: &Path) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..self.children.len()).collect();
+        let child = |i: &usize| &self.children[*i];
+        match order {
+            ChildOrder::Name => indices.sort_by(|a, b| child(a).name.cmp(&child(b).name)),
+            ChildOrder::Size => indices.sort_by(|a, b| {
+                let (a, b) = (child(a), child(b));
+                b.size_by(metric).cmp(&a.size_by(metric)).then_with(|| a.name.cmp(&b.name))
+            }),
+            ChildOrder::Modified => {
+                let modified = |i: &usize| fs::symlink_metadata(dir.join(&child(i).name)).and_then(|m| m.modified()).ok();
+                let mut timed: Vec<_> = indices.into_iter().map(|i| (std::cmp::Reverse(modified(&i)), i)).collect();
+                // `None` sorts first, so it is reversed along with the times to come last.
+                timed.sort_by(|(a, x), (b, y)| a.cmp(b).then_with(|| child(x).name.cmp(&child(y).name)));
+                indices = timed.into_iter().map(|(_, i)| i).collect();
+            }
+            ChildOrder::Listed => {}
+        }
+        indices
+    }
+
+    /// Sorts the children of this node and of every node below it by name, for output that
+    /// should come out the same from one scan to the next.
+    pub fn sort_by_name(&mut self) {
+        let mut stack = vec![self];
+        while let Some(node) = stack.pop() {
+            node.children.sort_by(|a, b| a.name.cmp(&b.name));
+            stack.extend(node.children.iter_mut());
+        }
+    }
+
+    /// The size of the node according to `metric`.
+    pub fn size_by(&self, metric: SizeMetric) -> u64 {
+        match metric {
+            SizeMetric::Apparent => self.size,
+            SizeMetric::Allocated => self.allocated,
+        }
+    }
+
+    /// Whether this is a sparse file: one that takes up much less disk space than its size,
+    /// like a virtual machine disk image or a core dump.
+    pub fn is_sparse(&self) -> bool {
+        self.children.is_empty() && self.size >= SPARSE_MIN_SIZE && self.allocated < self.size / 2
+    }
+}
+
+/// Matches the nodes of `left` and `right` by name, e.g. the children of a folder in two
+/// scans: each node of `left` with the node of the same name in `right`, if any, then the
+/// nodes only `right` has. Names are looked up in maps, so huge folders match quickly.
+pub fn pair_by_name<'a>(left: &'a [FileSystemNode], right: &'a [FileSystemNode]) -> Vec<(Option<&'a FileSystemNode>, Option<&'a FileSystemNode>)> {
+    let right_by_name: HashMap<&str, &FileSystemNode> = right.iter().map(|r| (r.name.as_str(), r)).collect();
+    let left_names: HashSet<&str> = left.iter().map(|l| l.name.as_str()).collect();
+    let mut pairs: Vec<_> = left.iter().map(|l| (Some(l), right_by_name.get(l.name.as_str()).copied())).collect();
+    pairs.extend(right.iter().filter(|r| !left_names.contains(r.name.as_str())).map(|r| (None, Some(r))));
+    pairs
+}
+
+/// How the children of a folder are ordered where they are listed. A scan keeps them in
+/// the order the file system listed them, which is cheapest on huge folders, and each view
+/// sorts the folders it shows when it shows them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChildOrder {
+    Name,
+    /// Largest first.
+    #[default]
+    Size,
+    /// Most recently modified first.
+    Modified,
+    /// As the file system listed them.
+    Listed,
+}
+
+impl ChildOrder {
+    /// Every order, as they are offered in the UI.
+    pub const ALL: [ChildOrder; 4] = [ChildOrder::Size, ChildOrder::Name, ChildOrder::Modified, ChildOrder::Listed];
+
+    /// A short name for the order, shown in the UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            ChildOrder::Name => "Name",
+            ChildOrder::Size => "Size",
+            ChildOrder::Modified => "Last modified",
+            ChildOrder::Listed => "As listed on the disk",
+        }
+    }
+
+    /// Parses the name of an order, as used in URLs: `name`, `size`, `modified`, or `none`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "name" => Some(ChildOrder::Name),
+            "size" => Some(ChildOrder::Size),
+            "modified" | "mtime" => Some(ChildOrder::Modified),
+            "none" => Some(ChildOrder::Listed),
+            _ => None,
+        }
+    }
+}
+
+/// A file that is not a regular file, directory, or link, and holds no data on the disk.
+/// The size such a file reports means something else, if anything, e.g. the size of the
+/// whole disk for a block device on some systems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpecialFile {
+    Socket,
+    Fifo,
+    BlockDevice,
+    CharacterDevice,
+}
+
+impl SpecialFile {
+    /// The kind of special file described by `metadata`, or `None` for a regular file.
+    #[cfg(unix)]
+    pub fn of(metadata: &fs::Metadata) -> Option<Self> {
+        use std::os::unix::fs::FileTypeExt;
+        let file_type = metadata.file_type();
+        if file_type.is_socket() {
+            Some(SpecialFile::Socket)
+        } else if file_type.is_fifo() {
+            Some(SpecialFile::Fifo)
+        } else if file_type.is_block_device() {
+            Some(SpecialFile::BlockDevice)
+        } else if file_type.is_char_device() {
+            Some(SpecialFile::CharacterDevice)
+        } else {
+            None
+        }
+    }
+
+    /// Special files of these kinds only exist on Unix.
+    #[cfg(not(unix))]
+    pub fn of(_metadata: &fs::Metadata) -> Option<Self> {
+        None
+    }
+
+    /// A short name for the kind, shown in the UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            SpecialFile::Socket => "Socket",
+            SpecialFile::Fifo => "Named pipe (FIFO)",
+            SpecialFile::BlockDevice => "Block device",
+            SpecialFile::CharacterDevice => "Character device",
+        }
+    }
+}
+
+/// Files smaller than this are never reported as sparse, since block rounding and
+/// compression make their allocated size unreliable.
+const SPARSE_MIN_SIZE: u64 = 1 << 20;
+
+/// Which size of a node to go by when weighing it against others.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SizeMetric {
+    /// The size of the contents, as reported by `ls`.
+    #[default]
+    Apparent,
+    /// The disk space actually used, as reported by `du`.
+    Allocated,
+}
+
+impl SizeMetric {
+    /// Both metrics, in the order they are offered in the UI.
+    pub const ALL: [SizeMetric; 2] = [SizeMetric::Apparent, SizeMetric::Allocated];
+
+    /// A short name for the metric, shown in the UI.
+    pub fn label(self) -> &'static str {
+        match self {
+            SizeMetric::Apparent => "Apparent size",
+            SizeMetric::Allocated => "Disk usage",
+        }
+    }
+}
+
+/// Options controlling what a scan visits and how much detail it keeps.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanOptions {
+    /// The number of levels below the root to keep in the tree. Deeper directories are
+    /// still counted towards their ancestors' sizes, but their contents are dropped.
+    /// `None` keeps everything.
+    pub max_depth: Option<usize>,
+    /// Glob patterns for entries to skip entirely. A pattern is matched against both
+    /// the entry's name and its full path, so `node_modules` and `/home/*/.cache` both work.
+    pub exclude: Vec<String>,
+    /// Whether to stay on the filesystem of the scan root and skip other mounts.
+    pub one_file_system: bool,
+    /// Whether to scan pseudo-filesystems such as `/proc` and `/sys`, which are skipped by default.
+    pub include_virtual: bool,
+    /// Mount points of pseudo-filesystems to scan even though the others are skipped.
+    pub include_mounts: Vec<PathBuf>,
+    /// How many levels below the root directories are listed. Deeper ones are left out
+    /// and counted as errors, as they are usually a folder copied into itself over and over.
+    pub max_nesting: usize,
+    /// Whether the sizes sockets, FIFOs, and devices report are counted. They hold no data,
+    /// so by default they count as empty.
+    pub count_special: bool,
+    /// Whether symbolic links are followed to what they point to. Otherwise a link counts
+    /// as a small file of its own.
+    pub follow_symlinks: bool,
+    /// Whether the contents of the files are hashed when the scan is saved as a snapshot,
+    /// so it can be verified later. The scan itself doesn't read the files.
+    pub hash_files: bool,
+    /// Which size the tree is shown by. Both are always measured.
+    pub metric: SizeMetric,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            exclude: Vec::new(),
+            one_file_system: false,
+            include_virtual: false,
+            include_mounts: Vec::new(),
+            max_nesting: DEFAULT_MAX_NESTING,
+            count_special: false,
+            follow_symlinks: true,
+            hash_files: false,
+            metric: SizeMetric::Apparent,
+        }
+    }
+}
+
+impl ScanOptions {
+    /// Compiles the exclusion patterns, skipping (and reporting) any that are invalid.
+    fn exclude_patterns(&self) -> Vec<Pattern> {
+        self.exclude
+            .iter()
+            .filter_map(|p| match Pattern::new(p) {
+                Ok(pattern) => Some(pattern),
+                Err(e) => {
+                    eprintln!("Ignoring invalid exclude pattern {:?}: {}", p, e);
+                    None
+                }
+            })
+            .collect()
+    }
 }
 
 /// Recursively scans a directory and builds a tree of `FileSystemNode`'s.
@@ -25,61 +328,569 @@ pub struct FileSystemNode {
 ///
 /// # Arguments
 ///
-/// * `path` - The path to the directory or file to build the tree from.
+/// * `path` - The path to the directory to build the tree from.
+/// * `options` - The depth limit, exclusions, and filesystem boundary to honour.
 ///
 /// # Returns
 ///
 /// A `Result` containing the root `FileSystemNode` of the scanned tree,
-/// or an `io::Error` if scanning fails at the root level.
-pub fn build_tree(path: &Path) -> Result<FileSystemNode, std::io::Error> {
-    let metadata = fs::metadata(path)?;
-
-    // Get the name of the file or directory from the path.
-    let name = path
-        .file_name()
-        .unwrap_or_else(|| path.as_os_str()) // Fallback for paths like "/" or "."
-        .to_string_lossy()
-        .into_owned();
-
-    if metadata.is_dir() {
-        let mut children = Vec::new();
-        let mut total_size = 0;
-
-        // Read all entries in the directory.
-        for entry in fs::read_dir(path)? {
-            let entry = entry?;
-            let child_path = entry.path();
-            
-            // Recursively call build_tree for each child.
-            match build_tree(&child_path) {
-                Ok(child_node) => {
-                    total_size += child_node.size;
-                    children.push(child_node);
+/// or a `ScanError` if scanning fails at the root level.
+pub fn build_tree(path: &Path, options: &ScanOptions) -> Result<FileSystemNode, ScanError> {
+    scan_tree(path, options, None, None, None).map(|(tree, _)| tree)
+}
+
+/// Like [`build_tree`], but reports the partial tree to `progress` while the scan runs,
+/// and gives up with [`ScanError::Cancelled`] as soon as `cancel` is set. If there is a
+/// `monitor`, the scan tells it about slow directories and skips those it is asked to.
+///
+/// Each report is a root holding the top-level entries finished since the previous report,
+/// each of them complete, so the partial tree is what the reports add up to and no entry is
+/// copied twice. Reports are throttled so that handling them doesn't slow the scan down.
+/// The statistics of the scan are returned along with the tree.
+///
+/// A panic during the scan, e.g. in `progress`, is returned as [`ScanError::Panicked`]
+/// rather than taking the thread down, so whoever waits for the result gets one.
+pub fn build_tree_with_progress(
+    path: &Path,
+    options: &ScanOptions,
+    progress: &dyn Fn(FileSystemNode),
+    cancel: &AtomicBool,
+    monitor: Option<&ScanMonitor>,
+) -> Result<(FileSystemNode, ScanStats), ScanError> {
+    scan_tree(path, options, Some(progress), Some(cancel), monitor)
+}
+
+fn scan_tree(
+    path: &Path,
+    options: &ScanOptions,
+    progress: Option<&dyn Fn(FileSystemNode)>,
+    cancel: Option<&AtomicBool>,
+    monitor: Option<&ScanMonitor>,
+) -> Result<(FileSystemNode, ScanStats), ScanError> {
+    // The scan's state is dropped with the panic, and the monitor only holds plain data.
+    let scan = || scan_tree_unguarded(path, options, progress, cancel, monitor);
+    let outer = GUARDED.replace(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(scan));
+    GUARDED.set(outer);
+    result.unwrap_or_else(|payload| Err(ScanError::Panicked(panic_message(&*payload))))
+}
+
+thread_local! {
+    /// Whether a scan is running on this thread, ready to catch a panic.
+    static GUARDED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether a panic raised on this thread now would be caught by the scan running on it and
+/// returned as [`ScanError::Panicked`], so the app carries on.
+pub fn panic_is_recovered() -> bool {
+    GUARDED.with(Cell::get)
+}
+
+/// The message a panic was raised with.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => payload.downcast_ref::<String>().cloned().unwrap_or_else(|| "unknown panic".to_string()),
+    }
+}
+
+fn scan_tree_unguarded(
+    path: &Path,
+    options: &ScanOptions,
+    progress: Option<&dyn Fn(FileSystemNode)>,
+    cancel: Option<&AtomicBool>,
+    monitor: Option<&ScanMonitor>,
+) -> Result<(FileSystemNode, ScanStats), ScanError> {
+    let started = Instant::now();
+    let metadata = fs::metadata(path).map_err(|e| ScanError::from_io(path, e))?;
+    if !metadata.is_dir() {
+        return Err(ScanError::NotADirectory(path.to_path_buf()));
+    }
+    let root_device = device_id(&metadata);
+    let mut skipped_devices = if options.include_virtual {
+        HashSet::new()
+    } else {
+        virtual_fs::skipped_devices(&options.include_mounts)
+    };
+    // Scanning a pseudo-filesystem directly is taken as a request to see it.
+    if let Some(root_device) = root_device {
+        skipped_devices.remove(&root_device);
+    }
+    let scan = Scan {
+        options,
+        exclude: options.exclude_patterns(),
+        root_device,
+        skipped_devices,
+        progress,
+        next_progress: Cell::new(None),
+        reported: Cell::new(0),
+        cancel,
+        monitor,
+        seen_links: RefCell::new(HashSet::new()),
+        clones: RefCell::new(Clones::default()),
+        aliases: RefCell::new(MountAliases::for_scan(path, |p| fs::metadata(p).ok().as_ref().and_then(file_id))),
+        stats: RefCell::new(ScanStats::default()),
+        root: path,
+        volume_lost: Cell::new(false),
+    };
+    let tree = scan.build(path, metadata)?;
+    let mut stats = scan.stats.into_inner();
+    stats.seconds = started.elapsed().as_secs_f64();
+    Ok((tree, stats))
+}
+
+/// The state shared by every level of a single scan.
+struct Scan<'a> {
+    options: &'a ScanOptions,
+    exclude: Vec<Pattern>,
+    /// The device the scan root lives on, used by the one-file-system option.
+    root_device: Option<u64>,
+    /// The devices of the pseudo-filesystems to skip.
+    skipped_devices: HashSet<u64>,
+    /// Where partial trees are reported, if anywhere.
+    progress: Option<&'a dyn Fn(FileSystemNode)>,
+    /// When the next partial tree may be reported.
+    next_progress: Cell<Option<Instant>>,
+    /// How many top-level entries were reported so far.
+    reported: Cell<usize>,
+    /// Set from another thread to stop the scan.
+    cancel: Option<&'a AtomicBool>,
+    /// Told about slow directories, and asked which to skip, if anything is watching.
+    monitor: Option<&'a ScanMonitor>,
+    /// The files with several hard links seen so far, so their disk space is only counted once.
+    seen_links: RefCell<HashSet<(u64, u64)>>,
+    /// The cloned files seen so far, so the blocks they share are only counted once.
+    clones: RefCell<Clones>,
+    /// The mounts that may show data found elsewhere in the scan.
+    aliases: RefCell<MountAliases>,
+    /// The statistics collected so far.
+    stats: RefCell<ScanStats>,
+    /// The scan root, to tell whether its volume is still there.
+    root: &'a Path,
+    /// Whether the volume holding the scan root went away during the scan.
+    volume_lost: Cell<bool>,
+}
+
+impl Scan<'_> {
+    /// Reports the top-level `children` of the root not reported yet, unless a report was made
+    /// very recently.
+    fn report_progress(&self, name: &str, children: &[FileSystemNode]) {
+        let Some(progress) = self.progress else {
+            return;
+        };
+        if self.next_progress.get().is_some_and(|t| Instant::now() < t) {
+            return;
+        }
+        let started = Instant::now();
+        progress(directory_node(name.to_string(), children[self.reported.get()..].to_vec()));
+        self.reported.set(children.len());
+        let wait = PROGRESS_INTERVAL.max(started.elapsed() * PROGRESS_BACKOFF);
+        self.next_progress.set(Some(Instant::now() + wait));
+    }
+
+    /// Whether the user asked to skip the directory at `path` while the scan was running.
+    fn is_skipped(&self, path: &Path) -> bool {
+        self.monitor.is_some_and(|m| m.is_skipped(path))
+    }
+
+    /// Whether to read `path` again after the share holding it stopped answering. The
+    /// user is asked through the monitor, and the scan waits for the answer; with nobody
+    /// watching, the scan carries on without it.
+    fn retry_unreachable(&self, path: &Path) -> bool {
+        self.monitor.is_some_and(|m| m.wait_for_reconnection(path, self.cancel))
+    }
+
+    /// Whether the volume holding the scan root went away, e.g. because the drive was ejected,
+    /// which `error` from reading it may mean. Once it has, every directory being listed is
+    /// finished with what was read, so the scan ends early with a partial tree rather than
+    /// an error for each entry left.
+    fn lost_volume(&self, error: &io::Error) -> bool {
+        if !self.volume_lost.get()
+            && error::is_removal(error)
+            && fs::metadata(self.root).map_or(true, |m| device_id(&m) != self.root_device)
+        {
+            self.volume_lost.set(true);
+            self.stats.borrow_mut().volume_lost = true;
+        }
+        self.volume_lost.get()
+    }
+
+    /// Runs `f`, a read from the directory at `path`, adding the time it took to `listing`.
+    fn read<T>(&self, path: &Path, listing: &mut Duration, f: impl FnOnce() -> T) -> T {
+        if let Some(monitor) = self.monitor {
+            monitor.start_waiting(path, *listing);
+        }
+        let started = Instant::now();
+        let result = f();
+        *listing += started.elapsed();
+        if let Some(monitor) = self.monitor {
+            monitor.stop_waiting();
+        }
+        result
+    }
+
+    /// Whether the directory at `path` should be skipped: because the user skipped it while
+    /// the scan runs, or because of the filesystem it is on.
+    fn is_excluded_mount(&self, path: &Path, metadata: &fs::Metadata) -> bool {
+        if !metadata.is_dir() {
+            return false;
+        }
+        let device = device_id(metadata);
+        self.is_skipped(path)
+            || (self.options.one_file_system && device != self.root_device)
+            || device.is_some_and(|d| self.skipped_devices.contains(&d))
+    }
+
+    /// Whether the entry at `path` matches an exclusion pattern.
+    fn matches_exclude(&self, path: &Path) -> bool {
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        self.exclude
+            .iter()
+            .any(|p| p.matches(&name) || p.matches_path(path))
+    }
+
+    /// Builds the node for `path`, the scan root, and everything below it.
+    ///
+    /// The tree is walked with a stack of the directories being listed rather than by
+    /// recursion, so folders nested thousands of levels deep, e.g. by a program that
+    /// created copies of itself inside itself, can't overflow the call stack. Directories
+    /// nested deeper than the scan options allow are left out as errors.
+    fn build(&self, path: &Path, metadata: fs::Metadata) -> Result<FileSystemNode, ScanError> {
+        // The identities of the directories on the stack, to detect symbolic links that
+        // loop back to one of them.
+        let mut ancestors = Vec::new();
+        // The name of the root is its last component, with a fallback for paths like "/" or ".".
+        let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned();
+        let mut stack = match self.enter(path, name, metadata, 0, &mut ancestors)? {
+            Entered::Node(node) => return Ok(node),
+            Entered::Directory(listing) => vec![listing],
+        };
+        while let Some(dir) = stack.last_mut() {
+            let (child_path, child, is_file) = match self.next_child(dir, &mut ancestors) {
+                Next::Descend(listing) => {
+                    stack.push(listing);
+                    continue;
                 }
-                Err(e) => {
-                    // Log an error for inaccessible files/dirs but continue scanning others.
-                    // This makes the scan more resilient to permission errors.
-                    eprintln!("Failed to scan {}: {}", child_path.display(), e);
+                Next::Skipped => continue,
+                Next::Child(child, is_file) => (std::mem::take(&mut dir.child_path), child, is_file),
+                Next::End(Err(ScanError::Disconnected(_))) if self.retry_unreachable(&dir.path) => {
+                    dir.restart();
+                    continue;
+                }
+                Next::End(Err(ScanError::Cancelled)) => return Err(ScanError::Cancelled),
+                Next::End(result) => {
+                    let Some(mut dir) = stack.pop() else { break };
+                    if dir.id.is_some() {
+                        ancestors.pop();
+                    }
+                    let node = result.map(|()| self.finish(&mut dir));
+                    (dir.path, node, false)
                 }
+            };
+            match stack.last_mut() {
+                Some(parent) => {
+                    self.adopt(parent, &child_path, child, is_file);
+                    // Whichever path it was, its memory serves for the paths of the next entries.
+                    parent.child_path = child_path;
+                }
+                None => return child,
             }
         }
-        children.sort_by(|a, b| a.name.cmp(&b.name));
+        unreachable!("the scan root is finished before the stack empties")
+    }
 
-        Ok(FileSystemNode {
-            name,
-            size: total_size,
-            children,
-        })
-    } else {
-        // It's a file, so it has a defined size and no children.
-        Ok(FileSystemNode {
+    /// Starts on the entry at `path`, called `name`, `depth` levels below the scan root: a
+    /// file becomes its node right away, and a directory a listing to walk.
+    fn enter(
+        &self,
+        path: &Path,
+        name: String,
+        metadata: fs::Metadata,
+        depth: usize,
+        ancestors: &mut Vec<(u64, u64)>,
+    ) -> Result<Entered, ScanError> {
+        if !metadata.is_dir() {
+            // It's a file, so it has a defined size and no children. Further links to a
+            // file that was already counted take up no additional disk space.
+            let repeated_link = hard_link_id(&metadata).is_some_and(|id| !self.seen_links.borrow_mut().insert(id));
+            let special = SpecialFile::of(&metadata);
+            // Special files hold no data, whatever size they report.
+            let counted = special.is_none() || self.options.count_special;
+            let size = if counted { metadata.len() } else { 0 };
+            let mut allocated = if counted && !repeated_link { allocated_size(&metadata) } else { 0 };
+            let mut stats = self.stats.borrow_mut();
+            if allocated > 0 && special.is_none() {
+                let (counted, cloned) = self.clones.borrow_mut().check(path, allocated);
+                allocated = counted;
+                stats.cloned_files.extend(cloned);
+            }
+            stats.record_file(path, depth, size);
+            if repeated_link {
+                stats.repeated_links.push(path.to_path_buf());
+            }
+            if special.is_some() {
+                stats.special_files += 1;
+            }
+            return Ok(Entered::Node(FileSystemNode { name, size, allocated, children: Vec::new(), special }));
+        }
+        if depth > self.options.max_nesting {
+            return Err(ScanError::TooDeep(path.to_path_buf()));
+        }
+        let id = file_id(&metadata);
+        if let Some(id) = id {
+            if ancestors.contains(&id) {
+                return Err(ScanError::CyclicSymlink(path.to_path_buf()));
+            }
+            // Data shown at more than one path is only counted at one of them.
+            if let Some(alias) = self.aliases.borrow_mut().check(path, id) {
+                self.stats.borrow_mut().aliases.push(alias);
+                return Ok(Entered::Node(directory_node(name, Vec::new())));
+            }
+            ancestors.push(id);
+        }
+        Ok(Entered::Directory(Listing::new(path.to_path_buf(), name, depth, id)))
+    }
+
+    /// Reads the next entry of `dir`, opening it first if it isn't yet.
+    fn next_child(&self, dir: &mut Listing, ancestors: &mut Vec<(u64, u64)>) -> Next {
+        if self.volume_lost.get() {
+            return Next::End(Ok(()));
+        }
+        let entries = match &mut dir.entries {
+            Some(entries) => entries,
+            entries @ None => match self.read(&dir.path, &mut dir.listing, || fs::read_dir(&dir.path)) {
+                Ok(opened) => entries.insert(opened),
+                Err(e) if self.lost_volume(&e) => return Next::End(Ok(())),
+                Err(e) => return Next::End(Err(ScanError::from_io(&dir.path, e))),
+            },
+        };
+        let Some(entry) = self.read(&dir.path, &mut dir.listing, || entries.next()) else {
+            return Next::End(Ok(()));
+        };
+        if self.cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
+            return Next::End(Err(ScanError::Cancelled));
+        }
+        // What was read so far is thrown away by the parent.
+        if self.is_skipped(&dir.path) {
+            return Next::End(Ok(()));
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) if self.lost_volume(&e) => return Next::End(Ok(())),
+            Err(e) => return Next::End(Err(ScanError::from_io(&dir.path, e))),
+        };
+        // The path is built in a buffer kept from the previous entries, so the name, which the
+        // node keeps, is the only allocation most entries need.
+        let file_name = entry.file_name();
+        dir.child_path.clone_from(&dir.path);
+        dir.child_path.push(&file_name);
+        let child_path = &dir.child_path;
+        // Patterns only need the path, so an excluded entry costs no further system call.
+        if self.matches_exclude(child_path) {
+            return Next::Skipped;
+        }
+
+        // The metadata is read relative to the open directory, which is cheaper than resolving
+        // the whole path again, and comes with the listing on Windows. It describes links
+        // themselves, so only links to follow need a second look at what they point to.
+        let mut child_metadata = self.read(&dir.path, &mut dir.listing, || entry.metadata());
+        if self.options.follow_symlinks && child_metadata.as_ref().is_ok_and(|m| m.file_type().is_symlink()) {
+            child_metadata = self.read(&dir.path, &mut dir.listing, || fs::metadata(child_path));
+        }
+        while let Err(e) = &child_metadata
+            && error::is_disconnection(e)
+            && self.retry_unreachable(child_path)
+        {
+            child_metadata = self.read(&dir.path, &mut dir.listing, || fs::metadata(child_path));
+        }
+        match child_metadata {
+            Ok(child_metadata) if self.is_excluded_mount(child_path, &child_metadata) => Next::Skipped,
+            Ok(child_metadata) => {
+                let is_file = !child_metadata.is_dir();
+                let name = file_name.into_string().unwrap_or_else(|name| name.to_string_lossy().into_owned());
+                match self.enter(child_path, name, child_metadata, dir.depth + 1, ancestors) {
+                    Ok(Entered::Directory(listing)) => Next::Descend(listing),
+                    Ok(Entered::Node(node)) => Next::Child(Ok(node), is_file),
+                    Err(e) => Next::Child(Err(e), is_file),
+                }
+            }
+            Err(e) if self.lost_volume(&e) => Next::Skipped,
+            Err(e) => Next::Child(Err(ScanError::from_io(child_path, e)), false),
+        }
+    }
+
+    /// Adds the node for the entry at `child_path` of `dir`, or logs why there is none.
+    fn adopt(&self, dir: &mut Listing, child_path: &Path, child: Result<FileSystemNode, ScanError>, is_file: bool) {
+        match child {
+            Ok(_) if self.is_skipped(child_path) => {}
+            Ok(child_node) => {
+                if is_file {
+                    dir.own_bytes += child_node.size;
+                }
+                dir.children.push(child_node);
+                if dir.depth == 0 && self.options.max_depth.is_none_or(|max| max > 0) {
+                    self.report_progress(&dir.name, &dir.children);
+                }
+            }
+            Err(e) => {
+                // Log an error for inaccessible files/dirs but continue scanning others.
+                // This makes the scan more resilient to permission errors.
+                eprintln!("Failed to scan {}: {}", child_path.display(), e);
+                self.stats.borrow_mut().record_error(&e);
+            }
+        }
+    }
+
+    /// Turns `dir`, listed to the end, into its node.
+    fn finish(&self, dir: &mut Listing) -> FileSystemNode {
+        self.stats.borrow_mut().record_directory(&dir.path, dir.depth, dir.own_bytes, dir.listing);
+        if let Some(monitor) = self.monitor {
+            monitor.finished(&dir.path, dir.listing);
+        }
+        // The children stay in the order they were listed; views sort what they show.
+        let mut node = directory_node(std::mem::take(&mut dir.name), std::mem::take(&mut dir.children));
+
+        // Past the depth limit, keep the total but drop the details.
+        if self.options.max_depth.is_some_and(|max| dir.depth >= max) {
+            node.children.clear();
+        }
+        node
+    }
+}
+
+/// An entry the scan has started on.
+enum Entered {
+    /// A file, or a directory that isn't listed, whose node is complete.
+    Node(FileSystemNode),
+    /// A directory, whose entries are to be read.
+    Directory(Listing),
+}
+
+/// What reading the next entry of a directory came to.
+enum Next {
+    /// The entry is a directory, to be listed before going on.
+    Descend(Listing),
+    /// The entry is excluded from the scan.
+    Skipped,
+    /// The entry at the `child_path` of the directory is done: its node, or why it couldn't be
+    /// scanned, and whether it is a file.
+    Child(Result<FileSystemNode, ScanError>, bool),
+    /// The directory has no more entries, or listing it failed.
+    End(Result<(), ScanError>),
+}
+
+/// A directory being listed, on the stack of a scan.
+struct Listing {
+    path: PathBuf,
+    name: String,
+    /// The path of the entry being read, kept to build the paths of the next ones in.
+    child_path: PathBuf,
+    /// How many levels below the scan root it is.
+    depth: usize,
+    /// Its identity, if it is on the scan's list of ancestors.
+    id: Option<(u64, u64)>,
+    /// Its entries, once it is opened.
+    entries: Option<fs::ReadDir>,
+    /// The nodes of the entries done so far.
+    children: Vec<FileSystemNode>,
+    /// The time spent listing it and reading the metadata of its entries.
+    listing: Duration,
+    /// The size of the files directly in it.
+    own_bytes: u64,
+}
+
+impl Listing {
+    fn new(path: PathBuf, name: String, depth: usize, id: Option<(u64, u64)>) -> Self {
+        Self {
+            path,
             name,
-            size: metadata.len(),
-            children: Vec::new(), 
-        })
+            child_path: PathBuf::new(),
+            depth,
+            id,
+            entries: None,
+            children: Vec::new(),
+            listing: Duration::ZERO,
+            own_bytes: 0,
+        }
+    }
+
+    /// Forgets what was read, to list the directory again from the start.
+    fn restart(&mut self) {
+        let path = std::mem::take(&mut self.path);
+        let name = std::mem::take(&mut self.name);
+        *self = Self::new(path, name, self.depth, self.id);
     }
 }
 
+/// Creates a directory node whose sizes are the totals of its children.
+fn directory_node(name: String, children: Vec<FileSystemNode>) -> FileSystemNode {
+    FileSystemNode {
+        name,
+        size: children.iter().map(|c| c.size).sum(),
+        allocated: children.iter().map(|c| c.allocated).sum(),
+        children,
+        special: None,
+    }
+}
+
+/// Returns the disk space allocated to the file described by `metadata`.
+#[cfg(unix)]
+fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    // `blocks` is always counted in 512-byte units, whatever the filesystem's block size.
+    metadata.blocks() * 512
+}
+
+/// Returns the disk space allocated to the file described by `metadata`. This isn't
+/// available on this platform, so the apparent size is used.
+#[cfg(not(unix))]
+fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+/// Returns the ID of the device that holds the file described by `metadata`.
+#[cfg(unix)]
+pub fn device_id(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+/// Returns the ID of the device that holds the file described by `metadata`.
+/// This isn't available on this platform, so the one-file-system option has no effect.
+#[cfg(not(unix))]
+pub fn device_id(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Returns the device and inode numbers that identify the file described by `metadata`.
+#[cfg(unix)]
+pub fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// Returns the device and inode numbers that identify the file described by `metadata`.
+/// These aren't available on this platform, so symbolic link loops aren't detected and
+/// files can't be told apart from their links.
+#[cfg(not(unix))]
+pub fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Returns the identity of the file described by `metadata` if it has more than one hard link.
+#[cfg(unix)]
+fn hard_link_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    if metadata.nlink() > 1 { file_id(metadata) } else { None }
+}
+
+/// Returns the identity of the file described by `metadata` if it has more than one hard link.
+/// Link counts aren't available on this platform, so hard links are counted every time.
+#[cfg(not(unix))]
+fn hard_link_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -108,27 +919,281 @@ mod tests {
         let expected = FileSystemNode {
             name: root.file_name().unwrap().to_string_lossy().into_owned(),
             size: 30,
+            allocated: 30,
             children: vec![
                 FileSystemNode {
                     name: "a.txt".to_string(),
                     size: 10,
+                    allocated: 10,
                     children: vec![],
+                    special: None,
                 },
                 FileSystemNode {
                     name: "sub".to_string(),
                     size: 20,
+                    allocated: 20,
                     children: vec![
                         FileSystemNode {
                             name: "b.txt".to_string(),
                             size: 20,
+                            allocated: 20,
                             children: vec![],
+                            special: None,
                         },
                     ],
+                    special: None,
                 },
             ],
+            special: None,
         };
 
-        let result = build_tree(root).unwrap();
+        // The allocated sizes depend on the filesystem, so only the apparent sizes are compared.
+        fn ignore_allocation(node: &mut FileSystemNode) {
+            node.allocated = node.size;
+            node.children.iter_mut().for_each(ignore_allocation);
+        }
+        let mut result = build_tree(root, &ScanOptions::default()).unwrap();
+        ignore_allocation(&mut result);
+        // The children come in the order the file system lists them.
+        result.sort_by_name();
         assert_eq!(result, expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_sorted_children() {
+        let dir = tempdir().unwrap();
+        for (name, size, age) in [("b.log", 30, 300), ("a.txt", 10, 100), ("c.iso", 30, 200)] {
+            let file = File::create(dir.path().join(name)).unwrap();
+            file.set_len(size).unwrap();
+            file.set_modified(std::time::SystemTime::now() - Duration::from_secs(age)).unwrap();
+        }
+        let tree = build_tree(dir.path(), &ScanOptions::default()).unwrap();
+        let names = |order| -> Vec<&str> {
+            tree.sorted_children(order, SizeMetric::Apparent, dir.path()).iter().map(|c| c.name.as_str()).collect()
+        };
+        assert_eq!(names(ChildOrder::Name), ["a.txt", "b.log", "c.iso"]);
+        // Equal sizes are ordered by name.
+        assert_eq!(names(ChildOrder::Size), ["b.log", "c.iso", "a.txt"]);
+        assert_eq!(names(ChildOrder::Modified), ["a.txt", "c.iso", "b.log"]);
+        let listed: Vec<&str> = tree.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names(ChildOrder::Listed), listed);
+        // Children that are gone from the disk come last.
+        fs::remove_file(dir.path().join("a.txt")).unwrap();
+        assert_eq!(names(ChildOrder::Modified), ["c.iso", "b.log", "a.txt"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_build_tree_detects_sparse_files() {
+        let dir = tempdir().unwrap();
+        File::create(dir.path().join("disk.img")).unwrap().set_len(64 << 20).unwrap();
+        File::create(dir.path().join("data.bin")).unwrap().write_all(&[1; 2 << 20]).unwrap();
+
+        let result = build_tree(dir.path(), &ScanOptions::default()).unwrap();
+        let image = result.find(&["disk.img".to_string()]).unwrap();
+        assert_eq!(image.size, 64 << 20);
+        assert!(image.is_sparse());
+        assert!(image.allocated < image.size_by(SizeMetric::Apparent));
+        assert!(!result.find(&["data.bin".to_string()]).unwrap().is_sparse());
+        assert_eq!(result.allocated, result.children.iter().map(|c| c.allocated).sum::<u64>());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_build_tree_counts_hard_links_once() {
+        let dir = tempdir().unwrap();
+        File::create(dir.path().join("a.bin")).unwrap().write_all(&[1; 2 << 20]).unwrap();
+        fs::hard_link(dir.path().join("a.bin"), dir.path().join("b.bin")).unwrap();
+
+        let (result, stats) =
+            build_tree_with_progress(dir.path(), &ScanOptions::default(), &|_| {}, &AtomicBool::new(false), None).unwrap();
+        let (a, b) = (result.find(&["a.bin".to_string()]).unwrap(), result.find(&["b.bin".to_string()]).unwrap());
+        assert_eq!((a.size, b.size), (2 << 20, 2 << 20));
+        // Whichever link is listed second takes up no disk space, and is recorded as such.
+        let (first, repeated) = if a.allocated == 0 { ("b.bin", "a.bin") } else { ("a.bin", "b.bin") };
+        assert!(result.find(&[first.to_string()]).unwrap().allocated > 0);
+        assert_eq!(stats.repeated_links, [dir.path().join(repeated)]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_build_tree_tags_special_files() {
+        let dir = tempdir().unwrap();
+        let _socket = std::os::unix::net::UnixListener::bind(dir.path().join("app.sock")).unwrap();
+        std::os::unix::fs::symlink("/dev/null", dir.path().join("null")).unwrap();
+        File::create(dir.path().join("data.bin")).unwrap().write_all(&[1; 100]).unwrap();
+
+        let (result, stats) =
+            build_tree_with_progress(dir.path(), &ScanOptions::default(), &|_| {}, &AtomicBool::new(false), None).unwrap();
+        let special = |name: &str| result.find(&[name.to_string()]).unwrap().special;
+        assert_eq!(special("app.sock"), Some(SpecialFile::Socket));
+        assert_eq!(special("null"), Some(SpecialFile::CharacterDevice));
+        assert_eq!(special("data.bin"), None);
+        assert_eq!(result.size, 100);
+        assert_eq!((stats.files, stats.special_files), (3, 2));
+    }
+
+    #[test]
+    fn test_build_tree_with_options() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        // /
+        // |- keep/deep/c.txt (30 bytes)
+        // |- node_modules/d.js (40 bytes)
+        create_dir_all(root.join("keep").join("deep")).unwrap();
+        create_dir_all(root.join("node_modules")).unwrap();
+        File::create(root.join("keep").join("deep").join("c.txt")).unwrap().write_all(&[0; 30]).unwrap();
+        File::create(root.join("node_modules").join("d.js")).unwrap().write_all(&[0; 40]).unwrap();
+
+        let options = ScanOptions {
+            max_depth: Some(2),
+            exclude: vec!["node_modules".to_string()],
+            one_file_system: true,
+            ..Default::default()
+        };
+        let result = build_tree(root, &options).unwrap();
+
+        // The excluded directory is skipped entirely.
+        assert_eq!(result.size, 30);
+        assert_eq!(result.children.len(), 1);
+        // The directory at the depth limit keeps its size but not its contents.
+        let deep = result.find(&["keep".to_string(), "deep".to_string()]).unwrap();
+        assert_eq!(deep.size, 30);
+        assert!(deep.children.is_empty());
+
+        // Directories can also be skipped while the scan runs.
+        let monitor = ScanMonitor::default();
+        monitor.skip(&root.join("keep"));
+        let options = ScanOptions::default();
+        let (result, _) = build_tree_with_progress(root, &options, &|_| {}, &AtomicBool::new(false), Some(&monitor)).unwrap();
+        assert_eq!(result.size, 40);
+        assert_eq!(result.children.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_build_tree_paths_and_links() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+
+        // /
+        // |- a/deep/x (1 byte)
+        // |- b/big (100 bytes)
+        // |- b/small (1 byte)
+        // |- link -> b/big
+        create_dir_all(root.join("a").join("deep")).unwrap();
+        create_dir_all(root.join("b")).unwrap();
+        File::create(root.join("a").join("deep").join("x")).unwrap().write_all(&[0; 1]).unwrap();
+        File::create(root.join("b").join("big")).unwrap().write_all(&[0; 100]).unwrap();
+        File::create(root.join("b").join("small")).unwrap().write_all(&[0; 1]).unwrap();
+        std::os::unix::fs::symlink("b/big", root.join("link")).unwrap();
+
+        // The paths of the entries are built in a buffer shared by their siblings, so those
+        // recorded after coming back from a subdirectory show whether it was reused right.
+        let options = ScanOptions::default();
+        let (result, stats) = build_tree_with_progress(root, &options, &|_| {}, &AtomicBool::new(false), None).unwrap();
+        assert_eq!(stats.deepest, Some((root.join("a").join("deep").join("x"), 3)));
+        assert_eq!(stats.largest_directory, Some((root.join("b"), 101)));
+        assert_eq!(result.find(&["link".to_string()]).unwrap().size, 100);
+
+        // Links that aren't followed are the size of their target's path.
+        let options = ScanOptions { follow_symlinks: false, ..ScanOptions::default() };
+        let result = build_tree(root, &options).unwrap();
+        assert_eq!(result.find(&["link".to_string()]).unwrap().size, 5);
+    }
+
+    #[test]
+    fn test_build_tree_of_deep_nesting() {
+        let dir = tempdir().unwrap();
+        let mut deepest = dir.path().to_path_buf();
+        for _ in 0..300 {
+            deepest.push("d");
+        }
+        create_dir_all(&deepest).unwrap();
+        File::create(deepest.join("f")).unwrap().write_all(&[0; 10]).unwrap();
+
+        // A thread with a small stack, which recursing level by level would overflow.
+        let scan = |options: ScanOptions| {
+            let root = dir.path().to_path_buf();
+            let scan = move || scan_tree(&root, &options, None, None, None).unwrap();
+            std::thread::Builder::new().stack_size(64 << 10).spawn(scan).unwrap().join().unwrap()
+        };
+        let (tree, stats) = scan(ScanOptions::default());
+        assert_eq!(tree.size, 10);
+        assert_eq!(stats.deepest.map(|(_, depth)| depth), Some(301));
+        assert_eq!(stats.too_deep, None);
+
+        // Past the nesting limit, the rest is left out and reported.
+        let (tree, stats) = scan(ScanOptions { max_nesting: 100, ..Default::default() });
+        assert_eq!(tree.size, 0);
+        assert_eq!(stats.directories, 101);
+        assert_eq!(stats.errors["Nested too deeply"], 1);
+        assert_eq!(stats.too_deep.map(|(path, count)| (path.components().count(), count)), Some((dir.path().components().count() + 101, 1)));
+    }
+
+    #[test]
+    fn test_build_tree_errors() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        File::create(root.join("a.txt")).unwrap().write_all(&[0; 10]).unwrap();
+
+        let options = ScanOptions::default();
+        assert!(matches!(build_tree(&root.join("missing"), &options), Err(ScanError::NotFound(_))));
+        assert!(matches!(build_tree(&root.join("a.txt"), &options), Err(ScanError::NotADirectory(_))));
+
+        let cancel = AtomicBool::new(true);
+        let result = build_tree_with_progress(root, &options, &|_| {}, &cancel, None);
+        assert!(matches!(result, Err(ScanError::Cancelled)));
+
+        // A panic becomes an error instead of taking the scanning thread down.
+        let progress = |_: FileSystemNode| {
+            assert!(panic_is_recovered());
+            panic!("progress failed")
+        };
+        let result = build_tree_with_progress(root, &options, &progress, &AtomicBool::new(false), None);
+        assert!(matches!(result, Err(ScanError::Panicked(ref m)) if m == "progress failed"));
+        assert!(!panic_is_recovered());
+
+        // A link back to the root is skipped instead of being followed forever.
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(root, root.join("loop")).unwrap();
+            let (result, stats) = build_tree_with_progress(root, &options, &|_| {}, &AtomicBool::new(false), None).unwrap();
+            assert_eq!(result.size, 10);
+            assert_eq!(result.children.len(), 1);
+            assert_eq!((stats.directories, stats.files), (1, 1));
+            assert_eq!(stats.errors["Symbolic link loop"], 1);
+
+            // Without following links, the link is a file of its own.
+            let options = ScanOptions { follow_symlinks: false, ..ScanOptions::default() };
+            let (result, stats) = build_tree_with_progress(root, &options, &|_| {}, &AtomicBool::new(false), None).unwrap();
+            assert_eq!(result.children.len(), 2);
+            assert!(result.find(&["loop".to_string()]).unwrap().children.is_empty());
+            assert!(stats.errors.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_scan_stops_when_volume_goes_away() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("usb");
+        for name in ["a", "b", "c"] {
+            fs::create_dir_all(root.join(name)).unwrap();
+            File::create(root.join(name).join("photo.jpg")).unwrap().write_all(&[0; 10]).unwrap();
+        }
+
+        // The drive disappears once the first folder was scanned.
+        let removed = Cell::new(false);
+        let eject = |_: FileSystemNode| {
+            if !removed.replace(true) {
+                fs::remove_dir_all(&root).unwrap();
+            }
+        };
+        let (result, stats) = build_tree_with_progress(&root, &ScanOptions::default(), &eject, &AtomicBool::new(false), None).unwrap();
+        assert!(stats.volume_lost);
+        assert_eq!(result.children.len(), 1);
+        assert_eq!(result.size, 10);
+        assert!(stats.errors.is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+//! This module runs user scripts over a finished scan, so power users can write their own
+//! reports without recompiling. Scripts are written in Rhai, a small language embedded in
+//! the app, and see the tree through a few functions:
+//!
+//! - `nodes()` lists every file and folder below the root, parents before their children.
+//! - `node(path)` and `children(path)` look up a path from the scan root, `""` being the root.
+//! - `total(nodes)` adds up the sizes of a list of nodes, and `format_size(bytes)` formats one.
+//!
+//! Each node is a map with `path`, `name`, `ext`, `dir`, `depth`, `files`, `size` (in the
+//! current size metric), `apparent`, and `allocated`. Arrays come with Rhai's `filter`,
+//! `map`, `reduce`, and `sort`, which cover most aggregates.
+//!
+//! Scripts can't touch the disk: they have no modules or file access, and their actions are
+//! only requests the app carries out once the script is done. `tag(path, "keep")` attaches
+//! a tag, and `export_csv(name, rows)` offers a list of maps to be saved as CSV. A script is
+//! stopped after a number of operations or once it runs for too long.
+
+use crate::format;
+use crate::notes::Tag;
+use crate::scanner::{FileSystemNode, SizeMetric};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map};
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The most operations a script may run.
+const MAX_OPERATIONS: u64 = 200_000_000;
+/// How long a script may run.
+const TIME_LIMIT: Duration = Duration::from_secs(60);
+
+/// A table a script offered to export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Export {
+    pub name: String,
+    pub csv: String,
+}
+
+/// What a script printed and asked for.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScriptOutput {
+    /// The lines printed with `print` and `debug`.
+    pub printed: Vec<String>,
+    /// The tags to attach, to the paths from the scan root.
+    pub tags: Vec<(Vec<String>, Tag)>,
+    pub exports: Vec<Export>,
+    /// What the script evaluated to, unless it is nothing.
+    pub result: Option<String>,
+}
+
+/// Runs `script` over `tree`, measuring sizes by `metric`. On failure, returns the error
+/// with everything printed until then.
+pub fn run(script: &str, tree: Arc<FileSystemNode>, metric: SizeMetric) -> Result<ScriptOutput, (String, Vec<String>)> {
+    let output = Arc::new(Mutex::new(ScriptOutput::default()));
+    let engine = engine(tree, metric, &output);
+    let result = engine.eval::<Dynamic>(script);
+    let mut output = std::mem::take(&mut *output.lock().unwrap_or_else(|e| e.into_inner()));
+    match result {
+        Ok(value) => {
+            output.result = Some(value).filter(|v| !v.is_unit()).map(|v| v.to_string());
+            Ok(output)
+        }
+        Err(e) => Err((e.to_string(), output.printed)),
+    }
+}
+
+/// An engine with the functions scripts see, collecting what they print and ask into `output`.
+fn engine(tree: Arc<FileSystemNode>, metric: SizeMetric, output: &Arc<Mutex<ScriptOutput>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+    engine.set_max_operations(MAX_OPERATIONS);
+    let started = Instant::now();
+    engine.on_progress(move |_| (started.elapsed() > TIME_LIMIT).then(|| Dynamic::from("the script ran for too long")));
+
+    let printed = output.clone();
+    engine.on_print(move |text| lock(&printed).printed.push(text.to_string()));
+    let printed = output.clone();
+    engine.on_debug(move |text, _, _| lock(&printed).printed.push(text.to_string()));
+
+    let all = tree.clone();
+    engine.register_fn("nodes", move || {
+        let mut nodes = Array::new();
+        collect(&all, &mut Vec::new(), metric, &mut nodes);
+        nodes
+    });
+    let lookup = tree.clone();
+    engine.register_fn("node", move |path: &str| {
+        let path = split(path);
+        lookup.find(&path).map_or(Dynamic::UNIT, |node| to_map(node, &path, metric).into())
+    });
+    let tagged = tree.clone();
+    engine.register_fn("children", move |path: &str| -> Array {
+        let path = split(path);
+        tree.find(&path).map_or_else(Array::new, |node| {
+            node.children
+                .iter()
+                .map(|child| to_map(child, &[path.clone(), vec![child.name.clone()]].concat(), metric).into())
+                .collect()
+        })
+    });
+    engine.register_fn("total", |nodes: Array| nodes.iter().filter_map(|n| n.read_lock::<Map>()?.get("size")?.as_int().ok()).sum::<i64>());
+    engine.register_fn("format_size", |bytes: i64| format::format_size(bytes.max(0) as u64));
+
+    let tags = output.clone();
+    engine.register_fn("tag", move |path: &str, tag: &str| -> Result<(), Box<EvalAltResult>> {
+        let tag = Tag::ALL.into_iter().find(|t| t.label().eq_ignore_ascii_case(tag)).ok_or_else(|| format!("there is no tag named {:?}", tag))?;
+        let path = split(path);
+        if tagged.find(&path).is_none() {
+            return Err(format!("there is nothing at {:?} in the scan", path.join("/")).into());
+        }
+        lock(&tags).tags.push((path, tag));
+        Ok(())
+    });
+    let exports = output.clone();
+    engine.register_fn("export_csv", move |name: &str, rows: Array| -> Result<(), Box<EvalAltResult>> {
+        let csv = to_csv(&rows)?;
+        lock(&exports).exports.push(Export { name: name.to_string(), csv });
+        Ok(())
+    });
+    engine
+}
+
+fn lock(output: &Mutex<ScriptOutput>) -> std::sync::MutexGuard<'_, ScriptOutput> {
+    output.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Splits a path from the scan root joined with `/` into its names.
+fn split(path: &str) -> Vec<String> {
+    path.split('/').filter(|name| !name.is_empty()).map(str::to_string).collect()
+}
+
+/// Adds the maps of every node below `node`, at `prefix` from the scan root, to `nodes`.
+fn collect(node: &FileSystemNode, prefix: &mut Vec<String>, metric: SizeMetric, nodes: &mut Array) {
+    for child in &node.children {
+        prefix.push(child.name.clone());
+        nodes.push(to_map(child, prefix, metric).into());
+        collect(child, prefix, metric, nodes);
+        prefix.pop();
+    }
+}
+
+/// The map a script sees for `node`, at `path` from the scan root.
+fn to_map(node: &FileSystemNode, path: &[String], metric: SizeMetric) -> Map {
+    let ext = node.name.rsplit_once('.').filter(|(stem, _)| !stem.is_empty() && node.children.is_empty()).map_or("", |(_, ext)| ext);
+    let mut map = Map::new();
+    map.insert("path".into(), path.join("/").into());
+    map.insert("name".into(), node.name.clone().into());
+    map.insert("ext".into(), ext.to_lowercase().into());
+    map.insert("dir".into(), (!node.children.is_empty()).into());
+    map.insert("depth".into(), (path.len() as i64).into());
+    map.insert("files".into(), (count_files(node) as i64).into());
+    map.insert("size".into(), (node.size_by(metric) as i64).into());
+    map.insert("apparent".into(), (node.size as i64).into());
+    map.insert("allocated".into(), (node.allocated as i64).into());
+    map
+}
+
+fn count_files(node: &FileSystemNode) -> u64 {
+    if node.children.is_empty() { 1 } else { node.children.iter().map(count_files).sum() }
+}
+
+/// Writes `rows`, which must be maps, as CSV, with a column for every key found in any row.
+fn to_csv(rows: &Array) -> Result<String, Box<EvalAltResult>> {
+    let rows: Vec<Map> = rows.iter().map(|row| row.clone().try_cast::<Map>().ok_or("every exported row must be a map")).collect::<Result<_, _>>()?;
+    let mut columns: Vec<&str> = rows.iter().flat_map(|row| row.keys().map(|k| k.as_str())).collect();
+    columns.sort_unstable();
+    columns.dedup();
+    let quote = |field: String| if field.contains([',', '"', '\n']) { format!("\"{}\"", field.replace('"', "\"\"")) } else { field };
+    let mut csv = columns.iter().map(|c| quote(c.to_string())).collect::<Vec<_>>().join(",");
+    csv.push('\n');
+    for row in &rows {
+        let fields: Vec<String> = columns.iter().map(|c| quote(row.get(*c).map_or_else(String::new, |v| v.to_string()))).collect();
+        let _ = writeln!(csv, "{}", fields.join(","));
+    }
+    Ok(csv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, size: u64, children: Vec<FileSystemNode>) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    #[test]
+    fn test_script_reports_tags_and_exports() {
+        let tree = Arc::new(node(
+            "root",
+            600,
+            vec![node("videos", 500, vec![node("a.mp4", 300, vec![]), node("b.MP4", 200, vec![])]), node("notes.txt", 100, vec![])],
+        ));
+        let script = r#"
+            let videos = nodes().filter(|n| n.ext == "mp4");
+            print(`${videos.len()} videos, ${total(videos)} bytes`);
+            for v in videos { if v.size > 250 { tag(v.path, "archive"); } }
+            export_csv("videos", videos.map(|n| #{ path: n.path, size: n.size }));
+            node("videos").files
+        "#;
+        let output = run(script, tree.clone(), SizeMetric::Apparent).unwrap();
+        assert_eq!(output.printed, ["2 videos, 500 bytes"]);
+        assert_eq!(output.tags, [(vec!["videos".to_string(), "a.mp4".to_string()], Tag::Archive)]);
+        assert_eq!(output.exports[0].csv, "path,size\nvideos/a.mp4,300\nvideos/b.MP4,200\n");
+        assert_eq!(output.result.as_deref(), Some("2"));
+
+        let (error, printed) = run(r#"print("before"); tag("videos", "delete")"#, tree.clone(), SizeMetric::Apparent).unwrap_err();
+        assert!(error.contains("no tag named"));
+        assert_eq!(printed, ["before"]);
+        let (error, _) = run(r#"tag("videos/c.mp4", "archive")"#, tree.clone(), SizeMetric::Apparent).unwrap_err();
+        assert!(error.contains("nothing at \"videos/c.mp4\""));
+        assert!(run(r#"import "hosts" as h;"#, tree, SizeMetric::Apparent).is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! This module implements searching the scanned tree by file name or path.
+//! It supports plain substring matching, regular expressions, and fuzzy
+//! (subsequence) matching, and ranks the results by match quality and size.
+
+use crate::scanner::FileSystemNode;
+use regex::RegexBuilder;
+
+/// The different ways a search query can be interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    /// Case-insensitive substring match against the full path.
+    Substring,
+    /// A regular expression matched against the full path.
+    Regex,
+    /// Fuzzy subsequence match against the full path.
+    Fuzzy,
+}
+
+impl SearchMode {
+    /// All modes, in the order they are offered in the UI.
+    pub const ALL: [SearchMode; 3] = [SearchMode::Substring, SearchMode::Regex, SearchMode::Fuzzy];
+
+    /// A short human-readable label for the mode.
+    pub fn label(self) -> &'static str {
+        match self {
+            SearchMode::Substring => "Substring",
+            SearchMode::Regex => "Regex",
+            SearchMode::Fuzzy => "Fuzzy",
+        }
+    }
+}
+
+/// Scores a node given its full path and its name, returning `None` if it doesn't match.
+type Matcher = dyn Fn(&str, &str) -> Option<f64>;
+
+/// A single node that matched a search query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    /// The names of the nodes leading from the scan root (exclusive) to the match (inclusive).
+    pub path: Vec<String>,
+    /// The path relative to the scan root, joined with `/`, as shown in the results list.
+    pub display_path: String,
+    /// The size of the matched node in bytes.
+    pub size: u64,
+    /// How well the query matched, from 0.0 (barely) to 1.0 (perfect).
+    pub score: f64,
+}
+
+/// Searches the tree below `root` for nodes whose path matches `query`.
+///
+/// Results are sorted by match quality first and size second, and truncated to `limit`.
+///
+/// # Returns
+///
+/// The ranked results, or a `regex::Error` if `mode` is `Regex` and the query is not a valid pattern.
+pub fn search(
+    root: &FileSystemNode,
+    query: &str,
+    mode: SearchMode,
+    limit: usize,
+) -> Result<Vec<SearchResult>, regex::Error> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let matcher: Box<Matcher> = match mode {
+        SearchMode::Substring => {
+            let needle = query.to_lowercase();
+            Box::new(move |path, name| {
+                let haystack = path.to_lowercase();
+                if !haystack.contains(&needle) {
+                    return None;
+                }
+                // Matches on the name itself rank above matches on a parent directory.
+                Some(if name.to_lowercase().contains(&needle) { 1.0 } else { 0.5 })
+            })
+        }
+        SearchMode::Regex => {
+            let re = RegexBuilder::new(query).case_insensitive(true).build()?;
+            Box::new(move |path, name| {
+                if re.is_match(name) {
+                    Some(1.0)
+                } else if re.is_match(path) {
+                    Some(0.5)
+                } else {
+                    None
+                }
+            })
+        }
+        SearchMode::Fuzzy => {
+            let needle = query.to_lowercase();
+            Box::new(move |path, _| fuzzy_score(&needle, &path.to_lowercase()))
+        }
+    };
+
+    let mut results = Vec::new();
+    let mut path = Vec::new();
+    collect_matches(&root.children, &mut path, matcher.as_ref(), &mut results);
+
+    results.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| b.size.cmp(&a.size))
+            .then_with(|| a.display_path.cmp(&b.display_path))
+    });
+    results.truncate(limit);
+    Ok(results)
+}
+
+/// Recursively walks `nodes`, pushing every match into `results`.
+fn collect_matches(
+    nodes: &[FileSystemNode],
+    path: &mut Vec<String>,
+    matcher: &Matcher,
+    results: &mut Vec<SearchResult>,
+) {
+    for node in nodes {
+        path.push(node.name.clone());
+        let display_path = path.join("/");
+        if let Some(score) = matcher(&display_path, &node.name) {
+            results.push(SearchResult {
+                path: path.clone(),
+                display_path,
+                size: node.size,
+                score,
+            });
+        }
+        collect_matches(&node.children, path, matcher, results);
+        path.pop();
+    }
+}
+
+/// Scores how well `needle` matches `haystack` as a subsequence.
+///
+/// Consecutive characters and characters at the start of a path component earn a bonus,
+/// so `"srcmain"` ranks `src/main.rs` above `scripts/run_main.sh`.
+/// Returns `None` if `needle` is not a subsequence of `haystack`.
+fn fuzzy_score(needle: &str, haystack: &str) -> Option<f64> {
+    let needle: Vec<char> = needle.chars().filter(|c| !c.is_whitespace()).collect();
+    if needle.is_empty() {
+        return None;
+    }
+
+    let mut score = 0.0;
+    let mut previous_match: Option<usize> = None;
+    let mut needle_index = 0;
+    let mut previous_char = '/';
+
+    for (i, c) in haystack.chars().enumerate() {
+        if needle_index < needle.len() && c == needle[needle_index] {
+            let mut char_score = 1.0;
+            if previous_match == Some(i.wrapping_sub(1)) {
+                char_score += 2.0;
+            }
+            if matches!(previous_char, '/' | '\\' | '_' | '-' | '.' | ' ') {
+                char_score += 1.5;
+            }
+            score += char_score;
+            previous_match = Some(i);
+            needle_index += 1;
+        }
+        previous_char = c;
+    }
+
+    if needle_index < needle.len() {
+        return None;
+    }
+
+    // Normalise against the best possible score for this needle, and slightly
+    // penalise long haystacks so tighter matches win ties.
+    let best = needle.len() as f64 * 4.5;
+    let length_penalty = 1.0 / (1.0 + haystack.len() as f64 / 100.0);
+    Some((score / best).min(1.0) * (0.8 + 0.2 * length_penalty))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaf(name: &str, size: u64) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children: vec![], special: None }
+    }
+
+    fn sample_tree() -> FileSystemNode {
+        FileSystemNode {
+            name: "root".to_string(),
+            size: 175,
+            allocated: 175,
+            children: vec![
+                FileSystemNode {
+                    name: "src".to_string(),
+                    size: 75,
+                    allocated: 75,
+                    children: vec![leaf("main.rs", 50), leaf("scanner.rs", 25)],
+                    special: None,
+                },
+                FileSystemNode {
+                    name: "scripts".to_string(),
+                    size: 100,
+                    allocated: 100,
+                    children: vec![leaf("run_main.sh", 100)],
+                    special: None,
+                },
+            ],
+            special: None,
+        }
+    }
+
+    #[test]
+    fn test_substring_search_ranks_by_size() {
+        let results = search(&sample_tree(), "main", SearchMode::Substring, 10).unwrap();
+        let paths: Vec<_> = results.iter().map(|r| r.display_path.as_str()).collect();
+        assert_eq!(paths, vec!["scripts/run_main.sh", "src/main.rs"]);
+        assert_eq!(results[1].path, vec!["src".to_string(), "main.rs".to_string()]);
+    }
+
+    #[test]
+    fn test_regex_search() {
+        let results = search(&sample_tree(), r"\.rs$", SearchMode::Regex, 10).unwrap();
+        let paths: Vec<_> = results.iter().map(|r| r.display_path.as_str()).collect();
+        assert_eq!(paths, vec!["src/main.rs", "src/scanner.rs"]);
+
+        assert!(search(&sample_tree(), "(", SearchMode::Regex, 10).is_err());
+    }
+
+    #[test]
+    fn test_fuzzy_search_prefers_tight_matches() {
+        let results = search(&sample_tree(), "srcmain", SearchMode::Fuzzy, 10).unwrap();
+        assert_eq!(results[0].display_path, "src/main.rs");
+        assert!(results.iter().all(|r| r.score > 0.0 && r.score <= 1.0));
+        assert!(search(&sample_tree(), "zzz", SearchMode::Fuzzy, 10).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,329 @@
+//! This module runs Disk Scout as an HTTP service without the GUI (`--serve <ADDR>`), so
+//! dashboards and monitoring systems can use the scanner. Scans run through a scan queue
+//! and are kept in memory until the server stops. Every endpoint answers with JSON:
+//!
+//! * `POST /scans` with `{"path": "/data"}` queues a scan and returns its `id`.
+//! * `GET /scans` lists the scans, and `GET /scans/<id>` shows one of them.
+//! * `DELETE /scans/<id>` cancels a scan.
+//! * `GET /scans/<id>/progress` streams the scan's progress as one JSON object per line
+//!   until it ends.
+//! * `GET /scans/<id>/size?path=a/b` returns the size of a folder or file in a finished scan.
+//! * `GET /scans/<id>/top?path=a/b&n=10` lists the largest items in a folder. With
+//!   `sort=name`, `sort=modified`, or `sort=none` it lists the first items in that order
+//!   instead.
+
+use crate::queue::{JobState, ScanJob, ScanQueue};
+use crate::scanner::{ChildOrder, FileSystemNode, ScanOptions, SizeMetric};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How often the progress stream reports.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+/// How many items `top` lists unless asked for another number.
+const DEFAULT_TOP: usize = 10;
+
+/// The scans of a running server.
+#[derive(Default)]
+pub struct ServerState {
+    /// The options every scan is run with, as given on the command line.
+    options: ScanOptions,
+    /// The queued, running, and ended scans.
+    queue: ScanQueue,
+    /// The trees of the finished scans, by job id.
+    trees: HashMap<u64, Arc<FileSystemNode>>,
+}
+
+impl ServerState {
+    /// Creates a server that scans with `options`.
+    pub fn new(options: ScanOptions) -> Self {
+        Self { options, ..Self::default() }
+    }
+
+    /// Picks up finished scans and starts queued ones.
+    fn poll(&mut self) {
+        for finished in self.queue.poll() {
+            self.trees.insert(finished.id, Arc::new(finished.tree));
+        }
+    }
+}
+
+/// The body of `POST /scans`.
+#[derive(Deserialize)]
+struct ScanRequest {
+    path: PathBuf,
+}
+
+/// Serves the API on `addr` until the process is stopped.
+pub fn serve(addr: &str, options: ScanOptions) -> io::Result<()> {
+    let server = tiny_http::Server::http(addr).map_err(io::Error::other)?;
+    println!("Serving on http://{}", server.server_addr());
+    let state = Arc::new(Mutex::new(ServerState::new(options)));
+    // Keep the queue moving even when no one is asking about it.
+    let poller = Arc::clone(&state);
+    thread::spawn(move || {
+        loop {
+            lock(&poller).poll();
+            thread::sleep(PROGRESS_INTERVAL);
+        }
+    });
+
+    for mut request in server.incoming_requests() {
+        let state = Arc::clone(&state);
+        // Progress streams stay open for as long as the scan runs, so each request gets a thread.
+        thread::spawn(move || {
+            let method = request.method().as_str().to_string();
+            let url = request.url().to_string();
+            if method == "GET"
+                && let Some(id) = progress_route(&url)
+            {
+                if let Err(e) = stream_progress(request.into_writer(), &state, id) {
+                    eprintln!("Progress stream of scan {} ended: {}", id, e);
+                }
+                return;
+            }
+            let mut body = String::new();
+            let (status, value) = match request.as_reader().read_to_string(&mut body) {
+                Ok(_) => route(&mut lock(&state), &method, &url, &body),
+                Err(e) => (400, error(format!("Unreadable request body: {}", e))),
+            };
+            let header = "Content-Type: application/json".parse::<tiny_http::Header>().expect("a valid header");
+            let response = tiny_http::Response::from_string(value.to_string())
+                .with_status_code(status)
+                .with_header(header);
+            if let Err(e) = request.respond(response) {
+                eprintln!("Failed to answer {} {}: {}", method, url, e);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Locks the server state. A panic in another request can't leave it half-updated in a
+/// way that matters, so a poisoned lock is used anyway.
+fn lock(state: &Mutex<ServerState>) -> std::sync::MutexGuard<'_, ServerState> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Returns the scan id if `url` is that of a progress stream.
+fn progress_route(url: &str) -> Option<u64> {
+    let (path, _) = split_url(url);
+    match path.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["scans", id, "progress"] => id.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Writes the progress of scan `id` to `writer` as an HTTP response of JSON lines, one
+/// every `PROGRESS_INTERVAL` until the scan ends. Each line is sent as a chunk of its own,
+/// so clients see it right away.
+fn stream_progress(mut writer: Box<dyn Write + Send>, state: &Mutex<ServerState>, id: u64) -> io::Result<()> {
+    let first = lock(state).queue.job(id).map(describe);
+    let Some(mut status) = first else {
+        let body = error(format!("No scan with id {}", id)).to_string();
+        write!(
+            writer,
+            "HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )?;
+        return writer.flush();
+    };
+    write!(writer, "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nTransfer-Encoding: chunked\r\n\r\n")?;
+    loop {
+        let line = format!("{}\n", status);
+        write!(writer, "{:x}\r\n{}\r\n", line.len(), line)?;
+        writer.flush()?;
+        if status["state"] != "queued" && status["state"] != "running" {
+            break;
+        }
+        thread::sleep(PROGRESS_INTERVAL);
+        let mut state = lock(state);
+        state.poll();
+        match state.queue.job(id) {
+            Some(job) => status = describe(job),
+            None => break,
+        }
+    }
+    write!(writer, "0\r\n\r\n")?;
+    writer.flush()
+}
+
+/// Answers a request other than a progress stream, returning the status code and body.
+pub fn route(state: &mut ServerState, method: &str, url: &str, body: &str) -> (u16, Value) {
+    state.poll();
+    let (path, query) = split_url(url);
+    let segments: Vec<&str> = path.iter().map(String::as_str).collect();
+    match (method, segments.as_slice()) {
+        ("GET", ["scans"]) => (200, Value::Array(state.queue.jobs().iter().map(describe).collect())),
+        ("POST", ["scans"]) => match serde_json::from_str::<ScanRequest>(body) {
+            Ok(request) => {
+                let id = state.queue.push(request.path, state.options.clone());
+                state.poll();
+                (201, json!({ "id": id }))
+            }
+            Err(e) => (400, error(format!("Expected {{\"path\": ...}}: {}", e))),
+        },
+        (method, ["scans", id, rest @ ..]) => {
+            let Some(job) = id.parse().ok().and_then(|id| state.queue.job(id)) else {
+                return (404, error(format!("No scan with id {}", id)));
+            };
+            let id = job.id;
+            match (method, rest) {
+                ("GET", []) => (200, describe(job)),
+                ("DELETE", []) => {
+                    state.queue.cancel(id);
+                    (200, describe(state.queue.job(id).expect("the job was just found")))
+                }
+                ("GET", ["size" | "top"]) => {
+                    let Some(tree) = state.trees.get(&id) else {
+                        return (409, error("The scan hasn't finished".to_string()));
+                    };
+                    let item_path: Vec<String> = query
+                        .get("path")
+                        .map(|p| p.split('/').filter(|n| !n.is_empty()).map(str::to_string).collect())
+                        .unwrap_or_default();
+                    let Some(node) = tree.find(&item_path) else {
+                        return (404, error(format!("Nothing at {:?} in the scan", item_path.join("/"))));
+                    };
+                    if rest == ["size"] {
+                        return (200, size_of(&item_path, node));
+                    }
+                    let n = query.get("n").and_then(|n| n.parse().ok()).unwrap_or(DEFAULT_TOP);
+                    let order = match query.get("sort") {
+                        None => ChildOrder::Size,
+                        Some(sort) => match ChildOrder::parse(sort) {
+                            Some(order) => order,
+                            None => return (400, error(format!("Unknown sort order {:?}", sort))),
+                        },
+                    };
+                    let dir = item_path.iter().fold(job.path.clone(), |dir, name| dir.join(name));
+                    let top = node
+                        .sorted_children(order, SizeMetric::Apparent, &dir)
+                        .into_iter()
+                        .take(n)
+                        .map(|child| {
+                            let mut path = item_path.clone();
+                            path.push(child.name.clone());
+                            size_of(&path, child)
+                        })
+                        .collect();
+                    (200, Value::Array(top))
+                }
+                _ => (404, error(format!("No endpoint {} {}", method, url))),
+            }
+        }
+        _ => (404, error(format!("No endpoint {} {}", method, url))),
+    }
+}
+
+/// Describes a scan job.
+fn describe(job: &ScanJob) -> Value {
+    let (state, message) = match &job.state {
+        JobState::Queued => ("queued", None),
+        JobState::Running => ("running", None),
+        JobState::Done => ("done", None),
+        JobState::Failed(e) => ("failed", Some(e.clone())),
+        JobState::Cancelled => ("cancelled", None),
+    };
+    json!({ "id": job.id, "path": job.path, "state": state, "found": job.found(), "error": message })
+}
+
+/// Describes the node at `path` (from the scan root).
+fn size_of(path: &[String], node: &FileSystemNode) -> Value {
+    json!({
+        "path": path.join("/"),
+        "size": node.size,
+        "allocated": node.allocated,
+        "is_dir": !node.children.is_empty(),
+        "items": node.children.len(),
+    })
+}
+
+/// Returns the JSON body of an error response.
+fn error(message: String) -> Value {
+    json!({ "error": message })
+}
+
+/// Splits `url` into its path segments and its query parameters, both percent-decoded.
+fn split_url(url: &str) -> (Vec<String>, HashMap<String, String>) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let segments = path.split('/').filter(|s| !s.is_empty()).map(decode).collect();
+    let params = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+        .filter(|(key, _)| !key.is_empty())
+        .map(|(key, value)| (decode(key), decode(value)))
+        .collect();
+    (segments, params)
+}
+
+/// Decodes `%XX` escapes and `+` (as a space) in a URL component.
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let hex = |i: usize| bytes.get(i).and_then(|b| (*b as char).to_digit(16));
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], hex(i + 1), hex(i + 2)) {
+            (b'%', Some(high), Some(low)) => {
+                decoded.push((high * 16 + low) as u8);
+                i += 2;
+            }
+            (b'+', _, _) => decoded.push(b' '),
+            (byte, _, _) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::Instant;
+
+    #[test]
+    fn test_scans_can_be_started_and_queried() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("my docs/old")).unwrap();
+        fs::write(dir.path().join("my docs/a.txt"), "a".repeat(30)).unwrap();
+        fs::write(dir.path().join("my docs/old/b.txt"), "b".repeat(20)).unwrap();
+        fs::write(dir.path().join("c.txt"), "c".repeat(5)).unwrap();
+
+        let mut state = ServerState::new(ScanOptions::default());
+        let body = json!({ "path": dir.path() }).to_string();
+        let (status, created) = route(&mut state, "POST", "/scans", &body);
+        assert_eq!(status, 201);
+        let id = created["id"].as_u64().unwrap();
+        assert_eq!(route(&mut state, "POST", "/scans", "{}").0, 400);
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while route(&mut state, "GET", &format!("/scans/{}", id), "").1["state"] != "done" {
+            assert!(Instant::now() < deadline, "the scan didn't finish");
+            thread::sleep(Duration::from_millis(5));
+        }
+        let (_, scans) = route(&mut state, "GET", "/scans", "");
+        assert_eq!(scans[0]["found"], 55);
+
+        let (status, size) = route(&mut state, "GET", &format!("/scans/{}/size?path=my%20docs", id), "");
+        assert_eq!(status, 200);
+        assert_eq!((size["path"].as_str(), size["size"].as_u64()), (Some("my docs"), Some(50)));
+
+        let (_, top) = route(&mut state, "GET", &format!("/scans/{}/top?path=my+docs&n=1", id), "");
+        assert_eq!(top, json!([{ "path": "my docs/a.txt", "size": 30, "allocated": top[0]["allocated"], "is_dir": false, "items": 0 }]));
+
+        let (_, first) = route(&mut state, "GET", &format!("/scans/{}/top?sort=name&n=1", id), "");
+        assert_eq!(first[0]["path"], "c.txt");
+        assert_eq!(route(&mut state, "GET", &format!("/scans/{}/top?sort=color", id), "").0, 400);
+        assert_eq!(route(&mut state, "GET", &format!("/scans/{}/size?path=nope", id), "").0, 404);
+        assert_eq!(route(&mut state, "GET", "/scans/99", "").0, 404);
+        assert_eq!(progress_route(&format!("/scans/{}/progress", id)), Some(id));
+    }
+}
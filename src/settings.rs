@@ -0,0 +1,179 @@
+//! This module holds the user's preferences, which are persisted between sessions
+//! using eframe's storage. They can also be exported to a settings file, to back them up or
+//! to import them on another machine.
+
+use crate::accessibility::AccessibilitySettings;
+use crate::alerts::AlertSettings;
+use crate::appearance::AppearanceSettings;
+use crate::basis::SizeBasis;
+use crate::budgets::BudgetSettings;
+use crate::compare::IgnoreRules;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::crash::CrashSettings;
+use crate::format::FormatSettings;
+use crate::hooks::HookSettings;
+use crate::palette::DepthPalette;
+use crate::scanner::ChildOrder;
+use crate::tooltip::TooltipSettings;
+use crate::touch::TouchSettings;
+use crate::treemap_style::TreemapStyle;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::workspace::WorkspaceSettings;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The key the settings are stored under in eframe's storage.
+pub const STORAGE_KEY: &str = "settings";
+
+/// What settings files say they are, to tell them from other JSON files.
+const BUNDLE_FORMAT: &str = "disk-scout-settings";
+/// The version of the settings files written. Files of later versions aren't imported.
+const BUNDLE_VERSION: u32 = 1;
+/// The extension of settings files.
+pub const BUNDLE_EXTENSION: &str = "json";
+
+/// A settings file.
+#[derive(Serialize, Deserialize)]
+struct Bundle {
+    format: String,
+    version: u32,
+    settings: Settings,
+}
+
+/// All user preferences.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// The tray icon and disk usage alerts.
+    pub alerts: AlertSettings,
+    /// What the treemap tooltips show, and when.
+    pub tooltip: TooltipSettings,
+    /// The colors of the treemap depth levels.
+    pub palette: DepthPalette,
+    /// The space budgets scans are checked against.
+    pub budgets: BudgetSettings,
+    /// The commands run after scans.
+    pub hooks: HookSettings,
+    /// Glob patterns for paths every scan skips, on top of those given on the command line.
+    pub always_exclude: Vec<String>,
+    /// Whether every finished scan is added to the history of its folder.
+    pub record_history: bool,
+    /// Whether actions that change the disk or the system are disabled.
+    pub read_only: bool,
+    /// The high-contrast treemap.
+    pub accessibility: AccessibilitySettings,
+    /// Touch mode.
+    pub touch: TouchSettings,
+    /// The interface scale and text size.
+    pub appearance: AppearanceSettings,
+    /// How the rectangles of the treemap are drawn.
+    pub treemap_style: TreemapStyle,
+    /// What shares of space are measured against.
+    pub size_basis: SizeBasis,
+    /// How sizes and numbers are written.
+    pub format: FormatSettings,
+    /// The order of the items in the list view.
+    pub list_order: ChildOrder,
+    /// The folders scanned last, most recent first.
+    pub recent_scans: Vec<PathBuf>,
+    /// Whether the tour of the treemap was taken or skipped.
+    pub tour_done: bool,
+    /// Whether the drive overview offers to read the SMART health of the disks.
+    pub disk_health: bool,
+    /// Whether a finished scan dominated by one folder opens on that folder.
+    pub focus_dominant: bool,
+    /// What comparisons of directories leave out.
+    pub compare_rules: IgnoreRules,
+    /// The groups of folders that can be switched between.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub workspaces: WorkspaceSettings,
+    /// Whether crash reports are saved, and what they hold.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub crash_reports: CrashSettings,
+    /// The folder whose search for duplicate files was stopped before it finished, if any.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unfinished_duplicates: Option<PathBuf>,
+    /// The dynamic libraries of analyzers to load at startup.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub analyzer_plugins: Vec<PathBuf>,
+    /// The script in the script console.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub console_script: String,
+}
+
+impl Settings {
+    /// Loads the settings from `storage`, falling back to the defaults.
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        storage
+            .and_then(|s| eframe::get_value(s, STORAGE_KEY))
+            .unwrap_or_default()
+    }
+
+    /// Writes the settings to `storage`.
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, STORAGE_KEY, self);
+    }
+
+    /// Writes the settings as a settings file, without what only applies to this machine.
+    pub fn export(&self) -> String {
+        let mut settings = self.clone();
+        settings.keep_local(&Settings::default());
+        let bundle = Bundle { format: BUNDLE_FORMAT.to_string(), version: BUNDLE_VERSION, settings };
+        serde_json::to_string_pretty(&bundle).expect("settings can always be written as JSON")
+    }
+
+    /// Reads the settings file `text`, keeping what only applies to this machine from these
+    /// settings. Settings the file doesn't have, e.g. because an older version wrote it, take
+    /// their defaults. The file's hooks are read like the rest, so the caller should have the
+    /// user confirm their commands before applying them.
+    pub fn import(&self, text: &str) -> Result<Settings, String> {
+        let bundle: Bundle = serde_json::from_str(text).map_err(|e| format!("not a settings file: {}", e))?;
+        if bundle.format != BUNDLE_FORMAT {
+            return Err(format!("not a settings file, but {:?}", bundle.format));
+        }
+        if bundle.version > BUNDLE_VERSION {
+            return Err(format!("written by a newer version of Disk Scout (version {} of the format)", bundle.version));
+        }
+        let mut settings = bundle.settings;
+        settings.keep_local(self);
+        Ok(settings)
+    }
+
+    /// Takes what only applies to this machine from `local`: the recent scans, the unfinished
+    /// search for duplicates, the libraries of analyzers, and which crash reports were offered.
+    fn keep_local(&mut self, local: &Settings) {
+        self.recent_scans = local.recent_scans.clone();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.unfinished_duplicates = local.unfinished_duplicates.clone();
+            self.analyzer_plugins = local.analyzer_plugins.clone();
+            self.crash_reports.offered_until = local.crash_reports.offered_until;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_and_import() {
+        let mut settings = Settings { always_exclude: vec!["**/node_modules".to_string()], read_only: true, ..Settings::default() };
+        settings.recent_scans = vec![PathBuf::from("/home/me/Videos")];
+        let text = settings.export();
+        assert!(!text.contains("Videos"));
+
+        let other = Settings { recent_scans: vec![PathBuf::from("/srv")], ..Settings::default() };
+        let imported = other.import(&text).unwrap();
+        assert_eq!(imported.always_exclude, settings.always_exclude);
+        assert!(imported.read_only);
+        assert_eq!(imported.recent_scans, other.recent_scans);
+
+        // Settings missing from the file take their defaults; other files and newer versions are refused.
+        let partial = other.import(r#"{"format": "disk-scout-settings", "version": 1, "settings": {"read_only": true}}"#).unwrap();
+        assert_eq!(partial, Settings { read_only: true, recent_scans: other.recent_scans.clone(), ..Settings::default() });
+        assert!(other.import(r#"{"format": "something-else", "version": 1, "settings": {}}"#).is_err());
+        assert!(other.import(r#"{"format": "disk-scout-settings", "version": 2, "settings": {}}"#).is_err());
+        assert!(other.import("[1, 2]").is_err());
+    }
+}
@@ -0,0 +1,349 @@
+//! This module installs and removes a "Scan with Disk Scout" entry in the file manager's
+//! context menu, so a folder can be opened in Disk Scout by right-clicking it.
+//!
+//! * On Windows, a verb is added to the `Directory` and `Directory\Background` shell keys
+//!   of the current user's registry hive.
+//! * On Linux, a script is added to Nautilus' scripts folder.
+//! * On macOS, a Quick Action (Finder service) workflow is added to `~/Library/Services`.
+//!
+//! It also opens files in the application the desktop associates with them.
+
+use crate::read_only;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The label shown in the context menu.
+pub const MENU_LABEL: &str = "Scan with Disk Scout";
+
+/// Whether the context menu entry is currently installed.
+pub fn is_installed() -> bool {
+    platform::is_installed()
+}
+
+/// Installs the context menu entry, pointing it at the currently running executable.
+pub fn install() -> io::Result<()> {
+    read_only::check("Changing the file manager menu")?;
+    let exe = std::env::current_exe()?;
+    platform::install(&exe)
+}
+
+/// Removes the context menu entry, if present.
+pub fn uninstall() -> io::Result<()> {
+    read_only::check("Changing the file manager menu")?;
+    platform::uninstall()
+}
+
+/// Opens `path` with the application the desktop associates with it.
+pub fn open_with_default_app(path: &Path) -> io::Result<()> {
+    #[cfg(windows)]
+    let opener = "explorer";
+    #[cfg(target_os = "macos")]
+    let opener = "open";
+    #[cfg(not(any(windows, target_os = "macos")))]
+    let opener = "xdg-open";
+    let mut child = std::process::Command::new(opener).arg(path).spawn()?;
+    // Wait for the opener on the side so it doesn't linger as a zombie process.
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Returns the user's home directory.
+#[cfg_attr(windows, allow(dead_code))]
+fn home_dir() -> io::Result<PathBuf> {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))
+}
+
+/// Quotes `path` for use inside a POSIX shell script.
+#[cfg_attr(windows, allow(dead_code))]
+fn shell_quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', r"'\''"))
+}
+
+/// The Nautilus script that opens every selected folder in Disk Scout.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn nautilus_script(exe: &Path) -> String {
+    format!(
+        "#!/bin/sh\n\
+         # Installed by Disk Scout. Opens the selected folder (or the current one) in Disk Scout.\n\
+         target=\"$(printf '%s' \"$NAUTILUS_SCRIPT_SELECTED_FILE_PATHS\" | head -n 1)\"\n\
+         [ -z \"$target\" ] && target=\"$1\"\n\
+         [ -z \"$target\" ] && target=\"$PWD\"\n\
+         exec {} \"$target\"\n",
+        shell_quote(exe)
+    )
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Where Nautilus looks for user scripts.
+    fn script_path() -> io::Result<PathBuf> {
+        let data_home = match std::env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => home_dir()?.join(".local").join("share"),
+        };
+        Ok(data_home.join("nautilus").join("scripts").join(MENU_LABEL))
+    }
+
+    pub fn is_installed() -> bool {
+        script_path().is_ok_and(|p| p.exists())
+    }
+
+    pub fn install(exe: &Path) -> io::Result<()> {
+        let path = script_path()?;
+        install_script(&path, exe)
+    }
+
+    pub fn uninstall() -> io::Result<()> {
+        let path = script_path()?;
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the script to `path` and makes it executable.
+    pub(super) fn install_script(path: &Path, exe: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, nautilus_script(exe))?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+    use std::fs;
+
+    /// The Quick Action bundle in the user's Services folder.
+    fn workflow_path() -> io::Result<PathBuf> {
+        Ok(home_dir()?
+            .join("Library")
+            .join("Services")
+            .join(format!("{}.workflow", MENU_LABEL)))
+    }
+
+    pub fn is_installed() -> bool {
+        workflow_path().is_ok_and(|p| p.exists())
+    }
+
+    pub fn install(exe: &Path) -> io::Result<()> {
+        let contents = workflow_path()?.join("Contents");
+        fs::create_dir_all(&contents)?;
+        fs::write(contents.join("Info.plist"), INFO_PLIST.replace("{label}", MENU_LABEL))?;
+        let command = format!("for f in \"$@\"; do {} \"$f\" & done", shell_quote(exe));
+        fs::write(contents.join("document.wflow"), DOCUMENT_WFLOW.replace("{command}", &xml_escape(&command)))?;
+        // Ask the services menu to pick up the new entry.
+        let _ = std::process::Command::new("/System/Library/CoreServices/pbs").arg("-update").status();
+        Ok(())
+    }
+
+    /// Escapes the characters that are special in XML text.
+    fn xml_escape(text: &str) -> String {
+        text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+    }
+
+    pub fn uninstall() -> io::Result<()> {
+        match fs::remove_dir_all(workflow_path()?) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Registers the workflow as a Finder service that accepts folders.
+    const INFO_PLIST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>NSServices</key>
+	<array>
+		<dict>
+			<key>NSMenuItem</key>
+			<dict>
+				<key>default</key>
+				<string>{label}</string>
+			</dict>
+			<key>NSMessage</key>
+			<string>runWorkflowAsService</string>
+			<key>NSRequiredContext</key>
+			<dict>
+				<key>NSApplicationIdentifier</key>
+				<string>com.apple.finder</string>
+			</dict>
+			<key>NSSendFileTypes</key>
+			<array>
+				<string>public.folder</string>
+			</array>
+		</dict>
+	</array>
+</dict>
+</plist>
+"#;
+
+    /// A workflow with a single "Run Shell Script" action that passes the folders as arguments.
+    const DOCUMENT_WFLOW: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>AMApplicationBuild</key>
+	<string>523</string>
+	<key>AMApplicationVersion</key>
+	<string>2.10</string>
+	<key>AMDocumentVersion</key>
+	<string>2</string>
+	<key>actions</key>
+	<array>
+		<dict>
+			<key>action</key>
+			<dict>
+				<key>AMAccepts</key>
+				<dict>
+					<key>Container</key>
+					<string>List</string>
+					<key>Optional</key>
+					<true/>
+					<key>Types</key>
+					<array>
+						<string>com.apple.cocoa.path</string>
+					</array>
+				</dict>
+				<key>AMActionVersion</key>
+				<string>2.0.3</string>
+				<key>AMParameterProperties</key>
+				<dict>
+					<key>COMMAND_STRING</key>
+					<dict/>
+					<key>inputMethod</key>
+					<dict/>
+					<key>shell</key>
+					<dict/>
+				</dict>
+				<key>ActionBundlePath</key>
+				<string>/System/Library/Automator/Run Shell Script.action</string>
+				<key>ActionName</key>
+				<string>Run Shell Script</string>
+				<key>ActionParameters</key>
+				<dict>
+					<key>COMMAND_STRING</key>
+					<string>{command}</string>
+					<key>inputMethod</key>
+					<integer>1</integer>
+					<key>shell</key>
+					<string>/bin/sh</string>
+				</dict>
+				<key>BundleIdentifier</key>
+				<string>com.apple.RunShellScript</string>
+				<key>CFBundleVersion</key>
+				<string>2.0.3</string>
+				<key>Class Name</key>
+				<string>RunShellScriptAction</string>
+			</dict>
+		</dict>
+	</array>
+	<key>workflowMetaData</key>
+	<dict>
+		<key>serviceInputTypeIdentifier</key>
+		<string>com.apple.Automator.fileSystemObject.folder</string>
+		<key>workflowTypeIdentifier</key>
+		<string>com.apple.Automator.servicesMenu</string>
+	</dict>
+</dict>
+</plist>
+"#;
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::*;
+    use std::process::Command;
+
+    /// The registry keys the verb is added under: right-clicking a folder, and
+    /// right-clicking the background of an open folder.
+    const KEYS: [(&str, &str); 2] = [
+        (r"HKCU\Software\Classes\Directory\shell\DiskScout", "%1"),
+        (r"HKCU\Software\Classes\Directory\Background\shell\DiskScout", "%V"),
+    ];
+
+    /// Runs `reg.exe` with `args`, turning a non-zero exit status into an error.
+    fn reg(args: &[&str]) -> io::Result<()> {
+        let status = Command::new("reg").args(args).status()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("reg {} failed with {}", args[0], status)))
+        }
+    }
+
+    pub fn is_installed() -> bool {
+        Command::new("reg")
+            .args(["query", KEYS[0].0])
+            .output()
+            .is_ok_and(|o| o.status.success())
+    }
+
+    pub fn install(exe: &Path) -> io::Result<()> {
+        let exe = exe.to_string_lossy();
+        for (key, placeholder) in KEYS {
+            let command = format!("\"{}\" \"{}\"", exe, placeholder);
+            reg(&["add", key, "/ve", "/d", MENU_LABEL, "/f"])?;
+            reg(&["add", key, "/v", "Icon", "/d", &exe, "/f"])?;
+            reg(&["add", &format!(r"{}\command", key), "/ve", "/d", &command, "/f"])?;
+        }
+        Ok(())
+    }
+
+    pub fn uninstall() -> io::Result<()> {
+        for (key, _) in KEYS {
+            // Deleting a key that doesn't exist fails, which is fine here.
+            let _ = reg(&["delete", key, "/f"]);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use super::*;
+
+    pub fn is_installed() -> bool {
+        false
+    }
+
+    pub fn install(_exe: &Path) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "no file manager integration on this platform"))
+    }
+
+    pub fn uninstall() -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nautilus_script_quotes_the_executable() {
+        let script = nautilus_script(Path::new("/opt/it's here/disk-scout"));
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains(r"exec '/opt/it'\''s here/disk-scout' "));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_install_script_is_executable() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scripts").join(MENU_LABEL);
+        platform::install_script(&path, Path::new("/usr/bin/disk-scout")).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o111, 0o111);
+    }
+}
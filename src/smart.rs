@@ -0,0 +1,227 @@
+//! This module reads the SMART health summary of each physical disk, so someone cleaning up
+//! a drive also notices when it is failing. On Linux and macOS it comes from `smartctl`,
+//! which has to be installed and usually needs root rights, and on Windows from the Storage
+//! module through PowerShell. Nothing is ever written to the disks.
+
+#[cfg(any(target_os = "linux", target_os = "macos", test))]
+use serde_json::Value;
+use std::io;
+#[cfg(any(target_os = "linux", target_os = "macos", windows))]
+use std::process::Command;
+
+/// Lists each physical disk's number, name, health status, temperature in degrees Celsius,
+/// and hours powered on, one disk per line with the fields separated by tabs. The counters
+/// are missing when Windows can't read them.
+#[cfg(windows)]
+const QUERY: &str = r#"
+$ErrorActionPreference = 'Stop'
+Get-PhysicalDisk | ForEach-Object {
+    $counters = $_ | Get-StorageReliabilityCounter -ErrorAction SilentlyContinue
+    "{0}`t{1}`t{2}`t{3}`t{4}" -f $_.DeviceId, $_.FriendlyName, $_.HealthStatus, $counters.Temperature, $counters.PowerOnHours
+}
+"#;
+
+/// What a disk says about its own health.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    Passed,
+    /// The disk passes, but something is worth watching, e.g. an attribute that crossed its
+    /// threshold in the past or a critical warning of an NVMe drive.
+    Warning,
+    /// The disk predicts it will fail.
+    Failing,
+    Unknown,
+}
+
+impl Health {
+    pub fn label(self) -> &'static str {
+        match self {
+            Health::Passed => "Passed",
+            Health::Warning => "Warning",
+            Health::Failing => "Failing",
+            Health::Unknown => "Unknown",
+        }
+    }
+}
+
+/// The health summary of a physical disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskHealth {
+    /// The device, e.g. `/dev/sda` or the disk number on Windows.
+    pub device: String,
+    /// The model of the disk, if known.
+    pub model: Option<String>,
+    pub health: Health,
+    /// The temperature in degrees Celsius, if known.
+    pub temperature: Option<u64>,
+    /// How many hours the disk has been powered on, if known.
+    pub power_on_hours: Option<u64>,
+}
+
+/// Reads the health summary of every physical disk `smartctl` finds.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn query() -> io::Result<Vec<DiskHealth>> {
+    let scan = run(Command::new("smartctl").args(["--scan", "--json"]))?;
+    let mut disks = Vec::new();
+    let mut failure = None;
+    for (device, kind) in parse_scan(&scan) {
+        match run(Command::new("smartctl").args(["--info", "--health", "--attributes", "--json", "-d", &kind, &device])) {
+            Ok(output) => disks.extend(parse_smartctl(&device, &output)),
+            Err(e) => failure = Some(io::Error::other(format!("{}: {}", device, e))),
+        }
+    }
+    // Some devices, like card readers, can't be asked, which is fine as long as others can.
+    match failure {
+        Some(failure) if disks.is_empty() => Err(failure),
+        _ => Ok(disks),
+    }
+}
+
+/// Reads the health summary of every physical disk Windows knows.
+#[cfg(windows)]
+pub fn query() -> io::Result<Vec<DiskHealth>> {
+    let output = Command::new("powershell").args(["-NoProfile", "-NonInteractive", "-Command", QUERY]).output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(stderr.trim().to_string()));
+    }
+    Ok(parse_windows(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// There is no way to read the disks' health on other platforms.
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn query() -> io::Result<Vec<DiskHealth>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "disk health can't be read on this platform"))
+}
+
+/// Runs `smartctl` as `command` and returns its JSON output. Its exit status is a bit mask,
+/// of which only the two lowest bits mean the command itself failed; the others report
+/// problems of the disk, which the output describes.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn run(command: &mut Command) -> io::Result<String> {
+    let output = command.output().map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => io::Error::new(e.kind(), "smartctl isn't installed; it comes with smartmontools"),
+        _ => e,
+    })?;
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    if output.status.code().is_none_or(|code| code & 0b11 != 0) {
+        let message = smartctl_message(&stdout).unwrap_or_else(|| String::from_utf8_lossy(&output.stderr).trim().to_string());
+        let message = if message.contains("Permission denied") || message.contains("Operation not permitted") {
+            "reading the disks' health needs root rights".to_string()
+        } else {
+            message
+        };
+        return Err(io::Error::other(message));
+    }
+    Ok(stdout)
+}
+
+/// The first message `smartctl` gave in its JSON `output`, e.g. why it couldn't open a device.
+#[cfg(any(target_os = "linux", target_os = "macos", test))]
+fn smartctl_message(output: &str) -> Option<String> {
+    let json: Value = serde_json::from_str(output).ok()?;
+    Some(json["smartctl"]["messages"].get(0)?["string"].as_str()?.to_string())
+}
+
+/// Parses the output of `smartctl --scan --json` into the devices and their types.
+#[cfg(any(target_os = "linux", target_os = "macos", test))]
+fn parse_scan(output: &str) -> Vec<(String, String)> {
+    let Ok(json) = serde_json::from_str::<Value>(output) else {
+        return Vec::new();
+    };
+    let Some(devices) = json["devices"].as_array() else {
+        return Vec::new();
+    };
+    devices
+        .iter()
+        .filter_map(|device| Some((device["name"].as_str()?.to_string(), device["type"].as_str()?.to_string())))
+        .collect()
+}
+
+/// Parses the JSON output of `smartctl --info --health --attributes` for `device`.
+#[cfg(any(target_os = "linux", target_os = "macos", test))]
+fn parse_smartctl(device: &str, output: &str) -> Option<DiskHealth> {
+    let json: Value = serde_json::from_str(output).ok()?;
+    let failed_before = json["ata_smart_attributes"]["table"]
+        .as_array()
+        .is_some_and(|table| table.iter().any(|a| a["when_failed"].as_str().is_some_and(|w| !w.is_empty())));
+    let critical_warning = json["nvme_smart_health_information_log"]["critical_warning"].as_u64().is_some_and(|w| w != 0);
+    let health = match json["smart_status"]["passed"].as_bool() {
+        Some(true) if failed_before || critical_warning => Health::Warning,
+        Some(true) => Health::Passed,
+        Some(false) => Health::Failing,
+        None => Health::Unknown,
+    };
+    Some(DiskHealth {
+        device: device.to_string(),
+        model: json["model_name"].as_str().map(str::to_string),
+        health,
+        temperature: json["temperature"]["current"].as_u64(),
+        power_on_hours: json["power_on_time"]["hours"].as_u64(),
+    })
+}
+
+/// Parses the output of `QUERY`. Lines that don't make sense are skipped.
+#[cfg(any(windows, test))]
+fn parse_windows(output: &str) -> Vec<DiskHealth> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.trim_end_matches('\r').split('\t').map(str::trim).collect();
+            let [device, model, health, temperature, hours] = fields[..] else {
+                return None;
+            };
+            let health = match health {
+                "Healthy" => Health::Passed,
+                "Warning" => Health::Warning,
+                "Unhealthy" => Health::Failing,
+                _ => Health::Unknown,
+            };
+            Some(DiskHealth {
+                device: device.to_string(),
+                model: Some(model.to_string()).filter(|m| !m.is_empty()),
+                health,
+                // Disks without a sensor report 0 degrees.
+                temperature: temperature.parse().ok().filter(|&t| t > 0),
+                power_on_hours: hours.parse().ok(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_smartctl() {
+        let scan = r#"{"devices": [
+            {"name": "/dev/sda", "info_name": "/dev/sda [SAT]", "type": "sat", "protocol": "ATA"},
+            {"name": "/dev/nvme0", "info_name": "/dev/nvme0", "type": "nvme", "protocol": "NVMe"}
+        ]}"#;
+        assert_eq!(parse_scan(scan), [("/dev/sda".to_string(), "sat".to_string()), ("/dev/nvme0".to_string(), "nvme".to_string())]);
+
+        let output = r#"{"model_name": "WDC WD40EFRX", "smart_status": {"passed": false},
+            "temperature": {"current": 41}, "power_on_time": {"hours": 31022}}"#;
+        let disk = parse_smartctl("/dev/sda", output).unwrap();
+        assert_eq!(disk.model.as_deref(), Some("WDC WD40EFRX"));
+        assert_eq!(disk.health, Health::Failing);
+        assert_eq!((disk.temperature, disk.power_on_hours), (Some(41), Some(31022)));
+
+        let denied = r#"{"smartctl": {"messages": [{"string": "Smartctl open device: /dev/sda failed: Permission denied", "severity": "error"}]}}"#;
+        let worn = r#"{"smart_status": {"passed": true}, "ata_smart_attributes": {"table": [
+            {"name": "Raw_Read_Error_Rate", "when_failed": ""}, {"name": "Reallocated_Sector_Ct", "when_failed": "past"}]}}"#;
+        assert_eq!(parse_smartctl("/dev/sdb", worn).unwrap().health, Health::Warning);
+        assert_eq!(parse_smartctl("/dev/sda", denied).unwrap().health, Health::Unknown);
+        assert_eq!(smartctl_message(denied).as_deref(), Some("Smartctl open device: /dev/sda failed: Permission denied"));
+    }
+
+    #[test]
+    fn test_parse_windows() {
+        let output = "0\tSamsung SSD 970\tHealthy\t38\t1200\r\n1\tST2000DM008\tUnhealthy\t0\t\r\nnonsense\n";
+        let disks = parse_windows(output);
+        assert_eq!(disks.len(), 2);
+        assert_eq!((disks[0].health, disks[0].temperature, disks[0].power_on_hours), (Health::Passed, Some(38), Some(1200)));
+        assert_eq!((disks[1].health, disks[1].temperature, disks[1].power_on_hours), (Health::Failing, None, None));
+    }
+}
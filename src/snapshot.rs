@@ -0,0 +1,343 @@
+//! This module saves scan results to disk and loads them back.
+//! A snapshot holds the scanned tree and, optionally, a content hash of every file,
+//! which allows later verifying that nothing changed.
+//!
+//! Snapshots start with a header recording the format version, the app, machine, and
+//! platform that made the scan, when, and with which scan options. The app keeps the same
+//! header for the tree it shows, so that where a tree came from stays known when it is
+//! shared between machines or compared over time. Files from older versions are converted
+//! when they are read, and fields added within a version are ignored by readers that don't
+//! know them, so only changes older readers can't cope with bump `FORMAT_VERSION`. A file
+//! from a newer format is rejected with a message saying so, rather than a parse error.
+
+use crate::format;
+use crate::notes::Notes;
+use crate::scanner::{FileSystemNode, ScanOptions};
+use crate::stats::ScanStats;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// The file extension used for snapshots.
+pub const EXTENSION: &str = "json";
+/// The version of the snapshot format written by this build. Version 1 is the format
+/// from before snapshots had a header.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Describes how and where a snapshot was made.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapshotHeader {
+    /// The version of the format the snapshot was saved in.
+    pub format_version: u32,
+    /// The version of Disk Scout that saved the snapshot. Empty if unknown.
+    pub app_version: String,
+    /// The operating system the scan was made on, e.g. `linux`. Empty if unknown.
+    pub platform: String,
+    /// The name of the machine the scan was made on. Empty if unknown.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub host: String,
+    /// When the snapshot was made, in seconds since the Unix epoch. 0 if unknown.
+    pub created_at: u64,
+    /// When the scan started, in seconds since the Unix epoch. 0 if unknown.
+    pub scan_started: u64,
+    /// When the scan finished, in seconds since the Unix epoch. 0 if unknown or still running.
+    pub scan_finished: u64,
+    /// The options the tree was scanned with, if it came from a scan.
+    pub scan_options: Option<ScanOptions>,
+    /// The statistics of the scan, if they were known when the snapshot was saved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_stats: Option<ScanStats>,
+}
+
+impl Default for SnapshotHeader {
+    /// The header of a snapshot saved before headers existed.
+    fn default() -> Self {
+        Self {
+            format_version: 1,
+            app_version: String::new(),
+            platform: String::new(),
+            host: String::new(),
+            created_at: 0,
+            scan_started: 0,
+            scan_finished: 0,
+            scan_options: None,
+            scan_stats: None,
+        }
+    }
+}
+
+impl SnapshotHeader {
+    /// The header of a snapshot made now by this build, of a tree scanned with `scan_options`.
+    pub fn new(scan_options: Option<ScanOptions>) -> Self {
+        Self {
+            format_version: FORMAT_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            platform: std::env::consts::OS.to_string(),
+            host: String::new(),
+            created_at: now(),
+            scan_started: 0,
+            scan_finished: 0,
+            scan_options,
+            scan_stats: None,
+        }
+    }
+
+    /// The header of a scan with `scan_options` starting now on this machine.
+    pub fn scan_starting(scan_options: ScanOptions) -> Self {
+        Self { host: host_name(), scan_started: now(), ..Self::new(Some(scan_options)) }
+    }
+
+    /// The header to save a snapshot of the scan with now, in the format of this build.
+    pub fn resaved(self) -> Self {
+        Self { format_version: FORMAT_VERSION, created_at: now(), ..self }
+    }
+
+    /// Notes that the scan finished now.
+    pub fn finish_scan(&mut self) {
+        self.scan_finished = now();
+    }
+
+    /// What the header tells about the scan, as labels and values, for the UI and reports.
+    /// Times are in UTC, since they may be read on another machine.
+    pub fn describe(&self) -> Vec<(&'static str, String)> {
+        let mut rows = Vec::new();
+        match (self.host.is_empty(), self.platform.is_empty()) {
+            (false, false) => rows.push(("Machine", format!("{} ({})", self.host, self.platform))),
+            (false, true) => rows.push(("Machine", self.host.clone())),
+            (true, false) => rows.push(("Platform", self.platform.clone())),
+            (true, true) => {}
+        }
+        if self.scan_started > 0 {
+            rows.push(("Started", format::format_timestamp(self.scan_started)));
+        }
+        if self.scan_finished > 0 {
+            let took = self.scan_finished.saturating_sub(self.scan_started);
+            let finished = format::format_timestamp(self.scan_finished);
+            rows.push(("Finished", if self.scan_started > 0 { format!("{}, after {} s", finished, took) } else { finished }));
+        }
+        if self.created_at > 0 && self.scan_started == 0 {
+            rows.push(("Made", format::format_timestamp(self.created_at)));
+        }
+        if !self.app_version.is_empty() {
+            rows.push(("Disk Scout", self.app_version.clone()));
+        }
+        if let Some(options) = &self.scan_options {
+            rows.push(("Options", describe_options(options)));
+        }
+        rows
+    }
+}
+
+/// Sums up the scan options that differ from the defaults.
+fn describe_options(options: &ScanOptions) -> String {
+    let defaults = ScanOptions::default();
+    let mut parts = Vec::new();
+    if let Some(depth) = options.max_depth {
+        parts.push(format!("{} levels kept", depth));
+    }
+    if !options.exclude.is_empty() {
+        parts.push(format!("skipping {}", options.exclude.join(", ")));
+    }
+    if options.one_file_system {
+        parts.push("one file system".to_string());
+    }
+    if options.include_virtual {
+        parts.push("pseudo-filesystems included".to_string());
+    }
+    for mount in &options.include_mounts {
+        parts.push(format!("{} included", mount.display()));
+    }
+    if options.max_nesting != defaults.max_nesting {
+        parts.push(format!("folders listed {} levels deep", options.max_nesting));
+    }
+    if options.count_special {
+        parts.push("special files counted".to_string());
+    }
+    if !options.follow_symlinks {
+        parts.push("links not followed".to_string());
+    }
+    if options.hash_files {
+        parts.push("files hashed".to_string());
+    }
+    if options.metric != defaults.metric {
+        parts.push(format!("shown by {}", options.metric.label().to_lowercase()));
+    }
+    if parts.is_empty() { "The defaults".to_string() } else { parts.join("; ") }
+}
+
+/// The name of this machine, or an empty string if it can't be told.
+pub fn host_name() -> String {
+    #[cfg(target_arch = "wasm32")]
+    return String::new();
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        // Windows sets COMPUTERNAME, some shells export HOSTNAME, and Linux keeps it in /proc.
+        let from_env = ["COMPUTERNAME", "HOSTNAME"].into_iter().find_map(|name| std::env::var(name).ok());
+        let name = from_env.or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok()).or_else(|| {
+            let output = std::process::Command::new("hostname").output().ok()?;
+            Some(String::from_utf8_lossy(&output.stdout).into_owned()).filter(|_| output.status.success())
+        });
+        name.map(|n| n.trim().to_string()).unwrap_or_default()
+    }
+}
+
+/// The current time in seconds since the Unix epoch. `SystemTime` isn't available in
+/// browsers, so the web build asks JavaScript.
+fn now() -> u64 {
+    #[cfg(target_arch = "wasm32")]
+    return (js_sys::Date::now() / 1000.0) as u64;
+    #[cfg(not(target_arch = "wasm32"))]
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// A saved scan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// How and where the snapshot was made.
+    #[serde(default)]
+    pub header: SnapshotHeader,
+    /// The path that was scanned.
+    pub root: PathBuf,
+    /// The scanned tree.
+    pub tree: FileSystemNode,
+    /// The content hash of every file, keyed by its path relative to `root` (joined with `/`).
+    /// `None` if hashes weren't recorded.
+    #[serde(default)]
+    pub hashes: Option<BTreeMap<String, String>>,
+    /// The tags and notes attached to nodes, keyed by their path relative to `root`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub notes: Notes,
+}
+
+impl Snapshot {
+    /// Creates a snapshot of `tree`, the contents of `root`, made now and without hashes.
+    pub fn new(root: PathBuf, tree: FileSystemNode) -> Self {
+        Self { header: SnapshotHeader::new(None), root, tree, hashes: None, notes: Notes::new() }
+    }
+}
+
+/// Writes `snapshot` to `path` as JSON.
+pub fn save(path: &Path, snapshot: &Snapshot) -> io::Result<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(writer, snapshot).map_err(io::Error::other)
+}
+
+/// Converts a parsed snapshot file, in this or an older version, into a snapshot.
+pub fn from_value(value: Value) -> io::Result<Snapshot> {
+    let version = value
+        .get("header")
+        .and_then(|h| h.get("format_version"))
+        .and_then(Value::as_u64)
+        .unwrap_or(1);
+    if version > u64::from(FORMAT_VERSION) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "The snapshot was saved in format version {}, but this version of Disk Scout only reads up to version {}. Update Disk Scout to open it.",
+                version, FORMAT_VERSION
+            ),
+        ));
+    }
+    let snapshot = serde_json::from_value(value).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(if version < 2 { upgrade_v1(snapshot) } else { snapshot })
+}
+
+/// Fills in what snapshots saved before headers existed may be missing.
+fn upgrade_v1(mut snapshot: Snapshot) -> Snapshot {
+    // Snapshots saved before disk usage was recorded have none at all; fall back to the sizes.
+    if snapshot.tree.allocated == 0 && snapshot.tree.size > 0 {
+        fill_allocated(&mut snapshot.tree);
+    }
+    snapshot
+}
+
+/// Sets the allocated size of every node to its apparent size.
+fn fill_allocated(node: &mut FileSystemNode) {
+    node.allocated = node.size;
+    node.children.iter_mut().for_each(fill_allocated);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::{Note, Tag};
+
+    fn from_slice(bytes: &[u8]) -> io::Result<Snapshot> {
+        from_value(serde_json::from_slice(bytes)?)
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.json");
+        let snapshot = Snapshot {
+            header: SnapshotHeader::new(Some(ScanOptions { one_file_system: true, ..ScanOptions::default() })),
+            root: PathBuf::from("/data"),
+            tree: FileSystemNode {
+                name: "data".to_string(),
+                size: 5,
+                allocated: 5,
+                children: vec![FileSystemNode { name: "a.txt".to_string(), size: 5, allocated: 5, children: vec![], special: None }],
+                special: None,
+            },
+            hashes: Some(BTreeMap::from([("a.txt".to_string(), "abc".to_string())])),
+            notes: Notes::from([("a.txt".to_string(), Note { tags: vec![Tag::Keep], text: String::new() })]),
+        };
+
+        save(&path, &snapshot).unwrap();
+        assert_eq!(from_slice(&std::fs::read(&path).unwrap()).unwrap(), snapshot);
+
+        assert_eq!(from_slice(b"not json").unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_header_describes_scan() {
+        let options = ScanOptions { max_depth: Some(3), exclude: vec!["*.iso".to_string()], follow_symlinks: false, ..ScanOptions::default() };
+        let header = SnapshotHeader {
+            host: "build-01".to_string(),
+            platform: "linux".to_string(),
+            app_version: "1.2.0".to_string(),
+            scan_started: 1_700_000_000,
+            scan_finished: 1_700_000_042,
+            scan_options: Some(options),
+            ..SnapshotHeader::default()
+        };
+        assert_eq!(
+            header.describe(),
+            [
+                ("Machine", "build-01 (linux)".to_string()),
+                ("Started", "2023-11-14T22:13:20Z".to_string()),
+                ("Finished", "2023-11-14T22:14:02Z, after 42 s".to_string()),
+                ("Disk Scout", "1.2.0".to_string()),
+                ("Options", "3 levels kept; skipping *.iso; links not followed".to_string()),
+            ]
+        );
+        // Where nothing is known, there is nothing to tell.
+        assert!(SnapshotHeader::default().describe().is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_versions() {
+        // Before headers, and before disk usage was recorded.
+        let v1 = br#"{"root": "/old", "tree": {"name": "old", "size": 4, "children": [{"name": "a", "size": 4, "children": []}]}}"#;
+        let snapshot = from_slice(v1).unwrap();
+        assert_eq!(snapshot.header, SnapshotHeader::default());
+        assert_eq!(snapshot.tree.children[0].allocated, 4);
+
+        // Fields this version doesn't know about are ignored.
+        let v2 = br#"{"header": {"format_version": 2, "platform": "linux", "checksum": "xyz"}, "root": "/new",
+                      "tree": {"name": "new", "size": 0, "allocated": 0, "children": [], "owner": "root"}}"#;
+        let snapshot = from_slice(v2).unwrap();
+        assert_eq!((snapshot.header.format_version, snapshot.header.platform.as_str()), (2, "linux"));
+
+        let future = br#"{"header": {"format_version": 99}, "layout": "changed"}"#;
+        let error = from_slice(future).unwrap_err();
+        assert!(error.to_string().contains("format version 99"), "{}", error);
+    }
+}
@@ -0,0 +1,141 @@
+//! This module collects statistics about a scan while it runs: how long it took, how much
+//! it visited and how fast, which errors it ran into, and which directories stood out, such
+//! as the deepest one and those that took longest to list. They are shown in the analysis
+//! panel, and saved with snapshots and sent to hooks along with the tree.
+
+use crate::aliases::Alias;
+use crate::clones::ClonedFile;
+use crate::error::ScanError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How many of the slowest directories to list are kept.
+pub const SLOWEST_COUNT: usize = 10;
+
+/// A directory and how long it took to list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlowDirectory {
+    pub path: PathBuf,
+    /// The time spent reading the directory and the metadata of its entries, in seconds,
+    /// not counting its subdirectories.
+    pub seconds: f64,
+}
+
+/// The statistics of a finished scan.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanStats {
+    /// How long the scan took, in seconds.
+    pub seconds: f64,
+    /// The number of directories visited, including the scan root.
+    pub directories: u64,
+    /// The number of files visited.
+    pub files: u64,
+    /// The apparent size of the files visited.
+    pub bytes: u64,
+    /// The number of entries that couldn't be scanned, by the category of the error.
+    pub errors: BTreeMap<String, u64>,
+    /// The most deeply nested entry, and how many levels below the scan root it is.
+    pub deepest: Option<(PathBuf, usize)>,
+    /// The directory with the most data in its own files, not counting its subdirectories,
+    /// and the size of those files.
+    pub largest_directory: Option<(PathBuf, u64)>,
+    /// The directories that took longest to list, slowest first.
+    pub slowest: Vec<SlowDirectory>,
+    /// The directories left empty because their contents were counted at other paths.
+    pub aliases: Vec<Alias>,
+    /// The files sharing blocks with clones of them, whose shared blocks are only counted once.
+    pub cloned_files: Vec<ClonedFile>,
+    /// The further hard links to files already counted at another link, which take up no
+    /// disk space of their own.
+    pub repeated_links: Vec<PathBuf>,
+    /// The first directory left out for being nested too deeply, and how many were.
+    pub too_deep: Option<(PathBuf, u64)>,
+    /// The number of sockets, FIFOs, and devices visited, which are among `files`.
+    pub special_files: u64,
+    /// Whether the volume being scanned went away, e.g. a drive that was ejected, so the
+    /// scan stopped early and the tree only holds what was read before.
+    pub volume_lost: bool,
+}
+
+impl ScanStats {
+    /// The apparent size of the files visited per second of the scan.
+    pub fn bytes_per_second(&self) -> u64 {
+        if self.seconds > 0.0 { (self.bytes as f64 / self.seconds) as u64 } else { 0 }
+    }
+
+    /// The number of entries that couldn't be scanned.
+    pub fn error_count(&self) -> u64 {
+        self.errors.values().sum()
+    }
+
+    /// Records a file of `size` bytes at `depth` levels below the scan root.
+    pub fn record_file(&mut self, path: &Path, depth: usize, size: u64) {
+        self.files += 1;
+        self.bytes += size;
+        self.record_depth(path, depth);
+    }
+
+    /// Records a directory at `depth` levels below the scan root whose own files hold
+    /// `own_bytes`, and which took `listing` to list.
+    pub fn record_directory(&mut self, path: &Path, depth: usize, own_bytes: u64, listing: Duration) {
+        self.directories += 1;
+        self.record_depth(path, depth);
+        // Equal sizes go to the first path in order, whatever order the scan took.
+        if self.largest_directory.as_ref().is_none_or(|(p, size)| own_bytes > *size || (own_bytes == *size && path < p.as_path())) {
+            self.largest_directory = Some((path.to_path_buf(), own_bytes));
+        }
+        let seconds = listing.as_secs_f64();
+        if self.slowest.len() < SLOWEST_COUNT || self.slowest.last().is_some_and(|s| seconds > s.seconds) {
+            let at = self.slowest.partition_point(|s| s.seconds >= seconds);
+            self.slowest.insert(at, SlowDirectory { path: path.to_path_buf(), seconds });
+            self.slowest.truncate(SLOWEST_COUNT);
+        }
+    }
+
+    /// Records an entry that couldn't be scanned because of `error`.
+    pub fn record_error(&mut self, error: &ScanError) {
+        *self.errors.entry(error.category().to_string()).or_default() += 1;
+        if let ScanError::TooDeep(path) = error {
+            self.too_deep.get_or_insert_with(|| (path.clone(), 0)).1 += 1;
+        }
+    }
+
+    fn record_depth(&mut self, path: &Path, depth: usize) {
+        if self.deepest.as_ref().is_none_or(|(_, deepest)| depth > *deepest) {
+            self.deepest = Some((path.to_path_buf(), depth));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    #[test]
+    fn test_stats_keep_the_extremes() {
+        let mut stats = ScanStats::default();
+        for i in 0..20u64 {
+            let dir = PathBuf::from(format!("/data/{}", i));
+            stats.record_file(&dir.join("file"), 2, 100);
+            stats.record_directory(&dir, 1, i * 10, Duration::from_millis(i));
+        }
+        stats.record_file(Path::new("/data/3/a/b/c"), 4, 50);
+        stats.record_error(&ScanError::from_io(Path::new("/data/x"), io::Error::from(io::ErrorKind::PermissionDenied)));
+        stats.record_error(&ScanError::from_io(Path::new("/data/y"), io::Error::from(io::ErrorKind::PermissionDenied)));
+        stats.seconds = 2.0;
+
+        assert_eq!((stats.directories, stats.files, stats.bytes), (20, 21, 2050));
+        assert_eq!(stats.bytes_per_second(), 1025);
+        assert_eq!(stats.error_count(), 2);
+        assert_eq!(stats.errors["Permission denied"], 2);
+        assert_eq!(stats.deepest, Some((PathBuf::from("/data/3/a/b/c"), 4)));
+        assert_eq!(stats.largest_directory, Some((PathBuf::from("/data/19"), 190)));
+        let slowest: Vec<_> = stats.slowest.iter().map(|s| s.path.clone()).collect();
+        let expected: Vec<_> = (10..20).rev().map(|i| PathBuf::from(format!("/data/{}", i))).collect();
+        assert_eq!(slowest, expected);
+    }
+}
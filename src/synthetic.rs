@@ -0,0 +1,243 @@
+//! This module generates synthetic file trees: the same seed always gives the same tree, and
+//! the depth, the number of items in each folder, and the file sizes follow distributions
+//! that can be tuned. The trees can be kept in memory or written to disk (to tmpfs where
+//! there is one) for the scanner to read back. Tests and benchmarks use them, and so does
+//! the demo mode (`--demo`), which shows the app without scanning a real disk.
+
+use crate::scanner::FileSystemNode;
+use std::collections::{HashSet, VecDeque};
+use std::fs::{self, File};
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+/// The block size allocated sizes are rounded up to.
+const BLOCK: u64 = 4096;
+
+/// Names given to folders, numbered when a folder holds several with the same name.
+const FOLDER_NAMES: [&str; 16] = [
+    "Documents", "Photos", "Videos", "Music", "Downloads", "projects", "src", "build", "node_modules", "target",
+    "cache", "logs", "backup", "archive", "assets", "data",
+];
+
+/// Names given to files, with an extension picked by their size.
+const FILE_NAMES: [&str; 12] =
+    ["report", "holiday", "notes", "index", "main", "config", "export", "recording", "setup", "draft", "scan", "image"];
+
+/// Extensions of files up to 64 KiB, up to 16 MiB, and larger.
+const EXTENSIONS: [[&str; 4]; 3] =
+    [["txt", "rs", "json", "md"], ["jpg", "pdf", "png", "mp3"], ["mp4", "iso", "zip", "mkv"]];
+
+/// What a synthetic tree looks like.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spec {
+    /// The seed of the generator. The same spec always gives the same tree.
+    pub seed: u64,
+    /// The number of nodes, the root included. The tree is smaller if `max_depth` runs out first.
+    pub nodes: usize,
+    /// How many levels of folders there can be below the root.
+    pub max_depth: usize,
+    /// How many items a folder holds, picked uniformly in this range.
+    pub fan_out: RangeInclusive<usize>,
+    /// The share of items that are folders, from 0.0 to 1.0, above `max_depth`.
+    pub folder_share: f64,
+    /// The range of file sizes in bytes. Each power of two in it is as likely, so there
+    /// are many small files and a few large ones, as on real disks.
+    pub file_size: RangeInclusive<u64>,
+}
+
+impl Default for Spec {
+    fn default() -> Self {
+        Self { seed: 1, nodes: 10_000, max_depth: 32, fan_out: 2..=12, folder_share: 0.25, file_size: 1..=1 << 20 }
+    }
+}
+
+impl Spec {
+    /// The tree shown in demo mode: a home folder of a few thousand items, some of them large.
+    pub fn demo() -> Self {
+        Self { seed: 2024, nodes: 4000, max_depth: 8, fan_out: 3..=14, folder_share: 0.3, file_size: 512..=8 << 30 }
+    }
+}
+
+/// A small pseudo-random generator (xorshift), so trees don't depend on a seed source.
+struct Random(u64);
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        // Xorshift never leaves zero, and nearby seeds should still give unrelated trees.
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number in `range`, roughly uniformly.
+    fn in_range(&mut self, range: RangeInclusive<u64>) -> u64 {
+        let (start, end) = (*range.start(), *range.end().max(range.start()));
+        start + self.next() % (end - start).saturating_add(1).max(1)
+    }
+
+    /// Whether an event with `probability` happens.
+    fn chance(&mut self, probability: f64) -> bool {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64 <= probability
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.next() as usize % items.len()]
+    }
+}
+
+/// A node of the tree being generated, before the sizes of the folders are added up.
+struct Entry {
+    name: String,
+    size: u64,
+    children: Vec<usize>,
+}
+
+/// Generates the tree described by `spec`. Its root is named "root".
+pub fn generate(spec: &Spec) -> FileSystemNode {
+    let mut random = Random::new(spec.seed);
+    let mut entries = vec![Entry { name: "root".to_string(), size: 0, children: Vec::new() }];
+    // Folders waiting to be filled, with their depth.
+    let mut folders = VecDeque::from([(0, 0)]);
+    while entries.len() < spec.nodes {
+        let Some((folder, depth)) = folders.pop_front() else {
+            break;
+        };
+        let fan_out = random.in_range(*spec.fan_out.start() as u64..=*spec.fan_out.end() as u64).max(1);
+        let mut names = HashSet::new();
+        for i in 0..fan_out {
+            if entries.len() >= spec.nodes {
+                break;
+            }
+            // The last item is a folder whenever nothing else is left to fill.
+            let may_nest = depth < spec.max_depth;
+            let is_folder = may_nest && (random.chance(spec.folder_share) || (i == fan_out - 1 && folders.is_empty()));
+            let (base, size) = if is_folder {
+                (random.pick(&FOLDER_NAMES).to_string(), 0)
+            } else {
+                let size = file_size(&mut random, &spec.file_size);
+                let class = match size {
+                    0..=0xFFFF => 0,
+                    0x1_0000..=0xFF_FFFF => 1,
+                    _ => 2,
+                };
+                (format!("{}.{}", random.pick(&FILE_NAMES), random.pick(&EXTENSIONS[class])), size)
+            };
+            let mut name = base.clone();
+            for n in 2.. {
+                if names.insert(name.clone()) {
+                    break;
+                }
+                name = match base.split_once('.') {
+                    Some((stem, extension)) => format!("{} ({}).{}", stem, n, extension),
+                    None => format!("{} ({})", base, n),
+                };
+            }
+            let index = entries.len();
+            if is_folder {
+                folders.push_back((index, depth + 1));
+            }
+            entries[folder].children.push(index);
+            entries.push(Entry { name, size, children: Vec::new() });
+        }
+    }
+    assemble(&entries, 0)
+}
+
+/// Picks a file size: a power of two in `range`, then a size within it.
+fn file_size(random: &mut Random, range: &RangeInclusive<u64>) -> u64 {
+    let (min, max) = (*range.start().max(&1), *range.end().max(range.start()).max(&1));
+    let power = random.in_range(u64::from(min.ilog2())..=u64::from(max.ilog2()));
+    let low = (1u64 << power).max(min);
+    let high = (1u64 << power).saturating_mul(2).saturating_sub(1).min(max);
+    random.in_range(low..=high.max(low))
+}
+
+/// Builds the node of `entries[index]`, adding up the sizes of folders.
+fn assemble(entries: &[Entry], index: usize) -> FileSystemNode {
+    let entry = &entries[index];
+    let children: Vec<FileSystemNode> = entry.children.iter().map(|&c| assemble(entries, c)).collect();
+    if children.is_empty() && entry.size > 0 {
+        let allocated = entry.size.div_ceil(BLOCK) * BLOCK;
+        return FileSystemNode { name: entry.name.clone(), size: entry.size, allocated, children, special: None };
+    }
+    let size = children.iter().map(|c| c.size).sum();
+    let allocated = children.iter().map(|c| c.allocated).sum();
+    FileSystemNode { name: entry.name.clone(), size, allocated, children, special: None }
+}
+
+/// Writes the children of `tree` into `dir`: folders as folders, and files as sparse files
+/// of their size, so large trees are quick to write and take up no space.
+pub fn write(dir: &Path, tree: &FileSystemNode) -> io::Result<()> {
+    for child in &tree.children {
+        let path = dir.join(&child.name);
+        if child.children.is_empty() && child.size > 0 {
+            File::create(&path)?.set_len(child.size)?;
+        } else {
+            fs::create_dir(&path)?;
+            write(&path, child)?;
+        }
+    }
+    Ok(())
+}
+
+/// Creates an empty temporary folder, in memory (on tmpfs) where the system has one, so
+/// the speed of the disk doesn't skew measurements.
+pub fn scratch_dir() -> io::Result<tempfile::TempDir> {
+    let shm = Path::new("/dev/shm");
+    if shm.is_dir()
+        && let Ok(dir) = tempfile::tempdir_in(shm)
+    {
+        return Ok(dir);
+    }
+    tempfile::tempdir()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{self, ScanOptions};
+
+    fn count(node: &FileSystemNode) -> usize {
+        1 + node.children.iter().map(count).sum::<usize>()
+    }
+
+    fn depth(node: &FileSystemNode) -> usize {
+        node.children.iter().map(|c| 1 + depth(c)).max().unwrap_or(0)
+    }
+
+    fn files(node: &FileSystemNode) -> Vec<u64> {
+        if node.children.is_empty() {
+            return vec![node.size];
+        }
+        node.children.iter().flat_map(files).collect()
+    }
+
+    #[test]
+    fn test_synthetic_trees_follow_their_spec_and_scan_back() {
+        let spec = Spec { nodes: 800, max_depth: 4, fan_out: 5..=12, folder_share: 0.5, file_size: 100..=5000, ..Spec::default() };
+        let tree = generate(&spec);
+        assert_eq!(count(&tree), 800);
+        assert_eq!(tree, generate(&spec));
+        assert_ne!(tree, generate(&Spec { seed: 2, ..spec.clone() }));
+        assert!(depth(&tree) <= 4 + 1);
+        // Folders left empty are generated too, so only look at sized files.
+        assert!(files(&tree).iter().filter(|&&s| s > 0).all(|s| (100..=5000).contains(s)));
+        assert_eq!(tree.size, tree.children.iter().map(|c| c.size).sum::<u64>());
+
+        // Without room for folders, the tree stops at the root's items.
+        let flat = generate(&Spec { max_depth: 0, fan_out: 5..=5, ..Spec::default() });
+        assert_eq!(count(&flat), 6);
+
+        let dir = scratch_dir().unwrap();
+        write(dir.path(), &tree).unwrap();
+        let scanned = scanner::build_tree(dir.path(), &ScanOptions::default()).unwrap();
+        assert_eq!(scanned.size, tree.size);
+        assert_eq!(count(&scanned), count(&tree));
+    }
+}
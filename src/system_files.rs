@@ -0,0 +1,171 @@
+//! This module explains the large items Windows keeps at the top of each drive, which
+//! confuse users scanning C:: the Recycle Bin, the page file, the hibernation file, and the
+//! swap file. They are hidden, protected, and often among the biggest items on the drive, yet
+//! none of them should be deleted by hand. The drive overview lists them with what they are
+//! for and a link to where Windows lets them be emptied, shrunk, or turned off, and their
+//! tooltips in the treemap say the same.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// An item Windows keeps at the top of a drive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemFile {
+    RecycleBin,
+    PageFile,
+    Hibernation,
+    SwapFile,
+}
+
+impl SystemFile {
+    pub const ALL: [Self; 4] = [Self::RecycleBin, Self::PageFile, Self::Hibernation, Self::SwapFile];
+
+    /// Its name at the top of the drive.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::RecycleBin => "$Recycle.Bin",
+            Self::PageFile => "pagefile.sys",
+            Self::Hibernation => "hiberfil.sys",
+            Self::SwapFile => "swapfile.sys",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::RecycleBin => "Recycle Bin",
+            Self::PageFile => "Page file",
+            Self::Hibernation => "Hibernation file",
+            Self::SwapFile => "Swap file",
+        }
+    }
+
+    /// What it is for and how to reclaim its space safely.
+    pub fn explanation(self) -> &'static str {
+        match self {
+            Self::RecycleBin => {
+                "The Recycle Bin of this drive, holding what every user deleted from it. Empty it from the \
+                 Recycle Bin, or let Storage Sense empty it regularly."
+            }
+            Self::PageFile => {
+                "The page file, where Windows moves memory that isn't in use. Windows sizes it itself; it can be \
+                 limited or moved to another drive in the virtual memory settings, but not deleted."
+            }
+            Self::Hibernation => {
+                "The hibernation file, which keeps the memory while the computer hibernates and for Fast Startup. \
+                 Running `powercfg /hibernate off` as administrator removes it, along with hibernation and Fast Startup."
+            }
+            Self::SwapFile => {
+                "The swap file of apps from the Microsoft Store. Windows manages it with the page file and \
+                 removes it when the page file is turned off."
+            }
+        }
+    }
+
+    /// The label of the link to where Windows manages it.
+    pub fn setting(self) -> &'static str {
+        match self {
+            Self::RecycleBin => "Open the Recycle Bin",
+            Self::PageFile | Self::SwapFile => "Virtual memory settings",
+            Self::Hibernation => "Power settings",
+        }
+    }
+
+    /// The item named `name` at the top of a drive, if it is one of these.
+    pub fn recognize(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// An item Windows keeps at the top of a drive, found on one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Found {
+    /// The drive, e.g. `C:\`.
+    pub volume: PathBuf,
+    pub file: SystemFile,
+    /// The size of the item, or of the parts of the Recycle Bin that could be read.
+    pub size: u64,
+    /// Whether some of the Recycle Bin couldn't be read, e.g. the bins of other users.
+    pub partial: bool,
+}
+
+/// Looks for the items Windows keeps at the top of the drive at `volume`.
+pub fn find(volume: &Path) -> Vec<Found> {
+    let Ok(entries) = fs::read_dir(volume) else {
+        return Vec::new();
+    };
+    let mut found: Vec<Found> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file = SystemFile::recognize(&entry.file_name().to_string_lossy())?;
+            // The listing has the sizes, which is as well, since these files are locked.
+            let metadata = entry.metadata().ok()?;
+            let (size, partial) = if metadata.is_dir() { folder_size(&entry.path()) } else { (metadata.len(), false) };
+            Some(Found { volume: volume.to_path_buf(), file, size, partial })
+        })
+        .collect();
+    found.sort_by_key(|f| SystemFile::ALL.iter().position(|&k| k == f.file));
+    found
+}
+
+/// The size of the files in the folder at `path`, and whether any of it couldn't be read.
+fn folder_size(path: &Path) -> (u64, bool) {
+    let Ok(entries) = fs::read_dir(path) else {
+        return (0, true);
+    };
+    let (mut size, mut partial) = (0, false);
+    for entry in entries {
+        let Some((entry, metadata)) = entry.ok().and_then(|e| e.metadata().ok().map(|m| (e, m))) else {
+            partial = true;
+            continue;
+        };
+        if metadata.is_dir() {
+            let (inner, inner_partial) = folder_size(&entry.path());
+            size += inner;
+            partial |= inner_partial;
+        } else if !metadata.is_symlink() {
+            size += metadata.len();
+        }
+    }
+    (size, partial)
+}
+
+/// Opens where Windows manages `file`. Nothing is changed until the user does so there.
+#[cfg(windows)]
+pub fn open_setting(file: SystemFile) -> io::Result<()> {
+    use std::process::Command;
+    let mut child = match file {
+        SystemFile::RecycleBin => Command::new("explorer").arg("shell:RecycleBinFolder").spawn()?,
+        SystemFile::PageFile | SystemFile::SwapFile => Command::new("SystemPropertiesPerformance.exe").spawn()?,
+        SystemFile::Hibernation => Command::new("explorer").arg("ms-settings:powersleep").spawn()?,
+    };
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// These settings only exist on Windows.
+#[cfg(not(windows))]
+pub fn open_setting(_file: SystemFile) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "these settings only exist on Windows"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_system_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("PAGEFILE.SYS"), vec![0; 300]).unwrap();
+        fs::write(dir.path().join("notes.txt"), "hello").unwrap();
+        let bin = dir.path().join("$Recycle.Bin").join("S-1-5-21-1000");
+        fs::create_dir_all(&bin).unwrap();
+        fs::write(bin.join("$RABC123.iso"), vec![0; 1000]).unwrap();
+        fs::write(bin.join("$IABC123.iso"), vec![0; 50]).unwrap();
+
+        let found: Vec<(SystemFile, u64, bool)> = find(dir.path()).into_iter().map(|f| (f.file, f.size, f.partial)).collect();
+        assert_eq!(found, [(SystemFile::RecycleBin, 1050, false), (SystemFile::PageFile, 300, false)]);
+        assert_eq!(SystemFile::recognize("HiberFil.sys"), Some(SystemFile::Hibernation));
+        assert_eq!(SystemFile::recognize("hiberfil.sys.bak"), None);
+    }
+}
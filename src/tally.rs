@@ -0,0 +1,124 @@
+//! This module adds up nodes ticked across different branches of the tree, to plan moving
+//! them to another disk. A node ticked inside a ticked folder is already part of it and
+//! isn't counted twice.
+
+use crate::scanner::{FileSystemNode, SizeMetric};
+
+/// The combined size and contents of the ticked nodes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Tally {
+    /// The combined size, by the metric it was tallied with.
+    pub size: u64,
+    /// The number of files.
+    pub files: u64,
+    /// The number of folders, including the ticked ones.
+    pub folders: u64,
+}
+
+impl Tally {
+    /// Returns the share of a volume of `total` bytes the nodes take up, in percent.
+    pub fn percent_of(&self, total: u64) -> Option<f64> {
+        (total > 0).then(|| self.size as f64 * 100.0 / total as f64)
+    }
+}
+
+/// A tally kept along with what it was made from, so it is only made again when that changes.
+#[derive(Debug, Default)]
+pub struct CachedTally {
+    /// The ticked paths, metric, and tree generation the tally was made for.
+    made_for: Option<(Vec<Vec<String>>, SizeMetric, u64)>,
+    tally: Tally,
+}
+
+impl CachedTally {
+    /// The tally of `ticked` in `tree`, the tree of `generation`, sized by `metric`.
+    pub fn get(&mut self, tree: &FileSystemNode, generation: u64, ticked: &[Vec<String>], metric: SizeMetric) -> Tally {
+        let fresh = self.made_for.as_ref().is_some_and(|(t, m, g)| t == ticked && *m == metric && *g == generation);
+        if !fresh {
+            self.tally = tally(tree, ticked, metric);
+            self.made_for = Some((ticked.to_vec(), metric, generation));
+        }
+        self.tally
+    }
+}
+
+/// Ticks the node at `path`, or unticks it if it was ticked.
+pub fn toggle(ticked: &mut Vec<Vec<String>>, path: &[String]) {
+    match ticked.iter().position(|p| p == path) {
+        Some(i) => {
+            ticked.remove(i);
+        }
+        None => ticked.push(path.to_vec()),
+    }
+}
+
+/// Adds up the nodes of `tree` at the `ticked` paths, sized by `metric`. Paths that are no
+/// longer in the tree are skipped.
+pub fn tally(tree: &FileSystemNode, ticked: &[Vec<String>], metric: SizeMetric) -> Tally {
+    let mut tally = Tally::default();
+    for path in ticked {
+        let inside_ticked = ticked.iter().any(|other| other.len() < path.len() && path.starts_with(other));
+        if inside_ticked {
+            continue;
+        }
+        let Some(node) = tree.find(path) else {
+            continue;
+        };
+        tally.size += node.size_by(metric);
+        count(node, &mut tally);
+    }
+    tally
+}
+
+/// Counts the files and folders of `node`, itself included.
+fn count(node: &FileSystemNode, tally: &mut Tally) {
+    if node.children.is_empty() {
+        tally.files += 1;
+        return;
+    }
+    tally.folders += 1;
+    for child in &node.children {
+        count(child, tally);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, size: u64, children: Vec<FileSystemNode>) -> FileSystemNode {
+        FileSystemNode { name: name.to_string(), size, allocated: size, children, special: None }
+    }
+
+    #[test]
+    fn test_ticked_nodes_are_added_up_once() {
+        let tree = node(
+            "home",
+            100,
+            vec![
+                node("videos", 70, vec![node("a.mp4", 40, vec![]), node("old", 30, vec![node("b.mp4", 30, vec![])])]),
+                node("music", 20, vec![node("c.flac", 20, vec![])]),
+                node("notes.txt", 10, vec![]),
+            ],
+        );
+        let path = |p: &str| p.split('/').map(str::to_string).collect::<Vec<_>>();
+        let mut ticked = Vec::new();
+        for p in ["videos", "videos/old/b.mp4", "notes.txt", "gone"] {
+            toggle(&mut ticked, &path(p));
+        }
+        let total = tally(&tree, &ticked, SizeMetric::Apparent);
+        assert_eq!(total, Tally { size: 80, files: 3, folders: 2 });
+        assert_eq!(total.percent_of(400), Some(20.0));
+        assert_eq!(total.percent_of(0), None);
+
+        toggle(&mut ticked, &path("videos"));
+        let total = tally(&tree, &ticked, SizeMetric::Apparent);
+        assert_eq!(total, Tally { size: 40, files: 2, folders: 0 });
+
+        let mut cached = CachedTally::default();
+        assert_eq!(cached.get(&tree, 1, &ticked, SizeMetric::Apparent), total);
+        let empty = node("home", 0, vec![]);
+        assert_eq!(cached.get(&empty, 1, &ticked, SizeMetric::Apparent), total);
+        assert_eq!(cached.get(&empty, 2, &ticked, SizeMetric::Apparent), Tally::default());
+    }
+}
@@ -0,0 +1,267 @@
+//! This module shows small previews of image files in tooltips and in the selection panel,
+//! so users can tell what an image is without opening it. Images are decoded and scaled on
+//! a few background threads, and the most recently used thumbnails are kept as textures.
+//! Moving the pointer over many images only decodes the last few of them: requests that
+//! haven't started yet are dropped when newer ones come in.
+
+use eframe::egui;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+/// The longest side of a thumbnail, in pixels.
+const THUMBNAIL_SIZE: u32 = 128;
+/// How many thumbnails are kept.
+const CACHE_CAPACITY: usize = 64;
+/// How many threads decode images.
+const WORKERS: usize = 2;
+/// How many images can wait to be decoded. The oldest requests are dropped past this.
+const MAX_PENDING: usize = 4;
+/// Files larger than this aren't decoded, since that would take too long and too much memory.
+const MAX_FILE_SIZE: u64 = 64 << 20;
+/// The extensions of the image formats that can be previewed.
+const EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "gif", "webp", "bmp", "ico"];
+
+/// Tells whether the file at `path` is an image that can be previewed, by its extension.
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// The preview of an image file.
+#[derive(Clone)]
+pub enum Thumbnail {
+    /// The image is being decoded.
+    Loading,
+    /// The thumbnail, and the dimensions of the full image.
+    Ready { texture: egui::TextureHandle, width: u32, height: u32 },
+    /// The image couldn't be previewed.
+    Failed(String),
+}
+
+/// A decoded and scaled image, or why it couldn't be.
+type Decoded = Result<(egui::ColorImage, u32, u32), String>;
+
+/// The images waiting to be decoded, shared with the workers.
+#[derive(Default)]
+struct Requests {
+    pending: Mutex<Pending>,
+    /// Wakes up the workers when an image is requested or the cache is dropped.
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct Pending {
+    /// The paths of the images to decode, oldest request first.
+    paths: VecDeque<PathBuf>,
+    /// Whether the cache was dropped, so the workers should stop.
+    closed: bool,
+}
+
+impl Requests {
+    /// Waits for the newest request, or `None` once the cache is dropped.
+    fn next(&self) -> Option<PathBuf> {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if pending.closed {
+                return None;
+            }
+            if let Some(path) = pending.paths.pop_back() {
+                return Some(path);
+            }
+            pending = self.changed.wait(pending).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+/// The thumbnails of the most recently previewed images.
+pub struct ThumbnailCache {
+    thumbnails: HashMap<PathBuf, Thumbnail>,
+    /// The previewed paths, least recently used first.
+    order: VecDeque<PathBuf>,
+    requests: Arc<Requests>,
+    /// Whether the workers were started, which happens on the first request.
+    started: bool,
+    sender: Sender<(PathBuf, Decoded)>,
+    receiver: Receiver<(PathBuf, Decoded)>,
+}
+
+impl Default for ThumbnailCache {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            thumbnails: HashMap::new(),
+            order: VecDeque::new(),
+            requests: Arc::default(),
+            started: false,
+            sender,
+            receiver,
+        }
+    }
+}
+
+impl Drop for ThumbnailCache {
+    fn drop(&mut self) {
+        self.requests.pending.lock().unwrap_or_else(|e| e.into_inner()).closed = true;
+        self.requests.changed.notify_all();
+    }
+}
+
+impl ThumbnailCache {
+    /// Returns the thumbnail of the image at `path`, starting to decode it if it isn't cached.
+    pub fn get(&mut self, ctx: &egui::Context, path: &Path) -> Thumbnail {
+        while let Ok((decoded_path, decoded)) = self.receiver.try_recv() {
+            // Thumbnails evicted while they were decoded are dropped.
+            if let Some(thumbnail) = self.thumbnails.get_mut(&decoded_path) {
+                *thumbnail = match decoded {
+                    Ok((image, width, height)) => {
+                        let name = decoded_path.to_string_lossy();
+                        let texture = ctx.load_texture(name, image, egui::TextureOptions::LINEAR);
+                        Thumbnail::Ready { texture, width, height }
+                    }
+                    Err(e) => Thumbnail::Failed(e),
+                };
+            }
+        }
+
+        if let Some(position) = self.order.iter().position(|p| p == path) {
+            let recent = self.order.remove(position).unwrap_or_else(|| path.to_path_buf());
+            self.order.push_back(recent);
+            return self.thumbnails[path].clone();
+        }
+        if !self.started {
+            self.start_workers(ctx);
+        }
+        self.request(path);
+        Thumbnail::Loading
+    }
+
+    /// Starts the threads that decode the requested images.
+    fn start_workers(&mut self, ctx: &egui::Context) {
+        self.started = true;
+        for _ in 0..WORKERS {
+            let (requests, sender, ctx) = (self.requests.clone(), self.sender.clone(), ctx.clone());
+            thread::spawn(move || {
+                while let Some(path) = requests.next() {
+                    let decoded = decode(&path);
+                    if sender.send((path, decoded)).is_err() {
+                        break;
+                    }
+                    ctx.request_repaint();
+                }
+            });
+        }
+    }
+
+    /// Queues the image at `path` to be decoded. The images requested last are the ones under
+    /// the pointer now, so the oldest requests that haven't started are dropped past
+    /// `MAX_PENDING`, and requested again if they are still shown.
+    fn request(&mut self, path: &Path) {
+        self.insert(path.to_path_buf(), Thumbnail::Loading);
+        let requests = self.requests.clone();
+        let mut pending = requests.pending.lock().unwrap_or_else(|e| e.into_inner());
+        pending.paths.push_back(path.to_path_buf());
+        while pending.paths.len() > MAX_PENDING {
+            if let Some(stale) = pending.paths.pop_front() {
+                self.thumbnails.remove(&stale);
+                self.order.retain(|p| *p != stale);
+            }
+        }
+        requests.changed.notify_one();
+    }
+
+    /// Caches `thumbnail` as the most recently used, evicting the least recently used one past
+    /// `CACHE_CAPACITY`.
+    fn insert(&mut self, path: PathBuf, thumbnail: Thumbnail) {
+        self.thumbnails.insert(path.clone(), thumbnail);
+        self.order.push_back(path);
+        if self.order.len() > CACHE_CAPACITY
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.thumbnails.remove(&oldest);
+        }
+    }
+}
+
+/// Decodes the image at `path` and scales it down to a thumbnail.
+fn decode(path: &Path) -> Decoded {
+    let size = path.metadata().map_err(|e| e.to_string())?.len();
+    if size > MAX_FILE_SIZE {
+        return Err("Too large to preview".to_string());
+    }
+    let image = image::open(path).map_err(|e| e.to_string())?;
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgba8();
+    let pixels = [thumbnail.width() as usize, thumbnail.height() as usize];
+    Ok((egui::ColorImage::from_rgba_unmultiplied(pixels, thumbnail.as_raw()), image.width(), image.height()))
+}
+
+/// Draws `thumbnail` with the dimensions of the image.
+pub fn show(ui: &mut egui::Ui, thumbnail: &Thumbnail) {
+    match thumbnail {
+        Thumbnail::Loading => {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.weak("Loading preview...");
+            });
+        }
+        Thumbnail::Ready { texture, width, height } => {
+            ui.image((texture.id(), texture.size_vec2()));
+            ui.label(format!("{} × {} pixels", width, height));
+        }
+        Thumbnail::Failed(e) => {
+            ui.weak(format!("No preview: {}", e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_images_are_scaled_down_and_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wide.PNG");
+        let red = image::RgbaImage::from_pixel(512, 256, image::Rgba([255, 0, 0, 255]));
+        red.save_with_format(&path, image::ImageFormat::Png).unwrap();
+        assert!(is_image(&path));
+        assert!(!is_image(Path::new("notes.txt")));
+
+        let (image, width, height) = decode(&path).unwrap();
+        assert_eq!((width, height), (512, 256));
+        assert_eq!(image.size, [128, 64]);
+        assert!(decode(&dir.path().join("missing.png")).is_err());
+
+        let ctx = egui::Context::default();
+        let mut cache = ThumbnailCache::default();
+        assert!(matches!(cache.get(&ctx, &path), Thumbnail::Loading));
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while !matches!(cache.get(&ctx, &path), Thumbnail::Ready { .. }) {
+            assert!(std::time::Instant::now() < deadline, "the thumbnail wasn't decoded");
+            thread::sleep(std::time::Duration::from_millis(5));
+        }
+        // The least recently used thumbnails are evicted.
+        for i in 0..CACHE_CAPACITY {
+            cache.insert(dir.path().join(format!("{}.png", i)), Thumbnail::Failed(String::new()));
+        }
+        assert!(!cache.thumbnails.contains_key(&path));
+        assert_eq!(cache.order.len(), CACHE_CAPACITY);
+    }
+
+    #[test]
+    fn test_stale_requests_are_dropped() {
+        // Without workers, nothing is taken off the queue.
+        let mut cache = ThumbnailCache::default();
+        let paths: Vec<PathBuf> = (0..10).map(|i| PathBuf::from(format!("{}.png", i))).collect();
+        for path in &paths {
+            cache.request(path);
+        }
+        let pending = cache.requests.pending.lock().unwrap();
+        assert_eq!(pending.paths, &paths[10 - MAX_PENDING..]);
+        assert_eq!(cache.thumbnails.len(), MAX_PENDING);
+        assert_eq!(cache.order, &paths[10 - MAX_PENDING..]);
+    }
+}
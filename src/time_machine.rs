@@ -0,0 +1,105 @@
+//! This module looks up the local Time Machine snapshots and the purgeable space of APFS
+//! volumes on macOS. Finder counts purgeable space, which includes the snapshots, as
+//! available, while the drive overview and a scan only see what is really free and what the
+//! files take up, so the numbers disagree. The snapshots come from `tmutil`, and the
+//! capacities from Foundation through JavaScript for Automation. macOS doesn't report the
+//! size of individual snapshots.
+
+use std::io;
+use std::path::{Path, PathBuf};
+#[cfg(target_os = "macos")]
+use std::process::Command;
+
+/// Prints the space available for important use (Finder's free space, which counts
+/// purgeable space) and the space really free on the volume at `argv[0]`, separated by a tab.
+#[cfg(target_os = "macos")]
+const CAPACITY_SCRIPT: &str = r#"
+ObjC.import('Foundation');
+function run(argv) {
+    const keys = ['NSURLVolumeAvailableCapacityForImportantUsageKey', 'NSURLVolumeAvailableCapacityKey'];
+    const values = $.NSURL.fileURLWithPath(argv[0]).resourceValuesForKeysError($(keys), null);
+    return keys.map(key => values.objectForKey(key).js).join('\t');
+}
+"#;
+
+/// The local snapshots and purgeable space of a volume.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalSnapshots {
+    /// Where the volume is mounted.
+    pub volume: PathBuf,
+    /// When each local snapshot was taken, as `YYYY-MM-DD-HHMMSS`, oldest first.
+    pub snapshots: Vec<String>,
+    /// The free space Finder shows, which counts purgeable space, if known.
+    pub finder_available: Option<u64>,
+    /// The space macOS can free when it is needed, snapshots among it, if known.
+    pub purgeable: Option<u64>,
+}
+
+/// Looks up the local snapshots and purgeable space of the volume mounted at `volume`.
+#[cfg(target_os = "macos")]
+pub fn query(volume: &Path) -> io::Result<LocalSnapshots> {
+    let output = run(Command::new("tmutil").arg("listlocalsnapshots").arg(volume))?;
+    let snapshots = parse_snapshots(&output);
+    let capacities = run(Command::new("osascript").args(["-l", "JavaScript", "-e", CAPACITY_SCRIPT]).arg(volume))
+        .ok()
+        .and_then(|output| parse_capacities(&output));
+    Ok(LocalSnapshots {
+        volume: volume.to_path_buf(),
+        snapshots,
+        finder_available: capacities.map(|(finder, _)| finder),
+        purgeable: capacities.map(|(finder, free)| finder.saturating_sub(free)),
+    })
+}
+
+/// Time Machine local snapshots only exist on macOS.
+#[cfg(not(target_os = "macos"))]
+pub fn query(_volume: &Path) -> io::Result<LocalSnapshots> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "local Time Machine snapshots only exist on macOS"))
+}
+
+/// Runs `command` and returns its standard output, turning a failure into an error.
+#[cfg(target_os = "macos")]
+fn run(command: &mut Command) -> io::Result<String> {
+    let output = command.output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(stderr.trim().to_string()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parses the output of `tmutil listlocalsnapshots`, which names each snapshot like
+/// `com.apple.TimeMachine.2024-05-01-093000.local`, after a header line on recent versions.
+#[cfg(any(target_os = "macos", test))]
+fn parse_snapshots(output: &str) -> Vec<String> {
+    let mut dates: Vec<String> = output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("com.apple.TimeMachine.")?.strip_suffix(".local"))
+        .map(str::to_string)
+        .collect();
+    dates.sort();
+    dates
+}
+
+/// Parses the output of `CAPACITY_SCRIPT`: Finder's free space and the really free space.
+#[cfg(any(target_os = "macos", test))]
+fn parse_capacities(output: &str) -> Option<(u64, u64)> {
+    let (finder, free) = output.trim().split_once('\t')?;
+    Some((finder.parse().ok()?, free.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_snapshots_and_capacities() {
+        let output = "Snapshots for disk /:\n\
+                      com.apple.TimeMachine.2024-05-02-101500.local\n\
+                      com.apple.TimeMachine.2024-05-01-093000.local\n\
+                      com.apple.os.update-ABCDEF\n";
+        assert_eq!(parse_snapshots(output), ["2024-05-01-093000", "2024-05-02-101500"]);
+        assert_eq!(parse_capacities("120000000000\t80000000000\n"), Some((120_000_000_000, 80_000_000_000)));
+        assert_eq!(parse_capacities("undefined\t80000000000\n"), None);
+    }
+}
@@ -0,0 +1,264 @@
+//! This module decides what the tooltip of a treemap node shows, based on the user's
+//! settings. The same details are shown when the tooltip is pinned, so they stay in view
+//! while the pointer moves on to the pinned tooltip's buttons.
+
+use crate::basis::Basis;
+use crate::clones::ClonedFile;
+use crate::format;
+use crate::scanner::{FileSystemNode, SizeMetric};
+use crate::system_files::SystemFile;
+use crate::volumes::VolumeInfo;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// The key that pins the tooltip of the hovered node, and unpins it again.
+pub const PIN_KEY: egui::Key = egui::Key::P;
+
+/// Which details the tooltip shows, and when.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TooltipSettings {
+    /// How long the pointer has to rest on a node before its tooltip appears, in seconds.
+    pub delay: f32,
+    /// Show the path of the node from the scan root.
+    pub show_path: bool,
+    /// Show the apparent size.
+    pub show_size: bool,
+    /// Show the disk usage, and whether the file is sparse.
+    pub show_disk_usage: bool,
+    /// Show the node's share of its parent and of the size basis.
+    pub show_share: bool,
+    /// Show how many items a folder contains.
+    pub show_item_count: bool,
+    /// Show the volume mounted at the node, if any.
+    pub show_mount: bool,
+    /// Flag folders of build output.
+    pub show_artifact: bool,
+    /// Show a thumbnail of image files.
+    pub show_thumbnail: bool,
+}
+
+impl Default for TooltipSettings {
+    fn default() -> Self {
+        Self {
+            delay: 0.0,
+            show_path: false,
+            show_size: true,
+            show_disk_usage: true,
+            show_share: false,
+            show_item_count: false,
+            show_mount: true,
+            show_artifact: true,
+            show_thumbnail: true,
+        }
+    }
+}
+
+impl TooltipSettings {
+    /// Settings that show every detail right away, for where there's room for all of them.
+    pub fn everything() -> Self {
+        Self {
+            delay: 0.0,
+            show_path: true,
+            show_size: true,
+            show_disk_usage: true,
+            show_share: true,
+            show_item_count: true,
+            show_mount: true,
+            show_artifact: true,
+            show_thumbnail: true,
+        }
+    }
+}
+
+/// What is known about a node the tooltip is shown for.
+pub struct NodeInfo<'a> {
+    /// The path of the node from the scan root.
+    pub path: &'a [String],
+    /// The node itself.
+    pub node: &'a FileSystemNode,
+    /// The node's parent, unless it is the scan root.
+    pub parent: Option<&'a FileSystemNode>,
+    /// The volume mounted at the node, if any.
+    pub mount: Option<&'a VolumeInfo>,
+    /// What the node holds, if it is a folder of build output.
+    pub artifact: Option<&'static str>,
+    /// What shares are measured against, besides the parent.
+    pub basis: Option<&'a Basis>,
+    /// The clone the node is, if it shares blocks with clones of it.
+    pub clone: Option<&'a ClonedFile>,
+    /// Whether the node is a further hard link to a file counted at another link.
+    pub repeated_link: bool,
+    /// Whether the scan root is the top of a drive.
+    pub drive_root: bool,
+}
+
+/// One line of a tooltip.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Detail {
+    /// A plain line.
+    Text(String),
+    /// A line that stands out, such as a warning.
+    Note(String),
+}
+
+/// Returns the lines of the tooltip for `info`, as picked by `settings`. Shares are of `metric`.
+pub fn details(settings: &TooltipSettings, info: &NodeInfo, metric: SizeMetric) -> Vec<Detail> {
+    let node = info.node;
+    let mut details = vec![Detail::Text(format!("Name: {}", node.name))];
+    if settings.show_path {
+        details.push(Detail::Text(format!("Path: {}", info.path.join("/"))));
+    }
+    if settings.show_size {
+        details.push(Detail::Text(format!("Size: {} ({})", format::format_size(node.size), format::format_exact(node.size))));
+    }
+    if settings.show_disk_usage {
+        details.push(Detail::Text(format!("Disk usage: {} ({})", format::format_size(node.allocated), format::format_exact(node.allocated))));
+        if let Some(clone) = info.clone {
+            let counted = if clone.counted_elsewhere { "counted with another clone" } else { "counted here" };
+            details.push(Detail::Note(format!(
+                "Clone: shares {} with other files ({}), so deleting it frees only the rest.",
+                format::format_size(clone.shared),
+                counted
+            )));
+        } else if info.repeated_link {
+            // It takes no disk space here, but not because it is sparse.
+            details.push(Detail::Note("Hard link: counted at another link, so deleting it frees nothing.".to_string()));
+        } else if node.is_sparse() {
+            details.push(Detail::Note("Sparse file: most of it takes no disk space.".to_string()));
+        }
+    }
+    // At the top of a drive, Windows keeps items that look like clutter but aren't.
+    if info.drive_root
+        && info.path.len() == 1
+        && let Some(file) = SystemFile::recognize(&node.name)
+    {
+        details.push(Detail::Note(format!("{}: {}", file.label(), file.explanation())));
+    }
+    if let Some(special) = node.special {
+        details.push(Detail::Note(format!("{}: not a regular file, so its size isn't data on the disk.", special.label())));
+    }
+    if settings.show_share
+        && let Some(parent) = info.parent
+        && parent.size_by(metric) > 0
+    {
+        let share = node.size_by(metric) as f64 / parent.size_by(metric) as f64 * 100.0;
+        details.push(Detail::Text(format!("{:.1}% of {}", share, parent.name)));
+    }
+    if settings.show_share
+        && let Some(basis) = info.basis
+    {
+        details.push(Detail::Text(basis.share(node.size_by(metric))));
+    }
+    if settings.show_item_count && !node.children.is_empty() {
+        details.push(Detail::Text(format!("Contains {} items", node.children.len())));
+    }
+    if settings.show_mount
+        && let Some(volume) = info.mount
+    {
+        details.push(Detail::Text(format!(
+            "Mount point: {} ({}), {} of {} free{}",
+            volume.name,
+            volume.file_system,
+            format::format_size(volume.available),
+            format::format_size(volume.total),
+            if volume.read_only { ", read-only" } else { "" }
+        )));
+    }
+    if settings.show_artifact
+        && let Some(kind) = info.artifact
+    {
+        details.push(Detail::Note(format!("Build artifacts: {}, safe to clean", kind)));
+    }
+    details
+}
+
+/// Draws the lines of a tooltip.
+pub fn show_details(ui: &mut egui::Ui, details: &[Detail]) {
+    for detail in details {
+        match detail {
+            Detail::Text(text) => ui.label(text),
+            Detail::Note(text) => ui.colored_label(egui::Color32::LIGHT_BLUE, text),
+        };
+    }
+}
+
+/// Draws the tooltip settings.
+pub fn show_settings(ui: &mut egui::Ui, settings: &mut TooltipSettings) {
+    ui.add(
+        egui::Slider::new(&mut settings.delay, 0.0..=2.0)
+            .suffix(" s")
+            .text("Delay before showing"),
+    );
+    ui.checkbox(&mut settings.show_path, "Path");
+    ui.checkbox(&mut settings.show_size, "Size");
+    ui.checkbox(&mut settings.show_disk_usage, "Disk usage");
+    ui.checkbox(&mut settings.show_share, "Share of parent folder");
+    ui.checkbox(&mut settings.show_item_count, "Number of items in folders");
+    ui.checkbox(&mut settings.show_mount, "Mounted volume");
+    ui.checkbox(&mut settings.show_artifact, "Build artifact badge");
+    if cfg!(not(target_arch = "wasm32")) {
+        ui.checkbox(&mut settings.show_thumbnail, "Thumbnail of images");
+    }
+    ui.weak(format!("Press {} over a node to pin its tooltip.", PIN_KEY.name()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_details_follow_settings() {
+        let file = FileSystemNode { name: "disk.img".to_string(), size: 8 << 20, allocated: 4096, children: vec![], special: None };
+        let parent = FileSystemNode {
+            name: "vms".to_string(),
+            size: 16 << 20,
+            allocated: 8192,
+            children: vec![file.clone()],
+            special: None,
+        };
+        let path = vec!["vms".to_string(), "disk.img".to_string()];
+        let basis = Basis { total: 64 << 20, name: "the volume /".to_string() };
+        let info = NodeInfo { path: &path, node: &file, parent: Some(&parent), mount: None, artifact: None, basis: Some(&basis), clone: None, repeated_link: false, drive_root: false };
+
+        let defaults = details(&TooltipSettings::default(), &info, SizeMetric::Apparent);
+        assert_eq!(defaults[0], Detail::Text("Name: disk.img".to_string()));
+        assert!(defaults.iter().any(|d| matches!(d, Detail::Note(_))));
+
+        // A further link to a large file takes no disk space, but isn't sparse.
+        let link = FileSystemNode { name: "copy.iso".to_string(), size: 1 << 20, allocated: 0, children: vec![], special: None };
+        let notes: Vec<_> = details(&TooltipSettings::default(), &NodeInfo { node: &link, repeated_link: true, ..info }, SizeMetric::Apparent)
+            .into_iter()
+            .filter_map(|d| match d {
+                Detail::Note(note) => Some(note),
+                Detail::Text(_) => None,
+            })
+            .collect();
+        assert_eq!(notes, ["Hard link: counted at another link, so deleting it frees nothing."]);
+
+        let settings = TooltipSettings {
+            show_path: true,
+            show_size: false,
+            show_disk_usage: false,
+            show_share: true,
+            ..TooltipSettings::default()
+        };
+        assert_eq!(
+            details(&settings, &info, SizeMetric::Apparent),
+            vec![
+                Detail::Text("Name: disk.img".to_string()),
+                Detail::Text("Path: vms/disk.img".to_string()),
+                Detail::Text("50.0% of vms".to_string()),
+                Detail::Text("12.5% of the volume /".to_string()),
+            ]
+        );
+
+        // Only the top of a drive holds the page file Windows keeps.
+        let page_file = FileSystemNode { name: "pagefile.sys".to_string(), ..file.clone() };
+        let path = vec!["pagefile.sys".to_string()];
+        let info = NodeInfo { path: &path, node: &page_file, parent: None, basis: None, ..info };
+        let explains = |info: &NodeInfo| details(&settings, info, SizeMetric::Apparent).iter().any(|d| matches!(d, Detail::Note(n) if n.starts_with("Page file")));
+        assert!(!explains(&info));
+        assert!(explains(&NodeInfo { drive_root: true, ..info }));
+    }
+}
@@ -0,0 +1,150 @@
+//! This module makes the app usable on touchscreens. Pinching zooms the treemap around the
+//! fingers and dragging with two fingers pans it (ctrl-scrolling and scrolling do the same
+//! with a mouse or touchpad), so small items can be reached. Pressing and holding opens the
+//! context menu, which egui reports like a right click. Touch mode enlarges the controls and
+//! ignores items too small to be tapped reliably, selecting the folder around them instead.
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// The furthest the treemap can be zoomed in.
+const MAX_SCALE: f32 = 16.0;
+
+/// The smallest treemap item that can be tapped in touch mode, in points. Smaller items are
+/// only reached by zooming in.
+pub const TOUCH_TARGET: f32 = 24.0;
+
+/// The touch preferences.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TouchSettings {
+    /// Whether the controls and hit targets are enlarged for fingers.
+    pub touch_mode: bool,
+}
+
+impl TouchSettings {
+    /// Draws the touch settings.
+    pub fn show_settings(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.touch_mode, "Touch mode")
+            .on_hover_text("Larger controls, and treemap items too small to tap select their folder");
+        ui.weak("Pinch to zoom the treemap, drag with two fingers to pan it, and press and hold an item for its menu.");
+    }
+
+    /// The smallest treemap item that can be hit, in points.
+    pub fn min_target(&self) -> f32 {
+        if self.touch_mode { TOUCH_TARGET } else { 1.0 }
+    }
+
+    /// Enlarges the controls in touch mode, and restores egui's sizes otherwise.
+    pub fn apply_style(&self, ctx: &egui::Context) {
+        let defaults = egui::style::Spacing::default();
+        let (interact_size, button_padding, item_spacing) = if self.touch_mode {
+            (egui::vec2(48.0, 36.0), egui::vec2(12.0, 8.0), egui::vec2(10.0, 8.0))
+        } else {
+            (defaults.interact_size, defaults.button_padding, defaults.item_spacing)
+        };
+        let spacing = &ctx.style().spacing;
+        if spacing.interact_size != interact_size || spacing.button_padding != button_padding || spacing.item_spacing != item_spacing {
+            ctx.style_mut(|style| {
+                style.spacing.interact_size = interact_size;
+                style.spacing.button_padding = button_padding;
+                style.spacing.item_spacing = item_spacing;
+            });
+        }
+    }
+}
+
+/// How far the treemap is zoomed in and panned, for the layout root it was zoomed on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Zoom {
+    /// The path of the layout root. Opening another folder resets the zoom.
+    pub root: Vec<String>,
+    /// The magnification, 1.0 showing the whole treemap.
+    pub scale: f32,
+    /// Where the top-left corner of the magnified treemap is, relative to the treemap area.
+    pub offset: egui::Vec2,
+}
+
+impl Default for Zoom {
+    fn default() -> Self {
+        Self { root: Vec::new(), scale: 1.0, offset: egui::Vec2::ZERO }
+    }
+}
+
+impl Zoom {
+    /// Whether the treemap is zoomed in.
+    pub fn is_zoomed(&self) -> bool {
+        self.scale > 1.0
+    }
+
+    /// Returns where the layout rectangle `rect` is drawn in the treemap area at `origin`.
+    pub fn to_screen(&self, rect: egui::Rect, origin: egui::Vec2) -> egui::Rect {
+        egui::Rect::from_min_size(egui::Pos2::ZERO + origin + self.offset + rect.min.to_vec2() * self.scale, rect.size() * self.scale)
+    }
+
+    /// Returns the layout coordinates of the point `pos` in the treemap area at `origin`.
+    pub fn to_layout(&self, pos: egui::Pos2, origin: egui::Vec2) -> egui::Pos2 {
+        egui::Pos2::ZERO + (pos - origin - self.offset).to_vec2() / self.scale
+    }
+
+    /// Zooms by `factor` around `center`, then pans by `pan`, keeping the treemap covering
+    /// `area`.
+    pub fn change(&mut self, factor: f32, center: egui::Pos2, pan: egui::Vec2, area: egui::Rect) {
+        let scale = (self.scale * factor).clamp(1.0, MAX_SCALE);
+        // The point under the center stays where it is.
+        let anchor = center - area.min - self.offset;
+        self.offset += anchor - anchor * (scale / self.scale) + pan;
+        self.scale = scale;
+        let min = area.size() * (1.0 - scale);
+        self.offset = self.offset.clamp(min, egui::Vec2::ZERO);
+    }
+
+    /// Applies this frame's pinch, ctrl-scroll, and pan gestures over the treemap's
+    /// `response`, after resetting the zoom if the layout root is no longer `root`.
+    pub fn update(&mut self, ui: &egui::Ui, response: &egui::Response, root: &[String]) {
+        if self.root != root {
+            *self = Self { root: root.to_vec(), ..Self::default() };
+        }
+        let Some(pointer) = response.hover_pos() else {
+            return;
+        };
+        let (factor, touch, scroll) = ui.input(|i| (i.zoom_delta(), i.multi_touch(), i.smooth_scroll_delta));
+        let pan = match touch {
+            Some(touch) => touch.translation_delta,
+            // Scrolling pans a zoomed-in treemap, and leaves the rest of the app alone otherwise.
+            None if self.is_zoomed() && factor == 1.0 => scroll,
+            None => egui::Vec2::ZERO,
+        };
+        if factor != 1.0 || pan != egui::Vec2::ZERO {
+            self.change(factor, pointer, pan, response.rect);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zoom_keeps_the_pinch_center_and_the_treemap_in_view() {
+        let area = egui::Rect::from_min_size(egui::pos2(100.0, 50.0), egui::vec2(400.0, 200.0));
+        let origin = area.min.to_vec2();
+        let mut zoom = Zoom::default();
+        let center = egui::pos2(200.0, 100.0);
+        let before = zoom.to_layout(center, origin);
+        zoom.change(2.0, center, egui::Vec2::ZERO, area);
+        assert_eq!(zoom.scale, 2.0);
+        assert_eq!(zoom.to_layout(center, origin), before);
+        let rect = egui::Rect::from_min_size(before, egui::vec2(10.0, 10.0));
+        assert_eq!(zoom.to_screen(rect, origin), egui::Rect::from_min_size(center, egui::vec2(20.0, 20.0)));
+
+        // Panning stops at the edges, and zooming out stops at the whole treemap.
+        zoom.change(1.0, center, egui::vec2(1000.0, 0.0), area);
+        assert_eq!(zoom.offset.x, 0.0);
+        zoom.change(1.0, center, egui::vec2(-1000.0, -1000.0), area);
+        assert_eq!(zoom.offset, egui::vec2(-400.0, -200.0));
+        zoom.change(0.1, center, egui::Vec2::ZERO, area);
+        assert!(!zoom.is_zoomed());
+        assert_eq!(zoom.offset, egui::Vec2::ZERO);
+    }
+}
@@ -0,0 +1,122 @@
+//! This module manages the optional system tray icon.
+//! The icon shows how full the fullest watched volume is, and its menu lists the
+//! watched volumes so a scan of any of them can be started from the tray.
+
+use crate::alerts::AlertSettings;
+use crate::format::format_size;
+use crate::volumes::VolumeInfo;
+use std::path::PathBuf;
+use tray_icon::menu::{Menu, MenuEvent, MenuId, MenuItem, PredefinedMenuItem};
+use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
+
+/// The size of the generated tray icon, in pixels.
+const ICON_SIZE: u32 = 32;
+
+/// Something the user picked from the tray menu.
+pub enum TrayCommand {
+    /// Bring the main window to the front.
+    Show,
+    /// Scan the volume mounted at the given path.
+    Scan(PathBuf),
+}
+
+/// The tray icon and the mapping from its menu entries to commands.
+pub struct Tray {
+    icon: TrayIcon,
+    show_id: MenuId,
+    scan_items: Vec<(MenuId, PathBuf)>,
+}
+
+impl Tray {
+    /// Creates the tray icon. Returns `None` if the platform has no tray available.
+    pub fn new() -> Option<Self> {
+        let icon = TrayIconBuilder::new()
+            .with_tooltip("Disk Scout")
+            .with_icon(usage_icon(0.0)?)
+            .build();
+        match icon {
+            Ok(icon) => Some(Self { icon, show_id: MenuId::new("show"), scan_items: Vec::new() }),
+            Err(e) => {
+                eprintln!("Failed to create tray icon: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Updates the icon, tooltip, and menu to reflect the latest volume usage.
+    pub fn update(&mut self, volumes: &[VolumeInfo], settings: &AlertSettings) {
+        let watched: Vec<&VolumeInfo> = volumes.iter().filter(|v| settings.is_watched(&v.label())).collect();
+
+        let tooltip = watched
+            .iter()
+            .map(|v| format!("{}: {} free", v.label(), format_size(v.available)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if let Err(e) = self.icon.set_tooltip(Some(format!("Disk Scout\n{}", tooltip))) {
+            eprintln!("Failed to update tray tooltip: {}", e);
+        }
+
+        let fullest = watched.iter().map(|v| v.usage_fraction()).fold(0.0, f64::max);
+        if let Some(icon) = usage_icon(fullest)
+            && let Err(e) = self.icon.set_icon(Some(icon))
+        {
+            eprintln!("Failed to update tray icon: {}", e);
+        }
+
+        let menu = Menu::new();
+        let show = MenuItem::with_id(self.show_id.clone(), "Show Disk Scout", true, None);
+        let _ = menu.append(&show);
+        let _ = menu.append(&PredefinedMenuItem::separator());
+        self.scan_items.clear();
+        for (i, volume) in watched.iter().enumerate() {
+            let percent = volume.usage_fraction() * 100.0;
+            let warning = if percent >= settings.threshold_percent { " ⚠" } else { "" };
+            let id = MenuId::new(format!("scan-{}", i));
+            let text = format!("Scan {} ({:.0}% full){}", volume.label(), percent, warning);
+            let _ = menu.append(&MenuItem::with_id(id.clone(), text, true, None));
+            self.scan_items.push((id, volume.mount_point.clone()));
+        }
+        self.icon.set_menu(Some(Box::new(menu)));
+    }
+
+    /// Returns the next command picked from the tray menu, if any.
+    pub fn poll(&self) -> Option<TrayCommand> {
+        let event = MenuEvent::receiver().try_recv().ok()?;
+        if event.id == self.show_id {
+            return Some(TrayCommand::Show);
+        }
+        self.scan_items
+            .iter()
+            .find(|(id, _)| *id == event.id)
+            .map(|(_, path)| TrayCommand::Scan(path.clone()))
+    }
+}
+
+/// Draws a tray icon showing a disk that is filled up to `usage` (0.0 to 1.0).
+/// The fill turns orange and then red as the disk fills up.
+fn usage_icon(usage: f64) -> Option<Icon> {
+    let fill_color: [u8; 4] = if usage >= 0.9 {
+        [220, 50, 47, 255]
+    } else if usage >= 0.75 {
+        [255, 153, 0, 255]
+    } else {
+        [0, 153, 255, 255]
+    };
+    let filled_from = ICON_SIZE - (usage.clamp(0.0, 1.0) * (ICON_SIZE - 4) as f64) as u32 - 2;
+
+    let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let border = x < 2 || y < 2 || x >= ICON_SIZE - 2 || y >= ICON_SIZE - 2;
+            let pixel = if border {
+                [200, 200, 200, 255]
+            } else if y >= filled_from {
+                fill_color
+            } else {
+                [2, 34, 63, 255]
+            };
+            rgba.extend_from_slice(&pixel);
+        }
+    }
+    Icon::from_rgba(rgba, ICON_SIZE, ICON_SIZE).ok()
+}
@@ -2,11 +2,12 @@
 //! It takes a hierarchical `FileSystemNode` tree and calculates the rectangular
 //! coordinates and dimensions needed to visualize it.
 
-use crate::scanner::FileSystemNode;
+use crate::scanner::{FileSystemNode, SizeMetric};
+use std::collections::HashMap;
 
 /// Represents a 2D rectangle with floating-point coordinates and dimensions.
 /// This is used to define the boundaries for each node in the treemap.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Rectangle {
     pub x: f64,
     pub y: f64,
@@ -15,13 +16,16 @@ pub struct Rectangle {
 }
 
 /// Represents a single, drawable item in our treemap layout.
-/// It links the file system data (name, size) to a specific `Rectangle`.
-#[derive(Debug)]
+/// It links a node of the file system tree, identified by its path, to a specific `Rectangle`.
+#[derive(Debug, Clone)]
 pub struct TreemapNode {
     pub rect: Rectangle,
-    pub name: String, 
-    pub size: u64,
     pub depth: usize,
+    /// The node's share of the combined size of itself and its siblings, from 0.0 to 1.0.
+    pub share: f64,
+    /// The names of the nodes leading from the layout root (exclusive) to this node (inclusive).
+    /// This identifies the node within the tree the layout was generated from.
+    pub path: Vec<String>,
 }
 
 /// Generates a treemap layout from a `FileSystemNode` tree.
@@ -30,48 +34,99 @@ pub struct TreemapNode {
 ///
 /// * `node` - A reference to the root `FileSystemNode` of the tree.
 /// * `bounds` - The initial rectangle (e.g., the window) to fit the treemap into.
+/// * `metric` - Which size of the nodes the areas are proportional to.
 ///
 /// # Returns
 ///
 /// A flat vector of `TreemapNode`'s, each representing a rectangle to be drawn.
-pub fn generate_treemap(node: &FileSystemNode, bounds: Rectangle) -> Vec<TreemapNode> {
+pub fn generate_treemap(node: &FileSystemNode, bounds: Rectangle, metric: SizeMetric) -> Vec<TreemapNode> {
+    generate_treemap_to_depth(node, bounds, metric, usize::MAX)
+}
+
+/// Generates a treemap layout like `generate_treemap`, but only `max_depth` levels deep.
+/// The folders at the deepest level are laid out without their contents, which they stand
+/// in for.
+pub fn generate_treemap_to_depth(node: &FileSystemNode, bounds: Rectangle, metric: SizeMetric, max_depth: usize) -> Vec<TreemapNode> {
     let mut results = Vec::new();
-    // The recursive helper function does the main work.
-    calculate_layout(&node.children, bounds, &mut results, true, 1);
+    calculate_layout(&node.children, bounds, &mut results, true, &[], metric, max_depth);
     results
 }
 
-/// A recursive helper function that implements the "slice-and-dice" treemap algorithm.
+/// Implements the "slice-and-dice" treemap algorithm.
 ///
 /// It sorts children by size, then alternates between slicing the `bounds` rectangle
-/// vertically and horizontally to position the children.
+/// vertically and horizontally to position the children. The nodes still to be laid out
+/// are kept on a stack rather than recursed into, so trees nested thousands of levels
+/// deep can't overflow the call stack.
 fn calculate_layout(
     nodes: &[FileSystemNode],
     bounds: Rectangle,
     results: &mut Vec<TreemapNode>,
     slice_vertically: bool,
-    depth: usize,
+    parent_path: &[String],
+    metric: SizeMetric,
+    max_depth: usize,
+) {
+    let mut pending = Vec::new();
+    push_level(&mut pending, nodes, bounds, slice_vertically, parent_path, metric);
+    while let Some(Pending { node, rect, share, path, slice_vertically }) = pending.pop() {
+        // The children are laid out next, flipping the slice direction, so each folder is
+        // followed by its contents.
+        if !node.children.is_empty() && path.len() < max_depth {
+            push_level(&mut pending, &node.children, rect, !slice_vertically, &path, metric);
+        }
+        results.push(TreemapNode { rect, depth: path.len(), share, path });
+    }
+}
+
+/// A node placed by `calculate_layout` that isn't in the results yet.
+struct Pending<'a> {
+    node: &'a FileSystemNode,
+    rect: Rectangle,
+    share: f64,
+    path: Vec<String>,
+    /// The direction the node's own rectangle was sliced in.
+    slice_vertically: bool,
+}
+
+/// Arranges `nodes`, the contents of the folder at `parent_path`, in `bounds`, and pushes
+/// them onto `pending` so the first of them comes off first.
+fn push_level<'a>(
+    pending: &mut Vec<Pending<'a>>,
+    nodes: &'a [FileSystemNode],
+    bounds: Rectangle,
+    slice_vertically: bool,
+    parent_path: &[String],
+    metric: SizeMetric,
 ) {
-    if nodes.is_empty() {
-        return;
+    for (node, rect, share) in arrange(nodes, bounds, slice_vertically, metric).into_iter().rev() {
+        let mut path = parent_path.to_vec();
+        path.push(node.name.clone());
+        pending.push(Pending { node, rect, share, path, slice_vertically });
     }
+}
 
-    let mut sorted_nodes = nodes.to_vec();
-    sorted_nodes.sort_by(|a, b| b.size.cmp(&a.size));
+/// Lays out one level of the treemap: sorts `nodes` by size, largest first, and slices
+/// `bounds` between them. Returns each node with its rectangle and its share of the level.
+fn arrange(nodes: &[FileSystemNode], bounds: Rectangle, slice_vertically: bool, metric: SizeMetric) -> Vec<(&FileSystemNode, Rectangle, f64)> {
+    let mut sorted_nodes: Vec<&FileSystemNode> = nodes.iter().collect();
+    // Ties are broken by name, as the scan keeps children in the order they were listed.
+    sorted_nodes.sort_by(|a, b| b.size_by(metric).cmp(&a.size_by(metric)).then_with(|| a.name.cmp(&b.name)));
 
     // Calculate the total size of all nodes at this level.
-    let total_size = sorted_nodes.iter().map(|n| n.size).sum::<u64>() as f64;
+    let total_size = sorted_nodes.iter().map(|n| n.size_by(metric)).sum::<u64>() as f64;
     if total_size == 0.0 {
-        return;
+        return Vec::new();
     }
 
      // Keep track of our position as we lay out rectangles.
     let mut current_x = bounds.x;
     let mut current_y = bounds.y;
 
-    for node in &sorted_nodes {
+    let mut arranged = Vec::with_capacity(sorted_nodes.len());
+    for node in sorted_nodes {
         // The proportion of the total size this node occupies.
-        let proportion = node.size as f64 / total_size;
+        let proportion = node.size_by(metric) as f64 / total_size;
         let child_bounds;
 
          // Decide whether to slice horizontally or vertically.
@@ -96,21 +151,102 @@ fn calculate_layout(
             };
             current_y += height;
         }
-        
-        results.push(TreemapNode {
-            rect: child_bounds,
-            name: node.name.clone(),
-            size: node.size,
-            depth,
-        });
+        arranged.push((node, child_bounds, proportion));
+    }
+    arranged
+}
+
+/// Updates `layout`, generated by `generate_treemap_to_depth` from `node` with the same
+/// `bounds`, `metric`, and `max_depth`, after the nodes at `changed` (paths from `node`)
+/// changed size or were removed. Only the folders along those paths are laid out again;
+/// everything else keeps its arrangement, moved and scaled into its new rectangle.
+pub fn update_layout(
+    layout: &[TreemapNode],
+    node: &FileSystemNode,
+    changed: &[Vec<String>],
+    bounds: Rectangle,
+    metric: SizeMetric,
+    max_depth: usize,
+) -> Vec<TreemapNode> {
+    if changed.is_empty() {
+        return layout.to_vec();
+    }
+    // The nodes that may be kept are the siblings of the folders along the paths.
+    let kept = layout
+        .iter()
+        .enumerate()
+        .filter(|(_, n)| n.path.split_last().is_some_and(|(_, parent)| changed.iter().any(|c| c.starts_with(parent))))
+        .map(|(i, n)| (n.path.as_slice(), i))
+        .collect();
+    let update = Update { layout, kept, changed, metric, max_depth };
+    let mut results = Vec::with_capacity(layout.len());
+    update.relayout(&node.children, bounds, true, &[], &mut results);
+    results
+}
+
+/// A layout being updated after a node changed.
+struct Update<'a> {
+    /// The layout before the change.
+    layout: &'a [TreemapNode],
+    /// The indices in `layout` of the nodes whose parent is on the path of a change.
+    kept: HashMap<&'a [String], usize>,
+    /// The paths of the nodes that changed.
+    changed: &'a [Vec<String>],
+    metric: SizeMetric,
+    max_depth: usize,
+}
 
-        // Recursively call for the children, flipping the slice direction.
-        if !node.children.is_empty() {
-            calculate_layout(&node.children, child_bounds, results, !slice_vertically, depth+1);
+impl Update<'_> {
+    /// Lays out `nodes`, the contents of the folder at `parent_path` on the path of a
+    /// change, in `bounds`, reusing the old layout of those off the paths.
+    fn relayout(&self, nodes: &[FileSystemNode], bounds: Rectangle, slice_vertically: bool, parent_path: &[String], results: &mut Vec<TreemapNode>) {
+        for (node, child_bounds, proportion) in arrange(nodes, bounds, slice_vertically, self.metric) {
+            let mut path = parent_path.to_vec();
+            path.push(node.name.clone());
+            let on_path = self.changed.iter().any(|c| c.starts_with(&path));
+            if !on_path && let Some(&i) = self.kept.get(path.as_slice()) {
+                let from = self.layout[i].rect;
+                let moved = self.layout[i..subtree_end(self.layout, i)]
+                    .iter()
+                    .map(|n| TreemapNode { rect: rescale(n.rect, from, child_bounds), ..n.clone() });
+                let start = results.len();
+                results.extend(moved);
+                results[start].share = proportion;
+                continue;
+            }
+
+            results.push(TreemapNode { rect: child_bounds, depth: path.len(), share: proportion, path: path.clone() });
+            if node.children.is_empty() || path.len() >= self.max_depth {
+                continue;
+            }
+            // A node that changed itself is laid out anew, and one on the way to a change only in part.
+            if on_path && !self.changed.contains(&path) {
+                self.relayout(&node.children, child_bounds, !slice_vertically, &path, results);
+            } else {
+                calculate_layout(&node.children, child_bounds, results, !slice_vertically, &path, self.metric, self.max_depth);
+            }
         }
     }
 }
 
+/// The index just past the contents of the node at `i` of `layout`, which follow it.
+fn subtree_end(layout: &[TreemapNode], i: usize) -> usize {
+    let depth = layout[i].depth;
+    layout[i + 1..].iter().position(|n| n.depth <= depth).map_or(layout.len(), |end| i + 1 + end)
+}
+
+/// Maps `rect`, somewhere within `from`, to the same place within `to`.
+fn rescale(rect: Rectangle, from: Rectangle, to: Rectangle) -> Rectangle {
+    let scale_x = if from.width > 0.0 { to.width / from.width } else { 0.0 };
+    let scale_y = if from.height > 0.0 { to.height / from.height } else { 0.0 };
+    Rectangle {
+        x: to.x + (rect.x - from.x) * scale_x,
+        y: to.y + (rect.y - from.y) * scale_y,
+        width: rect.width * scale_x,
+        height: rect.height * scale_y,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -121,49 +257,53 @@ mod tests {
         let tree = FileSystemNode {
             name: "root".to_string(),
             size: 60,
+            allocated: 60,
             children: vec![
-                FileSystemNode { name: "a".to_string(), size: 30, children: vec![] },
+                FileSystemNode { name: "a".to_string(), size: 30, allocated: 30, children: vec![], special: None },
                 FileSystemNode { 
                     name: "b".to_string(), 
                     size: 20, 
+                    allocated: 20,
                     children: vec![], //vec![FileSystemNode {
                     //     name: "b1".to_string(),
                     //     size: 20,
                     //     children: vec![],
                     // }], 
+                    special: None,
                 },
-                FileSystemNode { name: "c".to_string(), size: 10, children: vec![] },
+                FileSystemNode { name: "c".to_string(), size: 10, allocated: 10, children: vec![], special: None },
             ],
+            special: None,
         };
 
         let bounds = Rectangle { x: 0.0, y: 0.0, width: 100.0, height: 100.0 };
-        let layout = generate_treemap(&tree, bounds);
+        let layout = generate_treemap(&tree, bounds, SizeMetric::Apparent);
 
         // Expected layout:
         // 'a' takes 50% of the width (30/60)
         // 'b' takes 33.3% of the width (20/60)
         // 'c' takes 16.6% of the width (10/60)
-        let expected_layout = vec![
+        let expected_layout = [
             TreemapNode {
                 rect: Rectangle { x: 0.0, y: 0.0, width: 50.0, height: 100.0 },
-                name: "a".to_string(),
-                size: 30,
+                path: vec!["a".to_string()],
                 depth: 1,
+                share: 0.5,
             },
             TreemapNode {
                 rect: Rectangle { x: 50.0, y: 0.0, width: 100.0/3.0, height: 100.0 },
-                name: "b".to_string(),
-                size: 20,
+                path: vec!["b".to_string()],
                 depth: 1,
+                share: 1.0/3.0,
             },
             TreemapNode {
                 rect: Rectangle { x: 50.0 + 100.0/3.0, y: 0.0, width: 100.0/6.0, height: 100.0 },
-                name: "c".to_string(),
-                size: 10,
+                path: vec!["c".to_string()],
                 depth: 1,
+                share: 1.0/6.0,
             },
         ];
-        let expected_depths = vec![
+        let expected_depths = [
             ("a", 1),
             ("b", 1),
             ("c", 1),
@@ -173,16 +313,78 @@ mod tests {
         assert_eq!(layout.len(), expected_layout.len());
         for (i, node) in layout.iter().enumerate() {
             let expected_node = &expected_layout[i];
-            assert_eq!(node.name, expected_node.name);
-            assert_eq!(node.size, expected_node.size);
+            assert_eq!(node.path, expected_node.path);
+            assert!((node.share - expected_node.share).abs() < 1e-9);
             assert!((node.rect.x - expected_node.rect.x).abs() < 1e-9);
             assert!((node.rect.y - expected_node.rect.y).abs() < 1e-9);
             assert!((node.rect.width - expected_node.rect.width).abs() < 1e-9);
             assert!((node.rect.height - expected_node.rect.height).abs() < 1e-9);
         }
         for (name, depth) in expected_depths {
-            let node = layout.iter().find(|n| n.name == name).unwrap();
+            let node = layout.iter().find(|n| n.path.last().map(String::as_str) == Some(name)).unwrap();
             assert_eq!(node.depth, depth);
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_generate_treemap_to_depth() {
+        let file = |name: &str| FileSystemNode { name: name.to_string(), size: 10, allocated: 10, children: vec![], special: None };
+        let folder = |name: &str, children| FileSystemNode { name: name.to_string(), size: 20, allocated: 20, children, special: None };
+        let tree = folder("root", vec![folder("a", vec![folder("b", vec![file("c"), file("d")])])]);
+        let bounds = Rectangle { x: 0.0, y: 0.0, width: 100.0, height: 100.0 };
+
+        assert_eq!(generate_treemap(&tree, bounds, SizeMetric::Apparent).len(), 4);
+        let layout = generate_treemap_to_depth(&tree, bounds, SizeMetric::Apparent, 2);
+        let paths: Vec<String> = layout.iter().map(|n| n.path.join("/")).collect();
+        assert_eq!(paths, ["a", "a/b"]);
+        // The deepest folder shown covers what it holds.
+        assert_eq!(layout[1].rect, bounds);
+    }
+
+    #[test]
+    fn test_generate_treemap_of_deep_nesting() {
+        let mut tree = FileSystemNode { name: "file".to_string(), size: 10, allocated: 10, children: vec![], special: None };
+        for _ in 0..1000 {
+            tree = FileSystemNode { name: "d".to_string(), size: 10, allocated: 10, children: vec![tree], special: None };
+        }
+        let bounds = Rectangle { x: 0.0, y: 0.0, width: 100.0, height: 100.0 };
+        // A thread with a small stack, which recursing level by level would overflow.
+        let layout = std::thread::scope(|scope| {
+            let generate = || generate_treemap(&tree, bounds, SizeMetric::Apparent);
+            std::thread::Builder::new().stack_size(64 << 10).spawn_scoped(scope, generate).unwrap().join().unwrap()
+        });
+        assert_eq!(layout.len(), 1000);
+        assert!(layout.iter().enumerate().all(|(i, n)| n.depth == i + 1 && n.rect == bounds));
+        assert_eq!(layout[999].path.last().map(String::as_str), Some("file"));
+    }
+
+    #[test]
+    fn test_update_layout_matches_a_new_layout() {
+        let mut tree = crate::synthetic::generate(&crate::synthetic::Spec::demo());
+        let bounds = Rectangle { x: 0.0, y: 0.0, width: 800.0, height: 600.0 };
+        let layout = generate_treemap(&tree, bounds, SizeMetric::Apparent);
+        // The deepest file, so every level along the path is laid out again.
+        let deepest = layout.iter().filter(|n| tree.find(&n.path).is_some_and(|n| n.children.is_empty())).max_by_key(|n| n.depth).unwrap();
+        assert!(deepest.depth > 2);
+        // And the largest folder elsewhere, whose siblings all move.
+        let largest = layout.iter().filter(|n| n.depth == 1 && !deepest.path.starts_with(&n.path)).max_by(|a, b| a.share.total_cmp(&b.share)).unwrap();
+        let changed = [deepest.path.clone(), largest.path.clone()];
+        let removed = layout.iter().filter(|n| n.path.starts_with(&largest.path)).count();
+        for path in &changed {
+            tree.remove(path);
+        }
+
+        let updated = update_layout(&layout, &tree, &changed, bounds, SizeMetric::Apparent, usize::MAX);
+        let expected = generate_treemap(&tree, bounds, SizeMetric::Apparent);
+        assert_eq!(updated.len(), layout.len() - removed - 1);
+        assert_eq!(updated.len(), expected.len());
+        for (node, expected) in updated.iter().zip(&expected) {
+            assert_eq!(node.path, expected.path);
+            assert_eq!(node.depth, expected.depth);
+            assert!((node.share - expected.share).abs() < 1e-9);
+            let close = |a: f64, b: f64| (a - b).abs() < 1e-6;
+            assert!(close(node.rect.x, expected.rect.x) && close(node.rect.y, expected.rect.y));
+            assert!(close(node.rect.width, expected.rect.width) && close(node.rect.height, expected.rect.height));
+        }
+    }
+}
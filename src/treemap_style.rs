@@ -0,0 +1,111 @@
+//! This module holds how the rectangles of the treemap are drawn: how round their corners
+//! are, the width and color of their outlines, and the gap left between them. Each
+//! rectangle is drawn as one shape with these applied.
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// The color of the rectangles' outlines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StrokeColor {
+    /// A light gray, the same for every rectangle.
+    Gray,
+    /// A darker shade of the rectangle's own color.
+    Darker,
+    /// The color of the window behind the treemap, which looks like a gap.
+    Background,
+}
+
+impl StrokeColor {
+    /// Every stroke color, in the order they are offered.
+    pub const ALL: [StrokeColor; 3] = [StrokeColor::Gray, StrokeColor::Darker, StrokeColor::Background];
+
+    /// The name shown in the settings.
+    pub fn label(self) -> &'static str {
+        match self {
+            StrokeColor::Gray => "Gray",
+            StrokeColor::Darker => "Darker shade of the fill",
+            StrokeColor::Background => "Background",
+        }
+    }
+}
+
+/// The user's treemap drawing preferences.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TreemapStyle {
+    /// The radius of the rectangles' corners, in points; 0 for square corners.
+    pub corner_radius: f32,
+    /// The width of the rectangles' outlines, in points; 0 for none.
+    pub stroke_width: f32,
+    /// The color of the outlines.
+    pub stroke_color: StrokeColor,
+    /// The space left between neighboring rectangles, in points. Nested rectangles share the
+    /// edges of their folder, so a folder's contents reach its edges with no space between.
+    pub gap: f32,
+}
+
+impl Default for TreemapStyle {
+    fn default() -> Self {
+        Self { corner_radius: 3.0, stroke_width: 1.0, stroke_color: StrokeColor::Gray, gap: 0.0 }
+    }
+}
+
+impl TreemapStyle {
+    /// The part of `rect` that is drawn, leaving half the gap on each side for its
+    /// neighbors to leave the other half. Every rectangle shrinks by the same amount, so the
+    /// edges of a folder and of the items along them still coincide.
+    pub fn inset(&self, rect: egui::Rect) -> egui::Rect {
+        rect.shrink(self.gap / 2.0)
+    }
+
+    /// The outline of a rectangle filled with `fill`, on a window of color `background`.
+    pub fn stroke(&self, fill: egui::Color32, background: egui::Color32) -> egui::Stroke {
+        let color = match self.stroke_color {
+            StrokeColor::Gray => egui::Color32::from_gray(150),
+            StrokeColor::Darker => {
+                let darken = |c: u8| (f32::from(c) * 0.6) as u8;
+                egui::Color32::from_rgb(darken(fill.r()), darken(fill.g()), darken(fill.b()))
+            }
+            StrokeColor::Background => background,
+        };
+        egui::Stroke::new(self.stroke_width, color)
+    }
+
+    /// Draws the treemap style settings.
+    pub fn show_settings(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.corner_radius, 0.0..=8.0).step_by(0.5).suffix(" pt").text("Corner radius"));
+        ui.add(egui::Slider::new(&mut self.stroke_width, 0.0..=4.0).step_by(0.5).suffix(" pt").text("Outline width"));
+        egui::ComboBox::from_label("Outline color").selected_text(self.stroke_color.label()).show_ui(ui, |ui| {
+            for color in StrokeColor::ALL {
+                ui.selectable_value(&mut self.stroke_color, color, color.label());
+            }
+        });
+        ui.add(egui::Slider::new(&mut self.gap, 0.0..=6.0).step_by(0.5).suffix(" pt").text("Gap between items"));
+        if *self != Self::default() && ui.button("Reset").clicked() {
+            *self = Self::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inset_and_stroke() {
+        let rect = egui::Rect::from_min_size(egui::pos2(10.0, 10.0), egui::vec2(20.0, 10.0));
+        let fill = egui::Color32::from_rgb(100, 200, 50);
+        let background = egui::Color32::from_gray(27);
+
+        let style = TreemapStyle::default();
+        assert_eq!(style.inset(rect), rect);
+        assert_eq!(style.stroke(fill, background), egui::Stroke::new(1.0, egui::Color32::from_gray(150)));
+
+        let style = TreemapStyle { stroke_width: 2.0, stroke_color: StrokeColor::Darker, gap: 4.0, ..style };
+        assert_eq!(style.inset(rect), egui::Rect::from_min_max(egui::pos2(12.0, 12.0), egui::pos2(28.0, 18.0)));
+        assert_eq!(style.stroke(fill, background), egui::Stroke::new(2.0, egui::Color32::from_rgb(60, 120, 30)));
+        let style = TreemapStyle { stroke_color: StrokeColor::Background, ..style };
+        assert_eq!(style.stroke(fill, background).color, background);
+    }
+}
@@ -0,0 +1,152 @@
+//! This module knows about pseudo-filesystems: mounts such as `/proc` and `/sys` that don't
+//! take up any disk space, but can contain huge or endless virtual files that make a scan
+//! of `/` hang. Scans skip them by default, except for mounts the user asks to include.
+//!
+//! Only Linux has these, so elsewhere nothing is skipped.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Filesystem types that never hold files stored on a disk.
+pub const VIRTUAL_FS_TYPES: [&str; 18] = [
+    "proc",
+    "sysfs",
+    "devtmpfs",
+    "devpts",
+    "cgroup",
+    "cgroup2",
+    "securityfs",
+    "debugfs",
+    "tracefs",
+    "pstore",
+    "bpf",
+    "configfs",
+    "fusectl",
+    "mqueue",
+    "hugetlbfs",
+    "binfmt_misc",
+    "efivarfs",
+    "autofs",
+];
+
+/// Directories that are skipped whenever something is mounted on them, whatever the
+/// filesystem type. `/run` is usually a `tmpfs` holding sockets and runtime state.
+pub const VIRTUAL_PATHS: [&str; 4] = ["/proc", "/sys", "/dev", "/run"];
+
+/// A single entry of the mount table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mount {
+    /// Where the filesystem is mounted.
+    pub mount_point: PathBuf,
+    /// The filesystem type, e.g. "ext4" or "proc".
+    pub fs_type: String,
+    /// The mount options, e.g. "rw,relatime".
+    pub options: String,
+}
+
+impl Mount {
+    /// Whether this mount is a pseudo-filesystem that scans skip by default.
+    pub fn is_virtual(&self) -> bool {
+        VIRTUAL_FS_TYPES.contains(&self.fs_type.as_str())
+            || VIRTUAL_PATHS.iter().any(|p| self.mount_point == Path::new(p))
+    }
+}
+
+/// Parses a mount table in the format of `/proc/self/mounts`.
+pub fn parse_mounts(contents: &str) -> Vec<Mount> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            let mount_point = unescape(fields.next()?);
+            let fs_type = fields.next()?.to_string();
+            let options = unescape(fields.next().unwrap_or_default());
+            Some(Mount { mount_point: PathBuf::from(mount_point), fs_type, options })
+        })
+        .collect()
+}
+
+/// Decodes the octal escapes (e.g. `\040` for a space) used in the mount table.
+fn unescape(field: &str) -> String {
+    let mut result = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            let digits: String = chars.clone().take(3).collect();
+            if let Ok(byte) = u8::from_str_radix(&digits, 8) {
+                result.push(byte as char);
+                chars.nth(2);
+                continue;
+            }
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// Returns the currently mounted filesystems.
+#[cfg(target_os = "linux")]
+pub fn mounts() -> Vec<Mount> {
+    match std::fs::read_to_string("/proc/self/mounts") {
+        Ok(contents) => parse_mounts(&contents),
+        Err(e) => {
+            eprintln!("Failed to read the mount table: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Returns the currently mounted filesystems. There is no mount table to read on this platform.
+#[cfg(not(target_os = "linux"))]
+pub fn mounts() -> Vec<Mount> {
+    Vec::new()
+}
+
+/// Returns the device IDs of the virtual mounts that a scan should skip, leaving out
+/// those whose mount point is in `include`.
+///
+/// Devices are used rather than paths so that a mount is recognized however the scan
+/// reached it, e.g. through a relative root or a symbolic link.
+#[cfg(unix)]
+pub fn skipped_devices(include: &[PathBuf]) -> HashSet<u64> {
+    use std::os::unix::fs::MetadataExt;
+    let device = |path: &Path| std::fs::metadata(path).ok().map(|m| m.dev());
+    mounts()
+        .into_iter()
+        .filter(|m| m.is_virtual() && !include.contains(&m.mount_point))
+        // A preset path that isn't a mount of its own shares the device of its parent,
+        // and skipping that device would skip the whole parent filesystem.
+        .filter(|m| m.mount_point.parent().is_some_and(|parent| device(parent) != device(&m.mount_point)))
+        .filter_map(|m| device(&m.mount_point))
+        .collect()
+}
+
+/// Returns the device IDs of the virtual mounts that a scan should skip. Device IDs
+/// aren't available on this platform, so nothing is skipped.
+#[cfg(not(unix))]
+pub fn skipped_devices(_include: &[PathBuf]) -> HashSet<u64> {
+    HashSet::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mounts_and_classify() {
+        let table = "\
+/dev/sda1 / ext4 rw,relatime 0 0
+proc /proc proc rw,nosuid 0 0
+cgroup2 /sys/fs/cgroup cgroup2 rw 0 0
+tmpfs /run tmpfs rw 0 0
+tmpfs /tmp tmpfs rw 0 0
+/dev/sdb1 /media/My\\040Disk vfat rw 0 0
+";
+        let mounts = parse_mounts(table);
+        assert_eq!(mounts.len(), 6);
+        assert_eq!(mounts[5].mount_point, PathBuf::from("/media/My Disk"));
+        let virtual_points: Vec<_> = mounts.iter().filter(|m| m.is_virtual()).map(|m| &m.mount_point).collect();
+        assert_eq!(virtual_points, [Path::new("/proc"), Path::new("/sys/fs/cgroup"), Path::new("/run")]);
+    }
+}
@@ -0,0 +1,64 @@
+//! This module lists the mounted volumes (drives) of the system along with their usage.
+
+use std::path::PathBuf;
+
+/// Information about a single mounted volume.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VolumeInfo {
+    /// The device or volume name (e.g. "/dev/sda1" or "C:").
+    pub name: String,
+    /// Where the volume is mounted (e.g. "/" or "C:\").
+    pub mount_point: PathBuf,
+    /// The filesystem type (e.g. "ext4", "NTFS").
+    pub file_system: String,
+    /// The capacity of the volume in bytes.
+    pub total: u64,
+    /// The space still available to the user in bytes.
+    pub available: u64,
+    /// Whether the volume is mounted read-only.
+    pub read_only: bool,
+}
+
+impl VolumeInfo {
+    /// The space in use, in bytes.
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.available)
+    }
+
+    /// The fraction of the volume in use, from 0.0 to 1.0.
+    pub fn usage_fraction(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.used() as f64 / self.total as f64
+    }
+
+    /// A short label for the volume, e.g. "/home" or "C:\".
+    pub fn label(&self) -> String {
+        self.mount_point.to_string_lossy().into_owned()
+    }
+}
+
+/// Queries the operating system for the currently mounted volumes.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn list_volumes() -> Vec<VolumeInfo> {
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .map(|disk| VolumeInfo {
+            name: disk.name().to_string_lossy().into_owned(),
+            mount_point: disk.mount_point().to_path_buf(),
+            file_system: disk.file_system().to_string_lossy().into_owned(),
+            total: disk.total_space(),
+            available: disk.available_space(),
+            read_only: disk.is_read_only(),
+        })
+        .collect()
+}
+
+/// A browser can't see the volumes of the machine, so none are listed.
+#[cfg(target_arch = "wasm32")]
+pub fn list_volumes() -> Vec<VolumeInfo> {
+    Vec::new()
+}
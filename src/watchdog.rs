@@ -0,0 +1,133 @@
+//! This module watches the volumes of a finished scan while its results are browsed, so the
+//! UI notices when a removable drive is ejected or a share is unmounted. The parts of the
+//! tree on a volume that went away are no longer on disk, so the actions on their files
+//! are disabled until the volume is back.
+//!
+//! The volumes are checked on a thread of their own, since looking at a mount point whose
+//! device stopped answering can block for a long time.
+
+use crate::mounts::ScanMount;
+use crate::scanner;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How often the volumes are checked.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the watching thread checks whether it should stop.
+const STOP_POLL: Duration = Duration::from_millis(100);
+
+/// A volume of the scan, as it was when the watch started.
+#[derive(Debug, Clone)]
+struct Watched {
+    /// The path of the mount point from the scan root.
+    path: Vec<String>,
+    mount_point: PathBuf,
+    /// The device the mount point was on, where the platform tells.
+    device: Option<u64>,
+}
+
+impl Watched {
+    /// Whether the volume went away: its mount point is gone, or now belongs to another
+    /// device, which is what is left of a mount point once the volume is unmounted.
+    fn is_gone(&self) -> bool {
+        fs::metadata(&self.mount_point).map_or(true, |m| scanner::device_id(&m) != self.device)
+    }
+}
+
+/// What the watching thread shares with the UI.
+#[derive(Debug, Default)]
+struct Shared {
+    /// The paths, from the scan root, of the mount points whose volumes went away.
+    gone: Mutex<Vec<Vec<String>>>,
+    stop: AtomicBool,
+}
+
+/// Watches the volumes of a scan until dropped.
+#[derive(Debug)]
+pub struct VolumeWatchdog {
+    shared: Arc<Shared>,
+}
+
+impl VolumeWatchdog {
+    /// Starts watching the volumes in `mounts` that the scan covered.
+    pub fn start(mounts: &[ScanMount]) -> Self {
+        let watched: Vec<Watched> = mounts
+            .iter()
+            .filter(|m| !m.skipped)
+            .filter_map(|m| {
+                let metadata = fs::metadata(&m.volume.mount_point).ok()?;
+                Some(Watched { path: m.path.clone(), mount_point: m.volume.mount_point.clone(), device: scanner::device_id(&metadata) })
+            })
+            .collect();
+        let shared = Arc::new(Shared::default());
+        let watcher = shared.clone();
+        thread::spawn(move || {
+            while !watcher.stop.load(Ordering::Relaxed) {
+                let gone = watched.iter().filter(|w| w.is_gone()).map(|w| w.path.clone()).collect();
+                *watcher.gone.lock().unwrap_or_else(|e| e.into_inner()) = gone;
+                for _ in 0..CHECK_INTERVAL.as_millis() / STOP_POLL.as_millis() {
+                    if watcher.stop.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    thread::sleep(STOP_POLL);
+                }
+            }
+        });
+        Self { shared }
+    }
+
+    /// The paths, from the scan root, of the mount points whose volumes went away.
+    pub fn gone(&self) -> Vec<Vec<String>> {
+        self.shared.gone.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Whether the node at `path` from the scan root is on a volume that went away.
+    pub fn is_gone(&self, path: &[String]) -> bool {
+        self.shared.gone.lock().unwrap_or_else(|e| e.into_inner()).iter().any(|mount| path.starts_with(mount))
+    }
+}
+
+impl Drop for VolumeWatchdog {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::volumes::VolumeInfo;
+
+    #[test]
+    fn test_watchdog_notices_removed_volume() {
+        let dir = tempfile::tempdir().unwrap();
+        let mount_point = dir.path().join("usb");
+        fs::create_dir(&mount_point).unwrap();
+        let volume = VolumeInfo {
+            name: "/dev/sdb1".to_string(),
+            mount_point: mount_point.clone(),
+            file_system: "vfat".to_string(),
+            total: 0,
+            available: 0,
+            read_only: false,
+        };
+        let path = vec!["usb".to_string()];
+        let watchdog = VolumeWatchdog::start(&[ScanMount { volume, path: path.clone(), skipped: false }]);
+        thread::sleep(STOP_POLL);
+        assert!(!watchdog.is_gone(&[path.clone(), vec!["photo.jpg".to_string()]].concat()));
+
+        fs::remove_dir(&mount_point).unwrap();
+        let deadline = std::time::Instant::now() + CHECK_INTERVAL * 3;
+        while watchdog.gone().is_empty() && std::time::Instant::now() < deadline {
+            thread::sleep(STOP_POLL);
+        }
+        assert_eq!(watchdog.gone(), std::slice::from_ref(&path));
+        assert!(watchdog.is_gone(&[path, vec!["photo.jpg".to_string()]].concat()));
+        assert!(!watchdog.is_gone(&["home".to_string()]));
+    }
+}
@@ -0,0 +1,63 @@
+//! This module is the browser side of the web build. A browser can't scan the disk, so the
+//! user either picks a folder, whose file list (relative paths and sizes) is turned into a
+//! tree, or opens a scan exported elsewhere, such as a snapshot or an ncdu export.
+//!
+//! Both go through a hidden `<input type="file">` rather than the File System Access API:
+//! its directory picker only exists in Chromium, while a directory input works everywhere
+//! and reveals the same information.
+
+use crate::import;
+use crate::snapshot::Snapshot;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{FileList, HtmlInputElement};
+
+/// Shows the browser's file picker (or folder picker if `directory` is set) and waits for
+/// the user's choice. Returns `None` if nothing was picked.
+async fn pick(directory: bool) -> Option<FileList> {
+    let document = web_sys::window()?.document()?;
+    let input: HtmlInputElement = document.create_element("input").ok()?.dyn_into().ok()?;
+    input.set_type("file");
+    if directory {
+        input.set_attribute("webkitdirectory", "").ok()?;
+    } else {
+        input.set_accept(".json");
+    }
+    let picked = js_sys::Promise::new(&mut |resolve, _reject| {
+        input.set_onchange(Some(&resolve));
+        // Browsers that don't fire "cancel" leave the pick pending, which keeps the buttons disabled
+        // until the next pick; there's nothing better to listen for.
+        let _ = input.add_event_listener_with_callback("cancel", &resolve);
+    });
+    input.click();
+    JsFuture::from(picked).await.ok()?;
+    input.files().filter(|files| files.length() > 0)
+}
+
+/// Lets the user pick a folder and builds a tree from the sizes of the files in it.
+pub async fn open_folder() -> Option<Result<Snapshot, String>> {
+    let files = pick(true).await?;
+    let files = (0..files.length())
+        .filter_map(|i| files.get(i))
+        .map(|file| (relative_path(&file), file.size() as u64));
+    Some(import::tree_from_files(files).ok_or_else(|| "The folder is empty.".to_string()))
+}
+
+/// Lets the user pick an exported scan and reads it.
+pub async fn open_export() -> Option<Result<Snapshot, String>> {
+    let file = pick(false).await?.get(0)?;
+    let Ok(buffer) = JsFuture::from(file.array_buffer()).await else {
+        return Some(Err(format!("Failed to read {}", file.name())));
+    };
+    let bytes = js_sys::Uint8Array::new(&buffer).to_vec();
+    Some(import::parse_export(&bytes).map_err(|e| format!("Failed to open {}: {}", file.name(), e)))
+}
+
+/// The path of a file picked through a directory input, starting with the folder's name.
+/// `web-sys` doesn't expose `webkitRelativePath`, so it is read as a plain property.
+fn relative_path(file: &web_sys::File) -> String {
+    js_sys::Reflect::get(file, &"webkitRelativePath".into())
+        .ok()
+        .and_then(|path| path.as_string())
+        .unwrap_or_else(|| file.name())
+}
@@ -0,0 +1,219 @@
+//! This module draws what the app shows before there is anything to look at: the drives
+//! and recent scans to pick from, and a short tour of the treemap the first time one is
+//! shown.
+
+use crate::format;
+use crate::volumes::VolumeInfo;
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+/// How many recently scanned folders are remembered.
+pub const MAX_RECENT_SCANS: usize = 8;
+
+/// The width of a drive card, in points.
+const CARD_WIDTH: f32 = 220.0;
+
+/// What the user asked for in the empty state.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WelcomeAction {
+    /// Scan this folder or drive.
+    Scan(PathBuf),
+    /// Pick a folder to scan with a file dialog.
+    PickFolder,
+    /// Forget the recent scans.
+    ClearRecent,
+}
+
+/// Puts `path` first among the recent scans, dropping an earlier entry for it and the
+/// oldest entries beyond `MAX_RECENT_SCANS`.
+pub fn remember_scan(recent: &mut Vec<PathBuf>, path: &Path) {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    recent.retain(|p| *p != path);
+    recent.insert(0, path);
+    recent.truncate(MAX_RECENT_SCANS);
+}
+
+/// Draws the empty state: a button to pick a folder, a card for each drive with how full
+/// it is, and the recently scanned folders.
+pub fn show(ui: &mut egui::Ui, drives: &[VolumeInfo], recent: &[PathBuf]) -> Option<WelcomeAction> {
+    let mut action = None;
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        ui.vertical_centered(|ui| {
+            ui.add_space(24.0);
+            ui.heading("See what takes up the space");
+            if cfg!(target_arch = "wasm32") {
+                ui.label("Open a folder or an exported scan to begin.");
+                return;
+            }
+            ui.label("Scan a folder or a whole drive to see it as a treemap.");
+            ui.add_space(8.0);
+            if ui.button("Pick folder...").clicked() {
+                action = Some(WelcomeAction::PickFolder);
+            }
+        });
+        let drives: Vec<&VolumeInfo> = drives.iter().filter(|d| d.total > 0).collect();
+        if !drives.is_empty() {
+            ui.add_space(16.0);
+            ui.strong("Drives");
+            ui.horizontal_wrapped(|ui| {
+                for drive in drives {
+                    if let Some(a) = show_drive_card(ui, drive) {
+                        action = Some(a);
+                    }
+                }
+            });
+        }
+        if !recent.is_empty() {
+            ui.add_space(16.0);
+            ui.horizontal(|ui| {
+                ui.strong("Recent scans");
+                if ui.small_button("Clear").clicked() {
+                    action = Some(WelcomeAction::ClearRecent);
+                }
+            });
+            for path in recent {
+                let text = path.display().to_string();
+                if ui.link(text).on_hover_text("Scan again").clicked() {
+                    action = Some(WelcomeAction::Scan(path.clone()));
+                }
+            }
+        }
+    });
+    action
+}
+
+/// Draws a card for `drive` with a bar showing how full it is.
+fn show_drive_card(ui: &mut egui::Ui, drive: &VolumeInfo) -> Option<WelcomeAction> {
+    let mut action = None;
+    egui::Frame::group(ui.style()).show(ui, |ui| {
+        ui.set_width(CARD_WIDTH);
+        ui.horizontal(|ui| {
+            ui.strong(drive.label());
+            ui.weak(&drive.file_system);
+        });
+        let bar = egui::ProgressBar::new(drive.usage_fraction() as f32)
+            .desired_width(CARD_WIDTH)
+            .text(format!("{} of {} used", format::format_size(drive.used()), format::format_size(drive.total)));
+        ui.add(bar);
+        ui.horizontal(|ui| {
+            ui.label(format!("{} free", format::format_size(drive.available)));
+            if ui.button("Scan").clicked() {
+                action = Some(WelcomeAction::Scan(drive.mount_point.clone()));
+            }
+        });
+    });
+    action
+}
+
+/// A part of the window a step of the tour points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TourTarget {
+    /// The treemap.
+    Treemap,
+    /// The toolbar with the scan controls and view options.
+    Toolbar,
+    /// The menu bar.
+    MenuBar,
+}
+
+/// The steps of the tour: what to point at and what to say about it.
+pub const TOUR: [(TourTarget, &str); 6] = [
+    (TourTarget::Treemap, "Each box is a file or folder, as large as the space it takes. Folders hold their contents."),
+    (TourTarget::Treemap, "Click a box to select it and see its details, or rest the pointer on it for a tooltip."),
+    (TourTarget::Treemap, "Double-click a folder to open it. Middle-click or press Backspace to go back up."),
+    (TourTarget::Treemap, "Right-click a box for its menu, e.g. to exclude it or tick it to add it up with others."),
+    (TourTarget::Toolbar, "Scan another folder, or change which sizes count and how the map is drawn, here."),
+    (TourTarget::MenuBar, "Everything else is in the menus. Help lists all the mouse and keyboard controls."),
+];
+
+/// Where each part of the window the tour points at was drawn this frame.
+#[derive(Debug, Clone, Copy)]
+pub struct TourTargets {
+    /// The treemap's area.
+    pub treemap: egui::Rect,
+    /// The toolbar's area.
+    pub toolbar: egui::Rect,
+    /// The menu bar's area.
+    pub menu_bar: egui::Rect,
+}
+
+impl Default for TourTargets {
+    fn default() -> Self {
+        Self { treemap: egui::Rect::NOTHING, toolbar: egui::Rect::NOTHING, menu_bar: egui::Rect::NOTHING }
+    }
+}
+
+impl TourTargets {
+    fn rect(&self, target: TourTarget) -> egui::Rect {
+        match target {
+            TourTarget::Treemap => self.treemap,
+            TourTarget::Toolbar => self.toolbar,
+            TourTarget::MenuBar => self.menu_bar,
+        }
+    }
+}
+
+/// Draws the current step of the tour, outlining the part of the window it is about.
+/// Returns true once the user finished or skipped the tour.
+pub fn show_tour(ctx: &egui::Context, step: &mut usize, targets: &TourTargets) -> bool {
+    let Some(&(target, text)) = TOUR.get(*step) else {
+        return true;
+    };
+    let rect = targets.rect(target);
+    let stroke = egui::Stroke::new(3.0, ctx.style().visuals.selection.bg_fill);
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("tour_highlight")));
+    painter.rect_stroke(rect.shrink(1.5), 4.0, stroke);
+
+    // Bars are pointed at from below, the treemap from its middle.
+    let (pivot, position) = match target {
+        TourTarget::Treemap => (egui::Align2::CENTER_CENTER, rect.center()),
+        TourTarget::Toolbar | TourTarget::MenuBar => (egui::Align2::CENTER_TOP, rect.center_bottom() + egui::vec2(0.0, 8.0)),
+    };
+    let mut done = false;
+    egui::Window::new(format!("Tour ({} of {})", *step + 1, TOUR.len()))
+        .id(egui::Id::new("tour"))
+        .collapsible(false)
+        .resizable(false)
+        .pivot(pivot)
+        .fixed_pos(position)
+        .show(ctx, |ui| {
+            ui.set_max_width(300.0);
+            ui.label(text);
+            ui.horizontal(|ui| {
+                if ui.add_enabled(*step > 0, egui::Button::new("Back")).clicked() {
+                    *step -= 1;
+                }
+                let last = *step + 1 == TOUR.len();
+                if ui.button(if last { "Done" } else { "Next" }).clicked() {
+                    *step += 1;
+                    done = last;
+                }
+                if !last && ui.button("Skip").clicked() {
+                    done = true;
+                }
+            });
+        });
+    done
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remember_scan_moves_to_front_and_caps() {
+        let root = std::env::temp_dir();
+        let mut recent = Vec::new();
+        for i in 0..10 {
+            remember_scan(&mut recent, &root.join(i.to_string()));
+        }
+        assert_eq!(recent.len(), MAX_RECENT_SCANS);
+        assert_eq!(recent[0], root.join("9"));
+        assert_eq!(recent[MAX_RECENT_SCANS - 1], root.join("2"));
+
+        remember_scan(&mut recent, &root.join("5"));
+        assert_eq!(recent.len(), MAX_RECENT_SCANS);
+        assert_eq!(recent[0], root.join("5"));
+        assert_eq!(recent.iter().filter(|p| **p == root.join("5")).count(), 1);
+    }
+}
@@ -0,0 +1,166 @@
+//! This module groups several scan roots into workspaces, such as "Home NAS" and "Work
+//! laptop", that can be switched between. A workspace keeps the options each of its roots is
+//! scanned with, how often they are rescanned while the app is open, and the budgets and
+//! history setting that apply while it is active. The history of a root is kept per folder,
+//! so the snapshot history of a workspace is that of its roots.
+
+use crate::budgets::BudgetSettings;
+use crate::scanner::ScanOptions;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The settings that are swapped when switching workspaces.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    /// The space budgets scans are checked against.
+    pub budgets: BudgetSettings,
+    /// Whether every finished scan is added to the history of its folder.
+    pub record_history: bool,
+}
+
+/// A folder that is part of a workspace.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Root {
+    pub path: PathBuf,
+    /// The options the folder is scanned with.
+    pub options: ScanOptions,
+    /// How often the folder is rescanned in the background while the app is open, in hours.
+    /// 0 only scans it when asked.
+    pub every_hours: u32,
+    /// When the folder was last scanned from the workspace, in seconds since the Unix epoch.
+    pub last_scanned: Option<u64>,
+}
+
+impl Root {
+    /// Whether the folder is due for its scheduled scan at `now`.
+    pub fn is_due(&self, now: u64) -> bool {
+        self.every_hours > 0 && self.last_scanned.is_none_or(|last| now >= last + u64::from(self.every_hours) * 3600)
+    }
+}
+
+/// A named group of roots with the settings that apply to them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workspace {
+    pub name: String,
+    pub roots: Vec<Root>,
+    /// The settings applied while the workspace is active. They are only up to date while
+    /// it isn't, since the settings in use are those of the app.
+    pub profile: Profile,
+}
+
+/// The user's workspaces, and which one is active.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceSettings {
+    pub workspaces: Vec<Workspace>,
+    /// The index of the active workspace, if any.
+    pub active: Option<usize>,
+    /// The settings applied while no workspace is active, kept while one is.
+    pub outside: Profile,
+}
+
+impl WorkspaceSettings {
+    /// The active workspace, if any.
+    pub fn active(&self) -> Option<&Workspace> {
+        self.workspaces.get(self.active?)
+    }
+
+    /// The active workspace, if any.
+    pub fn active_mut(&mut self) -> Option<&mut Workspace> {
+        self.workspaces.get_mut(self.active?)
+    }
+
+    /// Makes the workspace at `index` active, or none of them. `current` holds the settings
+    /// in use, which are kept with the workspace being left and replaced by those of the
+    /// workspace being entered.
+    pub fn switch(&mut self, index: Option<usize>, current: &mut Profile) {
+        let index = index.filter(|&i| i < self.workspaces.len());
+        if index == self.active {
+            return;
+        }
+        let leaving = std::mem::take(current);
+        match self.active_mut() {
+            Some(workspace) => workspace.profile = leaving,
+            None => self.outside = leaving,
+        }
+        self.active = index;
+        *current = self.active().map_or_else(|| self.outside.clone(), |w| w.profile.clone());
+    }
+
+    /// Removes the workspace at `index`, leaving it first if it is active.
+    pub fn remove(&mut self, index: usize, current: &mut Profile) {
+        if self.active == Some(index) {
+            self.switch(None, current);
+        }
+        if index < self.workspaces.len() {
+            self.workspaces.remove(index);
+            self.active = self.active.map(|a| if a > index { a - 1 } else { a });
+        }
+    }
+
+    /// Marks the roots of the active workspace that are due for their scheduled scan at
+    /// `now` as scanned, and returns them.
+    pub fn take_due(&mut self, now: u64) -> Vec<Root> {
+        let Some(workspace) = self.active_mut() else {
+            return Vec::new();
+        };
+        let mut due = Vec::new();
+        for root in workspace.roots.iter_mut().filter(|r| r.is_due(now)) {
+            root.last_scanned = Some(now);
+            due.push(root.clone());
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budgets::Budget;
+
+    fn profile(pattern: &str) -> Profile {
+        let budgets = vec![Budget { pattern: pattern.to_string(), limit: 1 << 30 }];
+        Profile { budgets: BudgetSettings { budgets, notifications_enabled: false }, record_history: true }
+    }
+
+    #[test]
+    fn test_switching_workspaces_swaps_their_settings_and_schedules() {
+        let nas = Workspace {
+            name: "Home NAS".to_string(),
+            roots: vec![
+                Root { path: PathBuf::from("/mnt/nas"), every_hours: 24, ..Root::default() },
+                Root { path: PathBuf::from("/mnt/backup"), ..Root::default() },
+            ],
+            profile: profile("media"),
+        };
+        let laptop = Workspace { name: "Work laptop".to_string(), profile: profile("target"), ..Workspace::default() };
+        let mut settings = WorkspaceSettings { workspaces: vec![nas, laptop], ..WorkspaceSettings::default() };
+        let mut current = profile("Downloads");
+
+        settings.switch(Some(0), &mut current);
+        assert_eq!(current, profile("media"));
+        current.record_history = false;
+        settings.switch(Some(1), &mut current);
+        assert_eq!(current, profile("target"));
+        assert!(!settings.workspaces[0].profile.record_history);
+        settings.switch(None, &mut current);
+        assert_eq!(current, profile("Downloads"));
+        settings.switch(Some(7), &mut current);
+        assert_eq!(settings.active, None);
+
+        // Only scheduled roots are scanned, once per period.
+        settings.switch(Some(0), &mut current);
+        let due: Vec<PathBuf> = settings.take_due(100_000).into_iter().map(|r| r.path).collect();
+        assert_eq!(due, [PathBuf::from("/mnt/nas")]);
+        assert!(settings.take_due(100_000 + 3600).is_empty());
+        assert_eq!(settings.take_due(100_000 + 24 * 3600).len(), 1);
+
+        settings.remove(0, &mut current);
+        assert_eq!(settings.active, None);
+        assert_eq!(current, profile("Downloads"));
+        assert_eq!(settings.workspaces.len(), 1);
+    }
+}
@@ -0,0 +1,107 @@
+//! Compares the layouts of fixed synthetic trees with the ones checked in under
+//! `tests/golden`, one file per layout function and tree, so a change to `treemap.rs` that
+//! moves any rectangle shows up as a failing test naming the first one that moved.
+//!
+//! After a deliberate change to the layout, run the tests with `UPDATE_GOLDEN=1` to write
+//! the files anew, and review the difference before committing it. A new layout's file is
+//! written the same way; otherwise a missing file fails the test.
+
+use disk_scout::scanner::{FileSystemNode, SizeMetric};
+use disk_scout::synthetic::{self, Spec};
+use disk_scout::treemap::{self, Rectangle, TreemapNode};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// The area the layouts fill, the size of a small window.
+const BOUNDS: Rectangle = Rectangle { x: 0.0, y: 0.0, width: 800.0, height: 600.0 };
+/// How far a coordinate may be from the golden one, for the last digits of floating point
+/// arithmetic that differ between platforms.
+const TOLERANCE: f64 = 1e-6;
+
+/// A rectangle of a layout, as written to a golden file.
+#[derive(Debug, Serialize, Deserialize)]
+struct GoldenRect {
+    /// The path of the node from the layout root, joined with slashes.
+    path: String,
+    depth: usize,
+    share: f64,
+    /// The x, y, width, and height.
+    rect: [f64; 4],
+}
+
+impl From<&TreemapNode> for GoldenRect {
+    fn from(node: &TreemapNode) -> Self {
+        let rect = node.rect;
+        Self { path: node.path.join("/"), depth: node.depth, share: node.share, rect: [rect.x, rect.y, rect.width, rect.height] }
+    }
+}
+
+/// A small tree, a few levels deep.
+fn small() -> FileSystemNode {
+    synthetic::generate(&Spec { seed: 7, nodes: 60, max_depth: 4, fan_out: 2..=6, ..Spec::default() })
+}
+
+/// A narrow tree nested many levels deep.
+fn deep() -> FileSystemNode {
+    synthetic::generate(&Spec { seed: 11, nodes: 80, max_depth: 12, fan_out: 1..=3, folder_share: 0.5, ..Spec::default() })
+}
+
+/// Writes `layout` the way the golden files hold it: one rectangle per line, so a change
+/// reads well in a diff.
+fn to_golden(layout: &[TreemapNode]) -> String {
+    let lines: Vec<String> = layout.iter().map(|n| serde_json::to_string(&GoldenRect::from(n)).unwrap()).collect();
+    format!("[\n{}\n]\n", lines.join(",\n"))
+}
+
+/// Compares `layout` with the golden file `name`, or writes the file if `UPDATE_GOLDEN` is
+/// set. A missing file fails, so a fixture deleted or renamed by mistake doesn't go unnoticed.
+fn check(name: &str, layout: &[TreemapNode]) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.json", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, to_golden(layout)).unwrap();
+        return;
+    }
+    let text = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{}: can't read {} ({}); run with UPDATE_GOLDEN=1 to write it", name, path.display(), e));
+    let golden: Vec<GoldenRect> = serde_json::from_str(&text).unwrap();
+    let actual: Vec<GoldenRect> = layout.iter().map(GoldenRect::from).collect();
+    assert_eq!(actual.len(), golden.len(), "{}: the layout has a different number of rectangles", name);
+    for (i, (actual, golden)) in actual.iter().zip(&golden).enumerate() {
+        let close = |a: f64, b: f64| (a - b).abs() <= TOLERANCE;
+        let same = actual.path == golden.path
+            && actual.depth == golden.depth
+            && close(actual.share, golden.share)
+            && actual.rect.iter().zip(golden.rect).all(|(&a, g)| close(a, g));
+        assert!(same, "{}: rectangle {} is {:?}, but the golden file has {:?}", name, i, actual, golden);
+    }
+}
+
+#[test]
+fn test_generate_treemap() {
+    check("small", &treemap::generate_treemap(&small(), BOUNDS, SizeMetric::Apparent));
+    check("deep", &treemap::generate_treemap(&deep(), BOUNDS, SizeMetric::Apparent));
+    check("small_allocated", &treemap::generate_treemap(&small(), BOUNDS, SizeMetric::Allocated));
+}
+
+#[test]
+fn test_generate_treemap_to_depth() {
+    check("deep_to_depth_3", &treemap::generate_treemap_to_depth(&deep(), BOUNDS, SizeMetric::Apparent, 3));
+}
+
+#[test]
+fn test_update_layout() {
+    let mut tree = small();
+    let layout = treemap::generate_treemap(&tree, BOUNDS, SizeMetric::Apparent);
+    // The largest file below the top level, and the last top-level item.
+    let nested = layout.iter().filter(|n| n.depth > 1 && tree.find(&n.path).is_some_and(|n| n.children.is_empty()));
+    let changed = [
+        nested.max_by(|a, b| a.share.total_cmp(&b.share)).unwrap().path.clone(),
+        layout.iter().rfind(|n| n.depth == 1).unwrap().path.clone(),
+    ];
+    for path in &changed {
+        tree.remove(path);
+    }
+    check("small_updated", &treemap::update_layout(&layout, &tree, &changed, BOUNDS, SizeMetric::Apparent, usize::MAX));
+}